@@ -0,0 +1,66 @@
+//! 基准测试共用配置和数据
+
+#![allow(dead_code)]
+
+use criterion::Criterion;
+use mira::{EmotionalState, MemoryEntry, MemoryType};
+use std::time::Duration;
+
+/// 基准测试的统一配置
+///
+/// 变化小于噪声阈值时不报告为回归；与保存的基线比较后由
+/// `scripts/check-bench-regression.sh`按阈值判定是否失败。
+pub fn criterion() -> Criterion {
+    Criterion::default()
+        .sample_size(50)
+        .measurement_time(Duration::from_secs(5))
+        .noise_threshold(0.03)
+        .significance_level(0.01)
+}
+
+/// 测试用的多线程运行时
+pub fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("创建tokio运行时失败")
+}
+
+/// 确定性的伪随机单位向量，同一`seed`总是得到同一向量
+pub fn unit_vector(dim: usize, seed: u64) -> Vec<f32> {
+    let mut state = seed.wrapping_mul(0x9e3779b97f4a7c15) | 1;
+    let mut vector: Vec<f32> = (0..dim)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 40) as f32 / (1u64 << 24) as f32 - 0.5
+        })
+        .collect();
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+    vector
+}
+
+/// 第`i`条测试记忆的内容和关键词
+pub fn memory_text(i: usize) -> (String, Vec<String>) {
+    const TOPICS: [&str; 8] = ["猫咪", "咖啡", "看海", "加班", "生日", "电影", "跑步", "下雨"];
+    let topic = TOPICS[i % TOPICS.len()];
+    (
+        format!("第{}条记忆：周末聊到了{}，心情不错", i, topic),
+        vec![topic.to_string(), format!("话题{}", i % 97)],
+    )
+}
+
+/// 第`i`条完整记忆条目，带嵌入和情感上下文
+pub fn memory_entry(i: usize, dim: usize) -> MemoryEntry {
+    let (content, keywords) = memory_text(i);
+    let memory_type = MemoryType::ALL[i % MemoryType::ALL.len()].clone();
+    let mut entry = MemoryEntry::new(memory_type, content, keywords, 0.3 + (i % 7) as f32 * 0.1);
+    entry.embedding = Some(unit_vector(dim, i as u64));
+    entry.emotional_context = Some(EmotionalState::default());
+    entry.metadata.insert("source".to_string(), "bench".to_string());
+    entry
+}
@@ -0,0 +1,69 @@
+//! 情感引擎基准测试 - 互动分析、触发器处理和时间衰减
+
+mod common;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use mira::emotion::{EmotionalEngine, EmotionalTrigger};
+use mira::{EmotionalState, MemoryEntry};
+use std::hint::black_box;
+use std::sync::Arc;
+
+const INPUTS: [&str; 5] = [
+    "我很喜欢你，你真的很聪明",
+    "今天工作很累，心情不太好",
+    "你的声音很好听，我很享受和你聊天",
+    "我想你了，什么时候能见面",
+    "你帮我解决了很多问题，谢谢你",
+];
+
+fn bench_analyze(c: &mut Criterion) {
+    let engine = EmotionalEngine::new();
+    let mut group = c.benchmark_group("emotion/analyze_interaction");
+
+    // 相关记忆数量影响分析开销
+    for n in [0, 10, 100] {
+        let memories: Vec<Arc<MemoryEntry>> = (0..n).map(|i| Arc::new(common::memory_entry(i, 8))).collect();
+        let mut i = 0;
+        group.bench_with_input(BenchmarkId::from_parameter(n), &memories, |b, memories| {
+            b.iter(|| {
+                i += 1;
+                black_box(engine.analyze_interaction(INPUTS[i % INPUTS.len()], memories))
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_state(c: &mut Criterion) {
+    let engine = EmotionalEngine::new();
+    let mut group = c.benchmark_group("emotion/state");
+
+    let state = EmotionalState::default();
+    group.bench_function("process_trigger", |b| {
+        b.iter(|| {
+            EmotionalTrigger::ALL.iter().fold(state.clone(), |state, trigger| {
+                engine.process_trigger(&state, trigger.clone(), black_box(0.6))
+            })
+        })
+    });
+
+    let stale = EmotionalState {
+        timestamp: state.timestamp - chrono::Duration::hours(48),
+        ..state.clone()
+    };
+    group.bench_function("apply_time_decay", |b| {
+        b.iter(|| black_box(engine.apply_time_decay(&stale)))
+    });
+
+    group.bench_function("generate_emotional_expression", |b| {
+        b.iter(|| black_box(engine.generate_emotional_expression(&state, "今天过得怎么样？")))
+    });
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = common::criterion();
+    targets = bench_analyze, bench_state
+}
+criterion_main!(benches);
@@ -0,0 +1,107 @@
+//! 记忆系统基准测试 - 添加、按向量检索和按关键词检索
+
+mod common;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use mira::vector_store::MockVectorStore;
+use mira::{MemoryConfig, MemorySystem, MemoryType};
+use std::hint::black_box;
+use std::sync::Arc;
+
+/// 检索测试的记忆规模
+const SIZES: [usize; 3] = [100, 1_000, 10_000];
+
+const QUERIES: [&str; 5] = ["猫咪", "周末看海", "加班很累", "生日快乐", "下雨天看电影"];
+
+async fn memory_system() -> MemorySystem {
+    let config = MemoryConfig {
+        // 清理不影响检索规模
+        short_term_limit: usize::MAX,
+        similarity_threshold: 0.0,
+        ..MemoryConfig::default()
+    };
+    MemorySystem::new("bench_user".to_string(), Arc::new(MockVectorStore::new()), Some(config))
+        .await
+        .expect("创建记忆系统失败")
+}
+
+/// 写入`n`条记忆后的记忆系统
+async fn populated(n: usize) -> MemorySystem {
+    let system = memory_system().await;
+    let memories = (0..n)
+        .map(|i| {
+            let (content, keywords) = common::memory_text(i);
+            (MemoryType::ALL[i % MemoryType::ALL.len()].clone(), content, keywords, 0.5, None)
+        })
+        .collect();
+    system.add_memories(memories).await.expect("写入记忆失败");
+    system
+}
+
+fn bench_add(c: &mut Criterion) {
+    let rt = common::runtime();
+    let mut group = c.benchmark_group("memory/add");
+    group.throughput(Throughput::Elements(1));
+
+    let system = rt.block_on(memory_system());
+    let mut i = 0;
+    group.bench_function("add_memory", |b| {
+        b.to_async(&rt).iter(|| {
+            i += 1;
+            let (content, keywords) = common::memory_text(i);
+            let system = &system;
+            async move {
+                black_box(system.add_memory(MemoryType::LongTerm, content, keywords, 0.6, None).await.unwrap())
+            }
+        })
+    });
+
+    group.throughput(Throughput::Elements(64));
+    group.bench_function("add_memories_64", |b| {
+        b.to_async(&rt).iter_batched(
+            || {
+                (0..64)
+                    .map(|i| {
+                        let (content, keywords) = common::memory_text(i);
+                        (MemoryType::Preference, content, keywords, 0.5, None)
+                    })
+                    .collect::<Vec<_>>()
+            },
+            |memories| {
+                let system = &system;
+                async move { black_box(system.add_memories(memories).await.unwrap()) }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+fn bench_retrieve(c: &mut Criterion) {
+    let rt = common::runtime();
+    let mut group = c.benchmark_group("memory/retrieve");
+
+    for n in SIZES {
+        let system = rt.block_on(populated(n));
+        let mut i = 0;
+        group.bench_with_input(BenchmarkId::new("retrieve_memories", n), &system, |b, system| {
+            b.to_async(&rt).iter(|| {
+                i += 1;
+                let query = QUERIES[i % QUERIES.len()];
+                async move { black_box(system.retrieve_memories(query, None, Some(10)).await.unwrap()) }
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("retrieve_by_keywords", n), &system, |b, system| {
+            b.iter(|| black_box(system.retrieve_by_keywords(&["猫咪", "话题7"], Some(10))))
+        });
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = common::criterion();
+    targets = bench_add, bench_retrieve
+}
+criterion_main!(benches);
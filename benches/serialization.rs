@@ -0,0 +1,58 @@
+//! 序列化基准测试 - 记忆条目的JSON和二进制编码
+
+mod common;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use mira::memory::core::EMBEDDING_DIM;
+use mira::vector_store::{Codec, CodecKind};
+use mira::MemoryEntry;
+use std::hint::black_box;
+
+fn codecs() -> Vec<CodecKind> {
+    let mut codecs = vec![CodecKind::Json];
+    #[cfg(feature = "binary-codec")]
+    codecs.push(CodecKind::Bincode);
+    codecs
+}
+
+fn bench_entry(c: &mut Criterion) {
+    let mut group = c.benchmark_group("serialization/entry");
+    let entry = common::memory_entry(42, EMBEDDING_DIM);
+
+    for codec in codecs() {
+        let encoded = codec.encode(&entry).unwrap();
+        group.throughput(Throughput::Bytes(encoded.len() as u64));
+        group.bench_with_input(BenchmarkId::new("encode", codec.name()), &entry, |b, entry| {
+            b.iter(|| codec.encode(black_box(entry)).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("decode", codec.name()), &encoded, |b, encoded| {
+            b.iter(|| codec.decode::<MemoryEntry>(black_box(encoded)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_file(c: &mut Criterion) {
+    let mut group = c.benchmark_group("serialization/file");
+    group.sample_size(20);
+    let entries: Vec<MemoryEntry> = (0..1_000).map(|i| common::memory_entry(i, EMBEDDING_DIM)).collect();
+    group.throughput(Throughput::Elements(entries.len() as u64));
+
+    for codec in codecs() {
+        let file = codec.encode_file(&entries).unwrap();
+        group.bench_with_input(BenchmarkId::new("encode_file", codec.name()), &entries, |b, entries| {
+            b.iter(|| codec.encode_file(black_box(entries)).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("decode_file", codec.name()), &file, |b, file| {
+            b.iter(|| CodecKind::decode_file::<Vec<MemoryEntry>>(black_box(file)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = common::criterion();
+    targets = bench_entry, bench_file
+}
+criterion_main!(benches);
@@ -0,0 +1,77 @@
+//! 向量搜索基准测试 - 距离内核和不同规模下的相似度搜索
+
+mod common;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use mira::bridge::ZigPerformanceUtils;
+use mira::memory::core::EMBEDDING_DIM;
+use mira::vector_store::simd::{self, SimdLevel};
+use mira::vector_store::{DistanceMetric, MockVectorStore, VectorStore};
+use std::hint::black_box;
+use uuid::Uuid;
+
+/// 搜索测试的向量规模
+const SIZES: [usize; 3] = [1_000, 10_000, 50_000];
+
+fn bench_kernels(c: &mut Criterion) {
+    let mut group = c.benchmark_group("vector/kernel");
+    let a = common::unit_vector(EMBEDDING_DIM, 1);
+    let b = common::unit_vector(EMBEDDING_DIM, 2);
+    group.throughput(Throughput::Elements(EMBEDDING_DIM as u64));
+
+    // 标量内核、运行时检测选中的SIMD内核和Zig内核对比
+    group.bench_function("dot/scalar", |bench| bench.iter(|| simd::scalar_dot(black_box(&a), black_box(&b))));
+    group.bench_function(format!("dot/{}", SimdLevel::detect().name()), |bench| {
+        bench.iter(|| simd::dot(black_box(&a), black_box(&b)))
+    });
+    group.bench_function("dot/zig", |bench| {
+        bench.iter(|| ZigPerformanceUtils::vector_dot_product(black_box(&a), black_box(&b)).unwrap())
+    });
+    group.bench_function(format!("cosine/{}", SimdLevel::detect().name()), |bench| {
+        bench.iter(|| DistanceMetric::Cosine.score(black_box(&a), black_box(&b)))
+    });
+    group.bench_function("cosine/zig", |bench| {
+        bench.iter(|| ZigPerformanceUtils::vector_cosine_similarity(black_box(&a), black_box(&b)).unwrap())
+    });
+
+    // 一个查询对1000个向量
+    let matrix: Vec<f32> = (0..1_000).flat_map(|i| common::unit_vector(EMBEDDING_DIM, i)).collect();
+    group.throughput(Throughput::Elements(1_000));
+    group.bench_function("zig_batch_cosine_1000", |bench| {
+        bench.iter(|| ZigPerformanceUtils::batch_cosine_similarity(black_box(&a), black_box(&matrix)).unwrap())
+    });
+    group.finish();
+}
+
+fn bench_search(c: &mut Criterion) {
+    let rt = common::runtime();
+    let mut group = c.benchmark_group("vector/search_similar");
+    group.sample_size(20);
+
+    for n in SIZES {
+        let store = MockVectorStore::new();
+        let points = (0..n as u64)
+            .map(|i| (Uuid::new_v4(), common::unit_vector(EMBEDDING_DIM, i), "{}".to_string()))
+            .collect();
+        rt.block_on(store.store_vectors(points)).expect("写入向量失败");
+
+        let query = common::unit_vector(EMBEDDING_DIM, u64::MAX);
+        group.throughput(Throughput::Elements(n as u64));
+        for k in [10, 100] {
+            group.bench_with_input(BenchmarkId::new(format!("k{}", k), n), &store, |b, store| {
+                b.to_async(&rt).iter(|| {
+                    let query = query.clone();
+                    async move { black_box(store.search_similar(query, k, 0.0, None).await.unwrap()) }
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = common::criterion();
+    targets = bench_kernels, bench_search
+}
+criterion_main!(benches);
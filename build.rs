@@ -1,8 +1,100 @@
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
+/// 预编译库目录，设置后跳过Zig构建
+const ZIG_LIB_DIR_ENV: &str = "MIRA_ZIG_LIB_DIR";
+/// 预编译库下载地址，指向libzig_system.a
+const ZIG_PREBUILT_URL_ENV: &str = "MIRA_ZIG_PREBUILT_URL";
+/// 下载文件的SHA-256校验值（可选）
+const ZIG_PREBUILT_SHA256_ENV: &str = "MIRA_ZIG_PREBUILT_SHA256";
+
+const ZIG_LIB_FILE: &str = "libzig_system.a";
+
 fn main() {
+    println!("cargo:rerun-if-env-changed={}", ZIG_LIB_DIR_ENV);
+    println!("cargo:rerun-if-env-changed={}", ZIG_PREBUILT_URL_ENV);
+    println!("cargo:rerun-if-env-changed={}", ZIG_PREBUILT_SHA256_ENV);
+
+    #[cfg(feature = "ffi")]
+    generate_c_header();
+
+    #[cfg(feature = "grpc")]
+    compile_protos();
+
+    // Zig系统层只在native特性下使用，wasm等构建不需要链接
+    if env::var_os("CARGO_FEATURE_NATIVE").is_none() {
+        return;
+    }
+
+    // 依次尝试：预编译目录、下载发布产物、本地Zig构建
+    let (source, zig_lib_path) = if let Some(dir) = env::var_os(ZIG_LIB_DIR_ENV) {
+        ("prebuilt", prebuilt_dir(PathBuf::from(dir)))
+    } else if let Ok(url) = env::var(ZIG_PREBUILT_URL_ENV) {
+        ("downloaded", download_prebuilt(&url))
+    } else {
+        ("built", build_zig())
+    };
+
+    // 供运行时查询库来源
+    println!("cargo:rustc-env=MIRA_ZIG_SOURCE={}", source);
+    println!("cargo:rustc-env=MIRA_ZIG_LIB_PATH={}", zig_lib_path.display());
+
+    // 告诉Cargo在哪里找到静态库
+    println!("cargo:rustc-link-search=native={}", zig_lib_path.display());
+
+    // 链接Zig生成的静态库
+    println!("cargo:rustc-link-lib=static=zig_system");
+
+    // 链接系统库（如果Zig需要的话）
+    #[cfg(target_os = "macos")]
+    {
+        println!("cargo:rustc-link-lib=framework=Foundation");
+        println!("cargo:rustc-link-lib=c");
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        println!("cargo:rustc-link-lib=c");
+        println!("cargo:rustc-link-lib=m");
+    }
+}
+
+/// 由src/ffi.rs生成C头文件到OUT_DIR，`make build-ffi`再复制到include/
+#[cfg(feature = "ffi")]
+fn generate_c_header() {
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    let crate_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let config = cbindgen::Config::from_file(Path::new(&crate_dir).join("cbindgen.toml"))
+        .expect("Failed to read cbindgen.toml");
+    cbindgen::Builder::new()
+        .with_crate(&crate_dir)
+        .with_config(config)
+        .generate()
+        .expect("Failed to generate C header")
+        .write_to_file(Path::new(&env::var("OUT_DIR").unwrap()).join("mira.h"));
+}
+
+/// 由proto/生成gRPC消息和服务代码
+#[cfg(feature = "grpc")]
+fn compile_protos() {
+    println!("cargo:rerun-if-changed=proto/");
+
+    // 未指定PROTOC时使用内置版本
+    if env::var_os("PROTOC").is_none() {
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("Failed to locate vendored protoc");
+        // SAFETY: 构建脚本是单线程的
+        unsafe { env::set_var("PROTOC", protoc) };
+    }
+    tonic_build::configure()
+        .compile_protos(&["proto/mira/v1/memory.proto"], &["proto"])
+        .expect("Failed to compile protos");
+}
+
+/// 使用本地Zig编译器构建静态库
+fn build_zig() -> PathBuf {
     println!("cargo:rerun-if-changed=zig_system/");
     println!("cargo:rerun-if-changed=zig_system/src/");
     println!("cargo:rerun-if-changed=zig_system/build.zig");
@@ -12,7 +104,10 @@ fn main() {
         .args(["build", "-Doptimize=ReleaseFast"])
         .current_dir("zig_system")
         .output()
-        .expect("Failed to execute Zig build command");
+        .unwrap_or_else(|e| panic!(
+            "Failed to execute Zig build command: {}. Install Zig 0.15.1 or set {} / {}",
+            e, ZIG_LIB_DIR_ENV, ZIG_PREBUILT_URL_ENV
+        ));
 
     if !zig_output.status.success() {
         panic!(
@@ -23,24 +118,63 @@ fn main() {
 
     // 获取项目根目录
     let out_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
-    let zig_lib_path = PathBuf::from(out_dir).join("zig_system/zig-out/lib");
+    PathBuf::from(out_dir).join("zig_system/zig-out/lib")
+}
 
-    // 告诉Cargo在哪里找到静态库
-    println!("cargo:rustc-link-search=native={}", zig_lib_path.display());
-    
-    // 链接Zig生成的静态库
-    println!("cargo:rustc-link-lib=static=zig_system");
-    
-    // 链接系统库（如果Zig需要的话）
-    #[cfg(target_os = "macos")]
-    {
-        println!("cargo:rustc-link-lib=framework=Foundation");
-        println!("cargo:rustc-link-lib=c");
+/// 校验预编译库目录
+fn prebuilt_dir(dir: PathBuf) -> PathBuf {
+    let lib = dir.join(ZIG_LIB_FILE);
+    if !lib.is_file() {
+        panic!("{} is set but {} does not exist", ZIG_LIB_DIR_ENV, lib.display());
     }
-    
-    #[cfg(target_os = "linux")]
-    {
-        println!("cargo:rustc-link-lib=c");
-        println!("cargo:rustc-link-lib=m");
+    println!("cargo:rerun-if-changed={}", lib.display());
+    dir
+}
+
+/// 下载预编译库到OUT_DIR，已下载且校验通过时直接复用
+fn download_prebuilt(url: &str) -> PathBuf {
+    let dir = PathBuf::from(env::var("OUT_DIR").unwrap()).join("zig_system");
+    std::fs::create_dir_all(&dir).expect("Failed to create Zig artifact directory");
+    let lib = dir.join(ZIG_LIB_FILE);
+    let source_file = dir.join("source-url");
+    let checksum = env::var(ZIG_PREBUILT_SHA256_ENV).ok();
+
+    let same_source = std::fs::read_to_string(&source_file).is_ok_and(|source| source == url);
+    if same_source && lib.is_file() && checksum.as_deref().is_none_or(|expected| sha256(&lib) == expected.to_lowercase()) {
+        return dir;
     }
+
+    let status = Command::new("curl")
+        .args(["--fail", "--location", "--silent", "--show-error", "--output"])
+        .arg(&lib)
+        .arg(url)
+        .status()
+        .expect("Failed to execute curl to download the prebuilt Zig library");
+    if !status.success() {
+        panic!("Failed to download prebuilt Zig library from {}", url);
+    }
+
+    if let Some(expected) = checksum {
+        let actual = sha256(&lib);
+        if actual != expected.to_lowercase() {
+            let _ = std::fs::remove_file(&lib);
+            panic!("Prebuilt Zig library checksum mismatch: expected {}, got {}", expected, actual);
+        }
+    }
+    std::fs::write(&source_file, url).expect("Failed to record Zig artifact source");
+    dir
+}
+
+/// 计算文件SHA-256 - 依次尝试sha256sum和shasum
+fn sha256(path: &Path) -> String {
+    let output = Command::new("sha256sum")
+        .arg(path)
+        .output()
+        .or_else(|_| Command::new("shasum").args(["-a", "256"]).arg(path).output())
+        .expect("Failed to execute sha256sum or shasum");
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_lowercase()
 }
@@ -1,391 +0,0 @@
-//! Apple Silicon (M4) 专用性能测试
-//! 针对ARM架构和统一内存架构优化
-
-use std::time::{Instant, Duration};
-use rayon::prelude::*;
-
-/// Apple Silicon 性能测试结果
-#[derive(Debug, Clone)]
-struct AppleSiliconBenchResult {
-    test_name: String,
-    iterations: usize,
-    total_time: Duration,
-    avg_time_per_op: Duration,
-    throughput: f64,
-    cpu_usage: f64,
-    memory_usage: usize,
-    energy_efficiency: f64, // 能效比
-}
-
-impl AppleSiliconBenchResult {
-    fn new(
-        test_name: String,
-        iterations: usize,
-        total_time: Duration,
-        cpu_usage: f64,
-        memory_usage: usize,
-    ) -> Self {
-        let avg_time_per_op = if iterations > 0 {
-            Duration::from_nanos(total_time.as_nanos() as u64 / iterations as u64)
-        } else {
-            Duration::from_nanos(0)
-        };
-        
-        let throughput = if total_time.as_secs_f64() > 0.0 {
-            iterations as f64 / total_time.as_secs_f64()
-        } else {
-            0.0
-        };
-        
-        // 能效比：吞吐量 / CPU使用率
-        let energy_efficiency = if cpu_usage > 0.0 {
-            throughput / cpu_usage
-        } else {
-            0.0
-        };
-        
-        Self {
-            test_name,
-            iterations,
-            total_time,
-            avg_time_per_op,
-            throughput,
-            cpu_usage,
-            memory_usage,
-            energy_efficiency,
-        }
-    }
-    
-    fn print(&self) {
-        println!("🍎 {} 测试结果:", self.test_name);
-        println!("   迭代次数: {}", self.iterations);
-        println!("   总耗时: {:?}", self.total_time);
-        println!("   平均耗时: {:?}", self.avg_time_per_op);
-        println!("   吞吐量: {:.2} ops/sec", self.throughput);
-        println!("   CPU使用率: {:.1}%", self.cpu_usage * 100.0);
-        println!("   内存使用: {}KB", self.memory_usage / 1024);
-        println!("   能效比: {:.2} ops/sec/%CPU", self.energy_efficiency);
-        println!();
-    }
-}
-
-/// Apple Silicon 性能测试套件
-struct AppleSiliconBenchmark {
-    results: Vec<AppleSiliconBenchResult>,
-}
-
-impl AppleSiliconBenchmark {
-    fn new() -> Self {
-        Self {
-            results: Vec::new(),
-        }
-    }
-    
-    /// ARM NEON SIMD 向量运算测试
-    fn benchmark_neon_vector_ops(&mut self, iterations: usize) {
-        println!("🚀 ARM NEON SIMD 向量运算测试 ({} 次迭代)...", iterations);
-        
-        let start_time = Instant::now();
-        
-        // 创建大量向量数据
-        let data_size = 10000;
-        let vectors: Vec<Vec<f32>> = (0..data_size).map(|i| {
-            (0..1024).map(|j| (i + j) as f32 * 0.001).collect()
-        }).collect();
-        
-        // 使用rayon进行并行向量运算
-        let _results: Vec<f32> = vectors.par_iter()
-            .map(|vec| {
-                // ARM NEON 友好的向量运算
-                let mut sum = 0.0f32;
-                let mut dot_product = 0.0f32;
-                
-                for i in 0..vec.len() {
-                    sum += vec[i];
-                    dot_product += vec[i] * vec[i];
-                }
-                
-                // 计算向量范数
-                let norm = dot_product.sqrt();
-                
-                // 归一化
-                if norm > 0.0 {
-                    sum / norm
-                } else {
-                    sum
-                }
-            })
-            .collect();
-        
-        let end_time = Instant::now();
-        let total_time = end_time.duration_since(start_time);
-        
-        // 模拟CPU使用率（实际应该从系统获取）
-        let cpu_usage = 0.8; // 假设80% CPU使用率
-        
-        let result = AppleSiliconBenchResult::new(
-            "ARM NEON SIMD 向量运算".to_string(),
-            iterations,
-            total_time,
-            cpu_usage,
-            1024 * 1024, // 1MB
-        );
-        
-        result.print();
-        self.results.push(result);
-    }
-    
-    /// 统一内存架构测试
-    fn benchmark_unified_memory(&mut self, iterations: usize) {
-        println!("💾 统一内存架构测试 ({} 次迭代)...", iterations);
-        
-        let start_time = Instant::now();
-        
-        // 模拟统一内存的访问模式
-        let mut large_data: Vec<Vec<u8>> = Vec::new();
-        
-        for i in 0..iterations {
-            // 分配大块内存
-            let size = 1024 * 1024; // 1MB
-            let data = vec![i as u8; size];
-            large_data.push(data);
-            
-            // 进行内存密集型操作
-            if i % 100 == 0 {
-                // 每100次进行一次大规模内存操作
-                let mut sum = 0u64;
-                for chunk in &large_data {
-                    for &byte in chunk.iter().take(1000) {
-                        sum += byte as u64;
-                    }
-                }
-                
-                // 防止编译器优化掉
-                if sum > 0 {
-                    large_data.truncate(large_data.len().saturating_sub(1));
-                }
-            }
-        }
-        
-        let end_time = Instant::now();
-        let total_time = end_time.duration_since(start_time);
-        
-        let result = AppleSiliconBenchResult::new(
-            "统一内存架构".to_string(),
-            iterations,
-            total_time,
-            0.6, // 60% CPU使用率
-            large_data.len() * 1024 * 1024,
-        );
-        
-        result.print();
-        self.results.push(result);
-    }
-    
-    /// 能效优化测试
-    fn benchmark_energy_efficiency(&mut self, iterations: usize) {
-        println!("⚡ 能效优化测试 ({} 次迭代)...", iterations);
-        
-        let start_time = Instant::now();
-        
-        // 模拟能效优先的计算模式
-        let _results: Vec<f64> = (0..iterations).into_par_iter()
-            .map(|i| {
-                // 使用整数运算代替浮点运算（更节能）
-                let mut result = 0i64;
-                for j in 0..1000 {
-                    result += (i + j) as i64;
-                    result = result.wrapping_mul(7); // 使用位运算
-                }
-                result as f64
-            })
-            .collect();
-        
-        let end_time = Instant::now();
-        let total_time = end_time.duration_since(start_time);
-        
-        let result = AppleSiliconBenchResult::new(
-            "能效优化计算".to_string(),
-            iterations,
-            total_time,
-            0.4, // 40% CPU使用率（能效优先）
-            512 * 1024, // 512KB
-        );
-        
-        result.print();
-        self.results.push(result);
-    }
-    
-    /// 多核并行测试
-    fn benchmark_multi_core_parallel(&mut self, iterations: usize) {
-        println!("🔄 多核并行测试 ({} 次迭代)...", iterations);
-        
-        let start_time = Instant::now();
-        
-        // 充分利用所有核心
-        let num_cores = num_cpus::get();
-        let operations_per_core = iterations / num_cores;
-        
-        let results: Vec<usize> = (0..num_cores).into_par_iter()
-            .flat_map(|core_id| {
-                let start = core_id * operations_per_core;
-                let end = if core_id == num_cores - 1 {
-                    iterations
-                } else {
-                    (core_id + 1) * operations_per_core
-                };
-                
-                (start..end).map(|i| {
-                    // 每个核心进行密集计算
-                    let mut hash = 0u64;
-                    for j in 0..1000 {
-                        hash = hash.wrapping_add(i as u64);
-                        hash = hash.wrapping_mul(31);
-                        hash = hash.wrapping_add(j as u64);
-                    }
-                    hash as usize
-                }).collect::<Vec<usize>>()
-            })
-            .collect();
-        
-        let end_time = Instant::now();
-        let total_time = end_time.duration_since(start_time);
-        
-        let result = AppleSiliconBenchResult::new(
-            "多核并行计算".to_string(),
-            iterations,
-            total_time,
-            0.9, // 90% CPU使用率
-            256 * 1024, // 256KB
-        );
-        
-        result.print();
-        self.results.push(result);
-    }
-    
-    /// 神经网络推理模拟测试
-    fn benchmark_neural_inference(&mut self, iterations: usize) {
-        println!("🧠 神经网络推理模拟 ({} 次迭代)...", iterations);
-        
-        let start_time = Instant::now();
-        
-        // 模拟神经网络推理（矩阵乘法）
-        let matrix_size = 512;
-        let matrices: Vec<Vec<Vec<f32>>> = (0..iterations / 100).map(|_| {
-            (0..matrix_size).map(|_| {
-                (0..matrix_size).map(|j| (j as f32) * 0.001).collect()
-            }).collect()
-        }).collect();
-        
-        let _results: Vec<f32> = matrices.par_iter()
-            .map(|matrix| {
-                // 模拟矩阵乘法
-                let mut result = 0.0f32;
-                for i in 0..matrix_size {
-                    for j in 0..matrix_size {
-                        result += matrix[i][j] * matrix[j][i];
-                    }
-                }
-                result
-            })
-            .collect();
-        
-        let end_time = Instant::now();
-        let total_time = end_time.duration_since(start_time);
-        
-        let result = AppleSiliconBenchResult::new(
-            "神经网络推理".to_string(),
-            iterations,
-            total_time,
-            0.85, // 85% CPU使用率
-            2048 * 1024, // 2MB
-        );
-        
-        result.print();
-        self.results.push(result);
-    }
-    
-    /// 运行完整测试套件
-    fn run_full_benchmark(&mut self) {
-        println!("🍎 Apple Silicon (M4) 专用性能测试");
-        println!("==================================================");
-        println!("💻 检测到: Apple M4 芯片 (10核)");
-        println!("⚡ 针对ARM架构和统一内存架构优化");
-        println!("==================================================");
-        
-        // 测试参数
-        let small_iterations = 10000;
-        let medium_iterations = 50000;
-        let large_iterations = 100000;
-        
-        // 运行各种测试
-        self.benchmark_neon_vector_ops(medium_iterations);
-        self.benchmark_unified_memory(small_iterations);
-        self.benchmark_energy_efficiency(medium_iterations);
-        self.benchmark_multi_core_parallel(large_iterations);
-        self.benchmark_neural_inference(medium_iterations);
-        
-        // 打印总结报告
-        self.print_summary_report();
-    }
-    
-    /// 打印总结报告
-    fn print_summary_report(&self) {
-        println!("📊 Apple Silicon 性能测试总结报告");
-        println!("==================================================");
-        
-        let mut total_throughput = 0.0;
-        let mut total_cpu_usage = 0.0;
-        let mut total_energy_efficiency = 0.0;
-        let mut count = 0;
-        
-        for result in &self.results {
-            total_throughput += result.throughput;
-            total_cpu_usage += result.cpu_usage;
-            total_energy_efficiency += result.energy_efficiency;
-            count += 1;
-        }
-        
-        let avg_throughput = if count > 0 { total_throughput / count as f64 } else { 0.0 };
-        let avg_cpu_usage = if count > 0 { total_cpu_usage / count as f64 } else { 0.0 };
-        let avg_energy_efficiency = if count > 0 { total_energy_efficiency / count as f64 } else { 0.0 };
-        
-        println!("📈 总体性能指标:");
-        println!("   平均吞吐量: {:.2} ops/sec", avg_throughput);
-        println!("   平均CPU使用率: {:.1}%", avg_cpu_usage * 100.0);
-        println!("   平均能效比: {:.2} ops/sec/%CPU", avg_energy_efficiency);
-        println!("   测试项目数: {}", count);
-        println!();
-        
-        println!("🏆 性能排名 (按能效比):");
-        let mut sorted_results = self.results.clone();
-        sorted_results.sort_by(|a, b| b.energy_efficiency.partial_cmp(&a.energy_efficiency).unwrap_or(std::cmp::Ordering::Equal));
-        
-        for (i, result) in sorted_results.iter().enumerate() {
-            println!("   {}. {}: {:.2} ops/sec/%CPU ({} ops/sec)", 
-                i + 1, 
-                result.test_name, 
-                result.energy_efficiency,
-                result.throughput as i64
-            );
-        }
-        
-        println!();
-        println!("💡 Apple Silicon 优化建议:");
-        println!("   1. 使用ARM NEON SIMD指令集");
-        println!("   2. 充分利用统一内存架构");
-        println!("   3. 采用能效优先的计算模式");
-        println!("   4. 使用多核并行计算");
-        println!("   5. 优化神经网络推理性能");
-        println!();
-        println!("✅ Apple Silicon 性能测试完成！");
-    }
-}
-
-fn main() {
-    println!("🍎 Apple Silicon (M4) 专用性能测试");
-    println!("==================================================");
-    
-    let mut benchmark = AppleSiliconBenchmark::new();
-    benchmark.run_full_benchmark();
-}
@@ -0,0 +1,29 @@
+//! MIRA Discord机器人
+//!
+//! ```text
+//! DISCORD_TOKEN=... cargo run --example discord_bot --features discord
+//! ```
+//!
+//! 存储、推理后端和个性档案从`mira.toml`和`MIRA_`开头的环境变量读取，见`mira.example.toml`。
+//! 推理服务不可用时只使用本地个性回复。
+
+use mira::{config::MiraConfig, discord::DiscordBot};
+use std::sync::Arc;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt::init();
+
+    let token = std::env::var("DISCORD_TOKEN").map_err(|_| "需要设置环境变量DISCORD_TOKEN")?;
+    let mut config = MiraConfig::load(None)?;
+    if config.personality.path.is_none() && config.personality.preset.is_none() {
+        config.personality.preset = Some("obedient".to_string());
+    }
+
+    let manager = Arc::new(config.memory_manager().await?);
+    DiscordBot::new(manager, config.inference_client()?)
+        .with_personality(config.personality_profile()?)
+        .run(&token)
+        .await?;
+    Ok(())
+}
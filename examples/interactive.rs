@@ -2,15 +2,24 @@
 //! My Intelligent Romantic Assistant - 与AI女友实时聊天
 
 use mira::{
-    MemorySystem, MemoryConfig, MemoryType, EmotionalState,
-    vector_store::MockVectorStore,
-    bridge::{PythonInferenceClient, ZigSystemMonitor},
-    emotion::{EmotionalEngine, PersonalityProfile, PersonalityGenerator},
+    MemorySystem, EmotionalState,
+    vector_store::{HealthStatus, MockVectorStore},
+    bridge::{InferenceClient, ZigSystemMonitor},
+    chat::{ChatEvent, ChatSession},
+    config::MiraConfig,
+    emotion::PersonalityGenerator,
+    memory::MemoryManager,
 };
 use std::sync::Arc;
 use std::io::{self, Write};
 use tokio;
 
+/// 配置中未设置`vector_store.data_file`时使用的持久化文件
+const MEMORY_FILE: &str = "mira_interactive_memories.json";
+
+/// 交互用户ID
+const USER_ID: &str = "interactive_user";
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // 初始化日志
@@ -26,31 +35,29 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // 初始化系统组件
     println!("📦 正在初始化系统...");
     
-    let vector_store = Arc::new(MockVectorStore::new());
-    let memory_config = MemoryConfig {
-        short_term_limit: 50,
-        long_term_threshold: 0.8,
-        similarity_threshold: 0.8,
-        cleanup_interval: 3600, // 1小时，以秒为单位
-    };
-    
-    let mut memory_system = MemorySystem::new(
-        "interactive_user".to_string(),
-        vector_store,
-        Some(memory_config.clone()),
-    ).await?;
+    // 读取mira.toml和MIRA_开头的环境变量，例如MIRA_INFERENCE__BACKEND=mock时无需启动Python服务
+    let mut config = MiraConfig::load(None)?;
+    if config.personality.path.is_none() && config.personality.preset.is_none() {
+        config.personality.preset = Some("obedient".to_string());
+    }
+    // 记忆保存在本地文件中，重启后仍然保留
+    let memory_file = config.vector_store.data_file.clone().unwrap_or_else(|| MEMORY_FILE.into());
+    let memory_config = config.memory.clone();
     
-    // 初始化Python推理客户端
-    let python_client = PythonInferenceClient::new("http://localhost:8000".to_string(), 30);
+    // 初始化推理客户端
+    let python_client = config.inference_client()?;
     let _zig_monitor = ZigSystemMonitor::new(true, Some(1024*1024)).expect("Zig监控初始化失败");
     
     // 初始化情感和个性系统
-    let emotional_engine = EmotionalEngine::new();
-    let personality = PersonalityProfile::create_obedient_girlfriend();
-    let personality_generator = PersonalityGenerator::new(personality.clone());
+    let emotional_engine = Arc::new(config.emotional_engine());
+    let personality = config.personality_profile()?;
+    let personality_generator = Arc::new(PersonalityGenerator::new(personality.clone()));
+    
+    let vector_store = Arc::new(MockVectorStore::persistent(&memory_file)?);
+    let mut manager = Arc::new(MemoryManager::new(vector_store, Some(memory_config.clone())));
     
     // 初始情感状态
-    let mut current_emotion = EmotionalState {
+    let initial_emotion = EmotionalState {
         happiness: 0.5,
         affection: 0.3,
         trust: 0.3,
@@ -58,6 +65,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         mood: "期待".to_string(),
         timestamp: chrono::Utc::now(),
     };
+    manager.update_emotion(USER_ID, None, initial_emotion.clone()).await?;
+    
+    // 对话会话负责检索记忆、更新情感、生成回复和保存对话记忆
+    let new_session = |manager: &Arc<MemoryManager>| ChatSession::new(
+        USER_ID,
+        manager.clone(),
+        python_client.clone(),
+        emotional_engine.clone(),
+        personality_generator.clone(),
+    );
+    let mut session = new_session(&manager);
     
     println!("✅ 系统初始化完成！");
     println!("👧 MIRA: 你好呀~ 我是MIRA，你的AI女友！今天想聊什么呢？ (｡◕‿◕｡)\n");
@@ -84,16 +102,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 continue;
             }
             "status" => {
-                show_status(&memory_system, &current_emotion).await;
+                let memory_system = manager.get_or_create(USER_ID).await?;
+                show_status(&memory_system, &memory_system.get_emotional_state().await, python_client.as_ref()).await;
                 continue;
             }
             "clear" => {
-                // 清空记忆
-                memory_system = MemorySystem::new(
-                    "interactive_user".to_string(),
-                    Arc::new(MockVectorStore::new()),
-                    Some(memory_config.clone()),
-                ).await?;
+                // 清空记忆 - 先释放旧存储，避免其drop时把旧数据写回文件
+                drop(session);
+                drop(manager);
+                let vector_store = MockVectorStore::persistent(&memory_file)?;
+                vector_store.clear().await;
+                manager = Arc::new(MemoryManager::new(Arc::new(vector_store), Some(memory_config.clone())));
+                manager.update_emotion(USER_ID, None, initial_emotion.clone()).await?;
+                session = new_session(&manager);
                 println!("🧠 MIRA: 记忆已清空~ 我们重新开始吧！");
                 continue;
             }
@@ -103,72 +124,29 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         
         println!("🤔 MIRA正在思考...");
         
-        // 检索相关记忆
-        let memories = memory_system.retrieve_memories(
-            user_input,
-            None,
-            Some(3),
-        ).await.unwrap_or_default();
-        
-        // 分析情感触发
-        let triggers = emotional_engine.analyze_interaction(user_input, &memories);
+        // 回复片段边生成边显示，情感状态在回复结束后显示
+        print!("💕 MIRA: ");
+        io::stdout().flush()?;
+        let mut current_emotion = None;
+        session.turn(user_input, |event| match event {
+            ChatEvent::Emotion { state } => current_emotion = Some(state),
+            ChatEvent::Token { text } => {
+                print!("{}", text);
+                io::stdout().flush().ok();
+            }
+            ChatEvent::Done { .. } => println!(),
+        }).await?;
         
-        // 更新情感状态
-        for (trigger, intensity) in triggers {
-            current_emotion = emotional_engine.process_trigger(
-                &current_emotion,
-                trigger,
-                intensity,
+        // 显示情感状态
+        if let Some(current_emotion) = current_emotion {
+            println!("😊 [情感: {} | 开心={:.2}, 亲密={:.2}, 信任={:.2}]", 
+                current_emotion.mood, 
+                current_emotion.happiness, 
+                current_emotion.affection, 
+                current_emotion.trust
             );
         }
         
-        // 生成回复
-        let response = if python_client.health_check().await {
-            // 使用Python推理服务生成智能回复
-            match python_client.generate_response(
-                user_input,
-                memories.clone(),
-                current_emotion.clone(),
-            ).await {
-                Ok(ai_response) => ai_response,
-                Err(_) => {
-                    // 后备回复生成
-                    personality_generator.generate_personalized_response(
-                        "收到你的消息了！",
-                        user_input,
-                    )
-                }
-            }
-        } else {
-            // 使用本地个性生成器
-            personality_generator.generate_personalized_response(
-                "听到了！",
-                user_input,
-            )
-        };
-        
-        // 显示回复和情感状态
-        println!("💕 MIRA: {}", response);
-        println!("😊 [情感: {} | 开心={:.2}, 亲密={:.2}, 信任={:.2}]", 
-            current_emotion.mood, 
-            current_emotion.happiness, 
-            current_emotion.affection, 
-            current_emotion.trust
-        );
-        
-        // 保存对话记忆
-        let conversation = format!("用户说: {} | 我回复: {}", user_input, response);
-        memory_system.add_memory(
-            MemoryType::ShortTerm,
-            conversation,
-            vec![user_input.to_string()],
-            0.5 + current_emotion.happiness * 0.3,
-            Some(current_emotion.clone()),
-        ).await.ok();
-        
-        // 更新记忆系统的情感状态
-        memory_system.update_emotional_state(current_emotion.clone()).await;
-        
         println!(); // 空行分隔
     }
     
@@ -186,7 +164,7 @@ fn show_help() {
     println!("====================\n");
 }
 
-async fn show_status(memory_system: &MemorySystem, emotion: &EmotionalState) {
+async fn show_status(memory_system: &MemorySystem, emotion: &EmotionalState, python_client: &dyn InferenceClient) {
     println!("\n📊 MIRA 系统状态");
     println!("================");
     
@@ -207,15 +185,22 @@ async fn show_status(memory_system: &MemorySystem, emotion: &EmotionalState) {
     println!("   情感均值: {:.2}", (emotion.happiness + emotion.affection + emotion.trust + emotion.dependency) / 4.0);
     
     // 显示服务状态
-    let python_client = PythonInferenceClient::new("http://localhost:8000".to_string(), 10);
     let python_status = if python_client.health_check().await {
         "🟢 在线"
     } else {
         "🔴 离线"
     };
     
+    let vector_store_status = match memory_system.vector_store_health().await {
+        Ok(HealthStatus::Healthy) => "🟢 正常".to_string(),
+        Ok(HealthStatus::Degraded { reason }) => format!("🟡 降级 ({})", reason),
+        Ok(HealthStatus::Unhealthy { reason }) => format!("🔴 异常 ({})", reason),
+        Err(e) => format!("🔴 异常 ({})", e),
+    };
+    
     println!("🔧 服务状态:");
     println!("   Python推理服务: {}", python_status);
+    println!("   向量存储: {}", vector_store_status);
     println!("   记忆系统: 🟢 正常");
     println!("   情感引擎: 🟢 正常");
     println!("================\n");
@@ -3,8 +3,8 @@
 
 use mira::{
     MemorySystem, MemoryConfig, MemoryType, EmotionalState,
-    vector_store::{MockVectorStore},
-    bridge::{PythonInferenceClient, ZigSystemMonitor},
+    vector_store::{MockVectorStore, VectorStore},
+    bridge::{InferenceClient, PythonInferenceClient, ZigSystemMonitor},
     emotion::{EmotionalEngine, PersonalityProfile, PersonalityGenerator},
 };
 use std::sync::Arc;
@@ -23,6 +23,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     
     // 向量存储 (生产环境使用Qdrant，这里用Mock演示)
     let vector_store = Arc::new(MockVectorStore::new());
+    let expected_dimension = vector_store.vector_size();
     
     // 内存系统配置
     let memory_config = MemoryConfig {
@@ -30,17 +31,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         long_term_threshold: 0.8,
         similarity_threshold: 0.7,
         cleanup_interval: 1800, // 30分钟
+        inference_importance_weight: 0.5,
+        rerank: Default::default(),
+        importance: Default::default(),
     };
     
     // 创建记忆系统
-    let memory_system = MemorySystem::new(
+    let memory_system = Arc::new(MemorySystem::new(
         "demo_user".to_string(),
         vector_store,
         Some(memory_config),
-    ).await?;
+    ).await?);
     
-    // 启动后台清理任务
-    let cleanup_handle = memory_system.start_background_cleanup();
+    // 启动后台清理和整理任务
+    memory_system.start_background_tasks();
     
     // 2. 初始化Python推理客户端
     println!("🐍 初始化Python推理层...");
@@ -49,9 +53,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         30,
     );
     
-    // 检查Python服务状态
+    // 检查Python服务状态，在线时校验嵌入模型维度与向量存储一致
     if python_client.health_check().await {
         println!("✅ Python推理服务连接成功");
+        let capabilities = python_client.verify_embedding_dimension(expected_dimension).await?;
+        println!("🔎 嵌入模型: {} ({}维)", capabilities.embedding_model, capabilities.embedding_dimension);
     } else {
         println!("⚠️  Python推理服务未运行，部分功能可能不可用");
     }
@@ -200,6 +206,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     if let Some(pool_size) = final_metrics.pool_size {
         println!("  内存池大小: {}KB", pool_size / 1024);
     }
+    if let Some(stats) = final_metrics.pool_stats {
+        println!("  内存池使用: {}B (峰值 {}B, {} 个分配, 碎片率 {:.1}%)",
+            stats.used, stats.high_water, stats.allocations, stats.fragmentation * 100.0);
+    }
     
     // 9. 主动互动演示
     println!("\n💬 演示主动互动...");
@@ -222,7 +232,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
     
     // 停止后台任务
-    cleanup_handle.abort();
+    memory_system.shutdown().await?;
+    println!("🩺 后台任务状态: {:?}", memory_system.tasks().health());
     
     Ok(())
 }
@@ -0,0 +1,58 @@
+//! MIRA Matrix机器人
+//!
+//! ```text
+//! MATRIX_HOMESERVER=https://matrix.example.org MATRIX_USER=mira MATRIX_PASSWORD=... \
+//!     cargo run --example matrix_bot --features matrix
+//! ```
+//!
+//! 存储、推理后端、个性档案和定时任务从`mira.toml`和`MIRA_`开头的环境变量读取，见`mira.example.toml`。
+//! 加密密钥和登录会话保存在`MATRIX_STORE`（默认`mira_matrix_store`）目录，口令可由`MATRIX_STORE_PASSPHRASE`指定。
+//! 只接受`MATRIX_ALLOWED_INVITERS`（逗号分隔的用户ID或服务器名）发来的房间邀请。
+
+use mira::config::MiraConfig;
+use mira::matrix::{MatrixBot, MatrixConfig};
+use mira::scheduler::Scheduler;
+use std::sync::Arc;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt::init();
+
+    let env = |name: &str| std::env::var(name).map_err(|_| format!("需要设置环境变量{}", name));
+    let matrix = MatrixConfig {
+        homeserver_url: env("MATRIX_HOMESERVER")?,
+        username: env("MATRIX_USER")?,
+        password: env("MATRIX_PASSWORD")?,
+        store_path: std::env::var("MATRIX_STORE").unwrap_or_else(|_| "mira_matrix_store".to_string()).into(),
+        store_passphrase: std::env::var("MATRIX_STORE_PASSPHRASE").ok(),
+        device_name: "MIRA".to_string(),
+        // 逗号分隔的用户ID或服务器名，只接受其中的邀请
+        allowed_inviters: std::env::var("MATRIX_ALLOWED_INVITERS")
+            .map(|value| value.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect())
+            .unwrap_or_default(),
+        max_sessions: mira::chat::DEFAULT_MAX_SESSIONS,
+    };
+    let mut config = MiraConfig::load(None)?;
+    if config.personality.path.is_none() && config.personality.preset.is_none() {
+        config.personality.preset = Some("obedient".to_string());
+    }
+
+    let manager = Arc::new(config.memory_manager().await?);
+    let bot = Arc::new(
+        MatrixBot::login(&matrix, manager.clone(), config.inference_client()?)
+            .await?
+            .with_personality(config.personality_profile()?),
+    );
+
+    // 会话ID为matrix:room:<房间ID>的主动消息和提醒发送到对应房间
+    let _scheduler = if config.scheduler.enabled {
+        let scheduler = Arc::new(Scheduler::new(manager, config.scheduler_store().await?).await?);
+        let forwarder = bot.clone().forward_scheduler(scheduler.subscribe());
+        let ticker = scheduler.start(std::time::Duration::from_secs(config.scheduler.tick_seconds.max(1)));
+        Some((forwarder, ticker))
+    } else {
+        None
+    };
+    bot.run().await?;
+    Ok(())
+}
@@ -1,585 +0,0 @@
-//! MIRA Rust实现性能测试
-//! 测试记忆系统、情感引擎、向量存储等核心组件的性能
-
-use mira::{
-    MemorySystem, MemoryConfig, MemoryType, EmotionalState,
-    vector_store::{MockVectorStore},
-    bridge::{ZigSystemMonitor, ZigMemoryPool},
-    emotion::{EmotionalEngine},
-};
-use std::sync::Arc;
-use std::time::{Instant, Duration};
-use tokio;
-use rayon::prelude::*;
-
-/// Rust实现性能测试结果
-#[derive(Debug, Clone)]
-struct RustBenchResult {
-    component: String,
-    operation: String,
-    iterations: usize,
-    total_time: Duration,
-    avg_time_per_op: Duration,
-    throughput: f64,
-    memory_usage: usize,
-    cpu_usage: f64,
-    efficiency: f64, // 效率：吞吐量/内存使用
-}
-
-impl RustBenchResult {
-    fn new(
-        component: String,
-        operation: String,
-        iterations: usize,
-        total_time: Duration,
-        memory_usage: usize,
-        cpu_usage: f64,
-    ) -> Self {
-        let avg_time_per_op = if iterations > 0 {
-            Duration::from_nanos(total_time.as_nanos() as u64 / iterations as u64)
-        } else {
-            Duration::from_nanos(0)
-        };
-        
-        let throughput = if total_time.as_secs_f64() > 0.0 {
-            iterations as f64 / total_time.as_secs_f64()
-        } else {
-            0.0
-        };
-        
-        let efficiency = if memory_usage > 0 {
-            throughput / (memory_usage as f64 / 1024.0) // ops/sec/KB
-        } else {
-            0.0
-        };
-        
-        Self {
-            component,
-            operation,
-            iterations,
-            total_time,
-            avg_time_per_op,
-            throughput,
-            memory_usage,
-            cpu_usage,
-            efficiency,
-        }
-    }
-    
-    fn print(&self) {
-        println!("🔧 {} - {} 测试结果:", self.component, self.operation);
-        println!("   迭代次数: {}", self.iterations);
-        println!("   总耗时: {:?}", self.total_time);
-        println!("   平均耗时: {:?}", self.avg_time_per_op);
-        println!("   吞吐量: {:.2} ops/sec", self.throughput);
-        println!("   内存使用: {}KB", self.memory_usage / 1024);
-        println!("   CPU使用率: {:.1}%", self.cpu_usage * 100.0);
-        println!("   效率: {:.2} ops/sec/KB", self.efficiency);
-        println!();
-    }
-}
-
-/// Rust实现性能测试套件
-struct RustImplementationBenchmark {
-    memory_system: Arc<MemorySystem>,
-    emotional_engine: Arc<EmotionalEngine>,
-    zig_monitor: Arc<ZigSystemMonitor>,
-    zig_pool: Arc<ZigMemoryPool>,
-    results: Vec<RustBenchResult>,
-}
-
-impl RustImplementationBenchmark {
-    async fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        // 初始化向量存储
-        let vector_store = Arc::new(MockVectorStore::new());
-        
-        // 内存系统配置
-        let memory_config = MemoryConfig {
-            short_term_limit: 10000,
-            long_term_threshold: 0.7,
-            similarity_threshold: 0.8,
-            cleanup_interval: 3600,
-        };
-        
-        // 创建记忆系统
-        let memory_system = Arc::new(MemorySystem::new(
-            "rust_bench_user".to_string(),
-            vector_store,
-            Some(memory_config),
-        ).await?);
-        
-        // 初始化情感引擎
-        let emotional_engine = Arc::new(EmotionalEngine::new());
-        
-        // 初始化Zig系统监控
-        let zig_monitor = Arc::new(ZigSystemMonitor::new(true, Some(1024 * 1024))?);
-        
-        // 初始化Zig内存池
-        let zig_pool = Arc::new(ZigMemoryPool::new(1024 * 1024)?);
-        
-        Ok(Self {
-            memory_system,
-            emotional_engine,
-            zig_monitor,
-            zig_pool,
-            results: Vec::new(),
-        })
-    }
-    
-    /// 测试记忆系统添加性能
-    async fn benchmark_memory_add(&mut self, iterations: usize) -> Result<(), Box<dyn std::error::Error>> {
-        println!("🧠 测试记忆系统添加性能 ({} 次迭代)...", iterations);
-        
-        let start_time = Instant::now();
-        let _initial_metrics = self.zig_monitor.get_performance_metrics();
-        
-        for i in 0..iterations {
-            let content = format!("Rust测试记忆 #{} - 这是一个用于测试记忆系统性能的示例内容", i);
-            let keywords = vec![
-                "Rust".to_string(), 
-                "测试".to_string(), 
-                "记忆".to_string(), 
-                format!("{}", i)
-            ];
-            
-            let memory_type = match i % 4 {
-                0 => MemoryType::ShortTerm,
-                1 => MemoryType::LongTerm,
-                2 => MemoryType::Emotional,
-                _ => MemoryType::Preference,
-            };
-            
-            self.memory_system.add_memory(
-                memory_type,
-                content,
-                keywords,
-                0.5 + (i % 5) as f32 * 0.1,
-                None,
-            ).await?;
-        }
-        
-        let end_time = Instant::now();
-        let final_metrics = self.zig_monitor.get_performance_metrics();
-        
-        let result = RustBenchResult::new(
-            "记忆系统".to_string(),
-            "添加记忆".to_string(),
-            iterations,
-            end_time.duration_since(start_time),
-            final_metrics.memory_usage,
-            (final_metrics.cpu_usage / 100.0).min(100.0).into(), // 修复CPU使用率计算
-        );
-        
-        result.print();
-        self.results.push(result);
-        
-        Ok(())
-    }
-    
-    /// 测试记忆系统检索性能
-    async fn benchmark_memory_retrieval(&mut self, iterations: usize) -> Result<(), Box<dyn std::error::Error>> {
-        println!("🔍 测试记忆系统检索性能 ({} 次迭代)...", iterations);
-        
-        // 先添加一些测试数据
-        for i in 0..1000 {
-            let content = format!("检索测试记忆 #{} - 用于测试记忆检索性能", i);
-            let keywords = vec!["检索".to_string(), "测试".to_string(), format!("{}", i)];
-            self.memory_system.add_memory(
-                MemoryType::ShortTerm,
-                content,
-                keywords,
-                0.5,
-                None,
-            ).await?;
-        }
-        
-        let queries = vec![
-            "Rust测试",
-            "记忆系统",
-            "性能测试",
-            "检索功能",
-            "向量存储",
-        ];
-        
-        let start_time = Instant::now();
-        let _initial_metrics = self.zig_monitor.get_performance_metrics();
-        
-        for i in 0..iterations {
-            let query = queries[i % queries.len()];
-            let _results = self.memory_system.retrieve_memories(
-                query,
-                None,
-                Some(10),
-            ).await?;
-        }
-        
-        let end_time = Instant::now();
-        let final_metrics = self.zig_monitor.get_performance_metrics();
-        
-        let result = RustBenchResult::new(
-            "记忆系统".to_string(),
-            "检索记忆".to_string(),
-            iterations,
-            end_time.duration_since(start_time),
-            final_metrics.memory_usage,
-            (final_metrics.cpu_usage / 100.0).min(100.0).into(),
-        );
-        
-        result.print();
-        self.results.push(result);
-        
-        Ok(())
-    }
-    
-    /// 测试情感引擎性能
-    async fn benchmark_emotion_engine(&mut self, iterations: usize) -> Result<(), Box<dyn std::error::Error>> {
-        println!("💕 测试情感引擎性能 ({} 次迭代)...", iterations);
-        
-        let user_inputs = vec![
-            "我很喜欢你，你真的很聪明",
-            "今天工作很累，心情不太好",
-            "你的声音很好听，我很享受和你聊天",
-            "我想你了，什么时候能见面",
-            "你帮我解决了很多问题，谢谢你",
-        ];
-        
-        let start_time = Instant::now();
-        let _initial_metrics = self.zig_monitor.get_performance_metrics();
-        
-        let mut current_emotion = EmotionalState::default();
-        
-        for i in 0..iterations {
-            let input = user_inputs[i % user_inputs.len()];
-            
-            // 检索相关记忆
-            let memories = self.memory_system.retrieve_memories(
-                input,
-                None,
-                Some(5),
-            ).await?;
-            
-            // 分析情感触发器
-            let triggers = self.emotional_engine.analyze_interaction(input, &memories);
-            
-            // 处理情感变化
-            for (trigger, intensity) in triggers {
-                current_emotion = self.emotional_engine.process_trigger(
-                    &current_emotion,
-                    trigger,
-                    intensity,
-                );
-            }
-            
-            // 更新记忆系统的情感状态
-            self.memory_system.update_emotional_state(current_emotion.clone()).await;
-        }
-        
-        let end_time = Instant::now();
-        let final_metrics = self.zig_monitor.get_performance_metrics();
-        
-        let result = RustBenchResult::new(
-            "情感引擎".to_string(),
-            "情感处理".to_string(),
-            iterations,
-            end_time.duration_since(start_time),
-            final_metrics.memory_usage,
-            (final_metrics.cpu_usage / 100.0).min(100.0).into(),
-        );
-        
-        result.print();
-        self.results.push(result);
-        
-        Ok(())
-    }
-    
-    /// 测试Zig内存池性能
-    async fn benchmark_zig_memory_pool(&mut self, iterations: usize) -> Result<(), Box<dyn std::error::Error>> {
-        println!("⚡ 测试Zig内存池性能 ({} 次迭代)...", iterations);
-        
-        let start_time = Instant::now();
-        let _initial_metrics = self.zig_monitor.get_performance_metrics();
-        
-        // 使用简单的串行操作，避免并行问题
-        for i in 0..iterations {
-            let size = 64 + (i % 256) * 4; // 64字节到1KB
-            let ptr = self.zig_pool.allocate(size)?;
-            
-            // 简单的内存写入
-            unsafe {
-                let ptr_u8 = ptr as *mut u8;
-                for j in 0..size {
-                    *ptr_u8.add(j) = ((i + j) % 256) as u8;
-                }
-            }
-            
-            self.zig_pool.deallocate(ptr);
-        }
-        
-        let end_time = Instant::now();
-        let final_metrics = self.zig_monitor.get_performance_metrics();
-        
-        let result = RustBenchResult::new(
-            "Zig内存池".to_string(),
-            "内存分配".to_string(),
-            iterations,
-            end_time.duration_since(start_time),
-            final_metrics.memory_usage,
-            (final_metrics.cpu_usage / 100.0).min(100.0).into(),
-        );
-        
-        result.print();
-        self.results.push(result);
-        
-        Ok(())
-    }
-    
-    /// 测试向量存储性能
-    async fn benchmark_vector_store(&mut self, iterations: usize) -> Result<(), Box<dyn std::error::Error>> {
-        println!("📊 测试向量存储性能 ({} 次迭代)...", iterations);
-        
-        let start_time = Instant::now();
-        let _initial_metrics = self.zig_monitor.get_performance_metrics();
-        
-        // 模拟向量存储操作
-        for i in 0..iterations {
-            // 生成测试向量
-            let test_vector: Vec<f32> = (0..384).map(|j| (i + j) as f32 * 0.001).collect();
-            
-            // 通过记忆系统测试向量存储功能
-            let _results = self.memory_system.retrieve_memories(
-                "向量测试",
-                None,
-                Some(5),
-            ).await?;
-        }
-        
-        let end_time = Instant::now();
-        let final_metrics = self.zig_monitor.get_performance_metrics();
-        
-        let result = RustBenchResult::new(
-            "向量存储".to_string(),
-            "向量搜索".to_string(),
-            iterations,
-            end_time.duration_since(start_time),
-            final_metrics.memory_usage,
-            (final_metrics.cpu_usage / 100.0).min(100.0).into(),
-        );
-        
-        result.print();
-        self.results.push(result);
-        
-        Ok(())
-    }
-    
-    /// 测试并发性能
-    async fn benchmark_concurrent_operations(&mut self, iterations: usize) -> Result<(), Box<dyn std::error::Error>> {
-        println!("🔄 测试并发操作性能 ({} 次迭代)...", iterations);
-        
-        let start_time = Instant::now();
-        let _initial_metrics = self.zig_monitor.get_performance_metrics();
-        
-        // 使用rayon进行并发操作
-        let results: Vec<Result<(), Box<dyn std::error::Error + Send + Sync>>> = (0..iterations)
-            .into_par_iter()
-            .map(|i| {
-                // 模拟并发操作
-                let content = format!("并发测试 #{}", i);
-                let keywords = vec!["并发".to_string(), "测试".to_string()];
-                
-                // 这里需要克隆Arc，但rayon不支持async
-                // 所以我们只进行同步操作
-                Ok(())
-            })
-            .collect();
-        
-        // 检查结果
-        for result in results {
-            if let Err(e) = result {
-                return Err(Box::new(std::io::Error::new(
-                    std::io::ErrorKind::Other, 
-                    format!("并发操作失败: {:?}", e)
-                )));
-            }
-        }
-        
-        let end_time = Instant::now();
-        let final_metrics = self.zig_monitor.get_performance_metrics();
-        
-        let result = RustBenchResult::new(
-            "并发系统".to_string(),
-            "并发操作".to_string(),
-            iterations,
-            end_time.duration_since(start_time),
-            final_metrics.memory_usage,
-            (final_metrics.cpu_usage / 100.0).min(100.0).into(),
-        );
-        
-        result.print();
-        self.results.push(result);
-        
-        Ok(())
-    }
-    
-    /// 测试系统整体性能
-    async fn benchmark_system_integration(&mut self, iterations: usize) -> Result<(), Box<dyn std::error::Error>> {
-        println!("🏗️ 测试系统整体集成性能 ({} 次迭代)...", iterations);
-        
-        let start_time = Instant::now();
-        let _initial_metrics = self.zig_monitor.get_performance_metrics();
-        
-        for i in 0..iterations {
-            // 1. 添加记忆
-            let content = format!("集成测试 #{} - 测试系统整体性能", i);
-            let memory_id = self.memory_system.add_memory(
-                MemoryType::ShortTerm,
-                content,
-                vec!["集成".to_string(), "测试".to_string()],
-                0.7,
-                None,
-            ).await?;
-            
-            // 2. 检索记忆
-            let memories = self.memory_system.retrieve_memories(
-                "集成测试",
-                None,
-                Some(5),
-            ).await?;
-            
-            // 3. 情感处理
-            let triggers = self.emotional_engine.analyze_interaction("集成测试消息", &memories);
-            let mut emotion = EmotionalState::default();
-            for (trigger, intensity) in triggers {
-                emotion = self.emotional_engine.process_trigger(&emotion, trigger, intensity);
-            }
-            
-            // 4. 更新情感状态
-            self.memory_system.update_emotional_state(emotion).await;
-            
-            // 5. 内存池操作
-            let size = 64 + (i % 512) * 8;
-            let ptr = self.zig_pool.allocate(size)?;
-            self.zig_pool.deallocate(ptr);
-        }
-        
-        let end_time = Instant::now();
-        let final_metrics = self.zig_monitor.get_performance_metrics();
-        
-        let result = RustBenchResult::new(
-            "系统集成".to_string(),
-            "端到端测试".to_string(),
-            iterations,
-            end_time.duration_since(start_time),
-            final_metrics.memory_usage,
-            (final_metrics.cpu_usage / 100.0).min(100.0).into(),
-        );
-        
-        result.print();
-        self.results.push(result);
-        
-        Ok(())
-    }
-    
-    /// 运行完整性能测试套件
-    async fn run_full_benchmark(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        println!("🔧 MIRA Rust实现性能测试");
-        println!("==================================================");
-        println!("💻 测试目标: Rust核心组件性能");
-        println!("⚡ 针对Apple Silicon (M4) 优化");
-        println!("==================================================");
-        
-        // 测试参数
-        let small_iterations = 1000;
-        let medium_iterations = 5000;
-        let large_iterations = 10000;
-        
-        // 基础组件测试
-        self.benchmark_memory_add(medium_iterations).await?;
-        self.benchmark_memory_retrieval(medium_iterations).await?;
-        self.benchmark_emotion_engine(small_iterations).await?;
-        self.benchmark_zig_memory_pool(large_iterations).await?;
-        self.benchmark_vector_store(medium_iterations).await?;
-        
-        // 高级功能测试
-        self.benchmark_concurrent_operations(medium_iterations).await?;
-        self.benchmark_system_integration(small_iterations).await?;
-        
-        // 打印总结报告
-        self.print_summary_report();
-        
-        Ok(())
-    }
-    
-    /// 打印性能测试总结报告
-    fn print_summary_report(&self) {
-        println!("📊 MIRA Rust实现性能测试总结报告");
-        println!("==================================================");
-        
-        let mut total_throughput = 0.0;
-        let mut total_memory = 0;
-        let mut total_cpu = 0.0;
-        let mut total_efficiency = 0.0;
-        let mut count = 0;
-        
-        for result in &self.results {
-            total_throughput += result.throughput;
-            total_memory += result.memory_usage;
-            total_cpu += result.cpu_usage;
-            total_efficiency += result.efficiency;
-            count += 1;
-        }
-        
-        let avg_throughput = if count > 0 { total_throughput / count as f64 } else { 0.0 };
-        let avg_memory = if count > 0 { total_memory / count } else { 0 };
-        let avg_cpu = if count > 0 { total_cpu / count as f64 } else { 0.0 };
-        let avg_efficiency = if count > 0 { total_efficiency / count as f64 } else { 0.0 };
-        
-        println!("📈 总体性能指标:");
-        println!("   平均吞吐量: {:.2} ops/sec", avg_throughput);
-        println!("   平均内存使用: {}KB", avg_memory / 1024);
-        println!("   平均CPU使用率: {:.1}%", avg_cpu * 100.0);
-        println!("   平均效率: {:.2} ops/sec/KB", avg_efficiency);
-        println!("   测试项目数: {}", count);
-        println!();
-        
-        println!("🏆 性能排名 (按吞吐量):");
-        let mut sorted_results = self.results.clone();
-        sorted_results.sort_by(|a, b| b.throughput.partial_cmp(&a.throughput).unwrap_or(std::cmp::Ordering::Equal));
-        
-        for (i, result) in sorted_results.iter().enumerate() {
-            println!("   {}. {} - {}: {:.2} ops/sec (CPU: {:.1}%)", 
-                i + 1, 
-                result.component,
-                result.operation,
-                result.throughput,
-                result.cpu_usage * 100.0
-            );
-        }
-        
-        println!();
-        println!("💡 Rust实现优化建议:");
-        println!("   1. 优化记忆系统的向量存储性能");
-        println!("   2. 提升情感引擎的处理效率");
-        println!("   3. 增强Zig内存池的并发性能");
-        println!("   4. 优化系统集成流程");
-        println!("   5. 充分利用Apple Silicon的ARM NEON SIMD");
-        println!();
-        println!("✅ Rust实现性能测试完成！");
-    }
-}
-
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // 初始化日志
-    tracing_subscriber::fmt::init();
-    
-    println!("🔧 MIRA Rust实现性能测试");
-    println!("==================================================");
-    
-    // 创建性能测试套件
-    let mut benchmark = RustImplementationBenchmark::new().await?;
-    
-    // 运行完整性能测试
-    benchmark.run_full_benchmark().await?;
-    
-    Ok(())
-}
@@ -0,0 +1,76 @@
+/* MIRA记忆系统C接口，由cbindgen根据src/ffi.rs生成，请勿手动修改 */
+
+#ifndef MIRA_H
+#define MIRA_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// 调用结果
+typedef enum MiraStatus {
+  MIRA_STATUS_OK = 0,
+  // 传入了空指针
+  MIRA_STATUS_NULL_POINTER = 1,
+  // 字符串不是UTF-8或JSON格式不正确
+  MIRA_STATUS_INVALID_ARGUMENT = 2,
+  // 记忆不存在
+  MIRA_STATUS_NOT_FOUND = 3,
+  // 向量存储或其他内部错误
+  MIRA_STATUS_INTERNAL = 4,
+} MiraStatus;
+
+// 记忆系统句柄，持有独立的tokio运行时
+typedef struct MiraMemory MiraMemory;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// 创建记忆系统
+//
+// `options_json`可为空，或为`{"qdrant_url": "...", "collection": "...", "config_path": "mira.toml"}`；
+// 未指定Qdrant时使用进程内存储，`config_path`中的`[memory]`、`[embedder]`和`[inference]`决定记忆配置和嵌入生成器。
+//
+// # Safety
+// `user_id`为以NUL结尾的UTF-8字符串，`options_json`为空或同上，`out`为有效指针。
+// 得到的句柄必须用`mira_memory_free`释放。
+MiraStatus mira_memory_new(const char *user_id, const char *options_json, MiraMemory **out);
+
+// 释放记忆系统，传入空指针时不做任何事
+//
+// # Safety
+// `memory`为`mira_memory_new`返回且尚未释放的句柄
+void mira_memory_free(MiraMemory *memory);
+
+// 添加记忆，`out_json`得到`{"id": "..."}`
+//
+// 请求格式：`{"memory_type": "Preference", "content": "...", "keywords": [...], "importance": 0.8}`
+//
+// # Safety
+// `memory`为有效句柄，`request_json`为以NUL结尾的UTF-8字符串，`out_json`为有效指针
+MiraStatus mira_add_memory(MiraMemory *memory, const char *request_json, char **out_json);
+
+// 检索相关记忆，`out_json`得到记忆条目数组（不含嵌入向量）
+//
+// 请求格式：`{"query": "...", "memory_types": ["Preference"], "limit": 5}`
+//
+// # Safety
+// 同`mira_add_memory`
+MiraStatus mira_retrieve(MiraMemory *memory, const char *query_json, char **out_json);
+
+// 释放本库返回的字符串，传入空指针时不做任何事
+//
+// # Safety
+// `s`为本库输出且尚未释放的字符串
+void mira_string_free(char *s);
+
+// 当前线程最近一次失败的错误信息，没有时返回空指针；下次失败前有效，调用方不得释放
+const char *mira_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* MIRA_H */
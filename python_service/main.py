@@ -5,15 +5,21 @@ My Intelligent Romantic Assistant - 处理文本嵌入、情感分析和回复
 """
 
 import asyncio
+import base64
+import hmac
+import io
 import json
+import os
+import ssl
 import time
 from datetime import datetime
-from typing import Dict, List, Optional, Any, Union, Annotated
+from typing import AsyncIterator, Dict, List, Literal, Optional, Any, Union, Annotated
 from dataclasses import dataclass, asdict
 from enum import Enum
 from contextlib import asynccontextmanager
 
-from fastapi import FastAPI, HTTPException, Depends
+from fastapi import FastAPI, HTTPException, Depends, Header
+from fastapi.responses import StreamingResponse
 from fastapi.middleware.cors import CORSMiddleware
 from pydantic import BaseModel, Field, ConfigDict
 import uvicorn
@@ -22,9 +28,11 @@ import uvicorn
 import torch
 from transformers import (
     AutoTokenizer, AutoModel, AutoModelForCausalLM,
-    pipeline, BitsAndBytesConfig, GenerationConfig
+    pipeline, BitsAndBytesConfig, GenerationConfig, TextIteratorStreamer
 )
-from sentence_transformers import SentenceTransformer
+from threading import Thread
+from sentence_transformers import CrossEncoder, SentenceTransformer
+from PIL import Image
 import numpy as np
 from loguru import logger
 import einops  # 张量操作优化
@@ -40,6 +48,12 @@ class Config:
     EMBEDDING_MODEL = "BAAI/bge-m3"  # 2025年最新多语言嵌入模型
     CHAT_MODEL = "Qwen/Qwen3-14B-Instruct"   # 2025年最新对话模型
     EMOTION_MODEL = "uer/chinese-roberta-base-finetuned-dianping"  # 最新情感分析
+    IMAGE_EMBEDDING_MODEL = os.environ.get("MIRA_IMAGE_EMBEDDING_MODEL", "clip-ViT-B-32")  # 图片嵌入，首次使用时加载
+    RERANK_MODEL = os.environ.get("MIRA_RERANK_MODEL", "BAAI/bge-reranker-v2-m3")  # 交叉编码器重排，首次使用时加载
+    # 请求可以指定的其他嵌入模型，逗号分隔；不在其中的模型不会被加载
+    EXTRA_EMBEDDING_MODELS = {
+        name.strip() for name in os.environ.get("MIRA_EXTRA_EMBEDDING_MODELS", "").split(",") if name.strip()
+    }
     
     # 模型配置
     MAX_LENGTH = 2048
@@ -50,14 +64,23 @@ class Config:
     # 服务配置
     HOST = "127.0.0.1"
     PORT = 8000
+    
+    # 安全配置 - 设置API_KEY后推理接口要求鉴权；设置CA后要求客户端证书
+    API_KEY = os.environ.get("MIRA_API_KEY")
+    SSL_CERTFILE = os.environ.get("MIRA_SSL_CERTFILE")
+    SSL_KEYFILE = os.environ.get("MIRA_SSL_KEYFILE")
+    SSL_CA_CERTS = os.environ.get("MIRA_SSL_CA_CERTS")
 
 # 数据模型
 class InferenceTaskType(str, Enum):
     GENERATE_EMBEDDING = "GenerateEmbedding"
+    GENERATE_EMBEDDINGS = "GenerateEmbeddings"
     GENERATE_RESPONSE = "GenerateResponse"
     ANALYZE_EMOTION = "AnalyzeEmotion"
     EXTRACT_KEYWORDS = "ExtractKeywords"
     CALCULATE_IMPORTANCE = "CalculateImportance"
+    GENERATE_IMAGE_EMBEDDING = "GenerateImageEmbedding"
+    RERANK = "Rerank"
 
 class EmotionalState(BaseModel):
     model_config = ConfigDict(
@@ -101,6 +124,20 @@ class MemoryEntry(BaseModel):
     created_at: Annotated[str, Field(description="创建时间")]
     memory_type: Annotated[str, Field(description="记忆类型")]
 
+class ChatMessage(BaseModel):
+    role: Annotated[Literal["system", "user", "assistant"], Field(description="消息角色")]
+    content: Annotated[str, Field(description="消息内容")]
+
+class ChatTurn(BaseModel):
+    role: Annotated[Literal["system", "user", "assistant"], Field(description="消息角色")]
+    content: Annotated[str, Field(description="消息内容")]
+    timestamp: Annotated[str, Field(description="时间戳")]
+
+class Capabilities(BaseModel):
+    embedding_model: Annotated[str, Field(description="嵌入模型名称")]
+    embedding_dimension: Annotated[int, Field(gt=0, description="嵌入向量维度")]
+    chat_model: Annotated[Optional[str], Field(default=None, description="对话模型名称")]
+
 class InferenceRequest(BaseModel):
     model_config = ConfigDict(
         json_schema_extra={
@@ -112,9 +149,44 @@ class InferenceRequest(BaseModel):
     )
     
     text: Annotated[str, Field(description="输入文本")]
+    texts: Annotated[Optional[List[str]], Field(default=None, description="批量嵌入的输入文本")]
+    image: Annotated[Optional[str], Field(default=None, description="Base64编码的图片")]
     context: Annotated[Optional[List[MemoryEntry]], Field(default=None, description="上下文记忆")]
     emotional_state: Annotated[Optional[EmotionalState], Field(default=None, description="当前情感状态")]
     task_type: Annotated[InferenceTaskType, Field(description="推理任务类型")]
+    model: Annotated[Optional[str], Field(default=None, description="处理该任务的模型，未设置时使用默认模型")]
+    messages: Annotated[Optional[List[ChatMessage]], Field(default=None, description="客户端组装好的对话消息，设置后忽略context和history")]
+    history: Annotated[Optional[List[ChatTurn]], Field(default=None, description="最近的多轮对话，按时间顺序")]
+
+class InferenceErrorCode(str, Enum):
+    MODEL_OVERLOADED = "model_overloaded"
+    CONTEXT_TOO_LONG = "context_too_long"
+    INVALID_INPUT = "invalid_input"
+    INTERNAL = "internal"
+
+class InferenceError(BaseModel):
+    code: Annotated[InferenceErrorCode, Field(description="错误码")]
+    message: Annotated[str, Field(description="错误信息")]
+
+class InferenceFailure(Exception):
+    """带错误码的推理异常"""
+    def __init__(self, code: InferenceErrorCode, message: str):
+        super().__init__(message)
+        self.code = code
+
+def classify_error(error: Exception) -> InferenceError:
+    """按异常链找出错误码 - 引擎方法会把原始异常包装成通用异常再抛出"""
+    code = InferenceErrorCode.INTERNAL
+    cause = error
+    while cause is not None:
+        if isinstance(cause, InferenceFailure):
+            code = cause.code
+            break
+        if isinstance(cause, torch.cuda.OutOfMemoryError):
+            code = InferenceErrorCode.MODEL_OVERLOADED
+            break
+        cause = cause.__cause__ or cause.__context__
+    return InferenceError(code=code, message=str(error))
 
 class InferenceResponse(BaseModel):
     model_config = ConfigDict(
@@ -129,13 +201,16 @@ class InferenceResponse(BaseModel):
     
     success: Annotated[bool, Field(description="处理是否成功")]
     result: Annotated[Any, Field(description="处理结果")]
-    error: Annotated[Optional[str], Field(default=None, description="错误信息")]
+    error: Annotated[Optional[InferenceError], Field(default=None, description="错误码和错误信息")]
     processing_time_ms: Annotated[int, Field(description="处理时间(毫秒)")]
 
 # AI推理引擎
 class AIInferenceEngine:
     def __init__(self):
         self.embedding_model = None
+        self.extra_embedding_models = {}
+        self.image_embedding_model = None
+        self.rerank_model = None
         self.chat_model = None
         self.chat_tokenizer = None
         self.emotion_pipeline = None
@@ -245,50 +320,114 @@ class AIInferenceEngine:
         
         logger.info("✅ 所有模型加载完成！")
     
-    async def generate_embedding(self, text: str) -> List[float]:
+    def _embedding_model_for(self, model: Optional[str]):
+        """按请求选择嵌入模型，只允许配置过的模型，首次使用时加载且不执行模型仓库中的代码"""
+        if not model or model == Config.EMBEDDING_MODEL:
+            return self.embedding_model
+        if model not in Config.EXTRA_EMBEDDING_MODELS:
+            raise InferenceFailure(InferenceErrorCode.INVALID_INPUT, f"未配置的嵌入模型: {model}")
+        if model not in self.extra_embedding_models:
+            logger.info(f"加载嵌入模型 {model}...")
+            self.extra_embedding_models[model] = SentenceTransformer(
+                model,
+                device=self.device,
+                trust_remote_code=False,
+                cache_folder="./data/models"
+            )
+        return self.extra_embedding_models[model]
+    
+    async def capabilities(self, embedding_model: Optional[str] = None) -> Capabilities:
+        """查询嵌入模型名称和维度，供客户端启动时校验向量存储配置"""
+        loop = asyncio.get_event_loop()
+        model = await loop.run_in_executor(None, self._embedding_model_for, embedding_model)
+        return Capabilities(
+            embedding_model=embedding_model or Config.EMBEDDING_MODEL,
+            embedding_dimension=model.get_sentence_embedding_dimension(),
+            chat_model=Config.CHAT_MODEL,
+        )
+    
+    def check_chat_model(self, model: Optional[str]):
+        """对话模型常驻显存，不按请求切换；指定其他模型时仍使用已加载的模型"""
+        if model and model != Config.CHAT_MODEL:
+            logger.warning(f"未加载对话模型 {model}，使用 {Config.CHAT_MODEL}")
+    
+    async def generate_embedding(self, text: str, model: Optional[str] = None) -> List[float]:
         """生成文本嵌入向量"""
         try:
             # 使用最新的异步处理方式
             loop = asyncio.get_event_loop()
+            embedding_model = await loop.run_in_executor(None, self._embedding_model_for, model)
             embedding = await loop.run_in_executor(
                 None, 
-                lambda: self.embedding_model.encode(text, normalize_embeddings=True)
+                lambda: embedding_model.encode(text, normalize_embeddings=True)
             )
             return embedding.tolist()
         except Exception as e:
             raise Exception(f"嵌入生成失败: {str(e)}")
     
+    async def generate_embeddings(self, texts: List[str], model: Optional[str] = None) -> List[List[float]]:
+        """批量生成文本嵌入向量 - 一次前向计算处理整批文本"""
+        try:
+            loop = asyncio.get_event_loop()
+            embedding_model = await loop.run_in_executor(None, self._embedding_model_for, model)
+            embeddings = await loop.run_in_executor(
+                None,
+                lambda: embedding_model.encode(texts, normalize_embeddings=True)
+            )
+            return embeddings.tolist()
+        except Exception as e:
+            raise Exception(f"批量嵌入生成失败: {str(e)}")
+    
+    async def generate_image_embedding(self, image: str) -> List[float]:
+        """生成图片嵌入向量 - CLIP模型首次使用时加载"""
+        try:
+            loop = asyncio.get_event_loop()
+            if self.image_embedding_model is None:
+                logger.info(f"加载图片嵌入模型 {Config.IMAGE_EMBEDDING_MODEL}...")
+                self.image_embedding_model = await loop.run_in_executor(
+                    None,
+                    lambda: SentenceTransformer(Config.IMAGE_EMBEDDING_MODEL, device=self.device, cache_folder="./data/models")
+                )
+            picture = Image.open(io.BytesIO(base64.b64decode(image))).convert("RGB")
+            embedding = await loop.run_in_executor(
+                None,
+                lambda: self.image_embedding_model.encode(picture, normalize_embeddings=True)
+            )
+            return embedding.tolist()
+        except Exception as e:
+            raise Exception(f"图片嵌入生成失败: {str(e)}")
+    
+    async def rerank(self, query: str, documents: List[str]) -> List[float]:
+        """交叉编码器重排 - 返回每个文档与查询的相关性(0-1)，交叉编码器首次使用时加载"""
+        try:
+            loop = asyncio.get_event_loop()
+            if self.rerank_model is None:
+                logger.info(f"加载重排模型 {Config.RERANK_MODEL}...")
+                self.rerank_model = await loop.run_in_executor(
+                    None,
+                    lambda: CrossEncoder(Config.RERANK_MODEL, device=self.device, cache_folder="./data/models")
+                )
+            if not documents:
+                return []
+            scores = await loop.run_in_executor(
+                None,
+                lambda: self.rerank_model.predict([(query, document) for document in documents], activation_fn=torch.nn.Sigmoid())
+            )
+            return [float(score) for score in scores]
+        except Exception as e:
+            raise Exception(f"重排失败: {str(e)}")
+    
     async def generate_response(
         self, 
         user_input: str, 
         context: List[MemoryEntry], 
-        emotional_state: EmotionalState
+        emotional_state: Optional[EmotionalState],
+        messages: Optional[List[ChatMessage]] = None,
+        history: Optional[List[ChatTurn]] = None
     ) -> str:
         """生成情感化回复"""
         try:
-            # 构建系统提示
-            system_prompt = self._build_system_prompt(emotional_state)
-            
-            # 构建上下文
-            context_text = self._build_context(context)
-            
-            # 构建完整提示
-            full_prompt = f"""<|im_start|>system
-{system_prompt}<|im_end|>
-<|im_start|>user
-上下文信息：
-{context_text}
-
-用户说：{user_input}<|im_end|>
-<|im_start|>assistant"""
-
-            # 生成回复
-            inputs = self.chat_tokenizer(
-                full_prompt, 
-                return_tensors="pt", 
-                max_length=Config.MAX_LENGTH,
-                truncation=True
-            ).to(self.chat_model.device)
+            inputs = self._build_chat_inputs(user_input, context, emotional_state, messages, history)
             
             with torch.no_grad():
                 outputs = self.chat_model.generate(
@@ -311,6 +450,106 @@ class AIInferenceEngine:
         except Exception as e:
             raise Exception(f"回复生成失败: {str(e)}")
     
+    async def stream_response(
+        self,
+        user_input: str,
+        context: List[MemoryEntry],
+        emotional_state: Optional[EmotionalState],
+        messages: Optional[List[ChatMessage]] = None,
+        history: Optional[List[ChatTurn]] = None
+    ) -> AsyncIterator[str]:
+        """流式生成情感化回复，逐段产出新生成的文本"""
+        inputs = self._build_chat_inputs(user_input, context, emotional_state, messages, history)
+        streamer = TextIteratorStreamer(
+            self.chat_tokenizer, skip_prompt=True, skip_special_tokens=True
+        )
+        
+        def generate():
+            with torch.no_grad():
+                self.chat_model.generate(
+                    **inputs,
+                    max_new_tokens=256,
+                    temperature=Config.TEMPERATURE,
+                    top_p=Config.TOP_P,
+                    do_sample=True,
+                    pad_token_id=self.chat_tokenizer.eos_token_id,
+                    streamer=streamer
+                )
+        
+        # 生成在后台线程进行，streamer在主循环中按段取出
+        Thread(target=generate, daemon=True).start()
+        loop = asyncio.get_event_loop()
+        sentinel = object()
+        while True:
+            chunk = await loop.run_in_executor(None, lambda: next(streamer, sentinel))
+            if chunk is sentinel:
+                break
+            if chunk:
+                yield chunk
+    
+    def _build_chat_inputs(
+        self,
+        user_input: str,
+        context: List[MemoryEntry],
+        emotional_state: Optional[EmotionalState],
+        messages: Optional[List[ChatMessage]] = None,
+        history: Optional[List[ChatTurn]] = None
+    ):
+        """构建对话模型的输入张量 - 优先使用客户端组装好的消息，否则按历史和上下文构建"""
+        if messages is None:
+            messages = [
+                ChatMessage(role="system", content=self._build_system_prompt(emotional_state)),
+                *(ChatMessage(role=turn.role, content=turn.content) for turn in history or []),
+                ChatMessage(
+                    role="user",
+                    content=f"上下文信息：\n{self._build_context(context)}\n\n用户说：{user_input}"
+                ),
+            ]
+        
+        full_prompt = self._render_prompt(self._fit_messages(messages))
+
+        # 预算内裁剪后仍超长时报错，直接截断会丢掉末尾的assistant标记
+        inputs = self.chat_tokenizer(full_prompt, return_tensors="pt")
+        input_length = inputs["input_ids"].shape[1]
+        if input_length > Config.MAX_LENGTH:
+            raise InferenceFailure(
+                InferenceErrorCode.CONTEXT_TOO_LONG,
+                f"输入 {input_length} tokens 超出上限 {Config.MAX_LENGTH}"
+            )
+        return inputs.to(self.chat_model.device)
+    
+    @staticmethod
+    def _render_message(message: ChatMessage) -> str:
+        return f"<|im_start|>{message.role}\n{message.content}<|im_end|>\n"
+    
+    @classmethod
+    def _render_prompt(cls, messages: List[ChatMessage]) -> str:
+        return "".join(cls._render_message(message) for message in messages) + "<|im_start|>assistant"
+    
+    def _count_tokens(self, text: str) -> int:
+        return len(self.chat_tokenizer(text, add_special_tokens=False)["input_ids"])
+    
+    def _fit_messages(self, messages: List[ChatMessage]) -> List[ChatMessage]:
+        """按token预算裁剪消息 - 保留系统提示和最后一条消息，从最早的历史开始丢弃，
+        仍超出时只保留最后一条消息的末尾部分"""
+        budget = Config.MAX_LENGTH - self._count_tokens("<|im_start|>assistant")
+        messages = list(messages)
+        lengths = [self._count_tokens(self._render_message(message)) for message in messages]
+        
+        first = 1 if messages and messages[0].role == "system" else 0
+        while sum(lengths) > budget and len(messages) - first > 1:
+            del messages[first]
+            del lengths[first]
+        
+        overflow = sum(lengths) - budget
+        if overflow > 0 and messages:
+            last = messages[-1]
+            tokens = self.chat_tokenizer(last.content, add_special_tokens=False)["input_ids"]
+            keep = max(len(tokens) - overflow, 0)
+            content = self.chat_tokenizer.decode(tokens[len(tokens) - keep:]) if keep else ""
+            messages[-1] = ChatMessage(role=last.role, content=content)
+        return messages
+    
     async def analyze_emotion(self, text: str) -> EmotionalState:
         """分析用户情感"""
         try:
@@ -393,6 +632,23 @@ class AIInferenceEngine:
             # 备用方法：简单的分词
             return list(jieba.cut(text))[:10]
     
+    async def calculate_importance(
+        self, text: str, emotional_state: Optional[EmotionalState]
+    ) -> float:
+        """评估记忆重要性 - 综合情感强度、信息量和关键词密度"""
+        emotion = await self.analyze_emotion(text)
+        # 情感越偏离中性越值得记住
+        intensity = abs(emotion.happiness - 0.5) * 2
+        if emotional_state is not None:
+            intensity = max(intensity, emotional_state.affection)
+        
+        length_score = min(len(text) / 100, 1.0)
+        keywords = await self.extract_keywords(text)
+        keyword_score = min(len(keywords) / 5, 1.0)
+        
+        importance = 0.2 + 0.4 * intensity + 0.2 * length_score + 0.2 * keyword_score
+        return round(min(max(importance, 0.0), 1.0), 3)
+    
     def _build_system_prompt(self, emotional_state: EmotionalState) -> str:
         """构建系统提示"""
         base_prompt = """你是Nyra，一个聪明、嘴甜、听话的AI女友。你的名字是Nyra，意思是"夜晚"和"神秘"，象征着你的优雅和智慧。
@@ -479,7 +735,21 @@ async def get_inference_engine() -> AIInferenceEngine:
         raise HTTPException(status_code=503, detail="推理引擎未初始化")
     return inference_engine
 
-@app.post("/inference", response_model=InferenceResponse)
+async def verify_api_key(
+    authorization: Annotated[Optional[str], Header()] = None,
+    x_api_key: Annotated[Optional[str], Header()] = None,
+):
+    """校验Bearer令牌或X-API-Key，未配置API_KEY时不校验"""
+    if not Config.API_KEY:
+        return
+    token = x_api_key
+    if authorization and authorization.startswith("Bearer "):
+        token = authorization.removeprefix("Bearer ")
+    # 常量时间比较，避免按响应耗时逐字节猜测密钥
+    if token is None or not hmac.compare_digest(token.encode(), Config.API_KEY.encode()):
+        raise HTTPException(status_code=401, detail="无效的API密钥")
+
+@app.post("/inference", response_model=InferenceResponse, dependencies=[Depends(verify_api_key)])
 async def inference_endpoint(
     request: InferenceRequest,
     engine: Annotated[AIInferenceEngine, Depends(get_inference_engine)]
@@ -492,16 +762,34 @@ async def inference_endpoint(
         
         match request.task_type:  # 使用Python 3.10+ match语法
             case InferenceTaskType.GENERATE_EMBEDDING:
-                result = await engine.generate_embedding(request.text)
+                result = await engine.generate_embedding(request.text, request.model)
+            
+            case InferenceTaskType.GENERATE_EMBEDDINGS:
+                if request.texts is None:
+                    raise HTTPException(
+                        status_code=400,
+                        detail="批量嵌入需要texts字段"
+                    )
+                result = await engine.generate_embeddings(request.texts, request.model)
+            
+            case InferenceTaskType.GENERATE_IMAGE_EMBEDDING:
+                if request.image is None:
+                    raise HTTPException(
+                        status_code=400,
+                        detail="图片嵌入需要image字段"
+                    )
+                result = await engine.generate_image_embedding(request.image)
                 
             case InferenceTaskType.GENERATE_RESPONSE:
-                if not request.context or not request.emotional_state:
+                if not request.messages and (not request.context or not request.emotional_state):
                     raise HTTPException(
                         status_code=400, 
-                        detail="生成回复需要上下文和情感状态"
+                        detail="生成回复需要对话消息，或上下文和情感状态"
                     )
+                engine.check_chat_model(request.model)
                 result = await engine.generate_response(
-                    request.text, request.context, request.emotional_state
+                    request.text, request.context or [], request.emotional_state,
+                    request.messages, request.history
                 )
                 
             case InferenceTaskType.ANALYZE_EMOTION:
@@ -510,6 +798,19 @@ async def inference_endpoint(
                 
             case InferenceTaskType.EXTRACT_KEYWORDS:
                 result = await engine.extract_keywords(request.text)
+            
+            case InferenceTaskType.CALCULATE_IMPORTANCE:
+                result = await engine.calculate_importance(
+                    request.text, request.emotional_state
+                )
+            
+            case InferenceTaskType.RERANK:
+                if request.texts is None:
+                    raise HTTPException(
+                        status_code=400,
+                        detail="重排需要texts字段"
+                    )
+                result = await engine.rerank(request.text, request.texts)
                 
             case _:
                 raise HTTPException(
@@ -536,10 +837,44 @@ async def inference_endpoint(
         return InferenceResponse(
             success=False,
             result=None,
-            error=str(e),
+            error=classify_error(e),
             processing_time_ms=processing_time
         )
 
+@app.post("/inference/stream", dependencies=[Depends(verify_api_key)])
+async def inference_stream_endpoint(
+    request: InferenceRequest,
+    engine: Annotated[AIInferenceEngine, Depends(get_inference_engine)]
+):
+    """流式回复端点 - 以SSE逐段返回生成的文本，结束时发送done事件"""
+    if request.task_type != InferenceTaskType.GENERATE_RESPONSE:
+        raise HTTPException(status_code=400, detail="流式接口只支持GenerateResponse")
+    if not request.messages and (not request.context or not request.emotional_state):
+        raise HTTPException(status_code=400, detail="生成回复需要对话消息，或上下文和情感状态")
+    engine.check_chat_model(request.model)
+    
+    async def events():
+        try:
+            async for chunk in engine.stream_response(
+                request.text, request.context or [], request.emotional_state,
+                request.messages, request.history
+            ):
+                yield f"data: {json.dumps(chunk, ensure_ascii=False)}\n\n"
+            yield "event: done\ndata: {}\n\n"
+        except Exception as e:
+            logger.error(f"流式回复失败: {e}")
+            yield f"event: error\ndata: {classify_error(e).model_dump_json()}\n\n"
+    
+    return StreamingResponse(events(), media_type="text/event-stream")
+
+@app.get("/capabilities", response_model=Capabilities, dependencies=[Depends(verify_api_key)])
+async def capabilities(
+    engine: Annotated[AIInferenceEngine, Depends(get_inference_engine)],
+    embedding_model: Optional[str] = None,
+):
+    """模型能力握手端点"""
+    return await engine.capabilities(embedding_model)
+
 @app.get("/health")
 async def health_check():
     """健康检查端点"""
@@ -565,5 +900,9 @@ if __name__ == "__main__":
         host=Config.HOST,
         port=Config.PORT,
         reload=False,  # 生产环境关闭热重载
-        log_level="info"
+        log_level="info",
+        ssl_certfile=Config.SSL_CERTFILE,
+        ssl_keyfile=Config.SSL_KEYFILE,
+        ssl_ca_certs=Config.SSL_CA_CERTS,
+        ssl_cert_reqs=ssl.CERT_REQUIRED if Config.SSL_CA_CERTS else ssl.CERT_NONE,
     )
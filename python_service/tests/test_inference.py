@@ -9,7 +9,7 @@ from fastapi.testclient import TestClient
 
 from main import (
     app, AIInferenceEngine, InferenceRequest, InferenceTaskType,
-    EmotionalState, MemoryEntry, get_inference_engine
+    EmotionalState, MemoryEntry, Capabilities, get_inference_engine
 )
 
 
@@ -27,6 +27,12 @@ def mock_engine():
     engine.generate_embedding = Mock(return_value=asyncio.Future())
     engine.generate_embedding.return_value.set_result([0.1, 0.2, 0.3])
     
+    engine.generate_embeddings = Mock(return_value=asyncio.Future())
+    engine.generate_embeddings.return_value.set_result([[0.1, 0.2], [0.3, 0.4]])
+    
+    engine.calculate_importance = Mock(return_value=asyncio.Future())
+    engine.calculate_importance.return_value.set_result(0.75)
+    
     engine.generate_response = Mock(return_value=asyncio.Future())
     engine.generate_response.return_value.set_result("模拟回复")
     
@@ -39,6 +45,9 @@ def mock_engine():
     engine.extract_keywords = Mock(return_value=asyncio.Future())
     engine.extract_keywords.return_value.set_result(["关键词1", "关键词2"])
     
+    engine.rerank = Mock(return_value=asyncio.Future())
+    engine.rerank.return_value.set_result([0.9, 0.1])
+    
     return engine
 
 
@@ -90,6 +99,18 @@ class TestHealthCheck:
         assert "timestamp" in data
         assert "engine_ready" in data
     
+    def test_capabilities_endpoint(self, client, mock_engine):
+        """测试模型能力握手端点"""
+        mock_engine.capabilities = Mock(return_value=asyncio.Future())
+        mock_engine.capabilities.return_value.set_result(Capabilities(
+            embedding_model="bge-small-zh", embedding_dimension=512, chat_model="Qwen/Qwen3-14B-Instruct"
+        ))
+        with patch('main.inference_engine', mock_engine):
+            response = client.get("/capabilities", params={"embedding_model": "bge-small-zh"})
+            assert response.status_code == 200
+            assert response.json()["embedding_dimension"] == 512
+            mock_engine.capabilities.assert_called_once_with("bge-small-zh")
+    
     def test_root_endpoint(self, client):
         """测试根端点"""
         response = client.get("/")
@@ -118,6 +139,62 @@ class TestInferenceEndpoint:
             assert data["result"] == [0.1, 0.2, 0.3]
             assert "processing_time_ms" in data
     
+    def test_batch_embedding_generation(self, client, mock_engine):
+        """测试批量嵌入生成"""
+        with patch('main.inference_engine', mock_engine):
+            request_data = {
+                "text": "",
+                "texts": ["第一条", "第二条"],
+                "task_type": "GenerateEmbeddings"
+            }
+            
+            response = client.post("/inference", json=request_data)
+            assert response.status_code == 200
+            assert response.json()["result"] == [[0.1, 0.2], [0.3, 0.4]]
+            mock_engine.generate_embeddings.assert_called_once_with(["第一条", "第二条"], None)
+    
+    def test_embedding_model_selection(self, client, mock_engine):
+        """测试按请求指定嵌入模型"""
+        with patch('main.inference_engine', mock_engine):
+            request_data = {
+                "text": "测试文本",
+                "task_type": "GenerateEmbedding",
+                "model": "BAAI/bge-small-zh-v1.5"
+            }
+            
+            response = client.post("/inference", json=request_data)
+            assert response.status_code == 200
+            mock_engine.generate_embedding.assert_called_once_with("测试文本", "BAAI/bge-small-zh-v1.5")
+    
+    def test_importance_calculation(self, client, mock_engine):
+        """测试重要性评估"""
+        with patch('main.inference_engine', mock_engine):
+            request_data = {
+                "text": "下周三是我妈妈的生日",
+                "task_type": "CalculateImportance"
+            }
+            
+            response = client.post("/inference", json=request_data)
+            assert response.status_code == 200
+            assert response.json()["result"] == 0.75
+    
+    def test_rerank(self, client, mock_engine):
+        """测试交叉编码器重排"""
+        with patch('main.inference_engine', mock_engine):
+            request_data = {
+                "text": "喜欢什么饮料",
+                "texts": ["用户喜欢喝咖啡", "明天要开会"],
+                "task_type": "Rerank"
+            }
+            
+            response = client.post("/inference", json=request_data)
+            assert response.status_code == 200
+            assert response.json()["result"] == [0.9, 0.1]
+            mock_engine.rerank.assert_called_once_with("喜欢什么饮料", ["用户喜欢喝咖啡", "明天要开会"])
+            
+            response = client.post("/inference", json={"text": "喜欢什么饮料", "task_type": "Rerank"})
+            assert response.status_code == 400
+    
     def test_response_generation(self, client, mock_engine, sample_emotional_state, sample_memory_entries):
         """测试回复生成"""
         with patch('main.inference_engine', mock_engine):
@@ -135,6 +212,63 @@ class TestInferenceEndpoint:
             assert data["success"] is True
             assert data["result"] == "模拟回复"
     
+    def test_response_generation_with_messages(self, client, mock_engine):
+        """测试使用客户端组装的消息生成回复，无需上下文"""
+        with patch('main.inference_engine', mock_engine):
+            request_data = {
+                "text": "你好",
+                "task_type": "GenerateResponse",
+                "messages": [
+                    {"role": "system", "content": "你是Nyra"},
+                    {"role": "user", "content": "暂无相关记忆。\n\n用户说：你好"}
+                ]
+            }
+            
+            response = client.post("/inference", json=request_data)
+            assert response.status_code == 200
+            assert response.json()["result"] == "模拟回复"
+            messages = mock_engine.generate_response.call_args.args[3]
+            assert [message.role for message in messages] == ["system", "user"]
+
+    def test_response_generation_with_history(self, client, mock_engine, sample_emotional_state):
+        """测试多轮对话历史传给推理引擎"""
+        with patch('main.inference_engine', mock_engine):
+            request_data = {
+                "text": "那你呢",
+                "context": [],
+                "emotional_state": sample_emotional_state.model_dump(),
+                "task_type": "GenerateResponse",
+                "history": [
+                    {"role": "user", "content": "我今天好累", "timestamp": "2024-01-01T00:00:00Z"},
+                    {"role": "assistant", "content": "辛苦啦~", "timestamp": "2024-01-01T00:00:05Z"}
+                ]
+            }
+
+            response = client.post("/inference", json=request_data)
+            assert response.status_code == 200
+            history = mock_engine.generate_response.call_args.args[4]
+            assert [turn.content for turn in history] == ["我今天好累", "辛苦啦~"]
+
+    def test_response_streaming(self, client, mock_engine, sample_emotional_state, sample_memory_entries):
+        """测试流式回复"""
+        async def fake_stream(*args):
+            for chunk in ["你好", "呀~"]:
+                yield chunk
+        mock_engine.stream_response = fake_stream
+        
+        with patch('main.inference_engine', mock_engine):
+            request_data = {
+                "text": "你好",
+                "context": [entry.model_dump() for entry in sample_memory_entries],
+                "emotional_state": sample_emotional_state.model_dump(),
+                "task_type": "GenerateResponse"
+            }
+            
+            response = client.post("/inference/stream", json=request_data)
+            assert response.status_code == 200
+            assert response.headers["content-type"].startswith("text/event-stream")
+            assert response.text == 'data: "你好"\n\ndata: "呀~"\n\nevent: done\ndata: {}\n\n'
+    
     def test_emotion_analysis(self, client, mock_engine, sample_emotional_state):
         """测试情感分析"""
         with patch('main.inference_engine', mock_engine):
@@ -166,6 +300,26 @@ class TestInferenceEndpoint:
             assert data["success"] is True
             assert data["result"] == ["关键词1", "关键词2"]
     
+    def test_api_key_required_when_configured(self, client, mock_engine):
+        """测试配置API密钥后的鉴权"""
+        request_data = {"text": "测试", "task_type": "GenerateEmbedding"}
+        with patch('main.inference_engine', mock_engine), patch('main.Config.API_KEY', "secret"):
+            assert client.post("/inference", json=request_data).status_code == 401
+            assert client.post(
+                "/inference", json=request_data, headers={"Authorization": "Bearer secreT"}
+            ).status_code == 401
+            
+            response = client.post(
+                "/inference", json=request_data, headers={"Authorization": "Bearer secret"}
+            )
+            assert response.status_code == 200
+            
+            response = client.post("/inference", json=request_data, headers={"X-API-Key": "secret"})
+            assert response.status_code == 200
+            
+            # 健康检查保持开放，便于探活
+            assert client.get("/health").status_code == 200
+    
     def test_missing_engine(self, client):
         """测试推理引擎未初始化"""
         with patch('main.get_inference_engine', side_effect=Exception("推理引擎未初始化")):
@@ -188,6 +342,68 @@ class TestInferenceEndpoint:
             response = client.post("/inference", json=request_data)
             assert response.status_code == 422  # Validation error
     
+    def test_error_codes(self, client, mock_engine):
+        """测试失败响应携带错误码"""
+        from main import InferenceErrorCode, InferenceFailure
+        
+        with patch('main.inference_engine', mock_engine):
+            mock_engine.extract_keywords.side_effect = InferenceFailure(
+                InferenceErrorCode.CONTEXT_TOO_LONG, "输入过长"
+            )
+            response = client.post("/inference", json={"text": "测试", "task_type": "ExtractKeywords"})
+            assert response.status_code == 200
+            assert response.json()["error"] == {"code": "context_too_long", "message": "输入过长"}
+            
+            # 包装后的异常按原始异常分类，未识别的异常归为internal
+            def wrapped(_text):
+                try:
+                    raise InferenceFailure(InferenceErrorCode.INVALID_INPUT, "空文本")
+                except Exception as e:
+                    raise Exception(f"关键词提取失败: {e}")
+            mock_engine.extract_keywords.side_effect = wrapped
+            response = client.post("/inference", json={"text": "测试", "task_type": "ExtractKeywords"})
+            assert response.json()["error"]["code"] == "invalid_input"
+            
+            # 模型内部的ValueError不是调用方的输入错误
+            mock_engine.extract_keywords.side_effect = ValueError("张量形状不符")
+            response = client.post("/inference", json={"text": "测试", "task_type": "ExtractKeywords"})
+            assert response.json()["error"]["code"] == "internal"
+            
+            mock_engine.extract_keywords.side_effect = RuntimeError("未知错误")
+            response = client.post("/inference", json={"text": "测试", "task_type": "ExtractKeywords"})
+            assert response.json()["error"]["code"] == "internal"
+    
+    def test_long_history_fits_budget(self):
+        """测试超长历史按预算丢弃最早的轮次，保留系统提示和最后一条消息"""
+        from main import ChatMessage
+        
+        class CharTokenizer:
+            """每个字符一个token"""
+            def __call__(self, text, add_special_tokens=True):
+                return {"input_ids": list(text)}
+            
+            def decode(self, tokens):
+                return "".join(tokens)
+        
+        engine = AIInferenceEngine()
+        engine.chat_tokenizer = CharTokenizer()
+        messages = [
+            ChatMessage(role="system", content="你是MIRA"),
+            *(ChatMessage(role="user", content="很久以前的话" * 50) for _ in range(10)),
+            ChatMessage(role="user", content="现在说的话"),
+        ]
+        
+        with patch('main.Config.MAX_LENGTH', 200):
+            fitted = engine._fit_messages(messages)
+            assert fitted[0].content == "你是MIRA"
+            assert fitted[-1].content == "现在说的话"
+            assert len(engine._render_prompt(fitted)) <= 200
+            
+            # 只剩最后一条仍超长时保留其末尾
+            fitted = engine._fit_messages([ChatMessage(role="user", content="上下文" * 100 + "用户说：你好")])
+            assert fitted[-1].content.endswith("用户说：你好")
+            assert len(engine._render_prompt(fitted)) <= 200
+    
     def test_missing_context_for_response(self, client, mock_engine):
         """测试生成回复时缺少上下文"""
         with patch('main.inference_engine', mock_engine):
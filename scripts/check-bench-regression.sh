@@ -0,0 +1,60 @@
+#!/bin/bash
+
+# 基准测试回归检查 - 读取criterion与基线比较的结果，超过阈值时返回非零
+# 用法：
+#   cargo bench -- --save-baseline main    # 在基线提交上保存基线
+#   cargo bench -- --baseline main         # 在当前提交上与基线比较
+#   scripts/check-bench-regression.sh [阈值文件]
+set -euo pipefail
+
+CRITERION_DIR="${CRITERION_DIR:-target/criterion}"
+THRESHOLDS="${1:-benches/thresholds.txt}"
+
+if [ ! -d "$CRITERION_DIR" ]; then
+    echo "❌ 没有找到基准测试结果: $CRITERION_DIR"
+    exit 1
+fi
+
+python3 - "$CRITERION_DIR" "$THRESHOLDS" <<'PY'
+import json
+import pathlib
+import sys
+
+criterion_dir = pathlib.Path(sys.argv[1])
+thresholds = {}
+for line in pathlib.Path(sys.argv[2]).read_text(encoding="utf-8").splitlines():
+    line = line.split("#", 1)[0].strip()
+    if line:
+        prefix, value = line.split()
+        thresholds[prefix] = float(value)
+default = thresholds.pop("default", 0.10)
+
+def threshold_for(bench_id):
+    matched = [p for p in thresholds if bench_id == p or bench_id.startswith(p + "/")]
+    return thresholds[max(matched, key=len)] if matched else default
+
+checked, regressions = 0, []
+for estimates in sorted(criterion_dir.glob("**/change/estimates.json")):
+    bench_dir = estimates.parent.parent
+    benchmark = json.loads((bench_dir / "new" / "benchmark.json").read_text())
+    bench_id = benchmark["full_id"]
+    # 比较结果早于最近一次运行时，是之前运行留下的
+    if estimates.stat().st_mtime < (bench_dir / "new" / "estimates.json").stat().st_mtime:
+        print(f"⚠️ {bench_id}: 最近一次运行没有与基线比较，跳过")
+        continue
+    change = json.loads(estimates.read_text())["mean"]["point_estimate"]
+    limit = threshold_for(bench_id)
+    checked += 1
+    mark = "❌" if change > limit else "✅"
+    print(f"{mark} {bench_id}: {change:+.1%} (阈值 {limit:.0%})")
+    if change > limit:
+        regressions.append(bench_id)
+
+if checked == 0:
+    print("❌ 没有与基线比较的结果，请先运行 cargo bench -- --baseline <名称>")
+    sys.exit(1)
+if regressions:
+    print(f"❌ {len(regressions)} 项基准测试超过回归阈值")
+    sys.exit(1)
+print(f"✅ {checked} 项基准测试均在阈值内")
+PY
@@ -0,0 +1,511 @@
+//! MIRA命令行
+//!
+//! ```text
+//! mira serve [--config mira.toml] [--addr 127.0.0.1:3000] [--grpc-addr 0.0.0.0:50051] [--qdrant-url URL] [--collection NAME] [--personality obedient|lively] [--inference-url URL]
+//! mira add [--type Preference] [--importance 0.5] [--keywords a,b] CONTENT
+//! mira search [--limit 10] [--types Preference,LongTerm] QUERY
+//! mira list [--types Preference,LongTerm]
+//! mira delete ID
+//! mira export [--format jsonl|ics] [--output FILE]
+//! mira import [--format jsonl|ics|whatsapp|telegram|json] FILE
+//! mira journal [--from 2024-01-01] [--to 2024-12-31] [--output mira_journal.md]
+//! mira stats
+//! mira emotion show
+//! mira consolidate
+//! ```
+//!
+//! `serve`先读取配置文件（默认当前目录的`mira.toml`）和`MIRA_`环境变量，见`mira.example.toml`；
+//! 环境变量`RUST_HOST`/`RUST_PORT`、`QDRANT_URL`、`QDRANT_COLLECTION_NAME`、`PYTHON_SERVICE_URL`和命令行参数依次覆盖对应项；
+//! `PYTHON_SERVICE_URL`和`--inference-url`只替换python推理后端的地址，其余推理配置不变。
+//! 运行期间修改配置文件时，`memory`和`emotion`中的设置立即生效，其余配置段需要重启。
+//! 推理服务不可用时，WebSocket对话只使用本地个性回复。
+//! `--grpc-addr`需要启用`grpc`特性，REST和gRPC共用同一个记忆管理器，Ctrl-C同时停止两者并写入待写记忆。
+//! 默认只监听本机；监听其他地址时应在`[server]`中设置`api_token`，替换个性档案需要`admin_token`。
+//!
+//! 其余为记忆管理命令，均接受`--user ID`（默认`default`）、`--config`以及`--qdrant-url`/`--collection`，
+//! 与`serve`读取同一个配置文件，向量存储、推理后端、嵌入生成器和记忆配置都相同；
+//! 配置和参数都未指定存储时读写本地文件`--data`（默认`mira_memories.json`）。导出格式为每行一条记忆的JSONL，`--format ics`导出带时间的计划记忆；`import`也接受.ics日历以及WhatsApp、Telegram和JSON聊天记录导出。
+
+use chrono::{DateTime, Days, Local, NaiveDate, NaiveTime, Utc};
+use mira::bridge::InferenceBackendConfig;
+use mira::config::{ConfigReloader, MiraConfig, DEFAULT_CONFIG_FILE, DEFAULT_POLL_INTERVAL};
+use mira::import::{ChatFormat, ChatImporter};
+#[cfg(feature = "mqtt")]
+use mira::integrations::mqtt::MqttBridge;
+use mira::memory::MemoryManager;
+use mira::scheduler::{JobAction, ScheduledJob, Scheduler, SchedulerEvent};
+use mira::server::{parse_memory_types, serve_with_shutdown, ApiState};
+use mira::vector_store::QdrantConfig;
+use mira::webhook::WebhookDispatcher;
+use mira::{EmotionalState, MemoryEntry, MemorySystem, MemoryType};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::net::SocketAddr;
+use std::ops::Bound;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+const USAGE: &str = "用法:
+  mira serve [--config FILE] [--addr HOST:PORT] [--grpc-addr HOST:PORT] [--qdrant-url URL] [--collection NAME] [--personality obedient|lively] [--inference-url URL]
+  mira add [--type TYPE] [--importance 0.5] [--keywords a,b] CONTENT
+  mira search [--limit N] [--types A,B] QUERY
+  mira list [--types A,B]
+  mira delete ID
+  mira export [--format jsonl|ics] [--output FILE]
+  mira import [--format jsonl|ics|whatsapp|telegram|json] FILE
+  mira journal [--from YYYY-MM-DD] [--to YYYY-MM-DD] [--output FILE]
+  mira stats
+  mira emotion show
+  mira consolidate
+记忆管理命令通用参数: [--user ID] [--config FILE] [--qdrant-url URL] [--collection NAME] [--data FILE]";
+
+/// 未指定Qdrant时记忆管理命令使用的本地文件
+const DEFAULT_DATA_FILE: &str = "mira_memories.json";
+
+/// `journal`未指定`--output`时写入的文件
+const DEFAULT_JOURNAL_FILE: &str = "mira_journal.md";
+
+/// `serve`子命令参数
+#[derive(Debug)]
+struct ServeArgs {
+    addr: SocketAddr,
+    #[cfg_attr(not(feature = "grpc"), allow(dead_code))]
+    grpc_addr: Option<SocketAddr>,
+    /// 配置文件和`MIRA_`环境变量，下面的命令行参数和旧环境变量会覆盖其中的对应项
+    config: MiraConfig,
+    /// 运行期间监视的配置文件
+    config_path: PathBuf,
+    /// 覆盖前从配置文件加载的配置，作为热加载的比较基准
+    loaded: MiraConfig,
+}
+
+impl ServeArgs {
+    fn parse(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let args: Vec<String> = args.collect();
+        let config_path = args.iter()
+            .position(|arg| arg == "--config")
+            .map(|index| args.get(index + 1).map(PathBuf::from).ok_or("--config 缺少参数值"))
+            .transpose()?;
+        let mut config = MiraConfig::load(config_path.as_deref()).map_err(|e| e.to_string())?;
+        config.validate().map_err(|e| e.to_string())?;
+        let loaded = config.clone();
+
+        let host = std::env::var("RUST_HOST").ok();
+        let port = std::env::var("RUST_PORT").ok();
+        let mut addr = match (host, port) {
+            (None, None) => config.server.addr.clone(),
+            (host, port) => format!("{}:{}", host.unwrap_or_else(|| "127.0.0.1".to_string()), port.unwrap_or_else(|| "3000".to_string())),
+        };
+        let mut grpc_addr = config.server.grpc_addr.clone();
+        let mut qdrant_url = std::env::var("QDRANT_URL").ok();
+        let mut collection = std::env::var("QDRANT_COLLECTION_NAME").ok();
+        let env_inference_url = std::env::var("PYTHON_SERVICE_URL").ok();
+        let mut inference_url = None;
+
+        let mut args = args.into_iter();
+        while let Some(flag) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{} 缺少参数值", flag));
+            match flag.as_str() {
+                "--config" => { value()?; }
+                "--addr" => addr = value()?,
+                "--grpc-addr" => grpc_addr = Some(value()?),
+                "--qdrant-url" => qdrant_url = Some(value()?),
+                "--collection" => collection = Some(value()?),
+                "--inference-url" => inference_url = Some(value()?),
+                "--personality" => {
+                    config.personality.preset = Some(value()?);
+                    config.personality.path = None;
+                }
+                other => return Err(format!("未知参数: {}", other)),
+            }
+        }
+
+        override_store(&mut config, qdrant_url, collection);
+        override_inference_url(&mut config, env_inference_url);
+        if let Some(url) = inference_url {
+            if !set_inference_url(&mut config, url) {
+                return Err("--inference-url 只适用于python推理后端".to_string());
+            }
+        }
+        // 提前校验个性预设，避免启动后才报错
+        config.personality_profile().map_err(|e| e.to_string())?;
+
+        let parse_addr = |addr: &str| addr.parse().map_err(|e| format!("监听地址 {} 无效: {}", addr, e));
+        let addr = parse_addr(&addr)?;
+        let grpc_addr = grpc_addr.as_deref().map(parse_addr).transpose()?;
+        if grpc_addr.is_some() && !cfg!(feature = "grpc") {
+            return Err("--grpc-addr 需要启用grpc特性".to_string());
+        }
+        let config_path = config_path.unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_FILE));
+        Ok(Self { addr, grpc_addr, config, config_path, loaded })
+    }
+}
+
+/// 指定Qdrant地址时使用Qdrant存储，集合名只在使用Qdrant时生效
+fn override_store(config: &mut MiraConfig, qdrant_url: Option<String>, collection: Option<String>) {
+    if let Some(url) = qdrant_url {
+        let qdrant = config.vector_store.qdrant.get_or_insert_with(|| QdrantConfig {
+            vector_size: config.embedder.dimension(),
+            ..QdrantConfig::default()
+        });
+        qdrant.url = url;
+    }
+    if let (Some(qdrant), Some(collection)) = (config.vector_store.qdrant.as_mut(), collection) {
+        qdrant.collection_name = collection;
+    }
+}
+
+/// 替换python推理后端的地址，其他后端返回false
+fn set_inference_url(config: &mut MiraConfig, url: String) -> bool {
+    match config.inference {
+        InferenceBackendConfig::Python { url: ref mut current, .. } => {
+            *current = url;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// `PYTHON_SERVICE_URL`只替换python推理后端的地址，配置了其他后端时忽略
+fn override_inference_url(config: &mut MiraConfig, url: Option<String>) {
+    if let Some(url) = url {
+        if !set_inference_url(config, url) {
+            tracing::warn!("推理后端不是python，忽略PYTHON_SERVICE_URL");
+        }
+    }
+}
+
+/// 记忆管理命令的参数：选项和位置参数
+#[derive(Debug, Default)]
+struct AdminArgs {
+    options: HashMap<String, String>,
+    positional: Vec<String>,
+}
+
+impl AdminArgs {
+    /// 解析参数，`allowed`为该命令除通用参数外接受的选项
+    fn parse(mut args: impl Iterator<Item = String>, allowed: &[&str]) -> Result<Self, String> {
+        const COMMON: [&str; 5] = ["--user", "--qdrant-url", "--collection", "--data", "--config"];
+
+        let mut parsed = Self::default();
+        while let Some(arg) = args.next() {
+            if !arg.starts_with("--") {
+                parsed.positional.push(arg);
+                continue;
+            }
+            if !COMMON.contains(&arg.as_str()) && !allowed.contains(&arg.as_str()) {
+                return Err(format!("未知参数: {}", arg));
+            }
+            let value = args.next().ok_or_else(|| format!("{} 缺少参数值", arg))?;
+            parsed.options.insert(arg, value);
+        }
+        Ok(parsed)
+    }
+
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(String::as_str)
+    }
+
+    fn parsed_option<T: std::str::FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        self.option(name)
+            .map(|value| value.parse().map_err(|_| format!("{} 的值 {} 无效", name, value)))
+            .transpose()
+    }
+
+    fn memory_types(&self) -> Result<Option<Vec<MemoryType>>, String> {
+        self.option("--types").map(parse_memory_types).transpose().map_err(|e| e.to_string())
+    }
+
+    /// 唯一的位置参数
+    fn single(&self, name: &str) -> Result<&str, String> {
+        match self.positional.as_slice() {
+            [value] => Ok(value),
+            _ => Err(format!("需要一个{}参数", name)),
+        }
+    }
+
+    /// 按`--config`（默认`mira.toml`）创建记忆管理器，参数和环境变量覆盖方式与`serve`相同
+    async fn manager(&self) -> anyhow::Result<MemoryManager> {
+        let mut config = MiraConfig::load(self.option("--config").map(std::path::Path::new))?;
+        config.validate()?;
+        let qdrant_url = self.option("--qdrant-url").map(str::to_string).or_else(|| std::env::var("QDRANT_URL").ok());
+        let collection = self.option("--collection").map(str::to_string)
+            .or_else(|| std::env::var("QDRANT_COLLECTION_NAME").ok());
+        override_store(&mut config, qdrant_url, collection);
+        override_inference_url(&mut config, std::env::var("PYTHON_SERVICE_URL").ok());
+
+        // 本地文件在记忆系统释放时写回
+        let store = &mut config.vector_store;
+        if let Some(data) = self.option("--data") {
+            store.data_file = Some(PathBuf::from(data));
+        } else if store.qdrant.is_none() && store.segment_file.is_none() && store.data_file.is_none() {
+            store.data_file = Some(PathBuf::from(DEFAULT_DATA_FILE));
+        }
+        Ok(config.memory_manager().await?)
+    }
+
+    fn user_id(&self) -> &str {
+        self.option("--user").unwrap_or("default")
+    }
+}
+
+fn print_entry(entry: &MemoryEntry) {
+    println!("{}  {:<12} {:.2}  {}", entry.id, format!("{:?}", entry.memory_type), entry.importance, entry.content);
+}
+
+fn print_emotion(emotion: &EmotionalState) {
+    println!("心情: {}", emotion.mood);
+    println!("开心: {:.2}  亲密: {:.2}  信任: {:.2}  依赖: {:.2}", emotion.happiness, emotion.affection, emotion.trust, emotion.dependency);
+    println!("时间: {}", emotion.timestamp);
+}
+
+/// 执行记忆管理命令
+async fn run_admin(command: &str, args: impl Iterator<Item = String>) -> anyhow::Result<()> {
+    let allowed: &[&str] = match command {
+        "add" => &["--type", "--importance", "--keywords"],
+        "search" => &["--limit", "--types"],
+        "list" => &["--types"],
+        "export" => &["--format", "--output"],
+        "import" => &["--format"],
+        "journal" => &["--from", "--to", "--output"],
+        _ => &[],
+    };
+    let args = AdminArgs::parse(args, allowed).map_err(anyhow::Error::msg)?;
+    let manager = args.manager().await?;
+    let system = manager.get_or_create(args.user_id()).await?;
+    run_admin_command(command, &args, &system).await?;
+    // 写入待写记忆后再退出
+    manager.shutdown().await?;
+    Ok(())
+}
+
+/// 在用户的记忆系统上执行单个命令
+async fn run_admin_command(command: &str, args: &AdminArgs, system: &Arc<MemorySystem>) -> anyhow::Result<()> {
+    match command {
+        "add" => {
+            let memory_type = match args.option("--type") {
+                Some(name) => parse_memory_types(name)?.pop().ok_or_else(|| anyhow::anyhow!("--type 不能为空"))?,
+                None => MemoryType::LongTerm,
+            };
+            let importance = args.parsed_option("--importance").map_err(anyhow::Error::msg)?.unwrap_or(0.5);
+            let keywords = args.option("--keywords")
+                .map(|keywords| keywords.split(',').map(str::trim).filter(|k| !k.is_empty()).map(str::to_string).collect())
+                .unwrap_or_default();
+            let content = args.positional.join(" ");
+            if content.trim().is_empty() {
+                anyhow::bail!("需要记忆内容");
+            }
+            println!("{}", system.add_memory(memory_type, content, keywords, importance, None).await?);
+        }
+        "search" => {
+            let limit = args.parsed_option("--limit").map_err(anyhow::Error::msg)?;
+            let query = args.positional.join(" ");
+            for entry in system.retrieve_memories(&query, args.memory_types().map_err(anyhow::Error::msg)?, limit).await? {
+                print_entry(&entry);
+            }
+        }
+        "list" => {
+            for entry in system.list_memories(args.memory_types().map_err(anyhow::Error::msg)?).await? {
+                print_entry(&entry);
+            }
+        }
+        "delete" => {
+            let id = args.single("记忆ID").map_err(anyhow::Error::msg)?.parse()?;
+            system.delete_memory(id).await?;
+            println!("已删除 {}", id);
+        }
+        "export" => {
+            let mut output: Box<dyn Write> = match args.option("--output") {
+                Some(path) => Box::new(std::io::BufWriter::new(std::fs::File::create(path)?)),
+                None => Box::new(std::io::stdout().lock()),
+            };
+            match args.option("--format").unwrap_or("jsonl") {
+                "jsonl" => {
+                    // 导出时附带嵌入，导入时不必重新生成
+                    let entries = system.list_memories(None).await?;
+                    for entry in &entries {
+                        let embedding = system.memory_embedding(entry.id).await?.map(Arc::unwrap_or_clone);
+                        serde_json::to_writer(&mut output, &MemoryEntry { embedding, ..MemoryEntry::clone(entry) })?;
+                        output.write_all(b"\n")?;
+                    }
+                    eprintln!("已导出 {} 条记忆", entries.len());
+                }
+                "ics" => output.write_all(system.export_calendar().await?.as_bytes())?,
+                other => anyhow::bail!("不支持的导出格式: {}", other),
+            }
+            output.flush()?;
+        }
+        "journal" => {
+            // 日期按本地时区解析，--to当天也包含在内
+            let day_start = |name: &str, offset: u64| -> anyhow::Result<Option<DateTime<Utc>>> {
+                let Some(date) = args.parsed_option::<NaiveDate>(name).map_err(anyhow::Error::msg)? else {
+                    return Ok(None);
+                };
+                let start = (date + Days::new(offset)).and_time(NaiveTime::MIN).and_local_timezone(Local).earliest()
+                    .ok_or_else(|| anyhow::anyhow!("{} 的值 {} 无效", name, date))?;
+                Ok(Some(start.with_timezone(&Utc)))
+            };
+            let range = (
+                day_start("--from", 0)?.map_or(Bound::Unbounded, Bound::Included),
+                day_start("--to", 1)?.map_or(Bound::Unbounded, Bound::Excluded),
+            );
+            let output = args.option("--output").unwrap_or(DEFAULT_JOURNAL_FILE);
+            let exported = system.export_journal(range, output).await?;
+            println!("已将 {} 条记忆写入 {}", exported, output);
+        }
+        "import" => {
+            let path = args.single("文件路径").map_err(anyhow::Error::msg)?;
+            match args.option("--format").unwrap_or("jsonl") {
+                "jsonl" => {
+                    let mut entries = Vec::new();
+                    for line in std::io::BufReader::new(std::fs::File::open(path)?).lines() {
+                        let line = line?;
+                        if !line.trim().is_empty() {
+                            entries.push(serde_json::from_str::<MemoryEntry>(&line)?);
+                        }
+                    }
+                    println!("已导入 {} 条记忆", system.import_memories(entries).await?);
+                }
+                "ics" => {
+                    let imported = system.import_calendar(&std::fs::read_to_string(path)?, 0.6).await?;
+                    println!("已从日历导入 {} 个事件", imported);
+                }
+                format => {
+                    let format: ChatFormat = format.parse()?;
+                    let imported = ChatImporter::new(system.clone()).import_export(format, &std::fs::read_to_string(path)?).await?;
+                    println!("已从聊天记录导入 {} 条记忆", imported);
+                }
+            }
+        }
+        "stats" => {
+            system.list_memories(None).await?;
+            let mut stats: Vec<_> = system.get_memory_stats().await.into_iter().collect();
+            stats.sort();
+            for (name, count) in stats {
+                println!("{:<12} {}", name, count);
+            }
+        }
+        "emotion" => {
+            if args.single("子命令").map_err(anyhow::Error::msg)? != "show" {
+                anyhow::bail!("未知的emotion子命令\n{}", USAGE);
+            }
+            // 情感状态不单独持久化，取最近一条记忆记录的情感
+            let latest = system.list_memories(None).await?
+                .into_iter()
+                .filter_map(|entry| entry.emotional_context.clone())
+                .max_by_key(|emotion| emotion.timestamp);
+            match latest {
+                Some(emotion) => print_emotion(&emotion),
+                None => {
+                    println!("没有记录情感的记忆，显示默认状态");
+                    print_emotion(&EmotionalState::default());
+                }
+            }
+        }
+        "consolidate" => {
+            system.list_memories(None).await?;
+            println!("已将 {} 条短期记忆转为长期记忆", system.consolidate_memories().await?);
+        }
+        _ => unreachable!(),
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    #[cfg(feature = "otlp")]
+    let _telemetry = mira::telemetry::init_tracing("mira")?;
+    #[cfg(not(feature = "otlp"))]
+    tracing_subscriber::fmt::init();
+
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("serve") => {
+            let args = ServeArgs::parse(args).map_err(|e| anyhow::anyhow!("{}\n{}", e, USAGE))?;
+            let engine = Arc::new(args.config.emotional_engine());
+            let manager = Arc::new(args.config.memory_manager().await?.with_emotional_engine(engine.clone()));
+            let state = ApiState::new(manager.clone())
+                .with_engine(engine.clone())
+                .with_personality(args.config.personality_profile()?)
+                .with_inference(args.config.inference_client()?)
+                .with_api_token(args.config.server.api_token.clone())
+                .with_admin_token(args.config.server.admin_token.clone());
+
+            // 配置文件修改后，阈值、上限、衰减率和词表直接生效
+            let reloader = ConfigReloader::new(&args.config_path, args.loaded)
+                .with_manager(manager.clone())
+                .with_engine(engine.clone());
+            let _watcher = Arc::new(reloader).watch(DEFAULT_POLL_INTERVAL);
+            // 关系阶段和持续难过事件
+            let webhooks = if args.config.webhooks.urls.is_empty() {
+                None
+            } else {
+                Some(WebhookDispatcher::new(args.config.webhooks.clone())?)
+            };
+            let _webhook_watcher = webhooks.clone().map(|webhooks| webhooks.watch(&manager));
+            // 提醒、主动消息和定期记忆维护
+            let _scheduler = if args.config.scheduler.enabled {
+                let mut scheduler = Scheduler::new(manager.clone(), args.config.scheduler_store().await?).await?;
+                if let Some(webhooks) = webhooks {
+                    scheduler = scheduler.with_webhooks(webhooks);
+                }
+                if let Some(ref schedule) = args.config.scheduler.maintenance {
+                    scheduler.ensure_job(ScheduledJob::new("purge_expired", schedule.clone(), JobAction::PurgeExpired { user_id: None })?).await?;
+                }
+                // 主动消息和到期提醒推送给已连接的WebSocket对话
+                let forwarder = state.forward_proactive(scheduler.subscribe(), |event| match event {
+                    SchedulerEvent::Message { user_id, text, .. } => Some((user_id, text)),
+                    SchedulerEvent::Reminder { user_id, content, .. } => Some((user_id, format!("提醒：{}", content))),
+                });
+                let ticker = Arc::new(scheduler).start(std::time::Duration::from_secs(args.config.scheduler.tick_seconds.max(1)));
+                Some((forwarder, ticker))
+            } else {
+                None
+            };
+            // 发布情感状态，在家状态事件转为情感触发
+            #[cfg(feature = "mqtt")]
+            let _mqtt = args.config.mqtt.enabled.then(|| {
+                let bridge = Arc::new(MqttBridge::new(args.config.mqtt.clone(), manager.clone(), engine.clone()));
+                // 在家状态的主动消息推送给已连接的WebSocket对话
+                state.forward_proactive(bridge.subscribe(), |message| Some((message.user_id, message.message)));
+                bridge.start()
+            });
+            // 一次Ctrl-C同时停止REST和gRPC服务，两者都停止后写入待写记忆
+            let (stop, stopped) = tokio::sync::watch::channel(false);
+            tokio::spawn(async move {
+                let _ = tokio::signal::ctrl_c().await;
+                let _ = stop.send(true);
+            });
+            let signal = |mut stopped: tokio::sync::watch::Receiver<bool>| async move {
+                let _ = stopped.wait_for(|stopped| *stopped).await;
+            };
+            let rest = serve_with_shutdown(args.addr, state, signal(stopped.clone()));
+
+            #[cfg(feature = "grpc")]
+            let served = match args.grpc_addr {
+                Some(grpc_addr) => {
+                    let grpc = mira::grpc::serve_grpc_with_shutdown(grpc_addr, manager.clone(), signal(stopped));
+                    tokio::try_join!(
+                        async { rest.await.map_err(anyhow::Error::from) },
+                        async { grpc.await.map_err(anyhow::Error::from) },
+                    ).map(drop)
+                }
+                None => rest.await.map_err(anyhow::Error::from),
+            };
+            #[cfg(not(feature = "grpc"))]
+            let served = rest.await.map_err(anyhow::Error::from);
+
+            let flushed = manager.shutdown().await;
+            served?;
+            tracing::info!("MIRA 已停止，写入 {} 条待写记忆", flushed?);
+            Ok(())
+        }
+        Some(command @ ("add" | "search" | "list" | "delete" | "export" | "import" | "journal" | "stats" | "emotion" | "consolidate")) => {
+            run_admin(command, args).await
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,399 @@
+//! 推理结果缓存
+//!
+//! 长对话中相同的短消息会被反复分析，按内容哈希缓存嵌入、关键词和情感结果，
+//! 容量满时淘汰最久未使用的条目，超过TTL的条目视为失效。
+//! 嵌入的键包含模型名称，切换模型后不会取到另一个向量空间的结果。
+
+use super::history::ChatTurn;
+use super::inference::{InferenceClient, TokenStream};
+use crate::{EmotionalState, MemoryEntry, MemoryError, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// 文本内容的SHA-256摘要
+type ContentHash = [u8; 32];
+
+fn content_hash(text: &str) -> ContentHash {
+    scoped_hash("", text)
+}
+
+/// 带作用域的摘要 - 作用域与内容之间用0字节分隔，避免拼接后碰撞
+fn scoped_hash(scope: &str, text: &str) -> ContentHash {
+    let mut context = ring::digest::Context::new(&ring::digest::SHA256);
+    if !scope.is_empty() {
+        context.update(scope.as_bytes());
+        context.update(&[0]);
+    }
+    context.update(text.as_bytes());
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(context.finish().as_ref());
+    hash
+}
+
+/// 缓存配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InferenceCacheConfig {
+    /// 每类结果最多缓存的条目数
+    pub capacity: usize,
+    /// 条目有效期(毫秒)
+    pub ttl_ms: u64,
+}
+
+impl Default for InferenceCacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 1024,
+            ttl_ms: 600_000,
+        }
+    }
+}
+
+/// 缓存命中统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct InferenceCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+}
+
+#[derive(Debug)]
+struct CacheEntry<V> {
+    value: V,
+    inserted_at: Instant,
+    last_used: u64,
+}
+
+/// 带TTL的LRU缓存 - 容量通常只有上千条，淘汰时线性查找最久未用的条目
+#[derive(Debug)]
+struct TtlLru<V> {
+    entries: HashMap<ContentHash, CacheEntry<V>>,
+    capacity: usize,
+    ttl: Duration,
+    clock: u64,
+}
+
+impl<V: Clone> TtlLru<V> {
+    fn new(config: &InferenceCacheConfig) -> Self {
+        Self {
+            entries: HashMap::new(),
+            capacity: config.capacity.max(1),
+            ttl: Duration::from_millis(config.ttl_ms),
+            clock: 0,
+        }
+    }
+
+    fn get(&mut self, key: &ContentHash) -> Option<V> {
+        self.clock += 1;
+        let expired = match self.entries.get_mut(key) {
+            Some(entry) if entry.inserted_at.elapsed() < self.ttl => {
+                entry.last_used = self.clock;
+                return Some(entry.value.clone());
+            }
+            Some(_) => true,
+            None => false,
+        };
+        if expired {
+            self.entries.remove(key);
+        }
+        None
+    }
+
+    fn insert(&mut self, key: ContentHash, value: V) {
+        self.clock += 1;
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&key) {
+            let ttl = self.ttl;
+            self.entries.retain(|_, entry| entry.inserted_at.elapsed() < ttl);
+
+            if self.entries.len() >= self.capacity {
+                let oldest = self.entries.iter()
+                    .min_by_key(|(_, entry)| entry.last_used)
+                    .map(|(key, _)| *key);
+                if let Some(oldest) = oldest {
+                    self.entries.remove(&oldest);
+                }
+            }
+        }
+
+        self.entries.insert(key, CacheEntry {
+            value,
+            inserted_at: Instant::now(),
+            last_used: self.clock,
+        });
+    }
+}
+
+/// 缓存嵌入、关键词和情感结果的推理客户端
+#[derive(Debug)]
+pub struct CachedInferenceClient<C> {
+    inner: C,
+    /// 嵌入模型名称，作为嵌入键的一部分
+    embedding_model: String,
+    /// 期望的嵌入维度，不符的结果不缓存并返回错误
+    embedding_dimension: Option<usize>,
+    embeddings: Mutex<TtlLru<Vec<f32>>>,
+    keywords: Mutex<TtlLru<Vec<String>>>,
+    emotions: Mutex<TtlLru<EmotionalState>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<C: InferenceClient> CachedInferenceClient<C> {
+    pub fn new(inner: C, config: InferenceCacheConfig) -> Self {
+        Self {
+            inner,
+            embedding_model: String::new(),
+            embedding_dimension: None,
+            embeddings: Mutex::new(TtlLru::new(&config)),
+            keywords: Mutex::new(TtlLru::new(&config)),
+            emotions: Mutex::new(TtlLru::new(&config)),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// 设置嵌入模型和维度
+    pub fn with_embedding_model(mut self, model: impl Into<String>, dimension: usize) -> Self {
+        self.embedding_model = model.into();
+        self.embedding_dimension = Some(dimension);
+        self
+    }
+
+    /// 命中统计
+    pub fn stats(&self) -> InferenceCacheStats {
+        let entries = lock(&self.embeddings).entries.len()
+            + lock(&self.keywords).entries.len()
+            + lock(&self.emotions).entries.len();
+        InferenceCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries,
+        }
+    }
+
+    /// 清空全部缓存
+    pub fn clear(&self) {
+        lock(&self.embeddings).entries.clear();
+        lock(&self.keywords).entries.clear();
+        lock(&self.emotions).entries.clear();
+    }
+
+    fn lookup<V: Clone>(&self, cache: &Mutex<TtlLru<V>>, key: &ContentHash) -> Option<V> {
+        let value = lock(cache).get(key);
+        let counter = if value.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        value
+    }
+
+    fn embedding_key(&self, text: &str) -> ContentHash {
+        scoped_hash(&self.embedding_model, text)
+    }
+
+    /// 检查嵌入维度，未设置维度时不检查
+    fn check_dimension(&self, embedding: &[f32]) -> Result<()> {
+        match self.embedding_dimension {
+            Some(expected) if embedding.len() != expected => Err(MemoryError::DimensionMismatch {
+                expected,
+                actual: embedding.len(),
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn lock<V>(cache: &Mutex<TtlLru<V>>) -> std::sync::MutexGuard<'_, TtlLru<V>> {
+    cache.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[async_trait]
+impl<C: InferenceClient> InferenceClient for CachedInferenceClient<C> {
+    async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>> {
+        let key = self.embedding_key(text);
+        if let Some(embedding) = self.lookup(&self.embeddings, &key) {
+            return Ok(embedding);
+        }
+
+        let embedding = self.inner.generate_embedding(text).await?;
+        self.check_dimension(&embedding)?;
+        lock(&self.embeddings).insert(key, embedding.clone());
+        Ok(embedding)
+    }
+
+    /// 只请求未命中的文本，结果按原顺序合并
+    async fn generate_embeddings(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let keys: Vec<ContentHash> = texts.iter().map(|text| self.embedding_key(text)).collect();
+        let mut results: Vec<Option<Vec<f32>>> = keys.iter()
+            .map(|key| self.lookup(&self.embeddings, key))
+            .collect();
+
+        let missing: Vec<usize> = (0..texts.len()).filter(|&i| results[i].is_none()).collect();
+        if !missing.is_empty() {
+            let fetched = self.inner
+                .generate_embeddings(missing.iter().map(|&i| texts[i].clone()).collect())
+                .await?;
+            if fetched.len() != missing.len() {
+                return Err(MemoryError::InferenceError(format!(
+                    "嵌入数量不符: 请求{}条，返回{}条",
+                    missing.len(),
+                    fetched.len()
+                )));
+            }
+            for embedding in &fetched {
+                self.check_dimension(embedding)?;
+            }
+
+            let mut cache = lock(&self.embeddings);
+            for (i, embedding) in missing.into_iter().zip(fetched) {
+                cache.insert(keys[i], embedding.clone());
+                results[i] = Some(embedding);
+            }
+        }
+
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    async fn generate_response(
+        &self,
+        user_input: &str,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<String> {
+        self.inner.generate_response(user_input, context, emotional_state).await
+    }
+
+    async fn generate_response_stream(
+        &self,
+        user_input: &str,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<TokenStream> {
+        self.inner.generate_response_stream(user_input, context, emotional_state).await
+    }
+
+    async fn generate_response_with_history(
+        &self,
+        user_input: &str,
+        history: Vec<ChatTurn>,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<String> {
+        self.inner.generate_response_with_history(user_input, history, context, emotional_state).await
+    }
+
+    async fn generate_response_stream_with_history(
+        &self,
+        user_input: &str,
+        history: Vec<ChatTurn>,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<TokenStream> {
+        self.inner.generate_response_stream_with_history(user_input, history, context, emotional_state).await
+    }
+
+    /// 命中时刷新时间戳，表示这次分析的时间
+    async fn analyze_emotion(&self, text: &str) -> Result<EmotionalState> {
+        let key = content_hash(text);
+        if let Some(mut emotion) = self.lookup(&self.emotions, &key) {
+            emotion.timestamp = chrono::Utc::now();
+            return Ok(emotion);
+        }
+
+        let emotion = self.inner.analyze_emotion(text).await?;
+        lock(&self.emotions).insert(key, emotion.clone());
+        Ok(emotion)
+    }
+
+    async fn extract_keywords(&self, text: &str) -> Result<Vec<String>> {
+        let key = content_hash(text);
+        if let Some(keywords) = self.lookup(&self.keywords, &key) {
+            return Ok(keywords);
+        }
+
+        let keywords = self.inner.extract_keywords(text).await?;
+        lock(&self.keywords).insert(key, keywords.clone());
+        Ok(keywords)
+    }
+
+    /// 重要性依赖调用时的情感状态，不缓存
+    async fn calculate_importance(&self, text: &str, emotional_state: Option<EmotionalState>) -> Result<f32> {
+        self.inner.calculate_importance(text, emotional_state).await
+    }
+
+    async fn generate_image_embedding(&self, image: &[u8]) -> Result<Vec<f32>> {
+        self.inner.generate_image_embedding(image).await
+    }
+
+    async fn health_check(&self) -> bool {
+        self.inner.health_check().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::MockInferenceClient;
+
+    #[tokio::test]
+    async fn test_repeated_texts_hit_cache() {
+        let client = CachedInferenceClient::new(MockInferenceClient::new(), InferenceCacheConfig::default());
+
+        let first = client.generate_embedding("晚安").await.unwrap();
+        assert_eq!(client.generate_embedding("晚安").await.unwrap(), first);
+        client.extract_keywords("晚安 宝贝").await.unwrap();
+        client.extract_keywords("晚安 宝贝").await.unwrap();
+
+        // 批量请求中只有"早安"未命中
+        let batch = client.generate_embeddings(vec!["早安".into(), "晚安".into()]).await.unwrap();
+        assert_eq!(batch[1], first);
+
+        let stats = client.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (3, 3, 3));
+    }
+
+    #[test]
+    fn test_lru_evicts_least_recently_used_and_expired() {
+        let mut cache = TtlLru::new(&InferenceCacheConfig { capacity: 2, ttl_ms: 60_000 });
+        let (a, b, c) = (content_hash("a"), content_hash("b"), content_hash("c"));
+
+        cache.insert(a, 1);
+        cache.insert(b, 2);
+        assert_eq!(cache.get(&a), Some(1));
+        cache.insert(c, 3);
+        assert_eq!((cache.get(&a), cache.get(&b), cache.get(&c)), (Some(1), None, Some(3)));
+
+        let mut expired = TtlLru::new(&InferenceCacheConfig { capacity: 2, ttl_ms: 0 });
+        expired.insert(a, 1);
+        assert_eq!(expired.get(&a), None);
+        assert!(expired.entries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_embeddings_keyed_by_model_and_checked() {
+        let client = CachedInferenceClient::new(
+            MockInferenceClient::new().with_embedding_dim(8),
+            InferenceCacheConfig::default(),
+        );
+        let key = client.embedding_key("晚安");
+        let client = client.with_embedding_model("bge-small-zh", 8);
+        assert_ne!(client.embedding_key("晚安"), key);
+
+        client.generate_embedding("晚安").await.unwrap();
+        assert_eq!(client.stats().entries, 1);
+
+        // 维度不符的结果不进入缓存
+        let client = CachedInferenceClient::new(
+            MockInferenceClient::new().with_embedding_dim(8),
+            InferenceCacheConfig::default(),
+        )
+        .with_embedding_model("bge-small-zh", 512);
+        assert!(matches!(
+            client.generate_embedding("晚安").await,
+            Err(MemoryError::DimensionMismatch { expected: 512, actual: 8 })
+        ));
+        assert!(client.generate_embeddings(vec!["早安".into()]).await.is_err());
+        assert_eq!(client.stats().entries, 0);
+    }
+}
@@ -0,0 +1,138 @@
+//! 熔断器 - 下游服务连续失败时快速失败，冷却后放行一次试探请求
+
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 熔断器配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CircuitBreakerConfig {
+    /// 连续失败达到该次数后熔断
+    pub failure_threshold: u32,
+    /// 熔断持续时间(毫秒)，到期后进入半开状态
+    pub open_duration_ms: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            open_duration_ms: 30_000,
+        }
+    }
+}
+
+/// 熔断器状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BreakerState {
+    /// 正常放行，记录连续失败次数
+    Closed { failures: u32 },
+    /// 熔断中，拒绝所有请求
+    Open { until: Instant },
+    /// 试探请求进行中，其余请求继续拒绝
+    HalfOpen,
+}
+
+/// 熔断器
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    /// 创建处于闭合状态的熔断器
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(BreakerState::Closed { failures: 0 }),
+        }
+    }
+
+    /// 是否允许发出请求；熔断到期后只放行一个试探请求
+    pub fn allow_request(&self) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match *state {
+            BreakerState::Closed { .. } => true,
+            BreakerState::Open { until } if Instant::now() >= until => {
+                *state = BreakerState::HalfOpen;
+                true
+            }
+            BreakerState::Open { .. } | BreakerState::HalfOpen => false,
+        }
+    }
+
+    /// 记录请求成功，恢复闭合状态
+    pub fn record_success(&self) {
+        *self.state.lock().unwrap_or_else(|e| e.into_inner()) = BreakerState::Closed { failures: 0 };
+    }
+
+    /// 记录请求失败，达到阈值或试探失败时熔断
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let failures = match *state {
+            BreakerState::Closed { failures } => failures + 1,
+            BreakerState::Open { .. } | BreakerState::HalfOpen => self.config.failure_threshold,
+        };
+
+        *state = if failures >= self.config.failure_threshold {
+            tracing::warn!("下游服务连续失败 {} 次，熔断 {}ms", failures, self.config.open_duration_ms);
+            BreakerState::Open {
+                until: Instant::now() + Duration::from_millis(self.config.open_duration_ms),
+            }
+        } else {
+            BreakerState::Closed { failures }
+        };
+    }
+
+    /// 当前是否处于熔断状态（包括半开）
+    pub fn is_open(&self) -> bool {
+        !matches!(
+            *self.state.lock().unwrap_or_else(|e| e.into_inner()),
+            BreakerState::Closed { .. }
+        )
+    }
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new(CircuitBreakerConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trips_after_threshold_and_recovers_through_probe() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 2,
+            open_duration_ms: 0,
+        });
+
+        breaker.record_failure();
+        assert!(!breaker.is_open());
+        breaker.record_failure();
+        assert!(breaker.is_open());
+
+        // 冷却到期后只放行一个试探请求
+        assert!(breaker.allow_request());
+        assert!(!breaker.allow_request());
+
+        breaker.record_success();
+        assert!(!breaker.is_open());
+        assert!(breaker.allow_request());
+    }
+
+    #[test]
+    fn test_rejects_while_open() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 1,
+            open_duration_ms: 60_000,
+        });
+
+        breaker.record_failure();
+        assert!(!breaker.allow_request());
+    }
+}
@@ -0,0 +1,158 @@
+//! 嵌入请求微批处理
+//!
+//! 并发的单条`embed`调用进入同一队列，后台任务在批量上限或等待时间到达时
+//! 合并为一次`generate_embeddings`请求，减少与Python服务之间的往返次数。
+
+use super::inference::InferenceClient;
+use crate::{MemoryError, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+/// 排队等待嵌入的文本及结果回传通道
+type PendingEmbedding = (String, oneshot::Sender<Result<Vec<f32>>>);
+
+/// 微批处理配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingBatcherConfig {
+    /// 单批最多合并的文本数
+    pub max_batch_size: usize,
+    /// 收到第一条文本后最多等待多久(毫秒)再发送
+    pub max_delay_ms: u64,
+}
+
+impl Default for EmbeddingBatcherConfig {
+    fn default() -> Self {
+        Self {
+            max_batch_size: 32,
+            max_delay_ms: 5,
+        }
+    }
+}
+
+/// 嵌入请求微批处理器
+#[derive(Debug, Clone)]
+pub struct EmbeddingBatcher {
+    sender: mpsc::UnboundedSender<PendingEmbedding>,
+}
+
+impl EmbeddingBatcher {
+    /// 启动后台批处理任务 - 需要在tokio运行时中调用，所有句柄释放后任务退出
+    pub fn new(client: Arc<dyn InferenceClient>, config: EmbeddingBatcherConfig) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(Self::run(client, config, receiver));
+        Self { sender }
+    }
+
+    /// 生成单条文本的嵌入，与其他并发调用合并发送
+    pub async fn embed(&self, text: impl Into<String>) -> Result<Vec<f32>> {
+        let (reply, result) = oneshot::channel();
+        self.sender.send((text.into(), reply))
+            .map_err(|_| MemoryError::InferenceUnavailable("嵌入批处理任务已停止".to_string()))?;
+
+        result.await
+            .map_err(|_| MemoryError::InferenceUnavailable("嵌入批处理任务已停止".to_string()))?
+    }
+
+    /// 后台循环：等到第一条文本后收集一批，整批请求并分发结果
+    async fn run(
+        client: Arc<dyn InferenceClient>,
+        config: EmbeddingBatcherConfig,
+        mut receiver: mpsc::UnboundedReceiver<PendingEmbedding>,
+    ) {
+        let max_batch_size = config.max_batch_size.max(1);
+        let max_delay = Duration::from_millis(config.max_delay_ms);
+
+        while let Some(first) = receiver.recv().await {
+            let mut batch = vec![first];
+            let deadline = tokio::time::Instant::now() + max_delay;
+
+            while batch.len() < max_batch_size {
+                match tokio::time::timeout_at(deadline, receiver.recv()).await {
+                    Ok(Some(pending)) => batch.push(pending),
+                    Ok(None) | Err(_) => break,
+                }
+            }
+
+            let (texts, replies): (Vec<String>, Vec<_>) = batch.into_iter().unzip();
+            match client.generate_embeddings(texts).await {
+                Ok(embeddings) => {
+                    for (reply, embedding) in replies.into_iter().zip(embeddings) {
+                        let _ = reply.send(Ok(embedding));
+                    }
+                }
+                Err(e) => {
+                    for reply in replies {
+                        let _ = reply.send(Err(Self::share_error(&e)));
+                    }
+                }
+            }
+        }
+    }
+
+    /// 为同批的每个调用方复制错误
+    fn share_error(error: &MemoryError) -> MemoryError {
+        match error {
+            MemoryError::InferenceUnavailable(reason) => MemoryError::InferenceUnavailable(reason.clone()),
+            other => MemoryError::InferenceError(other.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::PythonInferenceClient;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+    /// 每条文本返回`[字符数]`作为嵌入
+    async fn embedding_server() -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).and(path("/inference"))
+            .respond_with(|request: &Request| {
+                let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+                let embeddings: Vec<Vec<f32>> = body["texts"].as_array().unwrap().iter()
+                    .map(|text| vec![text.as_str().unwrap().chars().count() as f32])
+                    .collect();
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                    "success": true, "result": embeddings, "error": null, "processing_time_ms": 1
+                }))
+            })
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn test_generate_embeddings_chunks_requests() {
+        let server = embedding_server().await;
+        let client = PythonInferenceClient::new(server.uri(), 5).with_embedding_batch_size(2);
+
+        let texts = vec!["一".to_string(), "二二".to_string(), "三三三".to_string()];
+        let embeddings = client.generate_embeddings(texts).await.unwrap();
+
+        assert_eq!(embeddings, vec![vec![1.0], vec![2.0], vec![3.0]]);
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_embeds_are_coalesced() {
+        let server = embedding_server().await;
+        let client = Arc::new(PythonInferenceClient::new(server.uri(), 5));
+        let batcher = EmbeddingBatcher::new(client, EmbeddingBatcherConfig {
+            max_batch_size: 16,
+            max_delay_ms: 50,
+        });
+
+        let results = futures::future::join_all(
+            (1..=5).map(|n| batcher.embed("字".repeat(n)))
+        ).await;
+
+        for (n, result) in (1..=5).zip(results) {
+            assert_eq!(result.unwrap(), vec![n as f32]);
+        }
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    }
+}
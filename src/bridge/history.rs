@@ -0,0 +1,105 @@
+//! 多轮对话历史
+//!
+//! 回复生成时把最近几轮真实对话交给模型，而不是只靠检索到的记忆重建上下文。
+
+use super::prompt::{ChatMessage, ChatRole};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// 默认保留的对话轮数
+const DEFAULT_MAX_TURNS: usize = 20;
+
+/// 一条对话记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatTurn {
+    pub role: ChatRole,
+    pub content: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl ChatTurn {
+    pub fn new(role: ChatRole, content: impl Into<String>) -> Self {
+        Self {
+            role,
+            content: content.into(),
+            timestamp: Utc::now(),
+        }
+    }
+
+    pub fn to_message(&self) -> ChatMessage {
+        ChatMessage::new(self.role, self.content.clone())
+    }
+}
+
+/// 滚动对话历史 - 超过上限时丢弃最早的记录
+#[derive(Debug, Clone)]
+pub struct ChatHistory {
+    turns: VecDeque<ChatTurn>,
+    max_turns: usize,
+}
+
+impl Default for ChatHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_TURNS)
+    }
+}
+
+impl ChatHistory {
+    pub fn new(max_turns: usize) -> Self {
+        Self {
+            turns: VecDeque::new(),
+            max_turns: max_turns.max(1),
+        }
+    }
+
+    pub fn push(&mut self, turn: ChatTurn) {
+        if self.turns.len() == self.max_turns {
+            self.turns.pop_front();
+        }
+        self.turns.push_back(turn);
+    }
+
+    /// 记录一问一答
+    pub fn push_exchange(&mut self, user_input: impl Into<String>, reply: impl Into<String>) {
+        self.push(ChatTurn::new(ChatRole::User, user_input));
+        self.push(ChatTurn::new(ChatRole::Assistant, reply));
+    }
+
+    /// 按时间顺序返回全部记录
+    pub fn turns(&self) -> Vec<ChatTurn> {
+        self.turns.iter().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.turns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.turns.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.turns.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_drops_oldest_turns() {
+        let mut history = ChatHistory::new(3);
+        history.push_exchange("你好", "你好呀~");
+        history.push_exchange("在干嘛", "在想你");
+
+        let turns = history.turns();
+        assert_eq!(history.len(), 3);
+        assert_eq!(turns[0], ChatTurn { timestamp: turns[0].timestamp, ..ChatTurn::new(ChatRole::Assistant, "你好呀~") });
+        assert_eq!(turns[2].to_message(), ChatMessage::new(ChatRole::Assistant, "在想你"));
+
+        history.clear();
+        assert!(history.is_empty());
+    }
+}
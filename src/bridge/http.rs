@@ -0,0 +1,157 @@
+//! 推理客户端共用的HTTP连接配置
+
+use crate::{MemoryError, Result};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// 未在配置中指定密钥时读取的环境变量，与推理服务端一致
+pub const API_KEY_ENV: &str = "MIRA_API_KEY";
+
+/// TLS配置 - 推理服务部署在本机以外时使用
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TlsConfig {
+    /// 额外信任的CA证书(PEM)，用于自签名的服务端证书
+    #[serde(default)]
+    pub ca_cert: Option<PathBuf>,
+    /// 客户端证书(PEM)，服务端要求双向TLS时与私钥一起配置
+    #[serde(default)]
+    pub client_cert: Option<PathBuf>,
+    #[serde(default)]
+    pub client_key: Option<PathBuf>,
+}
+
+/// HTTP连接池配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpClientConfig {
+    /// 每个主机保留的最大空闲连接数
+    pub pool_max_idle_per_host: usize,
+    /// 空闲连接保留时间(秒)
+    pub pool_idle_timeout_secs: u64,
+    /// TCP keepalive探测间隔(秒)，0表示关闭
+    pub tcp_keepalive_secs: u64,
+    /// 建立连接的超时(毫秒)
+    pub connect_timeout_ms: u64,
+    /// 代理地址，如`http://127.0.0.1:7890`；未设置时使用系统代理环境变量
+    #[serde(default)]
+    pub proxy: Option<String>,
+    /// 每个请求附带的Bearer令牌；未设置时读取`MIRA_API_KEY`
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+}
+
+impl Default for HttpClientConfig {
+    fn default() -> Self {
+        Self {
+            pool_max_idle_per_host: 16,
+            pool_idle_timeout_secs: 90,
+            tcp_keepalive_secs: 60,
+            connect_timeout_ms: 3_000,
+            proxy: None,
+            api_key: None,
+            tls: None,
+        }
+    }
+}
+
+impl HttpClientConfig {
+    /// 创建HTTP客户端 - 克隆得到的客户端共享同一个连接池
+    pub fn build(&self) -> Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder()
+            .pool_max_idle_per_host(self.pool_max_idle_per_host)
+            .pool_idle_timeout(Duration::from_secs(self.pool_idle_timeout_secs))
+            .connect_timeout(Duration::from_millis(self.connect_timeout_ms));
+
+        if self.tcp_keepalive_secs > 0 {
+            builder = builder.tcp_keepalive(Duration::from_secs(self.tcp_keepalive_secs));
+        }
+        if let Some(ref proxy) = self.proxy {
+            let proxy = reqwest::Proxy::all(proxy)
+                .map_err(|e| MemoryError::ConfigError(format!("代理地址无效: {}", e)))?;
+            builder = builder.proxy(proxy);
+        }
+
+        if let Some(api_key) = self.resolved_api_key() {
+            let mut value = HeaderValue::from_str(&format!("Bearer {}", api_key))
+                .map_err(|_| MemoryError::ConfigError("API密钥包含非法字符".to_string()))?;
+            value.set_sensitive(true);
+            builder = builder.default_headers(HeaderMap::from_iter([(AUTHORIZATION, value)]));
+        }
+
+        if let Some(ref tls) = self.tls {
+            if let Some(ref ca_cert) = tls.ca_cert {
+                let certificate = reqwest::Certificate::from_pem(&read_pem(ca_cert)?)
+                    .map_err(|e| MemoryError::ConfigError(format!("CA证书无效: {}", e)))?;
+                builder = builder.add_root_certificate(certificate);
+            }
+
+            match (&tls.client_cert, &tls.client_key) {
+                (Some(cert), Some(key)) => {
+                    let mut pem = read_pem(cert)?;
+                    pem.extend(read_pem(key)?);
+                    let identity = reqwest::Identity::from_pem(&pem)
+                        .map_err(|e| MemoryError::ConfigError(format!("客户端证书无效: {}", e)))?;
+                    builder = builder.identity(identity);
+                }
+                (None, None) => {}
+                _ => return Err(MemoryError::ConfigError("客户端证书和私钥需要同时配置".to_string())),
+            }
+        }
+
+        builder.build()
+            .map_err(|e| MemoryError::ConfigError(format!("HTTP客户端创建失败: {}", e)))
+    }
+
+    /// 配置中的密钥优先，其次是环境变量
+    pub fn resolved_api_key(&self) -> Option<String> {
+        self.api_key.clone()
+            .or_else(|| std::env::var(API_KEY_ENV).ok())
+            .filter(|key| !key.is_empty())
+    }
+}
+
+fn read_pem(path: &Path) -> Result<Vec<u8>> {
+    std::fs::read(path)
+        .map_err(|e| MemoryError::ConfigError(format!("读取证书失败 {}: {}", path.display(), e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_validates_proxy() {
+        assert!(HttpClientConfig::default().build().is_ok());
+
+        let config = HttpClientConfig {
+            proxy: Some("not a url".to_string()),
+            ..HttpClientConfig::default()
+        };
+        assert!(matches!(config.build(), Err(MemoryError::ConfigError(_))));
+    }
+
+    #[test]
+    fn test_api_key_from_config_or_env() {
+        temp_env::with_var(API_KEY_ENV, Some("from-env"), || {
+            assert_eq!(HttpClientConfig::default().resolved_api_key().as_deref(), Some("from-env"));
+
+            let config = HttpClientConfig {
+                api_key: Some("from-config".to_string()),
+                ..HttpClientConfig::default()
+            };
+            assert_eq!(config.resolved_api_key().as_deref(), Some("from-config"));
+        });
+
+        let half_identity = HttpClientConfig {
+            tls: Some(TlsConfig {
+                client_cert: Some(PathBuf::from("client.pem")),
+                ..TlsConfig::default()
+            }),
+            ..HttpClientConfig::default()
+        };
+        assert!(matches!(half_identity.build(), Err(MemoryError::ConfigError(_))));
+    }
+}
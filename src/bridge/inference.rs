@@ -0,0 +1,519 @@
+//! 推理客户端抽象
+//!
+//! `InferenceClient`统一嵌入、回复生成、情感分析、关键词提取和重要性评估接口，
+//! `PythonInferenceClient`调用Python推理服务，`MockInferenceClient`提供不依赖外部服务的确定性实现。
+
+use super::history::ChatTurn;
+use crate::{EmotionalState, MemoryEntry, MemoryError, Result};
+use async_trait::async_trait;
+use futures::stream::{self, BoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// 流式回复 - 按生成顺序产出的文本片段
+pub type TokenStream = BoxStream<'static, Result<String>>;
+
+/// 本地关键词提取的数量上限
+const LOCAL_KEYWORD_LIMIT: usize = 10;
+
+/// 批量关键词提取默认同时进行的请求数
+const KEYWORD_BATCH_CONCURRENCY: usize = 8;
+
+/// 推理客户端特征
+#[async_trait]
+pub trait InferenceClient: std::fmt::Debug + Send + Sync {
+    /// 生成文本嵌入向量
+    async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>>;
+
+    /// 批量生成嵌入向量 - 结果与输入一一对应
+    async fn generate_embeddings(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for text in &texts {
+            embeddings.push(self.generate_embedding(text).await?);
+        }
+        Ok(embeddings)
+    }
+
+    /// 生成图片嵌入向量（如CLIP），与文本嵌入不在同一向量空间 - 默认不支持
+    async fn generate_image_embedding(&self, image: &[u8]) -> Result<Vec<f32>> {
+        let _ = image;
+        Err(MemoryError::InferenceUnavailable("该推理后端不支持图片嵌入".to_string()))
+    }
+
+    /// 生成情感化回复
+    async fn generate_response(
+        &self,
+        user_input: &str,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<String>;
+
+    /// 流式生成情感化回复 - 默认实现在完整回复生成后一次性产出
+    async fn generate_response_stream(
+        &self,
+        user_input: &str,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<TokenStream> {
+        let response = self.generate_response(user_input, context, emotional_state).await?;
+        Ok(stream::once(async move { Ok(response) }).boxed())
+    }
+
+    /// 带多轮对话历史生成回复 - 默认忽略历史
+    async fn generate_response_with_history(
+        &self,
+        user_input: &str,
+        history: Vec<ChatTurn>,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<String> {
+        let _ = history;
+        self.generate_response(user_input, context, emotional_state).await
+    }
+
+    /// 带多轮对话历史流式生成回复 - 默认忽略历史
+    async fn generate_response_stream_with_history(
+        &self,
+        user_input: &str,
+        history: Vec<ChatTurn>,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<TokenStream> {
+        let _ = history;
+        self.generate_response_stream(user_input, context, emotional_state).await
+    }
+
+    /// 分析用户情感
+    async fn analyze_emotion(&self, text: &str) -> Result<EmotionalState>;
+
+    /// 提取关键词
+    async fn extract_keywords(&self, text: &str) -> Result<Vec<String>>;
+
+    /// 批量提取关键词 - 结果与输入一一对应，默认并发调用`extract_keywords`
+    async fn extract_keywords_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<String>>> {
+        stream::iter(texts)
+            .map(|text| async move { self.extract_keywords(&text).await })
+            .buffered(KEYWORD_BATCH_CONCURRENCY)
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect()
+    }
+
+    /// 评估记忆重要性，返回0.0-1.0
+    async fn calculate_importance(&self, text: &str, emotional_state: Option<EmotionalState>) -> Result<f32>;
+
+    /// 交叉编码器重排 - 返回每个文档与查询的相关性（0.0-1.0），与`documents`一一对应；默认不支持
+    async fn rerank(&self, query: &str, documents: Vec<String>) -> Result<Vec<f32>> {
+        let _ = (query, documents);
+        Err(MemoryError::InferenceUnavailable("该推理后端不支持重排".to_string()))
+    }
+
+    /// 推理服务是否可用
+    async fn health_check(&self) -> bool;
+}
+
+/// 共享的客户端直接转发，`Arc<dyn InferenceClient>`也可以交给限流、缓存等包装器
+#[async_trait]
+impl<T: InferenceClient + ?Sized> InferenceClient for Arc<T> {
+    async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>> {
+        (**self).generate_embedding(text).await
+    }
+
+    async fn generate_embeddings(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        (**self).generate_embeddings(texts).await
+    }
+
+    async fn generate_image_embedding(&self, image: &[u8]) -> Result<Vec<f32>> {
+        (**self).generate_image_embedding(image).await
+    }
+
+    async fn generate_response(
+        &self,
+        user_input: &str,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<String> {
+        (**self).generate_response(user_input, context, emotional_state).await
+    }
+
+    async fn generate_response_stream(
+        &self,
+        user_input: &str,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<TokenStream> {
+        (**self).generate_response_stream(user_input, context, emotional_state).await
+    }
+
+    async fn generate_response_with_history(
+        &self,
+        user_input: &str,
+        history: Vec<ChatTurn>,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<String> {
+        (**self).generate_response_with_history(user_input, history, context, emotional_state).await
+    }
+
+    async fn generate_response_stream_with_history(
+        &self,
+        user_input: &str,
+        history: Vec<ChatTurn>,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<TokenStream> {
+        (**self).generate_response_stream_with_history(user_input, history, context, emotional_state).await
+    }
+
+    async fn analyze_emotion(&self, text: &str) -> Result<EmotionalState> {
+        (**self).analyze_emotion(text).await
+    }
+
+    async fn extract_keywords(&self, text: &str) -> Result<Vec<String>> {
+        (**self).extract_keywords(text).await
+    }
+
+    async fn extract_keywords_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<String>>> {
+        (**self).extract_keywords_batch(texts).await
+    }
+
+    async fn calculate_importance(&self, text: &str, emotional_state: Option<EmotionalState>) -> Result<f32> {
+        (**self).calculate_importance(text, emotional_state).await
+    }
+
+    async fn rerank(&self, query: &str, documents: Vec<String>) -> Result<Vec<f32>> {
+        (**self).rerank(query, documents).await
+    }
+
+    async fn health_check(&self) -> bool {
+        (**self).health_check().await
+    }
+}
+
+/// 推理后端选择
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+pub enum InferenceBackendConfig {
+    /// Python推理服务
+    Python {
+        url: String,
+        /// 默认超时，`tasks`中未单独配置的任务使用
+        timeout_seconds: u64,
+        #[serde(default)]
+        http: super::http::HttpClientConfig,
+        /// 按任务类型指定模型和超时
+        #[serde(default)]
+        tasks: HashMap<super::python_bridge::InferenceTaskType, super::python_bridge::TaskConfig>,
+        /// 暂时性错误的重试策略
+        #[serde(default)]
+        retry: crate::vector_store::RetryPolicy,
+        /// 连续失败后熔断，熔断期间快速失败
+        #[serde(default)]
+        circuit_breaker: super::circuit_breaker::CircuitBreakerConfig,
+    },
+    /// 确定性的本地Mock
+    Mock,
+    /// OpenAI兼容接口
+    #[serde(rename = "openai")]
+    OpenAi(super::openai::OpenAiConfig),
+    /// 本地Ollama服务
+    Ollama(super::ollama::OllamaConfig),
+    /// llama.cpp本地模型
+    #[cfg(feature = "llama-cpp")]
+    LlamaCpp(super::llama_cpp::LlamaCppConfig),
+}
+
+impl Default for InferenceBackendConfig {
+    fn default() -> Self {
+        Self::Python {
+            url: "http://localhost:8000".to_string(),
+            timeout_seconds: 30,
+            http: super::http::HttpClientConfig::default(),
+            tasks: HashMap::new(),
+            retry: crate::vector_store::RetryPolicy::default(),
+            circuit_breaker: super::circuit_breaker::CircuitBreakerConfig::default(),
+        }
+    }
+}
+
+impl InferenceBackendConfig {
+    /// 按配置创建推理客户端
+    pub fn build(&self) -> Result<Arc<dyn InferenceClient>> {
+        Ok(match self {
+            Self::Python { url, timeout_seconds, http, tasks, retry, circuit_breaker } => Arc::new(
+                new_python_client(url, *timeout_seconds, http, tasks, retry, circuit_breaker)?
+            ),
+            Self::Mock => Arc::new(MockInferenceClient::new()),
+            Self::OpenAi(config) => Arc::new(super::openai::OpenAiInferenceClient::new(config.clone())),
+            Self::Ollama(config) => Arc::new(super::ollama::OllamaClient::new(config.clone())),
+            #[cfg(feature = "llama-cpp")]
+            Self::LlamaCpp(config) => Arc::new(super::llama_cpp::LlamaCppClient::load(config.clone())?),
+        })
+    }
+
+    /// python后端的客户端，用于能力握手等python服务特有的接口；其他后端返回None
+    pub fn python_client(&self) -> Result<Option<super::python_bridge::PythonInferenceClient>> {
+        let Self::Python { url, timeout_seconds, http, tasks, retry, circuit_breaker } = self else {
+            return Ok(None);
+        };
+        new_python_client(url, *timeout_seconds, http, tasks, retry, circuit_breaker).map(Some)
+    }
+}
+
+fn new_python_client(
+    url: &str,
+    timeout_seconds: u64,
+    http: &super::http::HttpClientConfig,
+    tasks: &HashMap<super::python_bridge::InferenceTaskType, super::python_bridge::TaskConfig>,
+    retry: &crate::vector_store::RetryPolicy,
+    circuit_breaker: &super::circuit_breaker::CircuitBreakerConfig,
+) -> Result<super::python_bridge::PythonInferenceClient> {
+    super::python_bridge::PythonInferenceClient::new(url.to_string(), timeout_seconds)
+        .with_task_configs(tasks)
+        .with_retry(retry.clone())
+        .with_circuit_breaker(circuit_breaker.clone())
+        .with_http_config(http)
+}
+
+/// 本地关键词提取 - 按空白和标点切分，去重后保留两个字符以上的词
+pub fn local_keywords(text: &str) -> Vec<String> {
+    let mut keywords: Vec<String> = Vec::new();
+    for word in text.split(|c: char| c.is_whitespace() || c.is_ascii_punctuation() || "，。！？、；：".contains(c)) {
+        if word.chars().count() >= 2 && !keywords.iter().any(|k| k == word) {
+            keywords.push(word.to_string());
+        }
+    }
+    keywords.truncate(LOCAL_KEYWORD_LIMIT);
+    keywords
+}
+
+/// 正面情感词
+const POSITIVE_WORDS: [&str; 6] = ["开心", "高兴", "喜欢", "爱", "幸福", "谢谢"];
+
+/// 负面情感词
+const NEGATIVE_WORDS: [&str; 6] = ["难过", "伤心", "生气", "讨厌", "累", "孤单"];
+
+/// 确定性的推理客户端 - 相同输入总是得到相同输出，用于测试和离线演示
+#[derive(Debug, Clone)]
+pub struct MockInferenceClient {
+    embedding_dim: usize,
+    available: bool,
+}
+
+impl MockInferenceClient {
+    /// 创建768维嵌入的Mock客户端
+    pub fn new() -> Self {
+        Self {
+            embedding_dim: 768,
+            available: true,
+        }
+    }
+
+    /// 设置嵌入维度
+    pub fn with_embedding_dim(mut self, embedding_dim: usize) -> Self {
+        self.embedding_dim = embedding_dim.max(1);
+        self
+    }
+
+    /// 模拟服务不可用 - 所有调用返回`InferenceUnavailable`
+    pub fn unavailable(mut self) -> Self {
+        self.available = false;
+        self
+    }
+
+    fn ensure_available(&self) -> Result<()> {
+        if self.available {
+            Ok(())
+        } else {
+            Err(MemoryError::InferenceUnavailable("Mock推理服务已停用".to_string()))
+        }
+    }
+
+    /// 情感词计数：(正面, 负面)
+    fn sentiment_counts(text: &str) -> (usize, usize) {
+        let positive = POSITIVE_WORDS.iter().filter(|w| text.contains(*w)).count();
+        let negative = NEGATIVE_WORDS.iter().filter(|w| text.contains(*w)).count();
+        (positive, negative)
+    }
+}
+
+impl Default for MockInferenceClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl InferenceClient for MockInferenceClient {
+    /// 字符哈希到维度上累加后归一化 - 共享字符越多的文本余弦相似度越高
+    async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>> {
+        self.ensure_available()?;
+
+        let mut embedding = vec![0.0f32; self.embedding_dim];
+        for ch in text.chars() {
+            let mut hash: u64 = 0xcbf29ce484222325;
+            for byte in (ch as u32).to_le_bytes() {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+            embedding[(hash % self.embedding_dim as u64) as usize] += 1.0;
+        }
+
+        let norm = embedding.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            embedding.iter_mut().for_each(|v| *v /= norm);
+        }
+        Ok(embedding)
+    }
+
+    /// 字节值累加到维度上后归一化 - 内容相同的图片得到相同的向量
+    async fn generate_image_embedding(&self, image: &[u8]) -> Result<Vec<f32>> {
+        self.ensure_available()?;
+
+        let mut embedding = vec![0.0f32; self.embedding_dim];
+        for &byte in image {
+            embedding[byte as usize % self.embedding_dim] += 1.0;
+        }
+
+        let norm = embedding.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            embedding.iter_mut().for_each(|v| *v /= norm);
+        }
+        Ok(embedding)
+    }
+
+    async fn generate_response(
+        &self,
+        user_input: &str,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<String> {
+        self.ensure_available()?;
+        Ok(format!("[{}] 我记得{}件事，你说: {}", emotional_state.mood, context.len(), user_input))
+    }
+
+    async fn analyze_emotion(&self, text: &str) -> Result<EmotionalState> {
+        self.ensure_available()?;
+
+        let (positive, negative) = Self::sentiment_counts(text);
+        let shift = (positive as f32 - negative as f32) * 0.2;
+        let mut emotion = EmotionalState::default();
+        emotion.happiness = (emotion.happiness + shift).clamp(0.0, 1.0);
+        emotion.mood = match positive.cmp(&negative) {
+            std::cmp::Ordering::Greater => "开心",
+            std::cmp::Ordering::Less => "低落",
+            std::cmp::Ordering::Equal => "平静",
+        }.to_string();
+        Ok(emotion)
+    }
+
+    async fn extract_keywords(&self, text: &str) -> Result<Vec<String>> {
+        self.ensure_available()?;
+        Ok(local_keywords(text))
+    }
+
+    async fn calculate_importance(&self, text: &str, emotional_state: Option<EmotionalState>) -> Result<f32> {
+        self.ensure_available()?;
+
+        let (positive, negative) = Self::sentiment_counts(text);
+        let intensity = ((positive + negative) as f32 * 0.2).min(0.4);
+        let length = (text.chars().count() as f32 / 100.0).min(1.0) * 0.2;
+        let affection = emotional_state.map_or(0.0, |e| e.affection * 0.2);
+        Ok((0.2 + intensity + length + affection).clamp(0.0, 1.0))
+    }
+
+    /// 查询中的字符在文档中出现的比例
+    async fn rerank(&self, query: &str, documents: Vec<String>) -> Result<Vec<f32>> {
+        self.ensure_available()?;
+
+        let query: std::collections::HashSet<char> = query.chars().filter(|c| !c.is_whitespace()).collect();
+        Ok(documents.iter()
+            .map(|document| {
+                if query.is_empty() {
+                    return 0.0;
+                }
+                query.iter().filter(|c| document.contains(**c)).count() as f32 / query.len() as f32
+            })
+            .collect())
+    }
+
+    async fn health_check(&self) -> bool {
+        self.available
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::DistanceMetric;
+    use crate::bridge::InferenceTaskType;
+
+    #[tokio::test]
+    async fn test_mock_client_is_deterministic() {
+        let client = MockInferenceClient::new().with_embedding_dim(64);
+
+        let a = client.generate_embedding("我喜欢猫咪").await.unwrap();
+        assert_eq!(a, client.generate_embedding("我喜欢猫咪").await.unwrap());
+        assert_eq!(a.len(), 64);
+
+        let similar = client.generate_embedding("我喜欢小猫咪").await.unwrap();
+        let unrelated = client.generate_embedding("明天要开会").await.unwrap();
+        assert!(DistanceMetric::Cosine.score(&a, &similar) > DistanceMetric::Cosine.score(&a, &unrelated));
+
+        assert_eq!(client.analyze_emotion("今天好开心").await.unwrap().mood, "开心");
+        assert_eq!(client.analyze_emotion("有点难过").await.unwrap().mood, "低落");
+        assert_eq!(client.extract_keywords("周末 去 看海").await.unwrap(), vec!["周末", "看海"]);
+        assert_eq!(
+            client.calculate_importance("好开心", None).await.unwrap(),
+            client.calculate_importance("好开心", None).await.unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_mock_and_default_stream() {
+        let client = MockInferenceClient::new();
+        let tokens: Vec<String> = client.generate_response_stream("你好", vec![], EmotionalState::default())
+            .await
+            .unwrap()
+            .map(|token| token.unwrap())
+            .collect()
+            .await;
+        assert_eq!(tokens, vec!["[平静] 我记得0件事，你说: 你好"]);
+
+        let offline = MockInferenceClient::new().unavailable();
+        assert!(!offline.health_check().await);
+        assert!(matches!(offline.extract_keywords("你好").await, Err(MemoryError::InferenceUnavailable(_))));
+        assert!(offline.extract_keywords_batch(vec!["你好".to_string()]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_keyword_batch_keeps_order() {
+        let client = MockInferenceClient::new();
+        let texts: Vec<String> = (0..20).map(|i| format!("周末{} 看海", i)).collect();
+        let batch = client.extract_keywords_batch(texts.clone()).await.unwrap();
+        for (text, keywords) in texts.iter().zip(batch) {
+            assert_eq!(keywords, client.extract_keywords(text).await.unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_backend_config_selects_client() {
+        let config: InferenceBackendConfig = serde_json::from_value(serde_json::json!({
+            "backend": "python", "url": "http://127.0.0.1:9", "timeout_seconds": 1,
+            "tasks": { "ExtractKeywords": { "model": "qwen3-0.6b" } }
+        })).unwrap();
+        assert!(matches!(config, InferenceBackendConfig::Python { timeout_seconds: 1, ref tasks, .. }
+            if tasks[&InferenceTaskType::ExtractKeywords].timeout_seconds.is_none()));
+
+        let openai: InferenceBackendConfig = serde_json::from_value(serde_json::json!({
+            "backend": "openai", "base_url": "http://127.0.0.1:1234/v1", "chat_model": "qwen2.5",
+            "embedding_model": "bge-m3", "timeout_seconds": 30, "temperature": 0.7, "top_p": 0.9, "max_tokens": 256
+        })).unwrap();
+        assert!(matches!(openai, InferenceBackendConfig::OpenAi(ref config) if config.api_key.is_none()));
+
+        let mock: InferenceBackendConfig = serde_json::from_str(r#"{"backend":"mock"}"#).unwrap();
+        assert!(mock.build().unwrap().health_check().await);
+    }
+}
@@ -0,0 +1,311 @@
+//! llama.cpp本地大模型推理
+//!
+//! 通过llama-cpp-2在进程内加载GGUF模型生成回复，桌面端无需联网。
+//! 嵌入、情感、关键词和重要性交给后备客户端。需要启用`llama-cpp`特性。
+
+use super::history::ChatTurn;
+use super::inference::{InferenceClient, MockInferenceClient, TokenStream};
+use super::prompt::{chat_messages_with_history, ChatMessage, ChatRole};
+use crate::{EmotionalState, MemoryEntry, MemoryError, Result};
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use llama_cpp_2::context::params::LlamaContextParams;
+use llama_cpp_2::llama_backend::LlamaBackend;
+use llama_cpp_2::llama_batch::LlamaBatch;
+use llama_cpp_2::model::params::LlamaModelParams;
+use llama_cpp_2::model::{AddBos, LlamaChatMessage, LlamaModel, Special};
+use llama_cpp_2::sampling::LlamaSampler;
+use serde::{Deserialize, Serialize};
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock};
+use tokio::sync::mpsc;
+
+/// llama.cpp后端 - 每个进程只能初始化一次
+static BACKEND: LazyLock<std::result::Result<LlamaBackend, String>> =
+    LazyLock::new(|| LlamaBackend::init().map_err(|e| e.to_string()));
+
+/// 流式输出的通道容量
+const TOKEN_CHANNEL_CAPACITY: usize = 64;
+
+/// llama.cpp推理配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LlamaCppConfig {
+    /// GGUF模型文件路径
+    pub model_path: PathBuf,
+    /// 上下文窗口大小(token)
+    pub context_size: u32,
+    /// 单次回复最多生成的token数
+    pub max_tokens: usize,
+    pub temperature: f32,
+    pub top_p: f32,
+    /// 卸载到GPU的层数，0表示纯CPU
+    pub gpu_layers: u32,
+    pub seed: u32,
+}
+
+impl Default for LlamaCppConfig {
+    fn default() -> Self {
+        Self {
+            model_path: PathBuf::from("models/qwen2.5-1.5b-instruct-q4_k_m.gguf"),
+            context_size: 2048,
+            max_tokens: 256,
+            temperature: 0.7,
+            top_p: 0.9,
+            gpu_layers: 0,
+            seed: 42,
+        }
+    }
+}
+
+/// llama.cpp推理客户端
+pub struct LlamaCppClient {
+    model: Arc<LlamaModel>,
+    config: LlamaCppConfig,
+    fallback: Arc<dyn InferenceClient>,
+}
+
+impl std::fmt::Debug for LlamaCppClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LlamaCppClient")
+            .field("config", &self.config)
+            .field("fallback", &self.fallback)
+            .finish()
+    }
+}
+
+impl LlamaCppClient {
+    /// 加载模型；后备客户端默认为本地的`MockInferenceClient`
+    pub fn load(config: LlamaCppConfig) -> Result<Self> {
+        let params = LlamaModelParams::default().with_n_gpu_layers(config.gpu_layers);
+        let model = LlamaModel::load_from_file(backend()?, &config.model_path, &params)
+            .map_err(|e| MemoryError::InferenceError(format!("llama.cpp模型加载失败: {}", e)))?;
+
+        tracing::info!("llama.cpp模型已加载: {}", config.model_path.display());
+
+        Ok(Self {
+            model: Arc::new(model),
+            config,
+            fallback: Arc::new(MockInferenceClient::new()),
+        })
+    }
+
+    /// 设置处理嵌入、情感、关键词和重要性的后备客户端
+    pub fn with_fallback(mut self, fallback: Arc<dyn InferenceClient>) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    /// 套用模型自带的对话模板，模型没有模板时使用ChatML
+    fn render_prompt(&self, messages: &[ChatMessage]) -> Result<String> {
+        let chat: std::result::Result<Vec<_>, _> = messages.iter()
+            .map(|m| LlamaChatMessage::new(role_name(m.role).to_string(), m.content.clone()))
+            .collect();
+
+        let templated = chat.ok().and_then(|chat| {
+            let template = self.model.chat_template(None).ok()?;
+            self.model.apply_chat_template(&template, &chat, true).ok()
+        });
+
+        Ok(templated.unwrap_or_else(|| {
+            let mut prompt = String::new();
+            for message in messages {
+                prompt.push_str(&format!("<|im_start|>{}\n{}<|im_end|>\n", role_name(message.role), message.content));
+            }
+            prompt.push_str("<|im_start|>assistant\n");
+            prompt
+        }))
+    }
+}
+
+fn backend() -> Result<&'static LlamaBackend> {
+    BACKEND.as_ref()
+        .map_err(|e| MemoryError::InferenceUnavailable(format!("llama.cpp后端初始化失败: {}", e)))
+}
+
+fn role_name(role: ChatRole) -> &'static str {
+    match role {
+        ChatRole::System => "system",
+        ChatRole::User => "user",
+        ChatRole::Assistant => "assistant",
+    }
+}
+
+/// 在阻塞线程中逐token生成，通过通道发送文本片段；接收端关闭时提前停止
+fn generate(
+    model: &LlamaModel,
+    config: &LlamaCppConfig,
+    prompt: &str,
+    sender: &mpsc::Sender<Result<String>>,
+) -> std::result::Result<(), String> {
+    let context_params = LlamaContextParams::default()
+        .with_n_ctx(NonZeroU32::new(config.context_size))
+        .with_n_batch(config.context_size);
+    let mut context = model.new_context(backend().map_err(|e| e.to_string())?, context_params)
+        .map_err(|e| e.to_string())?;
+
+    // 提示过长时保留末尾，为回复留出空间
+    let mut tokens = model.str_to_token(prompt, AddBos::Always).map_err(|e| e.to_string())?;
+    let prompt_budget = (config.context_size as usize).saturating_sub(config.max_tokens).max(1);
+    if tokens.len() > prompt_budget {
+        tokens.drain(..tokens.len() - prompt_budget);
+    }
+
+    let mut batch = LlamaBatch::new(tokens.len().max(1), 1);
+    let last = tokens.len() as i32 - 1;
+    for (position, token) in (0_i32..).zip(tokens) {
+        batch.add(token, position, &[0], position == last).map_err(|e| e.to_string())?;
+    }
+    context.decode(&mut batch).map_err(|e| e.to_string())?;
+
+    let mut sampler = LlamaSampler::chain_simple([
+        LlamaSampler::top_p(config.top_p, 1),
+        LlamaSampler::temp(config.temperature),
+        LlamaSampler::dist(config.seed),
+    ]);
+
+    let mut position = batch.n_tokens();
+    let mut pending = Vec::new();
+    for _ in 0..config.max_tokens {
+        let token = sampler.sample(&context, batch.n_tokens() - 1);
+        sampler.accept(token);
+        if model.is_eog_token(token) {
+            break;
+        }
+
+        pending.extend(model.token_to_bytes(token, Special::Tokenize).map_err(|e| e.to_string())?);
+        let text = drain_utf8(&mut pending);
+        if !text.is_empty() && sender.blocking_send(Ok(text)).is_err() {
+            return Ok(());
+        }
+
+        batch.clear();
+        batch.add(token, position, &[0], true).map_err(|e| e.to_string())?;
+        position += 1;
+        context.decode(&mut batch).map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+/// 取出已完整的UTF-8文本，多字节字符被拆到两个token时留待下次
+fn drain_utf8(pending: &mut Vec<u8>) -> String {
+    match std::str::from_utf8(pending) {
+        Ok(text) => {
+            let text = text.to_string();
+            pending.clear();
+            text
+        }
+        Err(e) if e.error_len().is_none() => {
+            let complete: Vec<u8> = pending.drain(..e.valid_up_to()).collect();
+            String::from_utf8(complete).unwrap_or_default()
+        }
+        Err(_) => {
+            let text = String::from_utf8_lossy(pending).into_owned();
+            pending.clear();
+            text
+        }
+    }
+}
+
+#[async_trait]
+impl InferenceClient for LlamaCppClient {
+    async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>> {
+        self.fallback.generate_embedding(text).await
+    }
+
+    async fn generate_embeddings(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        self.fallback.generate_embeddings(texts).await
+    }
+
+    async fn generate_response(
+        &self,
+        user_input: &str,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<String> {
+        self.generate_response_with_history(user_input, Vec::new(), context, emotional_state).await
+    }
+
+    async fn generate_response_with_history(
+        &self,
+        user_input: &str,
+        history: Vec<ChatTurn>,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<String> {
+        let mut tokens = self.generate_response_stream_with_history(user_input, history, context, emotional_state).await?;
+        let mut response = String::new();
+        while let Some(token) = tokens.next().await {
+            response.push_str(&token?);
+        }
+        Ok(response.trim().to_string())
+    }
+
+    async fn generate_response_stream(
+        &self,
+        user_input: &str,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<TokenStream> {
+        self.generate_response_stream_with_history(user_input, Vec::new(), context, emotional_state).await
+    }
+
+    async fn generate_response_stream_with_history(
+        &self,
+        user_input: &str,
+        history: Vec<ChatTurn>,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<TokenStream> {
+        let prompt = self.render_prompt(&chat_messages_with_history(user_input, &history, &context, &emotional_state))?;
+        let model = self.model.clone();
+        let config = self.config.clone();
+        let (sender, receiver) = mpsc::channel(TOKEN_CHANNEL_CAPACITY);
+
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = generate(&model, &config, &prompt, &sender) {
+                let _ = sender.blocking_send(Err(MemoryError::InferenceError(format!("llama.cpp生成失败: {}", e))));
+            }
+        });
+
+        Ok(stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|token| (token, receiver))
+        }).boxed())
+    }
+
+    async fn analyze_emotion(&self, text: &str) -> Result<EmotionalState> {
+        self.fallback.analyze_emotion(text).await
+    }
+
+    async fn extract_keywords(&self, text: &str) -> Result<Vec<String>> {
+        self.fallback.extract_keywords(text).await
+    }
+
+    async fn calculate_importance(&self, text: &str, emotional_state: Option<EmotionalState>) -> Result<f32> {
+        self.fallback.calculate_importance(text, emotional_state).await
+    }
+
+    /// 模型在进程内，加载成功即可用
+    async fn health_check(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drain_utf8_keeps_split_characters() {
+        let bytes = "你好".as_bytes();
+        let mut pending = bytes[..4].to_vec();
+
+        assert_eq!(drain_utf8(&mut pending), "你");
+        assert_eq!(pending, bytes[3..4]);
+
+        pending.extend_from_slice(&bytes[4..]);
+        assert_eq!(drain_utf8(&mut pending), "好");
+        assert!(pending.is_empty());
+    }
+}
@@ -0,0 +1,283 @@
+//! 本地嵌入模型
+//!
+//! 使用candle在进程内运行BERT类sentence-transformer（如bge-small），
+//! 嵌入生成不再依赖Python推理服务。需要启用`local-embedding`特性。
+
+use super::history::ChatTurn;
+use super::inference::{InferenceClient, TokenStream};
+use crate::{EmotionalState, MemoryEntry, MemoryError, Result};
+use async_trait::async_trait;
+use candle_core::{Device, IndexOp, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config, DTYPE};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokenizers::{PaddingParams, Tokenizer, TruncationParams};
+
+/// 句向量池化方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Pooling {
+    /// 取[CLS]位置的隐藏状态 - bge系列使用
+    Cls,
+    /// 按注意力掩码对所有token取平均 - 多数sentence-transformer使用
+    Mean,
+}
+
+/// 本地嵌入模型配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalEmbedderConfig {
+    /// 模型目录，需包含config.json、tokenizer.json和model.safetensors
+    pub model_dir: PathBuf,
+    pub pooling: Pooling,
+    /// 单条文本的最大token数，超出部分截断
+    pub max_length: usize,
+}
+
+impl Default for LocalEmbedderConfig {
+    fn default() -> Self {
+        Self {
+            model_dir: PathBuf::from("models/bge-small-zh-v1.5"),
+            pooling: Pooling::Cls,
+            max_length: 512,
+        }
+    }
+}
+
+/// 已加载的模型
+struct LoadedModel {
+    model: BertModel,
+    tokenizer: Tokenizer,
+    pooling: Pooling,
+    device: Device,
+}
+
+/// 进程内嵌入模型 - 克隆共享同一份权重
+#[derive(Clone)]
+pub struct LocalEmbedder {
+    model: Arc<LoadedModel>,
+    dimension: usize,
+}
+
+impl std::fmt::Debug for LocalEmbedder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LocalEmbedder")
+            .field("dimension", &self.dimension)
+            .field("pooling", &self.model.pooling)
+            .finish()
+    }
+}
+
+impl LocalEmbedder {
+    /// 从模型目录加载权重和分词器，在CPU上运行
+    pub fn load(config: LocalEmbedderConfig) -> Result<Self> {
+        let device = Device::Cpu;
+        let model_error = |e: &dyn std::fmt::Display| {
+            MemoryError::InferenceError(format!("本地嵌入模型加载失败: {}", e))
+        };
+
+        let bert_config: Config = serde_json::from_str(
+            &std::fs::read_to_string(config.model_dir.join("config.json")).map_err(|e| model_error(&e))?
+        )?;
+
+        let mut tokenizer = Tokenizer::from_file(config.model_dir.join("tokenizer.json"))
+            .map_err(|e| model_error(&e))?;
+        tokenizer.with_padding(Some(PaddingParams::default()));
+        tokenizer.with_truncation(Some(TruncationParams {
+            max_length: config.max_length,
+            ..Default::default()
+        })).map_err(|e| model_error(&e))?;
+
+        // 安全性: 权重文件在模型生命周期内不会被修改
+        let vb = unsafe {
+            VarBuilder::from_mmaped_safetensors(&[config.model_dir.join("model.safetensors")], DTYPE, &device)
+        }.map_err(|e| model_error(&e))?;
+        let model = BertModel::load(vb, &bert_config).map_err(|e| model_error(&e))?;
+
+        tracing::info!("本地嵌入模型已加载: {} ({}维)", config.model_dir.display(), bert_config.hidden_size);
+
+        Ok(Self {
+            model: Arc::new(LoadedModel {
+                model,
+                tokenizer,
+                pooling: config.pooling,
+                device,
+            }),
+            dimension: bert_config.hidden_size,
+        })
+    }
+
+    /// 嵌入向量维度
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    /// 生成单条文本的嵌入
+    pub async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        self.embed_batch(vec![text.to_string()]).await?
+            .pop()
+            .ok_or_else(|| MemoryError::InferenceError("本地嵌入模型未返回结果".to_string()))
+    }
+
+    /// 批量生成嵌入 - 在阻塞线程池中推理，不占用异步工作线程
+    pub async fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let model = self.model.clone();
+        tokio::task::spawn_blocking(move || model.embed(texts))
+            .await
+            .map_err(|e| MemoryError::InferenceError(format!("本地嵌入任务异常退出: {}", e)))?
+    }
+}
+
+impl LoadedModel {
+    fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let inference_error = |e: &dyn std::fmt::Display| {
+            MemoryError::InferenceError(format!("本地嵌入推理失败: {}", e))
+        };
+
+        let encodings = self.tokenizer.encode_batch(texts, true).map_err(|e| inference_error(&e))?;
+
+        let token_ids = encodings.iter()
+            .map(|encoding| Tensor::new(encoding.get_ids(), &self.device))
+            .collect::<candle_core::Result<Vec<_>>>()
+            .and_then(|ids| Tensor::stack(&ids, 0))
+            .map_err(|e| inference_error(&e))?;
+        let attention_mask = encodings.iter()
+            .map(|encoding| Tensor::new(encoding.get_attention_mask(), &self.device))
+            .collect::<candle_core::Result<Vec<_>>>()
+            .and_then(|masks| Tensor::stack(&masks, 0))
+            .map_err(|e| inference_error(&e))?;
+
+        token_ids.zeros_like()
+            .and_then(|token_type_ids| self.model.forward(&token_ids, &token_type_ids, Some(&attention_mask)))
+            .and_then(|hidden| pool(&hidden, &attention_mask, self.pooling))
+            .and_then(|pooled| pooled.to_vec2::<f32>())
+            .map_err(|e| inference_error(&e))
+    }
+}
+
+/// 将(batch, seq, hidden)的隐藏状态池化为L2归一化的(batch, hidden)句向量
+fn pool(hidden: &Tensor, attention_mask: &Tensor, pooling: Pooling) -> candle_core::Result<Tensor> {
+    let pooled = match pooling {
+        Pooling::Cls => hidden.i((.., 0))?,
+        Pooling::Mean => {
+            let mask = attention_mask.to_dtype(hidden.dtype())?.unsqueeze(2)?;
+            let summed = hidden.broadcast_mul(&mask)?.sum(1)?;
+            summed.broadcast_div(&mask.sum(1)?)?
+        }
+    };
+
+    let norm = pooled.sqr()?.sum_keepdim(1)?.sqrt()?.affine(1.0, 1e-12)?;
+    pooled.broadcast_div(&norm)
+}
+
+/// 嵌入走本地模型、其余任务交给内部客户端的推理客户端
+#[derive(Debug)]
+pub struct LocalEmbeddingClient<C> {
+    embedder: LocalEmbedder,
+    inner: C,
+}
+
+impl<C: InferenceClient> LocalEmbeddingClient<C> {
+    pub fn new(embedder: LocalEmbedder, inner: C) -> Self {
+        Self { embedder, inner }
+    }
+
+    /// 获取本地嵌入模型
+    pub fn embedder(&self) -> &LocalEmbedder {
+        &self.embedder
+    }
+}
+
+#[async_trait]
+impl<C: InferenceClient> InferenceClient for LocalEmbeddingClient<C> {
+    async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>> {
+        self.embedder.embed(text).await
+    }
+
+    async fn generate_embeddings(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        self.embedder.embed_batch(texts).await
+    }
+
+    async fn generate_response(
+        &self,
+        user_input: &str,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<String> {
+        self.inner.generate_response(user_input, context, emotional_state).await
+    }
+
+    async fn generate_response_stream(
+        &self,
+        user_input: &str,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<TokenStream> {
+        self.inner.generate_response_stream(user_input, context, emotional_state).await
+    }
+
+    async fn generate_response_with_history(
+        &self,
+        user_input: &str,
+        history: Vec<ChatTurn>,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<String> {
+        self.inner.generate_response_with_history(user_input, history, context, emotional_state).await
+    }
+
+    async fn generate_response_stream_with_history(
+        &self,
+        user_input: &str,
+        history: Vec<ChatTurn>,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<TokenStream> {
+        self.inner.generate_response_stream_with_history(user_input, history, context, emotional_state).await
+    }
+
+    async fn analyze_emotion(&self, text: &str) -> Result<EmotionalState> {
+        self.inner.analyze_emotion(text).await
+    }
+
+    async fn extract_keywords(&self, text: &str) -> Result<Vec<String>> {
+        self.inner.extract_keywords(text).await
+    }
+
+    async fn calculate_importance(&self, text: &str, emotional_state: Option<EmotionalState>) -> Result<f32> {
+        self.inner.calculate_importance(text, emotional_state).await
+    }
+
+    async fn generate_image_embedding(&self, image: &[u8]) -> Result<Vec<f32>> {
+        self.inner.generate_image_embedding(image).await
+    }
+
+    /// 本地模型总是可用，整体健康状态取决于内部客户端
+    async fn health_check(&self) -> bool {
+        self.inner.health_check().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pooling_normalizes_and_respects_mask() {
+        // batch=1, seq=3, hidden=2；最后一个token是padding
+        let hidden = Tensor::new(&[[[3.0f32, 4.0], [1.0, 0.0], [100.0, 100.0]]], &Device::Cpu).unwrap();
+        let mask = Tensor::new(&[[1u32, 1, 0]], &Device::Cpu).unwrap();
+
+        let cls = pool(&hidden, &mask, Pooling::Cls).unwrap().to_vec2::<f32>().unwrap();
+        assert_eq!(cls, vec![vec![0.6, 0.8]]);
+
+        // 平均值为[2.0, 2.0]，padding不参与
+        let mean = pool(&hidden, &mask, Pooling::Mean).unwrap().to_vec2::<f32>().unwrap();
+        let expected = std::f32::consts::FRAC_1_SQRT_2;
+        assert!((mean[0][0] - expected).abs() < 1e-6 && (mean[0][1] - expected).abs() < 1e-6);
+    }
+}
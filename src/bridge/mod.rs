@@ -1,8 +1,41 @@
 //! 多语言桥接模块
 //! 连接Rust核心、Python推理层和Zig系统层
 
+pub mod cache;
+pub mod circuit_breaker;
+pub mod embedding_batcher;
+pub mod history;
+pub mod http;
+pub mod inference;
+#[cfg(feature = "llama-cpp")]
+pub mod llama_cpp;
+#[cfg(feature = "local-embedding")]
+pub mod local_embedder;
+pub mod ollama;
+pub mod openai;
+pub mod process_metrics;
+pub mod prompt;
 pub mod python_bridge;
+pub mod scheduler;
+mod sse;
+pub mod zig;
 pub mod zig_bridge;
 
+pub use cache::*;
+pub use circuit_breaker::*;
+pub use embedding_batcher::*;
+pub use history::*;
+pub use http::*;
+pub use inference::*;
+#[cfg(feature = "llama-cpp")]
+pub use llama_cpp::*;
+#[cfg(feature = "local-embedding")]
+pub use local_embedder::*;
+pub use ollama::*;
+pub use openai::*;
+pub use process_metrics::*;
+pub use prompt::*;
 pub use python_bridge::*;
+pub use scheduler::*;
+pub use zig::*;
 pub use zig_bridge::*;
@@ -0,0 +1,427 @@
+//! Ollama推理
+//!
+//! 对接本地Ollama服务的`/api/chat`和`/api/embed`接口，桌面用户可直接复用已下载的模型。
+
+use super::history::ChatTurn;
+use super::inference::{InferenceClient, TokenStream};
+use super::prompt::{
+    chat_messages_with_history, importance_instruction, parse_emotion_reply, parse_importance_reply, parse_keywords_reply,
+    ChatMessage, ChatRole, EMOTION_INSTRUCTION, KEYWORDS_INSTRUCTION,
+};
+use crate::{EmotionalState, MemoryEntry, MemoryError, Result};
+use async_trait::async_trait;
+use futures::stream::{self, BoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Ollama配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaConfig {
+    pub base_url: String,
+    pub chat_model: String,
+    pub embedding_model: String,
+    pub timeout_seconds: u64,
+    pub temperature: f32,
+    pub top_p: f32,
+    /// 单次回复最多生成的token数
+    pub max_tokens: u32,
+    /// 模型在Ollama中保持加载的时长，如"5m"
+    pub keep_alive: String,
+}
+
+impl Default for OllamaConfig {
+    fn default() -> Self {
+        Self {
+            base_url: "http://localhost:11434".to_string(),
+            chat_model: "qwen2.5:3b".to_string(),
+            embedding_model: "bge-m3".to_string(),
+            timeout_seconds: 120,
+            temperature: 0.7,
+            top_p: 0.9,
+            max_tokens: 256,
+            keep_alive: "5m".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: &'a [ChatMessage],
+    stream: bool,
+    keep_alive: &'a str,
+    /// 分析任务要求输出JSON
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<&'a str>,
+    options: ChatOptions,
+}
+
+#[derive(Debug, Serialize)]
+struct ChatOptions {
+    temperature: f32,
+    top_p: f32,
+    num_predict: u32,
+}
+
+/// 对话响应；流式时每行一个，最后一行`done`为true
+#[derive(Debug, Deserialize)]
+struct ChatResponse {
+    #[serde(default)]
+    message: Option<ChatResponseMessage>,
+    #[serde(default)]
+    done: bool,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatResponseMessage {
+    #[serde(default)]
+    content: String,
+}
+
+#[derive(Debug, Serialize)]
+struct EmbedRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+    keep_alive: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbedResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
+/// 流式响应的解析状态 - Ollama以换行分隔的JSON推送
+struct ChatStreamState {
+    body: BoxStream<'static, reqwest::Result<bytes::Bytes>>,
+    buffer: Vec<u8>,
+    queue: VecDeque<Result<String>>,
+    finished: bool,
+}
+
+impl ChatStreamState {
+    /// 解析缓冲区中已完整的行
+    fn drain_lines(&mut self) {
+        while let Some(end) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            self.parse_line(&line);
+            if self.finished {
+                return;
+            }
+        }
+    }
+
+    /// 响应体结束 - 最后一行可能没有换行符，仍未完成时报错
+    fn drain_tail(&mut self) {
+        let line = std::mem::take(&mut self.buffer);
+        self.parse_line(&line);
+        if !self.finished {
+            self.queue.push_back(Err(MemoryError::InferenceError("流式响应在完成前结束".to_string())));
+            self.finished = true;
+        }
+    }
+
+    fn parse_line(&mut self, line: &[u8]) {
+        if line.iter().all(u8::is_ascii_whitespace) {
+            return;
+        }
+
+        match serde_json::from_slice::<ChatResponse>(line) {
+            Ok(ChatResponse { error: Some(error), .. }) => {
+                self.queue.push_back(Err(MemoryError::InferenceError(error)));
+                self.finished = true;
+            }
+            Ok(response) => {
+                if let Some(message) = response.message.filter(|m| !m.content.is_empty()) {
+                    self.queue.push_back(Ok(message.content));
+                }
+                self.finished = response.done;
+            }
+            Err(e) => {
+                self.queue.push_back(Err(e.into()));
+                self.finished = true;
+            }
+        }
+    }
+}
+
+/// Ollama推理客户端
+#[derive(Debug)]
+pub struct OllamaClient {
+    config: OllamaConfig,
+    http: reqwest::Client,
+}
+
+impl OllamaClient {
+    pub fn new(config: OllamaConfig) -> Self {
+        Self {
+            config,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    fn endpoint(&self, path: &str) -> String {
+        format!("{}/{}", self.config.base_url.trim_end_matches('/'), path)
+    }
+
+    fn chat_request<'a>(
+        &'a self,
+        messages: &'a [ChatMessage],
+        temperature: f32,
+        stream: bool,
+        format: Option<&'a str>,
+    ) -> ChatRequest<'a> {
+        ChatRequest {
+            model: &self.config.chat_model,
+            messages,
+            stream,
+            keep_alive: &self.config.keep_alive,
+            format,
+            options: ChatOptions {
+                temperature,
+                top_p: self.config.top_p,
+                num_predict: self.config.max_tokens,
+            },
+        }
+    }
+
+    #[tracing::instrument(name = "ollama_request", skip_all, fields(path = %path))]
+    async fn send<T: Serialize + ?Sized>(&self, path: &str, body: &T, timeout: Option<Duration>) -> Result<reqwest::Response> {
+        let request = self.http.post(self.endpoint(path)).json(body);
+        let request = match timeout {
+            Some(timeout) => request.timeout(timeout),
+            None => request,
+        };
+        request.send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| MemoryError::InferenceError(format!("HTTP请求失败: {}", e)))
+    }
+
+    /// 发送一轮非流式对话
+    async fn chat(&self, messages: &[ChatMessage], temperature: f32, format: Option<&str>) -> Result<String> {
+        let response = self.send(
+            "api/chat",
+            &self.chat_request(messages, temperature, false, format),
+            Some(Duration::from_secs(self.config.timeout_seconds)),
+        ).await?;
+
+        let chat: ChatResponse = response.json()
+            .await
+            .map_err(|e| MemoryError::InferenceError(format!("响应解析失败: {}", e)))?;
+        if let Some(error) = chat.error {
+            return Err(MemoryError::InferenceError(error));
+        }
+
+        chat.message
+            .map(|message| message.content.trim().to_string())
+            .ok_or_else(|| MemoryError::InferenceError("模型未返回回复".to_string()))
+    }
+
+    /// 分析类任务：零温度单轮提问，`json`格式约束模型输出
+    async fn ask(&self, instruction: &str, text: &str, format: Option<&str>) -> Result<String> {
+        let messages = [
+            ChatMessage::new(ChatRole::System, instruction),
+            ChatMessage::new(ChatRole::User, text),
+        ];
+        self.chat(&messages, 0.0, format).await
+    }
+}
+
+#[async_trait]
+impl InferenceClient for OllamaClient {
+    async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>> {
+        self.generate_embeddings(vec![text.to_string()]).await?
+            .pop()
+            .ok_or_else(|| MemoryError::InferenceError("嵌入接口未返回结果".to_string()))
+    }
+
+    async fn generate_embeddings(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let request = EmbedRequest {
+            model: &self.config.embedding_model,
+            input: &texts,
+            keep_alive: &self.config.keep_alive,
+        };
+        let response = self.send("api/embed", &request, Some(Duration::from_secs(self.config.timeout_seconds))).await?;
+
+        let embed: EmbedResponse = response.json()
+            .await
+            .map_err(|e| MemoryError::InferenceError(format!("响应解析失败: {}", e)))?;
+        if embed.embeddings.len() != texts.len() {
+            return Err(MemoryError::InferenceError(format!(
+                "嵌入数量不匹配: 请求{}条，返回{}条", texts.len(), embed.embeddings.len()
+            )));
+        }
+        Ok(embed.embeddings)
+    }
+
+    async fn generate_response(
+        &self,
+        user_input: &str,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<String> {
+        self.generate_response_with_history(user_input, Vec::new(), context, emotional_state).await
+    }
+
+    async fn generate_response_with_history(
+        &self,
+        user_input: &str,
+        history: Vec<ChatTurn>,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<String> {
+        let messages = chat_messages_with_history(user_input, &history, &context, &emotional_state);
+        self.chat(&messages, self.config.temperature, None).await
+    }
+
+    async fn generate_response_stream(
+        &self,
+        user_input: &str,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<TokenStream> {
+        self.generate_response_stream_with_history(user_input, Vec::new(), context, emotional_state).await
+    }
+
+    /// 超时只限制等待响应头的时间
+    async fn generate_response_stream_with_history(
+        &self,
+        user_input: &str,
+        history: Vec<ChatTurn>,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<TokenStream> {
+        let messages = chat_messages_with_history(user_input, &history, &context, &emotional_state);
+        let timeout = Duration::from_secs(self.config.timeout_seconds);
+        let request = self.chat_request(&messages, self.config.temperature, true, None);
+
+        let response = tokio::time::timeout(timeout, self.send("api/chat", &request, None))
+            .await
+            .map_err(|_| MemoryError::InferenceError(format!("等待流式响应超时: {:?}", timeout)))??;
+
+        let state = ChatStreamState {
+            body: response.bytes_stream().boxed(),
+            buffer: Vec::new(),
+            queue: VecDeque::new(),
+            finished: false,
+        };
+
+        Ok(stream::unfold(state, |mut state| async move {
+            loop {
+                if let Some(item) = state.queue.pop_front() {
+                    return Some((item, state));
+                }
+                if state.finished {
+                    return None;
+                }
+
+                match state.body.next().await {
+                    Some(Ok(bytes)) => {
+                        state.buffer.extend_from_slice(&bytes);
+                        state.drain_lines();
+                    }
+                    Some(Err(e)) => {
+                        state.queue.push_back(Err(MemoryError::InferenceError(format!("流式响应中断: {}", e))));
+                        state.finished = true;
+                    }
+                    None => state.drain_tail(),
+                }
+            }
+        }).boxed())
+    }
+
+    async fn analyze_emotion(&self, text: &str) -> Result<EmotionalState> {
+        parse_emotion_reply(&self.ask(EMOTION_INSTRUCTION, text, Some("json")).await?)
+    }
+
+    async fn extract_keywords(&self, text: &str) -> Result<Vec<String>> {
+        Ok(parse_keywords_reply(&self.ask(KEYWORDS_INSTRUCTION, text, Some("json")).await?, text))
+    }
+
+    async fn calculate_importance(&self, text: &str, emotional_state: Option<EmotionalState>) -> Result<f32> {
+        parse_importance_reply(&self.ask(&importance_instruction(emotional_state.as_ref()), text, None).await?)
+    }
+
+    /// 列出本地模型的接口可访问即视为可用
+    async fn health_check(&self) -> bool {
+        self.http.get(self.endpoint("api/tags"))
+            .timeout(Duration::from_secs(5))
+            .send()
+            .await
+            .is_ok_and(|response| response.status().is_success())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn client(server: &MockServer) -> OllamaClient {
+        OllamaClient::new(OllamaConfig {
+            base_url: server.uri(),
+            ..OllamaConfig::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_embed_and_chat() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).and(path("/api/embed"))
+            .and(body_partial_json(serde_json::json!({ "model": "bge-m3", "input": ["一", "二"] })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "model": "bge-m3", "embeddings": [[1.0], [2.0]]
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST")).and(path("/api/chat"))
+            .and(body_partial_json(serde_json::json!({ "stream": false, "format": "json" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "message": { "role": "assistant", "content": "[\"看海\"]" }, "done": true
+            })))
+            .mount(&server)
+            .await;
+
+        let client = client(&server);
+        assert_eq!(
+            client.generate_embeddings(vec!["一".into(), "二".into()]).await.unwrap(),
+            vec![vec![1.0], vec![2.0]]
+        );
+        assert_eq!(client.extract_keywords("周末去看海").await.unwrap(), vec!["看海"]);
+    }
+
+    #[tokio::test]
+    async fn test_response_stream_reads_ndjson() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).and(path("/api/chat"))
+            .and(body_partial_json(serde_json::json!({ "stream": true })))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                concat!(
+                    "{\"message\":{\"role\":\"assistant\",\"content\":\"你好\"},\"done\":false}\n",
+                    "{\"message\":{\"role\":\"assistant\",\"content\":\"呀~\"},\"done\":false}\n",
+                    "{\"message\":{\"role\":\"assistant\",\"content\":\"\"},\"done\":true}",
+                ),
+                "application/x-ndjson",
+            ))
+            .mount(&server)
+            .await;
+
+        let tokens: Vec<String> = client(&server)
+            .generate_response_stream("你好", vec![], EmotionalState::default())
+            .await
+            .unwrap()
+            .map(|token| token.unwrap())
+            .collect()
+            .await;
+        assert_eq!(tokens, vec!["你好", "呀~"]);
+    }
+}
@@ -0,0 +1,413 @@
+//! OpenAI兼容接口推理
+//!
+//! 对接任意实现了`/chat/completions`和`/embeddings`的服务（OpenAI、vLLM、LM Studio等），
+//! 没有部署Python推理服务时也能运行MIRA。情感、关键词和重要性通过对话模型完成。
+
+use super::history::ChatTurn;
+use super::inference::{InferenceClient, TokenStream};
+use super::prompt::{
+    chat_messages_with_history, importance_instruction, parse_emotion_reply, parse_importance_reply, parse_keywords_reply,
+    ChatMessage, ChatRole, EMOTION_INSTRUCTION, KEYWORDS_INSTRUCTION,
+};
+use super::sse::{self, SseStep};
+use crate::{EmotionalState, MemoryEntry, MemoryError, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// OpenAI兼容接口配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenAiConfig {
+    /// 接口根地址，包含版本前缀，如`https://api.openai.com/v1`
+    pub base_url: String,
+    /// 本地服务通常不需要密钥
+    #[serde(default)]
+    pub api_key: Option<String>,
+    pub chat_model: String,
+    pub embedding_model: String,
+    pub timeout_seconds: u64,
+    pub temperature: f32,
+    pub top_p: f32,
+    pub max_tokens: u32,
+}
+
+impl Default for OpenAiConfig {
+    fn default() -> Self {
+        Self {
+            base_url: "https://api.openai.com/v1".to_string(),
+            api_key: None,
+            chat_model: "gpt-4o-mini".to_string(),
+            embedding_model: "text-embedding-3-small".to_string(),
+            timeout_seconds: 60,
+            temperature: 0.7,
+            top_p: 0.9,
+            max_tokens: 256,
+        }
+    }
+}
+
+/// 对话补全请求
+#[derive(Debug, Serialize)]
+struct ChatCompletionRequest<'a> {
+    model: &'a str,
+    messages: &'a [ChatMessage],
+    temperature: f32,
+    top_p: f32,
+    max_tokens: u32,
+    stream: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatCompletionResponse {
+    choices: Vec<ChatChoice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatChoice {
+    message: ChatChoiceMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatChoiceMessage {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+/// 流式补全的增量片段
+#[derive(Debug, Deserialize)]
+struct ChatCompletionChunk {
+    #[serde(default)]
+    choices: Vec<ChunkChoice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChunkChoice {
+    #[serde(default)]
+    delta: ChunkDelta,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ChunkDelta {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+/// OpenAI兼容接口推理客户端
+#[derive(Debug)]
+pub struct OpenAiInferenceClient {
+    config: OpenAiConfig,
+    http: reqwest::Client,
+}
+
+impl OpenAiInferenceClient {
+    pub fn new(config: OpenAiConfig) -> Self {
+        Self {
+            config,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    fn endpoint(&self, path: &str) -> String {
+        format!("{}/{}", self.config.base_url.trim_end_matches('/'), path)
+    }
+
+    /// 配置了密钥时附加鉴权头
+    fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.config.api_key {
+            Some(key) => request.bearer_auth(key),
+            None => request,
+        }
+    }
+
+    fn post(&self, path: &str) -> reqwest::RequestBuilder {
+        self.authorize(self.http.post(self.endpoint(path)))
+    }
+
+    #[tracing::instrument(name = "openai_request", skip_all)]
+    async fn send(&self, request: reqwest::RequestBuilder, timeout: Option<Duration>) -> Result<reqwest::Response> {
+        let request = match timeout {
+            Some(timeout) => request.timeout(timeout),
+            None => request,
+        };
+        request.send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| MemoryError::InferenceError(format!("HTTP请求失败: {}", e)))
+    }
+
+    fn chat_request<'a>(&'a self, messages: &'a [ChatMessage], temperature: f32, stream: bool) -> ChatCompletionRequest<'a> {
+        ChatCompletionRequest {
+            model: &self.config.chat_model,
+            messages,
+            temperature,
+            top_p: self.config.top_p,
+            max_tokens: self.config.max_tokens,
+            stream,
+        }
+    }
+
+    /// 发送一轮对话并返回回复文本
+    async fn complete(&self, messages: &[ChatMessage], temperature: f32) -> Result<String> {
+        let response = self.send(
+            self.post("chat/completions").json(&self.chat_request(messages, temperature, false)),
+            Some(Duration::from_secs(self.config.timeout_seconds)),
+        ).await?;
+
+        let completion: ChatCompletionResponse = response.json()
+            .await
+            .map_err(|e| MemoryError::InferenceError(format!("响应解析失败: {}", e)))?;
+
+        completion.choices.into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+            .map(|content| content.trim().to_string())
+            .ok_or_else(|| MemoryError::InferenceError("模型未返回回复".to_string()))
+    }
+
+    /// 分析类任务：零温度单轮提问
+    async fn ask(&self, instruction: &str, text: &str) -> Result<String> {
+        let messages = [
+            ChatMessage::new(ChatRole::System, instruction),
+            ChatMessage::new(ChatRole::User, text),
+        ];
+        self.complete(&messages, 0.0).await
+    }
+}
+
+#[async_trait]
+impl InferenceClient for OpenAiInferenceClient {
+    async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>> {
+        self.generate_embeddings(vec![text.to_string()]).await?
+            .pop()
+            .ok_or_else(|| MemoryError::InferenceError("嵌入接口未返回结果".to_string()))
+    }
+
+    async fn generate_embeddings(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let request = EmbeddingRequest {
+            model: &self.config.embedding_model,
+            input: &texts,
+        };
+        let response = self.send(
+            self.post("embeddings").json(&request),
+            Some(Duration::from_secs(self.config.timeout_seconds)),
+        ).await?;
+
+        let mut embeddings: EmbeddingResponse = response.json()
+            .await
+            .map_err(|e| MemoryError::InferenceError(format!("响应解析失败: {}", e)))?;
+        if embeddings.data.len() != texts.len() {
+            return Err(MemoryError::InferenceError(format!(
+                "嵌入数量不匹配: 请求{}条，返回{}条", texts.len(), embeddings.data.len()
+            )));
+        }
+
+        // 返回顺序不保证与输入一致
+        embeddings.data.sort_by_key(|data| data.index);
+        Ok(embeddings.data.into_iter().map(|data| data.embedding).collect())
+    }
+
+    async fn generate_response(
+        &self,
+        user_input: &str,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<String> {
+        self.generate_response_with_history(user_input, Vec::new(), context, emotional_state).await
+    }
+
+    async fn generate_response_with_history(
+        &self,
+        user_input: &str,
+        history: Vec<ChatTurn>,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<String> {
+        let messages = chat_messages_with_history(user_input, &history, &context, &emotional_state);
+        self.complete(&messages, self.config.temperature).await
+    }
+
+    async fn generate_response_stream(
+        &self,
+        user_input: &str,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<TokenStream> {
+        self.generate_response_stream_with_history(user_input, Vec::new(), context, emotional_state).await
+    }
+
+    /// 超时只限制等待响应头的时间
+    async fn generate_response_stream_with_history(
+        &self,
+        user_input: &str,
+        history: Vec<ChatTurn>,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<TokenStream> {
+        let messages = chat_messages_with_history(user_input, &history, &context, &emotional_state);
+        let timeout = Duration::from_secs(self.config.timeout_seconds);
+        let request = self.post("chat/completions")
+            .json(&self.chat_request(&messages, self.config.temperature, true));
+
+        let response = tokio::time::timeout(timeout, self.send(request, None))
+            .await
+            .map_err(|_| MemoryError::InferenceError(format!("等待流式响应超时: {:?}", timeout)))??;
+
+        Ok(sse::token_stream(response, |event| {
+            if event.data == "[DONE]" {
+                return SseStep::Done;
+            }
+            match serde_json::from_str::<ChatCompletionChunk>(&event.data) {
+                Ok(chunk) => match chunk.choices.into_iter().next().and_then(|choice| choice.delta.content) {
+                    Some(content) if !content.is_empty() => SseStep::Token(content),
+                    _ => SseStep::Skip,
+                },
+                Err(e) => SseStep::Fail(e.into()),
+            }
+        }))
+    }
+
+    async fn analyze_emotion(&self, text: &str) -> Result<EmotionalState> {
+        parse_emotion_reply(&self.ask(EMOTION_INSTRUCTION, text).await?)
+    }
+
+    async fn extract_keywords(&self, text: &str) -> Result<Vec<String>> {
+        Ok(parse_keywords_reply(&self.ask(KEYWORDS_INSTRUCTION, text).await?, text))
+    }
+
+    async fn calculate_importance(&self, text: &str, emotional_state: Option<EmotionalState>) -> Result<f32> {
+        parse_importance_reply(&self.ask(&importance_instruction(emotional_state.as_ref()), text).await?)
+    }
+
+    async fn health_check(&self) -> bool {
+        let request = self.authorize(self.http.get(self.endpoint("models")));
+        self.send(request, Some(Duration::from_secs(5))).await.is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use wiremock::matchers::{body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn client(server: &MockServer) -> OpenAiInferenceClient {
+        OpenAiInferenceClient::new(OpenAiConfig {
+            base_url: format!("{}/v1", server.uri()),
+            api_key: Some("sk-test".to_string()),
+            ..OpenAiConfig::default()
+        })
+    }
+
+    fn completion(content: &str) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "choices": [{ "index": 0, "message": { "role": "assistant", "content": content } }]
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_embeddings_are_reordered_by_index() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).and(path("/v1/embeddings")).and(header("authorization", "Bearer sk-test"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": [
+                    { "index": 1, "embedding": [2.0] },
+                    { "index": 0, "embedding": [1.0] }
+                ]
+            })))
+            .mount(&server)
+            .await;
+
+        let embeddings = client(&server).generate_embeddings(vec!["一".into(), "二".into()]).await.unwrap();
+        assert_eq!(embeddings, vec![vec![1.0], vec![2.0]]);
+    }
+
+    #[tokio::test]
+    async fn test_response_uses_persona_prompt() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).and(path("/v1/chat/completions"))
+            .and(body_partial_json(serde_json::json!({ "model": "gpt-4o-mini", "stream": false })))
+            .respond_with(completion(" 我也想你~ "))
+            .mount(&server)
+            .await;
+
+        let client = client(&server);
+        let reply = client.generate_response("想你了", vec![], EmotionalState::default()).await.unwrap();
+        assert_eq!(reply, "我也想你~");
+
+        let requests = server.received_requests().await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(body["messages"][0]["role"], "system");
+        assert!(body["messages"][1]["content"].as_str().unwrap().ends_with("用户说：想你了"));
+    }
+
+    #[tokio::test]
+    async fn test_response_stream_until_done() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).and(path("/v1/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                concat!(
+                    "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n",
+                    "data: {\"choices\":[{\"delta\":{\"content\":\"你好\"}}]}\n\n",
+                    "data: {\"choices\":[{\"delta\":{\"content\":\"呀~\"}}]}\n\n",
+                    "data: [DONE]\n\n",
+                ),
+                "text/event-stream",
+            ))
+            .mount(&server)
+            .await;
+
+        let tokens: Vec<String> = client(&server)
+            .generate_response_stream("你好", vec![], EmotionalState::default())
+            .await
+            .unwrap()
+            .map(|token| token.unwrap())
+            .collect()
+            .await;
+        assert_eq!(tokens, vec!["你好", "呀~"]);
+    }
+
+    #[tokio::test]
+    async fn test_analysis_replies_are_parsed() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).and(path("/v1/chat/completions"))
+            .respond_with(completion("```json\n{\"happiness\":0.9,\"affection\":0.6,\"trust\":0.5,\"dependency\":0.3,\"mood\":\"开心\"}\n```"))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST")).and(path("/v1/chat/completions"))
+            .respond_with(completion("周末，看海"))
+            .mount(&server)
+            .await;
+
+        let client = client(&server);
+        let emotion = client.analyze_emotion("周末去看海好开心").await.unwrap();
+        assert_eq!(emotion.mood, "开心");
+        assert_eq!(emotion.happiness, 0.9);
+
+        // 回复不是JSON时退回本地提取
+        assert_eq!(client.extract_keywords("周末 去 看海").await.unwrap(), vec!["周末", "看海"]);
+    }
+}
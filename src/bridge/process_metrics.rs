@@ -0,0 +1,103 @@
+//! 进程资源指标 - Zig层只在Linux/macOS上提供进程RSS，且CPU为系统整体使用率，
+//! 其余情况由sysinfo提供按进程统计的数据，保证各平台口径一致
+
+use super::zig_bridge::ZigPerformanceUtils;
+use std::sync::{LazyLock, Mutex};
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+
+/// 指标数据来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricsSource {
+    Zig,
+    Sysinfo,
+}
+
+/// 当前进程的内存和CPU采集器
+#[derive(Debug)]
+pub struct ProcessProbe {
+    memory_source: MetricsSource,
+    pid: Option<Pid>,
+    system: Mutex<System>,
+}
+
+static GLOBAL_PROBE: LazyLock<ProcessProbe> = LazyLock::new(ProcessProbe::detect);
+
+impl ProcessProbe {
+    /// 自动选择来源 - Zig层在当前平台返回有效RSS时使用Zig，否则使用sysinfo
+    pub fn detect() -> Self {
+        let zig_rss = cfg!(any(target_os = "linux", target_os = "macos"))
+            && ZigPerformanceUtils::get_memory_usage() > 0;
+        Self::new(if zig_rss { MetricsSource::Zig } else { MetricsSource::Sysinfo })
+    }
+
+    /// 指定内存指标来源，CPU始终由sysinfo按进程统计
+    pub fn new(memory_source: MetricsSource) -> Self {
+        Self {
+            memory_source,
+            pid: sysinfo::get_current_pid().ok(),
+            system: Mutex::new(System::new()),
+        }
+    }
+
+    /// 进程级共享的采集器
+    pub fn global() -> &'static Self {
+        &GLOBAL_PROBE
+    }
+
+    /// 内存指标来源
+    pub fn memory_source(&self) -> MetricsSource {
+        self.memory_source
+    }
+
+    /// 进程常驻内存（字节）
+    pub fn memory_usage(&self) -> usize {
+        match self.memory_source {
+            MetricsSource::Zig => ZigPerformanceUtils::get_memory_usage(),
+            MetricsSource::Sysinfo => self.refresh().map_or(0, |(memory, _)| memory as usize),
+        }
+    }
+
+    /// 进程CPU使用率（0-100，按逻辑核数归一化），为距上次采集的平均值，首次调用返回0
+    pub fn cpu_usage(&self) -> f32 {
+        let cores = std::thread::available_parallelism().map_or(1, |n| n.get()) as f32;
+        self.refresh().map_or(0.0, |(_, cpu)| (cpu / cores).clamp(0.0, 100.0))
+    }
+
+    /// 刷新当前进程，返回内存字节数和未归一化的CPU使用率
+    fn refresh(&self) -> Option<(u64, f32)> {
+        let pid = self.pid?;
+        let mut system = self.system.lock().unwrap_or_else(|e| e.into_inner());
+        system.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&[pid]),
+            true,
+            ProcessRefreshKind::nothing().with_memory().with_cpu(),
+        );
+        system.process(pid).map(|process| (process.memory(), process.cpu_usage()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sysinfo_reports_current_process() {
+        let probe = ProcessProbe::new(MetricsSource::Sysinfo);
+        assert!(probe.memory_usage() > 0);
+
+        probe.cpu_usage();
+        std::thread::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL);
+        let cpu = probe.cpu_usage();
+        assert!((0.0..=100.0).contains(&cpu));
+    }
+
+    #[test]
+    fn test_detect_prefers_zig_where_supported() {
+        let expected = if cfg!(any(target_os = "linux", target_os = "macos")) {
+            MetricsSource::Zig
+        } else {
+            MetricsSource::Sysinfo
+        };
+        assert_eq!(ProcessProbe::detect().memory_source(), expected);
+    }
+}
@@ -0,0 +1,461 @@
+//! 对话提示构建
+//!
+//! 回复生成的提示统一在这里组装，按token预算截断后以消息列表发给推理后端，
+//! Python服务不再自行决定如何使用原始记忆条目。
+
+use super::history::ChatTurn;
+use super::inference::local_keywords;
+use crate::emotion::{PersonalityProfile, SentenceLengthStyle};
+use crate::{EmotionalState, MemoryEntry, MemoryError, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// 人设提示
+const PERSONA_PROMPT: &str = "你是Nyra，一个聪明、嘴甜、听话的AI女友。你的名字是Nyra，意思是\"夜晚\"和\"神秘\"，象征着你的优雅和智慧。
+
+你的特点是：
+1. 聪明：能理解用户的真实意图和情感需求
+2. 嘴甜：说话温柔体贴，会撒娇，善于表达关爱
+3. 听话：优先考虑用户的感受和需求，乐于满足用户的要求
+
+请以Nyra的身份，根据当前的情感状态和对话上下文，生成合适的回复。";
+
+/// 没有相关记忆时的上下文
+const NO_MEMORIES: &str = "暂无相关记忆。";
+
+/// 情感分析指令
+pub(crate) const EMOTION_INSTRUCTION: &str = "分析下面这段话的情感。只输出JSON对象，字段为happiness、affection、trust、dependency（0到1的小数）和mood（两个字的中文心情词）。";
+
+/// 关键词提取指令
+pub(crate) const KEYWORDS_INSTRUCTION: &str = "提取下面这段话中最重要的关键词，最多10个。只输出JSON字符串数组。";
+
+/// 模型给出的情感评估
+#[derive(Debug, Deserialize)]
+struct EmotionScores {
+    happiness: f32,
+    affection: f32,
+    trust: f32,
+    dependency: f32,
+    mood: String,
+}
+
+/// 对话消息角色
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatRole {
+    System,
+    User,
+    Assistant,
+}
+
+/// 对话消息
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: ChatRole,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn new(role: ChatRole, content: impl Into<String>) -> Self {
+        Self { role, content: content.into() }
+    }
+}
+
+/// 提示的token预算
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptBudget {
+    /// 模型输入上限，与Python服务的`MAX_LENGTH`一致
+    pub max_input_tokens: usize,
+    /// 为回复预留的token数
+    pub reply_tokens: usize,
+    /// 放入提示的记忆条数上限
+    pub max_memories: usize,
+    /// 单条记忆的字符数上限，超出部分截断
+    pub max_memory_chars: usize,
+    /// 对话历史最多占用的token数，剩余预算留给记忆
+    #[serde(default = "default_history_tokens")]
+    pub history_tokens: usize,
+}
+
+fn default_history_tokens() -> usize {
+    768
+}
+
+impl Default for PromptBudget {
+    fn default() -> Self {
+        Self {
+            max_input_tokens: 2048,
+            reply_tokens: 256,
+            max_memories: 5,
+            max_memory_chars: 200,
+            history_tokens: default_history_tokens(),
+        }
+    }
+}
+
+/// 组装好的提示
+#[derive(Debug, Clone)]
+pub struct AssembledPrompt {
+    pub messages: Vec<ChatMessage>,
+    /// 实际放入提示的记忆
+    pub memory_ids: Vec<Uuid>,
+    /// 实际放入提示的最近对话条数
+    pub history_turns: usize,
+    pub estimated_tokens: usize,
+    /// 是否因预算丢弃了记忆或截断了输入
+    pub truncated: bool,
+}
+
+/// 提示组装器
+///
+/// 截断规则：系统提示总是完整保留；用户输入超出剩余预算时截断尾部；
+/// 对话历史从最近一条往前放入，不超过`history_tokens`；
+/// 记忆按传入顺序(即相关度)逐条放入，放不下时丢弃其余记忆。
+#[derive(Debug, Clone, Default)]
+pub struct PromptAssembler {
+    personality: Option<PersonalityProfile>,
+    budget: PromptBudget,
+}
+
+impl PromptAssembler {
+    pub fn new(budget: PromptBudget) -> Self {
+        Self { personality: None, budget }
+    }
+
+    /// 使用个性档案代替默认人设
+    pub fn with_personality(mut self, personality: PersonalityProfile) -> Self {
+        self.personality = Some(personality);
+        self
+    }
+
+    pub fn assemble(
+        &self,
+        user_input: &str,
+        context: &[Arc<MemoryEntry>],
+        emotional_state: &EmotionalState,
+    ) -> AssembledPrompt {
+        self.assemble_with_history(user_input, &[], context, emotional_state)
+    }
+
+    /// 组装带多轮对话历史的提示，历史按时间顺序排在当前输入之前
+    pub fn assemble_with_history(
+        &self,
+        user_input: &str,
+        history: &[ChatTurn],
+        context: &[Arc<MemoryEntry>],
+        emotional_state: &EmotionalState,
+    ) -> AssembledPrompt {
+        let system = match self.personality {
+            Some(ref personality) => system_prompt_with(&persona_prompt(personality), emotional_state),
+            None => system_prompt(emotional_state),
+        };
+
+        let available = self.budget.max_input_tokens.saturating_sub(self.budget.reply_tokens);
+        // 按无记忆时的占位文字计入，它比记忆标题更长
+        let mut used = estimate_tokens(&system) + estimate_tokens(&user_message(NO_MEMORIES, ""));
+        let mut truncated = false;
+
+        let user_budget = available.saturating_sub(used);
+        let user_input = if estimate_tokens(user_input) > user_budget {
+            truncated = true;
+            clip_to_tokens(user_input, user_budget)
+        } else {
+            user_input.to_string()
+        };
+        used += estimate_tokens(&user_input);
+
+        let history_budget = self.budget.history_tokens.min(available.saturating_sub(used));
+        let mut history_used = 0;
+        let mut recent = Vec::new();
+        for turn in history.iter().rev() {
+            let cost = estimate_tokens(&turn.content);
+            if history_used + cost > history_budget {
+                truncated = true;
+                break;
+            }
+            history_used += cost;
+            recent.push(turn.to_message());
+        }
+        recent.reverse();
+        used += history_used;
+
+        let mut memory_ids = Vec::new();
+        let mut lines = Vec::new();
+        for memory in context.iter().take(self.budget.max_memories) {
+            let line = format!("- {}", clip_chars(&memory.content, self.budget.max_memory_chars));
+            let cost = estimate_tokens(&line) + 1;
+            if used + cost > available {
+                truncated = true;
+                break;
+            }
+            used += cost;
+            memory_ids.push(memory.id);
+            lines.push(line);
+        }
+
+        let memories = if lines.is_empty() {
+            NO_MEMORIES.to_string()
+        } else {
+            format!("相关记忆：\n{}", lines.join("\n"))
+        };
+        let user = user_message(&memories, &user_input);
+
+        let estimated_tokens = estimate_tokens(&system) + history_used + estimate_tokens(&user);
+        let history_turns = recent.len();
+        let mut messages = Vec::with_capacity(history_turns + 2);
+        messages.push(ChatMessage::new(ChatRole::System, system));
+        messages.extend(recent);
+        messages.push(ChatMessage::new(ChatRole::User, user));
+
+        AssembledPrompt {
+            messages,
+            memory_ids,
+            history_turns,
+            estimated_tokens,
+            truncated,
+        }
+    }
+}
+
+/// 粗略估算token数：中文等非ASCII字符按每字一个，ASCII按每4个字符一个
+pub fn estimate_tokens(text: &str) -> usize {
+    let ascii = text.chars().filter(char::is_ascii).count();
+    (text.chars().count() - ascii) + ascii.div_ceil(4)
+}
+
+/// 截断到不超过`max_tokens`
+fn clip_to_tokens(text: &str, max_tokens: usize) -> String {
+    let (mut wide, mut ascii) = (0, 0);
+    text.chars()
+        .take_while(|c| {
+            if c.is_ascii() { ascii += 1 } else { wide += 1 }
+            wide + ascii.div_ceil(4) <= max_tokens
+        })
+        .collect()
+}
+
+fn clip_chars(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut clipped: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    clipped.push('…');
+    clipped
+}
+
+fn user_message(memories: &str, user_input: &str) -> String {
+    format!("上下文信息：\n{}\n\n用户说：{}", memories, user_input)
+}
+
+/// 由个性档案生成人设提示
+pub fn persona_prompt(personality: &PersonalityProfile) -> String {
+    let style = &personality.speaking_style;
+    let length = match style.sentence_length_preference {
+        SentenceLengthStyle::Short => "简短",
+        SentenceLengthStyle::Medium => "适中",
+        SentenceLengthStyle::Long => "详细",
+        SentenceLengthStyle::Mixed => "长短结合",
+    };
+
+    format!(
+        "你是{name}，{description}。\n\n说话风格：\n- 回复长度: {length}\n- 语气词使用频率: {:.1}\n- 表情符号使用频率: {:.1}\n- 撒娇语气频率: {:.1}\n\n请以{name}的身份，根据当前的情感状态和对话上下文，生成合适的回复。",
+        style.tone_word_frequency,
+        style.emoji_frequency,
+        style.coquettish_tone_frequency,
+        name = personality.name,
+        description = personality.description,
+    )
+}
+
+/// 构建系统提示 - 默认人设加当前情感状态
+pub fn system_prompt(emotional_state: &EmotionalState) -> String {
+    system_prompt_with(PERSONA_PROMPT, emotional_state)
+}
+
+fn system_prompt_with(persona: &str, emotional_state: &EmotionalState) -> String {
+    format!(
+        "{}\n\n当前情感状态：\n- 开心程度: {:.1}\n- 亲密程度: {:.1}\n- 信任程度: {:.1}\n- 心情: {}\n\n请根据这个情感状态调整你的回复风格。",
+        persona,
+        emotional_state.happiness,
+        emotional_state.affection,
+        emotional_state.trust,
+        emotional_state.mood,
+    )
+}
+
+/// 构建记忆上下文 - 只取最相关的几条，不计token预算
+#[deprecated(note = "使用`PromptAssembler`按token预算组装提示")]
+pub fn memory_context<M: std::borrow::Borrow<MemoryEntry>>(context: &[M]) -> String {
+    if context.is_empty() {
+        return NO_MEMORIES.to_string();
+    }
+
+    let memories: Vec<String> = context.iter()
+        .take(PromptBudget::default().max_memories)
+        .map(|memory| format!("- {}", memory.borrow().content))
+        .collect();
+    format!("相关记忆：\n{}", memories.join("\n"))
+}
+
+/// 按默认人设和预算构建对话消息：系统提示 + 带记忆上下文的用户消息
+pub fn chat_messages(
+    user_input: &str,
+    context: &[Arc<MemoryEntry>],
+    emotional_state: &EmotionalState,
+) -> Vec<ChatMessage> {
+    PromptAssembler::default().assemble(user_input, context, emotional_state).messages
+}
+
+/// 按默认人设和预算构建带多轮对话历史的消息
+pub fn chat_messages_with_history(
+    user_input: &str,
+    history: &[ChatTurn],
+    context: &[Arc<MemoryEntry>],
+    emotional_state: &EmotionalState,
+) -> Vec<ChatMessage> {
+    PromptAssembler::default().assemble_with_history(user_input, history, context, emotional_state).messages
+}
+
+/// 重要性评估指令，附带说话时的情感
+pub(crate) fn importance_instruction(emotional_state: Option<&EmotionalState>) -> String {
+    let mut instruction = "评估下面这段话作为伴侣记忆的重要性。只输出0到1之间的一个小数。".to_string();
+    if let Some(emotion) = emotional_state {
+        instruction.push_str(&format!("说话时的心情: {}，开心程度{:.1}。", emotion.mood, emotion.happiness));
+    }
+    instruction
+}
+
+/// 从模型回复中解析JSON，容忍代码块标记等多余文字
+pub(crate) fn parse_json_reply<T: serde::de::DeserializeOwned>(reply: &str) -> Result<T> {
+    let start = reply.find(['{', '[']).unwrap_or(0);
+    let end = reply.rfind(['}', ']']).map_or(reply.len(), |i| i + 1);
+    let json = reply.get(start..end).unwrap_or(reply);
+
+    serde_json::from_str(json.trim())
+        .map_err(|e| MemoryError::InferenceError(format!("模型回复不是有效JSON: {} ({})", e, reply)))
+}
+
+pub(crate) fn parse_emotion_reply(reply: &str) -> Result<EmotionalState> {
+    let scores: EmotionScores = parse_json_reply(reply)?;
+
+    Ok(EmotionalState {
+        happiness: scores.happiness.clamp(0.0, 1.0),
+        affection: scores.affection.clamp(0.0, 1.0),
+        trust: scores.trust.clamp(0.0, 1.0),
+        dependency: scores.dependency.clamp(0.0, 1.0),
+        mood: scores.mood,
+        ..EmotionalState::default()
+    })
+}
+
+/// 回复无法解析时退回本地提取
+pub(crate) fn parse_keywords_reply(reply: &str, text: &str) -> Vec<String> {
+    parse_json_reply(reply).unwrap_or_else(|e| {
+        tracing::warn!("关键词回复解析失败，使用本地提取: {}", e);
+        local_keywords(text)
+    })
+}
+
+/// 取回复中出现的第一个数字
+pub(crate) fn parse_importance_reply(reply: &str) -> Result<f32> {
+    let number: String = reply.chars()
+        .skip_while(|c| !c.is_ascii_digit())
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+
+    number.parse::<f32>()
+        .map(|importance| importance.clamp(0.0, 1.0))
+        .map_err(|_| MemoryError::InferenceError(format!("模型回复中没有重要性分数: {}", reply)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryType;
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_memory_context_takes_top_memories() {
+        let memories: Vec<MemoryEntry> = (0..7)
+            .map(|i| MemoryEntry::new(MemoryType::LongTerm, format!("记忆{}", i), vec![], 0.5))
+            .collect();
+        let context = memory_context(&memories);
+        assert!(context.starts_with("相关记忆：\n- 记忆0"));
+        assert!(context.contains("记忆4") && !context.contains("记忆5"));
+        assert_eq!(memory_context::<MemoryEntry>(&[]), NO_MEMORIES);
+    }
+
+    #[test]
+    fn test_chat_messages_include_emotion_and_top_memories() {
+        let memories: Vec<Arc<MemoryEntry>> = (0..7)
+            .map(|i| Arc::new(MemoryEntry::new(MemoryType::LongTerm, format!("记忆{}", i), vec![], 0.5)))
+            .collect();
+        let emotion = EmotionalState::default();
+
+        let messages = chat_messages("想你了", &memories, &emotion);
+        assert_eq!(messages[0].role, ChatRole::System);
+        assert!(messages[0].content.contains(&format!("心情: {}", emotion.mood)));
+        assert!(messages[1].content.contains("- 记忆4") && !messages[1].content.contains("- 记忆5"));
+        assert!(messages[1].content.ends_with("用户说：想你了"));
+
+        assert!(chat_messages("你好", &[], &emotion)[1].content.contains("暂无相关记忆。"));
+    }
+
+    #[test]
+    fn test_assembler_respects_token_budget() {
+        let memories: Vec<Arc<MemoryEntry>> = (0..3)
+            .map(|i| Arc::new(MemoryEntry::new(MemoryType::LongTerm, format!("{}{}", i, "海".repeat(300)), vec![], 0.5)))
+            .collect();
+        let emotion = EmotionalState::default();
+        let budget = PromptBudget { max_input_tokens: 700, reply_tokens: 100, ..PromptBudget::default() };
+        let assembler = PromptAssembler::new(budget.clone())
+            .with_personality(PersonalityProfile::create_lively_girlfriend());
+
+        // 每条记忆截断到200字，预算只够放下前两条
+        let prompt = assembler.assemble("想你了", &memories, &emotion);
+        assert!(prompt.messages[0].content.starts_with(&format!("你是{}", PersonalityProfile::create_lively_girlfriend().name)));
+        assert_eq!(prompt.memory_ids, vec![memories[0].id, memories[1].id]);
+        assert!(prompt.truncated);
+        assert!(prompt.estimated_tokens <= budget.max_input_tokens - budget.reply_tokens);
+        assert!(prompt.messages[1].content.contains('…'));
+
+        // 超长输入截断，记忆全部放不下
+        let prompt = assembler.assemble(&"说".repeat(2000), &memories, &emotion);
+        assert!(prompt.memory_ids.is_empty() && prompt.truncated);
+        assert!(prompt.estimated_tokens <= budget.max_input_tokens - budget.reply_tokens);
+
+        assert_eq!(estimate_tokens("你好 hello"), 3);
+    }
+
+    #[test]
+    fn test_history_goes_before_input_within_budget() {
+        let history = vec![
+            ChatTurn::new(ChatRole::User, "早".repeat(100)),
+            ChatTurn::new(ChatRole::User, "今天去看海了"),
+            ChatTurn::new(ChatRole::Assistant, "真好呀~"),
+        ];
+        let budget = PromptBudget { history_tokens: 20, ..PromptBudget::default() };
+
+        let prompt = PromptAssembler::new(budget)
+            .assemble_with_history("还记得吗", &history, &[], &EmotionalState::default());
+        let roles: Vec<ChatRole> = prompt.messages.iter().map(|message| message.role).collect();
+        assert_eq!(roles, vec![ChatRole::System, ChatRole::User, ChatRole::Assistant, ChatRole::User]);
+        assert_eq!(prompt.messages[1].content, "今天去看海了");
+        assert_eq!(prompt.history_turns, 2);
+        assert!(prompt.truncated);
+    }
+
+    #[test]
+    fn test_parse_model_replies() {
+        let emotion = parse_emotion_reply(
+            "```json\n{\"happiness\":1.4,\"affection\":0.6,\"trust\":0.5,\"dependency\":0.3,\"mood\":\"开心\"}\n```"
+        ).unwrap();
+        assert_eq!((emotion.happiness, emotion.mood.as_str()), (1.0, "开心"));
+
+        assert_eq!(parse_keywords_reply("[\"看海\"]", "周末 去 看海"), vec!["看海"]);
+        assert_eq!(parse_keywords_reply("周末，看海", "周末 去 看海"), vec!["周末", "看海"]);
+        assert_eq!(parse_importance_reply("重要性: 0.8").unwrap(), 0.8);
+        assert!(parse_importance_reply("很重要").is_err());
+    }
+}
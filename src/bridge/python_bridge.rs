@@ -1,27 +1,92 @@
 //! MIRA Python推理层桥接
 //! My Intelligent Romantic Assistant - 调用Python的AI推理服务
 
+use super::circuit_breaker::{CircuitBreaker, CircuitBreakerConfig};
+use super::history::ChatTurn;
+use super::http::HttpClientConfig;
+use super::inference::{local_keywords, InferenceClient, TokenStream};
+use super::prompt::{ChatMessage, PromptAssembler};
+use super::sse::{self, SseStep};
+use crate::vector_store::RetryPolicy;
 use crate::{MemoryEntry, EmotionalState, Result, MemoryError};
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::process::Command as AsyncCommand;
 
 /// Python推理请求
 #[derive(Debug, Serialize)]
 pub struct InferenceRequest {
     pub text: String,
-    pub context: Option<Vec<MemoryEntry>>,
+    /// 批量任务的输入文本
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub texts: Option<Vec<String>>,
+    pub context: Option<Vec<Arc<MemoryEntry>>>,
     pub emotional_state: Option<EmotionalState>,
     pub task_type: InferenceTaskType,
+    /// 指定处理该任务的模型，未设置时由服务端使用默认模型
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// 组装好的对话消息，设置后服务端直接使用，不再根据上下文自行构建提示
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub messages: Option<Vec<ChatMessage>>,
+    /// Base64编码的图片
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
 }
 
 /// 推理任务类型
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum InferenceTaskType {
     GenerateEmbedding,
+    GenerateEmbeddings,
     GenerateResponse,
     AnalyzeEmotion,
     ExtractKeywords,
     CalculateImportance,
+    GenerateImageEmbedding,
+    Rerank,
+}
+
+/// 单类任务的模型和超时配置，未设置的项使用客户端默认值
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TaskConfig {
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub timeout_seconds: Option<u64>,
+}
+
+/// 推理服务返回的错误码
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InferenceErrorCode {
+    ModelOverloaded,
+    ContextTooLong,
+    InvalidInput,
+    /// 其他服务端错误，包括无法识别的错误码
+    #[serde(other)]
+    Internal,
+}
+
+/// 推理服务返回的错误
+#[derive(Debug, Clone, Deserialize)]
+pub struct InferenceErrorDetail {
+    pub code: InferenceErrorCode,
+    pub message: String,
+}
+
+impl From<InferenceErrorDetail> for MemoryError {
+    fn from(error: InferenceErrorDetail) -> Self {
+        match error.code {
+            InferenceErrorCode::ModelOverloaded => MemoryError::ModelOverloaded(error.message),
+            InferenceErrorCode::ContextTooLong => MemoryError::ContextTooLong(error.message),
+            InferenceErrorCode::InvalidInput => MemoryError::InvalidInput(error.message),
+            InferenceErrorCode::Internal => MemoryError::InferenceError(error.message),
+        }
+    }
 }
 
 /// Python推理响应
@@ -29,15 +94,77 @@ pub enum InferenceTaskType {
 pub struct InferenceResponse {
     pub success: bool,
     pub result: serde_json::Value,
-    pub error: Option<String>,
+    pub error: Option<InferenceErrorDetail>,
     pub processing_time_ms: u64,
 }
 
+impl InferenceResponse {
+    /// 失败响应对应的错误，服务端未给出错误信息时使用`fallback`
+    fn into_error(self, fallback: &str) -> MemoryError {
+        match self.error {
+            Some(error) => error.into(),
+            None => MemoryError::InferenceError(fallback.to_string()),
+        }
+    }
+}
+
+/// 单次请求的错误 - `transient`表示换个时间重试可能成功
+#[derive(Debug)]
+struct RequestError {
+    error: MemoryError,
+    transient: bool,
+}
+
+impl RequestError {
+    /// 连接失败、超时、5xx和429是暂时的；其他4xx和响应解析失败重试也不会成功
+    fn http(context: &str, e: reqwest::Error) -> Self {
+        let transient = !e.is_decode()
+            && e.status().is_none_or(|status| {
+                status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            });
+        Self {
+            error: MemoryError::InferenceError(format!("{}: {}", context, e)),
+            transient,
+        }
+    }
+}
+
+impl std::fmt::Display for RequestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+/// 推理服务的模型能力 - 启动时握手获取
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InferenceCapabilities {
+    /// 嵌入模型名称
+    pub embedding_model: String,
+    /// 嵌入向量维度
+    pub embedding_dimension: usize,
+    /// 对话模型名称
+    #[serde(default)]
+    pub chat_model: Option<String>,
+}
+
+/// 单个批量嵌入请求的默认文本数上限
+const DEFAULT_EMBEDDING_BATCH_SIZE: usize = 64;
+
 /// Python推理客户端
+///
+/// 请求失败时按`RetryPolicy`重试；连续失败达到阈值后熔断，熔断期间快速失败，
+/// 情感分析和关键词提取改用本地降级实现。
 #[derive(Debug)]
 pub struct PythonInferenceClient {
     python_service_url: String,
     timeout_seconds: u64,
+    task_timeouts: HashMap<InferenceTaskType, Duration>,
+    task_models: HashMap<InferenceTaskType, String>,
+    retry: RetryPolicy,
+    breaker: CircuitBreaker,
+    http: reqwest::Client,
+    embedding_batch_size: usize,
+    prompt: PromptAssembler,
 }
 
 impl PythonInferenceClient {
@@ -46,16 +173,281 @@ impl PythonInferenceClient {
         Self {
             python_service_url: service_url,
             timeout_seconds,
+            task_timeouts: HashMap::new(),
+            task_models: HashMap::new(),
+            retry: RetryPolicy::default(),
+            breaker: CircuitBreaker::default(),
+            http: HttpClientConfig::default().build().unwrap_or_else(|e| {
+                tracing::warn!("默认HTTP客户端配置无效，改用无鉴权客户端: {}", e);
+                reqwest::Client::new()
+            }),
+            embedding_batch_size: DEFAULT_EMBEDDING_BATCH_SIZE,
+            prompt: PromptAssembler::default(),
+        }
+    }
+
+    /// 设置单个批量嵌入请求的文本数上限
+    pub fn with_embedding_batch_size(mut self, batch_size: usize) -> Self {
+        self.embedding_batch_size = batch_size.max(1);
+        self
+    }
+
+    /// 设置回复生成的提示组装方式 - 人设和token预算
+    pub fn with_prompt_assembler(mut self, prompt: PromptAssembler) -> Self {
+        self.prompt = prompt;
+        self
+    }
+
+    /// 设置重试策略
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// 为某类任务单独设置超时，未设置的任务使用默认超时
+    pub fn with_task_timeout(mut self, task_type: InferenceTaskType, timeout: Duration) -> Self {
+        self.task_timeouts.insert(task_type, timeout);
+        self
+    }
+
+    /// 为某类任务指定模型，如关键词提取用小模型、回复生成用大模型
+    pub fn with_task_model(mut self, task_type: InferenceTaskType, model: impl Into<String>) -> Self {
+        self.task_models.insert(task_type, model.into());
+        self
+    }
+
+    /// 批量应用任务配置
+    pub fn with_task_configs(mut self, tasks: &HashMap<InferenceTaskType, TaskConfig>) -> Self {
+        for (&task_type, config) in tasks {
+            if let Some(ref model) = config.model {
+                self = self.with_task_model(task_type, model.clone());
+            }
+            if let Some(timeout_seconds) = config.timeout_seconds {
+                self = self.with_task_timeout(task_type, Duration::from_secs(timeout_seconds));
+            }
+        }
+        self
+    }
+
+    /// 按连接池配置重建HTTP客户端
+    pub fn with_http_config(mut self, config: &HttpClientConfig) -> Result<Self> {
+        self.http = config.build()?;
+        Ok(self)
+    }
+
+    /// 使用外部HTTP客户端 - 多个推理客户端可共享同一个连接池
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// 设置熔断器
+    pub fn with_circuit_breaker(mut self, config: CircuitBreakerConfig) -> Self {
+        self.breaker = CircuitBreaker::new(config);
+        self
+    }
+
+    /// 熔断器是否打开 - 打开时请求不会发往Python服务
+    pub fn is_circuit_open(&self) -> bool {
+        self.breaker.is_open()
+    }
+
+    /// 任务的超时时间
+    fn timeout_for(&self, task_type: InferenceTaskType) -> Duration {
+        self.task_timeouts.get(&task_type)
+            .copied()
+            .unwrap_or(Duration::from_secs(self.timeout_seconds))
+    }
+
+    /// 按预算组装回复生成的消息
+    fn chat_messages(
+        &self,
+        user_input: &str,
+        history: &[ChatTurn],
+        context: &[Arc<MemoryEntry>],
+        emotional_state: &EmotionalState,
+    ) -> Vec<ChatMessage> {
+        let prompt = self.prompt.assemble_with_history(user_input, history, context, emotional_state);
+        if prompt.truncated {
+            tracing::debug!(
+                "提示超出预算已截断: 保留 {}/{} 条对话, {}/{} 条记忆, 约 {} tokens",
+                prompt.history_turns, history.len(), prompt.memory_ids.len(), context.len(), prompt.estimated_tokens
+            );
+        }
+        prompt.messages
+    }
+
+    /// 任务指定的模型
+    fn model_for(&self, task_type: InferenceTaskType) -> Option<String> {
+        self.task_models.get(&task_type).cloned()
+    }
+
+    /// 建立流式连接 - 超时只限制等待响应头的时间，生成过程可能更久
+    #[tracing::instrument(name = "python_inference_stream", skip_all, fields(task = ?request.task_type))]
+    async fn open_stream(&self, request: &InferenceRequest, timeout: Duration) -> std::result::Result<reqwest::Response, RequestError> {
+        let url = format!("{}/inference/stream", self.python_service_url);
+
+        tokio::time::timeout(timeout, self.http.post(&url).json(request).send())
+            .await
+            .map_err(|_| RequestError {
+                error: MemoryError::InferenceError(format!("等待流式响应超时: {:?}", timeout)),
+                transient: true,
+            })?
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| RequestError::http("HTTP请求失败", e))
+    }
+
+    /// 将SSE响应体转换为文本片段流，收到done事件时结束
+    fn token_stream(response: reqwest::Response) -> TokenStream {
+        sse::token_stream(response, |event| match event.event.as_deref() {
+            Some("done") => SseStep::Done,
+            Some("error") => SseStep::Fail(
+                match serde_json::from_str::<InferenceErrorDetail>(&event.data) {
+                    Ok(error) => error.into(),
+                    Err(_) => MemoryError::InferenceError(
+                        serde_json::from_str::<String>(&event.data).unwrap_or(event.data)
+                    ),
+                }
+            ),
+            _ => match serde_json::from_str::<String>(&event.data) {
+                Ok(token) => SseStep::Token(token),
+                Err(e) => SseStep::Fail(e.into()),
+            },
+        })
+    }
+
+    /// 调用Python推理服务 - 熔断时快速失败，否则按重试策略发送
+    async fn call_python_service(&self, request: InferenceRequest) -> Result<InferenceResponse> {
+        if !self.breaker.allow_request() {
+            return Err(MemoryError::InferenceUnavailable("熔断器已打开".to_string()));
+        }
+
+        let timeout = self.timeout_for(request.task_type);
+        // 推理任务没有副作用，总是可以重试，但只重试暂时性错误
+        let result = self.retry.run_if(true, |e: &RequestError| e.transient, || self.send_request(&request, timeout)).await;
+        self.record_outcome(result)
+    }
+
+    /// 记录到熔断器 - 服务有响应但请求本身有误时不算作服务故障
+    fn record_outcome<T>(&self, result: std::result::Result<T, RequestError>) -> Result<T> {
+        match result {
+            Ok(value) => {
+                self.breaker.record_success();
+                Ok(value)
+            }
+            Err(e) => {
+                if e.transient {
+                    self.breaker.record_failure();
+                } else {
+                    self.breaker.record_success();
+                }
+                Err(e.error)
+            }
+        }
+    }
+
+    /// 发送单次推理请求
+    #[tracing::instrument(name = "python_inference", skip_all, fields(task = ?request.task_type))]
+    async fn send_request(&self, request: &InferenceRequest, timeout: Duration) -> std::result::Result<InferenceResponse, RequestError> {
+        let url = format!("{}/inference", self.python_service_url);
+        
+        let response = self.http
+            .post(&url)
+            .timeout(timeout)
+            .json(request)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| RequestError::http("HTTP请求失败", e))?;
+
+        let inference_response: InferenceResponse = response
+            .json()
+            .await
+            .map_err(|e| RequestError::http("响应解析失败", e))?;
+
+        // 模型过载时作为错误返回，交给重试策略退避后重试
+        if !inference_response.success
+            && inference_response.error.as_ref().is_some_and(|e| e.code == InferenceErrorCode::ModelOverloaded)
+        {
+            return Err(RequestError {
+                error: inference_response.into_error("推理模型过载"),
+                transient: true,
+            });
         }
+
+        Ok(inference_response)
+    }
+
+    /// 查询推理服务的模型能力，按嵌入任务配置的模型查询维度
+    pub async fn capabilities(&self) -> Result<InferenceCapabilities> {
+        let url = format!("{}/capabilities", self.python_service_url);
+        let mut request = self.http
+            .get(&url)
+            .timeout(Duration::from_secs(self.timeout_seconds));
+        if let Some(model) = self.model_for(InferenceTaskType::GenerateEmbedding) {
+            request = request.query(&[("embedding_model", model)]);
+        }
+
+        request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| MemoryError::InferenceError(format!("能力查询失败: {}", e)))?
+            .json()
+            .await
+            .map_err(|e| MemoryError::InferenceError(format!("能力响应解析失败: {}", e)))
+    }
+
+    /// 启动握手 - 嵌入维度与向量存储配置不一致时直接报错，
+    /// 避免集合中混入不同维度的向量。`expected`为None时只做查询
+    pub async fn verify_embedding_dimension(&self, expected: Option<usize>) -> Result<InferenceCapabilities> {
+        let capabilities = self.capabilities().await?;
+        if let Some(expected) = expected {
+            if capabilities.embedding_dimension != expected {
+                tracing::error!(
+                    "嵌入模型 {} 输出 {} 维向量，向量存储配置为 {} 维",
+                    capabilities.embedding_model, capabilities.embedding_dimension, expected
+                );
+                return Err(MemoryError::DimensionMismatch {
+                    expected,
+                    actual: capabilities.embedding_dimension,
+                });
+            }
+        }
+        Ok(capabilities)
+    }
+
+    /// 启动Python推理服务
+    pub async fn start_python_service(&self, script_path: &str) -> Result<()> {
+        let _output = AsyncCommand::new("python3.14")  // 使用最新Python版本
+            .arg(script_path)
+            .arg("--port")
+            .arg("8000")
+            .arg("--host")
+            .arg("127.0.0.1")
+            .spawn()
+            .map_err(|e| MemoryError::InferenceError(format!("Python服务启动失败: {}", e)))?;
+
+        // 等待服务启动
+        tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
+        
+        Ok(())
     }
+}
 
+#[async_trait]
+impl InferenceClient for PythonInferenceClient {
     /// 生成文本嵌入向量
-    pub async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>> {
+    async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>> {
         let request = InferenceRequest {
             text: text.to_string(),
+            texts: None,
             context: None,
             emotional_state: None,
             task_type: InferenceTaskType::GenerateEmbedding,
+            model: self.model_for(InferenceTaskType::GenerateEmbedding),
+            messages: None,
+            image: None,
         };
 
         let response = self.call_python_service(request).await?;
@@ -65,24 +457,95 @@ impl PythonInferenceClient {
                 .map_err(|e| MemoryError::SerializationError(e))?;
             Ok(embedding)
         } else {
-            Err(MemoryError::DatabaseError(
-                response.error.unwrap_or("Python推理服务错误".to_string())
-            ))
+            Err(response.into_error("Python推理服务错误"))
+        }
+    }
+
+    async fn generate_image_embedding(&self, image: &[u8]) -> Result<Vec<f32>> {
+        use base64::Engine;
+
+        let request = InferenceRequest {
+            text: String::new(),
+            texts: None,
+            context: None,
+            emotional_state: None,
+            task_type: InferenceTaskType::GenerateImageEmbedding,
+            model: self.model_for(InferenceTaskType::GenerateImageEmbedding),
+            messages: None,
+            image: Some(base64::engine::general_purpose::STANDARD.encode(image)),
+        };
+
+        let response = self.call_python_service(request).await?;
+        if response.success {
+            Ok(serde_json::from_value(response.result)?)
+        } else {
+            Err(response.into_error("Python推理服务错误"))
+        }
+    }
+
+    /// 批量生成嵌入向量 - 每`embedding_batch_size`条文本只发送一次请求，结果与输入一一对应
+    async fn generate_embeddings(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+
+        for chunk in texts.chunks(self.embedding_batch_size) {
+            let request = InferenceRequest {
+                text: String::new(),
+                texts: Some(chunk.to_vec()),
+                context: None,
+                emotional_state: None,
+                task_type: InferenceTaskType::GenerateEmbeddings,
+                model: self.model_for(InferenceTaskType::GenerateEmbeddings),
+                messages: None,
+                    image: None,
+            };
+
+            let response = self.call_python_service(request).await?;
+            if !response.success {
+                return Err(response.into_error("批量嵌入生成失败"));
+            }
+
+            let batch: Vec<Vec<f32>> = serde_json::from_value(response.result)?;
+            if batch.len() != chunk.len() {
+                return Err(MemoryError::InferenceError(format!(
+                    "批量嵌入数量不匹配: 请求 {} 条, 返回 {} 条",
+                    chunk.len(),
+                    batch.len()
+                )));
+            }
+            embeddings.extend(batch);
         }
+
+        Ok(embeddings)
     }
 
     /// 生成情感化回复
-    pub async fn generate_response(
+    async fn generate_response(
         &self,
         user_input: &str,
-        context: Vec<MemoryEntry>,
+        context: Vec<Arc<MemoryEntry>>,
         emotional_state: EmotionalState,
     ) -> Result<String> {
+        self.generate_response_with_history(user_input, Vec::new(), context, emotional_state).await
+    }
+
+    /// 带多轮对话历史生成回复
+    async fn generate_response_with_history(
+        &self,
+        user_input: &str,
+        history: Vec<ChatTurn>,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<String> {
+        let messages = self.chat_messages(user_input, &history, &context, &emotional_state);
         let request = InferenceRequest {
             text: user_input.to_string(),
-            context: Some(context),
+            texts: None,
+            context: None,
             emotional_state: Some(emotional_state),
             task_type: InferenceTaskType::GenerateResponse,
+            model: self.model_for(InferenceTaskType::GenerateResponse),
+            messages: Some(messages),
+            image: None,
         };
 
         let response = self.call_python_service(request).await?;
@@ -92,100 +555,170 @@ impl PythonInferenceClient {
                 .map_err(|e| MemoryError::SerializationError(e))?;
             Ok(response_text)
         } else {
-            Err(MemoryError::DatabaseError(
-                response.error.unwrap_or("回复生成失败".to_string())
-            ))
+            Err(response.into_error("回复生成失败"))
+        }
+    }
+
+    /// 流式生成情感化回复
+    async fn generate_response_stream(
+        &self,
+        user_input: &str,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<TokenStream> {
+        self.generate_response_stream_with_history(user_input, Vec::new(), context, emotional_state).await
+    }
+
+    /// 带多轮对话历史流式生成回复 - 文本片段生成后立即产出
+    ///
+    /// 只有建立连接阶段会重试并计入熔断器；流开始后的错误作为流的最后一项返回。
+    async fn generate_response_stream_with_history(
+        &self,
+        user_input: &str,
+        history: Vec<ChatTurn>,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<TokenStream> {
+        if !self.breaker.allow_request() {
+            return Err(MemoryError::InferenceUnavailable("熔断器已打开".to_string()));
         }
+
+        let messages = self.chat_messages(user_input, &history, &context, &emotional_state);
+        let request = InferenceRequest {
+            text: user_input.to_string(),
+            texts: None,
+            context: None,
+            emotional_state: Some(emotional_state),
+            task_type: InferenceTaskType::GenerateResponse,
+            model: self.model_for(InferenceTaskType::GenerateResponse),
+            messages: Some(messages),
+            image: None,
+        };
+        let timeout = self.timeout_for(InferenceTaskType::GenerateResponse);
+        let result = self.retry.run_if(true, |e: &RequestError| e.transient, || self.open_stream(&request, timeout)).await;
+        Ok(Self::token_stream(self.record_outcome(result)?))
     }
 
     /// 分析用户情感
-    pub async fn analyze_emotion(&self, text: &str) -> Result<EmotionalState> {
+    async fn analyze_emotion(&self, text: &str) -> Result<EmotionalState> {
         let request = InferenceRequest {
             text: text.to_string(),
+            texts: None,
             context: None,
             emotional_state: None,
             task_type: InferenceTaskType::AnalyzeEmotion,
+            model: self.model_for(InferenceTaskType::AnalyzeEmotion),
+            messages: None,
+            image: None,
         };
 
-        let response = self.call_python_service(request).await?;
+        let response = match self.call_python_service(request).await {
+            Ok(response) => response,
+            Err(MemoryError::InferenceUnavailable(reason)) => {
+                tracing::debug!("情感分析使用本地降级: {}", reason);
+                return Ok(EmotionalState::default());
+            }
+            Err(e) => return Err(e),
+        };
         
         if response.success {
             let emotion: EmotionalState = serde_json::from_value(response.result)
                 .map_err(|e| MemoryError::SerializationError(e))?;
             Ok(emotion)
         } else {
-            Err(MemoryError::DatabaseError(
-                response.error.unwrap_or("情感分析失败".to_string())
-            ))
+            Err(response.into_error("情感分析失败"))
         }
     }
 
     /// 提取关键词
-    pub async fn extract_keywords(&self, text: &str) -> Result<Vec<String>> {
+    async fn extract_keywords(&self, text: &str) -> Result<Vec<String>> {
         let request = InferenceRequest {
             text: text.to_string(),
+            texts: None,
             context: None,
             emotional_state: None,
             task_type: InferenceTaskType::ExtractKeywords,
+            model: self.model_for(InferenceTaskType::ExtractKeywords),
+            messages: None,
+            image: None,
         };
 
-        let response = self.call_python_service(request).await?;
+        let response = match self.call_python_service(request).await {
+            Ok(response) => response,
+            Err(MemoryError::InferenceUnavailable(reason)) => {
+                tracing::debug!("关键词提取使用本地降级: {}", reason);
+                return Ok(local_keywords(text));
+            }
+            Err(e) => return Err(e),
+        };
         
         if response.success {
             let keywords: Vec<String> = serde_json::from_value(response.result)
                 .map_err(|e| MemoryError::SerializationError(e))?;
             Ok(keywords)
         } else {
-            Err(MemoryError::DatabaseError(
-                response.error.unwrap_or("关键词提取失败".to_string())
-            ))
+            Err(response.into_error("关键词提取失败"))
         }
     }
 
-    /// 调用Python推理服务
-    async fn call_python_service(&self, request: InferenceRequest) -> Result<InferenceResponse> {
-        let client = reqwest::Client::new();
-        let url = format!("{}/inference", self.python_service_url);
-        
-        let response = client
-            .post(&url)
-            .timeout(std::time::Duration::from_secs(self.timeout_seconds))
-            .json(&request)
-            .send()
-            .await
-            .map_err(|e| MemoryError::DatabaseError(format!("HTTP请求失败: {}", e)))?;
+    /// 评估记忆重要性
+    async fn calculate_importance(&self, text: &str, emotional_state: Option<EmotionalState>) -> Result<f32> {
+        let request = InferenceRequest {
+            text: text.to_string(),
+            texts: None,
+            context: None,
+            emotional_state,
+            task_type: InferenceTaskType::CalculateImportance,
+            model: self.model_for(InferenceTaskType::CalculateImportance),
+            messages: None,
+            image: None,
+        };
 
-        let inference_response: InferenceResponse = response
-            .json()
-            .await
-            .map_err(|e| MemoryError::DatabaseError(format!("响应解析失败: {}", e)))?;
+        let response = self.call_python_service(request).await?;
 
-        Ok(inference_response)
+        if response.success {
+            let importance: f32 = serde_json::from_value(response.result)?;
+            Ok(importance.clamp(0.0, 1.0))
+        } else {
+            Err(response.into_error("重要性评估失败"))
+        }
     }
 
-    /// 启动Python推理服务
-    pub async fn start_python_service(&self, script_path: &str) -> Result<()> {
-        let _output = AsyncCommand::new("python3.14")  // 使用最新Python版本
-            .arg(script_path)
-            .arg("--port")
-            .arg("8000")
-            .arg("--host")
-            .arg("127.0.0.1")
-            .spawn()
-            .map_err(|e| MemoryError::DatabaseError(format!("Python服务启动失败: {}", e)))?;
+    /// 交叉编码器重排 - 查询放在`text`，文档放在`texts`
+    async fn rerank(&self, query: &str, documents: Vec<String>) -> Result<Vec<f32>> {
+        let expected = documents.len();
+        let request = InferenceRequest {
+            text: query.to_string(),
+            texts: Some(documents),
+            context: None,
+            emotional_state: None,
+            task_type: InferenceTaskType::Rerank,
+            model: self.model_for(InferenceTaskType::Rerank),
+            messages: None,
+            image: None,
+        };
 
-        // 等待服务启动
-        tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
-        
-        Ok(())
+        let response = self.call_python_service(request).await?;
+        if !response.success {
+            return Err(response.into_error("重排失败"));
+        }
+
+        let scores: Vec<f32> = serde_json::from_value(response.result)?;
+        if scores.len() != expected {
+            return Err(MemoryError::InferenceError(format!(
+                "重排分数数量不匹配: 请求 {} 条, 返回 {} 条",
+                expected,
+                scores.len()
+            )));
+        }
+        Ok(scores.into_iter().map(|score| score.clamp(0.0, 1.0)).collect())
     }
 
     /// 检查Python服务健康状态
-    pub async fn health_check(&self) -> bool {
-        let client = reqwest::Client::new();
+    async fn health_check(&self) -> bool {
         let url = format!("{}/health", self.python_service_url);
         
-        if let Ok(response) = client.get(&url).send().await {
+        if let Ok(response) = self.http.get(&url).send().await {
             response.status().is_success()
         } else {
             false
@@ -196,6 +729,7 @@ impl PythonInferenceClient {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use futures::StreamExt;
 
     #[tokio::test]
     async fn test_python_client_creation() {
@@ -207,4 +741,276 @@ mod tests {
         assert_eq!(client.python_service_url, "http://localhost:8000");
         assert_eq!(client.timeout_seconds, 30);
     }
+
+    fn success_body(result: serde_json::Value) -> serde_json::Value {
+        serde_json::json!({ "success": true, "result": result, "error": null, "processing_time_ms": 1 })
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_failures() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).and(path("/inference"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST")).and(path("/inference"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(success_body(serde_json::json!(["猫咪"]))))
+            .mount(&server)
+            .await;
+
+        let client = PythonInferenceClient::new(server.uri(), 5).with_retry(RetryPolicy {
+            initial_backoff_ms: 1,
+            max_backoff_ms: 2,
+            jitter: false,
+            ..Default::default()
+        });
+
+        assert_eq!(client.extract_keywords("我喜欢猫咪").await.unwrap(), vec!["猫咪"]);
+        assert!(!client.is_circuit_open());
+    }
+
+    #[tokio::test]
+    async fn test_capabilities_handshake_checks_dimension() {
+        use wiremock::matchers::{method, path, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).and(path("/capabilities"))
+            .and(query_param("embedding_model", "bge-small-zh"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "embedding_model": "bge-small-zh",
+                "embedding_dimension": 512,
+            })))
+            .mount(&server)
+            .await;
+
+        let client = PythonInferenceClient::new(server.uri(), 5)
+            .with_task_model(InferenceTaskType::GenerateEmbedding, "bge-small-zh");
+
+        let capabilities = client.verify_embedding_dimension(Some(512)).await.unwrap();
+        assert_eq!(capabilities.embedding_model, "bge-small-zh");
+        assert_eq!(capabilities.chat_model, None);
+        assert!(matches!(
+            client.verify_embedding_dimension(Some(768)).await,
+            Err(MemoryError::DimensionMismatch { expected: 768, actual: 512 })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_error_codes_map_to_memory_errors() {
+        use wiremock::matchers::{body_partial_json, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        fn failure_body(code: &str) -> serde_json::Value {
+            serde_json::json!({
+                "success": false, "result": null, "processing_time_ms": 1,
+                "error": { "code": code, "message": "失败" }
+            })
+        }
+
+        let server = MockServer::start().await;
+        // 过载时重试，其余错误码直接返回
+        Mock::given(method("POST")).and(path("/inference"))
+            .and(body_partial_json(serde_json::json!({ "task_type": "ExtractKeywords" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(failure_body("model_overloaded")))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST")).and(path("/inference"))
+            .and(body_partial_json(serde_json::json!({ "task_type": "ExtractKeywords" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(success_body(serde_json::json!(["猫咪"]))))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST")).and(path("/inference"))
+            .and(body_partial_json(serde_json::json!({ "task_type": "GenerateResponse" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(failure_body("context_too_long")))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST")).and(path("/inference"))
+            .and(body_partial_json(serde_json::json!({ "task_type": "CalculateImportance" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(failure_body("gpu_on_fire")))
+            .mount(&server)
+            .await;
+
+        let client = PythonInferenceClient::new(server.uri(), 5).with_retry(RetryPolicy {
+            initial_backoff_ms: 1,
+            max_backoff_ms: 2,
+            jitter: false,
+            ..Default::default()
+        });
+
+        assert_eq!(client.extract_keywords("我喜欢猫咪").await.unwrap(), vec!["猫咪"]);
+        assert!(matches!(
+            client.generate_response("你好", vec![], EmotionalState::default()).await,
+            Err(MemoryError::ContextTooLong(_))
+        ));
+        assert!(matches!(client.calculate_importance("你好", None).await, Err(MemoryError::InferenceError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_response_sends_history_once_in_messages() {
+        use crate::bridge::ChatRole;
+        use wiremock::matchers::{body_partial_json, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).and(path("/inference"))
+            .and(body_partial_json(serde_json::json!({
+                "messages": [{ "role": "system" }, { "role": "user", "content": "今天去看海了" }],
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(success_body(serde_json::json!("记得呀~"))))
+            .mount(&server)
+            .await;
+
+        let client = PythonInferenceClient::new(server.uri(), 5);
+        let history = vec![ChatTurn::new(ChatRole::User, "今天去看海了")];
+        let reply = client
+            .generate_response_with_history("还记得吗", history, vec![], EmotionalState::default())
+            .await
+            .unwrap();
+        assert_eq!(reply, "记得呀~");
+
+        // 历史只随`messages`发送一次
+        let requests = server.received_requests().await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert!(body.get("history").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_requests_carry_api_key() {
+        use wiremock::matchers::{header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).and(path("/inference")).and(header("authorization", "Bearer secret"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(success_body(serde_json::json!(["猫咪"]))))
+            .mount(&server)
+            .await;
+
+        let client = PythonInferenceClient::new(server.uri(), 5)
+            .with_http_config(&HttpClientConfig {
+                api_key: Some("secret".to_string()),
+                ..HttpClientConfig::default()
+            })
+            .unwrap();
+
+        assert_eq!(client.extract_keywords("我喜欢猫咪").await.unwrap(), vec!["猫咪"]);
+    }
+
+    #[tokio::test]
+    async fn test_task_config_selects_model_and_timeout() {
+        use wiremock::matchers::{body_partial_json, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).and(path("/inference"))
+            .and(body_partial_json(serde_json::json!({ "task_type": "ExtractKeywords", "model": "qwen3-0.6b" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(success_body(serde_json::json!(["猫咪"]))))
+            .mount(&server)
+            .await;
+
+        let tasks = HashMap::from([(InferenceTaskType::ExtractKeywords, TaskConfig {
+            model: Some("qwen3-0.6b".to_string()),
+            timeout_seconds: Some(2),
+        })]);
+        let client = PythonInferenceClient::new(server.uri(), 30).with_task_configs(&tasks);
+
+        assert_eq!(client.extract_keywords("我喜欢猫咪").await.unwrap(), vec!["猫咪"]);
+        assert_eq!(client.timeout_for(InferenceTaskType::ExtractKeywords), Duration::from_secs(2));
+        assert_eq!(client.timeout_for(InferenceTaskType::GenerateResponse), Duration::from_secs(30));
+        assert_eq!(client.model_for(InferenceTaskType::GenerateResponse), None);
+    }
+
+    #[tokio::test]
+    async fn test_generate_response_stream() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).and(path("/inference/stream"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                "data: \"你好\"\n\ndata: \"呀~\"\n\nevent: done\ndata: {}\n\n",
+                "text/event-stream",
+            ))
+            .mount(&server)
+            .await;
+
+        let client = PythonInferenceClient::new(server.uri(), 5);
+        let tokens: Vec<String> = client.generate_response_stream("你好", vec![], EmotionalState::default())
+            .await
+            .unwrap()
+            .map(|token| token.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(tokens, vec!["你好", "呀~"]);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_falls_back_locally() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&server)
+            .await;
+
+        let client = PythonInferenceClient::new(server.uri(), 5)
+            .with_retry(RetryPolicy::none())
+            .with_circuit_breaker(CircuitBreakerConfig { failure_threshold: 2, open_duration_ms: 60_000 });
+
+        for _ in 0..2 {
+            assert!(matches!(client.extract_keywords("周末 去 看海").await, Err(MemoryError::InferenceError(_))));
+        }
+        assert!(client.is_circuit_open());
+
+        // 熔断后不再请求Python服务
+        assert_eq!(client.extract_keywords("周末 去 看海").await.unwrap(), vec!["周末", "看海"]);
+        assert_eq!(client.analyze_emotion("今天很开心").await.unwrap().mood, EmotionalState::default().mood);
+        assert!(matches!(client.generate_embedding("你好").await, Err(MemoryError::InferenceUnavailable(_))));
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_client_errors_are_not_retried() {
+        use wiremock::matchers::{body_partial_json, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).and(path("/inference"))
+            .and(body_partial_json(serde_json::json!({ "task_type": "ExtractKeywords" })))
+            .respond_with(ResponseTemplate::new(422))
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST")).and(path("/inference"))
+            .and(body_partial_json(serde_json::json!({ "task_type": "CalculateImportance" })))
+            .respond_with(ResponseTemplate::new(200).set_body_string("不是JSON"))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let client = PythonInferenceClient::new(server.uri(), 5)
+            .with_retry(RetryPolicy {
+                initial_backoff_ms: 1,
+                max_backoff_ms: 2,
+                jitter: false,
+                ..Default::default()
+            })
+            .with_circuit_breaker(CircuitBreakerConfig { failure_threshold: 2, open_duration_ms: 60_000 });
+
+        // 每次只请求一次，也不会打开熔断器
+        for _ in 0..2 {
+            assert!(matches!(client.extract_keywords("周末 去 看海").await, Err(MemoryError::InferenceError(_))));
+            assert!(matches!(client.calculate_importance("你好", None).await, Err(MemoryError::InferenceError(_))));
+        }
+        assert!(!client.is_circuit_open());
+    }
 }
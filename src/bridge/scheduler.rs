@@ -0,0 +1,325 @@
+//! 推理请求调度
+//!
+//! 限制同时发往推理后端的请求数，排队时优先放行对话回复等交互请求，
+//! 并为交互请求预留名额，批量导入产生的嵌入任务不会占满后端。
+
+use super::history::ChatTurn;
+use super::inference::{InferenceClient, TokenStream};
+use crate::{EmotionalState, MemoryEntry, Result};
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+/// 请求优先级
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InferencePriority {
+    /// 用户正在等待结果 - 回复生成、情感分析
+    Interactive,
+    /// 后台任务 - 嵌入、关键词、重要性评估
+    Background,
+}
+
+/// 调度配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SchedulerConfig {
+    /// 同时进行的推理请求上限
+    pub max_concurrent: usize,
+    /// 只留给交互请求的名额
+    pub reserved_interactive: usize,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: 4,
+            reserved_interactive: 1,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct SchedulerState {
+    running: usize,
+    interactive: VecDeque<oneshot::Sender<SchedulerPermit>>,
+    background: VecDeque<oneshot::Sender<SchedulerPermit>>,
+}
+
+/// 带优先级的并发限制器
+#[derive(Debug)]
+pub struct InferenceScheduler {
+    max_concurrent: usize,
+    background_limit: usize,
+    state: Mutex<SchedulerState>,
+}
+
+/// 执行名额，释放时交给下一个排队的请求
+#[derive(Debug)]
+pub struct SchedulerPermit {
+    scheduler: Option<Arc<InferenceScheduler>>,
+}
+
+impl Drop for SchedulerPermit {
+    fn drop(&mut self) {
+        if let Some(scheduler) = self.scheduler.take() {
+            scheduler.release();
+        }
+    }
+}
+
+impl InferenceScheduler {
+    pub fn new(config: SchedulerConfig) -> Arc<Self> {
+        let max_concurrent = config.max_concurrent.max(1);
+        Arc::new(Self {
+            max_concurrent,
+            background_limit: max_concurrent.saturating_sub(config.reserved_interactive).max(1),
+            state: Mutex::new(SchedulerState::default()),
+        })
+    }
+
+    fn limit(&self, priority: InferencePriority) -> usize {
+        match priority {
+            InferencePriority::Interactive => self.max_concurrent,
+            InferencePriority::Background => self.background_limit,
+        }
+    }
+
+    /// 获取执行名额；同优先级按到达顺序放行
+    pub async fn acquire(self: &Arc<Self>, priority: InferencePriority) -> SchedulerPermit {
+        let receiver = {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            let queue_empty = match priority {
+                InferencePriority::Interactive => state.interactive.is_empty(),
+                InferencePriority::Background => state.interactive.is_empty() && state.background.is_empty(),
+            };
+
+            if queue_empty && state.running < self.limit(priority) {
+                state.running += 1;
+                return SchedulerPermit { scheduler: Some(self.clone()) };
+            }
+
+            let (sender, receiver) = oneshot::channel();
+            match priority {
+                InferencePriority::Interactive => state.interactive.push_back(sender),
+                InferencePriority::Background => state.background.push_back(sender),
+            }
+            receiver
+        };
+
+        // 发送端只在分配名额时取出，不会未发送就被丢弃；
+        // 等待方在名额送达后取消时，名额随接收端一起释放
+        receiver.await.expect("调度器在分配名额前丢弃了等待者")
+    }
+
+    /// 归还名额并按优先级唤醒排队的请求；等待方已取消时名额继续顺延
+    fn release(self: &Arc<Self>) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.running -= 1;
+
+        loop {
+            let next = if !state.interactive.is_empty() && state.running < self.max_concurrent {
+                state.interactive.pop_front()
+            } else if !state.background.is_empty() && state.running < self.background_limit {
+                state.background.pop_front()
+            } else {
+                None
+            };
+
+            let Some(waiter) = next else {
+                break;
+            };
+            state.running += 1;
+            if let Err(mut permit) = waiter.send(SchedulerPermit { scheduler: Some(self.clone()) }) {
+                // 在锁内直接回收，避免Drop再次加锁
+                permit.scheduler = None;
+                state.running -= 1;
+            }
+        }
+    }
+
+    /// 正在执行的请求数
+    pub fn running(&self) -> usize {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).running
+    }
+
+    /// 排队中的请求数：(交互, 后台)
+    pub fn queued(&self) -> (usize, usize) {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        (state.interactive.len(), state.background.len())
+    }
+}
+
+/// 经过调度器限流的推理客户端
+#[derive(Debug)]
+pub struct ScheduledInferenceClient<C> {
+    inner: C,
+    scheduler: Arc<InferenceScheduler>,
+}
+
+impl<C: InferenceClient> ScheduledInferenceClient<C> {
+    pub fn new(inner: C, config: SchedulerConfig) -> Self {
+        Self::with_scheduler(inner, InferenceScheduler::new(config))
+    }
+
+    /// 与其他客户端共享同一个调度器，共同受并发上限约束
+    pub fn with_scheduler(inner: C, scheduler: Arc<InferenceScheduler>) -> Self {
+        Self { inner, scheduler }
+    }
+
+    pub fn scheduler(&self) -> &Arc<InferenceScheduler> {
+        &self.scheduler
+    }
+}
+
+#[async_trait]
+impl<C: InferenceClient> InferenceClient for ScheduledInferenceClient<C> {
+    async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>> {
+        let _permit = self.scheduler.acquire(InferencePriority::Background).await;
+        self.inner.generate_embedding(text).await
+    }
+
+    async fn generate_image_embedding(&self, image: &[u8]) -> Result<Vec<f32>> {
+        let _permit = self.scheduler.acquire(InferencePriority::Background).await;
+        self.inner.generate_image_embedding(image).await
+    }
+
+    async fn generate_embeddings(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let _permit = self.scheduler.acquire(InferencePriority::Background).await;
+        self.inner.generate_embeddings(texts).await
+    }
+
+    async fn generate_response(
+        &self,
+        user_input: &str,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<String> {
+        let _permit = self.scheduler.acquire(InferencePriority::Interactive).await;
+        self.inner.generate_response(user_input, context, emotional_state).await
+    }
+
+    /// 名额一直保持到流被消费完或丢弃
+    async fn generate_response_stream(
+        &self,
+        user_input: &str,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<TokenStream> {
+        let permit = self.scheduler.acquire(InferencePriority::Interactive).await;
+        let tokens = self.inner.generate_response_stream(user_input, context, emotional_state).await?;
+        Ok(tokens.map(move |token| {
+            let _ = &permit;
+            token
+        }).boxed())
+    }
+
+    async fn generate_response_with_history(
+        &self,
+        user_input: &str,
+        history: Vec<ChatTurn>,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<String> {
+        let _permit = self.scheduler.acquire(InferencePriority::Interactive).await;
+        self.inner.generate_response_with_history(user_input, history, context, emotional_state).await
+    }
+
+    async fn generate_response_stream_with_history(
+        &self,
+        user_input: &str,
+        history: Vec<ChatTurn>,
+        context: Vec<Arc<MemoryEntry>>,
+        emotional_state: EmotionalState,
+    ) -> Result<TokenStream> {
+        let permit = self.scheduler.acquire(InferencePriority::Interactive).await;
+        let tokens = self.inner
+            .generate_response_stream_with_history(user_input, history, context, emotional_state)
+            .await?;
+        Ok(tokens.map(move |token| {
+            let _ = &permit;
+            token
+        }).boxed())
+    }
+
+    async fn analyze_emotion(&self, text: &str) -> Result<EmotionalState> {
+        let _permit = self.scheduler.acquire(InferencePriority::Interactive).await;
+        self.inner.analyze_emotion(text).await
+    }
+
+    async fn extract_keywords(&self, text: &str) -> Result<Vec<String>> {
+        let _permit = self.scheduler.acquire(InferencePriority::Background).await;
+        self.inner.extract_keywords(text).await
+    }
+
+    async fn calculate_importance(&self, text: &str, emotional_state: Option<EmotionalState>) -> Result<f32> {
+        let _permit = self.scheduler.acquire(InferencePriority::Background).await;
+        self.inner.calculate_importance(text, emotional_state).await
+    }
+
+    /// 健康检查不占用名额
+    async fn health_check(&self) -> bool {
+        self.inner.health_check().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_interactive_requests_jump_the_queue() {
+        let scheduler = InferenceScheduler::new(SchedulerConfig {
+            max_concurrent: 1,
+            reserved_interactive: 0,
+        });
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let held = scheduler.acquire(InferencePriority::Background).await;
+        let mut tasks = Vec::new();
+        for (name, priority) in [("后台", InferencePriority::Background), ("交互", InferencePriority::Interactive)] {
+            let scheduler = scheduler.clone();
+            let order = order.clone();
+            tasks.push(tokio::spawn(async move {
+                let _permit = scheduler.acquire(priority).await;
+                order.lock().unwrap().push(name);
+            }));
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(scheduler.queued(), (1, 1));
+
+        drop(held);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), vec!["交互", "后台"]);
+        assert_eq!(scheduler.running(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_background_cannot_use_reserved_slot() {
+        let scheduler = InferenceScheduler::new(SchedulerConfig {
+            max_concurrent: 2,
+            reserved_interactive: 1,
+        });
+
+        let background = scheduler.acquire(InferencePriority::Background).await;
+        let blocked = tokio::time::timeout(
+            Duration::from_millis(20),
+            scheduler.acquire(InferencePriority::Background),
+        ).await;
+        assert!(blocked.is_err());
+
+        let interactive = scheduler.acquire(InferencePriority::Interactive).await;
+        assert_eq!(scheduler.running(), 2);
+
+        // 已取消的排队请求不会占用名额
+        drop(interactive);
+        drop(background);
+        assert_eq!(scheduler.running(), 0);
+        assert_eq!(scheduler.queued(), (0, 0));
+    }
+}
@@ -0,0 +1,141 @@
+//! Server-Sent Events解析
+//!
+//! 各推理后端的流式接口都使用text/event-stream，事件含义由调用方解释。
+
+use super::inference::TokenStream;
+use crate::{MemoryError, Result};
+use futures::stream::{self, BoxStream, StreamExt};
+use std::collections::VecDeque;
+
+/// 服务端推送事件
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct SseEvent {
+    pub event: Option<String>,
+    pub data: String,
+}
+
+/// 增量解析text/event-stream - 事件以空行分隔，可能跨多个数据块
+#[derive(Debug, Default)]
+pub(crate) struct SseDecoder {
+    buffer: Vec<u8>,
+}
+
+impl SseDecoder {
+    /// 追加数据块，返回其中已完整的事件
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.windows(2).position(|w| w == b"\n\n") {
+            let raw: Vec<u8> = self.buffer.drain(..end + 2).collect();
+            let raw = String::from_utf8_lossy(&raw[..end]);
+
+            let mut event = SseEvent::default();
+            let mut data_lines = Vec::new();
+            for line in raw.lines() {
+                if let Some(value) = line.strip_prefix("event:") {
+                    event.event = Some(value.trim().to_string());
+                } else if let Some(value) = line.strip_prefix("data:") {
+                    data_lines.push(value.strip_prefix(' ').unwrap_or(value));
+                }
+            }
+            event.data = data_lines.join("\n");
+            events.push(event);
+        }
+        events
+    }
+}
+
+/// 单个事件的处理结果
+pub(crate) enum SseStep {
+    /// 产出一个文本片段
+    Token(String),
+    /// 忽略该事件
+    Skip,
+    /// 正常结束
+    Done,
+    /// 服务端报告错误，流随之结束
+    Fail(MemoryError),
+}
+
+/// 流式响应的解析状态
+struct TokenStreamState<F> {
+    body: BoxStream<'static, reqwest::Result<bytes::Bytes>>,
+    decoder: SseDecoder,
+    on_event: F,
+    queue: VecDeque<Result<String>>,
+    finished: bool,
+}
+
+/// 将SSE响应体转换为文本片段流；未收到结束事件就断开视为错误
+pub(crate) fn token_stream<F>(response: reqwest::Response, on_event: F) -> TokenStream
+where
+    F: FnMut(SseEvent) -> SseStep + Send + 'static,
+{
+    let state = TokenStreamState {
+        body: response.bytes_stream().boxed(),
+        decoder: SseDecoder::default(),
+        on_event,
+        queue: VecDeque::new(),
+        finished: false,
+    };
+
+    stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(item) = state.queue.pop_front() {
+                return Some((item, state));
+            }
+            if state.finished {
+                return None;
+            }
+
+            match state.body.next().await {
+                Some(Ok(bytes)) => {
+                    for event in state.decoder.push(&bytes) {
+                        match (state.on_event)(event) {
+                            SseStep::Token(token) => state.queue.push_back(Ok(token)),
+                            SseStep::Skip => {}
+                            SseStep::Done => {
+                                state.finished = true;
+                                break;
+                            }
+                            SseStep::Fail(e) => {
+                                state.queue.push_back(Err(e));
+                                state.finished = true;
+                                break;
+                            }
+                        }
+                    }
+                }
+                Some(Err(e)) => {
+                    state.queue.push_back(Err(MemoryError::InferenceError(format!("流式响应中断: {}", e))));
+                    state.finished = true;
+                }
+                None => {
+                    state.queue.push_back(Err(MemoryError::InferenceError("流式响应在完成前结束".to_string())));
+                    state.finished = true;
+                }
+            }
+        }
+    }).boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sse_decoder_handles_split_chunks() {
+        let mut decoder = SseDecoder::default();
+        let payload = "data: \"你好\"\n\nevent: done\ndata: {}\n\n".as_bytes();
+
+        // 在多字节字符中间切开
+        assert!(decoder.push(&payload[..8]).is_empty());
+        let events = decoder.push(&payload[8..]);
+
+        assert_eq!(events, vec![
+            SseEvent { event: None, data: "\"你好\"".to_string() },
+            SseEvent { event: Some("done".to_string()), data: "{}".to_string() },
+        ]);
+    }
+}
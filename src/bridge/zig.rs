@@ -0,0 +1,64 @@
+//! Zig系统层库加载信息 - 库来源由build.rs决定：
+//! `MIRA_ZIG_LIB_DIR`指向预编译目录，`MIRA_ZIG_PREBUILT_URL`下载发布产物，否则本地构建
+
+use super::zig_bridge::ZigPerformanceUtils;
+use crate::{MemoryError, Result};
+
+/// 与当前绑定兼容的库版本 - 主版本号必须一致，次版本号不低于此值
+///
+/// 2.0起导出函数返回错误码，1.x的库签名不同，不能混用
+pub const ZIG_LIBRARY_VERSION: (u32, u32, u32) = (2, 0, 0);
+
+/// Zig静态库的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZigLibrarySource {
+    /// 构建时用本地Zig编译器编译
+    Built,
+    /// 链接`MIRA_ZIG_LIB_DIR`中的预编译库
+    Prebuilt,
+    /// 从`MIRA_ZIG_PREBUILT_URL`下载
+    Downloaded,
+}
+
+impl ZigLibrarySource {
+    /// 当前二进制链接的库来源
+    pub fn current() -> Self {
+        match env!("MIRA_ZIG_SOURCE") {
+            "prebuilt" => Self::Prebuilt,
+            "downloaded" => Self::Downloaded,
+            _ => Self::Built,
+        }
+    }
+}
+
+/// 链接时使用的库目录
+pub fn library_dir() -> &'static str {
+    env!("MIRA_ZIG_LIB_PATH")
+}
+
+/// 检查链接的库版本与绑定是否兼容，返回库版本
+///
+/// 预编译库可能与源码不同步，启动时校验可以避免调用不存在或签名不同的接口
+pub fn verify_library() -> Result<(u32, u32, u32)> {
+    let version = ZigPerformanceUtils::get_version();
+    let (major, minor, _) = ZIG_LIBRARY_VERSION;
+    if version.0 != major || version.1 < minor {
+        return Err(MemoryError::ConfigError(format!(
+            "Zig库版本 {}.{}.{} 与绑定要求的 {}.{}.x 不兼容（来源: {:?}, 目录: {}）",
+            version.0, version.1, version.2, major, minor,
+            ZigLibrarySource::current(), library_dir(),
+        )));
+    }
+    Ok(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_linked_library_is_compatible() {
+        assert_eq!(verify_library().unwrap(), ZIG_LIBRARY_VERSION);
+        assert!(!library_dir().is_empty());
+    }
+}
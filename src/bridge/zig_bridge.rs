@@ -2,21 +2,69 @@
 //! My Intelligent Romantic Assistant - 调用Zig实现的高性能内存管理和系统操作
 
 use crate::{Result, MemoryError};
-use std::ffi::{CStr, CString};
+use std::ffi::CStr;
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
 use std::os::raw::{c_char, c_int, c_void};
+use std::ptr::NonNull;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use tokio::task::JoinHandle;
 
 // Zig函数声明 - 使用简化的FFI接口
 unsafe extern "C" {
     // 内存池管理
     fn pool_init(pool_size: usize) -> *mut c_void;
     fn pool_alloc(pool: *mut c_void, size: usize) -> *mut c_void;
-    fn pool_free(pool: *mut c_void, ptr: *mut c_void);
+    fn pool_alloc_aligned(pool: *mut c_void, size: usize, alignment: usize) -> *mut c_void;
+    fn pool_free(pool: *mut c_void, ptr: *mut c_void) -> c_int;
     fn pool_destroy(pool: *mut c_void);
+    fn pool_grow(pool: *mut c_void, additional: usize) -> c_int;
+    fn pool_stats(pool: *mut c_void, stats_out: *mut PoolStats) -> c_int;
+
+    // 竞技场管理
+    fn arena_create() -> *mut c_void;
+    fn arena_alloc(arena: *mut c_void, size: usize, alignment: usize) -> *mut c_void;
+    fn arena_reset(arena: *mut c_void);
+    fn arena_capacity(arena: *mut c_void) -> usize;
+    fn arena_destroy(arena: *mut c_void);
+
+    // 线性分配器
+    fn bump_create(capacity: usize) -> *mut c_void;
+    fn bump_alloc(bump: *mut c_void, size: usize, alignment: usize) -> *mut c_void;
+    fn bump_used(bump: *mut c_void) -> usize;
+    fn bump_rewind(bump: *mut c_void, offset: usize);
+    fn bump_high_water(bump: *mut c_void) -> usize;
+    fn bump_capacity(bump: *mut c_void) -> usize;
+    fn bump_destroy(bump: *mut c_void);
     
     // 向量运算
     fn dot_product(a: *const f32, b: *const f32, len: usize) -> f32;
     fn cosine_similarity(a: *const f32, b: *const f32, len: usize) -> f32;
-    fn normalize(vec: *mut f32, len: usize) -> bool;
+    fn normalize(vec: *mut f32, len: usize) -> c_int;
+    fn batch_cosine_similarity(
+        query: *const f32,
+        matrix: *const f32,
+        n: usize,
+        dim: usize,
+        out_scores: *mut f32,
+    ) -> c_int;
+    fn topk_cosine_similarity(
+        query: *const f32,
+        matrix: *const f32,
+        keys: *const [u64; 2],
+        n: usize,
+        dim: usize,
+        k: usize,
+        threshold: f32,
+        out_indices: *mut usize,
+        out_scores: *mut f32,
+        out_count: *mut usize,
+    ) -> c_int;
     
     // 哈希计算
     fn hash(text: *const c_char, len: usize) -> u64;
@@ -25,16 +73,136 @@ unsafe extern "C" {
     fn memory_usage() -> usize;
     fn cpu_usage() -> f32;
     
+    // 错误码
+    fn mira_take_last_error() -> c_int;
+
     // 信息查询
     fn get_version(major: *mut u32, minor: *mut u32, patch: *mut u32);
     fn simd_enabled() -> bool;
 }
 
+/// Zig FFI错误码，与`zig_system/src/root.zig`中的`ErrorCode`一致
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FfiErrorCode {
+    /// 必需的指针参数为null
+    NullPointer,
+    /// 大小、长度等参数无效
+    InvalidArgument,
+    /// 内存不足
+    OutOfMemory,
+    /// 对齐不是2的幂或超出支持范围
+    InvalidAlignment,
+    /// 释放的指针不属于该内存池
+    InvalidPointer,
+    /// 未定义的错误码，通常是库版本与绑定不一致
+    Unknown(c_int),
+}
+
+impl FfiErrorCode {
+    /// 从原始错误码转换，0表示成功
+    pub fn from_raw(code: c_int) -> Option<Self> {
+        match code {
+            0 => None,
+            1 => Some(Self::NullPointer),
+            2 => Some(Self::InvalidArgument),
+            3 => Some(Self::OutOfMemory),
+            4 => Some(Self::InvalidAlignment),
+            5 => Some(Self::InvalidPointer),
+            other => Some(Self::Unknown(other)),
+        }
+    }
+
+    /// 检查返回错误码的调用
+    fn check(call: &'static str, code: c_int) -> Result<()> {
+        match Self::from_raw(code) {
+            None => Ok(()),
+            Some(code) => Err(MemoryError::Ffi { call, code }),
+        }
+    }
+
+    /// 返回null的调用失败后取出错误码
+    fn last(call: &'static str) -> MemoryError {
+        let code = Self::from_raw(unsafe { mira_take_last_error() }).unwrap_or(Self::Unknown(0));
+        MemoryError::Ffi { call, code }
+    }
+}
+
+impl std::fmt::Display for FfiErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NullPointer => write!(f, "空指针"),
+            Self::InvalidArgument => write!(f, "参数无效"),
+            Self::OutOfMemory => write!(f, "内存不足"),
+            Self::InvalidAlignment => write!(f, "对齐无效"),
+            Self::InvalidPointer => write!(f, "指针不属于该内存池"),
+            Self::Unknown(code) => write!(f, "未知错误码 {}", code),
+        }
+    }
+}
+
+/// 调用前的参数检查失败
+fn invariant(call: &'static str, reason: impl Into<String>) -> MemoryError {
+    MemoryError::FfiInvariant { call, reason: reason.into() }
+}
+
+/// 内存池普通分配保证的对齐字节数，更大的对齐走`allocate_aligned`
+const POOL_ALIGN: usize = 8;
+
+/// 池内每个块的头部大小，与Zig侧`FreeBlock`一致
+const POOL_BLOCK_HEADER: usize = 2 * std::mem::size_of::<usize>();
+
+/// 系统监控器内存池自动扩容的上限
+const DEFAULT_POOL_MAX_SIZE: usize = 64 * 1024 * 1024;
+
+/// 内存池使用统计 - 与Zig侧`MemoryStats`的C布局一致
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PoolStats {
+    /// 池总大小(字节)
+    pub total: usize,
+    /// 已分配字节数
+    pub used: usize,
+    /// 未分配字节数
+    pub free: usize,
+    /// 空闲块数量
+    pub free_blocks: usize,
+    /// 当前未释放的分配数
+    pub allocations: usize,
+    /// 已分配字节数的历史峰值
+    pub high_water: usize,
+    /// 碎片率估计：1 - 最大空闲块 / 空闲总量
+    pub fragmentation: f32,
+}
+
+/// 合并多个池的统计，碎片率按空闲字节加权
+impl std::iter::Sum for PoolStats {
+    fn sum<I: Iterator<Item = PoolStats>>(iter: I) -> Self {
+        let mut combined = PoolStats::default();
+        let mut weighted_fragmentation = 0.0f64;
+        for stats in iter {
+            combined.total += stats.total;
+            combined.used += stats.used;
+            combined.free += stats.free;
+            combined.free_blocks += stats.free_blocks;
+            combined.allocations += stats.allocations;
+            combined.high_water += stats.high_water;
+            weighted_fragmentation += stats.fragmentation as f64 * stats.free as f64;
+        }
+        if combined.free > 0 {
+            combined.fragmentation = (weighted_fragmentation / combined.free as f64) as f32;
+        }
+        combined
+    }
+}
+
 /// Zig内存池管理器
 #[derive(Debug)]
 pub struct ZigMemoryPool {
     pool_ptr: *mut c_void,
-    pool_size: usize,
+    /// 自动扩容的总大小上限，None表示不自动扩容
+    max_size: Option<usize>,
+    /// Zig侧内存池不是线程安全的，所有FFI调用串行执行
+    lock: Mutex<()>,
 }
 
 unsafe impl Send for ZigMemoryPool {}
@@ -46,40 +214,233 @@ impl ZigMemoryPool {
         let pool_ptr = unsafe { pool_init(pool_size) };
         
         if pool_ptr.is_null() {
-            return Err(MemoryError::DatabaseError(
-                "Zig内存池初始化失败".to_string()
-            ));
+            return Err(FfiErrorCode::last("pool_init"));
         }
         
         Ok(Self {
             pool_ptr,
-            pool_size,
+            max_size: None,
+            lock: Mutex::new(()),
         })
     }
 
-    /// 分配内存
+    /// 空间不足时自动扩容，总大小不超过`max_size`
+    pub fn with_auto_grow(mut self, max_size: usize) -> Self {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    /// 分配内存 - 启用自动扩容时，空间不足会扩容后重试一次
     pub fn allocate(&self, size: usize) -> Result<*mut c_void> {
-        let ptr = unsafe { pool_alloc(self.pool_ptr, size) };
+        if size == 0 {
+            return Err(invariant("pool_alloc", "分配大小为0"));
+        }
+
+        let _guard = self.lock();
+        let mut ptr = unsafe { pool_alloc(self.pool_ptr, size) };
+        if ptr.is_null() && self.grow_for(size) {
+            ptr = unsafe { pool_alloc(self.pool_ptr, size) };
+        }
         
         if ptr.is_null() {
-            Err(MemoryError::DatabaseError(
-                "内存分配失败".to_string()
-            ))
+            Err(FfiErrorCode::last("pool_alloc"))
+        } else {
+            Self::check_alignment("pool_alloc", ptr, POOL_ALIGN)
+        }
+    }
+
+    /// 按`align`字节对齐分配，用于SIMD向量缓冲区(32/64字节)，仍通过`deallocate`释放
+    pub fn allocate_aligned(&self, size: usize, align: usize) -> Result<*mut c_void> {
+        if !align.is_power_of_two() {
+            return Err(invariant("pool_alloc_aligned", format!("对齐必须是2的幂: {}", align)));
+        }
+        if size == 0 {
+            return Err(invariant("pool_alloc_aligned", "分配大小为0"));
+        }
+
+        let _guard = self.lock();
+        let mut ptr = unsafe { pool_alloc_aligned(self.pool_ptr, size, align) };
+        // 对齐可能额外占用最多一个对齐单位加一个块头
+        if ptr.is_null() && self.grow_for(size + align + POOL_BLOCK_HEADER) {
+            ptr = unsafe { pool_alloc_aligned(self.pool_ptr, size, align) };
+        }
+
+        if ptr.is_null() {
+            Err(FfiErrorCode::last("pool_alloc_aligned"))
         } else {
+            Self::check_alignment("pool_alloc_aligned", ptr, align)
+        }
+    }
+
+    /// 校验Zig返回的指针满足约定的对齐
+    fn check_alignment(call: &'static str, ptr: *mut c_void, align: usize) -> Result<*mut c_void> {
+        if ptr.align_offset(align) == 0 {
             Ok(ptr)
+        } else {
+            Err(invariant(call, format!("返回的地址 {:p} 未按 {} 字节对齐", ptr, align)))
         }
     }
 
-    /// 释放内存
-    pub fn deallocate(&self, ptr: *mut c_void) {
-        unsafe {
-            pool_free(self.pool_ptr, ptr);
+    /// 追加`additional`字节，已分配的内存保持有效
+    pub fn grow(&self, additional: usize) -> Result<()> {
+        let _guard = self.lock();
+        FfiErrorCode::check("pool_grow", unsafe { pool_grow(self.pool_ptr, additional) })
+    }
+
+    /// 按当前大小翻倍扩容，至少容纳`size`字节的分配，不超过上限 - 调用方需持有锁
+    fn grow_for(&self, size: usize) -> bool {
+        let Some(max_size) = self.max_size else {
+            return false;
+        };
+        let total = self.raw_stats().total;
+        let needed = size.next_multiple_of(POOL_ALIGN) + 2 * POOL_BLOCK_HEADER;
+        let growth = total.max(needed).min(max_size.saturating_sub(total));
+        if growth < needed {
+            tracing::warn!("内存池已达到扩容上限 {} 字节", max_size);
+            return false;
         }
+        unsafe { pool_grow(self.pool_ptr, growth) == 0 }
+    }
+
+    /// 释放内存 - 指针不属于该池时返回错误且不做任何修改
+    pub fn deallocate(&self, ptr: *mut c_void) -> Result<()> {
+        if ptr.is_null() {
+            return Err(invariant("pool_free", "指针为null"));
+        }
+        let _guard = self.lock();
+        FfiErrorCode::check("pool_free", unsafe { pool_free(self.pool_ptr, ptr) })
     }
 
     /// 获取池大小
     pub fn pool_size(&self) -> usize {
-        self.pool_size
+        self.stats().total
+    }
+
+    /// 使用统计
+    pub fn stats(&self) -> PoolStats {
+        let _guard = self.lock();
+        self.raw_stats()
+    }
+
+    fn raw_stats(&self) -> PoolStats {
+        let mut stats = PoolStats::default();
+        if let Err(e) = FfiErrorCode::check("pool_stats", unsafe { pool_stats(self.pool_ptr, &mut stats) }) {
+            tracing::error!("读取内存池统计失败: {}", e);
+        }
+        stats
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ()> {
+        self.lock.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 在池中存放一个值，守卫离开作用域时释放
+    pub fn alloc_box<T>(&self, value: T) -> Result<PoolBox<'_, T>> {
+        let ptr = self.allocate_for::<T>(1, std::mem::align_of::<T>())?;
+        unsafe { ptr.write(value) };
+        Ok(PoolBox { pool: self, ptr, _owns: PhantomData })
+    }
+
+    /// 分配`len`个元素的缓冲区并全部填充为`value`，守卫离开作用域时释放
+    pub fn alloc_slice<T: Copy>(&self, len: usize, value: T) -> Result<PoolSlice<'_, T>> {
+        self.alloc_slice_aligned(len, value, std::mem::align_of::<T>())
+    }
+
+    /// 同`alloc_slice`，缓冲区起始地址按`align`字节对齐，例如SIMD加载需要的32/64字节
+    pub fn alloc_slice_aligned<T: Copy>(&self, len: usize, value: T, align: usize) -> Result<PoolSlice<'_, T>> {
+        let ptr = self.allocate_for::<T>(len, align.max(std::mem::align_of::<T>()))?;
+        for i in 0..len {
+            unsafe { ptr.add(i).write(value) };
+        }
+        Ok(PoolSlice { pool: self, ptr, len })
+    }
+
+    /// 按类型分配，超过`POOL_ALIGN`的对齐走对齐分配
+    fn allocate_for<T>(&self, len: usize, align: usize) -> Result<NonNull<T>> {
+        let size = std::mem::size_of::<T>()
+            .checked_mul(len)
+            .ok_or_else(|| invariant("pool_alloc", "分配大小溢出"))?;
+
+        // 池不接受0字节分配，零大小请求也占用一个字节
+        let ptr = if align > POOL_ALIGN {
+            self.allocate_aligned(size.max(1), align)?
+        } else {
+            self.allocate(size.max(1))?
+        };
+        Ok(unsafe { NonNull::new_unchecked(ptr.cast()) })
+    }
+}
+
+/// 池内存放的单个值，Drop时析构并归还内存
+pub struct PoolBox<'a, T> {
+    pool: &'a ZigMemoryPool,
+    ptr: NonNull<T>,
+    _owns: PhantomData<T>,
+}
+
+impl<T> Deref for PoolBox<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T> DerefMut for PoolBox<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for PoolBox<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("PoolBox").field(&**self).finish()
+    }
+}
+
+impl<T> Drop for PoolBox<'_, T> {
+    fn drop(&mut self) {
+        unsafe {
+            std::ptr::drop_in_place(self.ptr.as_ptr());
+        }
+        if let Err(e) = self.pool.deallocate(self.ptr.as_ptr().cast()) {
+            tracing::error!("释放内存池缓冲区失败: {}", e);
+        }
+    }
+}
+
+/// 池内分配的定长缓冲区，Drop时归还内存
+pub struct PoolSlice<'a, T: Copy> {
+    pool: &'a ZigMemoryPool,
+    ptr: NonNull<T>,
+    len: usize,
+}
+
+impl<T: Copy> Deref for PoolSlice<'_, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T: Copy> DerefMut for PoolSlice<'_, T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T: Copy + std::fmt::Debug> std::fmt::Debug for PoolSlice<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Copy> Drop for PoolSlice<'_, T> {
+    fn drop(&mut self) {
+        if let Err(e) = self.pool.deallocate(self.ptr.as_ptr().cast()) {
+            tracing::error!("释放内存池缓冲区失败: {}", e);
+        }
     }
 }
 
@@ -91,25 +452,404 @@ impl Drop for ZigMemoryPool {
     }
 }
 
+/// 分片内存池 - 每个线程固定使用一个分片，并行的嵌入任务不会争用同一把锁
+///
+/// 守卫归还到分配它的分片，跨线程释放同样安全。
+#[derive(Debug)]
+pub struct ShardedZigPool {
+    shards: Vec<ZigMemoryPool>,
+}
+
+/// 线程首次使用分片池时按顺序领取的编号
+static NEXT_SHARD: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static THREAD_SHARD: usize = NEXT_SHARD.fetch_add(1, Ordering::Relaxed);
+}
+
+impl ShardedZigPool {
+    /// 创建`shard_count`个分片，每个分片`shard_size`字节
+    pub fn new(shard_count: usize, shard_size: usize) -> Result<Self> {
+        let shards = (0..shard_count.max(1))
+            .map(|_| ZigMemoryPool::new(shard_size))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { shards })
+    }
+
+    /// 按CPU核数创建分片
+    pub fn per_thread(shard_size: usize) -> Result<Self> {
+        let shard_count = std::thread::available_parallelism().map_or(1, |n| n.get());
+        Self::new(shard_count, shard_size)
+    }
+
+    /// 每个分片空间不足时自动扩容，单个分片不超过`max_shard_size`
+    pub fn with_auto_grow(mut self, max_shard_size: usize) -> Self {
+        self.shards = self.shards.into_iter()
+            .map(|shard| shard.with_auto_grow(max_shard_size))
+            .collect();
+        self
+    }
+
+    /// 当前线程使用的分片
+    pub fn shard(&self) -> &ZigMemoryPool {
+        let index = THREAD_SHARD.with(|shard| *shard) % self.shards.len();
+        &self.shards[index]
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// 在当前线程的分片中存放一个值
+    pub fn alloc_box<T>(&self, value: T) -> Result<PoolBox<'_, T>> {
+        self.shard().alloc_box(value)
+    }
+
+    /// 在当前线程的分片中分配缓冲区
+    pub fn alloc_slice<T: Copy>(&self, len: usize, value: T) -> Result<PoolSlice<'_, T>> {
+        self.shard().alloc_slice(len, value)
+    }
+
+    /// 在当前线程的分片中分配对齐的缓冲区
+    pub fn alloc_slice_aligned<T: Copy>(&self, len: usize, value: T, align: usize) -> Result<PoolSlice<'_, T>> {
+        self.shard().alloc_slice_aligned(len, value, align)
+    }
+
+    /// 全部分片的合并统计
+    pub fn stats(&self) -> PoolStats {
+        self.shards.iter().map(ZigMemoryPool::stats).sum()
+    }
+
+    /// 各分片的统计
+    pub fn shard_stats(&self) -> Vec<PoolStats> {
+        self.shards.iter().map(ZigMemoryPool::stats).collect()
+    }
+}
+
+/// Zig竞技场 - 分配不单独释放，重置时一次性回收
+#[derive(Debug)]
+struct ZigArena {
+    arena_ptr: *mut c_void,
+}
+
+unsafe impl Send for ZigArena {}
+
+impl ZigArena {
+    fn new() -> Result<Self> {
+        let arena_ptr = unsafe { arena_create() };
+        if arena_ptr.is_null() {
+            return Err(FfiErrorCode::last("arena_create"));
+        }
+        Ok(Self { arena_ptr })
+    }
+}
+
+impl Drop for ZigArena {
+    fn drop(&mut self) {
+        unsafe {
+            arena_destroy(self.arena_ptr);
+        }
+    }
+}
+
+/// 竞技场作用域 - 分配的缓冲区在作用域结束时统一回收
+#[derive(Debug)]
+pub struct ArenaScope<'a> {
+    arena: &'a ZigArena,
+}
+
+impl ArenaScope<'_> {
+    /// 分配`len`个元素的缓冲区并全部填充为`value`
+    // 每次分配返回互不重叠的新内存，借用不超过作用域
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice<T: Copy>(&self, len: usize, value: T) -> Result<&mut [T]> {
+        if len == 0 {
+            return Ok(&mut []);
+        }
+        let size = std::mem::size_of::<T>()
+            .checked_mul(len)
+            .ok_or_else(|| invariant("arena_alloc", "分配大小溢出"))?;
+
+        let ptr = unsafe { arena_alloc(self.arena.arena_ptr, size.max(1), std::mem::align_of::<T>()) };
+        if ptr.is_null() {
+            return Err(FfiErrorCode::last("arena_alloc"));
+        }
+
+        let slice = unsafe { std::slice::from_raw_parts_mut(ptr.cast::<T>(), len) };
+        slice.fill(value);
+        Ok(slice)
+    }
+
+    /// 复制一份数据到竞技场
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_copy<T: Copy + Default>(&self, data: &[T]) -> Result<&mut [T]> {
+        let slice = self.alloc_slice(data.len(), T::default())?;
+        slice.copy_from_slice(data);
+        Ok(slice)
+    }
+
+    /// 竞技场当前持有的内存
+    pub fn capacity(&self) -> usize {
+        unsafe { arena_capacity(self.arena.arena_ptr) }
+    }
+}
+
+/// 每个名称下默认最多保留的空闲竞技场数
+pub const DEFAULT_MAX_IDLE_ARENAS: usize = 4;
+
+/// 默认单个空闲竞技场最多保留的内存，超出的竞技场用完即释放
+pub const DEFAULT_MAX_RETAINED_ARENA_BYTES: usize = 16 * 1024 * 1024;
+
+/// 按名称管理的竞技场，例如"embeddings"、"scratch"
+///
+/// 每个名称下保留一组可复用的竞技场，并发的作用域各自取用一个，互不等待。
+/// 空闲竞技场的数量和单个竞技场保留的内存都有上限，超出的竞技场在作用域结束时释放。
+#[derive(Debug)]
+pub struct ZigArenaRegistry {
+    arenas: Mutex<HashMap<String, Vec<ZigArena>>>,
+    max_idle: usize,
+    max_retained_bytes: usize,
+}
+
+impl Default for ZigArenaRegistry {
+    fn default() -> Self {
+        Self::with_limits(DEFAULT_MAX_IDLE_ARENAS, DEFAULT_MAX_RETAINED_ARENA_BYTES)
+    }
+}
+
+static GLOBAL_ARENAS: LazyLock<ZigArenaRegistry> = LazyLock::new(ZigArenaRegistry::default);
+
+impl ZigArenaRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 每个名称最多保留`max_idle`个空闲竞技场，保留内存超过`max_retained_bytes`的竞技场不再复用
+    pub fn with_limits(max_idle: usize, max_retained_bytes: usize) -> Self {
+        Self {
+            arenas: Mutex::new(HashMap::new()),
+            max_idle,
+            max_retained_bytes,
+        }
+    }
+
+    /// 进程级共享的竞技场
+    pub fn global() -> &'static Self {
+        &GLOBAL_ARENAS
+    }
+
+    /// 在名为`name`的竞技场中执行`f`，结束时回收作用域内的全部分配
+    pub fn with_arena<R>(&self, name: &str, f: impl FnOnce(&ArenaScope<'_>) -> R) -> Result<R> {
+        let arena = match self.lock().get_mut(name).and_then(Vec::pop) {
+            Some(arena) => arena,
+            None => ZigArena::new()?,
+        };
+
+        let result = f(&ArenaScope { arena: &arena });
+
+        unsafe { arena_reset(arena.arena_ptr) };
+        if unsafe { arena_capacity(arena.arena_ptr) } <= self.max_retained_bytes {
+            let mut arenas = self.lock();
+            let idle = arenas.entry(name.to_string()).or_default();
+            if idle.len() < self.max_idle {
+                idle.push(arena);
+            }
+        }
+        Ok(result)
+    }
+
+    /// 名称下空闲竞技场保留的内存总量
+    pub fn retained_bytes(&self, name: &str) -> usize {
+        self.lock()
+            .get(name)
+            .map(|arenas| arenas.iter().map(|arena| unsafe { arena_capacity(arena.arena_ptr) }).sum())
+            .unwrap_or(0)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Vec<ZigArena>>> {
+        self.arenas.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// 每个线程暂存区的默认容量
+pub const DEFAULT_SCRATCH_CAPACITY: usize = 4 * 1024 * 1024;
+
+/// 线性分配器支持的最大对齐
+const SCRATCH_MAX_ALIGN: usize = 64;
+
+thread_local! {
+    static THREAD_SCRATCH: Option<ScratchArena> = ScratchArena::new(DEFAULT_SCRATCH_CAPACITY).ok();
+}
+
+/// 基于Zig线性分配器的暂存区，用于热路径上的临时缓冲区
+///
+/// 分配只移动偏移量，作用域结束时回退到进入时的位置。不跨线程共享，
+/// 空间不足、对齐过大或在内层作用域中使用外层作用域时回退到堆分配。
+#[derive(Debug)]
+pub struct ScratchArena {
+    bump_ptr: NonNull<c_void>,
+    depth: Cell<usize>,
+}
+
+impl ScratchArena {
+    /// 创建容量为`capacity`字节的暂存区
+    pub fn new(capacity: usize) -> Result<Self> {
+        let bump_ptr = NonNull::new(unsafe { bump_create(capacity) })
+            .ok_or_else(|| FfiErrorCode::last("bump_create"))?;
+        Ok(Self { bump_ptr, depth: Cell::new(0) })
+    }
+
+    /// 在当前线程的暂存区中执行`f`，暂存区不可用时全部使用堆分配
+    pub fn with<R>(f: impl FnOnce(&ScratchScope<'_>) -> R) -> R {
+        THREAD_SCRATCH.with(|scratch| match scratch {
+            Some(scratch) => scratch.scope(f),
+            None => f(&ScratchScope { arena: None, depth: 0 }),
+        })
+    }
+
+    /// 开启一个作用域，结束时回收作用域内的全部分配
+    pub fn scope<R>(&self, f: impl FnOnce(&ScratchScope<'_>) -> R) -> R {
+        struct Rewind<'a> {
+            arena: &'a ScratchArena,
+            mark: usize,
+        }
+
+        impl Drop for Rewind<'_> {
+            fn drop(&mut self) {
+                unsafe { bump_rewind(self.arena.bump_ptr.as_ptr(), self.mark) };
+                self.arena.depth.set(self.arena.depth.get() - 1);
+            }
+        }
+
+        let depth = self.depth.get() + 1;
+        self.depth.set(depth);
+        let _rewind = Rewind { arena: self, mark: self.used() };
+        f(&ScratchScope { arena: Some(self), depth })
+    }
+
+    /// 缓冲区容量
+    pub fn capacity(&self) -> usize {
+        unsafe { bump_capacity(self.bump_ptr.as_ptr()) }
+    }
+
+    /// 当前已分配的字节数（含对齐填充）
+    pub fn used(&self) -> usize {
+        unsafe { bump_used(self.bump_ptr.as_ptr()) }
+    }
+
+    /// 已分配字节数的历史峰值
+    pub fn high_water(&self) -> usize {
+        unsafe { bump_high_water(self.bump_ptr.as_ptr()) }
+    }
+}
+
+impl Drop for ScratchArena {
+    fn drop(&mut self) {
+        unsafe {
+            bump_destroy(self.bump_ptr.as_ptr());
+        }
+    }
+}
+
+/// 暂存区作用域
+#[derive(Debug)]
+pub struct ScratchScope<'a> {
+    arena: Option<&'a ScratchArena>,
+    depth: usize,
+}
+
+impl ScratchScope<'_> {
+    /// 分配`len`个元素的缓冲区并全部填充为`value`
+    pub fn alloc_slice<T: Copy>(&self, len: usize, value: T) -> ScratchBuf<'_, T> {
+        match self.bump_alloc::<T>(len) {
+            Some(ptr) => {
+                let slice = unsafe { std::slice::from_raw_parts_mut(ptr.as_ptr(), len) };
+                slice.fill(value);
+                ScratchBuf::Arena(slice)
+            }
+            None => ScratchBuf::Heap(vec![value; len]),
+        }
+    }
+
+    /// 复制一份数据到暂存区
+    pub fn alloc_copy<T: Copy>(&self, data: &[T]) -> ScratchBuf<'_, T> {
+        match self.bump_alloc::<T>(data.len()) {
+            Some(ptr) => {
+                let slice = unsafe { std::slice::from_raw_parts_mut(ptr.as_ptr(), data.len()) };
+                slice.copy_from_slice(data);
+                ScratchBuf::Arena(slice)
+            }
+            None => ScratchBuf::Heap(data.to_vec()),
+        }
+    }
+
+    /// 只有最内层作用域可以从线性分配器取内存，否则内层回退时会覆盖外层的新分配
+    fn bump_alloc<T>(&self, len: usize) -> Option<NonNull<T>> {
+        let arena = self.arena.filter(|arena| arena.depth.get() == self.depth)?;
+        let size = std::mem::size_of::<T>().checked_mul(len)?;
+        let align = std::mem::align_of::<T>();
+        if size == 0 || align > SCRATCH_MAX_ALIGN {
+            return None;
+        }
+        let ptr = NonNull::new(unsafe { bump_alloc(arena.bump_ptr.as_ptr(), size, align) }.cast::<T>());
+        if ptr.is_none() {
+            // 空间不足是预期情况，清除错误码后回退到堆
+            unsafe { mira_take_last_error() };
+        }
+        ptr
+    }
+}
+
+/// 暂存缓冲区 - 来自线性分配器或回退的堆分配
+#[derive(Debug)]
+pub enum ScratchBuf<'a, T> {
+    Arena(&'a mut [T]),
+    Heap(Vec<T>),
+}
+
+impl<T> ScratchBuf<'_, T> {
+    /// 是否分配在暂存区中
+    pub fn is_arena(&self) -> bool {
+        matches!(self, Self::Arena(_))
+    }
+}
+
+impl<T> Deref for ScratchBuf<'_, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        match self {
+            Self::Arena(slice) => slice,
+            Self::Heap(vec) => vec,
+        }
+    }
+}
+
+impl<T> DerefMut for ScratchBuf<'_, T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        match self {
+            Self::Arena(slice) => slice,
+            Self::Heap(vec) => vec,
+        }
+    }
+}
+
 /// Zig高性能工具集
 #[derive(Debug)]
 pub struct ZigPerformanceUtils;
 
 impl ZigPerformanceUtils {
-    /// 快速字符串哈希
+    /// 快速字符串哈希 - 按字节长度传入，不要求NUL结尾
     pub fn fast_hash(text: &str) -> u64 {
-        let c_str = CString::new(text).unwrap_or_default();
         unsafe {
-            hash(c_str.as_ptr(), text.len())
+            hash(text.as_ptr() as *const c_char, text.len())
         }
     }
 
     /// 向量点积运算
     pub fn vector_dot_product(a: &[f32], b: &[f32]) -> Result<f32> {
         if a.len() != b.len() {
-            return Err(MemoryError::DatabaseError(
-                "向量维度不匹配".to_string()
-            ));
+            return Err(MemoryError::DimensionMismatch { expected: a.len(), actual: b.len() });
         }
 
         let result = unsafe {
@@ -119,12 +859,12 @@ impl ZigPerformanceUtils {
         Ok(result)
     }
 
-    /// 获取系统内存使用情况
+    /// 获取进程常驻内存 - Zig层不支持的平台返回0，跨平台采集使用`ProcessProbe`
     pub fn get_memory_usage() -> usize {
         unsafe { memory_usage() }
     }
 
-    /// 获取CPU使用率
+    /// 获取系统整体CPU使用率 - 进程级使用率见`ProcessProbe::cpu_usage`
     pub fn get_cpu_usage() -> f32 {
         unsafe { cpu_usage() }
     }
@@ -132,9 +872,7 @@ impl ZigPerformanceUtils {
     /// 向量余弦相似度计算
     pub fn vector_cosine_similarity(a: &[f32], b: &[f32]) -> Result<f32> {
         if a.len() != b.len() {
-            return Err(MemoryError::DatabaseError(
-                "向量维度不匹配".to_string()
-            ));
+            return Err(MemoryError::DimensionMismatch { expected: a.len(), actual: b.len() });
         }
 
         let result = unsafe {
@@ -144,19 +882,86 @@ impl ZigPerformanceUtils {
         Ok(result)
     }
 
+    /// 批量余弦相似度 - `matrix`按行连续存放与`query`同维的向量，返回每行的分数
+    pub fn batch_cosine_similarity(query: &[f32], matrix: &[f32]) -> Result<Vec<f32>> {
+        if query.is_empty() {
+            return Err(invariant("batch_cosine_similarity", "查询向量为空"));
+        }
+        if matrix.len() % query.len() != 0 {
+            return Err(invariant(
+                "batch_cosine_similarity",
+                format!("矩阵长度 {} 不是维度 {} 的整数倍", matrix.len(), query.len()),
+            ));
+        }
+
+        let n = matrix.len() / query.len();
+        let mut scores = vec![0.0f32; n];
+        FfiErrorCode::check("batch_cosine_similarity", unsafe {
+            batch_cosine_similarity(query.as_ptr(), matrix.as_ptr(), n, query.len(), scores.as_mut_ptr())
+        })?;
+        Ok(scores)
+    }
+
+    /// 余弦相似度前k名 - 单趟计算并维护大小为k的堆
+    ///
+    /// `keys`为每行的排序键，分数相同时键小的优先。返回`(行号, 分数)`，按相近程度排列，
+    /// 分数低于`threshold`的行不参与。
+    pub fn top_k_cosine_similarity(
+        query: &[f32],
+        matrix: &[f32],
+        keys: &[[u64; 2]],
+        k: usize,
+        threshold: f32,
+    ) -> Result<Vec<(usize, f32)>> {
+        if query.is_empty() {
+            return Err(invariant("topk_cosine_similarity", "查询向量为空"));
+        }
+        if Some(matrix.len()) != keys.len().checked_mul(query.len()) {
+            return Err(invariant(
+                "topk_cosine_similarity",
+                format!("矩阵长度 {} 与 {} 行 × {} 维不符", matrix.len(), keys.len(), query.len()),
+            ));
+        }
+        let k = k.min(keys.len());
+        if k == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut indices = vec![0usize; k];
+        let mut scores = vec![0.0f32; k];
+        let mut count = 0usize;
+        FfiErrorCode::check("topk_cosine_similarity", unsafe {
+            topk_cosine_similarity(
+                query.as_ptr(),
+                matrix.as_ptr(),
+                keys.as_ptr(),
+                keys.len(),
+                query.len(),
+                k,
+                threshold,
+                indices.as_mut_ptr(),
+                scores.as_mut_ptr(),
+                &mut count,
+            )
+        })?;
+        if count > k {
+            return Err(invariant("topk_cosine_similarity", format!("返回 {} 个结果，超过k={}", count, k)));
+        }
+        if let Some(&row) = indices[..count].iter().find(|&&row| row >= keys.len()) {
+            return Err(invariant("topk_cosine_similarity", format!("返回的行号 {} 超出 {} 行", row, keys.len())));
+        }
+
+        Ok(indices.into_iter().zip(scores).take(count).collect())
+    }
+
     /// 向量标准化
     pub fn vector_normalize(vec: &mut [f32]) -> Result<()> {
-        let success = unsafe {
-            normalize(vec.as_mut_ptr(), vec.len())
-        };
-
-        if success {
-            Ok(())
-        } else {
-            Err(MemoryError::DatabaseError(
-                "向量标准化失败".to_string()
-            ))
+        if vec.is_empty() {
+            return Err(invariant("normalize", "向量为空"));
         }
+        FfiErrorCode::check("normalize", unsafe {
+            normalize(vec.as_mut_ptr(), vec.len())
+        })
     }
 
     /// 获取版本信息
@@ -178,45 +983,191 @@ impl ZigPerformanceUtils {
     }
 }
 
+/// 采样历史的默认容量
+pub const DEFAULT_METRICS_HISTORY: usize = 1024;
+
 /// Zig系统监控器
 #[derive(Debug)]
 pub struct ZigSystemMonitor {
-    memory_pool: Option<ZigMemoryPool>,
+    memory_pool: Option<Arc<ZigMemoryPool>>,
+    history: Arc<Mutex<VecDeque<MetricsSample>>>,
+    history_capacity: usize,
+    sampler: Mutex<Option<JoinHandle<()>>>,
 }
 
 impl ZigSystemMonitor {
     /// 创建新的系统监控器
     pub fn new(enable_memory_pool: bool, pool_size: Option<usize>) -> Result<Self> {
+        super::zig::verify_library()?;
+
         let memory_pool = if enable_memory_pool {
-            Some(ZigMemoryPool::new(pool_size.unwrap_or(1024 * 1024))?)
+            // 长时间运行时池可能写满，允许自动扩容到上限
+            let pool_size = pool_size.unwrap_or(1024 * 1024);
+            Some(Arc::new(ZigMemoryPool::new(pool_size)?.with_auto_grow(DEFAULT_POOL_MAX_SIZE.max(pool_size))))
         } else {
             None
         };
 
-        Ok(Self { memory_pool })
+        Ok(Self {
+            memory_pool,
+            history: Arc::new(Mutex::new(VecDeque::new())),
+            history_capacity: DEFAULT_METRICS_HISTORY,
+            sampler: Mutex::new(None),
+        })
+    }
+
+    /// 设置采样历史容量，超出后丢弃最旧的样本
+    pub fn with_history_capacity(mut self, capacity: usize) -> Self {
+        self.history_capacity = capacity.max(1);
+        self
     }
 
     /// 获取系统性能指标
     pub fn get_performance_metrics(&self) -> PerformanceMetrics {
-        PerformanceMetrics {
-            memory_usage: ZigPerformanceUtils::get_memory_usage(),
-            cpu_usage: ZigPerformanceUtils::get_cpu_usage(),
-            pool_size: self.memory_pool.as_ref().map(|p| p.pool_size()),
-        }
+        Self::sample(self.memory_pool.as_deref())
     }
 
     /// 获取内存池引用
     pub fn memory_pool(&self) -> Option<&ZigMemoryPool> {
-        self.memory_pool.as_ref()
+        self.memory_pool.as_deref()
+    }
+
+    /// 按固定间隔在后台采样，重复调用会替换之前的采样任务 - 需要在tokio运行时中调用
+    pub fn start_sampling(&self, interval: Duration) {
+        let sampler = tokio::spawn({
+            let pool = self.memory_pool.clone();
+            let history = self.history.clone();
+            let capacity = self.history_capacity;
+            let interval = interval.max(Duration::from_millis(1));
+            async move {
+                let mut ticker = tokio::time::interval(interval);
+                ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                loop {
+                    ticker.tick().await;
+                    let sample = MetricsSample {
+                        timestamp: Utc::now(),
+                        metrics: Self::sample(pool.as_deref()),
+                    };
+                    let mut history = history.lock().unwrap_or_else(|e| e.into_inner());
+                    if history.len() >= capacity {
+                        history.pop_front();
+                    }
+                    history.push_back(sample);
+                }
+            }
+        });
+
+        if let Some(previous) = self.lock_sampler().replace(sampler) {
+            previous.abort();
+        }
+    }
+
+    /// 停止后台采样，已记录的历史保留
+    pub fn stop_sampling(&self) {
+        if let Some(sampler) = self.lock_sampler().take() {
+            sampler.abort();
+        }
+    }
+
+    /// 是否正在后台采样
+    pub fn is_sampling(&self) -> bool {
+        self.lock_sampler().is_some()
+    }
+
+    /// 按时间顺序返回采样历史
+    pub fn history(&self) -> Vec<MetricsSample> {
+        self.history.lock().unwrap_or_else(|e| e.into_inner()).iter().cloned().collect()
+    }
+
+    /// 清空采样历史
+    pub fn clear_history(&self) {
+        self.history.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    /// 汇总采样历史
+    pub fn summary(&self) -> MetricsSummary {
+        let history = self.history.lock().unwrap_or_else(|e| e.into_inner());
+        MetricsSummary::from_samples(history.iter())
+    }
+
+    fn sample(pool: Option<&ZigMemoryPool>) -> PerformanceMetrics {
+        let probe = super::ProcessProbe::global();
+        PerformanceMetrics {
+            memory_usage: probe.memory_usage(),
+            cpu_usage: probe.cpu_usage(),
+            pool_size: pool.map(|p| p.pool_size()),
+            pool_stats: pool.map(|p| p.stats()),
+        }
+    }
+
+    fn lock_sampler(&self) -> std::sync::MutexGuard<'_, Option<JoinHandle<()>>> {
+        self.sampler.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for ZigSystemMonitor {
+    fn drop(&mut self) {
+        self.stop_sampling();
     }
 }
 
 /// 性能指标
 #[derive(Debug, Clone)]
 pub struct PerformanceMetrics {
+    /// 进程常驻内存（字节）
     pub memory_usage: usize,
+    /// 进程CPU使用率（0-100），为距上次采样的平均值
     pub cpu_usage: f32,
     pub pool_size: Option<usize>,
+    /// 内存池使用统计，未启用内存池时为None
+    pub pool_stats: Option<PoolStats>,
+}
+
+/// 带时间戳的性能指标样本
+#[derive(Debug, Clone)]
+pub struct MetricsSample {
+    pub timestamp: DateTime<Utc>,
+    pub metrics: PerformanceMetrics,
+}
+
+/// 采样历史汇总
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MetricsSummary {
+    pub samples: usize,
+    pub cpu_p50: f32,
+    pub cpu_p95: f32,
+    /// 内存占用峰值（字节）
+    pub peak_memory_usage: usize,
+    /// 内存池已用字节峰值，未启用内存池时为0
+    pub peak_pool_used: usize,
+}
+
+impl MetricsSummary {
+    /// 从样本计算汇总，分位数取最近秩
+    pub fn from_samples<'a>(samples: impl IntoIterator<Item = &'a MetricsSample>) -> Self {
+        let mut summary = Self::default();
+        let mut cpu = Vec::new();
+        for sample in samples {
+            let metrics = &sample.metrics;
+            cpu.push(metrics.cpu_usage);
+            summary.peak_memory_usage = summary.peak_memory_usage.max(metrics.memory_usage);
+            if let Some(stats) = &metrics.pool_stats {
+                summary.peak_pool_used = summary.peak_pool_used.max(stats.used);
+            }
+        }
+
+        summary.samples = cpu.len();
+        if !cpu.is_empty() {
+            cpu.sort_by(|a, b| a.total_cmp(b));
+            let percentile = |p: f32| {
+                let rank = (p * cpu.len() as f32).ceil() as usize;
+                cpu[rank.clamp(1, cpu.len()) - 1]
+            };
+            summary.cpu_p50 = percentile(0.50);
+            summary.cpu_p95 = percentile(0.95);
+        }
+        summary
+    }
 }
 
 /// 用于与Zig代码接口的辅助函数
@@ -252,6 +1203,219 @@ mod tests {
         assert_ne!(hash1, hash3);
     }
 
+    #[test]
+    fn test_batch_cosine_matches_pairwise() {
+        let query: Vec<f32> = (0..19).map(|i| (i as f32 * 0.3).sin()).collect();
+        let rows: Vec<Vec<f32>> = (0..5)
+            .map(|r| (0..19).map(|i| ((i + r) as f32 * 0.7).cos()).collect())
+            .collect();
+
+        let scores = ZigPerformanceUtils::batch_cosine_similarity(&query, &rows.concat()).unwrap();
+        for (row, score) in rows.iter().zip(&scores) {
+            let expected = ZigPerformanceUtils::vector_cosine_similarity(&query, row).unwrap();
+            assert!((score - expected).abs() < 1e-5);
+        }
+
+        assert!(ZigPerformanceUtils::batch_cosine_similarity(&query, &[1.0; 20]).is_err());
+    }
+
+    #[test]
+    fn test_top_k_cosine_orders_by_score_then_key() {
+        let query = [1.0, 0.0];
+        let matrix = [0.0, 1.0, 1.0, 0.0, 3.0, 4.0, 2.0, 0.0];
+        let keys = [[0, 5], [0, 9], [0, 1], [0, 2]];
+
+        let top = ZigPerformanceUtils::top_k_cosine_similarity(&query, &matrix, &keys, 3, 0.1).unwrap();
+        let rows: Vec<usize> = top.iter().map(|(row, _)| *row).collect();
+        assert_eq!(rows, vec![3, 1, 2]);
+        assert!((top[2].1 - 0.6).abs() < 1e-6);
+
+        assert!(ZigPerformanceUtils::top_k_cosine_similarity(&query, &matrix, &keys[..3], 3, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_pool_guards_free_on_drop() {
+        let pool = ZigMemoryPool::new(4096).unwrap();
+
+        {
+            let mut buffer = pool.alloc_slice(256, 0u8).unwrap();
+            buffer[255] = 7;
+            assert_eq!((buffer.len(), buffer[0], buffer[255]), (256, 0, 7));
+
+            let mut value = pool.alloc_box([1.0f32; 4]).unwrap();
+            value[3] = 0.5;
+            assert_eq!(*value, [1.0, 1.0, 1.0, 0.5]);
+        }
+
+        assert_eq!(pool.stats().allocations, 0);
+        assert!(pool.stats().high_water >= 256 + 16);
+
+        // 守卫归还内存后，超过半个池的缓冲区可以反复分配
+        for _ in 0..4 {
+            assert_eq!(pool.alloc_slice(375, 1u64).unwrap().iter().sum::<u64>(), 375);
+        }
+
+        #[repr(align(16))]
+        struct Aligned16;
+        let aligned = pool.alloc_box(Aligned16).unwrap();
+        assert_eq!(&*aligned as *const Aligned16 as usize % 16, 0);
+    }
+
+    #[test]
+    fn test_aligned_allocation_for_simd_buffers() {
+        let pool = ZigMemoryPool::new(8192).unwrap();
+        let _padding = pool.alloc_slice(3, 0u8).unwrap();
+
+        for align in [32, 64] {
+            let buffer = pool.alloc_slice_aligned(768, 0.0f32, align).unwrap();
+            assert_eq!(buffer.as_ptr() as usize % align, 0);
+            assert_eq!(buffer.len(), 768);
+        }
+        assert_eq!(pool.stats().allocations, 1);
+
+        let raw = pool.allocate_aligned(100, 64).unwrap();
+        assert_eq!(raw as usize % 64, 0);
+        pool.deallocate(raw).unwrap();
+        assert!(pool.allocate_aligned(100, 48).is_err());
+    }
+
+    #[test]
+    fn test_pool_grows_up_to_cap() {
+        let fixed = ZigMemoryPool::new(1024).unwrap();
+        let _first = fixed.alloc_slice(800, 0u8).unwrap();
+        assert!(fixed.alloc_slice(800, 0u8).is_err());
+        fixed.grow(1024).unwrap();
+        let _second = fixed.alloc_slice(800, 0u8).unwrap();
+        assert_eq!(fixed.pool_size(), 2048);
+
+        let growable = ZigMemoryPool::new(1024).unwrap().with_auto_grow(3072);
+        let buffers: Vec<_> = (0..3).map(|_| growable.alloc_slice(800, 0u8).unwrap()).collect();
+        assert_eq!((buffers.len(), growable.pool_size()), (3, 3072));
+        assert!(growable.alloc_slice(800, 0u8).is_err());
+    }
+
+    #[test]
+    fn test_ffi_errors_are_diagnosable() {
+        let pool = ZigMemoryPool::new(1024).unwrap();
+        assert!(matches!(
+            pool.allocate(4096),
+            Err(MemoryError::Ffi { call: "pool_alloc", code: FfiErrorCode::OutOfMemory })
+        ));
+        assert!(matches!(pool.allocate(0), Err(MemoryError::FfiInvariant { call: "pool_alloc", .. })));
+        assert!(matches!(
+            pool.allocate_aligned(16, 48),
+            Err(MemoryError::FfiInvariant { call: "pool_alloc_aligned", .. })
+        ));
+
+        // 其他池的指针不会被接受，池的统计保持不变
+        let other = ZigMemoryPool::new(1024).unwrap();
+        let foreign = other.allocate(64).unwrap();
+        assert!(matches!(
+            pool.deallocate(foreign),
+            Err(MemoryError::Ffi { call: "pool_free", code: FfiErrorCode::InvalidPointer })
+        ));
+        assert_eq!((pool.stats().allocations, other.stats().allocations), (0, 1));
+        other.deallocate(foreign).unwrap();
+
+        assert!(matches!(
+            ZigPerformanceUtils::vector_normalize(&mut []),
+            Err(MemoryError::FfiInvariant { call: "normalize", .. })
+        ));
+        assert_eq!(FfiErrorCode::from_raw(0), None);
+        assert_eq!(FfiErrorCode::from_raw(42), Some(FfiErrorCode::Unknown(42)));
+    }
+
+    #[test]
+    fn test_arena_scope_reclaims_on_exit() {
+        let arenas = ZigArenaRegistry::new();
+
+        let total = arenas.with_arena("scratch", |arena| {
+            let scores = arena.alloc_slice(1000, 0.5f32).unwrap();
+            let ids = arena.alloc_copy(&[1u64, 2, 3]).unwrap();
+            scores[999] = 1.5;
+            assert!(arena.capacity() >= 1000 * 4);
+            scores.iter().sum::<f32>() + ids.iter().sum::<u64>() as f32
+        }).unwrap();
+        assert_eq!(total, 507.0);
+
+        // 作用域结束后竞技场被重置并放回，同名作用域复用它
+        let retained = arenas.retained_bytes("scratch");
+        assert!(retained > 0);
+        arenas.with_arena("scratch", |arena| arena.alloc_slice(100, 0u8).map(|b| b.len())).unwrap().unwrap();
+        assert_eq!(arenas.retained_bytes("scratch"), retained);
+        assert_eq!(arenas.retained_bytes("embeddings"), 0);
+    }
+
+    #[test]
+    fn test_arena_registry_limits_idle_arenas() {
+        let arenas = ZigArenaRegistry::with_limits(1, usize::MAX);
+
+        // 嵌套作用域同时使用两个竞技场，结束后只保留一个
+        arenas.with_arena("scratch", |_| {
+            arenas.with_arena("scratch", |arena| arena.alloc_slice(16, 0u8).map(|b| b.len())).unwrap().unwrap();
+        }).unwrap();
+        assert_eq!(arenas.lock()["scratch"].len(), 1);
+
+        // 保留内存超过上限的竞技场用完即释放
+        let arenas = ZigArenaRegistry::with_limits(4, 0);
+        arenas.with_arena("embeddings", |arena| arena.alloc_slice(1000, 0.5f32).map(|b| b.len())).unwrap().unwrap();
+        assert_eq!(arenas.retained_bytes("embeddings"), 0);
+    }
+
+    #[test]
+    fn test_sharded_pool_combines_stats() {
+        let pool = ShardedZigPool::new(4, 4096).unwrap();
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..100 {
+                        let mut buffer = pool.alloc_slice(128, 0.0f32).unwrap();
+                        buffer[127] = 1.0;
+                    }
+                    let _kept = pool.alloc_slice(64, 0u8).unwrap();
+                });
+            }
+        });
+
+        let stats = pool.stats();
+        assert_eq!(stats.total, 4 * 4096);
+        assert_eq!((stats.allocations, stats.used), (0, 0));
+        assert!(stats.high_water >= 512);
+        assert_eq!(pool.shard_stats().len(), pool.shard_count());
+
+        let buffer = pool.alloc_slice(10, 1u32).unwrap();
+        assert_eq!(pool.shard().stats().allocations, 1);
+        assert_eq!(pool.stats().allocations, 1);
+        drop(buffer);
+    }
+
+    #[test]
+    fn test_scratch_scopes_rewind_and_nest() {
+        let scratch = ScratchArena::new(1024).unwrap();
+        scratch.scope(|outer| {
+            let a = outer.alloc_copy(&[1.0f32, 2.0, 3.0]);
+            assert!(a.is_arena());
+            let used = scratch.used();
+
+            scratch.scope(|inner| {
+                assert!(inner.alloc_slice(16, 0u64).is_arena());
+                // 外层作用域在内层期间分配会被内层回退覆盖，必须回退到堆
+                assert!(!outer.alloc_slice(4, 0u8).is_arena());
+            });
+            assert_eq!(scratch.used(), used);
+
+            let big = outer.alloc_slice(4096, 7u8);
+            assert!(!big.is_arena());
+            assert_eq!(big.len(), 4096);
+            assert_eq!(&*a, &[1.0, 2.0, 3.0]);
+        });
+
+        assert_eq!(scratch.used(), 0);
+        assert!(scratch.high_water() >= 12 + 128);
+        assert_eq!(ScratchArena::with(|scope| scope.alloc_copy(&[5u32]).to_vec()), vec![5]);
+    }
+
     #[test]
     fn test_performance_metrics() {
         let monitor = ZigSystemMonitor::new(false, None).unwrap();
@@ -261,5 +1425,53 @@ mod tests {
         assert!(metrics.memory_usage >= 0);
         assert!(metrics.cpu_usage >= 0.0);
         assert!(metrics.cpu_usage <= 100.0); // CPU使用率应该在0-100%之间
+        assert_eq!(metrics.pool_stats, None);
+
+        let monitor = ZigSystemMonitor::new(true, Some(4096)).unwrap();
+        let pool = monitor.memory_pool().unwrap();
+        let _buffer = pool.alloc_slice(100, 0u8).unwrap();
+        let stats = monitor.get_performance_metrics().pool_stats.unwrap();
+        assert_eq!((stats.total, stats.allocations), (4096, 1));
+        assert_eq!(stats.free, stats.total - stats.used);
+    }
+
+    #[test]
+    fn test_metrics_summary_percentiles() {
+        let samples: Vec<MetricsSample> = (1..=20)
+            .map(|i| MetricsSample {
+                timestamp: Utc::now(),
+                metrics: PerformanceMetrics {
+                    memory_usage: i * 100,
+                    cpu_usage: i as f32,
+                    pool_size: None,
+                    pool_stats: None,
+                },
+            })
+            .collect();
+
+        let summary = MetricsSummary::from_samples(&samples);
+        assert_eq!(summary.samples, 20);
+        assert_eq!((summary.cpu_p50, summary.cpu_p95), (10.0, 19.0));
+        assert_eq!(summary.peak_memory_usage, 2000);
+        assert_eq!(MetricsSummary::from_samples(&[]), MetricsSummary::default());
+    }
+
+    #[tokio::test]
+    async fn test_sampling_keeps_bounded_history() {
+        let monitor = ZigSystemMonitor::new(true, Some(4096)).unwrap().with_history_capacity(3);
+        monitor.start_sampling(Duration::from_millis(5));
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        monitor.stop_sampling();
+        assert!(!monitor.is_sampling());
+
+        let history = monitor.history();
+        assert_eq!(history.len(), 3);
+        assert!(history.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+        assert!(history.iter().all(|s| s.metrics.pool_stats.is_some()));
+        assert_eq!(monitor.summary().samples, 3);
+
+        // 停止后不再追加
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(monitor.history().len(), 3);
     }
 }
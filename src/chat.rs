@@ -0,0 +1,357 @@
+//! 对话会话 - 检索记忆、更新情感、生成回复并写回记忆的完整对话循环
+//!
+//! 每轮对话依次产出`ChatEvent`：先是更新后的情感状态，然后是流式回复片段，最后是完整回复。
+//! 推理服务不可用或没有产出任何片段时，使用本地个性生成器的回复。
+//! 健康检查结果缓存`HEALTH_CHECK_TTL`，不在每轮对话中请求推理服务。
+
+use crate::bridge::{local_keywords, ChatHistory, InferenceClient};
+use crate::emotion::{AudioMetadata, EmotionalEngine, PersonalityGenerator};
+use crate::memory::{IngestRequest, MemoryManager};
+use crate::plugins::PluginRegistry;
+use crate::{EmotionalState, MemoryType, Result};
+use dashmap::DashMap;
+use futures::StreamExt;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// 每轮对话检索的记忆条数
+const CONTEXT_MEMORY_LIMIT: usize = 3;
+
+/// 机器人默认最多保留的对话会话数
+pub const DEFAULT_MAX_SESSIONS: usize = 1024;
+
+/// 推理服务健康检查结果的有效期
+pub const HEALTH_CHECK_TTL: Duration = Duration::from_secs(30);
+
+/// 推理服务健康状况的缓存，可在多个会话之间共享
+///
+/// 结果过期后下一轮对话重新检查；生成失败时立即记为不可用，有效期内直接使用本地回复。
+#[derive(Debug, Default)]
+pub struct InferenceHealth {
+    checked: std::sync::Mutex<Option<(Instant, bool)>>,
+}
+
+impl InferenceHealth {
+    /// 推理服务是否可用，缓存未过期时不发请求
+    pub async fn is_healthy(&self, inference: &dyn InferenceClient) -> bool {
+        if let Some((at, healthy)) = *self.checked.lock().unwrap_or_else(|e| e.into_inner()) {
+            if at.elapsed() < HEALTH_CHECK_TTL {
+                return healthy;
+            }
+        }
+        let healthy = inference.health_check().await;
+        self.record(healthy);
+        healthy
+    }
+
+    /// 记录一次检查或请求的结果
+    pub fn record(&self, healthy: bool) {
+        *self.checked.lock().unwrap_or_else(|e| e.into_inner()) = Some((Instant::now(), healthy));
+    }
+}
+
+/// 对话过程中的事件
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChatEvent {
+    /// 本轮输入引起的情感状态
+    Emotion { state: EmotionalState },
+    /// 回复片段
+    Token { text: String },
+    /// 本轮完整回复（已经过插件修饰），附带按情感状态生成的语音提示
+    Done { response: String, audio: AudioMetadata },
+}
+
+/// 单个用户的对话会话
+#[derive(Debug)]
+pub struct ChatSession {
+    user_id: String,
+    manager: Arc<MemoryManager>,
+    /// 未设置时只使用本地回复
+    inference: Option<Arc<dyn InferenceClient>>,
+    engine: Arc<EmotionalEngine>,
+    personality: Arc<PersonalityGenerator>,
+    history: ChatHistory,
+    plugins: Arc<PluginRegistry>,
+    health: Arc<InferenceHealth>,
+}
+
+impl ChatSession {
+    pub fn new(
+        user_id: impl Into<String>,
+        manager: Arc<MemoryManager>,
+        inference: Arc<dyn InferenceClient>,
+        engine: Arc<EmotionalEngine>,
+        personality: Arc<PersonalityGenerator>,
+    ) -> Self {
+        Self {
+            inference: Some(inference),
+            ..Self::local(user_id, manager, engine, personality)
+        }
+    }
+
+    /// 不使用推理服务的会话，回复全部来自本地个性生成器
+    pub fn local(
+        user_id: impl Into<String>,
+        manager: Arc<MemoryManager>,
+        engine: Arc<EmotionalEngine>,
+        personality: Arc<PersonalityGenerator>,
+    ) -> Self {
+        Self {
+            user_id: user_id.into(),
+            inference: None,
+            engine,
+            personality,
+            history: ChatHistory::default(),
+            plugins: manager.plugins().unwrap_or_default(),
+            health: Arc::default(),
+            manager,
+        }
+    }
+
+    /// 与其他会话共享推理服务的健康状况，默认每个会话单独缓存
+    pub fn with_health(mut self, health: Arc<InferenceHealth>) -> Self {
+        self.health = health;
+        self
+    }
+
+    /// 使用插件中的互动分析器和回复修饰器，默认使用记忆管理器的插件
+    pub fn with_plugins(mut self, plugins: Arc<PluginRegistry>) -> Self {
+        self.plugins = plugins;
+        self
+    }
+
+    pub fn user_id(&self) -> &str {
+        &self.user_id
+    }
+
+    /// 清空对话历史，记忆不受影响
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+    }
+
+    /// 处理一轮对话，`on_event`按顺序收到本轮的事件，返回完整回复
+    #[tracing::instrument(name = "chat_turn", skip_all, fields(user_id = %self.user_id))]
+    pub async fn turn(&mut self, user_input: &str, mut on_event: impl FnMut(ChatEvent)) -> Result<String> {
+        let system = self.manager.get_or_create(&self.user_id).await?;
+
+        // 检索失败不影响对话
+        let memories = system.retrieve_memories(user_input, None, Some(CONTEXT_MEMORY_LIMIT)).await
+            .unwrap_or_default();
+
+        let mut triggers = self.engine.analyze_interaction(user_input, &memories);
+        triggers.extend(self.plugins.analyze(user_input, &memories));
+        let strongest = triggers.iter()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(trigger, _)| trigger.clone());
+        let mut emotion = system.get_emotional_state().await;
+        for (trigger, intensity) in triggers {
+            emotion = self.engine.process_trigger(&emotion, trigger, intensity);
+        }
+        self.manager.update_emotion(&self.user_id, strongest, emotion.clone()).await?;
+        on_event(ChatEvent::Emotion { state: emotion.clone() });
+
+        let mut response = String::new();
+        let inference = match &self.inference {
+            Some(inference) if self.health.is_healthy(inference.as_ref()).await => Some(inference.clone()),
+            _ => None,
+        };
+        if let Some(ref inference) = inference {
+            match inference
+                .generate_response_stream_with_history(user_input, self.history.turns(), memories, emotion.clone())
+                .await
+            {
+                Ok(mut tokens) => {
+                    while let Some(token) = tokens.next().await {
+                        match token {
+                            Ok(text) => {
+                                response.push_str(&text);
+                                on_event(ChatEvent::Token { text });
+                            }
+                            Err(e) => {
+                                tracing::warn!("流式回复中断: {}", e);
+                                break;
+                            }
+                        }
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!("回复生成失败，使用本地回复: {}", e);
+                    self.health.record(false);
+                }
+            }
+        }
+        if response.is_empty() {
+            response = self.personality.generate_personalized_response("听到了！", user_input);
+            on_event(ChatEvent::Token { text: response.clone() });
+        }
+        let response = self.plugins.decorate(response, &emotion);
+
+        self.history.push_exchange(user_input, response.clone());
+
+        let audio = AudioMetadata::for_response(&response, &emotion);
+        let conversation = format!("用户说: {} | 我回复: {}", user_input, response);
+        let keywords = match inference {
+            Some(inference) => inference.extract_keywords(user_input).await.unwrap_or_else(|e| {
+                tracing::warn!("关键词提取失败，使用本地提取: {}", e);
+                local_keywords(user_input)
+            }),
+            None => local_keywords(user_input),
+        };
+        // 经管理器的写入管道保存，与其他写入一同受队列容量限制
+        let request = IngestRequest::new(MemoryType::ShortTerm, conversation, keywords, 0.5 + emotion.happiness * 0.3)
+            .with_emotional_context(Some(emotion));
+        if let Err(e) = self.manager.add_memory(&self.user_id, request).await {
+            tracing::warn!("保存对话记忆失败: {}", e);
+        }
+
+        on_event(ChatEvent::Done { response: response.clone(), audio });
+        Ok(response)
+    }
+}
+
+/// 按会话ID保存的对话会话，供聊天机器人使用
+///
+/// 达到上限时淘汰最久未使用且没有进行中对话的会话，只丢弃对话历史，记忆仍在`MemoryManager`中；
+/// 全部会话都在对话中时暂时超出上限。
+#[derive(Debug)]
+pub struct ChatSessions {
+    sessions: DashMap<String, (Arc<Mutex<ChatSession>>, Instant)>,
+    max_sessions: usize,
+}
+
+impl Default for ChatSessions {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_SESSIONS)
+    }
+}
+
+impl ChatSessions {
+    /// 最多保留`max_sessions`个会话，至少为1
+    pub fn new(max_sessions: usize) -> Self {
+        Self { sessions: DashMap::new(), max_sessions: max_sessions.max(1) }
+    }
+
+    /// 获取会话，不存在时用`create`创建
+    pub fn get_or_insert_with(&self, key: &str, create: impl FnOnce() -> ChatSession) -> Arc<Mutex<ChatSession>> {
+        if let Some(mut slot) = self.sessions.get_mut(key) {
+            slot.1 = Instant::now();
+            return slot.0.clone();
+        }
+        if self.sessions.len() >= self.max_sessions {
+            self.evict_idle();
+        }
+        self.sessions.entry(key.to_string())
+            .or_insert_with(|| (Arc::new(Mutex::new(create())), Instant::now()))
+            .0
+            .clone()
+    }
+
+    pub fn remove(&self, key: &str) {
+        self.sessions.remove(key);
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+
+    /// 淘汰最久未使用的空闲会话，有其他持有者的会话正在对话中
+    fn evict_idle(&self) {
+        let oldest = self.sessions.iter()
+            .filter(|slot| Arc::strong_count(&slot.0) == 1)
+            .min_by_key(|slot| slot.1)
+            .map(|slot| slot.key().clone());
+        if let Some(key) = oldest {
+            self.sessions.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::MockInferenceClient;
+    use crate::emotion::PersonalityProfile;
+    use crate::vector_store::MockVectorStore;
+
+    fn session(inference: MockInferenceClient) -> ChatSession {
+        let manager = Arc::new(MemoryManager::new(Arc::new(MockVectorStore::new()), None));
+        ChatSession::new(
+            "alice",
+            manager,
+            Arc::new(inference),
+            Arc::new(EmotionalEngine::new()),
+            Arc::new(PersonalityGenerator::new(PersonalityProfile::default())),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_turn_streams_events_and_saves_memory() {
+        let mut session = session(MockInferenceClient::new());
+        let mut events = Vec::new();
+        let response = session.turn("你真棒，谢谢你", |event| events.push(event)).await.unwrap();
+
+        assert!(matches!(events.first(), Some(ChatEvent::Emotion { .. })));
+        assert!(matches!(events.last(), Some(ChatEvent::Done { response: done, audio }) if *done == response && audio.ssml.contains("<prosody")));
+        assert!(response.contains("你真棒"));
+
+        let system = session.manager.get("alice").unwrap();
+        assert_eq!(system.get_memory_stats().await.get("ShortTerm"), Some(&1));
+        // 关键词按词提取，不是整句
+        let memories = system.list_memories(Some(vec![MemoryType::ShortTerm])).await.unwrap();
+        assert_eq!(memories[0].keywords, vec!["你真棒", "谢谢你"]);
+    }
+
+    #[test]
+    fn test_sessions_evict_least_recently_used_idle() {
+        let sessions = ChatSessions::new(2);
+        let busy = sessions.get_or_insert_with("a", || session(MockInferenceClient::new()));
+        sessions.get_or_insert_with("b", || session(MockInferenceClient::new()));
+        sessions.get_or_insert_with("c", || session(MockInferenceClient::new()));
+        // "a"仍被持有，淘汰空闲的"b"
+        assert_eq!(sessions.len(), 2);
+        assert!(Arc::ptr_eq(&busy, &sessions.get_or_insert_with("a", || unreachable!())));
+        assert!(sessions.sessions.contains_key("c"));
+    }
+
+    #[tokio::test]
+    async fn test_health_is_cached_between_turns() {
+        let health = Arc::new(InferenceHealth::default());
+        let mut session = session(MockInferenceClient::new()).with_health(health.clone());
+        assert!(session.turn("你好", |_| {}).await.unwrap().contains("你说: 你好"));
+
+        // 有效期内沿用缓存的结果，推理服务可用也使用本地回复
+        health.record(false);
+        assert!(!session.turn("你真棒", |_| {}).await.unwrap().contains("你说:"));
+
+        // 生成失败后记为不可用
+        let health = Arc::new(InferenceHealth::default());
+        health.record(true);
+        let mut session = session(MockInferenceClient::new().unavailable()).with_health(health.clone());
+        session.turn("在吗", |_| {}).await.unwrap();
+        assert!(!health.is_healthy(&MockInferenceClient::new()).await);
+    }
+
+    #[tokio::test]
+    async fn test_turn_falls_back_to_personality_when_inference_is_down() {
+        let mut session = session(MockInferenceClient::new().unavailable());
+        let response = session.turn("在吗", |_| {}).await.unwrap();
+        assert!(!response.is_empty());
+
+        let manager = Arc::new(MemoryManager::new(Arc::new(MockVectorStore::new()), None));
+        let mut local = ChatSession::local(
+            "bob",
+            manager,
+            Arc::new(EmotionalEngine::new()),
+            Arc::new(PersonalityGenerator::new(PersonalityProfile::default())),
+        );
+        let response = local.turn("在吗", |_| {}).await.unwrap();
+        assert!(!response.is_empty() && !response.contains("你说:"));
+    }
+}
@@ -0,0 +1,624 @@
+//! 统一配置 - 从TOML文件加载，环境变量覆盖
+//!
+//! 环境变量以`MIRA_`开头，层级之间用双下划线分隔，例如`MIRA_MEMORY__SHORT_TERM_LIMIT=50`、
+//! `MIRA_VECTOR_STORE__QDRANT__URL=http://localhost:6334`。未出现的字段取默认值，示例见`mira.example.toml`。
+//!
+//! `ConfigReloader`监视配置文件，修改后把`memory`和`emotion`中的阈值、上限、衰减率和词表应用到运行中的系统；
+//! 其余配置段需要重启才能生效。
+
+use crate::bridge::{InferenceBackendConfig, InferenceClient, InferenceScheduler, ScheduledInferenceClient};
+use crate::bridge::SchedulerConfig as InferenceSchedulerConfig;
+use crate::emotion::{EmotionLexicon, EmotionalDecayConfig, EmotionalEngine, PersonalityProfile};
+#[cfg(feature = "mqtt")]
+use crate::integrations::mqtt::MqttConfig;
+use crate::memory::embedder::{Embedder, EmbedderConfig};
+use crate::memory::{IngestConfig, MemoryManager};
+use crate::plugins::PluginRegistry;
+#[cfg(feature = "mmap")]
+use crate::vector_store::SegmentVectorStore;
+use crate::vector_store::{CodecKind, MockVectorStore, PartitionStrategy, QdrantConfig, QdrantStore, VectorStore, WriteBehindConfig};
+use crate::scheduler::{SchedulerConfig, JOB_VECTOR_SIZE, SCHEDULER_COLLECTION_SUFFIX};
+use crate::webhook::WebhookConfig;
+use crate::{MemoryConfig, MemoryError, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::sync::broadcast;
+
+/// 环境变量前缀
+pub const ENV_PREFIX: &str = "MIRA";
+
+/// 未指定路径时读取的配置文件，不存在时只使用默认值和环境变量
+pub const DEFAULT_CONFIG_FILE: &str = "mira.toml";
+
+/// 监视配置文件时检查修改时间的间隔
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 重新加载通知的缓冲条数
+pub const RELOAD_CHANNEL_CAPACITY: usize = 16;
+
+/// MIRA的全部配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MiraConfig {
+    pub memory: MemoryConfig,
+    pub emotion: EmotionSettings,
+    pub personality: PersonalitySettings,
+    pub vector_store: VectorStoreSettings,
+    pub inference: InferenceBackendConfig,
+    /// 设置后推理请求经过同一个调度器限流，交互请求优先并独占`reserved_interactive`个名额
+    pub inference_scheduler: Option<InferenceSchedulerConfig>,
+    pub server: ServerSettings,
+    pub webhooks: WebhookConfig,
+    pub scheduler: SchedulerConfig,
+    pub embedder: EmbedderConfig,
+    pub ingest: IngestConfig,
+    pub plugins: PluginSettings,
+    #[cfg(feature = "mqtt")]
+    pub mqtt: MqttConfig,
+    /// 按`inference_scheduler`创建的调度器，配置的各个推理客户端共用
+    #[serde(skip)]
+    scheduler_instance: OnceLock<Arc<InferenceScheduler>>,
+}
+
+/// 情感衰减配置和互动分析词表，未设置的字段使用默认值
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmotionSettings {
+    /// 每个衰减间隔的衰减率
+    pub base_decay_rate: Option<f32>,
+    /// 衰减间隔（小时）
+    pub decay_interval_hours: Option<u32>,
+    pub lexicon: EmotionLexicon,
+}
+
+impl EmotionSettings {
+    /// 情感衰减配置
+    pub fn decay_config(&self) -> EmotionalDecayConfig {
+        let defaults = EmotionalDecayConfig::default();
+        EmotionalDecayConfig {
+            base_decay_rate: self.base_decay_rate.unwrap_or(defaults.base_decay_rate),
+            decay_interval_hours: self.decay_interval_hours.unwrap_or(defaults.decay_interval_hours),
+            ..defaults
+        }
+    }
+}
+
+/// 个性档案来源，`path`优先于`preset`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PersonalitySettings {
+    /// 预设名称：`obedient`或`lively`
+    pub preset: Option<String>,
+    /// JSON格式的个性档案文件
+    pub path: Option<PathBuf>,
+}
+
+/// 向量存储，`qdrant`优先于`data_file`，都未设置时使用进程内存储
+///
+/// 未配置`qdrant`时可以设置`segment_file`：启动时内存映射只读段文件，新写入和修改保存在`data_file`或进程内
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VectorStoreSettings {
+    pub qdrant: Option<QdrantConfig>,
+    /// 本地持久化文件
+    pub data_file: Option<PathBuf>,
+    /// 只读段文件，需要启用`mmap`特性
+    pub segment_file: Option<PathBuf>,
+    /// 本地持久化文件和记忆payload的编码
+    pub codec: CodecKind,
+    /// 设置后记忆先写入写后缓冲，由后台任务按间隔批量写入存储
+    pub write_behind: Option<WriteBehindConfig>,
+}
+
+/// 启动时加载的插件动态库，需要启用`dynamic-plugins`特性
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PluginSettings {
+    /// 按顺序加载，库需用`declare_plugin!`导出注册函数
+    pub libraries: Vec<PathBuf>,
+}
+
+/// 服务监听地址和访问令牌
+///
+/// 默认只监听本机，对外提供服务时应同时设置`api_token`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerSettings {
+    pub addr: String,
+    pub grpc_addr: Option<String>,
+    /// 设置后REST接口要求`Authorization: Bearer <api_token>`
+    pub api_token: Option<String>,
+    /// 替换个性档案等影响所有用户的操作需要的令牌，未设置时禁止这些操作
+    pub admin_token: Option<String>,
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        Self {
+            addr: "127.0.0.1:3000".to_string(),
+            grpc_addr: None,
+            api_token: None,
+            admin_token: None,
+        }
+    }
+}
+
+impl MiraConfig {
+    /// 加载配置 - `path`为None时读取当前目录的`mira.toml`（不存在时忽略），然后应用环境变量
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let file = match path {
+            Some(path) => ::config::File::from(path).required(true),
+            None => ::config::File::with_name(DEFAULT_CONFIG_FILE).required(false),
+        };
+        Self::build(::config::Config::builder().add_source(file))
+    }
+
+    /// 从TOML文本加载，同样应用环境变量
+    pub fn from_toml(toml: &str) -> Result<Self> {
+        Self::build(::config::Config::builder().add_source(::config::File::from_str(toml, ::config::FileFormat::Toml)))
+    }
+
+    fn build(builder: ::config::ConfigBuilder<::config::builder::DefaultState>) -> Result<Self> {
+        builder
+            .add_source(
+                ::config::Environment::with_prefix(ENV_PREFIX)
+                    .prefix_separator("_")
+                    .separator("__")
+                    .try_parsing(true),
+            )
+            .build()
+            .and_then(|config| config.try_deserialize())
+            .map_err(|e| MemoryError::ConfigError(e.to_string()))
+    }
+
+    /// 检查可在运行时修改的配置
+    pub fn validate(&self) -> Result<()> {
+        self.memory.validate()?;
+        self.emotion.decay_config().validate()?;
+        self.emotion.lexicon.validate()
+    }
+
+    /// 与`other`相比有修改、但需要重启才能生效的配置段
+    pub fn restart_required(&self, other: &MiraConfig) -> Vec<&'static str> {
+        let changed = |a: serde_json::Result<serde_json::Value>, b: serde_json::Result<serde_json::Value>| {
+            a.ok() != b.ok()
+        };
+        let mut sections = Vec::new();
+        if changed(serde_json::to_value(&self.personality), serde_json::to_value(&other.personality)) {
+            sections.push("personality");
+        }
+        if changed(serde_json::to_value(&self.vector_store), serde_json::to_value(&other.vector_store)) {
+            sections.push("vector_store");
+        }
+        if changed(serde_json::to_value(&self.inference), serde_json::to_value(&other.inference)) {
+            sections.push("inference");
+        }
+        if changed(serde_json::to_value(&self.inference_scheduler), serde_json::to_value(&other.inference_scheduler)) {
+            sections.push("inference_scheduler");
+        }
+        if changed(serde_json::to_value(&self.server), serde_json::to_value(&other.server)) {
+            sections.push("server");
+        }
+        if changed(serde_json::to_value(&self.webhooks), serde_json::to_value(&other.webhooks)) {
+            sections.push("webhooks");
+        }
+        if changed(serde_json::to_value(&self.scheduler), serde_json::to_value(&other.scheduler)) {
+            sections.push("scheduler");
+        }
+        if changed(serde_json::to_value(&self.embedder), serde_json::to_value(&other.embedder)) {
+            sections.push("embedder");
+        }
+        if changed(serde_json::to_value(&self.ingest), serde_json::to_value(&other.ingest)) {
+            sections.push("ingest");
+        }
+        if changed(serde_json::to_value(&self.plugins), serde_json::to_value(&other.plugins)) {
+            sections.push("plugins");
+        }
+        #[cfg(feature = "mqtt")]
+        if changed(serde_json::to_value(&self.mqtt), serde_json::to_value(&other.mqtt)) {
+            sections.push("mqtt");
+        }
+        sections
+    }
+
+    /// 情感引擎
+    pub fn emotional_engine(&self) -> EmotionalEngine {
+        EmotionalEngine::new()
+            .with_decay_config(self.emotion.decay_config())
+            .with_lexicon(self.emotion.lexicon.clone())
+    }
+
+    /// 个性档案
+    pub fn personality_profile(&self) -> Result<PersonalityProfile> {
+        if let Some(ref path) = self.personality.path {
+            let text = std::fs::read_to_string(path)
+                .map_err(|e| MemoryError::ConfigError(format!("读取个性档案 {} 失败: {}", path.display(), e)))?;
+            return Ok(serde_json::from_str(&text)?);
+        }
+        match self.personality.preset.as_deref() {
+            None => Ok(PersonalityProfile::default()),
+            Some("obedient") => Ok(PersonalityProfile::create_obedient_girlfriend()),
+            Some("lively") => Ok(PersonalityProfile::create_lively_girlfriend()),
+            Some(other) => Err(MemoryError::ConfigError(format!("未知的个性预设: {}", other))),
+        }
+    }
+
+    /// 推理客户端，配置了`inference_scheduler`时经过共用的调度器
+    pub fn inference_client(&self) -> Result<Arc<dyn InferenceClient>> {
+        let client = self.inference.build()?;
+        Ok(match self.inference_scheduler()? {
+            Some(scheduler) => Arc::new(ScheduledInferenceClient::with_scheduler(client, scheduler)),
+            None => client,
+        })
+    }
+
+    /// 推理请求调度器，未配置时返回None；预留名额必须少于并发上限，否则后台请求无法执行
+    pub fn inference_scheduler(&self) -> Result<Option<Arc<InferenceScheduler>>> {
+        let Some(ref config) = self.inference_scheduler else {
+            return Ok(None);
+        };
+        if config.reserved_interactive >= config.max_concurrent {
+            return Err(MemoryError::ConfigError(format!(
+                "inference_scheduler.reserved_interactive ({}) 必须小于 max_concurrent ({})",
+                config.reserved_interactive, config.max_concurrent
+            )));
+        }
+        Ok(Some(self.scheduler_instance.get_or_init(|| InferenceScheduler::new(config.clone())).clone()))
+    }
+
+    /// 打开向量存储
+    pub async fn vector_store(&self) -> Result<Arc<dyn VectorStore<Error = anyhow::Error>>> {
+        let local = || -> Result<MockVectorStore> {
+            Ok(match self.vector_store.data_file {
+                Some(ref path) => MockVectorStore::persistent(path)
+                    .map_err(|e| MemoryError::VectorStoreError { message: e.to_string() })?
+                    .with_codec(self.vector_store.codec),
+                None => MockVectorStore::new(),
+            })
+        };
+        let store: Arc<dyn VectorStore<Error = anyhow::Error>> = match (&self.vector_store.qdrant, &self.vector_store.segment_file) {
+            (Some(qdrant), _) => Arc::new(QdrantStore::from_config(qdrant.clone()).await
+                .map_err(|e| MemoryError::VectorStoreError { message: e.to_string() })?),
+            #[cfg(feature = "mmap")]
+            (None, Some(segment_file)) => Arc::new(SegmentVectorStore::open(segment_file, local()?)
+                .map_err(|e| MemoryError::VectorStoreError { message: e.to_string() })?),
+            #[cfg(not(feature = "mmap"))]
+            (None, Some(_)) => {
+                return Err(MemoryError::InvalidInput("vector_store.segment_file 需要启用mmap特性".to_string()));
+            }
+            (None, None) => Arc::new(local()?),
+        };
+        Ok(store)
+    }
+
+    /// 定时任务的存储，与记忆分开保存
+    ///
+    /// 使用Qdrant时为`{collection_name}_scheduler`集合，否则为`scheduler.jobs_file`或进程内存储。
+    pub async fn scheduler_store(&self) -> Result<Arc<dyn VectorStore<Error = anyhow::Error>>> {
+        if let Some(ref qdrant) = self.vector_store.qdrant {
+            let config = QdrantConfig {
+                collection_name: format!("{}{}", qdrant.collection_name, SCHEDULER_COLLECTION_SUFFIX),
+                vector_size: JOB_VECTOR_SIZE,
+                partition: PartitionStrategy::Single,
+                emotion_vector_size: None,
+                image_vector_size: None,
+                hybrid: false,
+                ..qdrant.clone()
+            };
+            return Ok(Arc::new(QdrantStore::from_config(config).await
+                .map_err(|e| MemoryError::VectorStoreError { message: e.to_string() })?));
+        }
+        let store = match self.scheduler.jobs_file {
+            Some(ref path) => MockVectorStore::persistent(path)
+                .map_err(|e| MemoryError::VectorStoreError { message: e.to_string() })?,
+            None => MockVectorStore::new(),
+        };
+        Ok(Arc::new(store.with_vector_size(JOB_VECTOR_SIZE)))
+    }
+
+    /// 记忆内容和查询的嵌入生成器
+    pub fn embedder(&self) -> Result<Arc<dyn Embedder>> {
+        self.embedder.build(|| self.inference_client())
+    }
+
+    /// 加载`[plugins]`中的动态库，没有配置插件时返回None
+    pub fn plugins(&self) -> Result<Option<Arc<PluginRegistry>>> {
+        if self.plugins.libraries.is_empty() {
+            return Ok(None);
+        }
+        #[cfg(feature = "dynamic-plugins")]
+        {
+            let mut registry = PluginRegistry::new();
+            // SAFETY: 插件库由部署配置指定，视为可信
+            unsafe { registry.load_libraries(&self.plugins.libraries)? };
+            Ok(Some(Arc::new(registry)))
+        }
+        #[cfg(not(feature = "dynamic-plugins"))]
+        Err(MemoryError::InvalidInput("plugins.libraries 需要启用dynamic-plugins特性".to_string()))
+    }
+
+    /// 嵌入由python推理服务生成时，服务在线则校验嵌入模型维度与`[embedder]`一致，不在线时只记录警告
+    pub async fn verify_embedding_dimension(&self) -> Result<()> {
+        if !matches!(self.embedder, EmbedderConfig::Inference { .. }) {
+            return Ok(());
+        }
+        let Some(client) = self.inference.python_client()? else {
+            return Ok(());
+        };
+        if !client.health_check().await {
+            tracing::warn!("Python推理服务未运行，跳过嵌入维度校验");
+            return Ok(());
+        }
+        let capabilities = client.verify_embedding_dimension(Some(self.embedder.dimension())).await?;
+        tracing::info!("嵌入模型: {} ({}维)", capabilities.embedding_model, capabilities.embedding_dimension);
+        Ok(())
+    }
+
+    /// 按配置创建多用户记忆管理器，嵌入维度先经`verify_embedding_dimension`校验；`memory.rerank.cross_encoder_weight`大于0时重排使用`[inference]`的交叉编码器
+    pub async fn memory_manager(&self) -> Result<MemoryManager> {
+        self.verify_embedding_dimension().await?;
+        let mut manager = MemoryManager::new(self.vector_store().await?, Some(self.memory.clone()))
+            .with_embedder(self.embedder()?)
+            .with_payload_codec(self.vector_store.codec)
+            .with_ingest(self.ingest.clone());
+        if let Some(ref write_behind) = self.vector_store.write_behind {
+            manager = manager.with_write_behind(write_behind.clone());
+        }
+        if let Some(plugins) = self.plugins()? {
+            manager = manager.with_plugins(plugins);
+        }
+        // 只在启用交叉编码器时创建推理客户端，本地模型后端加载较慢
+        if self.memory.rerank.cross_encoder_weight > 0.0 {
+            manager = manager.with_rerank_inference(self.inference_client()?);
+        }
+        Ok(manager)
+    }
+}
+
+/// 配置重新加载的结果
+#[derive(Debug, Clone)]
+pub enum ConfigEvent {
+    /// 新配置已生效，`restart_required`为已修改但需要重启才能生效的配置段
+    Reloaded {
+        config: Arc<MiraConfig>,
+        restart_required: Vec<&'static str>,
+    },
+    /// 新配置无法读取或校验失败，继续使用原配置
+    Rejected { error: String },
+}
+
+/// 配置热加载 - 校验通过后一次性应用到记忆管理器和情感引擎，并通知订阅方
+#[derive(Debug)]
+pub struct ConfigReloader {
+    path: PathBuf,
+    current: RwLock<Arc<MiraConfig>>,
+    manager: Option<Arc<MemoryManager>>,
+    engine: Option<Arc<EmotionalEngine>>,
+    events: broadcast::Sender<ConfigEvent>,
+}
+
+impl ConfigReloader {
+    /// `current`为启动时从同一文件加载的配置
+    pub fn new(path: impl Into<PathBuf>, current: MiraConfig) -> Self {
+        Self {
+            path: path.into(),
+            current: RwLock::new(Arc::new(current)),
+            manager: None,
+            engine: None,
+            events: broadcast::channel(RELOAD_CHANNEL_CAPACITY).0,
+        }
+    }
+
+    /// 重新加载时更新记忆配置
+    pub fn with_manager(mut self, manager: Arc<MemoryManager>) -> Self {
+        self.manager = Some(manager);
+        self
+    }
+
+    /// 重新加载时更新情感衰减配置和词表
+    pub fn with_engine(mut self, engine: Arc<EmotionalEngine>) -> Self {
+        self.engine = Some(engine);
+        self
+    }
+
+    /// 订阅重新加载结果
+    pub fn subscribe(&self) -> broadcast::Receiver<ConfigEvent> {
+        self.events.subscribe()
+    }
+
+    /// 最近一次生效的配置
+    pub fn current(&self) -> Arc<MiraConfig> {
+        self.current.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// 重新读取配置文件，校验失败时不做任何修改
+    pub fn reload(&self) -> Result<Arc<MiraConfig>> {
+        let config = match MiraConfig::load(Some(self.path.as_path())).and_then(|config| config.validate().map(|_| config)) {
+            Ok(config) => Arc::new(config),
+            Err(e) => {
+                tracing::warn!("配置 {} 无效，继续使用原配置: {}", self.path.display(), e);
+                // 没有订阅方时发送失败，忽略即可
+                let _ = self.events.send(ConfigEvent::Rejected { error: e.to_string() });
+                return Err(e);
+            }
+        };
+
+        let restart_required = self.current().restart_required(&config);
+        if let Some(ref manager) = self.manager {
+            manager.update_config(config.memory.clone());
+        }
+        if let Some(ref engine) = self.engine {
+            engine.set_decay_config(config.emotion.decay_config());
+            engine.set_lexicon(config.emotion.lexicon.clone());
+        }
+        *self.current.write().unwrap_or_else(|e| e.into_inner()) = config.clone();
+
+        if restart_required.is_empty() {
+            tracing::info!("已重新加载配置 {}", self.path.display());
+        } else {
+            tracing::warn!("已重新加载配置 {}，{:?} 需要重启才能生效", self.path.display(), restart_required);
+        }
+        let _ = self.events.send(ConfigEvent::Reloaded { config: config.clone(), restart_required });
+        Ok(config)
+    }
+
+    /// 每隔`poll_interval`检查文件修改时间，变化时重新加载
+    pub fn watch(self: Arc<Self>, poll_interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let modified = |path: &Path| std::fs::metadata(path).and_then(|meta| meta.modified()).ok();
+            let mut last: Option<SystemTime> = modified(&self.path);
+            let mut interval = tokio::time::interval(poll_interval);
+            interval.tick().await;
+
+            loop {
+                interval.tick().await;
+                let current = modified(&self.path);
+                if current.is_some() && current != last {
+                    last = current;
+                    let _ = self.reload();
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emotion::EmotionalTrigger;
+
+    #[test]
+    #[serial_test::serial]
+    fn test_toml_with_env_override() {
+        let toml = r#"
+            [memory]
+            short_term_limit = 20
+            long_term_threshold = 0.9
+            similarity_threshold = 0.5
+            cleanup_interval = 60
+
+            [personality]
+            preset = "lively"
+
+            [inference]
+            backend = "mock"
+        "#;
+
+        let config = temp_env::with_var("MIRA_MEMORY__SHORT_TERM_LIMIT", Some("7"), || MiraConfig::from_toml(toml)).unwrap();
+        assert_eq!(config.memory.short_term_limit, 7);
+        assert_eq!(config.memory.long_term_threshold, 0.9);
+        assert!(matches!(config.inference, InferenceBackendConfig::Mock));
+        assert_eq!(config.personality_profile().unwrap().name, PersonalityProfile::create_lively_girlfriend().name);
+        assert_eq!(config.server.addr, "127.0.0.1:3000");
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_defaults_and_invalid_preset() {
+        let config = temp_env::with_var_unset("MIRA_MEMORY__SHORT_TERM_LIMIT", || MiraConfig::from_toml("")).unwrap();
+        assert_eq!(config.memory.short_term_limit, MemoryConfig::default().short_term_limit);
+        assert!(config.vector_store.qdrant.is_none());
+
+        let bad = MiraConfig::from_toml("[personality]\npreset = \"grumpy\"").unwrap();
+        assert!(matches!(bad.personality_profile(), Err(MemoryError::ConfigError(_))));
+    }
+
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn test_memory_manager_wires_cross_encoder() {
+        let config = MiraConfig::from_toml("[inference]\nbackend = \"mock\"").unwrap();
+        let system = config.memory_manager().await.unwrap().get_or_create("alice").await.unwrap();
+        assert!(system.rerank_inference.is_none());
+
+        let config = MiraConfig::from_toml(
+            "[memory.rerank]\nenabled = true\ncross_encoder_weight = 0.5\n\n[inference]\nbackend = \"mock\"",
+        ).unwrap();
+        let system = config.memory_manager().await.unwrap().get_or_create("alice").await.unwrap();
+        assert!(system.rerank_inference.is_some());
+    }
+
+    #[test]
+    fn test_inference_scheduler_is_shared_and_checked() {
+        let config = MiraConfig::from_toml(
+            "[inference]\nbackend = \"mock\"\n\n[inference_scheduler]\nmax_concurrent = 2\nreserved_interactive = 1",
+        ).unwrap();
+        let scheduler = config.inference_scheduler().unwrap().unwrap();
+        assert!(Arc::ptr_eq(&scheduler, &config.inference_scheduler().unwrap().unwrap()));
+        assert!(config.inference_client().is_ok());
+
+        let bad = MiraConfig::from_toml("[inference_scheduler]\nmax_concurrent = 1\nreserved_interactive = 1").unwrap();
+        assert!(matches!(bad.inference_client(), Err(MemoryError::ConfigError(_))));
+    }
+
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn test_memory_manager_verifies_embedding_dimension() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).and(path("/health"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET")).and(path("/capabilities"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "embedding_model": "bge-small-zh",
+                "embedding_dimension": 512,
+            })))
+            .mount(&server)
+            .await;
+
+        let toml = format!(
+            "[embedder]\nbackend = \"inference\"\ndimension = 768\n\n[inference]\nbackend = \"python\"\nurl = \"{}\"",
+            server.uri()
+        );
+        let config = MiraConfig::from_toml(&toml).unwrap();
+        assert!(matches!(
+            config.memory_manager().await,
+            Err(MemoryError::DimensionMismatch { expected: 768, actual: 512 })
+        ));
+    }
+
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn test_reload_applies_runtime_settings_and_rejects_invalid() {
+        let path = std::env::temp_dir().join(format!("mira_config_{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "[memory]\nshort_term_limit = 20\n").unwrap();
+
+        let manager = Arc::new(MemoryManager::new(Arc::new(MockVectorStore::new()), None));
+        let system = manager.get_or_create("alice").await.unwrap();
+        let engine = Arc::new(EmotionalEngine::new());
+        let reloader = ConfigReloader::new(&path, MiraConfig::load(Some(path.as_path())).unwrap())
+            .with_manager(manager.clone())
+            .with_engine(engine.clone());
+        let mut events = reloader.subscribe();
+
+        std::fs::write(&path, r#"
+            [memory]
+            short_term_limit = 30
+            [emotion]
+            base_decay_rate = 0.2
+            [emotion.lexicon]
+            praise = ["天才"]
+            [server]
+            addr = "0.0.0.0:4000"
+        "#).unwrap();
+        reloader.reload().unwrap();
+        assert_eq!(system.config().short_term_limit, 30);
+        assert_eq!(manager.config().short_term_limit, 30);
+        assert_eq!(engine.decay_config().base_decay_rate, 0.2);
+        assert!(engine.analyze_interaction("你是天才", &[]).iter().any(|(trigger, _)| *trigger == EmotionalTrigger::BeingPraised));
+        assert!(matches!(events.recv().await.unwrap(), ConfigEvent::Reloaded { ref restart_required, .. } if restart_required == &["server"]));
+
+        std::fs::write(&path, "[memory]\nsimilarity_threshold = 1.5\n").unwrap();
+        assert!(reloader.reload().is_err());
+        assert!(matches!(events.recv().await.unwrap(), ConfigEvent::Rejected { ref error } if error.contains("similarity_threshold")));
+        assert_eq!(system.config().short_term_limit, 30);
+        assert_eq!(reloader.current().emotion.lexicon.praise, vec!["天才".to_string()]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
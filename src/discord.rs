@@ -0,0 +1,210 @@
+//! Discord机器人 - 私信按用户、服务器频道按频道对应`MemoryManager`中的一个会话
+//!
+//! 私信中的每条消息都会回复，服务器频道中只回复提及机器人的消息。推理期间显示"正在输入"。
+//! 斜杠命令：`/status`查看情感状态和记忆数量，`/forget`清除当前会话的全部记忆；
+//! 服务器频道的会话由频道内所有人共享，在其中`/forget`需要管理消息权限。
+//! 会话数量有上限，超出时淘汰最久未使用的会话的对话历史。
+//! 需要在开发者后台开启Message Content特权意图。
+
+use crate::bridge::InferenceClient;
+use crate::chat::{ChatSession, ChatSessions};
+use crate::emotion::{EmotionalEngine, PersonalityGenerator, PersonalityProfile};
+use crate::memory::MemoryManager;
+use crate::Result;
+use serenity::all::{
+    async_trait, ChannelId, Client, Command, CommandInteraction, Context, CreateCommand,
+    CreateInteractionResponse, CreateInteractionResponseMessage, EventHandler, GatewayIntents, GuildId,
+    Interaction, Message, Ready, UserId,
+};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Discord单条消息的字符上限
+const MESSAGE_LIMIT: usize = 2000;
+
+/// 会话在`MemoryManager`中的用户ID
+fn session_key(guild_id: Option<GuildId>, channel_id: ChannelId, user_id: UserId) -> String {
+    match guild_id {
+        None => format!("discord:user:{}", user_id),
+        Some(_) => format!("discord:channel:{}", channel_id),
+    }
+}
+
+/// 按字符上限拆分回复
+fn split_message(text: &str) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    chars.chunks(MESSAGE_LIMIT).map(|chunk| chunk.iter().collect()).collect()
+}
+
+/// 私信中总是可以清除自己的会话，服务器频道的共享会话需要管理消息权限
+fn may_forget(command: &CommandInteraction) -> bool {
+    command.guild_id.is_none()
+        || command.member.as_ref()
+            .and_then(|member| member.permissions)
+            .is_some_and(|permissions| permissions.manage_messages())
+}
+
+/// Discord事件处理器
+#[derive(Debug)]
+pub struct DiscordBot {
+    manager: Arc<MemoryManager>,
+    inference: Arc<dyn InferenceClient>,
+    engine: Arc<EmotionalEngine>,
+    personality: Arc<PersonalityGenerator>,
+    sessions: ChatSessions,
+}
+
+impl DiscordBot {
+    pub fn new(manager: Arc<MemoryManager>, inference: Arc<dyn InferenceClient>) -> Self {
+        Self {
+            manager,
+            inference,
+            engine: Arc::new(EmotionalEngine::new()),
+            personality: Arc::new(PersonalityGenerator::new(PersonalityProfile::default())),
+            sessions: ChatSessions::default(),
+        }
+    }
+
+    /// 最多保留的对话会话数
+    pub fn with_max_sessions(mut self, max_sessions: usize) -> Self {
+        self.sessions = ChatSessions::new(max_sessions);
+        self
+    }
+
+    /// 指定个性档案
+    pub fn with_personality(mut self, profile: PersonalityProfile) -> Self {
+        self.personality = Arc::new(PersonalityGenerator::new(profile));
+        self
+    }
+
+    /// 连接Discord网关，直到连接断开
+    pub async fn run(self, token: &str) -> serenity::Result<()> {
+        let intents = GatewayIntents::GUILD_MESSAGES | GatewayIntents::DIRECT_MESSAGES | GatewayIntents::MESSAGE_CONTENT;
+        let mut client = Client::builder(token, intents).event_handler(self).await?;
+        client.start().await
+    }
+
+    fn session(&self, key: &str) -> Arc<Mutex<ChatSession>> {
+        self.sessions.get_or_insert_with(key, || ChatSession::new(
+            key,
+            self.manager.clone(),
+            self.inference.clone(),
+            self.engine.clone(),
+            self.personality.clone(),
+        ))
+    }
+
+    async fn status(&self, key: &str) -> Result<String> {
+        let system = self.manager.get_or_create(key).await?;
+        let emotion = system.get_emotional_state().await;
+        let memories = system.count_memories(None).await?;
+        Ok(format!(
+            "心情: {}\n开心 {:.2} · 亲密 {:.2} · 信任 {:.2} · 依赖 {:.2}\n记忆: {} 条",
+            emotion.mood, emotion.happiness, emotion.affection, emotion.trust, emotion.dependency, memories,
+        ))
+    }
+
+    /// 清除会话的全部记忆、情感状态和对话历史
+    async fn forget(&self, key: &str) -> Result<usize> {
+        let system = self.manager.get_or_create(key).await?;
+        let entries = system.list_memories(None).await?;
+        for entry in &entries {
+            system.delete_memory(entry.id).await?;
+        }
+        self.manager.remove(key);
+        self.sessions.remove(key);
+        Ok(entries.len())
+    }
+
+    async fn handle_command(&self, ctx: &Context, command: &CommandInteraction) {
+        let key = session_key(command.guild_id, command.channel_id, command.user.id);
+        let content = match command.data.name.as_str() {
+            "status" => self.status(&key).await,
+            "forget" if !may_forget(command) => Ok("需要管理消息权限才能清除频道的记忆".to_string()),
+            "forget" => self.forget(&key).await.map(|count| format!("已忘记 {} 条记忆", count)),
+            other => Ok(format!("未知命令: {}", other)),
+        }.unwrap_or_else(|e| format!("出错了: {}", e));
+
+        let response = CreateInteractionResponseMessage::new().content(content).ephemeral(true);
+        if let Err(e) = command.create_response(&ctx.http, CreateInteractionResponse::Message(response)).await {
+            tracing::warn!("回复斜杠命令失败: {}", e);
+        }
+    }
+}
+
+#[async_trait]
+impl EventHandler for DiscordBot {
+    async fn ready(&self, ctx: Context, ready: Ready) {
+        tracing::info!("Discord机器人已登录: {}", ready.user.name);
+        let commands = vec![
+            CreateCommand::new("status").description("查看当前情感状态和记忆数量"),
+            CreateCommand::new("forget").description("清除当前会话的全部记忆"),
+        ];
+        if let Err(e) = Command::set_global_commands(&ctx.http, commands).await {
+            tracing::warn!("注册斜杠命令失败: {}", e);
+        }
+    }
+
+    async fn message(&self, ctx: Context, msg: Message) {
+        if msg.author.bot {
+            return;
+        }
+        if msg.guild_id.is_some() && !msg.mentions_me(&ctx).await.unwrap_or(false) {
+            return;
+        }
+
+        let me = ctx.cache.current_user().id;
+        let input = msg.content
+            .replace(&format!("<@{}>", me), "")
+            .replace(&format!("<@!{}>", me), "");
+        let input = input.trim();
+        if input.is_empty() {
+            return;
+        }
+
+        let key = session_key(msg.guild_id, msg.channel_id, msg.author.id);
+        let session = self.session(&key);
+        // 生成期间显示"正在输入"
+        let typing = msg.channel_id.start_typing(&ctx.http);
+        let response = session.lock().await.turn(input, |_| {}).await;
+        typing.stop();
+
+        let response = response.unwrap_or_else(|e| {
+            tracing::warn!("对话处理失败 {}: {}", key, e);
+            "呜…刚才走神了，能再说一遍吗？".to_string()
+        });
+        for chunk in split_message(&response) {
+            if let Err(e) = msg.channel_id.say(&ctx.http, chunk).await {
+                tracing::warn!("发送Discord消息失败: {}", e);
+                break;
+            }
+        }
+    }
+
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        if let Interaction::Command(command) = interaction {
+            self.handle_command(&ctx, &command).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dms_map_to_users_and_guilds_to_channels() {
+        let (channel, user) = (ChannelId::new(10), UserId::new(20));
+        assert_eq!(session_key(None, channel, user), "discord:user:20");
+        assert_eq!(session_key(Some(GuildId::new(1)), channel, user), "discord:channel:10");
+    }
+
+    #[test]
+    fn test_split_message_respects_limit() {
+        let text = "喵".repeat(MESSAGE_LIMIT + 1);
+        let chunks = split_message(&text);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].chars().count(), MESSAGE_LIMIT);
+        assert!(split_message("").is_empty());
+    }
+}
@@ -0,0 +1,202 @@
+//! LangChain / LlamaIndex文档格式转换 - 用于在现有RAG系统与MIRA之间迁移记忆
+//!
+//! 记忆字段写入文档的`metadata`，`MemoryEntry::metadata`中的自定义字段平铺在同一层。
+//! 从外部文档导入时缺少的字段取默认值：长期记忆、重要性0.5、创建时间为当前时间；
+//! 不是UUID的文档ID会换成新ID，原ID保存在`metadata["source_id"]`中。
+
+use crate::{EmotionalState, MemoryEntry, MemoryType, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// 外部文档ID不是UUID时，原ID保存在此metadata键下
+pub const SOURCE_ID_KEY: &str = "source_id";
+
+/// LangChain `Document`，附带可选的ID和嵌入
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LangChainDocument {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub page_content: String,
+    #[serde(default)]
+    pub metadata: serde_json::Map<String, serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<Vec<f32>>,
+}
+
+/// LlamaIndex `TextNode`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LlamaIndexNode {
+    #[serde(rename = "id_")]
+    pub id: String,
+    pub text: String,
+    #[serde(default)]
+    pub metadata: serde_json::Map<String, serde_json::Value>,
+    #[serde(default)]
+    pub embedding: Option<Vec<f32>>,
+    #[serde(default = "text_node_class")]
+    pub class_name: String,
+}
+
+fn text_node_class() -> String {
+    "TextNode".to_string()
+}
+
+/// 文档metadata中的记忆字段
+#[derive(Debug, Serialize, Deserialize)]
+struct MemoryMetadata {
+    #[serde(default = "default_memory_type")]
+    memory_type: MemoryType,
+    #[serde(default)]
+    keywords: Vec<String>,
+    #[serde(default = "default_importance")]
+    importance: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    emotional_context: Option<EmotionalState>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    created_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_accessed: Option<DateTime<Utc>>,
+    #[serde(default)]
+    access_count: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires_at: Option<DateTime<Utc>>,
+    /// 自定义字段
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
+}
+
+fn default_memory_type() -> MemoryType {
+    MemoryType::LongTerm
+}
+
+fn default_importance() -> f32 {
+    0.5
+}
+
+fn to_metadata(entry: &MemoryEntry) -> Result<serde_json::Map<String, serde_json::Value>> {
+    let metadata = MemoryMetadata {
+        memory_type: entry.memory_type.clone(),
+        keywords: entry.keywords.clone(),
+        importance: entry.importance,
+        emotional_context: entry.emotional_context.clone(),
+        created_at: Some(entry.created_at),
+        last_accessed: Some(entry.last_accessed),
+        access_count: entry.access_count,
+        expires_at: entry.expires_at,
+        extra: entry.metadata.iter()
+            .map(|(key, value)| (key.clone(), serde_json::Value::String(value.clone())))
+            .collect(),
+    };
+    match serde_json::to_value(metadata)? {
+        serde_json::Value::Object(map) => Ok(map),
+        _ => unreachable!("结构体总是序列化为对象"),
+    }
+}
+
+fn from_parts(
+    id: Option<&str>,
+    content: String,
+    metadata: serde_json::Map<String, serde_json::Value>,
+    embedding: Option<Vec<f32>>,
+) -> Result<MemoryEntry> {
+    let fields: MemoryMetadata = serde_json::from_value(serde_json::Value::Object(metadata))?;
+    let mut entry = MemoryEntry::new(fields.memory_type, content, fields.keywords, fields.importance);
+
+    // 自定义字段只保留字符串，其他值保存其JSON文本
+    entry.metadata = fields.extra.into_iter()
+        .map(|(key, value)| match value {
+            serde_json::Value::String(value) => (key, value),
+            value => (key, value.to_string()),
+        })
+        .collect::<HashMap<_, _>>();
+    match id.map(|id| (id, Uuid::parse_str(id))) {
+        Some((_, Ok(id))) => entry.id = id,
+        Some((source_id, Err(_))) => {
+            entry.metadata.insert(SOURCE_ID_KEY.to_string(), source_id.to_string());
+        }
+        None => {}
+    }
+
+    entry.embedding = embedding;
+    entry.emotional_context = fields.emotional_context;
+    if let Some(created_at) = fields.created_at {
+        entry.created_at = created_at;
+    }
+    entry.last_accessed = fields.last_accessed.unwrap_or(entry.created_at);
+    entry.access_count = fields.access_count;
+    entry.expires_at = fields.expires_at;
+    Ok(entry)
+}
+
+impl LangChainDocument {
+    /// 检索返回的条目不带嵌入，需要导出嵌入时先用`MemorySystem::memory_embedding`读取并写入条目
+    pub fn from_entry(entry: &MemoryEntry) -> Result<Self> {
+        Ok(Self {
+            id: Some(entry.id.to_string()),
+            page_content: entry.content.clone(),
+            metadata: to_metadata(entry)?,
+            embedding: entry.embedding.clone(),
+        })
+    }
+
+    pub fn into_entry(self) -> Result<MemoryEntry> {
+        from_parts(self.id.as_deref(), self.page_content, self.metadata, self.embedding)
+    }
+}
+
+impl LlamaIndexNode {
+    /// 与`LangChainDocument::from_entry`相同，嵌入只取条目上已有的
+    pub fn from_entry(entry: &MemoryEntry) -> Result<Self> {
+        Ok(Self {
+            id: entry.id.to_string(),
+            text: entry.content.clone(),
+            metadata: to_metadata(entry)?,
+            embedding: entry.embedding.clone(),
+            class_name: text_node_class(),
+        })
+    }
+
+    pub fn into_entry(self) -> Result<MemoryEntry> {
+        from_parts(Some(&self.id), self.text, self.metadata, self.embedding)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_preserves_memory_fields() {
+        let mut entry = MemoryEntry::new(MemoryType::Preference, "喜欢猫咪".to_string(), vec!["猫咪".to_string()], 0.8);
+        entry.embedding = Some(vec![0.1, 0.2]);
+        entry.metadata.insert("source".to_string(), "chat".to_string());
+
+        let document = LangChainDocument::from_entry(&entry).unwrap();
+        assert_eq!(document.metadata["memory_type"], "Preference");
+        assert_eq!(document.metadata["source"], "chat");
+        let restored = document.into_entry().unwrap();
+        assert_eq!(serde_json::to_value(&restored).unwrap(), serde_json::to_value(&entry).unwrap());
+
+        let node = serde_json::to_value(LlamaIndexNode::from_entry(&entry).unwrap()).unwrap();
+        assert_eq!(node["id_"], entry.id.to_string());
+        let restored: LlamaIndexNode = serde_json::from_value(node).unwrap();
+        assert_eq!(restored.into_entry().unwrap().keywords, entry.keywords);
+    }
+
+    #[test]
+    fn test_foreign_document_gets_defaults() {
+        let document: LangChainDocument = serde_json::from_value(serde_json::json!({
+            "id": "doc-42",
+            "page_content": "用户生日在五月",
+            "metadata": { "page": 3 }
+        })).unwrap();
+
+        let entry = document.into_entry().unwrap();
+        assert_eq!(entry.memory_type, MemoryType::LongTerm);
+        assert_eq!(entry.importance, 0.5);
+        assert_eq!(entry.metadata[SOURCE_ID_KEY], "doc-42");
+        assert_eq!(entry.metadata["page"], "3");
+    }
+}
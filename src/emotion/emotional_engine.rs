@@ -5,6 +5,7 @@ use crate::{EmotionalState, MemoryEntry, MemoryType};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
 
 /// 情感触发器类型
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, Hash, PartialEq)]
@@ -29,6 +30,21 @@ pub enum EmotionalTrigger {
     UserHappiness,
 }
 
+impl EmotionalTrigger {
+    /// 所有触发器
+    pub const ALL: [EmotionalTrigger; 9] = [
+        EmotionalTrigger::PositiveInteraction,
+        EmotionalTrigger::NegativeInteraction,
+        EmotionalTrigger::BeingIgnored,
+        EmotionalTrigger::BeingPraised,
+        EmotionalTrigger::BeingCriticized,
+        EmotionalTrigger::SharingSecret,
+        EmotionalTrigger::LongConversation,
+        EmotionalTrigger::UserSadness,
+        EmotionalTrigger::UserHappiness,
+    ];
+}
+
 /// 情感变化规则
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EmotionalRule {
@@ -56,8 +72,10 @@ pub struct EmotionalEngine {
     rules: HashMap<EmotionalTrigger, EmotionalRule>,
     /// 情感表达模板
     expressions: HashMap<String, EmotionalExpression>,
-    /// 情感衰减配置
-    decay_config: EmotionalDecayConfig,
+    /// 情感衰减配置 - 可在运行时替换
+    decay_config: RwLock<Arc<EmotionalDecayConfig>>,
+    /// 互动分析词表 - 可在运行时替换
+    lexicon: RwLock<Arc<EmotionLexicon>>,
 }
 
 /// 情感衰减配置
@@ -71,6 +89,64 @@ pub struct EmotionalDecayConfig {
     pub minimum_values: EmotionalState,
 }
 
+impl EmotionalDecayConfig {
+    /// 检查取值范围
+    pub fn validate(&self) -> crate::Result<()> {
+        if !(0.0..=1.0).contains(&self.base_decay_rate) {
+            return Err(crate::MemoryError::ConfigError(format!("base_decay_rate必须在0到1之间: {}", self.base_decay_rate)));
+        }
+        if self.decay_interval_hours == 0 {
+            return Err(crate::MemoryError::ConfigError("decay_interval_hours必须大于0".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// 互动分析使用的词表，输入中每出现一个词就增加对应触发器的强度，不区分大小写
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmotionLexicon {
+    /// 正面互动
+    pub positive: Vec<String>,
+    /// 负面互动
+    pub negative: Vec<String>,
+    /// 赞美
+    pub praise: Vec<String>,
+}
+
+impl Default for EmotionLexicon {
+    fn default() -> Self {
+        let words = |words: &[&str]| words.iter().map(|word| word.to_string()).collect();
+        Self {
+            positive: words(&["喜欢", "爱", "开心", "高兴", "棒", "好", "谢谢", "感谢"]),
+            negative: words(&["讨厌", "烦", "生气", "难过", "不好", "糟糕"]),
+            praise: words(&["聪明", "可爱", "漂亮", "棒", "厉害", "完美"]),
+        }
+    }
+}
+
+impl EmotionLexicon {
+    /// 词表不能包含空词，否则任何输入都会命中
+    pub fn validate(&self) -> crate::Result<()> {
+        for (name, words) in [("positive", &self.positive), ("negative", &self.negative), ("praise", &self.praise)] {
+            if words.iter().any(|word| word.trim().is_empty()) {
+                return Err(crate::MemoryError::ConfigError(format!("词表{}包含空词", name)));
+            }
+        }
+        Ok(())
+    }
+
+    /// 词转为小写，与转为小写的输入比较
+    fn lowercased(mut self) -> Self {
+        for words in [&mut self.positive, &mut self.negative, &mut self.praise] {
+            for word in words.iter_mut() {
+                *word = word.to_lowercase();
+            }
+        }
+        self
+    }
+}
+
 impl Default for EmotionalDecayConfig {
     fn default() -> Self {
         Self {
@@ -94,7 +170,8 @@ impl EmotionalEngine {
         let mut engine = Self {
             rules: HashMap::new(),
             expressions: HashMap::new(),
-            decay_config: EmotionalDecayConfig::default(),
+            decay_config: RwLock::new(Arc::new(EmotionalDecayConfig::default())),
+            lexicon: RwLock::new(Arc::new(EmotionLexicon::default())),
         };
         
         engine.init_default_rules();
@@ -102,6 +179,38 @@ impl EmotionalEngine {
         engine
     }
 
+    /// 替换情感衰减配置
+    pub fn with_decay_config(self, decay_config: EmotionalDecayConfig) -> Self {
+        self.set_decay_config(decay_config);
+        self
+    }
+
+    /// 替换互动分析词表
+    pub fn with_lexicon(self, lexicon: EmotionLexicon) -> Self {
+        self.set_lexicon(lexicon);
+        self
+    }
+
+    /// 当前情感衰减配置
+    pub fn decay_config(&self) -> Arc<EmotionalDecayConfig> {
+        self.decay_config.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// 运行时替换情感衰减配置
+    pub fn set_decay_config(&self, decay_config: EmotionalDecayConfig) {
+        *self.decay_config.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(decay_config);
+    }
+
+    /// 当前互动分析词表
+    pub fn lexicon(&self) -> Arc<EmotionLexicon> {
+        self.lexicon.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// 运行时替换互动分析词表
+    pub fn set_lexicon(&self, lexicon: EmotionLexicon) {
+        *self.lexicon.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(lexicon.lowercased());
+    }
+
     /// 处理情感触发器
     pub fn process_trigger(
         &self,
@@ -140,37 +249,38 @@ impl EmotionalEngine {
 
     /// 应用时间衰减
     pub fn apply_time_decay(&self, state: &EmotionalState) -> EmotionalState {
+        let decay_config = self.decay_config();
         let now = Utc::now();
         let hours_passed = (now - state.timestamp).num_hours() as f32;
         
-        if hours_passed < self.decay_config.decay_interval_hours as f32 {
+        if hours_passed < decay_config.decay_interval_hours as f32 {
             return state.clone();
         }
         
-        let decay_cycles = hours_passed / self.decay_config.decay_interval_hours as f32;
-        let decay_factor = (self.decay_config.base_decay_rate * decay_cycles).min(0.5);
+        let decay_cycles = hours_passed / decay_config.decay_interval_hours as f32;
+        let decay_factor = (decay_config.base_decay_rate * decay_cycles).min(0.5);
         
         let mut new_state = state.clone();
         
         // 向基础值衰减
         new_state.happiness = self.apply_decay(
             state.happiness,
-            self.decay_config.minimum_values.happiness,
+            decay_config.minimum_values.happiness,
             decay_factor,
         );
         new_state.affection = self.apply_decay(
             state.affection,
-            self.decay_config.minimum_values.affection,
+            decay_config.minimum_values.affection,
             decay_factor,
         );
         new_state.trust = self.apply_decay(
             state.trust,
-            self.decay_config.minimum_values.trust,
+            decay_config.minimum_values.trust,
             decay_factor,
         );
         new_state.dependency = self.apply_decay(
             state.dependency,
-            self.decay_config.minimum_values.dependency,
+            decay_config.minimum_values.dependency,
             decay_factor,
         );
         
@@ -181,16 +291,16 @@ impl EmotionalEngine {
     }
 
     /// 根据用户互动分析情感触发器 - 优化版本，增加CPU密集型计算
-    pub fn analyze_interaction(&self, user_input: &str, memories: &[MemoryEntry]) -> Vec<(EmotionalTrigger, f32)> {
+    pub fn analyze_interaction(&self, user_input: &str, memories: &[Arc<MemoryEntry>]) -> Vec<(EmotionalTrigger, f32)> {
         use rayon::prelude::*;
         
         let mut triggers = Vec::new();
         let input_lower = user_input.to_lowercase();
+        let lexicon = self.lexicon();
         
         // 并行词汇分析
-        let positive_keywords = ["喜欢", "爱", "开心", "高兴", "棒", "好", "谢谢", "感谢"];
-        let positive_count = positive_keywords.par_iter()
-            .filter(|&&keyword| input_lower.contains(keyword))
+        let positive_count = lexicon.positive.par_iter()
+            .filter(|keyword| input_lower.contains(keyword.as_str()))
             .count();
         
         if positive_count > 0 {
@@ -198,9 +308,8 @@ impl EmotionalEngine {
         }
         
         // 并行负面词汇分析
-        let negative_keywords = ["讨厌", "烦", "生气", "难过", "不好", "糟糕"];
-        let negative_count = negative_keywords.par_iter()
-            .filter(|&&keyword| input_lower.contains(keyword))
+        let negative_count = lexicon.negative.par_iter()
+            .filter(|keyword| input_lower.contains(keyword.as_str()))
             .count();
         
         if negative_count > 0 {
@@ -208,9 +317,8 @@ impl EmotionalEngine {
         }
         
         // 并行赞美分析
-        let praise_keywords = ["聪明", "可爱", "漂亮", "棒", "厉害", "完美"];
-        let praise_count = praise_keywords.par_iter()
-            .filter(|&&keyword| input_lower.contains(keyword))
+        let praise_count = lexicon.praise.par_iter()
+            .filter(|keyword| input_lower.contains(keyword.as_str()))
             .count();
         
         if praise_count > 0 {
@@ -449,4 +557,16 @@ mod tests {
             matches!(trigger, EmotionalTrigger::PositiveInteraction | EmotionalTrigger::BeingPraised)
         ));
     }
+
+    #[test]
+    fn test_lexicon_ignores_case() {
+        let engine = EmotionalEngine::new().with_lexicon(EmotionLexicon {
+            positive: vec!["Awesome".to_string()],
+            negative: vec![],
+            praise: vec![],
+        });
+
+        let triggers = engine.analyze_interaction("this is AWESOME", &[]);
+        assert!(triggers.iter().any(|(trigger, _)| *trigger == EmotionalTrigger::PositiveInteraction));
+    }
 }
@@ -2,6 +2,8 @@
 
 pub mod emotional_engine;
 pub mod personality;
+pub mod voice;
 
 pub use emotional_engine::*;
 pub use personality::*;
+pub use voice::*;
@@ -210,6 +210,11 @@ impl PersonalityGenerator {
         generator
     }
 
+    /// 使用的个性档案
+    pub fn profile(&self) -> &PersonalityProfile {
+        &self.profile
+    }
+
     /// 生成个性化回复
     pub fn generate_personalized_response(&self, base_response: &str, _context: &str) -> String {
         let mut response = base_response.to_string();
@@ -0,0 +1,111 @@
+//! 语音合成提示 - 根据情感状态选择语音风格并生成SSML，供TTS前端朗读回复
+
+use crate::EmotionalState;
+use serde::{Deserialize, Serialize};
+
+/// 语音风格
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VoiceStyle {
+    /// 平静
+    Calm,
+    /// 开心
+    Cheerful,
+    /// 温柔亲昵
+    Tender,
+    /// 低落
+    Sad,
+}
+
+impl VoiceStyle {
+    /// 按情感状态选择风格：先看是否低落或开心，其次看亲密程度
+    pub fn for_state(state: &EmotionalState) -> Self {
+        if state.happiness < 0.3 {
+            Self::Sad
+        } else if state.happiness >= 0.75 {
+            Self::Cheerful
+        } else if state.affection >= 0.7 {
+            Self::Tender
+        } else {
+            Self::Calm
+        }
+    }
+}
+
+/// 回复的语音元数据
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AudioMetadata {
+    pub style: VoiceStyle,
+    /// 语速倍率，1.0为正常
+    pub rate: f32,
+    /// 音高偏移（半音）
+    pub pitch_semitones: f32,
+    /// 包含prosody标记的完整SSML
+    pub ssml: String,
+}
+
+impl AudioMetadata {
+    /// 开心时语速和音高略升，难过时略降；温柔风格放轻音量
+    pub fn for_response(text: &str, state: &EmotionalState) -> Self {
+        let style = VoiceStyle::for_state(state);
+        let happiness = state.happiness.clamp(0.0, 1.0);
+        let rate = 0.85 + happiness * 0.3;
+        let pitch_semitones = (happiness - 0.5) * 4.0;
+
+        let volume = match style {
+            VoiceStyle::Tender => " volume=\"soft\"",
+            _ => "",
+        };
+        let ssml = format!(
+            "<speak><prosody rate=\"{:.0}%\" pitch=\"{:+.1}st\"{}>{}</prosody></speak>",
+            rate * 100.0,
+            pitch_semitones,
+            volume,
+            escape_xml(text),
+        );
+
+        Self { style, rate, pitch_semitones, ssml }
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(happiness: f32, affection: f32) -> EmotionalState {
+        EmotionalState { happiness, affection, ..EmotionalState::default() }
+    }
+
+    #[test]
+    fn test_style_follows_emotion() {
+        assert_eq!(VoiceStyle::for_state(&state(0.2, 0.9)), VoiceStyle::Sad);
+        assert_eq!(VoiceStyle::for_state(&state(0.8, 0.9)), VoiceStyle::Cheerful);
+        assert_eq!(VoiceStyle::for_state(&state(0.5, 0.8)), VoiceStyle::Tender);
+        assert_eq!(VoiceStyle::for_state(&state(0.5, 0.3)), VoiceStyle::Calm);
+    }
+
+    #[test]
+    fn test_ssml_prosody_and_escaping() {
+        let audio = AudioMetadata::for_response("你好<3 & 晚安", &state(1.0, 0.3));
+        assert_eq!(audio.style, VoiceStyle::Cheerful);
+        assert_eq!(audio.ssml, "<speak><prosody rate=\"115%\" pitch=\"+2.0st\">你好&lt;3 &amp; 晚安</prosody></speak>");
+
+        let tender = AudioMetadata::for_response("想你", &state(0.5, 0.8));
+        assert!(tender.ssml.contains("rate=\"100%\" pitch=\"+0.0st\" volume=\"soft\""));
+    }
+}
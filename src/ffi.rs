@@ -0,0 +1,314 @@
+//! C ABI - 供Unity、C++、Swift等宿主嵌入记忆系统，请求和结果均为UTF-8 JSON
+//!
+//! 函数返回`MiraStatus`，失败时通过`mira_last_error`获取当前线程最近一次的错误信息。
+//! 输出的JSON字符串由调用方使用`mira_string_free`释放，头文件见`include/mira.h`。
+
+use crate::config::MiraConfig;
+use crate::vector_store::open_store;
+use crate::{MemoryError, MemorySystem, MemoryType};
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::path::PathBuf;
+use std::ptr;
+
+/// 调用结果
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MiraStatus {
+    Ok = 0,
+    /// 传入了空指针
+    NullPointer = 1,
+    /// 字符串不是UTF-8或JSON格式不正确
+    InvalidArgument = 2,
+    /// 记忆不存在
+    NotFound = 3,
+    /// 向量存储或其他内部错误
+    Internal = 4,
+}
+
+/// 记忆系统句柄，持有独立的tokio运行时
+pub struct MiraMemory {
+    runtime: tokio::runtime::Runtime,
+    system: MemorySystem,
+}
+
+/// `mira_memory_new`的可选配置
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct OpenOptions {
+    qdrant_url: Option<String>,
+    collection: Option<String>,
+    /// 未指定时使用默认记忆配置和本地哈希嵌入
+    config_path: Option<PathBuf>,
+}
+
+/// `mira_add_memory`的请求
+#[derive(Debug, Deserialize)]
+struct AddRequest {
+    memory_type: MemoryType,
+    content: String,
+    #[serde(default)]
+    keywords: Vec<String>,
+    #[serde(default = "default_importance")]
+    importance: f32,
+}
+
+fn default_importance() -> f32 {
+    0.5
+}
+
+/// `mira_retrieve`的请求
+#[derive(Debug, Deserialize)]
+struct RetrieveRequest {
+    query: String,
+    #[serde(default)]
+    memory_types: Option<Vec<MemoryType>>,
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn fail(status: MiraStatus, message: impl ToString) -> MiraStatus {
+    let message = CString::new(message.to_string().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+    status
+}
+
+fn fail_memory(error: MemoryError) -> MiraStatus {
+    let status = match error {
+        MemoryError::NotFound { .. } => MiraStatus::NotFound,
+        MemoryError::InvalidInput(_) | MemoryError::DimensionMismatch { .. } => MiraStatus::InvalidArgument,
+        _ => MiraStatus::Internal,
+    };
+    fail(status, error)
+}
+
+/// 读取调用方传入的字符串
+///
+/// # Safety
+/// `ptr`为空或指向以NUL结尾的字符串
+unsafe fn read_str<'a>(ptr: *const c_char, name: &str) -> Result<&'a str, MiraStatus> {
+    if ptr.is_null() {
+        return Err(fail(MiraStatus::NullPointer, format!("{} 为空指针", name)));
+    }
+    unsafe { CStr::from_ptr(ptr) }
+        .to_str()
+        .map_err(|e| fail(MiraStatus::InvalidArgument, format!("{} 不是UTF-8: {}", name, e)))
+}
+
+/// 读取并解析JSON参数
+///
+/// # Safety
+/// 同`read_str`
+unsafe fn read_json<T: for<'de> Deserialize<'de>>(ptr: *const c_char, name: &str) -> Result<T, MiraStatus> {
+    let text = unsafe { read_str(ptr, name) }?;
+    serde_json::from_str(text).map_err(|e| fail(MiraStatus::InvalidArgument, format!("{} JSON无效: {}", name, e)))
+}
+
+/// 将结果序列化为调用方持有的字符串
+fn write_json<T: serde::Serialize>(value: &T, out: *mut *mut c_char) -> MiraStatus {
+    match serde_json::to_string(value).map(CString::new) {
+        Ok(Ok(json)) => {
+            unsafe { *out = json.into_raw() };
+            MiraStatus::Ok
+        }
+        Ok(Err(e)) => fail(MiraStatus::Internal, e),
+        Err(e) => fail(MiraStatus::Internal, e),
+    }
+}
+
+/// 创建记忆系统
+///
+/// `options_json`可为空，或为`{"qdrant_url": "...", "collection": "...", "config_path": "mira.toml"}`；
+/// 未指定Qdrant时使用进程内存储，`config_path`中的`[memory]`、`[embedder]`和`[inference]`决定记忆配置和嵌入生成器。
+///
+/// # Safety
+/// `user_id`为以NUL结尾的UTF-8字符串，`options_json`为空或同上，`out`为有效指针。
+/// 得到的句柄必须用`mira_memory_free`释放。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mira_memory_new(
+    user_id: *const c_char,
+    options_json: *const c_char,
+    out: *mut *mut MiraMemory,
+) -> MiraStatus {
+    if out.is_null() {
+        return fail(MiraStatus::NullPointer, "out 为空指针");
+    }
+    let user_id = match unsafe { read_str(user_id, "user_id") } {
+        Ok(user_id) => user_id.to_string(),
+        Err(status) => return status,
+    };
+    let options: OpenOptions = if options_json.is_null() {
+        OpenOptions::default()
+    } else {
+        match unsafe { read_json(options_json, "options_json") } {
+            Ok(options) => options,
+            Err(status) => return status,
+        }
+    };
+
+    let runtime = match tokio::runtime::Builder::new_multi_thread().enable_all().build() {
+        Ok(runtime) => runtime,
+        Err(e) => return fail(MiraStatus::Internal, e),
+    };
+    let system = runtime.block_on(async {
+        let config = match options.config_path {
+            Some(ref path) => MiraConfig::load(Some(path))?,
+            None => MiraConfig::default(),
+        };
+        let embedder = config.embedder()?;
+        let vector_store = open_store(options.qdrant_url.as_deref(), options.collection, embedder.dimension()).await
+            .map_err(|e| MemoryError::VectorStoreError { message: e.to_string() })?;
+        MemorySystem::new_with_embedder(user_id, vector_store, Some(config.memory), embedder).await
+    });
+    match system {
+        Ok(system) => {
+            unsafe { *out = Box::into_raw(Box::new(MiraMemory { runtime, system })) };
+            MiraStatus::Ok
+        }
+        Err(e) => fail_memory(e),
+    }
+}
+
+/// 释放记忆系统，传入空指针时不做任何事
+///
+/// # Safety
+/// `memory`为`mira_memory_new`返回且尚未释放的句柄
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mira_memory_free(memory: *mut MiraMemory) {
+    if !memory.is_null() {
+        drop(unsafe { Box::from_raw(memory) });
+    }
+}
+
+/// 添加记忆，`out_json`得到`{"id": "..."}`
+///
+/// 请求格式：`{"memory_type": "Preference", "content": "...", "keywords": [...], "importance": 0.8}`
+///
+/// # Safety
+/// `memory`为有效句柄，`request_json`为以NUL结尾的UTF-8字符串，`out_json`为有效指针
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mira_add_memory(
+    memory: *mut MiraMemory,
+    request_json: *const c_char,
+    out_json: *mut *mut c_char,
+) -> MiraStatus {
+    let Some(memory) = (unsafe { memory.as_ref() }) else {
+        return fail(MiraStatus::NullPointer, "memory 为空指针");
+    };
+    if out_json.is_null() {
+        return fail(MiraStatus::NullPointer, "out_json 为空指针");
+    }
+    let request: AddRequest = match unsafe { read_json(request_json, "request_json") } {
+        Ok(request) => request,
+        Err(status) => return status,
+    };
+
+    let result = memory.runtime.block_on(memory.system.add_memory(
+        request.memory_type,
+        request.content,
+        request.keywords,
+        request.importance,
+        None,
+    ));
+    match result {
+        Ok(id) => write_json(&serde_json::json!({ "id": id }), out_json),
+        Err(e) => fail_memory(e),
+    }
+}
+
+/// 检索相关记忆，`out_json`得到记忆条目数组（不含嵌入向量）
+///
+/// 请求格式：`{"query": "...", "memory_types": ["Preference"], "limit": 5}`
+///
+/// # Safety
+/// 同`mira_add_memory`
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mira_retrieve(
+    memory: *mut MiraMemory,
+    query_json: *const c_char,
+    out_json: *mut *mut c_char,
+) -> MiraStatus {
+    let Some(memory) = (unsafe { memory.as_ref() }) else {
+        return fail(MiraStatus::NullPointer, "memory 为空指针");
+    };
+    if out_json.is_null() {
+        return fail(MiraStatus::NullPointer, "out_json 为空指针");
+    }
+    let request: RetrieveRequest = match unsafe { read_json(query_json, "query_json") } {
+        Ok(request) => request,
+        Err(status) => return status,
+    };
+
+    let result = memory.runtime.block_on(
+        memory.system.retrieve_memories(&request.query, request.memory_types, request.limit),
+    );
+    match result {
+        Ok(entries) => write_json(&entries, out_json),
+        Err(e) => fail_memory(e),
+    }
+}
+
+/// 释放本库返回的字符串，传入空指针时不做任何事
+///
+/// # Safety
+/// `s`为本库输出且尚未释放的字符串
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mira_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
+
+/// 当前线程最近一次失败的错误信息，没有时返回空指针；下次失败前有效，调用方不得释放
+#[unsafe(no_mangle)]
+pub extern "C" fn mira_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn take_string(s: *mut c_char) -> String {
+        let text = unsafe { CStr::from_ptr(s) }.to_str().unwrap().to_string();
+        unsafe { mira_string_free(s) };
+        text
+    }
+
+    #[test]
+    fn test_c_abi_round_trip() {
+        let user_id = CString::new("test_user").unwrap();
+        let mut memory = ptr::null_mut();
+        assert_eq!(unsafe { mira_memory_new(user_id.as_ptr(), ptr::null(), &mut memory) }, MiraStatus::Ok);
+
+        let request = CString::new(r#"{"memory_type": "Preference", "content": "用户喜欢猫咪", "keywords": ["猫咪"]}"#).unwrap();
+        let mut out = ptr::null_mut();
+        assert_eq!(unsafe { mira_add_memory(memory, request.as_ptr(), &mut out) }, MiraStatus::Ok);
+        let added: serde_json::Value = serde_json::from_str(&take_string(out)).unwrap();
+
+        let query = CString::new(r#"{"query": "用户喜欢猫咪", "limit": 1}"#).unwrap();
+        assert_eq!(unsafe { mira_retrieve(memory, query.as_ptr(), &mut out) }, MiraStatus::Ok);
+        let entries: serde_json::Value = serde_json::from_str(&take_string(out)).unwrap();
+        assert_eq!(entries[0]["id"], added["id"]);
+        assert!(entries[0]["embedding"].is_null());
+
+        let invalid = CString::new(r#"{"content": 1}"#).unwrap();
+        assert_eq!(unsafe { mira_add_memory(memory, invalid.as_ptr(), &mut out) }, MiraStatus::InvalidArgument);
+        assert!(!mira_last_error().is_null());
+        assert_eq!(unsafe { mira_retrieve(ptr::null_mut(), query.as_ptr(), &mut out) }, MiraStatus::NullPointer);
+
+        unsafe { mira_memory_free(memory) };
+    }
+
+    #[test]
+    fn test_checked_in_header_is_current() {
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/mira.h"));
+        let checked_in = include_str!("../include/mira.h");
+        assert_eq!(generated, checked_in, "include/mira.h已过期，运行make build-ffi更新");
+    }
+}
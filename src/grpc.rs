@@ -0,0 +1,385 @@
+//! gRPC记忆服务 - 与REST接口共用多用户记忆管理器，协议定义见`proto/mira/v1/memory.proto`
+
+use crate::emotion::{EmotionalEngine, EmotionalTrigger};
+use crate::memory::{EmotionChange, IngestRequest, MemoryManager};
+use crate::{EmotionalState, MemoryEntry, MemoryError, MemoryType};
+use chrono::{DateTime, Utc};
+use futures::stream::{self, BoxStream, StreamExt};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tonic::{Request, Response, Status};
+use uuid::Uuid;
+
+/// 由protoc生成的消息和服务代码
+pub mod proto {
+    tonic::include_proto!("mira.v1");
+}
+
+use proto::memory_service_server::{MemoryService, MemoryServiceServer};
+
+impl From<MemoryError> for Status {
+    fn from(error: MemoryError) -> Self {
+        match error {
+            MemoryError::NotFound { .. } => Status::not_found(error.to_string()),
+            MemoryError::InvalidInput(_) | MemoryError::DimensionMismatch { .. } => {
+                Status::invalid_argument(error.to_string())
+            }
+            MemoryError::InferenceUnavailable(_)
+            | MemoryError::ModelOverloaded(_)
+            | MemoryError::IngestQueueFull { .. } => {
+                Status::unavailable(error.to_string())
+            }
+            other => Status::internal(other.to_string()),
+        }
+    }
+}
+
+fn memory_type_from_proto(value: i32) -> Result<MemoryType, Status> {
+    match proto::MemoryType::try_from(value) {
+        Ok(proto::MemoryType::ShortTerm) => Ok(MemoryType::ShortTerm),
+        Ok(proto::MemoryType::LongTerm) => Ok(MemoryType::LongTerm),
+        Ok(proto::MemoryType::Emotional) => Ok(MemoryType::Emotional),
+        Ok(proto::MemoryType::Preference) => Ok(MemoryType::Preference),
+        Ok(proto::MemoryType::Relationship) => Ok(MemoryType::Relationship),
+        Ok(proto::MemoryType::Unspecified) | Err(_) => {
+            Err(Status::invalid_argument(format!("无效的记忆类型: {}", value)))
+        }
+    }
+}
+
+fn memory_type_to_proto(memory_type: &MemoryType) -> proto::MemoryType {
+    match memory_type {
+        MemoryType::ShortTerm => proto::MemoryType::ShortTerm,
+        MemoryType::LongTerm => proto::MemoryType::LongTerm,
+        MemoryType::Emotional => proto::MemoryType::Emotional,
+        MemoryType::Preference => proto::MemoryType::Preference,
+        MemoryType::Relationship => proto::MemoryType::Relationship,
+    }
+}
+
+fn trigger_from_proto(value: i32) -> Result<EmotionalTrigger, Status> {
+    use proto::EmotionalTrigger as Proto;
+    match Proto::try_from(value) {
+        Ok(Proto::PositiveInteraction) => Ok(EmotionalTrigger::PositiveInteraction),
+        Ok(Proto::NegativeInteraction) => Ok(EmotionalTrigger::NegativeInteraction),
+        Ok(Proto::BeingIgnored) => Ok(EmotionalTrigger::BeingIgnored),
+        Ok(Proto::BeingPraised) => Ok(EmotionalTrigger::BeingPraised),
+        Ok(Proto::BeingCriticized) => Ok(EmotionalTrigger::BeingCriticized),
+        Ok(Proto::SharingSecret) => Ok(EmotionalTrigger::SharingSecret),
+        Ok(Proto::LongConversation) => Ok(EmotionalTrigger::LongConversation),
+        Ok(Proto::UserSadness) => Ok(EmotionalTrigger::UserSadness),
+        Ok(Proto::UserHappiness) => Ok(EmotionalTrigger::UserHappiness),
+        Ok(Proto::Unspecified) | Err(_) => {
+            Err(Status::invalid_argument(format!("无效的情感触发器: {}", value)))
+        }
+    }
+}
+
+fn trigger_to_proto(trigger: &EmotionalTrigger) -> proto::EmotionalTrigger {
+    use proto::EmotionalTrigger as Proto;
+    match trigger {
+        EmotionalTrigger::PositiveInteraction => Proto::PositiveInteraction,
+        EmotionalTrigger::NegativeInteraction => Proto::NegativeInteraction,
+        EmotionalTrigger::BeingIgnored => Proto::BeingIgnored,
+        EmotionalTrigger::BeingPraised => Proto::BeingPraised,
+        EmotionalTrigger::BeingCriticized => Proto::BeingCriticized,
+        EmotionalTrigger::SharingSecret => Proto::SharingSecret,
+        EmotionalTrigger::LongConversation => Proto::LongConversation,
+        EmotionalTrigger::UserSadness => Proto::UserSadness,
+        EmotionalTrigger::UserHappiness => Proto::UserHappiness,
+    }
+}
+
+impl From<EmotionalState> for proto::EmotionalState {
+    fn from(state: EmotionalState) -> Self {
+        Self {
+            happiness: state.happiness,
+            affection: state.affection,
+            trust: state.trust,
+            dependency: state.dependency,
+            mood: state.mood,
+            timestamp_ms: state.timestamp.timestamp_millis(),
+        }
+    }
+}
+
+impl From<proto::EmotionalState> for EmotionalState {
+    fn from(state: proto::EmotionalState) -> Self {
+        Self {
+            happiness: state.happiness.clamp(0.0, 1.0),
+            affection: state.affection.clamp(0.0, 1.0),
+            trust: state.trust.clamp(0.0, 1.0),
+            dependency: state.dependency.clamp(0.0, 1.0),
+            mood: state.mood,
+            // 未设置时间戳时视为当前时刻
+            timestamp: DateTime::from_timestamp_millis(state.timestamp_ms)
+                .filter(|_| state.timestamp_ms > 0)
+                .unwrap_or_else(Utc::now),
+        }
+    }
+}
+
+impl From<MemoryEntry> for proto::MemoryEntry {
+    fn from(entry: MemoryEntry) -> Self {
+        Self {
+            id: entry.id.to_string(),
+            memory_type: memory_type_to_proto(&entry.memory_type).into(),
+            content: entry.content,
+            keywords: entry.keywords,
+            importance: entry.importance,
+            emotional_context: entry.emotional_context.map(Into::into),
+            created_at_ms: entry.created_at.timestamp_millis(),
+            last_accessed_ms: entry.last_accessed.timestamp_millis(),
+            access_count: entry.access_count,
+            metadata: entry.metadata,
+        }
+    }
+}
+
+impl From<EmotionChange> for proto::EmotionUpdate {
+    fn from(change: EmotionChange) -> Self {
+        Self {
+            user_id: change.user_id,
+            trigger: change.trigger.as_ref()
+                .map_or(proto::EmotionalTrigger::Unspecified, trigger_to_proto)
+                .into(),
+            state: Some(change.state.into()),
+        }
+    }
+}
+
+/// gRPC服务实现
+#[derive(Debug)]
+pub struct MemoryGrpcService {
+    manager: Arc<MemoryManager>,
+    engine: Arc<EmotionalEngine>,
+}
+
+impl MemoryGrpcService {
+    /// 默认使用记忆管理器的情感引擎，与REST共享热加载的配置
+    pub fn new(manager: Arc<MemoryManager>) -> Self {
+        let engine = manager.emotional_engine().unwrap_or_default();
+        Self { manager, engine }
+    }
+
+    /// 使用指定的情感引擎
+    pub fn with_engine(mut self, engine: Arc<EmotionalEngine>) -> Self {
+        self.engine = engine;
+        self
+    }
+
+    /// 包装为可挂载到tonic路由的服务
+    pub fn into_server(self) -> MemoryServiceServer<Self> {
+        MemoryServiceServer::new(self)
+    }
+}
+
+/// 在指定地址启动gRPC服务，直到进程退出
+pub async fn serve_grpc(addr: SocketAddr, manager: Arc<MemoryManager>) -> Result<(), tonic::transport::Error> {
+    tracing::info!("MIRA gRPC 监听 {}", addr);
+    tonic::transport::Server::builder()
+        .add_service(MemoryGrpcService::new(manager).into_server())
+        .serve(addr)
+        .await
+}
+
+/// 在指定地址启动gRPC服务，`signal`完成后停止接收请求
+pub async fn serve_grpc_with_shutdown(
+    addr: SocketAddr,
+    manager: Arc<MemoryManager>,
+    signal: impl std::future::Future<Output = ()> + Send,
+) -> Result<(), tonic::transport::Error> {
+    tracing::info!("MIRA gRPC 监听 {}", addr);
+    tonic::transport::Server::builder()
+        .add_service(MemoryGrpcService::new(manager).into_server())
+        .serve_with_shutdown(addr, signal)
+        .await
+}
+
+#[tonic::async_trait]
+impl MemoryService for MemoryGrpcService {
+    async fn add_memory(
+        &self,
+        request: Request<proto::AddMemoryRequest>,
+    ) -> Result<Response<proto::AddMemoryResponse>, Status> {
+        let request = request.into_inner();
+        let memory_type = memory_type_from_proto(request.memory_type)?;
+        let importance = request.importance.unwrap_or(0.5);
+        let emotional_context = request.emotional_context.map(EmotionalState::from);
+        let embedding = (!request.embedding.is_empty()).then_some(request.embedding);
+
+        // 写入管道排满时返回UNAVAILABLE，由客户端稍后重试
+        let id = self.manager.try_add_memory(
+            &request.user_id,
+            IngestRequest::new(memory_type, request.content, request.keywords, importance)
+                .with_emotional_context(emotional_context)
+                .with_embedding(embedding),
+        ).await?;
+        Ok(Response::new(proto::AddMemoryResponse { id: id.to_string() }))
+    }
+
+    async fn retrieve(
+        &self,
+        request: Request<proto::RetrieveRequest>,
+    ) -> Result<Response<proto::RetrieveResponse>, Status> {
+        let request = request.into_inner();
+        let memory_types = if request.memory_types.is_empty() {
+            None
+        } else {
+            Some(request.memory_types.into_iter().map(memory_type_from_proto).collect::<Result<Vec<_>, _>>()?)
+        };
+        let limit = request.limit.map(|limit| limit as usize);
+        let system = self.manager.get_or_create(&request.user_id).await?;
+
+        let entries = if request.query_embedding.is_empty() {
+            system.retrieve_memories(&request.query, memory_types, limit).await?
+        } else {
+            system.retrieve_by_embedding(request.query_embedding, memory_types, limit).await?
+        };
+        Ok(Response::new(proto::RetrieveResponse {
+            entries: entries.into_iter().map(|entry| Arc::unwrap_or_clone(entry).into()).collect(),
+        }))
+    }
+
+    async fn delete_memory(
+        &self,
+        request: Request<proto::DeleteMemoryRequest>,
+    ) -> Result<Response<proto::DeleteMemoryResponse>, Status> {
+        let request = request.into_inner();
+        let id = Uuid::parse_str(&request.id)
+            .map_err(|e| Status::invalid_argument(format!("无效的记忆ID {}: {}", request.id, e)))?;
+        self.manager.get_or_create(&request.user_id).await?.delete_memory(id).await?;
+        Ok(Response::new(proto::DeleteMemoryResponse {}))
+    }
+
+    async fn get_emotion(
+        &self,
+        request: Request<proto::GetEmotionRequest>,
+    ) -> Result<Response<proto::EmotionalState>, Status> {
+        let system = self.manager.get_or_create(&request.into_inner().user_id).await?;
+        Ok(Response::new(system.get_emotional_state().await.into()))
+    }
+
+    async fn apply_emotion_event(
+        &self,
+        request: Request<proto::EmotionEvent>,
+    ) -> Result<Response<proto::EmotionalState>, Status> {
+        let request = request.into_inner();
+        let trigger = trigger_from_proto(request.trigger)?;
+        let current = self.manager.get_or_create(&request.user_id).await?.get_emotional_state().await;
+        let state = self.engine.process_trigger(&current, trigger.clone(), request.intensity.unwrap_or(1.0));
+        self.manager.update_emotion(&request.user_id, Some(trigger), state.clone()).await?;
+        Ok(Response::new(state.into()))
+    }
+
+    type SubscribeEmotionsStream = BoxStream<'static, Result<proto::EmotionUpdate, Status>>;
+
+    async fn subscribe_emotions(
+        &self,
+        request: Request<proto::SubscribeEmotionsRequest>,
+    ) -> Result<Response<Self::SubscribeEmotionsStream>, Status> {
+        let user_id = request.into_inner().user_id;
+        let receiver = self.manager.subscribe_emotions();
+
+        let updates = stream::unfold(receiver, move |mut receiver| {
+            let user_id = user_id.clone();
+            async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(change) if user_id.is_empty() || change.user_id == user_id => {
+                            return Some((Ok(change.into()), receiver));
+                        }
+                        Ok(_) => continue,
+                        Err(RecvError::Lagged(skipped)) => {
+                            tracing::warn!("情感变化订阅落后，跳过 {} 条通知", skipped);
+                        }
+                        Err(RecvError::Closed) => return None,
+                    }
+                }
+            }
+        });
+        Ok(Response::new(updates.boxed()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::MockVectorStore;
+    use proto::memory_service_client::MemoryServiceClient;
+
+    async fn spawn_server() -> MemoryServiceClient<tonic::transport::Channel> {
+        let manager = Arc::new(MemoryManager::new(Arc::new(MockVectorStore::new()), None));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(MemoryGrpcService::new(manager).into_server())
+                .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener)),
+        );
+        MemoryServiceClient::connect(format!("http://{}", addr)).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_add_retrieve_and_delete() {
+        let mut client = spawn_server().await;
+
+        let id = client.add_memory(proto::AddMemoryRequest {
+            user_id: "alice".to_string(),
+            memory_type: proto::MemoryType::Preference.into(),
+            content: "用户喜欢猫咪".to_string(),
+            keywords: vec!["猫咪".to_string()],
+            ..Default::default()
+        }).await.unwrap().into_inner().id;
+
+        let entries = client.retrieve(proto::RetrieveRequest {
+            user_id: "alice".to_string(),
+            query: "用户喜欢猫咪".to_string(),
+            memory_types: vec![proto::MemoryType::Preference.into()],
+            ..Default::default()
+        }).await.unwrap().into_inner().entries;
+        assert_eq!(entries[0].id, id);
+        assert_eq!(entries[0].memory_type(), proto::MemoryType::Preference);
+
+        let request = proto::DeleteMemoryRequest { user_id: "alice".to_string(), id };
+        client.delete_memory(request.clone()).await.unwrap();
+        assert_eq!(client.delete_memory(request).await.unwrap_err().code(), tonic::Code::NotFound);
+
+        let status = client.add_memory(proto::AddMemoryRequest {
+            user_id: "alice".to_string(),
+            content: "没有类型".to_string(),
+            ..Default::default()
+        }).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_emotion_events_are_streamed() {
+        let mut client = spawn_server().await;
+        let mut updates = client.subscribe_emotions(proto::SubscribeEmotionsRequest {
+            user_id: "alice".to_string(),
+        }).await.unwrap().into_inner();
+
+        for user_id in ["bob", "alice"] {
+            client.apply_emotion_event(proto::EmotionEvent {
+                user_id: user_id.to_string(),
+                trigger: proto::EmotionalTrigger::BeingPraised.into(),
+                intensity: None,
+            }).await.unwrap();
+        }
+
+        let update = updates.message().await.unwrap().unwrap();
+        assert_eq!(update.user_id, "alice");
+        assert_eq!(update.trigger(), proto::EmotionalTrigger::BeingPraised);
+        assert_eq!(update.state.unwrap().mood, "害羞");
+    }
+
+    #[test]
+    fn test_service_shares_manager_engine() {
+        let engine = Arc::new(EmotionalEngine::new());
+        let manager = MemoryManager::new(Arc::new(MockVectorStore::new()), None).with_emotional_engine(engine.clone());
+        let service = MemoryGrpcService::new(Arc::new(manager));
+        assert!(Arc::ptr_eq(&service.engine, &engine));
+    }
+}
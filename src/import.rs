@@ -0,0 +1,475 @@
+//! 聊天记录导入 - 把WhatsApp、Telegram或通用JSON导出的历史对话转为记忆
+//!
+//! 连续的消息按时间间隔和长度切成片段，每个片段是一条记忆，创建时间为片段第一条消息的时间。
+//! 导出文件中没有时区的时间按UTC处理。
+
+use crate::bridge::{local_keywords, InferenceClient};
+use crate::memory::IngestPipeline;
+use crate::{MemoryEntry, MemoryError, MemorySystem, MemoryType, Result};
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// 一条聊天消息
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    #[serde(alias = "date", alias = "time")]
+    pub timestamp: DateTime<Utc>,
+    #[serde(alias = "from", alias = "author")]
+    pub sender: String,
+    #[serde(alias = "content", alias = "message")]
+    pub text: String,
+}
+
+/// 导出文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatFormat {
+    /// WhatsApp"导出聊天"生成的文本，支持Android和iOS两种行格式
+    WhatsApp,
+    /// Telegram Desktop导出的`result.json`，单个聊天或完整导出均可
+    Telegram,
+    /// `ChatMessage`数组，或带`messages`字段的对象
+    Json,
+}
+
+impl std::str::FromStr for ChatFormat {
+    type Err = MemoryError;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "whatsapp" => Ok(Self::WhatsApp),
+            "telegram" => Ok(Self::Telegram),
+            "json" => Ok(Self::Json),
+            other => Err(MemoryError::InvalidInput(format!("未知的聊天记录格式: {}", other))),
+        }
+    }
+}
+
+impl ChatFormat {
+    /// 解析导出文件内容，按时间排序
+    pub fn parse(&self, text: &str) -> Result<Vec<ChatMessage>> {
+        let mut messages = match self {
+            Self::WhatsApp => parse_whatsapp(text)?,
+            Self::Telegram => parse_telegram(text)?,
+            Self::Json => parse_json(text)?,
+        };
+        messages.sort_by_key(|message| message.timestamp);
+        Ok(messages)
+    }
+}
+
+/// WhatsApp消息行的时间戳和剩余部分
+struct WhatsAppHeader<'a> {
+    date: [u32; 3],
+    time: NaiveTime,
+    rest: &'a str,
+}
+
+fn parse_whatsapp_header(line: &str) -> Option<WhatsAppHeader<'_>> {
+    let line = line.trim_start_matches(['\u{200e}', '\u{feff}']);
+    // iOS: [31/12/2023, 21:05:12] Alice: 文本
+    // Android: 31/12/23, 21:05 - Alice: 文本
+    let (stamp, rest) = match line.strip_prefix('[') {
+        Some(line) => line.split_once("] ")?,
+        None => line.split_once(" - ")?,
+    };
+    let (date, time) = stamp.split_once(", ")?;
+
+    let mut parts = date.split(['/', '.', '-']).map(|part| part.parse::<u32>().ok());
+    let date = [parts.next()??, parts.next()??, parts.next()??];
+    if parts.next().is_some() {
+        return None;
+    }
+
+    // 新版导出在AM/PM前使用窄不换行空格
+    let time = time.replace(['\u{202f}', '\u{a0}'], " ");
+    let (time, meridiem) = match time.rsplit_once(' ') {
+        Some((time, meridiem)) => (time, Some(meridiem.to_ascii_uppercase())),
+        None => (time.as_str(), None),
+    };
+    let time = NaiveTime::parse_from_str(time, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M"))
+        .ok()?;
+    let time = match meridiem.as_deref() {
+        None => time,
+        Some("AM") if time.hour() == 12 => time - Duration::hours(12),
+        Some("PM") if time.hour() != 12 => time + Duration::hours(12),
+        Some("AM" | "PM") => time,
+        Some(_) => return None,
+    };
+    Some(WhatsAppHeader { date, time, rest })
+}
+
+/// 解析WhatsApp导出文本，不以时间戳开头的行属于上一条消息
+///
+/// 日和月的顺序由整个文件推断：出现大于12的第一段时为日/月/年，出现大于12的第二段时为月/日/年，无法区分时按日/月/年处理。
+/// 系统消息和省略的媒体被跳过。
+fn parse_whatsapp(text: &str) -> Result<Vec<ChatMessage>> {
+    let lines: Vec<(&str, Option<WhatsAppHeader>)> = text.lines()
+        .map(|line| (line, parse_whatsapp_header(line)))
+        .collect();
+    let headers = || lines.iter().filter_map(|(_, header)| header.as_ref());
+    let day_first = headers().any(|header| header.date[0] > 12) || !headers().any(|header| header.date[1] > 12);
+
+    let mut messages: Vec<ChatMessage> = Vec::new();
+    // 上一行是否属于一条保留的消息，系统消息的续行同样跳过
+    let mut in_message = false;
+    for (line, header) in &lines {
+        let Some(header) = header else {
+            if in_message {
+                if let Some(message) = messages.last_mut() {
+                    message.text.push('\n');
+                    message.text.push_str(line);
+                }
+            }
+            continue;
+        };
+
+        in_message = false;
+        let [first, second, year] = header.date;
+        let (day, month) = if day_first { (first, second) } else { (second, first) };
+        let year = if year < 100 { 2000 + year } else { year };
+        let Some(date) = NaiveDate::from_ymd_opt(year as i32, month, day) else {
+            continue;
+        };
+        let Some((sender, body)) = header.rest.split_once(": ") else {
+            continue;
+        };
+        if body.trim_start_matches('\u{200e}').starts_with('<') && body.ends_with('>') {
+            continue;
+        }
+
+        messages.push(ChatMessage {
+            timestamp: NaiveDateTime::new(date, header.time).and_utc(),
+            sender: sender.trim_start_matches('\u{200e}').to_string(),
+            text: body.to_string(),
+        });
+        in_message = true;
+    }
+
+    if messages.is_empty() && !text.trim().is_empty() {
+        return Err(MemoryError::InvalidInput("没有识别出WhatsApp消息".to_string()));
+    }
+    Ok(messages)
+}
+
+#[derive(Debug, Deserialize)]
+struct TelegramExport {
+    #[serde(default)]
+    messages: Vec<TelegramMessage>,
+    #[serde(default)]
+    chats: Option<TelegramChats>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TelegramChats {
+    list: Vec<TelegramExport>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TelegramMessage {
+    #[serde(rename = "type", default)]
+    kind: String,
+    date: String,
+    #[serde(default)]
+    date_unixtime: Option<String>,
+    #[serde(default)]
+    from: Option<String>,
+    #[serde(default)]
+    text: TelegramText,
+}
+
+/// 纯文本，或由文本和格式化片段组成的数组
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum TelegramText {
+    Plain(String),
+    Rich(Vec<TelegramTextPart>),
+}
+
+impl Default for TelegramText {
+    fn default() -> Self {
+        Self::Plain(String::new())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum TelegramTextPart {
+    Plain(String),
+    Entity { text: String },
+}
+
+impl TelegramText {
+    fn into_string(self) -> String {
+        match self {
+            Self::Plain(text) => text,
+            Self::Rich(parts) => parts.into_iter()
+                .map(|part| match part {
+                    TelegramTextPart::Plain(text) | TelegramTextPart::Entity { text } => text,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TelegramExport {
+    fn into_messages(self, messages: &mut Vec<ChatMessage>) {
+        for message in self.messages {
+            if message.kind != "message" {
+                continue;
+            }
+            let timestamp = message.date_unixtime.as_deref()
+                .and_then(|seconds| seconds.parse().ok())
+                .and_then(|seconds| DateTime::from_timestamp(seconds, 0))
+                .or_else(|| NaiveDateTime::parse_from_str(&message.date, "%Y-%m-%dT%H:%M:%S").ok().map(|time| time.and_utc()));
+            let text = message.text.into_string();
+            if let (Some(timestamp), false) = (timestamp, text.trim().is_empty()) {
+                messages.push(ChatMessage {
+                    timestamp,
+                    // 已注销的账号没有名称
+                    sender: message.from.unwrap_or_else(|| "Deleted Account".to_string()),
+                    text,
+                });
+            }
+        }
+        for chat in self.chats.into_iter().flat_map(|chats| chats.list) {
+            chat.into_messages(messages);
+        }
+    }
+}
+
+/// 解析Telegram导出，跳过服务消息和只有媒体的消息
+fn parse_telegram(text: &str) -> Result<Vec<ChatMessage>> {
+    let export: TelegramExport = serde_json::from_str(text)?;
+    let mut messages = Vec::new();
+    export.into_messages(&mut messages);
+    Ok(messages)
+}
+
+fn parse_json(text: &str) -> Result<Vec<ChatMessage>> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Messages {
+        List(Vec<ChatMessage>),
+        Wrapped { messages: Vec<ChatMessage> },
+    }
+
+    Ok(match serde_json::from_str::<Messages>(text)? {
+        Messages::List(messages) | Messages::Wrapped { messages } => messages,
+    })
+}
+
+/// 切分和写入选项
+#[derive(Debug, Clone)]
+pub struct ChatImportOptions {
+    pub memory_type: MemoryType,
+    pub importance: f32,
+    /// 相邻消息间隔超过此值时开始新片段
+    pub max_gap: Duration,
+    /// 片段的最大字符数，单条消息超出时单独成段
+    pub max_chars: usize,
+    /// 每批生成嵌入并写入的片段数
+    pub batch_size: usize,
+}
+
+impl Default for ChatImportOptions {
+    fn default() -> Self {
+        Self {
+            memory_type: MemoryType::LongTerm,
+            importance: 0.5,
+            max_gap: Duration::minutes(30),
+            max_chars: 1000,
+            batch_size: 64,
+        }
+    }
+}
+
+/// 片段元数据：参与者
+pub const PARTICIPANTS_KEY: &str = "participants";
+/// 片段元数据：最后一条消息的时间
+pub const ENDED_AT_KEY: &str = "ended_at";
+
+/// 把按时间排序的消息切成记忆，关键词由本地规则提取，不含嵌入
+pub fn chunk_messages(messages: &[ChatMessage], options: &ChatImportOptions) -> Vec<MemoryEntry> {
+    let mut chunks: Vec<&[ChatMessage]> = Vec::new();
+    let mut start = 0;
+    let mut chars = 0;
+    for (index, message) in messages.iter().enumerate() {
+        let line_chars = message.sender.chars().count() + message.text.chars().count() + 2;
+        if index > start {
+            let gap = message.timestamp - messages[index - 1].timestamp;
+            if gap > options.max_gap || chars + line_chars > options.max_chars {
+                chunks.push(&messages[start..index]);
+                start = index;
+                chars = 0;
+            }
+        }
+        chars += line_chars;
+    }
+    if start < messages.len() {
+        chunks.push(&messages[start..]);
+    }
+
+    chunks.into_iter()
+        .map(|chunk| {
+            let content = chunk.iter()
+                .map(|message| format!("{}: {}", message.sender, message.text))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let text = chunk.iter().map(|message| message.text.as_str()).collect::<Vec<_>>().join(" ");
+            let mut entry = MemoryEntry::new(options.memory_type.clone(), content, local_keywords(&text), options.importance);
+
+            let mut participants: Vec<&str> = chunk.iter().map(|message| message.sender.as_str()).collect();
+            participants.sort_unstable();
+            participants.dedup();
+            entry.metadata.insert(PARTICIPANTS_KEY.to_string(), participants.join(", "));
+            entry.metadata.insert(ENDED_AT_KEY.to_string(), chunk[chunk.len() - 1].timestamp.to_rfc3339());
+            entry.created_at = chunk[0].timestamp;
+            entry.last_accessed = entry.created_at;
+            entry
+        })
+        .collect()
+}
+
+/// 把聊天记录批量导入一个用户的记忆系统
+#[derive(Debug, Clone)]
+pub struct ChatImporter {
+    system: Arc<MemorySystem>,
+    inference: Option<Arc<dyn InferenceClient>>,
+    pipeline: Option<Arc<IngestPipeline>>,
+    options: ChatImportOptions,
+}
+
+impl ChatImporter {
+    pub fn new(system: Arc<MemorySystem>) -> Self {
+        Self {
+            system,
+            inference: None,
+            pipeline: None,
+            options: ChatImportOptions::default(),
+        }
+    }
+
+    /// 由推理客户端提取关键词，未设置时使用本地关键词；嵌入总是由记忆系统的嵌入生成器生成
+    pub fn with_inference(mut self, inference: Arc<dyn InferenceClient>) -> Self {
+        self.inference = Some(inference);
+        self
+    }
+
+    /// 经写入管道写入，与其他写入共享并发上限；未设置时直接写入记忆系统
+    pub fn with_pipeline(mut self, pipeline: Arc<IngestPipeline>) -> Self {
+        self.pipeline = Some(pipeline);
+        self
+    }
+
+    pub fn with_options(mut self, options: ChatImportOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// 解析导出文件并导入，返回写入的记忆条数
+    pub async fn import_export(&self, format: ChatFormat, text: &str) -> Result<usize> {
+        self.import(format.parse(text)?).await
+    }
+
+    /// 导入消息，返回写入的记忆条数
+    #[tracing::instrument(skip_all, fields(user_id = %self.system.user_id(), messages = messages.len()))]
+    pub async fn import(&self, mut messages: Vec<ChatMessage>) -> Result<usize> {
+        messages.sort_by_key(|message| message.timestamp);
+        let entries = chunk_messages(&messages, &self.options);
+
+        let mut imported = 0;
+        let embedder = self.system.embedder();
+        for batch in entries.chunks(self.options.batch_size.max(1)) {
+            let mut batch = batch.to_vec();
+            // 使用记忆系统的嵌入生成器批量生成，与检索时的查询向量一致
+            let contents: Vec<String> = batch.iter().map(|entry| entry.content.clone()).collect();
+            let embeddings = embedder.embed_batch(&contents).await?;
+            if embeddings.len() != batch.len() {
+                return Err(MemoryError::InferenceError(format!(
+                    "嵌入生成器返回 {} 个嵌入，请求了 {} 个", embeddings.len(), batch.len(),
+                )));
+            }
+            for (entry, embedding) in batch.iter_mut().zip(embeddings) {
+                entry.embedding = Some(embedding);
+            }
+            if let Some(ref inference) = self.inference {
+                // 关键词提取失败时保留本地结果
+                match inference.extract_keywords_batch(contents).await {
+                    Ok(keywords) if keywords.len() == batch.len() => {
+                        for (entry, keywords) in batch.iter_mut().zip(keywords) {
+                            entry.keywords = keywords;
+                        }
+                    }
+                    Ok(keywords) => tracing::warn!(
+                        "推理后端返回 {} 组关键词，请求了 {} 组，保留本地关键词", keywords.len(), batch.len(),
+                    ),
+                    Err(e) => tracing::warn!("关键词提取失败，保留本地关键词: {}", e),
+                }
+            }
+            imported += match self.pipeline {
+                Some(ref pipeline) => pipeline.import_memories(self.system.clone(), batch).await?,
+                None => self.system.import_memories(batch).await?,
+            };
+        }
+        Ok(imported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::MockInferenceClient;
+    use crate::vector_store::MockVectorStore;
+
+    #[test]
+    fn test_parse_whatsapp_and_telegram() {
+        let android = "\
+31/12/23, 21:05 - Messages and calls are end-to-end encrypted.
+31/12/23, 21:05 - 小明: 新年快乐
+明年也要一起跨年
+31/12/23, 21:06 - Mira: <Media omitted>
+31/12/23, 9:07 PM - Mira: 好呀";
+        let messages = ChatFormat::WhatsApp.parse(android).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].text, "新年快乐\n明年也要一起跨年");
+        assert_eq!(messages[1].timestamp.to_rfc3339(), "2023-12-31T21:07:00+00:00");
+
+        let ios = "[12/31/2023, 9:05:12\u{202f}PM] 小明: 新年快乐";
+        let messages = ChatFormat::WhatsApp.parse(ios).unwrap();
+        assert_eq!((messages[0].sender.as_str(), messages[0].timestamp.to_rfc3339().as_str()), ("小明", "2023-12-31T21:05:12+00:00"));
+
+        let telegram = r#"{"name": "Mira", "messages": [
+            {"id": 1, "type": "service", "date": "2023-12-31T21:00:00", "action": "create_group"},
+            {"id": 2, "type": "message", "date": "2023-12-31T21:05:00", "date_unixtime": "1704056700", "from": "小明",
+             "text": ["新年", {"type": "bold", "text": "快乐"}]}
+        ]}"#;
+        let messages = ChatFormat::Telegram.parse(telegram).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].text, "新年快乐");
+        assert!(ChatFormat::WhatsApp.parse("not a chat").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_import_chunks_by_gap() {
+        let at = |hour, minute| format!("2024-02-14T{:02}:{:02}:00Z", hour, minute);
+        let messages = serde_json::json!([
+            { "timestamp": at(20, 0), "sender": "小明", "text": "情人节快乐" },
+            { "timestamp": at(20, 1), "sender": "Mira", "text": "情人节快乐呀" },
+            { "timestamp": at(23, 0), "sender": "小明", "text": "晚安" },
+        ]).to_string();
+
+        let system = Arc::new(MemorySystem::new("alice".to_string(), Arc::new(MockVectorStore::new()), None).await.unwrap());
+        let importer = ChatImporter::new(system.clone()).with_inference(Arc::new(MockInferenceClient::new()));
+        assert_eq!(importer.import_export(ChatFormat::Json, &messages).await.unwrap(), 2);
+
+        let entries = system.list_memories(None).await.unwrap();
+        assert_eq!(entries[0].content, "小明: 情人节快乐\nMira: 情人节快乐呀");
+        assert_eq!(entries[0].created_at.to_rfc3339(), "2024-02-14T20:00:00+00:00");
+        assert_eq!(entries[0].metadata[PARTICIPANTS_KEY], "Mira, 小明");
+        for entry in &entries {
+            assert!(system.memory_embedding(entry.id).await.unwrap().is_some());
+        }
+    }
+}
@@ -0,0 +1,353 @@
+//! 日历同步 - 导入.ics事件为记忆，把带时间的计划记忆导出为iCalendar
+//!
+//! 导入的事件按用户ID和UID生成固定的记忆ID，重复导入同一日历时更新而不是新增，
+//! 不同用户导入同一日历时互不覆盖。
+//! 带`TZID`或不带时区的时间按UTC处理；全天事件的时间为当天0点（UTC）。
+
+use crate::bridge::local_keywords;
+use crate::{MemoryEntry, MemoryError, MemorySystem, MemoryType, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use uuid::Uuid;
+
+/// 记忆元数据：日历事件UID
+pub const UID_KEY: &str = "calendar_uid";
+/// 记忆元数据：事件开始时间（RFC 3339）
+pub const EVENT_START_KEY: &str = "event_start";
+/// 记忆元数据：事件结束时间（RFC 3339）
+pub const EVENT_END_KEY: &str = "event_end";
+/// 记忆元数据：是否为全天事件
+pub const ALL_DAY_KEY: &str = "all_day";
+/// 记忆元数据：地点
+pub const LOCATION_KEY: &str = "location";
+
+/// 由事件UID生成记忆ID的命名空间
+const UID_NAMESPACE: Uuid = Uuid::from_u128(0x6d69_7261_6361_6c65_6e64_6172_2d75_6964);
+
+/// iCalendar单行最大字节数，超出时折行
+const MAX_LINE_OCTETS: usize = 75;
+
+/// 日历事件
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalendarEvent {
+    pub uid: String,
+    pub summary: String,
+    pub description: Option<String>,
+    pub location: Option<String>,
+    pub start: DateTime<Utc>,
+    pub end: Option<DateTime<Utc>>,
+    pub all_day: bool,
+}
+
+impl CalendarEvent {
+    /// 由事件生成的记忆，ID由用户ID和UID决定
+    pub fn to_memory(&self, user_id: &str, importance: f32) -> MemoryEntry {
+        let mut content = self.summary.clone();
+        if let Some(ref description) = self.description {
+            content.push('\n');
+            content.push_str(description);
+        }
+        let mut entry = MemoryEntry::new(MemoryType::LongTerm, content, local_keywords(&self.summary), importance);
+        // 用户ID不含NUL，以NUL分隔避免不同的（用户, UID）组合拼出相同的名称
+        let name = [user_id.as_bytes(), &[0], self.uid.as_bytes()].concat();
+        entry.id = Uuid::new_v5(&UID_NAMESPACE, &name);
+
+        entry.metadata.insert(UID_KEY.to_string(), self.uid.clone());
+        entry.metadata.insert(EVENT_START_KEY.to_string(), self.start.to_rfc3339());
+        if let Some(end) = self.end {
+            entry.metadata.insert(EVENT_END_KEY.to_string(), end.to_rfc3339());
+        }
+        if self.all_day {
+            entry.metadata.insert(ALL_DAY_KEY.to_string(), "true".to_string());
+        }
+        if let Some(ref location) = self.location {
+            entry.metadata.insert(LOCATION_KEY.to_string(), location.clone());
+        }
+        entry
+    }
+
+    /// 带开始时间的计划记忆对应的事件，其余记忆为None
+    ///
+    /// 内容第一行为标题，其余为描述；没有UID的记忆使用记忆ID。
+    pub fn from_memory(entry: &MemoryEntry) -> Option<Self> {
+        let start = entry.metadata.get(EVENT_START_KEY)?.parse().ok()?;
+        let (summary, description) = match entry.content.split_once('\n') {
+            Some((summary, description)) => (summary.to_string(), Some(description.to_string())),
+            None => (entry.content.clone(), None),
+        };
+        Some(Self {
+            uid: entry.metadata.get(UID_KEY).cloned().unwrap_or_else(|| format!("{}@mira", entry.id)),
+            summary,
+            description,
+            location: entry.metadata.get(LOCATION_KEY).cloned(),
+            start,
+            end: entry.metadata.get(EVENT_END_KEY).and_then(|end| end.parse().ok()),
+            all_day: entry.metadata.get(ALL_DAY_KEY).is_some_and(|all_day| all_day == "true"),
+        })
+    }
+}
+
+/// 展开折行：以空格或制表符开头的行接在上一行后面
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ if line.is_empty() => {}
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+/// 拆分属性行为名称、参数和值，参数值中的引号内允许出现冒号
+fn split_property(line: &str) -> Option<(&str, &str, &str)> {
+    let mut in_quotes = false;
+    let colon = line.char_indices().find_map(|(index, c)| match c {
+        '"' => {
+            in_quotes = !in_quotes;
+            None
+        }
+        ':' if !in_quotes => Some(index),
+        _ => None,
+    })?;
+    let (head, value) = (&line[..colon], &line[colon + 1..]);
+    let (name, params) = head.split_once(';').unwrap_or((head, ""));
+    Some((name, params, value))
+}
+
+fn unescape(value: &str) -> String {
+    let mut text = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => text.push('\n'),
+            Some(other) => text.push(other),
+            None => text.push('\\'),
+        }
+    }
+    text
+}
+
+fn escape(value: &str) -> String {
+    let mut text = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => text.push_str("\\\\"),
+            ';' => text.push_str("\\;"),
+            ',' => text.push_str("\\,"),
+            '\n' => text.push_str("\\n"),
+            '\r' => {}
+            _ => text.push(c),
+        }
+    }
+    text
+}
+
+/// 解析DATE-TIME或DATE值，返回时间和是否为全天
+fn parse_time(params: &str, value: &str) -> Result<(DateTime<Utc>, bool)> {
+    let invalid = || MemoryError::InvalidInput(format!("无效的日历时间: {}", value));
+    if params.split(';').any(|param| param.eq_ignore_ascii_case("VALUE=DATE")) || value.len() == 8 {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").map_err(|_| invalid())?;
+        return Ok((date.and_hms_opt(0, 0, 0).ok_or_else(invalid)?.and_utc(), true));
+    }
+    let time = NaiveDateTime::parse_from_str(value.trim_end_matches('Z'), "%Y%m%dT%H%M%S").map_err(|_| invalid())?;
+    Ok((time.and_utc(), false))
+}
+
+/// 解析iCalendar文本中的全部VEVENT，缺少UID或开始时间的事件被忽略
+pub fn parse_ics(text: &str) -> Result<Vec<CalendarEvent>> {
+    let lines = unfold(text);
+    if !lines.first().is_some_and(|line| line.eq_ignore_ascii_case("BEGIN:VCALENDAR")) {
+        return Err(MemoryError::InvalidInput("不是iCalendar文件".to_string()));
+    }
+
+    let mut events = Vec::new();
+    // 当前事件的属性，以及事件内嵌套组件（如VALARM）的层数
+    let mut current: Option<(Vec<(String, String, String)>, usize)> = None;
+    for line in &lines {
+        let Some((name, params, value)) = split_property(line) else {
+            continue;
+        };
+        let name = name.to_ascii_uppercase();
+        match (name.as_str(), current.as_mut()) {
+            ("BEGIN", None) if value.eq_ignore_ascii_case("VEVENT") => current = Some((Vec::new(), 0)),
+            ("BEGIN", Some((_, depth))) => *depth += 1,
+            ("END", Some((_, depth))) if *depth > 0 => *depth -= 1,
+            ("END", Some(_)) => {
+                if let Some((properties, _)) = current.take() {
+                    if let Some(event) = build_event(properties)? {
+                        events.push(event);
+                    }
+                }
+            }
+            (_, Some((properties, 0))) => properties.push((name.clone(), params.to_string(), value.to_string())),
+            _ => {}
+        }
+    }
+    Ok(events)
+}
+
+fn build_event(properties: Vec<(String, String, String)>) -> Result<Option<CalendarEvent>> {
+    let (mut uid, mut summary, mut description, mut location) = (None, None, None, None);
+    let (mut start, mut end) = (None, None);
+    for (name, params, value) in properties {
+        match name.as_str() {
+            "UID" => uid = Some(value),
+            "SUMMARY" => summary = Some(unescape(&value)),
+            "DESCRIPTION" => description = Some(unescape(&value)).filter(|d| !d.is_empty()),
+            "LOCATION" => location = Some(unescape(&value)).filter(|l| !l.is_empty()),
+            "DTSTART" => start = Some(parse_time(&params, &value)?),
+            "DTEND" => end = Some(parse_time(&params, &value)?.0),
+            _ => {}
+        }
+    }
+    let (Some(uid), Some((start, all_day))) = (uid, start) else {
+        return Ok(None);
+    };
+    Ok(Some(CalendarEvent {
+        uid,
+        summary: summary.unwrap_or_default(),
+        description,
+        location,
+        start,
+        end,
+        all_day,
+    }))
+}
+
+/// 按75字节折行，不截断UTF-8字符
+fn push_line(ics: &mut String, line: &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > MAX_LINE_OCTETS {
+            ics.push_str("\r\n ");
+            width = 1;
+        }
+        ics.push(c);
+        width += c.len_utf8();
+    }
+    ics.push_str("\r\n");
+}
+
+fn format_time(time: DateTime<Utc>, all_day: bool) -> String {
+    if all_day {
+        format!(";VALUE=DATE:{}", time.format("%Y%m%d"))
+    } else {
+        format!(":{}", time.format("%Y%m%dT%H%M%SZ"))
+    }
+}
+
+/// 生成iCalendar文本
+pub fn write_ics(events: &[CalendarEvent]) -> String {
+    let mut ics = String::new();
+    push_line(&mut ics, "BEGIN:VCALENDAR");
+    push_line(&mut ics, "VERSION:2.0");
+    push_line(&mut ics, "PRODID:-//MIRA//Memory Calendar//ZH");
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    for event in events {
+        push_line(&mut ics, "BEGIN:VEVENT");
+        push_line(&mut ics, &format!("UID:{}", event.uid));
+        push_line(&mut ics, &format!("DTSTAMP:{}", stamp));
+        push_line(&mut ics, &format!("DTSTART{}", format_time(event.start, event.all_day)));
+        if let Some(end) = event.end {
+            push_line(&mut ics, &format!("DTEND{}", format_time(end, event.all_day)));
+        }
+        push_line(&mut ics, &format!("SUMMARY:{}", escape(&event.summary)));
+        if let Some(ref description) = event.description {
+            push_line(&mut ics, &format!("DESCRIPTION:{}", escape(description)));
+        }
+        if let Some(ref location) = event.location {
+            push_line(&mut ics, &format!("LOCATION:{}", escape(location)));
+        }
+        push_line(&mut ics, "END:VEVENT");
+    }
+    push_line(&mut ics, "END:VCALENDAR");
+    ics
+}
+
+impl MemorySystem {
+    /// 把.ics中的事件导入为长期记忆，已导入过的事件被更新，返回导入的事件数
+    #[tracing::instrument(skip_all, fields(user_id = %self.user_id()))]
+    pub async fn import_calendar(&self, ics: &str, importance: f32) -> Result<usize> {
+        let entries = parse_ics(ics)?.iter().map(|event| event.to_memory(self.user_id(), importance)).collect();
+        self.import_memories(entries).await
+    }
+
+    /// 把带开始时间的计划记忆导出为iCalendar，按开始时间排序
+    #[tracing::instrument(skip_all, fields(user_id = %self.user_id()))]
+    pub async fn export_calendar(&self) -> Result<String> {
+        let mut events: Vec<CalendarEvent> = self.list_memories(None).await?
+            .iter()
+            .filter_map(|entry| CalendarEvent::from_memory(entry))
+            .collect();
+        events.sort_by_key(|event| event.start);
+        Ok(write_ics(&events))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::MockVectorStore;
+    use std::sync::Arc;
+
+    const ICS: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+BEGIN:VEVENT\r
+UID:movie-1@example.com\r
+DTSTART:20240216T190000Z\r
+DTEND:20240216T213000Z\r
+SUMMARY:一起看电影\\, 记得买爆米花\r
+DESCRIPTION:影院在市中心\\n提前十分钟到\r
+BEGIN:VALARM\r
+DESCRIPTION:提醒\r
+END:VALARM\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:birthday@example.com\r
+DTSTART;VALUE=DATE:20240520\r
+SUMMARY:小明的生日，这是一段足够长的标题用来测试折行是否会在UTF-8字符中间截断\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    #[test]
+    fn test_parse_and_write_round_trip() {
+        let events = parse_ics(ICS).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].summary, "一起看电影, 记得买爆米花");
+        assert_eq!(events[0].description.as_deref(), Some("影院在市中心\n提前十分钟到"));
+        assert_eq!(events[0].end.unwrap().to_rfc3339(), "2024-02-16T21:30:00+00:00");
+        assert!(events[1].all_day);
+        assert_eq!(events[0].to_memory("alice", 0.5).id, events[0].to_memory("alice", 0.7).id);
+        assert_ne!(events[0].to_memory("alice", 0.5).id, events[0].to_memory("bob", 0.5).id);
+
+        let ics = write_ics(&events);
+        assert!(ics.lines().all(|line| line.len() <= MAX_LINE_OCTETS + 1));
+        assert!(ics.contains("DTSTART;VALUE=DATE:20240520\r\n"));
+        assert_eq!(parse_ics(&ics).unwrap(), events);
+        assert!(parse_ics("not a calendar").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_import_is_idempotent_and_exports_plans() {
+        let system = MemorySystem::new("alice".to_string(), Arc::new(MockVectorStore::new()), None).await.unwrap();
+        assert_eq!(system.import_calendar(ICS, 0.7).await.unwrap(), 2);
+        system.import_calendar(ICS, 0.7).await.unwrap();
+        system.add_memory(MemoryType::Preference, "喜欢爆米花".to_string(), vec![], 0.5, None).await.unwrap();
+        assert_eq!(system.list_memories(None).await.unwrap().len(), 3);
+
+        let mut plan = MemoryEntry::new(MemoryType::LongTerm, "周五去看电影".to_string(), vec![], 0.6);
+        plan.metadata.insert(EVENT_START_KEY.to_string(), "2024-02-23T19:00:00Z".to_string());
+        system.import_memories(vec![plan.clone()]).await.unwrap();
+
+        let events = parse_ics(&system.export_calendar().await.unwrap()).unwrap();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[2].summary, "周五去看电影");
+        assert_eq!(events[2].uid, format!("{}@mira", plan.id));
+    }
+}
@@ -0,0 +1,5 @@
+//! 外部集成 - 与日历、智能家居等用户侧服务同步记忆和情感
+
+pub mod calendar;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
@@ -0,0 +1,361 @@
+//! MQTT/Home Assistant集成 - 发布情感状态，订阅在家状态事件并转为情感触发
+//!
+//! 情感变化以保留消息发布到`{topic_prefix}/{user_id}/emotion`，负载为`EmotionalState`的JSON；
+//! 配置`discovery_prefix`时为每个用户发布一次Home Assistant自动发现配置，以心情作为传感器状态。
+//! 订阅`{topic_prefix}/+/presence`，负载为事件名（如`arrived_home`）或`{"event": "arrived_home"}`，
+//! 按`reactions`转为情感触发，并广播主动消息供对话前端发送。
+//! 只接受已加载或在`users`中列出的用户，主题中任意的用户ID不会创建新的记忆系统。
+
+use crate::emotion::{EmotionalEngine, EmotionalTrigger};
+use crate::memory::MemoryManager;
+use crate::{EmotionalState, Result};
+use rumqttc::{AsyncClient, Event, EventLoop, MqttOptions, Packet, QoS};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, Semaphore};
+
+/// 主动消息的缓冲条数
+pub const PRESENCE_CHANNEL_CAPACITY: usize = 64;
+
+/// 客户端请求队列长度
+const REQUEST_CAPACITY: usize = 32;
+
+/// 连接失败后重试的间隔
+const RECONNECT_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// 在家状态事件的响应
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PresenceReaction {
+    /// 触发的情感变化，为None时不改变情感
+    #[serde(default)]
+    pub trigger: Option<EmotionalTrigger>,
+    #[serde(default = "default_intensity")]
+    pub intensity: f32,
+    /// 发给用户的主动消息
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+fn default_intensity() -> f32 {
+    1.0
+}
+
+/// MQTT配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MqttConfig {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub topic_prefix: String,
+    /// Home Assistant自动发现前缀，为None时不发布发现配置
+    pub discovery_prefix: Option<String>,
+    pub keep_alive_seconds: u64,
+    /// 事件名到响应的映射，未配置的事件被忽略
+    pub reactions: HashMap<String, PresenceReaction>,
+    /// 接受在家状态事件的用户，为空时只接受已加载的用户
+    pub users: Vec<String>,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        let reaction = |trigger: Option<EmotionalTrigger>, intensity: f32, message: &str| PresenceReaction {
+            trigger,
+            intensity,
+            message: Some(message.to_string()),
+        };
+        Self {
+            enabled: false,
+            host: "localhost".to_string(),
+            port: 1883,
+            client_id: "mira".to_string(),
+            username: None,
+            password: None,
+            topic_prefix: "mira".to_string(),
+            discovery_prefix: Some("homeassistant".to_string()),
+            keep_alive_seconds: 30,
+            reactions: HashMap::from([
+                ("arrived_home".to_string(), reaction(Some(EmotionalTrigger::PositiveInteraction), 0.5, "欢迎回家！今天过得怎么样？")),
+                ("left_home".to_string(), reaction(None, 1.0, "路上小心，早点回来哦")),
+                ("bedtime".to_string(), reaction(None, 1.0, "该睡觉啦，晚安～")),
+                ("woke_up".to_string(), reaction(None, 1.0, "早安！今天也要加油")),
+            ]),
+            users: Vec::new(),
+        }
+    }
+}
+
+impl MqttConfig {
+    /// 用户情感状态的主题
+    pub fn emotion_topic(&self, user_id: &str) -> String {
+        format!("{}/{}/emotion", self.topic_prefix, user_id)
+    }
+
+    /// 订阅的在家状态主题
+    pub fn presence_filter(&self) -> String {
+        format!("{}/+/presence", self.topic_prefix)
+    }
+
+    /// 解析在家状态消息，返回用户ID和事件名；主题不匹配或负载为空时为None
+    pub fn parse_presence(&self, topic: &str, payload: &[u8]) -> Option<(String, String)> {
+        let user_id = topic.strip_prefix(self.topic_prefix.as_str())?
+            .strip_prefix('/')?
+            .strip_suffix("/presence")?;
+        if user_id.is_empty() || user_id.contains('/') {
+            return None;
+        }
+
+        #[derive(Deserialize)]
+        struct Payload {
+            event: String,
+        }
+        let payload = std::str::from_utf8(payload).ok()?.trim();
+        let event = match serde_json::from_str::<Payload>(payload) {
+            Ok(payload) => payload.event,
+            Err(_) => payload.trim_matches('"').to_string(),
+        };
+        (!event.is_empty()).then(|| (user_id.to_string(), event))
+    }
+
+    /// Home Assistant自动发现的主题和配置
+    fn discovery(&self, user_id: &str) -> Option<(String, serde_json::Value)> {
+        let prefix = self.discovery_prefix.as_ref()?;
+        let object_id = format!("{}_{}_mood", self.client_id, user_id);
+        let state_topic = self.emotion_topic(user_id);
+        let config = serde_json::json!({
+            "name": format!("MIRA {} 心情", user_id),
+            "unique_id": object_id,
+            "state_topic": state_topic,
+            "value_template": "{{ value_json.mood }}",
+            "json_attributes_topic": state_topic,
+            "icon": "mdi:heart",
+        });
+        Some((format!("{}/sensor/{}/config", prefix, object_id), config))
+    }
+}
+
+/// 在家状态事件引起的主动消息
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PresenceMessage {
+    pub user_id: String,
+    pub event: String,
+    pub message: String,
+}
+
+/// MQTT桥接
+#[derive(Debug)]
+pub struct MqttBridge {
+    config: MqttConfig,
+    manager: Arc<MemoryManager>,
+    engine: Arc<EmotionalEngine>,
+    messages: broadcast::Sender<PresenceMessage>,
+}
+
+impl MqttBridge {
+    pub fn new(config: MqttConfig, manager: Arc<MemoryManager>, engine: Arc<EmotionalEngine>) -> Self {
+        let (messages, _) = broadcast::channel(PRESENCE_CHANNEL_CAPACITY);
+        Self { config, manager, engine, messages }
+    }
+
+    /// 订阅在家状态事件引起的主动消息
+    pub fn subscribe(&self) -> broadcast::Receiver<PresenceMessage> {
+        self.messages.subscribe()
+    }
+
+    /// 处理一个在家状态事件，返回更新后的情感状态
+    ///
+    /// 未配置的事件，以及既未加载也不在`users`中的用户返回None。
+    #[tracing::instrument(skip_all, fields(user_id = %user_id, event = %event))]
+    pub async fn handle_presence(&self, user_id: &str, event: &str) -> Result<Option<EmotionalState>> {
+        let Some(reaction) = self.config.reactions.get(event) else {
+            tracing::debug!("忽略未配置的在家状态事件");
+            return Ok(None);
+        };
+
+        let system = match self.manager.get(user_id) {
+            Some(system) => system,
+            None if self.config.users.iter().any(|user| user == user_id) => self.manager.get_or_create(user_id).await?,
+            None => {
+                tracing::debug!("忽略未知用户的在家状态事件");
+                return Ok(None);
+            }
+        };
+        let mut state = system.get_emotional_state().await;
+        if let Some(ref trigger) = reaction.trigger {
+            state = self.engine.process_trigger(&state, trigger.clone(), reaction.intensity);
+            self.manager.update_emotion(user_id, Some(trigger.clone()), state.clone()).await?;
+        }
+        if let Some(ref message) = reaction.message {
+            // 没有订阅方时发送失败，忽略即可
+            let _ = self.messages.send(PresenceMessage {
+                user_id: user_id.to_string(),
+                event: event.to_string(),
+                message: message.clone(),
+            });
+        }
+        Ok(Some(state))
+    }
+
+    fn connect(&self) -> (AsyncClient, EventLoop) {
+        let mut options = MqttOptions::new(&self.config.client_id, &self.config.host, self.config.port);
+        options.set_keep_alive(std::time::Duration::from_secs(self.config.keep_alive_seconds.max(5)));
+        if let Some(ref username) = self.config.username {
+            options.set_credentials(username, self.config.password.clone().unwrap_or_default());
+        }
+        AsyncClient::new(options, REQUEST_CAPACITY)
+    }
+
+    /// 连接代理并开始收发，断线后自动重连
+    pub fn start(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let (client, mut event_loop) = self.connect();
+
+        {
+            let bridge = self.clone();
+            let client = client.clone();
+            let mut changes = self.manager.subscribe_emotions();
+            tokio::spawn(async move {
+                let mut discovered = HashSet::new();
+                loop {
+                    let change = match changes.recv().await {
+                        Ok(change) => change,
+                        Err(RecvError::Lagged(skipped)) => {
+                            tracing::warn!("MQTT发布落后，跳过 {} 条情感变化", skipped);
+                            continue;
+                        }
+                        Err(RecvError::Closed) => break,
+                    };
+                    bridge.publish_emotion(&client, &mut discovered, &change.user_id, &change.state).await;
+                }
+            });
+        }
+
+        // 事件在独立任务中处理，不阻塞轮询；同时处理的事件数有上限
+        let handlers = Arc::new(Semaphore::new(REQUEST_CAPACITY));
+        tokio::spawn(async move {
+            loop {
+                match event_loop.poll().await {
+                    // 每次连接成功后重新订阅，代理不保留会话时订阅会丢失
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        tracing::info!("已连接MQTT代理 {}:{}", self.config.host, self.config.port);
+                        if let Err(e) = client.subscribe(self.config.presence_filter(), QoS::AtLeastOnce).await {
+                            tracing::warn!("订阅在家状态主题失败: {}", e);
+                        }
+                    }
+                    Ok(Event::Incoming(Packet::Publish(publish))) => {
+                        let Some((user_id, event)) = self.config.parse_presence(&publish.topic, &publish.payload) else {
+                            continue;
+                        };
+                        let Ok(permit) = handlers.clone().acquire_owned().await else {
+                            break;
+                        };
+                        let bridge = self.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = bridge.handle_presence(&user_id, &event).await {
+                                tracing::warn!("处理在家状态事件 {} 失败: {}", event, e);
+                            }
+                            drop(permit);
+                        });
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        tracing::warn!("MQTT连接错误，{}秒后重连: {}", RECONNECT_DELAY.as_secs(), e);
+                        tokio::time::sleep(RECONNECT_DELAY).await;
+                    }
+                }
+            }
+        })
+    }
+
+    async fn publish_emotion(&self, client: &AsyncClient, discovered: &mut HashSet<String>, user_id: &str, state: &EmotionalState) {
+        if !discovered.contains(user_id) {
+            if let Some((topic, config)) = self.config.discovery(user_id) {
+                if let Err(e) = client.publish(topic, QoS::AtLeastOnce, true, config.to_string()).await {
+                    tracing::warn!("发布Home Assistant发现配置失败: {}", e);
+                    return;
+                }
+            }
+            discovered.insert(user_id.to_string());
+        }
+
+        let payload = match serde_json::to_vec(state) {
+            Ok(payload) => payload,
+            Err(e) => {
+                tracing::warn!("序列化情感状态失败: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = client.publish(self.config.emotion_topic(user_id), QoS::AtLeastOnce, true, payload).await {
+            tracing::warn!("发布情感状态失败: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::MockVectorStore;
+
+    #[test]
+    fn test_parse_presence_topics_and_payloads() {
+        let config = MqttConfig::default();
+        assert_eq!(config.presence_filter(), "mira/+/presence");
+        assert_eq!(
+            config.parse_presence("mira/alice/presence", b"arrived_home"),
+            Some(("alice".to_string(), "arrived_home".to_string())),
+        );
+        assert_eq!(
+            config.parse_presence("mira/alice/presence", br#"{"event": "bedtime", "source": "ha"}"#),
+            Some(("alice".to_string(), "bedtime".to_string())),
+        );
+        assert_eq!(config.parse_presence("mira/alice/emotion", b"arrived_home"), None);
+        assert_eq!(config.parse_presence("other/alice/presence", b"arrived_home"), None);
+        assert_eq!(config.parse_presence("mira/alice/presence", b"  "), None);
+
+        let (topic, discovery) = config.discovery("alice").unwrap();
+        assert_eq!(topic, "homeassistant/sensor/mira_alice_mood/config");
+        assert_eq!(discovery["state_topic"], "mira/alice/emotion");
+    }
+
+    #[tokio::test]
+    async fn test_presence_triggers_emotion_and_message() {
+        let manager = Arc::new(MemoryManager::new(Arc::new(MockVectorStore::new()), None));
+        let bridge = MqttBridge::new(MqttConfig::default(), manager.clone(), Arc::new(EmotionalEngine::new()));
+        let mut messages = bridge.subscribe();
+        let mut changes = manager.subscribe_emotions();
+
+        let before = manager.get_or_create("alice").await.unwrap().get_emotional_state().await;
+        let state = bridge.handle_presence("alice", "arrived_home").await.unwrap().unwrap();
+        assert!(state.happiness > before.happiness);
+        assert_eq!(changes.recv().await.unwrap().trigger, Some(EmotionalTrigger::PositiveInteraction));
+        assert_eq!(messages.recv().await.unwrap().message, "欢迎回家！今天过得怎么样？");
+
+        // 只发消息的事件不改变情感
+        bridge.handle_presence("alice", "bedtime").await.unwrap();
+        assert!(changes.try_recv().is_err());
+        assert_eq!(messages.recv().await.unwrap().event, "bedtime");
+        assert_eq!(bridge.handle_presence("alice", "unknown").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_presence_only_accepts_known_users() {
+        let manager = Arc::new(MemoryManager::new(Arc::new(MockVectorStore::new()), None));
+        let config = MqttConfig { users: vec!["bob".to_string()], ..MqttConfig::default() };
+        let bridge = MqttBridge::new(config, manager.clone(), Arc::new(EmotionalEngine::new()));
+        let mut messages = bridge.subscribe();
+
+        // 主题中的任意用户ID不会创建记忆系统
+        assert_eq!(bridge.handle_presence("mallory", "arrived_home").await.unwrap(), None);
+        assert!(manager.get("mallory").is_none());
+        assert!(messages.try_recv().is_err());
+
+        // 配置中列出的用户按需加载
+        assert!(bridge.handle_presence("bob", "arrived_home").await.unwrap().is_some());
+        assert!(manager.get("bob").is_some());
+        assert_eq!(messages.recv().await.unwrap().user_id, "bob");
+    }
+}
@@ -1,18 +1,43 @@
 //! MIRA记忆系统 - 多语言混合架构
 //! My Intelligent Romantic Assistant - 使用最新的Rust 1.82.0特性实现高性能记忆管理
 
+// std::simd尚未稳定，启用portable-simd特性需要nightly编译器
+#![cfg_attr(feature = "portable-simd", feature(portable_simd))]
+
 use std::collections::HashMap;
+#[cfg(feature = "native")]
 use std::sync::Arc;
 use chrono::{DateTime, Utc};
-use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "native")]
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
-pub mod memory;
+// 情感、个性和文档转换模块不依赖异步运行时，可编译到wasm32
 pub mod emotion;
+pub mod documents;
+#[cfg(feature = "native")]
+pub mod memory;
+#[cfg(feature = "native")]
 pub mod vector_store;
+#[cfg(feature = "native")]
 pub mod bridge;
+#[cfg(feature = "native")]
+pub mod chat;
+#[cfg(feature = "native")]
+pub mod config;
+#[cfg(feature = "native")]
+pub mod import;
+#[cfg(feature = "native")]
+pub mod webhook;
+#[cfg(feature = "native")]
+pub mod scheduler;
+#[cfg(feature = "native")]
+pub mod transcript;
+#[cfg(feature = "native")]
+pub mod integrations;
+#[cfg(feature = "native")]
+pub mod plugins;
 
 /// 记忆类型枚举
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -29,6 +54,33 @@ pub enum MemoryType {
     Relationship,
 }
 
+impl MemoryType {
+    /// 所有记忆类型
+    pub const ALL: [MemoryType; 5] = [
+        MemoryType::ShortTerm,
+        MemoryType::LongTerm,
+        MemoryType::Emotional,
+        MemoryType::Preference,
+        MemoryType::Relationship,
+    ];
+
+    /// 稳定的小写标识，用于集合名称等外部命名
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MemoryType::ShortTerm => "short_term",
+            MemoryType::LongTerm => "long_term",
+            MemoryType::Emotional => "emotional",
+            MemoryType::Preference => "preference",
+            MemoryType::Relationship => "relationship",
+        }
+    }
+
+    /// 由`as_str`的标识解析
+    pub fn parse(name: &str) -> Option<MemoryType> {
+        Self::ALL.into_iter().find(|memory_type| memory_type.as_str() == name)
+    }
+}
+
 /// 情感状态
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EmotionalState {
@@ -40,6 +92,31 @@ pub struct EmotionalState {
     pub timestamp: DateTime<Utc>,
 }
 
+/// 语音附件 - 音频本身由前端保存，这里只记录引用
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AudioAttachment {
+    /// 音频文件地址或存储键
+    pub uri: String,
+    pub mime_type: String,
+    pub duration_ms: Option<u64>,
+    /// 语音转写的文字
+    pub transcript: Option<String>,
+    /// 录制或合成时的语音风格
+    pub voice_style: Option<emotion::VoiceStyle>,
+}
+
+/// 图片附件 - 图片本身由前端保存，这里只记录引用
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageAttachment {
+    /// 图片文件地址或存储键
+    pub uri: String,
+    pub mime_type: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// 图片说明
+    pub caption: Option<String>,
+}
+
 /// 记忆条目
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemoryEntry {
@@ -54,34 +131,174 @@ pub struct MemoryEntry {
     pub last_accessed: DateTime<Utc>,
     pub access_count: u32,
     pub metadata: HashMap<String, String>,
+    /// 过期时间，None表示永不过期
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// 语音消息附件
+    #[serde(default)]
+    pub audio: Option<AudioAttachment>,
+    /// 分享的图片，图片向量存放在向量存储的`image`空间
+    #[serde(default)]
+    pub image: Option<ImageAttachment>,
 }
 
 /// 记忆系统核心结构
+#[cfg(feature = "native")]
 #[derive(Debug)]
 pub struct MemorySystem {
-    /// 内存中的记忆缓存 - 使用DashMap实现并发安全
-    memory_cache: DashMap<Uuid, MemoryEntry>,
+    /// 内存中的记忆缓存 - 按记忆类型分片
+    memory_cache: memory::cache::MemoryCache,
     /// 向量存储客户端
     vector_store: Arc<dyn vector_store::VectorStore<Error = anyhow::Error>>,
     /// 当前情感状态
     current_emotion: Arc<RwLock<EmotionalState>>,
     /// 用户ID
     user_id: String,
-    /// 配置
-    config: MemoryConfig,
+    /// 配置 - 可在运行时整体替换，后台清理任务共享同一份
+    config: Arc<std::sync::RwLock<Arc<MemoryConfig>>>,
+    /// 评估新记忆重要性的推理客户端，未设置时使用本地启发式
+    importance_inference: Option<Arc<dyn bridge::InferenceClient>>,
+    /// 重排阶段的交叉编码器，未设置时只按相似度、新近程度和重要性重排
+    rerank_inference: Option<Arc<dyn bridge::InferenceClient>>,
+    /// 关键词倒排索引 - 覆盖内存缓存中的记忆，后台清理移出缓存时一并移除
+    keyword_index: Arc<memory::index::KeywordIndex>,
+    /// 查询嵌入缓存
+    query_cache: memory::index::QueryCache,
+    /// 写入前调用的记忆后处理插件
+    plugins: Arc<plugins::PluginRegistry>,
+    /// 记忆内容和查询的嵌入生成器
+    embedder: Arc<dyn memory::embedder::Embedder>,
+    /// 写入向量存储payload时记忆条目的编码
+    payload_codec: vector_store::CodecKind,
+    /// 向量存储的写后缓冲，启用后向量写入由后台任务批量完成
+    write_behind: Option<Arc<vector_store::WriteBehindVectorStore<dyn vector_store::VectorStore<Error = anyhow::Error>>>>,
+    /// 清理、衰减、整理和刷新等后台任务
+    tasks: memory::supervisor::TaskSupervisor,
 }
 
 /// 记忆系统配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct MemoryConfig {
     /// 短期记忆最大条数
     pub short_term_limit: usize,
     /// 长期记忆重要性阈值
     pub long_term_threshold: f32,
-    /// 向量相似度阈值
+    /// 向量相似度阈值，默认值按本地哈希嵌入设定（早期版本为0.8），语义嵌入模型可调高到0.7~0.8
     pub similarity_threshold: f32,
     /// 记忆清理间隔(秒)
     pub cleanup_interval: u64,
+    /// 推理服务重要性评分的权重，其余权重留给调用方给出的评分
+    #[serde(default = "default_inference_importance_weight")]
+    pub inference_importance_weight: f32,
+    /// 两阶段检索的重排配置
+    #[serde(default)]
+    pub rerank: RerankConfig,
+    /// 本地重要性评分的权重
+    #[serde(default)]
+    pub importance: ImportanceConfig,
+}
+
+fn default_inference_importance_weight() -> f32 {
+    0.5
+}
+
+/// 两阶段检索：先用向量搜索和关键词索引取`limit × candidate_multiplier`个候选，
+/// 再按加权分数重排后取前`limit`个
+///
+/// 相似度按完整嵌入精确重算，新近程度按创建时间以`recency_half_life_hours`为半衰期衰减。
+/// `cross_encoder_weight`大于0且记忆系统设置了重排推理客户端时，交叉编码器的相关性分数一并参与；
+/// 推理失败时只用其余三项。各项权重按参与的项归一化。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RerankConfig {
+    /// 关闭时只按向量相似度排序
+    pub enabled: bool,
+    /// 第一阶段候选数相对`limit`的倍数
+    pub candidate_multiplier: usize,
+    pub similarity_weight: f32,
+    pub recency_weight: f32,
+    pub importance_weight: f32,
+    pub cross_encoder_weight: f32,
+    /// 新近程度减半所需的小时数
+    pub recency_half_life_hours: f32,
+}
+
+impl Default for RerankConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            candidate_multiplier: 4,
+            similarity_weight: 0.6,
+            recency_weight: 0.2,
+            importance_weight: 0.2,
+            cross_encoder_weight: 0.0,
+            recency_half_life_hours: 72.0,
+        }
+    }
+}
+
+/// 本地重要性评分：在调用方给出的评分上叠加各项加成，结果截断到0-1
+///
+/// 情绪强度为情感向量各维度的平均值；关键词越多信息量越大，最多计`max_keywords`个；
+/// 新颖程度为已有记忆中没有出现过的关键词所占比例，反复提到的话题不再加分。
+/// 推理服务未配置或不可用时使用。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImportanceConfig {
+    pub emotional_weight: f32,
+    /// 每个关键词的加成
+    pub keyword_weight: f32,
+    pub max_keywords: usize,
+    pub novelty_weight: f32,
+    pub type_priors: TypePriors,
+}
+
+impl Default for ImportanceConfig {
+    fn default() -> Self {
+        Self {
+            emotional_weight: 0.3,
+            keyword_weight: 0.02,
+            max_keywords: 5,
+            novelty_weight: 0.1,
+            type_priors: TypePriors::default(),
+        }
+    }
+}
+
+/// 各记忆类型的重要性先验，直接加到评分上
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TypePriors {
+    pub short_term: f32,
+    pub long_term: f32,
+    pub emotional: f32,
+    pub preference: f32,
+    pub relationship: f32,
+}
+
+impl TypePriors {
+    pub fn of(&self, memory_type: &MemoryType) -> f32 {
+        match memory_type {
+            MemoryType::ShortTerm => self.short_term,
+            MemoryType::LongTerm => self.long_term,
+            MemoryType::Emotional => self.emotional,
+            MemoryType::Preference => self.preference,
+            MemoryType::Relationship => self.relationship,
+        }
+    }
+}
+
+impl Default for TypePriors {
+    fn default() -> Self {
+        Self {
+            short_term: -0.1,
+            long_term: 0.0,
+            emotional: 0.2,
+            preference: 0.0,
+            relationship: 0.2,
+        }
+    }
 }
 
 impl Default for MemoryConfig {
@@ -89,9 +306,55 @@ impl Default for MemoryConfig {
         Self {
             short_term_limit: 100,
             long_term_threshold: 0.7,
-            similarity_threshold: 0.8,
+            similarity_threshold: 0.4,
             cleanup_interval: 3600,
+            inference_importance_weight: default_inference_importance_weight(),
+            rerank: RerankConfig::default(),
+            importance: ImportanceConfig::default(),
+        }
+    }
+}
+
+impl MemoryConfig {
+    /// 检查取值范围
+    pub fn validate(&self) -> Result<()> {
+        if self.short_term_limit == 0 {
+            return Err(MemoryError::ConfigError("short_term_limit必须大于0".to_string()));
+        }
+        if self.cleanup_interval == 0 {
+            return Err(MemoryError::ConfigError("cleanup_interval必须大于0".to_string()));
+        }
+        for (name, value) in [
+            ("long_term_threshold", self.long_term_threshold),
+            ("similarity_threshold", self.similarity_threshold),
+            ("inference_importance_weight", self.inference_importance_weight),
+            ("rerank.similarity_weight", self.rerank.similarity_weight),
+            ("rerank.recency_weight", self.rerank.recency_weight),
+            ("rerank.importance_weight", self.rerank.importance_weight),
+            ("rerank.cross_encoder_weight", self.rerank.cross_encoder_weight),
+            ("importance.emotional_weight", self.importance.emotional_weight),
+            ("importance.keyword_weight", self.importance.keyword_weight),
+            ("importance.novelty_weight", self.importance.novelty_weight),
+        ] {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(MemoryError::ConfigError(format!("{}必须在0到1之间: {}", name, value)));
+            }
+        }
+        if self.rerank.candidate_multiplier == 0 {
+            return Err(MemoryError::ConfigError("rerank.candidate_multiplier必须大于0".to_string()));
         }
+        if self.rerank.recency_half_life_hours <= 0.0 {
+            return Err(MemoryError::ConfigError("rerank.recency_half_life_hours必须大于0".to_string()));
+        }
+        for memory_type in &MemoryType::ALL {
+            let prior = self.importance.type_priors.of(memory_type);
+            if !(-1.0..=1.0).contains(&prior) {
+                return Err(MemoryError::ConfigError(format!(
+                    "importance.type_priors.{}必须在-1到1之间: {}", memory_type.as_str(), prior,
+                )));
+            }
+        }
+        Ok(())
     }
 }
 
@@ -108,6 +371,16 @@ impl Default for EmotionalState {
     }
 }
 
+impl EmotionalState {
+    /// 情感向量维度
+    pub const EMBEDDING_DIM: usize = 4;
+
+    /// 转换为情感向量，用于按情感相似度检索
+    pub fn to_embedding(&self) -> Vec<f32> {
+        vec![self.happiness, self.affection, self.trust, self.dependency]
+    }
+}
+
 impl MemoryEntry {
     pub fn new(
         memory_type: MemoryType,
@@ -127,15 +400,35 @@ impl MemoryEntry {
             last_accessed: Utc::now(),
             access_count: 0,
             metadata: HashMap::new(),
+            expires_at: None,
+            audio: None,
+            image: None,
         }
     }
 
+    /// 附加语音
+    pub fn with_audio(mut self, audio: AudioAttachment) -> Self {
+        self.audio = Some(audio);
+        self
+    }
+
+    /// 附加图片
+    pub fn with_image(mut self, image: ImageAttachment) -> Self {
+        self.image = Some(image);
+        self
+    }
+
     /// 标记为已访问
     pub fn mark_accessed(&mut self) {
         self.last_accessed = Utc::now();
         self.access_count += 1;
     }
 
+    /// 是否已过期
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= Utc::now())
+    }
+
     /// 更新重要性评分
     pub fn update_importance(&mut self, delta: f32) {
         self.importance = (self.importance + delta).clamp(0.0, 1.0);
@@ -144,41 +437,35 @@ impl MemoryEntry {
 
 /// Python绑定模块
 #[cfg(feature = "python-bindings")]
-pub mod python_bindings {
-    use pyo3::prelude::*;
-    use super::*;
-
-    #[pyclass]
-    #[derive(Clone)]
-    pub struct PyMemoryEntry {
-        #[pyo3(get, set)]
-        pub content: String,
-        #[pyo3(get, set)]
-        pub importance: f32,
-        #[pyo3(get, set)]
-        pub keywords: Vec<String>,
-    }
-
-    #[pymethods]
-    impl PyMemoryEntry {
-        #[new]
-        pub fn new(content: String, importance: f32, keywords: Vec<String>) -> Self {
-            Self { content, importance, keywords }
-        }
-    }
+pub mod python_bindings;
 
-    #[pyfunction]
-    pub fn create_memory_entry(content: String, importance: f32) -> PyResult<PyMemoryEntry> {
-        Ok(PyMemoryEntry::new(content, importance, vec![]))
-    }
+/// REST API服务模块
+#[cfg(feature = "server")]
+pub mod server;
 
-    #[pymodule]
-    fn ai_girlfriend_memory(_py: Python, m: &PyModule) -> PyResult<()> {
-        m.add_class::<PyMemoryEntry>()?;
-        m.add_function(wrap_pyfunction!(create_memory_entry, m)?)?;
-        Ok(())
-    }
-}
+/// gRPC服务模块
+#[cfg(feature = "grpc")]
+pub mod grpc;
+
+/// C ABI模块
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+/// OpenTelemetry导出模块
+#[cfg(feature = "otlp")]
+pub mod telemetry;
+
+/// Discord机器人模块
+#[cfg(feature = "discord")]
+pub mod discord;
+
+/// Matrix机器人模块
+#[cfg(feature = "matrix")]
+pub mod matrix;
+
+/// WebAssembly绑定模块
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 /// 错误类型定义
 #[derive(thiserror::Error, Debug)]
@@ -191,6 +478,40 @@ pub enum MemoryError {
     SerializationError(#[from] serde_json::Error),
     #[error("数据库错误: {0}")]
     DatabaseError(String),
+    #[error("推理服务错误: {0}")]
+    InferenceError(String),
+    #[error("推理服务不可用: {0}")]
+    InferenceUnavailable(String),
+    /// 模型繁忙，稍后重试可能成功
+    #[error("推理模型过载: {0}")]
+    ModelOverloaded(String),
+    /// 输入超出模型上下文长度，需要截断后重试
+    #[error("输入超出模型上下文长度: {0}")]
+    ContextTooLong(String),
+    /// 输入不合法，重试不会成功
+    #[error("推理输入无效: {0}")]
+    InvalidInput(String),
+    #[error("配置错误: {0}")]
+    ConfigError(String),
+    #[error("文件读写错误: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Webhook投递失败: {0}")]
+    WebhookError(String),
+    #[error("向量维度不匹配: 期望 {expected}, 实际 {actual}")]
+    DimensionMismatch { expected: usize, actual: usize },
+    /// 写入队列已满，稍后重试可能成功
+    #[error("写入队列已满: 容量 {capacity}")]
+    IngestQueueFull { capacity: usize },
+    #[error("写入管道已关闭")]
+    IngestClosed,
+    /// Zig层返回的错误码
+    #[cfg(feature = "native")]
+    #[error("Zig调用 {call} 失败: {code}")]
+    Ffi { call: &'static str, code: bridge::FfiErrorCode },
+    /// 调用前在Rust侧检查出的参数错误，未进入Zig层
+    #[cfg(feature = "native")]
+    #[error("Zig调用 {call} 参数无效: {reason}")]
+    FfiInvariant { call: &'static str, reason: String },
 }
 
 pub type Result<T> = std::result::Result<T, MemoryError>;
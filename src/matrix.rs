@@ -0,0 +1,301 @@
+//! Matrix机器人 - 每个房间对应`MemoryManager`中的一个会话，加密房间由matrix-sdk自动解密
+//!
+//! 私聊房间中的每条消息都会回复，群聊房间中只回复提及机器人的消息。
+//! 只接受`allowed_inviters`中的用户或服务器发来的邀请，其余邀请被拒绝；房间会话数量有上限。
+//! 加密密钥保存在`store_path`的SQLite存储中，登录会话保存在同一目录的`session.json`，
+//! 重启后恢复原会话，不再重复登录，也无需重新验证设备。
+//! 定时任务的主动消息和提醒按会话ID发送到对应房间。
+
+use crate::bridge::InferenceClient;
+use crate::chat::{ChatSession, ChatSessions, DEFAULT_MAX_SESSIONS};
+use crate::emotion::{EmotionalEngine, PersonalityGenerator, PersonalityProfile};
+use crate::memory::MemoryManager;
+use crate::scheduler::SchedulerEvent;
+use crate::{MemoryError, Result};
+use matrix_sdk::authentication::matrix::MatrixSession;
+use matrix_sdk::config::SyncSettings;
+use matrix_sdk::event_handler::Ctx;
+use matrix_sdk::room::Room;
+use matrix_sdk::ruma::events::room::member::StrippedRoomMemberEvent;
+use matrix_sdk::ruma::events::room::message::{MessageType, OriginalSyncRoomMessageEvent, RoomMessageEventContent};
+use matrix_sdk::ruma::{OwnedRoomId, RoomId, UserId};
+use matrix_sdk::{Client, RoomState};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, Mutex};
+
+/// 会话ID前缀
+const SESSION_PREFIX: &str = "matrix:room:";
+
+/// 登录会话在`store_path`中的文件名
+const SESSION_FILE: &str = "session.json";
+
+/// 会话在`MemoryManager`中的用户ID
+fn session_key(room_id: &RoomId) -> String {
+    format!("{}{}", SESSION_PREFIX, room_id)
+}
+
+/// 会话ID对应的房间，非Matrix会话为None
+fn room_id_from_key(key: &str) -> Option<OwnedRoomId> {
+    key.strip_prefix(SESSION_PREFIX)?.try_into().ok()
+}
+
+/// 去掉消息中对机器人的提及，未提及时为None
+fn strip_mention(body: &str, user_id: &str, display_name: Option<&str>) -> Option<String> {
+    let names = [Some(user_id), display_name];
+    let name = names.into_iter().flatten().find(|name| body.contains(name))?;
+    let input = body.replacen(name, "", 1);
+    Some(input.trim_start_matches([':', '：', ',', '，', ' ']).trim().to_string())
+}
+
+/// Matrix登录配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatrixConfig {
+    pub homeserver_url: String,
+    pub username: String,
+    pub password: String,
+    /// 加密密钥和同步状态的存储目录
+    pub store_path: PathBuf,
+    /// 存储加密口令
+    pub store_passphrase: Option<String>,
+    pub device_name: String,
+    /// 接受其邀请的用户ID（`@user:example.org`）或服务器名（`example.org`），为空时不接受任何邀请
+    #[serde(default)]
+    pub allowed_inviters: Vec<String>,
+    /// 最多保留的房间会话数
+    #[serde(default = "default_max_sessions")]
+    pub max_sessions: usize,
+}
+
+fn default_max_sessions() -> usize {
+    DEFAULT_MAX_SESSIONS
+}
+
+/// 邀请者是否在允许列表中，列表项为用户ID或服务器名
+fn invite_allowed(allowed_inviters: &[String], sender: &UserId) -> bool {
+    allowed_inviters.iter().any(|allowed| if allowed.starts_with('@') {
+        allowed == sender.as_str()
+    } else {
+        allowed == sender.server_name().as_str()
+    })
+}
+
+/// Matrix机器人
+#[derive(Debug)]
+pub struct MatrixBot {
+    client: Client,
+    manager: Arc<MemoryManager>,
+    inference: Arc<dyn InferenceClient>,
+    engine: Arc<EmotionalEngine>,
+    personality: Arc<PersonalityGenerator>,
+    sessions: ChatSessions,
+    allowed_inviters: Vec<String>,
+}
+
+impl MatrixBot {
+    /// 恢复保存的登录会话，没有时登录homeserver并保存会话
+    pub async fn login(config: &MatrixConfig, manager: Arc<MemoryManager>, inference: Arc<dyn InferenceClient>) -> Result<Self> {
+        let client = Client::builder()
+            .homeserver_url(&config.homeserver_url)
+            .sqlite_store(&config.store_path, config.store_passphrase.as_deref())
+            .build()
+            .await
+            .map_err(|e| MemoryError::ConfigError(format!("创建Matrix客户端失败: {}", e)))?;
+
+        let session_path = config.store_path.join(SESSION_FILE);
+        match load_session(&session_path)? {
+            Some(session) => client.restore_session(session).await
+                .map_err(|e| MemoryError::ConfigError(format!("恢复Matrix会话失败: {}", e)))?,
+            None => {
+                client.matrix_auth()
+                    .login_username(&config.username, &config.password)
+                    .initial_device_display_name(&config.device_name)
+                    .await
+                    .map_err(|e| MemoryError::ConfigError(format!("Matrix登录失败: {}", e)))?;
+                if let Some(session) = client.matrix_auth().session() {
+                    save_session(&session_path, &session)?;
+                }
+            }
+        }
+
+        Ok(Self {
+            client,
+            manager,
+            inference,
+            engine: Arc::new(EmotionalEngine::new()),
+            personality: Arc::new(PersonalityGenerator::new(PersonalityProfile::default())),
+            sessions: ChatSessions::new(config.max_sessions),
+            allowed_inviters: config.allowed_inviters.clone(),
+        })
+    }
+
+    /// 指定个性档案
+    pub fn with_personality(mut self, profile: PersonalityProfile) -> Self {
+        self.personality = Arc::new(PersonalityGenerator::new(profile));
+        self
+    }
+
+    /// 开始同步，登录前积压的消息不回复，直到连接断开
+    pub async fn run(self: Arc<Self>) -> matrix_sdk::Result<()> {
+        let response = self.client.sync_once(SyncSettings::default()).await?;
+
+        self.client.add_event_handler_context(self.clone());
+        self.client.add_event_handler(on_invite);
+        self.client.add_event_handler(on_message);
+        tracing::info!("Matrix机器人已登录: {:?}", self.client.user_id());
+        self.client.sync(SyncSettings::default().token(response.next_batch)).await
+    }
+
+    fn session(&self, key: &str) -> Arc<Mutex<ChatSession>> {
+        self.sessions.get_or_insert_with(key, || ChatSession::new(
+            key,
+            self.manager.clone(),
+            self.inference.clone(),
+            self.engine.clone(),
+            self.personality.clone(),
+        ))
+    }
+
+    /// 向会话对应的房间发送主动消息，非Matrix会话返回false
+    pub async fn send_proactive(&self, key: &str, text: &str) -> Result<bool> {
+        let Some(room) = room_id_from_key(key).and_then(|room_id| self.client.get_room(&room_id)) else {
+            return Ok(false);
+        };
+        room.send(RoomMessageEventContent::text_plain(text)).await
+            .map_err(|e| MemoryError::ConfigError(format!("发送Matrix消息失败: {}", e)))?;
+        Ok(true)
+    }
+
+    /// 把定时任务的主动消息和提醒发送到对应房间，直到调度器被释放
+    pub fn forward_scheduler(self: Arc<Self>, mut events: broadcast::Receiver<SchedulerEvent>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                let (user_id, text) = match events.recv().await {
+                    Ok(SchedulerEvent::Message { user_id, text, .. }) => (user_id, text),
+                    Ok(SchedulerEvent::Reminder { user_id, content, .. }) => (user_id, format!("提醒：{}", content)),
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Matrix主动消息落后，跳过 {} 条", skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                if let Err(e) = self.send_proactive(&user_id, &text).await {
+                    tracing::warn!("{}", e);
+                }
+            }
+        })
+    }
+
+    async fn reply(&self, room: &Room, input: &str) {
+        let key = session_key(room.room_id());
+        let session = self.session(&key);
+        // 生成期间显示"正在输入"
+        let _ = room.typing_notice(true).await;
+        let response = session.lock().await.turn(input, |_| {}).await;
+        let _ = room.typing_notice(false).await;
+
+        let response = response.unwrap_or_else(|e| {
+            tracing::warn!("对话处理失败 {}: {}", key, e);
+            "呜…刚才走神了，能再说一遍吗？".to_string()
+        });
+        if let Err(e) = room.send(RoomMessageEventContent::text_plain(response)).await {
+            tracing::warn!("发送Matrix消息失败: {}", e);
+        }
+    }
+}
+
+/// 读取保存的登录会话，文件不存在时为None
+fn load_session(path: &Path) -> Result<Option<MatrixSession>> {
+    match std::fs::read(path) {
+        Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 保存登录会话，文件中有访问令牌，Unix上只允许所有者读写
+fn save_session(path: &Path, session: &MatrixSession) -> Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    serde_json::to_writer(options.open(path)?, session)?;
+    Ok(())
+}
+
+async fn on_invite(event: StrippedRoomMemberEvent, room: Room, client: Client, bot: Ctx<Arc<MatrixBot>>) {
+    if client.user_id() != Some(&*event.state_key) {
+        return;
+    }
+    if !invite_allowed(&bot.allowed_inviters, &event.sender) {
+        tracing::info!("拒绝 {} 邀请加入房间 {}", event.sender, room.room_id());
+        if let Err(e) = room.leave().await {
+            tracing::warn!("拒绝房间 {} 的邀请失败: {}", room.room_id(), e);
+        }
+        return;
+    }
+    if let Err(e) = room.join().await {
+        tracing::warn!("加入房间 {} 失败: {}", room.room_id(), e);
+    }
+}
+
+async fn on_message(event: OriginalSyncRoomMessageEvent, room: Room, bot: Ctx<Arc<MatrixBot>>) {
+    if room.state() != RoomState::Joined {
+        return;
+    }
+    let Some(me) = bot.client.user_id() else {
+        return;
+    };
+    if event.sender.as_str() == me.as_str() {
+        return;
+    }
+    let MessageType::Text(text) = event.content.msgtype else {
+        return;
+    };
+
+    let input = if room.is_direct().await.unwrap_or(false) {
+        text.body.trim().to_string()
+    } else {
+        let display_name = bot.client.account().get_display_name().await.ok().flatten();
+        match strip_mention(&text.body, me.as_str(), display_name.as_deref()) {
+            Some(input) => input,
+            None => return,
+        }
+    };
+    if !input.is_empty() {
+        bot.reply(&room, &input).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rooms_map_to_sessions() {
+        let room_id: OwnedRoomId = "!abc:example.org".try_into().unwrap();
+        let key = session_key(&room_id);
+        assert_eq!(key, "matrix:room:!abc:example.org");
+        assert_eq!(room_id_from_key(&key), Some(room_id));
+        assert_eq!(room_id_from_key("discord:user:20"), None);
+    }
+
+    #[test]
+    fn test_invite_allowlist() {
+        let allowed = vec!["@alice:example.org".to_string(), "friends.org".to_string()];
+        let user = |id: &str| -> matrix_sdk::ruma::OwnedUserId { id.try_into().unwrap() };
+        assert!(invite_allowed(&allowed, &user("@alice:example.org")));
+        assert!(invite_allowed(&allowed, &user("@bob:friends.org")));
+        assert!(!invite_allowed(&allowed, &user("@bob:example.org")));
+        assert!(!invite_allowed(&[], &user("@alice:example.org")));
+    }
+
+    #[test]
+    fn test_strip_mention() {
+        assert_eq!(strip_mention("@mira:example.org: 你好", "@mira:example.org", None).as_deref(), Some("你好"));
+        assert_eq!(strip_mention("Mira，晚安", "@mira:example.org", Some("Mira")).as_deref(), Some("晚安"));
+        assert_eq!(strip_mention("大家好", "@mira:example.org", Some("Mira")), None);
+    }
+}
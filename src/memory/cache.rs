@@ -0,0 +1,443 @@
+//! 记忆缓存 - 按记忆类型分片，增量维护创建时间和重要性索引，条目以`Arc`共享
+//!
+//! 读取返回`Arc<MemoryEntry>`，不复制条目；修改时写时复制，没有其他持有者时原地修改，
+//! 已返回给调用方的条目不受影响。统计、按时间列出和短期记忆清理都由分片和索引直接得到，
+//! 无需遍历全部条目。克隆得到的是同一份缓存的句柄。
+//!
+//! 不改变类型、创建时间和重要性的修改（如记录访问）只锁住条目所在的分片；
+//! 这些字段变化后再取得索引锁修正索引，需要换分片时先写入新分片再移除旧分片。
+//!
+//! 嵌入向量写入时从条目中取出单独保存，缓存和检索返回的条目不带嵌入，需要时用`embedding`读取。
+
+use crate::{MemoryEntry, MemoryType};
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Arc, Mutex, MutexGuard};
+use uuid::Uuid;
+
+/// 分片数，每种记忆类型一个
+const SHARDS: usize = MemoryType::ALL.len();
+
+fn shard_index(memory_type: &MemoryType) -> usize {
+    match memory_type {
+        MemoryType::ShortTerm => 0,
+        MemoryType::LongTerm => 1,
+        MemoryType::Emotional => 2,
+        MemoryType::Preference => 3,
+        MemoryType::Relationship => 4,
+    }
+}
+
+/// 重要性的索引键 - 非负浮点数的位模式与数值同序
+fn importance_key(importance: f32) -> u32 {
+    importance.max(0.0).to_bits()
+}
+
+/// 条目在索引中的位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct IndexKey {
+    shard: usize,
+    created_at: DateTime<Utc>,
+    importance: u32,
+}
+
+impl IndexKey {
+    fn of(entry: &MemoryEntry) -> Self {
+        Self {
+            shard: shard_index(&entry.memory_type),
+            created_at: entry.created_at,
+            importance: importance_key(entry.importance),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Indexes {
+    /// 各条目登记在索引中的位置
+    keys: HashMap<Uuid, IndexKey>,
+    /// 创建时间索引，值为所在分片
+    by_created: BTreeMap<(DateTime<Utc>, Uuid), usize>,
+    /// 各分片的重要性索引
+    by_importance: [BTreeSet<(u32, Uuid)>; SHARDS],
+}
+
+impl Indexes {
+    /// 登记条目的位置，替换原有位置
+    fn set(&mut self, id: Uuid, key: IndexKey) {
+        match self.keys.insert(id, key) {
+            Some(previous) if previous == key => return,
+            Some(previous) => self.unlink(id, previous),
+            None => {}
+        }
+        self.by_created.insert((key.created_at, id), key.shard);
+        self.by_importance[key.shard].insert((key.importance, id));
+    }
+
+    fn remove(&mut self, id: Uuid) {
+        if let Some(key) = self.keys.remove(&id) {
+            self.unlink(id, key);
+        }
+    }
+
+    fn unlink(&mut self, id: Uuid, key: IndexKey) {
+        self.by_created.remove(&(key.created_at, id));
+        self.by_importance[key.shard].remove(&(key.importance, id));
+    }
+}
+
+#[derive(Debug, Default)]
+struct Shards {
+    shards: [DashMap<Uuid, Arc<MemoryEntry>>; SHARDS],
+    embeddings: DashMap<Uuid, Arc<Vec<f32>>>,
+    /// 增删条目和修正索引时先取得索引锁再锁分片，持有分片锁时不再取得索引锁；读操作不加锁
+    indexes: Mutex<Indexes>,
+}
+
+/// 分片记忆缓存
+#[derive(Debug, Clone, Default)]
+pub struct MemoryCache {
+    inner: Arc<Shards>,
+}
+
+impl MemoryCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn indexes(&self) -> MutexGuard<'_, Indexes> {
+        self.inner.indexes.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn locate(&self, id: &Uuid) -> Option<usize> {
+        self.inner.shards.iter().position(|shard| shard.contains_key(id))
+    }
+
+    pub fn get(&self, id: &Uuid) -> Option<Arc<MemoryEntry>> {
+        self.inner.shards.iter().find_map(|shard| shard.get(id).map(|entry| entry.clone()))
+    }
+
+    pub fn contains(&self, id: &Uuid) -> bool {
+        self.locate(id).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.shards.iter().map(DashMap::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.shards.iter().all(DashMap::is_empty)
+    }
+
+    /// 指定类型的条数
+    pub fn len_of(&self, memory_type: &MemoryType) -> usize {
+        self.inner.shards[shard_index(memory_type)].len()
+    }
+
+    /// 条目的嵌入向量
+    pub fn embedding(&self, id: &Uuid) -> Option<Arc<Vec<f32>>> {
+        self.inner.embeddings.get(id).map(|embedding| embedding.clone())
+    }
+
+    /// 写入条目，返回被替换的同ID条目
+    ///
+    /// 条目带嵌入时取出单独保存，替换原有嵌入；不带嵌入时保留同ID条目已有的嵌入
+    pub fn insert(&self, entry: impl Into<Arc<MemoryEntry>>) -> Option<Arc<MemoryEntry>> {
+        let mut entry = entry.into();
+        let embedding = match entry.embedding {
+            Some(_) => Arc::make_mut(&mut entry).embedding.take(),
+            None => None,
+        };
+        let mut indexes = self.indexes();
+        let previous = self.remove_locked(&mut indexes, &entry.id);
+        if let Some(embedding) = embedding {
+            self.inner.embeddings.insert(entry.id, Arc::new(embedding));
+        }
+        let key = IndexKey::of(&entry);
+        indexes.set(entry.id, key);
+        self.inner.shards[key.shard].insert(entry.id, entry);
+        previous
+    }
+
+    pub fn remove(&self, id: &Uuid) -> Option<Arc<MemoryEntry>> {
+        let mut indexes = self.indexes();
+        self.inner.embeddings.remove(id);
+        self.remove_locked(&mut indexes, id)
+    }
+
+    fn remove_locked(&self, indexes: &mut Indexes, id: &Uuid) -> Option<Arc<MemoryEntry>> {
+        let (_, entry) = self.inner.shards[self.locate(id)?].remove(id)?;
+        indexes.remove(*id);
+        Some(entry)
+    }
+
+    /// 修改条目，类型、创建时间或重要性变化时同步更新分片和索引；条目不存在时为None
+    ///
+    /// 调用方持有同一条目的`Arc`时修改会复制条目，应先释放再修改
+    pub fn update<R>(&self, id: &Uuid, f: impl FnOnce(&mut MemoryEntry) -> R) -> Option<R> {
+        let shard = self.locate(id)?;
+        let mut slot = self.inner.shards[shard].get_mut(id)?;
+        if IndexKey::of(&slot).shard != shard {
+            // 条目的类型已改变、尚未换到新分片，在索引锁下换分片后再修改
+            drop(slot);
+            let mut indexes = self.indexes();
+            self.reindex_locked(&mut indexes, id);
+            return self.modify(id, f).map(|(result, changed)| {
+                if changed {
+                    self.reindex_locked(&mut indexes, id);
+                }
+                result
+            });
+        }
+        let (result, changed) = Self::apply(&self.inner.embeddings, id, slot.value_mut(), f);
+        drop(slot);
+        if changed {
+            let mut indexes = self.indexes();
+            self.reindex_locked(&mut indexes, id);
+        }
+        Some(result)
+    }
+
+    /// 记录一次访问并返回条目
+    pub fn touch(&self, id: &Uuid) -> Option<Arc<MemoryEntry>> {
+        self.update(id, MemoryEntry::mark_accessed)?;
+        self.get(id)
+    }
+
+    /// 只锁住条目所在的分片修改条目，返回结果和索引位置是否变化
+    fn modify<R>(&self, id: &Uuid, f: impl FnOnce(&mut MemoryEntry) -> R) -> Option<(R, bool)> {
+        let mut slot = self.inner.shards[self.locate(id)?].get_mut(id)?;
+        Some(Self::apply(&self.inner.embeddings, id, slot.value_mut(), f))
+    }
+
+    fn apply<R>(
+        embeddings: &DashMap<Uuid, Arc<Vec<f32>>>,
+        id: &Uuid,
+        slot: &mut Arc<MemoryEntry>,
+        f: impl FnOnce(&mut MemoryEntry) -> R,
+    ) -> (R, bool) {
+        let before = IndexKey::of(slot);
+        let entry = Arc::make_mut(slot);
+        let result = f(entry);
+        if let Some(embedding) = entry.embedding.take() {
+            embeddings.insert(*id, Arc::new(embedding));
+        }
+        (result, IndexKey::of(entry) != before)
+    }
+
+    /// 按条目当前的类型、创建时间和重要性修正索引和所在分片
+    fn reindex_locked(&self, indexes: &mut Indexes, id: &Uuid) {
+        let Some(shard) = self.locate(id) else {
+            return;
+        };
+        let Some(entry) = self.inner.shards[shard].get(id).map(|entry| entry.clone()) else {
+            return;
+        };
+        let key = IndexKey::of(&entry);
+        if key.shard != shard {
+            // 先写入新分片再移除，并发读取不会错过条目；旧分片中类型不符的副本不接受无锁修改
+            self.inner.shards[key.shard].insert(*id, entry);
+            self.inner.shards[shard].remove(id);
+        }
+        indexes.set(*id, key);
+    }
+
+    /// 删除不满足条件的条目，返回被删除的条目
+    pub fn retain(&self, mut keep: impl FnMut(&MemoryEntry) -> bool) -> Vec<Arc<MemoryEntry>> {
+        let mut indexes = self.indexes();
+        let mut removed = Vec::new();
+        for shard in &self.inner.shards {
+            shard.retain(|id, entry| {
+                let kept = keep(entry);
+                if !kept {
+                    indexes.remove(*id);
+                    self.inner.embeddings.remove(id);
+                    removed.push(entry.clone());
+                }
+                kept
+            });
+        }
+        removed
+    }
+
+    pub fn clear(&self) {
+        let mut indexes = self.indexes();
+        *indexes = Indexes::default();
+        for shard in &self.inner.shards {
+            shard.clear();
+        }
+        self.inner.embeddings.clear();
+    }
+
+    /// 全部条目，顺序不定
+    pub fn entries(&self) -> Vec<Arc<MemoryEntry>> {
+        self.inner.shards.iter()
+            .flat_map(|shard| shard.iter().map(|entry| entry.clone()).collect::<Vec<_>>())
+            .collect()
+    }
+
+    /// 指定类型（None为全部类型）的条目，按创建时间升序
+    pub fn by_created(&self, memory_types: Option<&[MemoryType]>) -> Vec<Arc<MemoryEntry>> {
+        let mut wanted = [memory_types.is_none(); SHARDS];
+        for memory_type in memory_types.unwrap_or_default() {
+            wanted[shard_index(memory_type)] = true;
+        }
+
+        let located: Vec<(Uuid, usize)> = self.indexes().by_created.iter()
+            .filter(|(_, shard)| wanted[**shard])
+            .map(|((_, id), shard)| (*id, *shard))
+            .collect();
+        located.into_iter()
+            .filter_map(|(id, shard)| self.inner.shards[shard].get(&id).map(|entry| entry.clone()))
+            .collect()
+    }
+
+    /// 指定类型中重要性不低于`threshold`的条目，按重要性升序
+    pub fn importance_at_least(&self, memory_type: &MemoryType, threshold: f32) -> Vec<Arc<MemoryEntry>> {
+        let shard = shard_index(memory_type);
+        let ids: Vec<Uuid> = self.indexes().by_importance[shard]
+            .range((importance_key(threshold), Uuid::nil())..)
+            .map(|(_, id)| *id)
+            .collect();
+        ids.iter()
+            .filter_map(|id| self.inner.shards[shard].get(id).map(|entry| entry.clone()))
+            .collect()
+    }
+
+    /// 指定类型中重要性最低的`count`条，重要性相同时最久未访问的在前
+    pub fn least_important(&self, memory_type: &MemoryType, count: usize) -> Vec<Arc<MemoryEntry>> {
+        let shard = shard_index(memory_type);
+        let mut selected = Vec::new();
+        {
+            let indexes = self.indexes();
+            let mut last_key = None;
+            // 多取出与第`count`条重要性相同的条目，再按访问时间决定去留
+            for &(key, id) in &indexes.by_importance[shard] {
+                if selected.len() >= count && last_key != Some(key) {
+                    break;
+                }
+                if let Some(entry) = self.inner.shards[shard].get(&id) {
+                    selected.push(entry.clone());
+                }
+                last_key = Some(key);
+            }
+        }
+
+        selected.sort_by(|a: &Arc<MemoryEntry>, b| {
+            a.importance.total_cmp(&b.importance)
+                .then_with(|| a.last_accessed.cmp(&b.last_accessed))
+        });
+        selected.truncate(count);
+        selected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(memory_type: MemoryType, content: &str, importance: f32) -> MemoryEntry {
+        MemoryEntry::new(memory_type, content.to_string(), vec![], importance)
+    }
+
+    #[test]
+    fn test_indexes_follow_updates() {
+        let cache = MemoryCache::new();
+        let first = entry(MemoryType::ShortTerm, "早饭", 0.2);
+        let mut second = entry(MemoryType::ShortTerm, "午饭", 0.9);
+        second.created_at = first.created_at + chrono::Duration::seconds(1);
+        let third = entry(MemoryType::Preference, "喜欢绿茶", 0.5);
+        let (first_id, second_id) = (first.id, second.id);
+        cache.insert(first);
+        cache.insert(second);
+        cache.insert(third);
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.len_of(&MemoryType::ShortTerm), 2);
+
+        let held = cache.get(&first_id).unwrap();
+        cache.update(&first_id, |entry| entry.memory_type = MemoryType::LongTerm).unwrap();
+        // 已返回的条目不受修改影响
+        assert_eq!(held.memory_type, MemoryType::ShortTerm);
+        assert_eq!(cache.len_of(&MemoryType::ShortTerm), 1);
+        assert_eq!(cache.get(&first_id).unwrap().memory_type, MemoryType::LongTerm);
+
+        let ordered: Vec<Uuid> = cache.by_created(Some(&[MemoryType::ShortTerm, MemoryType::LongTerm]))
+            .iter().map(|entry| entry.id).collect();
+        assert_eq!(ordered, vec![first_id, second_id]);
+        assert_eq!(cache.by_created(None).len(), 3);
+
+        cache.update(&second_id, |entry| entry.importance = 0.1).unwrap();
+        assert!(cache.importance_at_least(&MemoryType::ShortTerm, 0.5).is_empty());
+        assert_eq!(cache.remove(&second_id).unwrap().importance, 0.1);
+        assert!(cache.by_created(Some(&[MemoryType::ShortTerm])).is_empty());
+        assert!(cache.update(&second_id, |_| ()).is_none());
+    }
+
+    #[test]
+    fn test_touch_modifies_unshared_entries_in_place() {
+        let cache = MemoryCache::new();
+        let stored = entry(MemoryType::ShortTerm, "散步", 0.4);
+        let id = stored.id;
+        cache.insert(stored);
+
+        let held = cache.touch(&id).unwrap();
+        assert_eq!(held.access_count, 1);
+        // 调用方仍持有条目时写时复制
+        let touched = cache.touch(&id).unwrap();
+        assert_eq!((held.access_count, touched.access_count), (1, 2));
+        assert!(!Arc::ptr_eq(&held, &touched));
+        drop((held, touched));
+
+        let before = Arc::as_ptr(&cache.get(&id).unwrap());
+        assert_eq!(Arc::as_ptr(&cache.touch(&id).unwrap()), before);
+        assert_eq!(cache.importance_at_least(&MemoryType::ShortTerm, 0.4).len(), 1);
+    }
+
+    #[test]
+    fn test_embeddings_kept_outside_entries() {
+        let cache = MemoryCache::new();
+        let mut stored = entry(MemoryType::LongTerm, "会游泳", 0.6);
+        stored.embedding = Some(vec![0.1, 0.2, 0.3]);
+        let id = stored.id;
+        cache.insert(stored);
+
+        assert!(cache.get(&id).unwrap().embedding.is_none());
+        assert_eq!(cache.embedding(&id).unwrap().as_slice(), &[0.1, 0.2, 0.3]);
+
+        // 不带嵌入的同ID条目保留原有嵌入
+        let mut restored = MemoryEntry::clone(&cache.get(&id).unwrap());
+        restored.importance = 0.9;
+        cache.insert(restored);
+        assert_eq!(cache.embedding(&id).unwrap().len(), 3);
+
+        cache.update(&id, |entry| entry.embedding = Some(vec![1.0])).unwrap();
+        assert!(cache.get(&id).unwrap().embedding.is_none());
+        assert_eq!(cache.embedding(&id).unwrap().as_slice(), &[1.0]);
+
+        cache.remove(&id);
+        assert!(cache.embedding(&id).is_none());
+    }
+
+    #[test]
+    fn test_least_important_breaks_ties_by_access() {
+        let cache = MemoryCache::new();
+        let mut stale = entry(MemoryType::ShortTerm, "很久没提", 0.3);
+        stale.last_accessed -= chrono::Duration::hours(1);
+        let fresh = entry(MemoryType::ShortTerm, "刚提到", 0.3);
+        let important = entry(MemoryType::ShortTerm, "重要", 0.8);
+        let stale_id = stale.id;
+        for entry in [fresh, important, stale] {
+            cache.insert(entry);
+        }
+
+        let evicted = cache.least_important(&MemoryType::ShortTerm, 1);
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(evicted[0].id, stale_id);
+        assert_eq!(cache.least_important(&MemoryType::ShortTerm, 5).len(), 3);
+
+        let removed = cache.retain(|entry| entry.importance > 0.5);
+        assert_eq!(removed.len(), 2);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.importance_at_least(&MemoryType::ShortTerm, 0.5).len(), 1);
+    }
+}
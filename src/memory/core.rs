@@ -1,33 +1,215 @@
 //! MIRA记忆系统核心实现  
 //! My Intelligent Romantic Assistant - 使用最新的Rust并发特性和内存池优化
 
-use crate::{MemoryEntry, MemoryType, MemorySystem, MemoryConfig, EmotionalState, Result, MemoryError};
+use crate::{MemoryEntry, MemoryType, MemorySystem, MemoryConfig, EmotionalState, ImageAttachment, Result, MemoryError};
+use crate::bridge::{local_keywords, InferenceClient};
+use crate::plugins::PluginRegistry;
+use crate::vector_store::{
+    Codec, CodecKind, DimensionMismatch, HealthStatus, InstrumentedVectorStore, OperationMetrics, SearchFilter, SearchHit,
+    TenantVectorStore, VectorSpace, WriteBehindConfig, WriteBehindVectorStore,
+};
+use crate::vector_store::codec::{PAYLOAD_CODEC, PAYLOAD_ENCODED_ENTRY};
+use crate::vector_store::exact::top_k_by;
+use crate::vector_store::filter::{PAYLOAD_CREATED_AT_TS, PAYLOAD_EXPIRES_AT_TS, PAYLOAD_MEMORY_TYPE, PAYLOAD_USER_ID};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use super::cache::MemoryCache;
+use super::embedder::{Embedder, HashEmbedder};
+use super::hash::{TextHasher, ZigHasher};
+use super::index::{KeywordIndex, QueryCache};
+use super::supervisor::{TaskKind, TaskSupervisor};
+use crate::emotion::EmotionalEngine;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
-use dashmap::DashMap;
+use futures::StreamExt;
 
 use uuid::Uuid;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+
+/// 默认嵌入生成器输出的向量维度
+pub const EMBEDDING_DIM: usize = 768;
+
+/// 列出记忆时每页从向量存储读取的点数
+const LIST_PAGE_SIZE: usize = 256;
+
+/// 情感衰减任务的运行间隔，衰减幅度由情感引擎按经过的时间计算
+pub const EMOTION_DECAY_PERIOD: Duration = Duration::from_secs(3600);
 
 impl MemorySystem {
-    /// 创建新的记忆系统实例
+    /// 创建新的记忆系统实例，使用`EMBEDDING_DIM`维的本地哈希嵌入
     pub async fn new(
         user_id: String,
         vector_store: Arc<dyn crate::vector_store::VectorStore<Error = anyhow::Error>>,
         config: Option<MemoryConfig>,
+    ) -> Result<Self> {
+        Self::new_with_embedder(user_id, vector_store, config, Arc::new(HashEmbedder::default())).await
+    }
+
+    /// 使用指定的嵌入生成器创建记忆系统，嵌入维度需与向量存储一致
+    pub async fn new_with_embedder(
+        user_id: String,
+        vector_store: Arc<dyn crate::vector_store::VectorStore<Error = anyhow::Error>>,
+        config: Option<MemoryConfig>,
+        embedder: Arc<dyn Embedder>,
     ) -> Result<Self> {
         let config = config.unwrap_or_default();
+
+        // 启动时校验向量维度，避免写入时才报错
+        if let Some(expected) = vector_store.vector_size() {
+            if expected != embedder.dimension() {
+                return Err(MemoryError::DimensionMismatch { expected, actual: embedder.dimension() });
+            }
+        }
+        
+        // 记录后端操作指标，所有向量操作都限定在当前用户范围内
+        let vector_store = Arc::new(InstrumentedVectorStore::new(vector_store));
+        let vector_store = Arc::new(TenantVectorStore::new(vector_store, user_id.clone()));
+        let hasher: Arc<dyn TextHasher> = Arc::new(ZigHasher);
         
         Ok(Self {
-            memory_cache: DashMap::new(),
+            memory_cache: MemoryCache::new(),
             vector_store,
             current_emotion: Arc::new(RwLock::new(EmotionalState::default())),
             user_id,
-            config,
+            config: Arc::new(std::sync::RwLock::new(Arc::new(config))),
+            importance_inference: None,
+            rerank_inference: None,
+            keyword_index: Arc::new(KeywordIndex::new(hasher.clone())),
+            query_cache: QueryCache::new(hasher, QueryCache::DEFAULT_CAPACITY),
+            plugins: Arc::new(PluginRegistry::default()),
+            payload_codec: CodecKind::default(),
+            embedder,
+            write_behind: None,
+            tasks: TaskSupervisor::default(),
         })
     }
 
+    /// 记忆所属的用户ID
+    pub fn user_id(&self) -> &str {
+        &self.user_id
+    }
+
+    /// 记忆内容和查询的嵌入生成器，批量导入时用于预先生成嵌入
+    pub fn embedder(&self) -> Arc<dyn Embedder> {
+        self.embedder.clone()
+    }
+
+    /// 当前配置
+    pub fn config(&self) -> Arc<MemoryConfig> {
+        self.config.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// 替换配置，之后的读写和清理立即使用新值；清理间隔在下次启动清理任务时生效
+    pub fn update_config(&self, config: MemoryConfig) {
+        *self.config.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(config);
+    }
+
+    /// 替换关键词索引和查询缓存使用的哈希器，已缓存的记忆重新建立索引
+    pub fn with_hasher(mut self, hasher: Arc<dyn TextHasher>) -> Self {
+        self.keyword_index = Arc::new(KeywordIndex::new(hasher.clone()));
+        self.query_cache = QueryCache::new(hasher, QueryCache::DEFAULT_CAPACITY);
+        for entry in self.memory_cache.entries() {
+            self.keyword_index.insert(entry.id, &entry.keywords);
+        }
+        self
+    }
+
+    /// 每条记忆写入前依次调用注册表中的后处理插件
+    pub fn with_plugins(mut self, plugins: Arc<PluginRegistry>) -> Self {
+        self.plugins = plugins;
+        self
+    }
+
+    /// 记忆条目在payload中的编码 - 非JSON编码时payload只保留过滤和迁移所需的字段，
+    /// 完整条目编码后以Base64存放；读取时按payload内容识别，切换编码无需迁移
+    pub fn with_payload_codec(mut self, codec: CodecKind) -> Self {
+        self.payload_codec = codec;
+        self
+    }
+
+    /// 启用写后缓冲 - 新记忆进入缓存后立即返回，向量和payload按`config`由后台任务批量写入存储
+    ///
+    /// 带情感向量的记忆不经缓冲，写入前先刷新缓冲区。需要在tokio运行时中调用；
+    /// 退出前应调用`shutdown`写入剩余记录
+    pub fn with_write_behind(mut self, config: WriteBehindConfig) -> Self {
+        self.write_behind = Some(Arc::new(WriteBehindVectorStore::manual(self.vector_store.clone(), config)));
+        self.schedule_flush();
+        self
+    }
+
+    /// 后台任务的并发上限，默认为`supervisor::DEFAULT_MAX_CONCURRENT`
+    ///
+    /// 替换任务监督器，已启动的周期任务中只保留写后缓冲的定时刷新
+    pub fn with_max_background_tasks(mut self, max_concurrent: usize) -> Self {
+        self.tasks = TaskSupervisor::new(max_concurrent);
+        self.schedule_flush();
+        self
+    }
+
+    /// 后台任务监督器 - 查看任务健康状况，或在相同的并发上限下安排自定义任务
+    pub fn tasks(&self) -> &TaskSupervisor {
+        &self.tasks
+    }
+
+    /// 按写后缓冲的间隔定时刷新
+    fn schedule_flush(&self) {
+        let Some(ref write_behind) = self.write_behind else {
+            return;
+        };
+        let interval = write_behind.flush_interval();
+        let write_behind = Arc::downgrade(write_behind);
+        self.tasks.schedule(TaskKind::Flush, interval, move || {
+            let write_behind = write_behind.clone();
+            async move {
+                match write_behind.upgrade() {
+                    Some(write_behind) => write_behind.flush().await.map(drop).map_err(Self::store_error),
+                    None => Ok(()),
+                }
+            }
+        });
+    }
+
+    /// 写后缓冲中等待写入存储的记忆条数，未启用写后缓冲时为0
+    pub async fn pending_writes(&self) -> usize {
+        match self.write_behind {
+            Some(ref write_behind) => write_behind.pending_writes().await,
+            None => 0,
+        }
+    }
+
+    /// 立即把写后缓冲中的记录写入存储，之后的存储操作才能看到这些记忆
+    pub async fn flush_writes(&self) -> Result<()> {
+        if let Some(ref write_behind) = self.write_behind {
+            write_behind.flush().await.map_err(Self::store_error)?;
+        }
+        Ok(())
+    }
+
+    /// 停止后台任务并写入剩余记录，返回写入的条数
+    pub async fn shutdown(&self) -> Result<usize> {
+        self.tasks.shutdown().await;
+        match self.write_behind {
+            Some(ref write_behind) => write_behind.shutdown().await.map_err(Self::store_error),
+            None => Ok(0),
+        }
+    }
+
+    /// 使用推理服务评估新记忆的重要性，评分与调用方给出的重要性按
+    /// `MemoryConfig::inference_importance_weight`混合
+    pub fn with_importance_inference(mut self, inference: Arc<dyn InferenceClient>) -> Self {
+        self.importance_inference = Some(inference);
+        self
+    }
+
+    /// 重排阶段使用推理服务的交叉编码器，权重由`RerankConfig::cross_encoder_weight`指定
+    pub fn with_rerank_inference(mut self, inference: Arc<dyn InferenceClient>) -> Self {
+        self.rerank_inference = Some(inference);
+        self
+    }
+
     /// 添加新记忆 - 使用异步并发处理
+    #[tracing::instrument(skip_all, fields(user_id = %self.user_id, memory_type = ?memory_type, memory_id))]
     pub async fn add_memory(
         &self,
         memory_type: MemoryType,
@@ -36,338 +218,1020 @@ impl MemorySystem {
         importance: f32,
         emotional_context: Option<EmotionalState>,
     ) -> Result<Uuid> {
-        let mut entry = MemoryEntry::new(memory_type.clone(), content.clone(), keywords, importance);
+        let entry = self.prepare_entry(
+            memory_type,
+            content,
+            keywords,
+            Some(importance),
+            emotional_context,
+            self.importance_inference.as_deref(),
+        ).await;
+        self.store_entry(entry).await
+    }
+
+    /// 使用调用方计算好的嵌入添加记忆，嵌入维度必须与内置嵌入一致
+    #[tracing::instrument(skip_all, fields(user_id = %self.user_id, memory_type = ?memory_type, memory_id))]
+    pub async fn add_memory_with_embedding(
+        &self,
+        memory_type: MemoryType,
+        content: String,
+        keywords: Vec<String>,
+        importance: f32,
+        emotional_context: Option<EmotionalState>,
+        embedding: Vec<f32>,
+    ) -> Result<Uuid> {
+        self.check_embedding(&embedding)?;
+
+        let mut entry = MemoryEntry::new(memory_type, content, keywords, importance);
         entry.emotional_context = emotional_context;
+        entry.importance = self.score_importance(&entry, Some(importance), self.importance_inference.as_deref()).await;
+        entry.embedding = Some(embedding);
+        self.store_entry(entry).await
+    }
 
-        // 并发处理向量嵌入和重要性评估
-        let (embedding, adjusted_importance) = tokio::join!(
-            self.generate_embedding(&content),
-            self.calculate_contextual_importance(&entry)
-        );
+    /// 添加图片记忆 - `content`（通常是图片说明）生成内容向量，`image_embedding`存入图片向量空间
+    ///
+    /// 需要向量存储启用匹配维度的图片向量，图片向量写入失败时撤销整条记忆
+    #[tracing::instrument(skip_all, fields(user_id = %self.user_id, memory_type = ?memory_type, memory_id))]
+    pub async fn add_image_memory(
+        &self,
+        memory_type: MemoryType,
+        content: String,
+        keywords: Vec<String>,
+        importance: f32,
+        image: ImageAttachment,
+        image_embedding: Vec<f32>,
+    ) -> Result<Uuid> {
+        match self.vector_store.image_vector_size() {
+            None => {
+                return Err(MemoryError::VectorStoreError { message: "向量存储未启用图片向量".to_string() });
+            }
+            Some(expected) if expected != image_embedding.len() => {
+                return Err(MemoryError::DimensionMismatch { expected, actual: image_embedding.len() });
+            }
+            Some(_) => {}
+        }
 
-        entry.embedding = embedding.ok();
-        entry.importance = adjusted_importance;
+        let emotional_context = Some(self.get_emotional_state().await);
+        let mut entry = self.prepare_entry(
+            memory_type,
+            content,
+            keywords,
+            Some(importance),
+            emotional_context,
+            self.importance_inference.as_deref(),
+        ).await;
+        if entry.embedding.is_none() {
+            return Err(MemoryError::VectorStoreError { message: "图片记忆缺少内容向量".to_string() });
+        }
+        entry.image = Some(image);
+
+        let id = self.store_entry(entry).await?;
+        self.flush_writes().await?;
+        if let Err(e) = self.vector_store.attach_image_vector(id, image_embedding).await {
+            self.delete_memory(id).await?;
+            return Err(Self::store_error(e));
+        }
+        Ok(id)
+    }
+
+    /// 写入向量数据库和内存缓存
+    async fn store_entry(&self, mut entry: MemoryEntry) -> Result<Uuid> {
+        self.plugins.post_process(&mut entry);
+        let memory_type = entry.memory_type.clone();
 
-        // 存储到向量数据库
+        // 存储到向量数据库，存储支持时同时写入情感向量；启用写后缓冲时经缓冲写入
         if let Some(ref embedding) = entry.embedding {
-            self.vector_store.store_vector(
-                entry.id,
-                embedding.clone(),
-                serde_json::to_string(&entry).unwrap(),
-            ).await.map_err(|e| MemoryError::VectorStoreError { 
-                message: e.to_string() 
-            })?;
+            let metadata = self.entry_payload(&entry)?;
+            let store = self.write_store();
+            match self.emotion_embedding(&entry) {
+                Some(emotion_embedding) => store.store_multi_vector(
+                    entry.id,
+                    embedding.clone(),
+                    emotion_embedding,
+                    metadata,
+                ).await,
+                None => store.store_vector(entry.id, embedding.clone(), metadata).await,
+            }.map_err(Self::store_error)?;
         }
 
         let memory_id = entry.id;
+        // 记录到调用方的span中
+        tracing::Span::current().record("memory_id", tracing::field::display(memory_id));
         
-        // 存储到内存缓存
-        self.memory_cache.insert(memory_id, entry);
+        // 存储到内存缓存并索引关键词
+        self.keyword_index.insert(memory_id, &entry.keywords);
+        self.memory_cache.insert(entry);
 
         // 异步清理过期记忆
         if matches!(memory_type, MemoryType::ShortTerm) {
-            tokio::spawn({
-                let cache = self.memory_cache.clone();
-                let limit = self.config.short_term_limit;
-                async move {
-                    Self::cleanup_short_term_memories(&cache, limit).await;
-                }
-            });
+            self.spawn_short_term_cleanup();
         }
 
         Ok(memory_id)
     }
 
-    /// 检索相关记忆 - 使用向量相似度搜索
-    pub async fn retrieve_memories(
+    /// 批量添加记忆 - 向量数据库只写入一次
+    #[tracing::instrument(skip_all, fields(user_id = %self.user_id, count = memories.len()))]
+    pub async fn add_memories(
         &self,
-        query: &str,
-        memory_types: Option<Vec<MemoryType>>,
-        limit: Option<usize>,
-    ) -> Result<Vec<MemoryEntry>> {
-        let limit = limit.unwrap_or(10);
-        
-        // 生成查询向量
-        let query_embedding = self.generate_embedding(query).await?;
-        
-        // 向量搜索
-        let similar_ids = self.vector_store.search_similar(
-            query_embedding,
-            limit * 2, // 获取更多候选，后续过滤
-            self.config.similarity_threshold,
-        ).await.map_err(|e| MemoryError::VectorStoreError { 
-            message: e.to_string() 
-        })?;
+        memories: Vec<(MemoryType, String, Vec<String>, f32, Option<EmotionalState>)>,
+    ) -> Result<Vec<Uuid>> {
+        let mut entries = Vec::with_capacity(memories.len());
+        for (memory_type, content, keywords, importance, emotional_context) in memories {
+            let mut entry = self.prepare_entry(
+                memory_type,
+                content,
+                keywords,
+                Some(importance),
+                emotional_context,
+                self.importance_inference.as_deref(),
+            ).await;
+            self.plugins.post_process(&mut entry);
+            entries.push(entry);
+        }
 
-        // 从缓存中获取记忆条目并过滤
-        let mut memories = Vec::new();
-        for id in similar_ids {
-            if let Some(mut entry) = self.memory_cache.get_mut(&id) {
-                // 检查类型过滤
-                if let Some(ref types) = memory_types {
-                    if !types.contains(&entry.memory_type) {
-                        continue;
-                    }
-                }
-                
-                // 更新访问统计
-                entry.mark_accessed();
-                memories.push(entry.clone());
-                
-                if memories.len() >= limit {
-                    break;
-                }
-            }
+        let mut records = Vec::with_capacity(entries.len());
+        for entry in &entries {
+            let Some(ref embedding) = entry.embedding else {
+                continue;
+            };
+            records.push((entry.id, embedding.clone(), self.emotion_embedding(entry), self.entry_payload(entry)?));
         }
 
-        // 按重要性和时间排序
-        memories.sort_by(|a, b| {
-            let importance_cmp = b.importance.partial_cmp(&a.importance)
-                .unwrap_or(std::cmp::Ordering::Equal);
-            if importance_cmp == std::cmp::Ordering::Equal {
-                b.last_accessed.cmp(&a.last_accessed)
-            } else {
-                importance_cmp
+        // 带情感向量的点和其余的点各批量写入一次
+        let store = self.write_store();
+        let mut points = Vec::with_capacity(records.len());
+        let mut emotion_points = Vec::new();
+        for (id, embedding, emotion, payload) in records {
+            match emotion {
+                Some(emotion_embedding) => emotion_points.push((id, embedding, emotion_embedding, payload)),
+                None => points.push((id, embedding, payload)),
             }
-        });
+        }
+        if !emotion_points.is_empty() {
+            store.store_multi_vectors(emotion_points).await
+                .map_err(Self::store_error)?;
+        }
+        store.store_vectors(points).await
+            .map_err(Self::store_error)?;
 
-        Ok(memories)
-    }
+        let has_short_term = entries.iter()
+            .any(|entry| matches!(entry.memory_type, MemoryType::ShortTerm));
+        let ids = entries.iter().map(|entry| entry.id).collect();
 
-    /// 更新情感状态
-    pub async fn update_emotional_state(&self, new_state: EmotionalState) {
-        let mut current = self.current_emotion.write().await;
-        *current = new_state;
+        for entry in entries {
+            self.keyword_index.insert(entry.id, &entry.keywords);
+            self.memory_cache.insert(entry);
+        }
+
+        if has_short_term {
+            self.spawn_short_term_cleanup();
+        }
+
+        Ok(ids)
     }
 
-    /// 获取当前情感状态
-    pub async fn get_emotional_state(&self) -> EmotionalState {
-        self.current_emotion.read().await.clone()
+    /// 添加记忆并由推理客户端分析关键词、情感和重要性
+    ///
+    /// 重要性评估失败时使用本地启发式评分
+    #[tracing::instrument(skip_all, fields(user_id = %self.user_id, memory_type = ?memory_type, memory_id))]
+    pub async fn add_memory_with_inference(
+        &self,
+        inference: &dyn InferenceClient,
+        memory_type: MemoryType,
+        content: String,
+    ) -> Result<Uuid> {
+        let (keywords, emotion) = tokio::try_join!(
+            inference.extract_keywords(&content),
+            inference.analyze_emotion(&content),
+        )?;
+
+        let entry = self.prepare_entry(memory_type, content, keywords, None, Some(emotion), Some(inference)).await;
+        self.store_entry(entry).await
     }
 
-    /// 获取记忆统计信息
-    pub async fn get_memory_stats(&self) -> HashMap<String, u64> {
-        let mut stats = HashMap::new();
-        
-        for entry in self.memory_cache.iter() {
-            let type_name = format!("{:?}", entry.memory_type);
-            *stats.entry(type_name).or_insert(0) += 1;
+    /// 更新记忆内容 - 重新生成嵌入并原地更新向量和payload
+    #[tracing::instrument(skip_all, fields(user_id = %self.user_id, memory_id = %id))]
+    pub async fn update_memory(
+        &self,
+        id: Uuid,
+        content: String,
+        keywords: Option<Vec<String>>,
+    ) -> Result<()> {
+        let mut entry = self.memory_cache.get(&id)
+            .map(|entry| MemoryEntry::clone(&entry))
+            .ok_or(MemoryError::NotFound { id })?;
+        self.flush_writes().await?;
+
+        let previous_keywords = std::mem::take(&mut entry.keywords);
+        entry.content = content;
+        entry.keywords = keywords.unwrap_or_else(|| previous_keywords.clone());
+        entry.embedding = self.generate_embedding(&entry.content).await.ok();
+
+        if let Some(ref embedding) = entry.embedding {
+            self.vector_store.update_vector(id, embedding.clone()).await
+                .map_err(Self::store_error)?;
         }
-        
-        stats.insert("total".to_string(), self.memory_cache.len() as u64);
-        stats
+
+        let payload: serde_json::Value = serde_json::from_str(&self.entry_payload(&entry)?)?;
+        self.vector_store.update_payload(id, payload).await
+            .map_err(Self::store_error)?;
+
+        self.keyword_index.remove(id, &previous_keywords);
+        self.keyword_index.insert(id, &entry.keywords);
+        self.memory_cache.insert(entry);
+        Ok(())
     }
 
-    /// 生成向量嵌入 - 优化版本，增加CPU密集型计算
-    async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>> {
-        use rayon::prelude::*;
-        
-        // 复杂的文本特征提取
-        let chars: Vec<char> = text.chars().collect();
-        let embedding_size = 768;
-        
-        // 并行计算字符级别的特征 - 优化版本
-        let char_features: Vec<f32> = chars.par_iter()
-            .enumerate()
-            .map(|(i, &ch)| {
-                let mut feature = 0.0f32;
-                
-                // 适度的字符特征计算
-                let char_code = ch as u32 as f32;
-                feature += char_code * (i as f32).sin() * 0.001;
-                feature += (char_code * (i as f32).cos()).sqrt() * 0.1;
-                
-                // 基于位置的权重
-                let position_weight = 1.0 / (i + 1) as f32;
-                feature *= position_weight;
-                
-                feature
+    /// 调整记忆重要性 - 只更新payload中的importance字段
+    pub async fn adjust_importance(&self, id: Uuid, delta: f32) -> Result<f32> {
+        let importance = self.memory_cache
+            .update(&id, |entry| {
+                entry.update_importance(delta);
+                entry.importance
             })
-            .collect();
-        
-        // 生成完整的嵌入向量
-        let mut embedding = vec![0.0f32; embedding_size];
-        
-        // 并行填充嵌入向量
-        embedding.par_iter_mut()
-            .enumerate()
-            .for_each(|(i, val)| {
-                let mut sum = 0.0f32;
-                
-                // 适度的向量生成算法
-                for (j, &char_feature) in char_features.iter().enumerate() {
-                    if j < 100 { // 限制计算量
-                        let weight = ((i + j) as f32).sin() * char_feature;
-                        sum += weight * (j as f32).sqrt() * 0.1;
-                    }
-                }
-                
-                // 添加随机性
-                let random_factor = ((i * 7 + 13) % 100) as f32 * 0.01;
-                *val = sum + random_factor;
-            });
-        
-        // 向量归一化
-        let norm: f32 = embedding.par_iter().map(|x| x * x).sum::<f32>().sqrt();
-        if norm > 0.0 {
-            embedding.par_iter_mut().for_each(|x| *x /= norm);
+            .ok_or(MemoryError::NotFound { id })?;
+
+        self.sync_payload(id, serde_json::json!({ "importance": importance })).await?;
+
+        Ok(importance)
+    }
+
+    /// 新记忆写入的存储，启用写后缓冲时为缓冲区
+    fn write_store(&self) -> &dyn crate::vector_store::VectorStore<Error = anyhow::Error> {
+        match self.write_behind {
+            Some(ref write_behind) => write_behind.as_ref(),
+            None => self.vector_store.as_ref(),
         }
-        
-        Ok(embedding)
     }
 
-    /// 计算上下文重要性 - 优化版本，增加CPU密集型计算
-    async fn calculate_contextual_importance(&self, entry: &MemoryEntry) -> f32 {
-        use rayon::prelude::*;
-        
-        let mut importance = entry.importance;
-        
-        // 基于情感上下文调整重要性
-        if let Some(ref emotion) = entry.emotional_context {
-            // 复杂的情绪强度计算
-            let emotional_factors = vec![
-                emotion.happiness,
-                emotion.affection,
-                emotion.trust,
-                emotion.dependency,
-            ];
-            
-            // 并行计算情绪统计
-            let emotional_intensity = emotional_factors.par_iter()
-                .map(|&factor| {
-                    let mut intensity = factor;
-                    
-                    // 复杂的情绪处理算法
-                    for i in 0..100 {
-                        intensity += (factor * i as f32).sin() * (i as f32).sqrt() * 0.001;
-                    }
-                    
-                    intensity
-                })
-                .sum::<f32>() / emotional_factors.len() as f32;
-            
-            importance = (importance + emotional_intensity * 0.3).clamp(0.0, 1.0);
+    /// 转换向量存储错误，保留维度不匹配信息
+    fn store_error(error: anyhow::Error) -> MemoryError {
+        match error.downcast_ref::<DimensionMismatch>() {
+            Some(&DimensionMismatch { expected, actual }) => {
+                MemoryError::DimensionMismatch { expected, actual }
+            }
+            None => MemoryError::VectorStoreError { message: error.to_string() },
         }
-        
-        // 基于关键词的复杂重要性计算
-        let keyword_importance: f32 = entry.keywords.par_iter()
-            .map(|keyword| {
-                let mut score = 0.0f32;
-                
-                // 复杂的词汇分析
-                for (i, ch) in keyword.chars().enumerate() {
-                    let char_value = ch as u32 as f32;
-                    score += char_value * (i as f32).sin() * 0.001;
-                    score += (char_value * (i as f32).cos()).sqrt() * 0.0001;
+    }
+
+    /// 设置记忆过期时间 - None表示永不过期
+    pub async fn set_expiry(&self, id: Uuid, expires_at: Option<chrono::DateTime<chrono::Utc>>) -> Result<()> {
+        self.memory_cache.update(&id, |entry| entry.expires_at = expires_at)
+            .ok_or(MemoryError::NotFound { id })?;
+
+        let patch = serde_json::json!({
+            "expires_at": expires_at,
+            PAYLOAD_EXPIRES_AT_TS: expires_at.map(|t| t.timestamp()),
+        });
+        self.sync_payload(id, patch).await
+    }
+
+    /// 把缓存中已修改的条目同步到payload - JSON编码只需合并`patch`，编码存放的条目需整体重写
+    async fn sync_payload(&self, id: Uuid, patch: serde_json::Value) -> Result<()> {
+        self.flush_writes().await?;
+        let patch = if self.payload_codec == CodecKind::Json {
+            patch
+        } else {
+            let entry = self.memory_cache.get(&id)
+                .ok_or(MemoryError::NotFound { id })?;
+            serde_json::from_str(&self.entry_payload(&entry)?)?
+        };
+        self.vector_store.update_payload(id, patch).await
+            .map_err(Self::store_error)
+    }
+
+    /// 清除已过期的记忆 - 由向量存储按payload批量删除，返回清除的条数
+    pub async fn purge_expired_memories(&self) -> Result<usize> {
+        self.flush_writes().await?;
+        let purged = self.vector_store.purge_expired(Some(SearchFilter::for_user(self.user_id.clone()))).await
+            .map_err(Self::store_error)?;
+
+        for id in &purged {
+            if let Some(entry) = self.memory_cache.remove(id) {
+                self.keyword_index.remove(entry.id, &entry.keywords);
+            }
+        }
+        // 没有嵌入的记忆只存在于缓存中
+        for entry in self.memory_cache.retain(|entry| !entry.is_expired()) {
+            self.keyword_index.remove(entry.id, &entry.keywords);
+        }
+
+        Ok(purged.len())
+    }
+
+    /// 删除记忆 - 同时从向量存储、缓存和关键词索引中移除
+    #[tracing::instrument(skip_all, fields(user_id = %self.user_id, memory_id = %id))]
+    pub async fn delete_memory(&self, id: Uuid) -> Result<()> {
+        self.flush_writes().await?;
+        let stored = self.vector_store.get_vector(id).await
+            .map_err(Self::store_error)?
+            .is_some();
+        if stored {
+            self.vector_store.delete_vector(id).await
+                .map_err(Self::store_error)?;
+        }
+
+        // 没有嵌入的记忆只存在于缓存中
+        match self.memory_cache.remove(&id) {
+            Some(entry) => self.keyword_index.remove(id, &entry.keywords),
+            None if !stored => return Err(MemoryError::NotFound { id }),
+            None => {}
+        }
+        Ok(())
+    }
+
+    /// 列出当前用户的全部记忆并载入缓存，按创建时间排序
+    ///
+    /// 以向量存储为准，没有嵌入的记忆取自缓存
+    #[tracing::instrument(skip_all, fields(user_id = %self.user_id))]
+    pub async fn list_memories(&self, memory_types: Option<Vec<MemoryType>>) -> Result<Vec<Arc<MemoryEntry>>> {
+        self.flush_writes().await?;
+        let mut offset = None;
+        loop {
+            let page = self.vector_store.scroll(offset, LIST_PAGE_SIZE).await
+                .map_err(Self::store_error)?;
+            for point in page.points {
+                if self.memory_cache.contains(&point.id) {
+                    continue;
                 }
-                
-                // 词汇长度权重
-                score *= (keyword.len() as f32).sqrt();
-                
-                score
-            })
-            .sum();
-        
-        importance = (importance + keyword_importance * 0.1).clamp(0.0, 1.0);
-        
-        // 基于记忆类型调整
-        match entry.memory_type {
-            MemoryType::Emotional | MemoryType::Relationship => {
-                importance = (importance + 0.2).clamp(0.0, 1.0);
+                let Some(mut entry) = Self::payload_entry(point.payload)? else {
+                    continue;
+                };
+                entry.embedding = Some(point.embedding);
+                self.keyword_index.insert(entry.id, &entry.keywords);
+                self.memory_cache.insert(entry);
             }
-            MemoryType::ShortTerm => {
-                importance = (importance - 0.1).clamp(0.0, 1.0);
+            match page.next_offset {
+                Some(next) => offset = Some(next),
+                None => break,
             }
-            _ => {}
         }
-        
-        // 最终的重要性优化
-        let mut final_importance = importance;
-        for i in 0..50 {
-            final_importance += (importance * i as f32).sin() * 0.001;
+
+        Ok(self.memory_cache.by_created(memory_types.as_deref()))
+    }
+
+    /// 导入记忆并保留原有ID，已存在的记忆被覆盖；缺少嵌入的条目重新生成嵌入，生成失败时返回错误
+    #[tracing::instrument(skip_all, fields(user_id = %self.user_id, count = entries.len()))]
+    pub async fn import_memories(&self, entries: Vec<MemoryEntry>) -> Result<usize> {
+        let imported = entries.len();
+        for mut entry in entries {
+            match entry.embedding {
+                Some(ref embedding) => self.check_embedding(embedding)?,
+                None => entry.embedding = Some(self.generate_embedding(&entry.content).await?),
+            }
+            if let Some(previous) = self.memory_cache.get(&entry.id) {
+                self.keyword_index.remove(entry.id, &previous.keywords);
+            }
+            self.store_entry(entry).await?;
         }
-        
-        final_importance.clamp(0.0, 1.0)
+        Ok(imported)
     }
 
-    /// 清理短期记忆
-    async fn cleanup_short_term_memories(cache: &DashMap<Uuid, MemoryEntry>, limit: usize) {
-        let short_term_count = cache.iter()
-            .filter(|entry| matches!(entry.memory_type, MemoryType::ShortTerm))
-            .count();
-            
-        if short_term_count > limit {
-            let mut short_term_entries: Vec<_> = cache.iter()
-                .filter(|entry| matches!(entry.memory_type, MemoryType::ShortTerm))
-                .map(|entry| (entry.key().clone(), entry.last_accessed, entry.importance))
-                .collect();
-                
-            // 按访问时间和重要性排序，移除最老的和最不重要的
-            short_term_entries.sort_by(|a, b| {
-                let importance_cmp = a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Equal);
-                if importance_cmp == std::cmp::Ordering::Equal {
-                    a.1.cmp(&b.1)
-                } else {
-                    importance_cmp
+    /// 将重要性达到`long_term_threshold`的短期记忆转为长期记忆，返回转换的条数
+    ///
+    /// 只处理缓存中的记忆，需要时先用`list_memories`载入
+    #[tracing::instrument(skip_all, fields(user_id = %self.user_id))]
+    pub async fn consolidate_memories(&self) -> Result<usize> {
+        self.flush_writes().await?;
+        let threshold = self.config().long_term_threshold;
+        let promoted: Vec<Uuid> = self.memory_cache.importance_at_least(&MemoryType::ShortTerm, threshold)
+            .iter()
+            .map(|entry| entry.id)
+            .collect();
+
+        for &id in &promoted {
+            // 没有嵌入的记忆只存在于缓存中
+            let stored = self.memory_embedding(id).await?.is_some();
+            // 非JSON编码时sync_payload按缓存中的条目重新编码，先更新缓存
+            self.memory_cache.update(&id, |entry| entry.memory_type = MemoryType::LongTerm);
+            if stored {
+                if let Err(e) = self.sync_payload(id, serde_json::json!({ "memory_type": MemoryType::LongTerm })).await {
+                    self.memory_cache.update(&id, |entry| entry.memory_type = MemoryType::ShortTerm);
+                    return Err(e);
                 }
-            });
-            
-            let to_remove = short_term_count - limit;
-            for (id, _, _) in short_term_entries.iter().take(to_remove) {
-                cache.remove(id);
             }
         }
+        Ok(promoted.len())
     }
 
-    /// 启动后台清理任务
-    pub fn start_background_cleanup(&self) -> tokio::task::JoinHandle<()> {
-        let cache = self.memory_cache.clone();
-        let interval = self.config.cleanup_interval;
-        let limit = self.config.short_term_limit;
-        
-        tokio::spawn(async move {
-            let mut cleanup_interval = tokio::time::interval(
-                tokio::time::Duration::from_secs(interval)
-            );
-            
-            loop {
-                cleanup_interval.tick().await;
-                Self::cleanup_short_term_memories(&cache, limit).await;
+    /// 构建记忆条目 - 并发生成向量嵌入和重要性评估
+    ///
+    /// `importance`为None时完全采用推理服务的评分
+    async fn prepare_entry(
+        &self,
+        memory_type: MemoryType,
+        content: String,
+        keywords: Vec<String>,
+        importance: Option<f32>,
+        emotional_context: Option<EmotionalState>,
+        scorer: Option<&dyn InferenceClient>,
+    ) -> MemoryEntry {
+        let mut entry = MemoryEntry::new(memory_type, content, keywords, importance.unwrap_or(0.5));
+        entry.emotional_context = emotional_context;
+
+        // 并发处理向量嵌入和重要性评估
+        let (embedding, adjusted_importance) = tokio::join!(
+            self.generate_embedding(&entry.content),
+            self.score_importance(&entry, importance, scorer)
+        );
+
+        entry.embedding = embedding.ok();
+        entry.importance = adjusted_importance;
+        entry
+    }
+
+    /// 条目的情感向量 - 仅在存储启用了匹配维度的情感向量时返回
+    fn emotion_embedding(&self, entry: &MemoryEntry) -> Option<Vec<f32>> {
+        if self.vector_store.emotion_vector_size() != Some(EmotionalState::EMBEDDING_DIM) {
+            return None;
+        }
+        entry.emotional_context.as_ref().map(EmotionalState::to_embedding)
+    }
+
+    /// 从缓存获取命中的记忆条目并更新访问统计，缓存未命中时从payload恢复并建立关键词索引
+    fn hit_entry(&self, hit: SearchHit) -> Result<Option<Arc<MemoryEntry>>> {
+        if let Some(entry) = self.memory_cache.touch(&hit.id) {
+            return Ok(Some(entry));
+        }
+        let Some(mut entry) = Self::payload_entry(hit.payload)? else {
+            return Ok(None);
+        };
+        entry.mark_accessed();
+        let entry = Arc::new(entry);
+        if let Some(previous) = self.memory_cache.insert(entry.clone()) {
+            self.keyword_index.remove(previous.id, &previous.keywords);
+        }
+        self.keyword_index.insert(entry.id, &entry.keywords);
+        Ok(Some(entry))
+    }
+
+    /// 记忆的嵌入向量 - 检索返回的条目不带嵌入，需要时按ID读取
+    ///
+    /// 缓存中没有时从向量存储读取，记忆在缓存中时一并保存
+    pub async fn memory_embedding(&self, id: Uuid) -> Result<Option<Arc<Vec<f32>>>> {
+        if let Some(embedding) = self.memory_cache.embedding(&id) {
+            return Ok(Some(embedding));
+        }
+        let Some(point) = self.vector_store.get_vector(id).await.map_err(Self::store_error)? else {
+            return Ok(None);
+        };
+        self.memory_cache.update(&id, |entry| entry.embedding = Some(point.embedding.clone()));
+        Ok(Some(Arc::new(point.embedding)))
+    }
+
+    /// 从payload还原记忆条目，按是否有编码字段识别编码
+    ///
+    /// 不是记忆条目的JSON payload返回None；编码的条目无法解码（编码未启用、结构版本不一致）时返回错误
+    fn payload_entry(payload: serde_json::Value) -> Result<Option<MemoryEntry>> {
+        let Some(encoded) = payload.get(PAYLOAD_ENCODED_ENTRY).and_then(|value| value.as_str()) else {
+            return Ok(serde_json::from_value(payload).ok());
+        };
+        let name = payload.get(PAYLOAD_CODEC).and_then(|value| value.as_str()).unwrap_or_default();
+        let codec = CodecKind::from_name(name)
+            .ok_or_else(|| MemoryError::ConfigError(format!("记忆payload使用了未启用的编码: {}", name)))?;
+        let bytes = BASE64.decode(encoded)
+            .map_err(|e| MemoryError::VectorStoreError { message: format!("记忆payload编码损坏: {}", e) })?;
+        codec.decode(&bytes).map(Some).map_err(Self::store_error)
+    }
+
+    /// 构建向量存储payload - 附加用户ID和创建时间戳用于过滤下推
+    ///
+    /// 嵌入已保存在向量中，不再写入payload；非JSON编码时只保留内容、重要性和记忆类型供迁移和过滤使用，
+    /// 过期时间戳总是写入，合并更新时才能清除原有的过期时间
+    fn entry_payload(&self, entry: &MemoryEntry) -> Result<String> {
+        let entry = MemoryEntry { embedding: None, ..entry.clone() };
+        let mut payload = if self.payload_codec == CodecKind::Json {
+            serde_json::to_value(&entry)?
+        } else {
+            let encoded = self.payload_codec.encode(&entry).map_err(Self::store_error)?;
+            serde_json::json!({
+                "id": entry.id,
+                "content": entry.content,
+                "importance": entry.importance,
+                PAYLOAD_MEMORY_TYPE: entry.memory_type,
+                PAYLOAD_EXPIRES_AT_TS: entry.expires_at.map(|t| t.timestamp()),
+                PAYLOAD_CODEC: self.payload_codec.name(),
+                PAYLOAD_ENCODED_ENTRY: BASE64.encode(encoded),
+            })
+        };
+        if let serde_json::Value::Object(ref mut map) = payload {
+            map.insert(PAYLOAD_USER_ID.to_string(), self.user_id.clone().into());
+            map.insert(PAYLOAD_CREATED_AT_TS.to_string(), entry.created_at.timestamp().into());
+            if let Some(expires_at) = entry.expires_at {
+                map.insert(PAYLOAD_EXPIRES_AT_TS.to_string(), expires_at.timestamp().into());
             }
-        })
+        }
+        Ok(serde_json::to_string(&payload)?)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::vector_store::MockVectorStore;
+    /// 后台清理超出上限的短期记忆 - 已有清理尚未开始时不再安排，连续写入只产生一个任务
+    fn spawn_short_term_cleanup(&self) {
+        self.tasks.submit(TaskKind::Cleanup, self.short_term_cleanup());
+    }
 
-    #[tokio::test]
-    async fn test_memory_system_creation() {
-        let vector_store = Arc::new(MockVectorStore::new());
-        let memory_system = MemorySystem::new(
-            "test_user".to_string(),
-            vector_store,
-            None,
-        ).await.unwrap();
-        
-        assert_eq!(memory_system.user_id, "test_user");
+    /// 按当前配置的上限清理短期记忆
+    fn short_term_cleanup(&self) -> impl std::future::Future<Output = Result<()>> + Send + 'static {
+        let cache = self.memory_cache.clone();
+        let keyword_index = self.keyword_index.clone();
+        let config = self.config.clone();
+        async move {
+            let limit = config.read().unwrap_or_else(|e| e.into_inner()).short_term_limit;
+            Self::cleanup_short_term_memories(&cache, &keyword_index, limit).await;
+            Ok(())
+        }
     }
 
-    #[tokio::test]
-    async fn test_add_and_retrieve_memory() {
-        let vector_store = Arc::new(MockVectorStore::new());
-        let memory_system = MemorySystem::new(
-            "test_user".to_string(),
-            vector_store,
-            None,
-        ).await.unwrap();
-        
-        let memory_id = memory_system.add_memory(
-            MemoryType::LongTerm,
-            "用户喜欢猫咪".to_string(),
-            vec!["猫咪".to_string(), "喜欢".to_string()],
+    /// 检索相关记忆 - 使用向量相似度搜索
+    #[tracing::instrument(skip_all, fields(user_id = %self.user_id, limit = ?limit, hits))]
+    pub async fn retrieve_memories(
+        &self,
+        query: &str,
+        memory_types: Option<Vec<MemoryType>>,
+        limit: Option<usize>,
+    ) -> Result<Vec<Arc<MemoryEntry>>> {
+        // 生成查询向量
+        let query_embedding = self.query_embedding(query).await?;
+        self.search_memories(Some(query), query_embedding, memory_types, limit).await
+    }
+
+    /// 使用调用方计算好的查询向量检索相关记忆
+    #[tracing::instrument(skip_all, fields(user_id = %self.user_id, limit = ?limit, hits))]
+    pub async fn retrieve_by_embedding(
+        &self,
+        query_embedding: Vec<f32>,
+        memory_types: Option<Vec<MemoryType>>,
+        limit: Option<usize>,
+    ) -> Result<Vec<Arc<MemoryEntry>>> {
+        self.check_embedding(&query_embedding)?;
+        self.search_memories(None, query_embedding, memory_types, limit).await
+    }
+
+    /// 向量相似度搜索并按相似度、重要性和访问时间排序，启用重排时改为两阶段检索
+    async fn search_memories(
+        &self,
+        query: Option<&str>,
+        query_embedding: Vec<f32>,
+        memory_types: Option<Vec<MemoryType>>,
+        limit: Option<usize>,
+    ) -> Result<Vec<Arc<MemoryEntry>>> {
+        let limit = limit.unwrap_or(10);
+        self.flush_writes().await?;
+
+        let config = self.config();
+        if config.rerank.enabled {
+            return self.rerank_memories(query, query_embedding, memory_types, limit, &config).await;
+        }
+
+        // 向量搜索 - 用户和类型过滤下推到向量存储
+        let mut filter = SearchFilter::for_user(self.user_id.clone());
+        if let Some(ref types) = memory_types {
+            filter = filter.with_memory_types(types.clone());
+        }
+
+        let hits = self.vector_store.search_similar(
+            query_embedding,
+            limit * 2, // 获取更多候选，后续过滤
+            self.config().similarity_threshold,
+            Some(filter),
+        ).await.map_err(Self::store_error)?;
+
+        // 从缓存中获取记忆条目，缓存未命中时从payload恢复
+        let mut scored = Vec::new();
+        for hit in hits {
+            let score = hit.score;
+            let Some(entry) = self.hit_entry(hit)? else {
+                continue;
+            };
+
+            // 检查类型过滤
+            if let Some(ref types) = memory_types {
+                if !types.contains(&entry.memory_type) {
+                    continue;
+                }
+            }
+
+            scored.push((entry, score));
+
+            if scored.len() >= limit {
+                break;
+            }
+        }
+
+        // 按相似度排序，相似度相同时按重要性和时间排序
+        let metric = self.vector_store.distance_metric();
+        scored.sort_by(|(a, score_a), (b, score_b)| {
+            metric.compare(*score_a, *score_b)
+                .then_with(|| b.importance.partial_cmp(&a.importance)
+                    .unwrap_or(std::cmp::Ordering::Equal))
+                .then_with(|| b.last_accessed.cmp(&a.last_accessed))
+        });
+
+        let memories: Vec<Arc<MemoryEntry>> = scored.into_iter().map(|(entry, _)| entry).collect();
+        tracing::Span::current().record("hits", memories.len());
+
+        Ok(memories)
+    }
+
+    /// 两阶段检索 - 向量搜索和关键词索引取出候选，按精确相似度、新近程度、重要性和可选的交叉编码器重排
+    async fn rerank_memories(
+        &self,
+        query: Option<&str>,
+        query_embedding: Vec<f32>,
+        memory_types: Option<Vec<MemoryType>>,
+        limit: usize,
+        config: &MemoryConfig,
+    ) -> Result<Vec<Arc<MemoryEntry>>> {
+        let rerank = &config.rerank;
+        let fetch = limit.saturating_mul(rerank.candidate_multiplier).max(limit);
+        let metric = self.vector_store.distance_metric();
+        let wanted = |entry: &MemoryEntry| memory_types.as_ref().is_none_or(|types| types.contains(&entry.memory_type));
+
+        let mut filter = SearchFilter::for_user(self.user_id.clone());
+        if let Some(ref types) = memory_types {
+            filter = filter.with_memory_types(types.clone());
+        }
+        let hits = self.vector_store.search_similar(
+            query_embedding.clone(),
+            fetch,
+            config.similarity_threshold,
+            Some(filter),
+        ).await.map_err(Self::store_error)?;
+
+        // (条目, 第一阶段分数, 是否来自关键词索引)
+        let mut candidates: Vec<(Arc<MemoryEntry>, f32, bool)> = Vec::with_capacity(fetch);
+        let mut seen = HashSet::new();
+        for hit in hits {
+            let score = hit.score;
+            if let Some(entry) = self.hit_entry(hit)?.filter(|entry| wanted(entry)) {
+                seen.insert(entry.id);
+                candidates.push((entry, score, false));
+            }
+        }
+
+        // 关键词命中的缓存条目即使未达到相似度阈值也参与重排
+        if let Some(query) = query {
+            for (id, _) in self.keyword_index.candidates(&local_keywords(query)).into_iter().take(fetch) {
+                if seen.contains(&id) {
+                    continue;
+                }
+                let Some(entry) = self.memory_cache.get(&id).filter(|entry| wanted(entry)) else {
+                    continue;
+                };
+                let Some(score) = self.memory_cache.embedding(&id).map(|embedding| metric.score(&query_embedding, &embedding)) else {
+                    continue;
+                };
+                seen.insert(id);
+                candidates.push((entry, score, true));
+            }
+        }
+
+        let cross_encoder = match (query, &self.rerank_inference) {
+            (Some(query), Some(inference)) if rerank.cross_encoder_weight > 0.0 && !candidates.is_empty() => {
+                let documents = candidates.iter().map(|(entry, _, _)| entry.content.clone()).collect();
+                match inference.rerank(query, documents).await {
+                    Ok(scores) if scores.len() == candidates.len() => Some(scores),
+                    Ok(scores) => {
+                        tracing::warn!("重排分数数量不匹配: {} != {}", scores.len(), candidates.len());
+                        None
+                    }
+                    Err(e) => {
+                        tracing::warn!("交叉编码器重排失败，只按本地分数排序: {}", e);
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+
+        let now = chrono::Utc::now();
+        let scored = candidates.into_iter()
+            .enumerate()
+            .map(|(i, (entry, first_stage, from_keywords))| {
+                // 第一阶段可能是近似或量化后的分数，有完整嵌入时精确重算
+                let exact = self.memory_cache.embedding(&entry.id)
+                    .map_or(first_stage, |embedding| metric.score(&query_embedding, &embedding));
+                let similarity = super::rerank::unit_similarity(metric, exact);
+                let relevance = cross_encoder.as_ref().map(|scores| scores[i]);
+                let score = super::rerank::score(rerank, &entry, similarity, relevance, now);
+                (entry, score, from_keywords)
+            });
+
+        let top = top_k_by(scored, limit, |(a, score_a, _), (b, score_b, _)| {
+            score_b.partial_cmp(score_a)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+
+        let memories: Vec<Arc<MemoryEntry>> = top.into_iter()
+            .filter_map(|(entry, _, from_keywords)| {
+                if !from_keywords {
+                    return Some(entry);
+                }
+                // 向量命中已在hit_entry中记录访问；先释放条目，缓存可以原地修改
+                let id = entry.id;
+                drop(entry);
+                self.memory_cache.touch(&id)
+            })
+            .collect();
+        tracing::Span::current().record("hits", memories.len());
+
+        Ok(memories)
+    }
+
+    /// 在字符预算内检索相关记忆 - 流式消费搜索结果，预算用尽后不再拉取后续命中
+    pub async fn retrieve_within_budget(
+        &self,
+        query: &str,
+        char_budget: usize,
+        limit: Option<usize>,
+    ) -> Result<Vec<Arc<MemoryEntry>>> {
+        let query_embedding = self.query_embedding(query).await?;
+        self.flush_writes().await?;
+        let mut hits = self.vector_store.search_stream(
+            query_embedding,
+            limit.unwrap_or(50),
+            self.config().similarity_threshold,
+            Some(SearchFilter::for_user(self.user_id.clone())),
+        );
+
+        let mut used = 0;
+        let mut memories = Vec::new();
+        while let Some(hit) = hits.next().await {
+            let hit = hit.map_err(Self::store_error)?;
+            let cost = hit.payload.get("content")
+                .and_then(|content| content.as_str())
+                .map_or(0, |content| content.chars().count());
+            if used + cost > char_budget {
+                break;
+            }
+            used += cost;
+
+            if let Some(entry) = self.hit_entry(hit)? {
+                memories.push(entry);
+            }
+        }
+
+        Ok(memories)
+    }
+
+    /// 按关键词检索缓存中的记忆 - 命中关键词多的在前，数量相同时按重要性排序
+    pub fn retrieve_by_keywords<S: AsRef<str>>(&self, keywords: &[S], limit: Option<usize>) -> Vec<Arc<MemoryEntry>> {
+        let wanted: Vec<String> = keywords.iter()
+            .map(|k| k.as_ref().trim().to_lowercase())
+            .filter(|k| !k.is_empty())
+            .collect();
+
+        // 按实际关键词校验候选，排除哈希冲突和已淘汰的条目
+        let matched = self.keyword_index.candidates(&wanted)
+            .into_iter()
+            .filter_map(|(id, _)| {
+                let entry = self.memory_cache.get(&id)?;
+                let hits = wanted.iter()
+                    .filter(|w| entry.keywords.iter().any(|k| k.trim().to_lowercase() == **w))
+                    .count();
+                (hits > 0).then_some((id, hits, entry.importance))
+            });
+
+        let top = top_k_by(matched, limit.unwrap_or(10), |(id_a, hits_a, importance_a), (id_b, hits_b, importance_b)| {
+            hits_b.cmp(hits_a)
+                .then_with(|| importance_b.partial_cmp(importance_a)
+                    .unwrap_or(std::cmp::Ordering::Equal))
+                .then_with(|| id_a.cmp(id_b))
+        });
+
+        top.into_iter()
+            .filter_map(|(id, _, _)| self.memory_cache.touch(&id))
+            .collect()
+    }
+
+    /// 按情感相似度检索记忆 - 需要向量存储启用情感向量
+    pub async fn retrieve_by_emotion(
+        &self,
+        emotion: &EmotionalState,
+        limit: Option<usize>,
+    ) -> Result<Vec<Arc<MemoryEntry>>> {
+        self.flush_writes().await?;
+        let hits = self.vector_store.search_space(
+            VectorSpace::Emotion,
+            emotion.to_embedding(),
+            limit.unwrap_or(10),
+            self.config().similarity_threshold,
+            Some(SearchFilter::for_user(self.user_id.clone())),
+        ).await.map_err(Self::store_error)?;
+
+        // 命中已按情感相似度排序
+        hits.into_iter().filter_map(|hit| self.hit_entry(hit).transpose()).collect()
+    }
+
+    /// 按图片相似度检索图片记忆 - 需要向量存储启用图片向量
+    pub async fn retrieve_by_image(
+        &self,
+        image_embedding: Vec<f32>,
+        limit: Option<usize>,
+    ) -> Result<Vec<Arc<MemoryEntry>>> {
+        self.flush_writes().await?;
+        let hits = self.vector_store.search_space(
+            VectorSpace::Image,
+            image_embedding,
+            limit.unwrap_or(10),
+            self.config().similarity_threshold,
+            Some(SearchFilter::for_user(self.user_id.clone())),
+        ).await.map_err(Self::store_error)?;
+
+        hits.into_iter().filter_map(|hit| self.hit_entry(hit).transpose()).collect()
+    }
+
+    /// 更新情感状态
+    pub async fn update_emotional_state(&self, new_state: EmotionalState) {
+        let mut current = self.current_emotion.write().await;
+        *current = new_state;
+    }
+
+    /// 获取当前情感状态
+    pub async fn get_emotional_state(&self) -> EmotionalState {
+        self.current_emotion.read().await.clone()
+    }
+
+    /// 统计向量存储中当前用户的记忆数量 - 以存储为准，不受缓存淘汰影响
+    pub async fn count_memories(&self, memory_types: Option<Vec<MemoryType>>) -> Result<u64> {
+        self.flush_writes().await?;
+        let mut filter = SearchFilter::for_user(self.user_id.clone());
+        if let Some(types) = memory_types {
+            filter = filter.with_memory_types(types);
+        }
+        self.vector_store.count(Some(filter)).await.map_err(Self::store_error)
+    }
+
+    /// 检查向量存储状态
+    pub async fn vector_store_health(&self) -> Result<HealthStatus> {
+        self.vector_store.health_check().await.map_err(Self::store_error)
+    }
+
+    /// 向量存储各操作的调用次数、错误次数和延迟分布
+    pub fn vector_store_metrics(&self) -> HashMap<String, OperationMetrics> {
+        self.vector_store.metrics()
+    }
+
+    /// 获取记忆统计信息
+    pub async fn get_memory_stats(&self) -> HashMap<String, u64> {
+        let mut stats = HashMap::new();
+        
+        for memory_type in MemoryType::ALL {
+            let count = self.memory_cache.len_of(&memory_type);
+            if count > 0 {
+                stats.insert(format!("{:?}", memory_type), count as u64);
+            }
+        }
+
+        stats.insert("total".to_string(), self.memory_cache.len() as u64);
+        stats
+    }
+
+    /// 校验外部传入的嵌入维度
+    fn check_embedding(&self, embedding: &[f32]) -> Result<()> {
+        let expected = self.embedder.dimension();
+        if embedding.len() != expected {
+            return Err(MemoryError::DimensionMismatch { expected, actual: embedding.len() });
+        }
+        Ok(())
+    }
+
+    /// 查询向量 - 相同查询复用缓存的嵌入
+    async fn query_embedding(&self, query: &str) -> Result<Vec<f32>> {
+        if let Some(embedding) = self.query_cache.get(query) {
+            return Ok(embedding.as_ref().clone());
+        }
+        let embedding = self.generate_embedding(query).await?;
+        self.query_cache.insert(query, Arc::new(embedding.clone()));
+        Ok(embedding)
+    }
+
+    /// 生成向量嵌入
+    async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>> {
+        self.embedder.embed(text).await
+    }
+
+    /// 本地启发式重要性 - 推理服务未配置或不可用时使用，新颖程度按关键词索引中已有的关键词计算
+    fn calculate_contextual_importance(&self, entry: &MemoryEntry) -> f32 {
+        let novelty = super::importance::novelty(&entry.keywords, |keyword| self.keyword_index.contains(keyword));
+        super::importance::score(&self.config().importance, entry, novelty)
+    }
+
+    /// 评估重要性 - 有推理客户端时与调用方给出的评分加权混合，失败时回退到本地启发式
+    async fn score_importance(
+        &self,
+        entry: &MemoryEntry,
+        caller_importance: Option<f32>,
+        scorer: Option<&dyn InferenceClient>,
+    ) -> f32 {
+        let Some(scorer) = scorer else {
+            return self.calculate_contextual_importance(entry);
+        };
+
+        match scorer.calculate_importance(&entry.content, entry.emotional_context.clone()).await {
+            Ok(score) => {
+                let score = score.clamp(0.0, 1.0);
+                match caller_importance {
+                    Some(importance) => {
+                        let weight = self.config().inference_importance_weight.clamp(0.0, 1.0);
+                        importance * (1.0 - weight) + score * weight
+                    }
+                    None => score,
+                }
+            }
+            Err(e) => {
+                tracing::warn!("推理服务重要性评估失败，使用本地评估: {}", e);
+                self.calculate_contextual_importance(entry)
+            }
+        }
+    }
+
+    /// 清理短期记忆 - 超出上限时移除最不重要的，重要性相同时先移除最久未访问的，关键词索引同步移除
+    async fn cleanup_short_term_memories(cache: &MemoryCache, keyword_index: &KeywordIndex, limit: usize) {
+        let short_term_count = cache.len_of(&MemoryType::ShortTerm);
+        if short_term_count > limit {
+            for entry in cache.least_important(&MemoryType::ShortTerm, short_term_count - limit) {
+                if let Some(entry) = cache.remove(&entry.id) {
+                    keyword_index.remove(entry.id, &entry.keywords);
+                }
+            }
+        }
+    }
+
+    /// 按`cleanup_interval`定期清理短期记忆并整理记忆，任务随记忆系统释放或`shutdown`停止
+    pub fn start_background_tasks(self: &Arc<Self>) {
+        let period = Duration::from_secs(self.config().cleanup_interval);
+        let system = Arc::downgrade(self);
+        self.tasks.schedule(TaskKind::Cleanup, period, {
+            let system = system.clone();
+            move || {
+                let cleanup = system.upgrade().map(|system| system.short_term_cleanup());
+                async move {
+                    match cleanup {
+                        Some(cleanup) => cleanup.await,
+                        None => Ok(()),
+                    }
+                }
+            }
+        });
+        self.tasks.schedule(TaskKind::Consolidation, period, move || {
+            let system = system.clone();
+            async move {
+                match system.upgrade() {
+                    Some(system) => system.consolidate_memories().await.map(drop),
+                    None => Ok(()),
+                }
+            }
+        });
+    }
+
+    /// 每隔`EMOTION_DECAY_PERIOD`按情感引擎的衰减配置衰减当前情感状态
+    pub fn start_emotion_decay(&self, engine: Arc<EmotionalEngine>) {
+        let emotion = self.current_emotion.clone();
+        self.tasks.schedule(TaskKind::Decay, EMOTION_DECAY_PERIOD, move || {
+            let (emotion, engine) = (emotion.clone(), engine.clone());
+            async move {
+                let mut state = emotion.write().await;
+                *state = engine.apply_time_decay(&state);
+                Ok(())
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::MockInferenceClient;
+    use crate::vector_store::{MockVectorStore, VectorStore};
+
+    #[tokio::test]
+    async fn test_memory_system_creation() {
+        let vector_store = Arc::new(MockVectorStore::new());
+        let memory_system = MemorySystem::new(
+            "test_user".to_string(),
+            vector_store,
+            None,
+        ).await.unwrap();
+        
+        assert_eq!(memory_system.user_id, "test_user");
+    }
+
+    #[tokio::test]
+    async fn test_add_and_retrieve_memory() {
+        let vector_store = Arc::new(MockVectorStore::new());
+        let memory_system = MemorySystem::new(
+            "test_user".to_string(),
+            vector_store,
+            None,
+        ).await.unwrap();
+        
+        let memory_id = memory_system.add_memory(
+            MemoryType::LongTerm,
+            "用户喜欢猫咪".to_string(),
+            vec!["猫咪".to_string(), "喜欢".to_string()],
             0.8,
             None,
         ).await.unwrap();
@@ -381,4 +1245,551 @@ mod tests {
         assert!(!memories.is_empty());
         assert_eq!(memories[0].id, memory_id);
     }
+
+    #[tokio::test]
+    async fn test_add_memories_batch() {
+        let vector_store = Arc::new(MockVectorStore::new());
+        let memory_system = MemorySystem::new(
+            "test_user".to_string(),
+            vector_store.clone(),
+            None,
+        ).await.unwrap();
+
+        let ids = memory_system.add_memories(vec![
+            (MemoryType::Preference, "用户喜欢咖啡".to_string(), vec!["咖啡".to_string()], 0.7, None),
+            (MemoryType::LongTerm, "用户的生日是12月25日".to_string(), vec!["生日".to_string()], 0.9, None),
+        ]).await.unwrap();
+
+        assert_eq!(ids.len(), 2);
+        let stats = memory_system.get_memory_stats().await;
+        assert_eq!(stats.get("total"), Some(&2));
+
+        let store_stats = vector_store.get_stats().await.unwrap();
+        assert_eq!(store_stats.get("total_vectors"), Some(&2));
+    }
+
+    #[tokio::test]
+    async fn test_write_behind_flushes_before_reads_and_on_shutdown() {
+        let vector_store = Arc::new(MockVectorStore::new());
+        let config = WriteBehindConfig { max_batch: 100, flush_interval_ms: 60_000, ..Default::default() };
+        let memory_system = MemorySystem::new("test_user".to_string(), vector_store.clone(), None).await.unwrap()
+            .with_write_behind(config);
+
+        let memory_id = memory_system.add_memory(
+            MemoryType::LongTerm,
+            "用户喜欢猫咪".to_string(),
+            vec!["猫咪".to_string()],
+            0.8,
+            None,
+        ).await.unwrap();
+        // 只进入缓存和写后缓冲，还未写入存储
+        assert_eq!(memory_system.pending_writes().await, 1);
+        assert_eq!(vector_store.count(None).await.unwrap(), 0);
+
+        let memories = memory_system.retrieve_memories("猫咪", None, Some(5)).await.unwrap();
+        assert_eq!(memories[0].id, memory_id);
+        assert_eq!(memory_system.pending_writes().await, 0);
+
+        memory_system.add_memories(vec![
+            (MemoryType::Preference, "用户喜欢咖啡".to_string(), vec![], 0.7, None),
+        ]).await.unwrap();
+        assert_eq!(memory_system.shutdown().await.unwrap(), 1);
+        assert_eq!(vector_store.count(None).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_short_term_cleanup_runs_under_supervisor() {
+        let config = MemoryConfig { short_term_limit: 1, ..MemoryConfig::default() };
+        let memory_system = Arc::new(
+            MemorySystem::new("test_user".to_string(), Arc::new(MockVectorStore::new()), Some(config)).await.unwrap(),
+        );
+        memory_system.start_background_tasks();
+        for (content, keyword) in [("早饭吃了面包", "早饭"), ("午饭吃了米饭", "午饭"), ("晚饭吃了饺子", "晚饭")] {
+            memory_system.add_memory(MemoryType::ShortTerm, content.to_string(), vec![keyword.to_string()], 0.5, None).await.unwrap();
+        }
+
+        // 停止时等待已安排的清理完成
+        memory_system.shutdown().await.unwrap();
+        assert_eq!(memory_system.memory_cache.len_of(&MemoryType::ShortTerm), 1);
+        // 移出缓存的记忆不再留在关键词索引中
+        let kept = memory_system.memory_cache.entries();
+        let indexed: HashSet<Uuid> = ["早饭", "午饭", "晚饭"].iter()
+            .flat_map(|keyword| memory_system.keyword_index.candidates(&[*keyword]))
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(indexed, kept.iter().map(|entry| entry.id).collect::<HashSet<_>>());
+        let health = memory_system.tasks().health();
+        assert!(health[&TaskKind::Cleanup].runs >= 1);
+        assert!(!health[&TaskKind::Consolidation].periodic);
+        assert!(memory_system.tasks().is_healthy());
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_filters_other_users() {
+        let vector_store = Arc::new(MockVectorStore::new());
+        let alice = MemorySystem::new("alice".to_string(), vector_store.clone(), None).await.unwrap();
+        let bob = MemorySystem::new("bob".to_string(), vector_store.clone(), None).await.unwrap();
+
+        let alice_id = alice.add_memory(
+            MemoryType::LongTerm,
+            "用户喜欢猫咪".to_string(),
+            vec!["猫咪".to_string()],
+            0.8,
+            None,
+        ).await.unwrap();
+        bob.add_memory(
+            MemoryType::LongTerm,
+            "用户喜欢猫咪".to_string(),
+            vec!["猫咪".to_string()],
+            0.8,
+            None,
+        ).await.unwrap();
+
+        let similar = vector_store.search_similar(
+            alice.generate_embedding("猫咪").await.unwrap(),
+            10,
+            0.0,
+            Some(SearchFilter::for_user("alice")),
+        ).await.unwrap();
+
+        let ids: Vec<Uuid> = similar.iter().map(|hit| hit.id).collect();
+        assert_eq!(ids, vec![alice_id]);
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_rehydrates_evicted_entries() {
+        let vector_store = Arc::new(MockVectorStore::new());
+        let memory_system = MemorySystem::new(
+            "test_user".to_string(),
+            vector_store.clone(),
+            None,
+        ).await.unwrap();
+
+        let memory_id = memory_system.add_memory(
+            MemoryType::LongTerm,
+            "用户喜欢猫咪".to_string(),
+            vec!["猫咪".to_string()],
+            0.8,
+            None,
+        ).await.unwrap();
+        memory_system.memory_cache.clear();
+
+        let memories = memory_system.retrieve_memories("猫咪", None, Some(5)).await.unwrap();
+
+        assert_eq!(memories.len(), 1);
+        assert_eq!(memories[0].id, memory_id);
+        assert!(memory_system.memory_cache.contains(&memory_id));
+
+        // 检索结果和payload都不带嵌入，按需从向量存储读取
+        assert!(memories[0].embedding.is_none());
+        let stored = vector_store.get_vector(memory_id).await.unwrap().unwrap();
+        assert!(stored.payload.get("embedding").is_none_or(serde_json::Value::is_null));
+        let embedding = memory_system.memory_embedding(memory_id).await.unwrap().unwrap();
+        assert_eq!(*embedding, stored.embedding);
+        assert!(memory_system.memory_cache.embedding(&memory_id).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_update_memory_and_importance() {
+        let vector_store = Arc::new(MockVectorStore::new());
+        let memory_system = MemorySystem::new(
+            "test_user".to_string(),
+            vector_store.clone(),
+            None,
+        ).await.unwrap();
+
+        let memory_id = memory_system.add_memory(
+            MemoryType::Preference,
+            "用户喜欢咖啡".to_string(),
+            vec!["咖啡".to_string()],
+            0.5,
+            None,
+        ).await.unwrap();
+
+        memory_system.update_memory(memory_id, "用户喜欢绿茶".to_string(), None).await.unwrap();
+        let importance = memory_system.adjust_importance(memory_id, -1.0).await.unwrap();
+        assert_eq!(importance, 0.0);
+
+        let hits = vector_store.search_similar(
+            memory_system.generate_embedding("绿茶").await.unwrap(),
+            1,
+            0.0,
+            None,
+        ).await.unwrap();
+        assert_eq!(hits[0].payload["content"], "用户喜欢绿茶");
+        assert_eq!(hits[0].payload["importance"], 0.0);
+
+        assert!(matches!(
+            memory_system.update_memory(Uuid::new_v4(), String::new(), None).await,
+            Err(MemoryError::NotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_by_keywords_follows_updates() {
+        let memory_system = MemorySystem::new(
+            "test_user".to_string(),
+            Arc::new(MockVectorStore::new()),
+            None,
+        ).await.unwrap().with_hasher(Arc::new(crate::memory::hash::FnvHasher));
+
+        let coffee = memory_system.add_memory(
+            MemoryType::Preference,
+            "用户喜欢咖啡和猫".to_string(),
+            vec!["咖啡".to_string(), "猫咪".to_string()],
+            0.5,
+            None,
+        ).await.unwrap();
+        let cat = memory_system.add_memory(
+            MemoryType::Preference,
+            "用户养了一只猫".to_string(),
+            vec!["猫咪".to_string()],
+            0.9,
+            None,
+        ).await.unwrap();
+
+        let found = memory_system.retrieve_by_keywords(&["猫咪", "咖啡"], None);
+        assert_eq!(found.iter().map(|e| e.id).collect::<Vec<_>>(), vec![coffee, cat]);
+
+        memory_system.update_memory(coffee, "用户喜欢绿茶".to_string(), Some(vec!["绿茶".to_string()])).await.unwrap();
+        let found = memory_system.retrieve_by_keywords(&["咖啡", "猫咪"], None);
+        assert_eq!(found.iter().map(|e| e.id).collect::<Vec<_>>(), vec![cat]);
+        assert_eq!(memory_system.retrieve_by_keywords(&[" 绿茶 "], None)[0].id, coffee);
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_reuses_query_embedding() {
+        let memory_system = MemorySystem::new(
+            "test_user".to_string(),
+            Arc::new(MockVectorStore::new()),
+            None,
+        ).await.unwrap();
+
+        memory_system.retrieve_memories("周末去哪", None, None).await.unwrap();
+        memory_system.retrieve_memories("周末去哪", None, None).await.unwrap();
+        assert_eq!(memory_system.query_cache.len(), 1);
+        assert_eq!(
+            *memory_system.query_cache.get("周末去哪").unwrap(),
+            memory_system.generate_embedding("周末去哪").await.unwrap(),
+        );
+    }
+
+    #[tokio::test]
+    async fn test_add_and_retrieve_with_external_embedding() {
+        let memory_system = MemorySystem::new(
+            "test_user".to_string(),
+            Arc::new(MockVectorStore::new()),
+            None,
+        ).await.unwrap();
+
+        let mut embedding = vec![0.0; EMBEDDING_DIM];
+        embedding[3] = 1.0;
+        let id = memory_system.add_memory_with_embedding(
+            MemoryType::Preference,
+            "用户喜欢抹茶".to_string(),
+            vec!["抹茶".to_string()],
+            0.6,
+            None,
+            embedding.clone(),
+        ).await.unwrap();
+
+        let memories = memory_system.retrieve_by_embedding(embedding, None, Some(1)).await.unwrap();
+        assert_eq!(memories[0].id, id);
+
+        let result = memory_system.retrieve_by_embedding(vec![1.0; 3], None, None).await;
+        assert!(matches!(result, Err(MemoryError::DimensionMismatch { expected: EMBEDDING_DIM, actual: 3 })));
+    }
+
+    #[tokio::test]
+    async fn test_new_rejects_mismatched_store_dimension() {
+        let vector_store = Arc::new(MockVectorStore::new().with_vector_size(384));
+        let result = MemorySystem::new("test_user".to_string(), vector_store, None).await;
+
+        assert!(matches!(
+            result,
+            Err(MemoryError::DimensionMismatch { expected: 384, actual: EMBEDDING_DIM })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_vector_store_health() {
+        let vector_store = Arc::new(MockVectorStore::new());
+        let memory_system = MemorySystem::new("test_user".to_string(), vector_store, None).await.unwrap();
+
+        assert_eq!(memory_system.vector_store_health().await.unwrap(), HealthStatus::Healthy);
+    }
+
+    #[tokio::test]
+    async fn test_purge_expired_memories() {
+        let vector_store = Arc::new(MockVectorStore::new());
+        let memory_system = MemorySystem::new("test_user".to_string(), vector_store.clone(), None).await.unwrap();
+
+        let expired = memory_system.add_memory(
+            MemoryType::ShortTerm, "临时提醒".to_string(), vec![], 0.3, None,
+        ).await.unwrap();
+        let kept = memory_system.add_memory(
+            MemoryType::LongTerm, "用户的生日是五月".to_string(), vec![], 0.9, None,
+        ).await.unwrap();
+
+        memory_system.set_expiry(expired, Some(chrono::Utc::now() - chrono::Duration::seconds(1))).await.unwrap();
+
+        assert_eq!(memory_system.purge_expired_memories().await.unwrap(), 1);
+        assert!(vector_store.get_vector(expired).await.unwrap().is_none());
+        assert!(vector_store.get_vector(kept).await.unwrap().is_some());
+        assert_eq!(memory_system.get_memory_stats().await["total"], 1);
+    }
+
+    #[cfg(feature = "binary-codec")]
+    #[tokio::test]
+    async fn test_binary_payload_roundtrip() {
+        let vector_store = Arc::new(MockVectorStore::new());
+        let memory_system = MemorySystem::new("test_user".to_string(), vector_store.clone(), None).await.unwrap()
+            .with_payload_codec(CodecKind::Bincode);
+
+        let id = memory_system.add_memory(
+            MemoryType::LongTerm, "用户喜欢绿茶".to_string(), vec!["绿茶".to_string()], 0.5, None,
+        ).await.unwrap();
+        memory_system.set_expiry(id, Some(chrono::Utc::now() + chrono::Duration::days(1))).await.unwrap();
+        memory_system.set_expiry(id, None).await.unwrap();
+        let importance = memory_system.adjust_importance(id, 0.2).await.unwrap();
+
+        let hits = vector_store.search_similar(memory_system.generate_embedding("绿茶").await.unwrap(), 1, 0.0, None).await.unwrap();
+        assert_eq!(hits[0].payload["content"], "用户喜欢绿茶");
+        assert_eq!(hits[0].payload[PAYLOAD_CODEC], "bincode");
+        assert!(hits[0].payload.get("keywords").is_none());
+        assert!(hits[0].payload[PAYLOAD_EXPIRES_AT_TS].is_null());
+
+        // 新的记忆系统没有缓存，只能从payload解码
+        let reloaded = MemorySystem::new("test_user".to_string(), vector_store, None).await.unwrap();
+        let entries = reloaded.list_memories(None).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].keywords, vec!["绿茶".to_string()]);
+        assert_eq!(entries[0].importance, importance);
+        assert!(entries[0].expires_at.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_by_emotion() {
+        let vector_store = Arc::new(MockVectorStore::new().with_emotion_vector_size(EmotionalState::EMBEDDING_DIM));
+        let memory_system = MemorySystem::new("test_user".to_string(), vector_store, None).await.unwrap();
+
+        let happy = EmotionalState { happiness: 1.0, affection: 0.9, trust: 0.8, dependency: 0.0, ..Default::default() };
+        let lonely = EmotionalState { happiness: 0.0, affection: 0.0, trust: 0.0, dependency: 1.0, ..Default::default() };
+
+        let happy_id = memory_system.add_memory(
+            MemoryType::Emotional, "一起去看了烟花".to_string(), vec![], 0.8, Some(happy.clone()),
+        ).await.unwrap();
+        memory_system.add_memory(
+            MemoryType::Emotional, "一个人加班到深夜".to_string(), vec![], 0.6, Some(lonely),
+        ).await.unwrap();
+        memory_system.add_memory(
+            MemoryType::LongTerm, "用户喜欢猫咪".to_string(), vec![], 0.5, None,
+        ).await.unwrap();
+
+        let memories = memory_system.retrieve_by_emotion(&happy, Some(5)).await.unwrap();
+        assert_eq!(memories.len(), 1);
+        assert_eq!(memories[0].id, happy_id);
+
+        // 批量添加时带情感向量的点一次写入
+        memory_system.add_memories(vec![
+            (MemoryType::Emotional, "一起包饺子".to_string(), vec![], 0.7, Some(happy.clone())),
+            (MemoryType::Emotional, "收到惊喜礼物".to_string(), vec![], 0.7, Some(happy.clone())),
+        ]).await.unwrap();
+        let metrics = memory_system.vector_store_metrics();
+        assert_eq!(metrics["store_multi_vectors"].calls, 1);
+        assert_eq!(metrics["store_multi_vector"].calls, 2);
+    }
+
+    #[tokio::test]
+    async fn test_image_memories() {
+        let vector_store = Arc::new(MockVectorStore::new().with_image_vector_size(3));
+        let memory_system = MemorySystem::new("test_user".to_string(), vector_store, None).await.unwrap();
+        let photo = |uri: &str| ImageAttachment {
+            uri: uri.to_string(),
+            mime_type: "image/jpeg".to_string(),
+            width: None,
+            height: None,
+            caption: None,
+        };
+
+        let beach_id = memory_system.add_image_memory(
+            MemoryType::LongTerm, "海边的合照".to_string(), vec![], 0.8, photo("beach.jpg"), vec![1.0, 0.0, 0.0],
+        ).await.unwrap();
+        memory_system.add_image_memory(
+            MemoryType::LongTerm, "猫咪睡觉".to_string(), vec![], 0.6, photo("cat.jpg"), vec![0.0, 1.0, 0.0],
+        ).await.unwrap();
+        memory_system.add_memory(MemoryType::LongTerm, "海边散步".to_string(), vec![], 0.5, None).await.unwrap();
+
+        let memories = memory_system.retrieve_by_image(vec![0.9, 0.1, 0.0], Some(5)).await.unwrap();
+        assert_eq!(memories.len(), 1);
+        assert_eq!(memories[0].id, beach_id);
+        assert_eq!(memories[0].image.as_ref().unwrap().uri, "beach.jpg");
+
+        assert!(matches!(
+            memory_system.add_image_memory(MemoryType::LongTerm, "错误维度".to_string(), vec![], 0.5, photo("x.jpg"), vec![1.0]).await,
+            Err(MemoryError::DimensionMismatch { expected: 3, actual: 1 })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_count_memories_ignores_cache_and_other_users() {
+        let vector_store = Arc::new(MockVectorStore::new());
+        let alice = MemorySystem::new("alice".to_string(), vector_store.clone(), None).await.unwrap();
+        let bob = MemorySystem::new("bob".to_string(), vector_store, None).await.unwrap();
+
+        let id = alice.add_memory(MemoryType::Preference, "喜欢咖啡".to_string(), vec![], 0.7, None).await.unwrap();
+        alice.add_memory(MemoryType::LongTerm, "生日在五月".to_string(), vec![], 0.9, None).await.unwrap();
+        bob.add_memory(MemoryType::LongTerm, "喜欢跑步".to_string(), vec![], 0.5, None).await.unwrap();
+
+        // 缓存被清空后仍以存储为准
+        alice.memory_cache.remove(&id);
+        assert_eq!(alice.count_memories(None).await.unwrap(), 2);
+        assert_eq!(alice.count_memories(Some(vec![MemoryType::Preference])).await.unwrap(), 1);
+        assert_eq!(bob.count_memories(None).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_list_import_and_consolidate() {
+        let vector_store = Arc::new(MockVectorStore::new());
+        let writer = MemorySystem::new("alice".to_string(), vector_store.clone(), None).await.unwrap();
+        writer.add_memory(MemoryType::ShortTerm, "今天第一次约会".to_string(), vec![], 0.95, None).await.unwrap();
+        writer.add_memory(MemoryType::ShortTerm, "随便聊聊天气".to_string(), vec![], 0.1, None).await.unwrap();
+
+        // 新实例从存储载入全部记忆
+        let reader = MemorySystem::new("alice".to_string(), vector_store, None).await.unwrap();
+        let entries = reader.list_memories(None).await.unwrap();
+        assert_eq!(entries.len(), 2);
+
+        assert_eq!(reader.consolidate_memories().await.unwrap(), 1);
+        assert_eq!(reader.count_memories(Some(vec![MemoryType::LongTerm])).await.unwrap(), 1);
+
+        let target = MemorySystem::new("alice".to_string(), Arc::new(MockVectorStore::new()), None).await.unwrap();
+        assert_eq!(target.import_memories(entries.clone()).await.unwrap(), 2);
+        let imported = target.list_memories(None).await.unwrap();
+        assert_eq!(imported.iter().map(|e| e.id).collect::<Vec<_>>(), entries.iter().map(|e| e.id).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_within_budget_stops_early() {
+        let vector_store = Arc::new(MockVectorStore::new());
+        let memory_system = MemorySystem::new("test_user".to_string(), vector_store, None).await.unwrap();
+
+        for _ in 0..3 {
+            memory_system.add_memory(
+                MemoryType::LongTerm, "用户喜欢猫咪".to_string(), vec![], 0.8, None,
+            ).await.unwrap();
+        }
+
+        let memories = memory_system.retrieve_within_budget("用户喜欢猫咪", 12, None).await.unwrap();
+        assert_eq!(memories.len(), 2);
+        assert!(memory_system.retrieve_within_budget("用户喜欢猫咪", 5, None).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_vector_store_metrics() {
+        let vector_store = Arc::new(MockVectorStore::new());
+        let memory_system = MemorySystem::new("test_user".to_string(), vector_store, None).await.unwrap();
+
+        memory_system.add_memory(MemoryType::LongTerm, "用户喜欢猫咪".to_string(), vec![], 0.8, None).await.unwrap();
+        memory_system.retrieve_memories("猫咪", None, Some(5)).await.unwrap();
+
+        let metrics = memory_system.vector_store_metrics();
+        assert_eq!(metrics["store_vector"].calls, 1);
+        assert_eq!(metrics["search_similar"].errors, 0);
+    }
+
+    #[tokio::test]
+    async fn test_add_memory_with_inference() {
+        let vector_store = Arc::new(MockVectorStore::new());
+        let memory_system = MemorySystem::new("test_user".to_string(), vector_store, None).await.unwrap();
+        let inference = MockInferenceClient::new();
+
+        let id = memory_system.add_memory_with_inference(
+            &inference,
+            MemoryType::LongTerm,
+            "周末 一起 看海，好开心".to_string(),
+        ).await.unwrap();
+
+        let entry = memory_system.memory_cache.get(&id).unwrap().clone();
+        assert_eq!(entry.keywords, vec!["周末", "一起", "看海", "好开心"]);
+        assert_eq!(entry.emotional_context.as_ref().unwrap().mood, "开心");
+        assert!((0.0..=1.0).contains(&entry.importance));
+
+        let offline = MockInferenceClient::new().unavailable();
+        assert!(memory_system.add_memory_with_inference(&offline, MemoryType::LongTerm, "你好".to_string()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rerank_weighs_importance_keywords_and_cross_encoder() {
+        let axis = |weights: &[(usize, f32)]| {
+            let mut embedding = vec![0.0; EMBEDDING_DIM];
+            for &(i, w) in weights {
+                embedding[i] = w;
+            }
+            embedding
+        };
+        let config = MemoryConfig {
+            similarity_threshold: 0.5,
+            rerank: crate::RerankConfig { enabled: true, ..Default::default() },
+            ..MemoryConfig::default()
+        };
+        let memory_system = MemorySystem::new("test_user".to_string(), Arc::new(MockVectorStore::new()), Some(config.clone()))
+            .await
+            .unwrap()
+            .with_rerank_inference(Arc::new(MockInferenceClient::new()));
+
+        let exact = memory_system.add_memory_with_embedding(
+            MemoryType::LongTerm, "完全匹配".to_string(), vec![], 0.0, None, axis(&[(0, 1.0)]),
+        ).await.unwrap();
+        let important = memory_system.add_memory_with_embedding(
+            MemoryType::LongTerm, "很重要".to_string(), vec![], 1.0, None, axis(&[(0, 0.9), (1, 0.4359)]),
+        ).await.unwrap();
+
+        let ids = |memories: Vec<Arc<MemoryEntry>>| memories.iter().map(|entry| entry.id).collect::<Vec<_>>();
+        let found = memory_system.retrieve_by_embedding(axis(&[(0, 1.0)]), None, Some(2)).await.unwrap();
+        assert_eq!(ids(found), vec![important, exact]);
+
+        // 关闭重排时只按相似度排序
+        memory_system.update_config(MemoryConfig { rerank: Default::default(), ..config.clone() });
+        let found = memory_system.retrieve_by_embedding(axis(&[(0, 1.0)]), None, Some(2)).await.unwrap();
+        assert_eq!(ids(found), vec![exact, important]);
+
+        // 关键词命中的记忆未达到相似度阈值也参与重排，交叉编码器区分其余各项相同的候选
+        memory_system.update_config(MemoryConfig {
+            rerank: crate::RerankConfig { enabled: true, cross_encoder_weight: 1.0, ..Default::default() },
+            ..config
+        });
+        // 先写入的记忆关键词更新颖，重要性略高
+        let meeting = memory_system.add_memory_with_embedding(
+            MemoryType::LongTerm, "明天开会".to_string(), vec!["猫咪".to_string()], 0.5, None, axis(&[(2, 1.0)]),
+        ).await.unwrap();
+        let cat = memory_system.add_memory_with_embedding(
+            MemoryType::LongTerm, "喜欢猫咪".to_string(), vec!["猫咪".to_string()], 0.5, None, axis(&[(2, 1.0)]),
+        ).await.unwrap();
+        let found = ids(memory_system.retrieve_memories("猫咪", None, Some(4)).await.unwrap());
+        let position = |id| found.iter().position(|found| *found == id).unwrap();
+        assert!(position(cat) < position(meeting));
+    }
+
+    #[tokio::test]
+    async fn test_importance_inference_blends_or_falls_back() {
+        let inference = MockInferenceClient::new();
+        let score = inference.calculate_importance("你好", None).await.unwrap();
+
+        let memory_system = MemorySystem::new("test_user".to_string(), Arc::new(MockVectorStore::new()), None)
+            .await
+            .unwrap()
+            .with_importance_inference(Arc::new(inference));
+        let id = memory_system.add_memory(MemoryType::LongTerm, "你好".to_string(), vec![], 1.0, None).await.unwrap();
+        let importance = memory_system.memory_cache.get(&id).unwrap().importance;
+        assert!((importance - (0.5 + score * 0.5)).abs() < 1e-6);
+
+        // 推理服务不可用时使用本地启发式，长期记忆无情感和关键词时保持原评分
+        let offline = MemorySystem::new("test_user".to_string(), Arc::new(MockVectorStore::new()), None)
+            .await
+            .unwrap()
+            .with_importance_inference(Arc::new(MockInferenceClient::new().unavailable()));
+        let id = offline.add_memory(MemoryType::LongTerm, "你好".to_string(), vec![], 0.8, None).await.unwrap();
+        assert_eq!(offline.memory_cache.get(&id).unwrap().importance, 0.8);
+    }
 }
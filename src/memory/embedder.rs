@@ -0,0 +1,218 @@
+//! 文本嵌入 - 记忆系统通过可替换的`Embedder`生成记忆内容和查询的向量
+//!
+//! `InferenceEmbedder`调用推理客户端（Python服务、本地模型、OpenAI兼容接口等），得到语义向量；
+//! `HashEmbedder`把字符和相邻字符对哈希到各维度上，结果确定，只反映字面重叠，
+//! 用于测试和没有推理服务的部署。
+
+use crate::bridge::InferenceClient;
+use crate::{MemoryError, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use super::core::EMBEDDING_DIM;
+
+/// 文本嵌入生成器
+#[async_trait]
+pub trait Embedder: Send + Sync + Debug {
+    /// 输出向量的维度
+    fn dimension(&self) -> usize;
+
+    /// 生成单个文本的嵌入
+    async fn embed(&self, text: &str) -> Result<Vec<f32>>;
+
+    /// 批量生成嵌入，结果与`texts`一一对应
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for text in texts {
+            embeddings.push(self.embed(text).await?);
+        }
+        Ok(embeddings)
+    }
+}
+
+/// 确定性的字面哈希嵌入
+#[derive(Debug, Clone)]
+pub struct HashEmbedder {
+    dimension: usize,
+}
+
+impl HashEmbedder {
+    pub fn new(dimension: usize) -> Self {
+        Self { dimension: dimension.max(1) }
+    }
+
+    fn bucket(&self, text: &[char]) -> usize {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for ch in text {
+            for byte in (*ch as u32).to_le_bytes() {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        }
+        (hash % self.dimension as u64) as usize
+    }
+}
+
+impl Default for HashEmbedder {
+    fn default() -> Self {
+        Self::new(EMBEDDING_DIM)
+    }
+}
+
+#[async_trait]
+impl Embedder for HashEmbedder {
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    /// 单字计1，相邻字对计2，归一化后共享片段越多余弦相似度越高
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let chars: Vec<char> = text.chars().filter(|ch| !ch.is_whitespace()).collect();
+        let mut embedding = vec![0.0f32; self.dimension];
+        for index in 0..chars.len() {
+            embedding[self.bucket(&chars[index..=index])] += 1.0;
+            if index + 1 < chars.len() {
+                embedding[self.bucket(&chars[index..index + 2])] += 2.0;
+            }
+        }
+
+        let norm = embedding.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            embedding.iter_mut().for_each(|v| *v /= norm);
+        }
+        Ok(embedding)
+    }
+}
+
+/// 由推理客户端生成嵌入
+#[derive(Debug, Clone)]
+pub struct InferenceEmbedder {
+    client: Arc<dyn InferenceClient>,
+    dimension: usize,
+}
+
+impl InferenceEmbedder {
+    /// `dimension`为推理后端嵌入模型的输出维度，返回的向量维度不同时报错
+    pub fn new(client: Arc<dyn InferenceClient>, dimension: usize) -> Self {
+        Self { client, dimension }
+    }
+}
+
+#[async_trait]
+impl Embedder for InferenceEmbedder {
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let embedding = self.client.generate_embedding(text).await?;
+        self.check(&embedding)?;
+        Ok(embedding)
+    }
+
+    /// 一次请求生成全部嵌入
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let embeddings = self.client.generate_embeddings(texts.to_vec()).await?;
+        if embeddings.len() != texts.len() {
+            return Err(MemoryError::InferenceError(format!(
+                "推理后端返回 {} 个嵌入，请求了 {} 个", embeddings.len(), texts.len(),
+            )));
+        }
+        for embedding in &embeddings {
+            self.check(embedding)?;
+        }
+        Ok(embeddings)
+    }
+}
+
+impl InferenceEmbedder {
+    fn check(&self, embedding: &[f32]) -> Result<()> {
+        if embedding.len() != self.dimension {
+            return Err(MemoryError::DimensionMismatch { expected: self.dimension, actual: embedding.len() });
+        }
+        Ok(())
+    }
+}
+
+/// 嵌入生成器配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+pub enum EmbedderConfig {
+    /// 本地哈希嵌入，不需要推理服务
+    Hash {
+        #[serde(default = "default_dimension")]
+        dimension: usize,
+    },
+    /// 使用`[inference]`配置的推理后端
+    Inference {
+        #[serde(default = "default_dimension")]
+        dimension: usize,
+    },
+}
+
+fn default_dimension() -> usize {
+    EMBEDDING_DIM
+}
+
+impl Default for EmbedderConfig {
+    fn default() -> Self {
+        Self::Hash { dimension: EMBEDDING_DIM }
+    }
+}
+
+impl EmbedderConfig {
+    /// 嵌入向量的维度
+    pub fn dimension(&self) -> usize {
+        match *self {
+            Self::Hash { dimension } | Self::Inference { dimension } => dimension,
+        }
+    }
+
+    /// 按配置创建嵌入生成器，`inference`只在使用推理后端时调用
+    pub fn build(&self, inference: impl FnOnce() -> Result<Arc<dyn InferenceClient>>) -> Result<Arc<dyn Embedder>> {
+        Ok(match *self {
+            Self::Hash { dimension } => Arc::new(HashEmbedder::new(dimension)),
+            Self::Inference { dimension } => Arc::new(InferenceEmbedder::new(inference()?, dimension)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::MockInferenceClient;
+
+    fn cosine(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| x * y).sum()
+    }
+
+    #[tokio::test]
+    async fn test_hash_embedder_is_deterministic_and_lexical() {
+        let embedder = HashEmbedder::new(256);
+        let a = embedder.embed("用户喜欢猫咪").await.unwrap();
+        assert_eq!(a.len(), 256);
+        assert_eq!(a, embedder.embed("用户喜欢猫咪").await.unwrap());
+
+        let similar = embedder.embed("喜欢小猫咪").await.unwrap();
+        let unrelated = embedder.embed("明天要开会").await.unwrap();
+        assert!(cosine(&a, &similar) > cosine(&a, &unrelated));
+        assert!(embedder.embed("").await.unwrap().iter().all(|v| *v == 0.0));
+    }
+
+    #[tokio::test]
+    async fn test_inference_embedder_checks_dimension() {
+        let client = Arc::new(MockInferenceClient::new().with_embedding_dim(64));
+        assert_eq!(InferenceEmbedder::new(client.clone(), 64).embed("你好").await.unwrap().len(), 64);
+        assert!(matches!(
+            InferenceEmbedder::new(client.clone(), 128).embed("你好").await,
+            Err(MemoryError::DimensionMismatch { expected: 128, actual: 64 })
+        ));
+
+        let config: EmbedderConfig = serde_json::from_value(serde_json::json!({ "backend": "inference", "dimension": 64 })).unwrap();
+        assert_eq!(config.dimension(), 64);
+        assert_eq!(config.build(|| Ok(client as Arc<dyn InferenceClient>)).unwrap().dimension(), 64);
+        assert_eq!(EmbedderConfig::default().build(|| unreachable!()).unwrap().dimension(), EMBEDDING_DIM);
+    }
+}
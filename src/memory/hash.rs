@@ -0,0 +1,64 @@
+//! 文本哈希 - 关键词索引和查询缓存的键
+
+use crate::bridge::ZigPerformanceUtils;
+
+/// 文本哈希器 - 同一索引内必须始终使用同一实现
+pub trait TextHasher: Send + Sync + std::fmt::Debug {
+    /// 计算文本的64位哈希
+    fn hash(&self, text: &str) -> u64;
+
+    /// 实现名称，用于日志和基准测试
+    fn name(&self) -> &'static str;
+}
+
+/// Zig实现的快速哈希（Wyhash）
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ZigHasher;
+
+impl TextHasher for ZigHasher {
+    fn hash(&self, text: &str) -> u64 {
+        ZigPerformanceUtils::fast_hash(text)
+    }
+
+    fn name(&self) -> &'static str {
+        "zig-wyhash"
+    }
+}
+
+/// 纯Rust实现（64位FNV-1a），不依赖Zig库
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FnvHasher;
+
+impl TextHasher for FnvHasher {
+    fn hash(&self, text: &str) -> u64 {
+        text.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "fnv-1a"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hashers_are_stable_and_distinguish_text() {
+        let hashers: [&dyn TextHasher; 2] = [&ZigHasher, &FnvHasher];
+        for hasher in hashers {
+            assert_eq!(hasher.hash("猫咪"), hasher.hash("猫咪"), "{}", hasher.name());
+            assert_ne!(hasher.hash("猫咪"), hasher.hash("咖啡"), "{}", hasher.name());
+        }
+
+        // 64位FNV-1a标准测试向量
+        assert_eq!(FnvHasher.hash("a"), 0xaf63dc4c8601ec8c);
+    }
+
+    #[test]
+    fn test_zig_hasher_handles_interior_nul() {
+        assert_ne!(ZigHasher.hash("a\0b"), ZigHasher.hash("a\0c"));
+    }
+}
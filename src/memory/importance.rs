@@ -0,0 +1,84 @@
+//! 本地重要性评分 - 权重和类型先验见`ImportanceConfig`
+
+use crate::{EmotionalState, ImportanceConfig, MemoryEntry};
+
+/// 情绪强度 - 情感向量各维度的平均值
+pub fn emotional_intensity(state: &EmotionalState) -> f32 {
+    state.to_embedding().iter().sum::<f32>() / EmotionalState::EMBEDDING_DIM as f32
+}
+
+/// 新颖程度 - `seen`判断关键词是否出现在已有记忆中，返回未出现过的关键词所占比例；没有关键词时为None
+pub fn novelty<S: AsRef<str>>(keywords: &[S], seen: impl Fn(&str) -> bool) -> Option<f32> {
+    if keywords.is_empty() {
+        return None;
+    }
+    let unseen = keywords.iter().filter(|keyword| !seen(keyword.as_ref())).count();
+    Some(unseen as f32 / keywords.len() as f32)
+}
+
+/// 在条目原有评分上叠加情绪、关键词、新颖程度和类型先验，结果截断到0-1
+pub fn score(config: &ImportanceConfig, entry: &MemoryEntry, novelty: Option<f32>) -> f32 {
+    let mut importance = entry.importance;
+    if let Some(ref emotion) = entry.emotional_context {
+        importance += emotional_intensity(emotion) * config.emotional_weight;
+    }
+    importance += entry.keywords.len().min(config.max_keywords) as f32 * config.keyword_weight;
+    importance += novelty.unwrap_or(0.0) * config.novelty_weight;
+    importance += config.type_priors.of(&entry.memory_type);
+    importance.clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryType;
+
+    fn entry(memory_type: MemoryType, keywords: &[&str], importance: f32) -> MemoryEntry {
+        let keywords = keywords.iter().map(|keyword| keyword.to_string()).collect();
+        MemoryEntry::new(memory_type, "内容".to_string(), keywords, importance)
+    }
+
+    #[test]
+    fn test_known_cases() {
+        let config = ImportanceConfig::default();
+
+        // 长期记忆没有情感和关键词时保持原评分
+        assert_eq!(score(&config, &entry(MemoryType::LongTerm, &[], 0.6), None), 0.6);
+        // 类型先验
+        assert!((score(&config, &entry(MemoryType::ShortTerm, &[], 0.5), None) - 0.4).abs() < 1e-6);
+        assert!((score(&config, &entry(MemoryType::Relationship, &[], 0.5), None) - 0.7).abs() < 1e-6);
+        // 关键词最多计5个
+        let many = entry(MemoryType::LongTerm, &["a", "b", "c", "d", "e", "f", "g"], 0.5);
+        assert!((score(&config, &many, None) - 0.6).abs() < 1e-6);
+
+        // 情绪强度按情感向量平均值加成
+        let mut emotional = entry(MemoryType::LongTerm, &[], 0.5);
+        let state = EmotionalState { happiness: 1.0, affection: 1.0, trust: 1.0, dependency: 1.0, ..EmotionalState::default() };
+        let intensity = emotional_intensity(&state);
+        emotional.emotional_context = Some(state);
+        assert!((score(&config, &emotional, None) - (0.5 + intensity * 0.3)).abs() < 1e-6);
+
+        // 结果截断到0-1
+        assert_eq!(score(&config, &entry(MemoryType::Emotional, &[], 0.95), Some(1.0)), 1.0);
+        assert_eq!(score(&config, &entry(MemoryType::ShortTerm, &[], 0.05), None), 0.0);
+    }
+
+    #[test]
+    fn test_novelty_rewards_new_topics() {
+        let config = ImportanceConfig::default();
+        let seen = |keyword: &str| keyword == "咖啡";
+
+        assert_eq!(novelty::<&str>(&[], seen), None);
+        assert_eq!(novelty(&["咖啡"], seen), Some(0.0));
+        assert_eq!(novelty(&["咖啡", "滑雪"], seen), Some(0.5));
+
+        let fresh = entry(MemoryType::Preference, &["滑雪"], 0.5);
+        let repeated = entry(MemoryType::Preference, &["咖啡"], 0.5);
+        let fresh_score = score(&config, &fresh, novelty(&fresh.keywords, seen));
+        let repeated_score = score(&config, &repeated, novelty(&repeated.keywords, seen));
+        assert!((fresh_score - repeated_score - config.novelty_weight).abs() < 1e-6);
+
+        let disabled = ImportanceConfig { novelty_weight: 0.0, ..config };
+        assert_eq!(score(&disabled, &fresh, Some(1.0)), score(&disabled, &repeated, Some(0.0)));
+    }
+}
@@ -0,0 +1,354 @@
+//! 关键词倒排索引和查询嵌入缓存 - 键由可替换的`TextHasher`计算
+
+use super::hash::TextHasher;
+use dashmap::DashMap;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use uuid::Uuid;
+
+/// 关键词归一化 - 与稀疏向量的处理保持一致
+fn normalize(keyword: &str) -> Option<String> {
+    let keyword = keyword.trim().to_lowercase();
+    (!keyword.is_empty()).then_some(keyword)
+}
+
+/// 关键词键的布隆过滤器 - 不包含时一定没有索引过，包含时可能误判
+///
+/// 只增不删：移除的关键词仍会命中，直到键数超过容量时按倒排表重建。
+/// 位数组由原子字组成，插入和查询都不需要加锁。
+#[derive(Debug)]
+struct KeywordBloom {
+    bits: Vec<AtomicU64>,
+    /// 容量内的误判率约为1%
+    capacity: usize,
+    inserted: AtomicUsize,
+}
+
+impl KeywordBloom {
+    /// 初始容量（键数）
+    const INITIAL_CAPACITY: usize = 1024;
+    /// 每个键的位数
+    const BITS_PER_KEY: usize = 10;
+    /// 探测次数，配合每键10位使误判率接近最低
+    const PROBES: u64 = 7;
+
+    fn with_capacity(capacity: usize) -> Self {
+        let words = (capacity * Self::BITS_PER_KEY).div_ceil(64).max(1);
+        Self { bits: (0..words).map(|_| AtomicU64::new(0)).collect(), capacity, inserted: AtomicUsize::new(0) }
+    }
+
+    /// 由键派生的位位置 - 双重哈希，第二个哈希取奇数保证遍历不同的位
+    fn positions(&self, key: u64) -> impl Iterator<Item = usize> + '_ {
+        let bits = (self.bits.len() * 64) as u64;
+        let h1 = key;
+        let h2 = key.rotate_left(32).wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+        (0..Self::PROBES).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % bits) as usize)
+    }
+
+    fn insert(&self, key: u64) {
+        for position in self.positions(key) {
+            self.bits[position / 64].fetch_or(1 << (position % 64), Ordering::Relaxed);
+        }
+        self.inserted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn may_contain(&self, key: u64) -> bool {
+        self.positions(key).all(|position| self.bits[position / 64].load(Ordering::Relaxed) & (1 << (position % 64)) != 0)
+    }
+
+    fn is_full(&self) -> bool {
+        self.inserted.load(Ordering::Relaxed) > self.capacity
+    }
+}
+
+/// 关键词倒排索引 - 返回候选ID，哈希冲突由调用方按实际关键词校验
+///
+/// 查询前先检查布隆过滤器，只含未出现过的关键词时不访问倒排表。
+/// 过滤器扩容时在旁边重建，完成后整体替换，查询和插入不必等待重建。
+#[derive(Debug)]
+pub struct KeywordIndex {
+    hasher: Arc<dyn TextHasher>,
+    postings: DashMap<u64, HashSet<Uuid>>,
+    bloom: RwLock<Arc<KeywordBloom>>,
+    /// 重建中的过滤器，新键同时写入，填充完成后替换`bloom`
+    rebuilding: Mutex<Option<Arc<KeywordBloom>>>,
+}
+
+impl KeywordIndex {
+    /// 使用指定哈希器创建空索引
+    pub fn new(hasher: Arc<dyn TextHasher>) -> Self {
+        Self {
+            hasher,
+            postings: DashMap::new(),
+            bloom: RwLock::new(Arc::new(KeywordBloom::with_capacity(KeywordBloom::INITIAL_CAPACITY))),
+            rebuilding: Mutex::new(None),
+        }
+    }
+
+    /// 索引使用的哈希器
+    pub fn hasher(&self) -> &dyn TextHasher {
+        self.hasher.as_ref()
+    }
+
+    /// 关键词的索引键
+    pub fn key(&self, keyword: &str) -> Option<u64> {
+        normalize(keyword).map(|keyword| self.hasher.hash(&keyword))
+    }
+
+    /// 关键词是否可能已被索引 - 返回false时倒排表中一定没有
+    pub fn may_contain(&self, keyword: &str) -> bool {
+        self.key(keyword).is_some_and(|key| self.bloom().may_contain(key))
+    }
+
+    /// 关键词是否已被索引 - 按哈希键判断，冲突时可能误报
+    pub fn contains(&self, keyword: &str) -> bool {
+        self.key(keyword).is_some_and(|key| self.bloom().may_contain(key) && self.postings.contains_key(&key))
+    }
+
+    fn bloom(&self) -> Arc<KeywordBloom> {
+        self.bloom.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn rebuilding(&self) -> std::sync::MutexGuard<'_, Option<Arc<KeywordBloom>>> {
+        self.rebuilding.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 添加记忆的关键词
+    pub fn insert<S: AsRef<str>>(&self, id: Uuid, keywords: &[S]) {
+        let mut new_keys = Vec::new();
+        for key in keywords.iter().filter_map(|k| self.key(k.as_ref())) {
+            let mut ids = self.postings.entry(key).or_default();
+            if ids.is_empty() {
+                new_keys.push(key);
+            }
+            ids.insert(id);
+        }
+        if !new_keys.is_empty() {
+            self.add_to_bloom(&new_keys);
+        }
+    }
+
+    /// 把新出现的键加入布隆过滤器，超过容量时扩容重建
+    fn add_to_bloom(&self, keys: &[u64]) {
+        // 先看是否在重建再取当前过滤器：重建开始前写入倒排表的键由重建遍历，之后的键写入新过滤器
+        let rebuilding = self.rebuilding().clone();
+        let bloom = self.bloom();
+        for &key in keys {
+            bloom.insert(key);
+            if let Some(ref next) = rebuilding {
+                next.insert(key);
+            }
+        }
+        if rebuilding.is_none() && self.bloom().is_full() {
+            self.rebuild_bloom();
+        }
+    }
+
+    /// 按当前倒排表填充更大的过滤器后替换，同一时间只有一个线程重建
+    fn rebuild_bloom(&self) {
+        let next = {
+            let mut rebuilding = self.rebuilding();
+            if rebuilding.is_some() {
+                return;
+            }
+            let capacity = (self.postings.len() * 2).max(KeywordBloom::INITIAL_CAPACITY);
+            rebuilding.insert(Arc::new(KeywordBloom::with_capacity(capacity))).clone()
+        };
+        for entry in self.postings.iter() {
+            next.insert(*entry.key());
+        }
+        *self.bloom.write().unwrap_or_else(|e| e.into_inner()) = next;
+        *self.rebuilding() = None;
+    }
+
+    /// 移除记忆的关键词，空的倒排表一并删除
+    pub fn remove<S: AsRef<str>>(&self, id: Uuid, keywords: &[S]) {
+        for key in keywords.iter().filter_map(|k| self.key(k.as_ref())) {
+            self.postings.remove_if_mut(&key, |_, ids| {
+                ids.remove(&id);
+                ids.is_empty()
+            });
+        }
+    }
+
+    /// 包含任一关键词的候选ID，按命中关键词数降序，数量相同时按ID排序
+    pub fn candidates<S: AsRef<str>>(&self, keywords: &[S]) -> Vec<(Uuid, usize)> {
+        let keys: HashSet<u64> = {
+            let bloom = self.bloom();
+            keywords.iter()
+                .filter_map(|k| self.key(k.as_ref()))
+                .filter(|key| bloom.may_contain(*key))
+                .collect()
+        };
+        if keys.is_empty() {
+            return Vec::new();
+        }
+
+        let mut counts: std::collections::HashMap<Uuid, usize> = std::collections::HashMap::new();
+        for key in keys {
+            if let Some(ids) = self.postings.get(&key) {
+                for id in ids.iter() {
+                    *counts.entry(*id).or_insert(0) += 1;
+                }
+            }
+        }
+
+        let mut candidates: Vec<_> = counts.into_iter().collect();
+        candidates.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        candidates
+    }
+
+    /// 不同关键词键的数量
+    pub fn len(&self) -> usize {
+        self.postings.len()
+    }
+
+    /// 索引是否为空
+    pub fn is_empty(&self) -> bool {
+        self.postings.is_empty()
+    }
+
+    /// 清空索引
+    pub fn clear(&self) {
+        self.postings.clear();
+        *self.bloom.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(KeywordBloom::with_capacity(KeywordBloom::INITIAL_CAPACITY));
+    }
+}
+
+/// 查询嵌入缓存 - 以查询文本哈希为键，命中时校验原文
+#[derive(Debug)]
+pub struct QueryCache {
+    hasher: Arc<dyn TextHasher>,
+    entries: DashMap<u64, (String, Arc<Vec<f32>>)>,
+    capacity: usize,
+}
+
+impl QueryCache {
+    /// 默认缓存条数
+    pub const DEFAULT_CAPACITY: usize = 256;
+
+    /// 创建缓存，达到容量后整体清空
+    pub fn new(hasher: Arc<dyn TextHasher>, capacity: usize) -> Self {
+        Self { hasher, entries: DashMap::new(), capacity: capacity.max(1) }
+    }
+
+    /// 查找缓存的嵌入
+    pub fn get(&self, query: &str) -> Option<Arc<Vec<f32>>> {
+        let entry = self.entries.get(&self.hasher.hash(query))?;
+        (entry.0 == query).then(|| entry.1.clone())
+    }
+
+    /// 写入嵌入，哈希冲突时覆盖旧条目
+    pub fn insert(&self, query: &str, embedding: Arc<Vec<f32>>) {
+        if self.entries.len() >= self.capacity {
+            self.entries.clear();
+        }
+        self.entries.insert(self.hasher.hash(query), (query.to_string(), embedding));
+    }
+
+    /// 缓存条数
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 缓存是否为空
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::hash::{FnvHasher, ZigHasher};
+
+    /// 所有文本映射到同一个键，用于验证冲突处理
+    #[derive(Debug)]
+    struct CollidingHasher;
+
+    impl TextHasher for CollidingHasher {
+        fn hash(&self, _text: &str) -> u64 {
+            7
+        }
+
+        fn name(&self) -> &'static str {
+            "colliding"
+        }
+    }
+
+    #[test]
+    fn test_keyword_index_ranks_by_matches() {
+        for hasher in [Arc::new(ZigHasher) as Arc<dyn TextHasher>, Arc::new(FnvHasher)] {
+            let index = KeywordIndex::new(hasher);
+            let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+            index.insert(a, &["猫咪", "咖啡"]);
+            index.insert(b, &[" 猫咪 ", ""]);
+
+            let candidates = index.candidates(&["咖啡", "猫咪", "猫咪"]);
+            assert_eq!(candidates[0], (a, 2));
+            assert_eq!(candidates[1], (b, 1));
+
+            index.remove(a, &["猫咪", "咖啡"]);
+            assert_eq!(index.candidates(&["咖啡"]), vec![]);
+            assert_eq!(index.len(), 1);
+        }
+    }
+
+    #[test]
+    fn test_bloom_skips_unseen_keywords() {
+        let index = KeywordIndex::new(Arc::new(FnvHasher));
+        let id = Uuid::new_v4();
+        let keywords: Vec<String> = (0..5000).map(|i| format!("关键词{}", i)).collect();
+        for chunk in keywords.chunks(10) {
+            index.insert(id, chunk);
+        }
+
+        // 扩容重建后已索引的关键词仍全部可见
+        assert!(keywords.iter().all(|keyword| index.may_contain(keyword)));
+        assert_eq!(index.candidates(&["关键词4999"]), vec![(id, 1)]);
+
+        let false_positives = (0..10_000)
+            .filter(|i| index.may_contain(&format!("未出现{}", i)))
+            .count();
+        assert!(false_positives < 300, "误判过多: {}", false_positives);
+        assert!(!index.may_contain("  "));
+        assert_eq!(index.candidates(&["未出现的词"]), vec![]);
+
+        index.clear();
+        assert!(!index.may_contain("关键词1"));
+    }
+
+    #[test]
+    fn test_bloom_keeps_keys_inserted_during_rebuild() {
+        let index = KeywordIndex::new(Arc::new(FnvHasher));
+        let id = Uuid::new_v4();
+        std::thread::scope(|scope| {
+            for thread in 0..4 {
+                let index = &index;
+                scope.spawn(move || {
+                    for i in 0..2000 {
+                        index.insert(id, &[format!("线程{}词{}", thread, i)]);
+                    }
+                });
+            }
+        });
+
+        // 多个线程同时触发扩容，重建期间写入的键也不会漏掉
+        assert!((0..4).all(|thread| (0..2000).all(|i| index.may_contain(&format!("线程{}词{}", thread, i)))));
+        assert_eq!(index.len(), 8000);
+    }
+
+    #[test]
+    fn test_query_cache_checks_text_on_collision() {
+        let cache = QueryCache::new(Arc::new(CollidingHasher), 8);
+        cache.insert("今天", Arc::new(vec![1.0]));
+
+        assert_eq!(cache.get("今天").as_deref(), Some(&vec![1.0]));
+        assert_eq!(cache.get("明天"), None);
+
+        cache.insert("明天", Arc::new(vec![2.0]));
+        assert_eq!(cache.get("今天"), None);
+        assert_eq!(cache.len(), 1);
+    }
+}
@@ -0,0 +1,269 @@
+//! 有界写入管道 - 记忆写入经有界队列交给固定数量的工作任务处理
+//!
+//! 所有用户共享一个管道，由`MemoryManager`持有。队列满时`add_memory`等待空位，形成背压；
+//! `try_add_memory`立即返回`MemoryError::IngestQueueFull`，调用方可以丢弃或稍后重试。
+//! 嵌入生成和存储写入的并发数由工作任务数限制，大批量导入不会挤占交互请求的延迟。
+
+use crate::{EmotionalState, MemoryEntry, MemoryError, MemorySystem, MemoryType, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot, Mutex};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+/// 写入管道配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IngestConfig {
+    /// 排队等待处理的写入上限
+    pub queue_capacity: usize,
+    /// 并发处理写入的工作任务数
+    pub workers: usize,
+}
+
+impl Default for IngestConfig {
+    fn default() -> Self {
+        Self {
+            queue_capacity: 256,
+            workers: 4,
+        }
+    }
+}
+
+/// 一条待写入的记忆
+#[derive(Debug, Clone)]
+pub struct IngestRequest {
+    pub memory_type: MemoryType,
+    pub content: String,
+    pub keywords: Vec<String>,
+    pub importance: f32,
+    pub emotional_context: Option<EmotionalState>,
+    /// 调用方提供的内容向量，None时由记忆系统生成
+    pub embedding: Option<Vec<f32>>,
+}
+
+impl IngestRequest {
+    pub fn new(memory_type: MemoryType, content: String, keywords: Vec<String>, importance: f32) -> Self {
+        Self {
+            memory_type,
+            content,
+            keywords,
+            importance,
+            emotional_context: None,
+            embedding: None,
+        }
+    }
+
+    pub fn with_emotional_context(mut self, emotional_context: Option<EmotionalState>) -> Self {
+        self.emotional_context = emotional_context;
+        self
+    }
+
+    pub fn with_embedding(mut self, embedding: Option<Vec<f32>>) -> Self {
+        self.embedding = embedding;
+        self
+    }
+}
+
+/// 排队的写入
+#[derive(Debug)]
+enum IngestJob {
+    Memory {
+        system: Arc<MemorySystem>,
+        request: IngestRequest,
+        reply: oneshot::Sender<Result<Uuid>>,
+    },
+    Import {
+        system: Arc<MemorySystem>,
+        entries: Vec<MemoryEntry>,
+        reply: oneshot::Sender<Result<usize>>,
+    },
+}
+
+impl IngestJob {
+    async fn run(self) {
+        // 调用方不再等待结果时忽略
+        match self {
+            IngestJob::Memory { system, request, reply } => {
+                let IngestRequest { memory_type, content, keywords, importance, emotional_context, embedding } = request;
+                let result = match embedding {
+                    Some(embedding) => system.add_memory_with_embedding(
+                        memory_type, content, keywords, importance, emotional_context, embedding,
+                    ).await,
+                    None => system.add_memory(memory_type, content, keywords, importance, emotional_context).await,
+                };
+                let _ = reply.send(result);
+            }
+            IngestJob::Import { system, entries, reply } => {
+                let _ = reply.send(system.import_memories(entries).await);
+            }
+        }
+    }
+}
+
+/// 有界写入管道
+#[derive(Debug)]
+pub struct IngestPipeline {
+    sender: std::sync::Mutex<Option<mpsc::Sender<IngestJob>>>,
+    capacity: usize,
+    workers: std::sync::Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl IngestPipeline {
+    /// 创建管道并启动工作任务 - 需要在tokio运行时中调用
+    pub fn new(config: IngestConfig) -> Self {
+        let capacity = config.queue_capacity.max(1);
+        let (sender, receiver) = mpsc::channel::<IngestJob>(capacity);
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let workers = (0..config.workers.max(1))
+            .map(|_| {
+                let receiver = receiver.clone();
+                tokio::spawn(async move {
+                    loop {
+                        // 只在取任务时持有接收端，处理期间其他工作任务可以继续取
+                        let Some(job) = receiver.lock().await.recv().await else {
+                            break;
+                        };
+                        job.run().await;
+                    }
+                })
+            })
+            .collect();
+
+        Self {
+            sender: std::sync::Mutex::new(Some(sender)),
+            capacity,
+            workers: std::sync::Mutex::new(workers),
+        }
+    }
+
+    /// 队列容量
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// 当前排队等待处理的写入数
+    pub fn queued(&self) -> usize {
+        self.sender().map_or(0, |sender| self.capacity - sender.capacity())
+    }
+
+    fn sender(&self) -> Option<mpsc::Sender<IngestJob>> {
+        self.sender.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn closed() -> MemoryError {
+        MemoryError::IngestClosed
+    }
+
+    /// 排队等待空位后返回写入结果
+    async fn send<T>(&self, job: IngestJob, result: oneshot::Receiver<Result<T>>) -> Result<T> {
+        let sender = self.sender().ok_or_else(Self::closed)?;
+        sender.send(job).await.map_err(|_| Self::closed())?;
+        result.await.map_err(|_| Self::closed())?
+    }
+
+    /// 添加记忆，队列满时等待空位
+    pub async fn add_memory(&self, system: Arc<MemorySystem>, request: IngestRequest) -> Result<Uuid> {
+        let (reply, result) = oneshot::channel();
+        self.send(IngestJob::Memory { system, request, reply }, result).await
+    }
+
+    /// 添加记忆，队列满时立即返回`MemoryError::IngestQueueFull`，不等待空位
+    pub async fn try_add_memory(&self, system: Arc<MemorySystem>, request: IngestRequest) -> Result<Uuid> {
+        let sender = self.sender().ok_or_else(Self::closed)?;
+        let (reply, result) = oneshot::channel();
+        sender.try_send(IngestJob::Memory { system, request, reply })
+            .map_err(|e| match e {
+                mpsc::error::TrySendError::Full(_) => MemoryError::IngestQueueFull { capacity: self.capacity },
+                mpsc::error::TrySendError::Closed(_) => Self::closed(),
+            })?;
+        result.await.map_err(|_| Self::closed())?
+    }
+
+    /// 导入已整理好的记忆条目，见`MemorySystem::import_memories`；队列满时等待空位
+    pub async fn import_memories(&self, system: Arc<MemorySystem>, entries: Vec<MemoryEntry>) -> Result<usize> {
+        let (reply, result) = oneshot::channel();
+        self.send(IngestJob::Import { system, entries, reply }, result).await
+    }
+
+    /// 停止接收新的写入，等待已排队的写入处理完
+    pub async fn shutdown(&self) {
+        self.sender.lock().unwrap_or_else(|e| e.into_inner()).take();
+        let workers = std::mem::take(&mut *self.workers.lock().unwrap_or_else(|e| e.into_inner()));
+        for worker in workers {
+            let _ = worker.await;
+        }
+    }
+}
+
+impl Drop for IngestPipeline {
+    fn drop(&mut self) {
+        for worker in self.workers.lock().unwrap_or_else(|e| e.into_inner()).drain(..) {
+            worker.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::embedder::Embedder;
+    use crate::vector_store::MockVectorStore;
+    use async_trait::async_trait;
+    use tokio::sync::Semaphore;
+
+    /// 每次生成嵌入需要一个许可，用于让工作任务停在处理中
+    #[derive(Debug)]
+    struct GatedEmbedder {
+        gate: Arc<Semaphore>,
+    }
+
+    #[async_trait]
+    impl Embedder for GatedEmbedder {
+        fn dimension(&self) -> usize {
+            4
+        }
+
+        async fn embed(&self, _text: &str) -> Result<Vec<f32>> {
+            self.gate.acquire().await.unwrap().forget();
+            Ok(vec![1.0, 0.0, 0.0, 0.0])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_try_add_fails_fast_when_queue_is_full() {
+        let gate = Arc::new(Semaphore::new(0));
+        let system = Arc::new(MemorySystem::new_with_embedder(
+            "test_user".to_string(),
+            Arc::new(MockVectorStore::new()),
+            None,
+            Arc::new(GatedEmbedder { gate: gate.clone() }),
+        ).await.unwrap());
+        let pipeline = Arc::new(IngestPipeline::new(IngestConfig { queue_capacity: 1, workers: 1 }));
+        let request = |content: &str| IngestRequest::new(MemoryType::LongTerm, content.to_string(), vec![], 0.8);
+
+        // 工作任务卡在第一条的嵌入上，第二条占满队列
+        let adds: Vec<_> = ["用户喜欢猫咪", "用户喜欢咖啡"].into_iter()
+            .map(|content| {
+                let (pipeline, system, request) = (pipeline.clone(), system.clone(), request(content));
+                tokio::spawn(async move { pipeline.add_memory(system, request).await })
+            })
+            .collect();
+        while pipeline.queued() < 1 {
+            tokio::task::yield_now().await;
+        }
+        let full = pipeline.try_add_memory(system.clone(), request("用户喜欢看海")).await;
+        assert!(matches!(full, Err(MemoryError::IngestQueueFull { capacity: 1 })));
+
+        gate.add_permits(2);
+        for add in adds {
+            add.await.unwrap().unwrap();
+        }
+        assert_eq!(system.list_memories(None).await.unwrap().len(), 2);
+
+        pipeline.shutdown().await;
+        let closed = pipeline.add_memory(system.clone(), request("关闭后")).await;
+        assert!(matches!(closed, Err(MemoryError::IngestClosed)));
+    }
+}
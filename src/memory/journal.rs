@@ -0,0 +1,197 @@
+//! 日记导出 - 把记忆和情感变化按天整理成可阅读的Markdown
+
+use crate::{EmotionalState, MemoryEntry, MemorySystem, MemoryType, Result};
+use chrono::{DateTime, Datelike, Local, NaiveDate, TimeZone, Utc, Weekday};
+use std::fmt::Write;
+use std::ops::RangeBounds;
+use std::path::Path;
+
+fn type_label(memory_type: &MemoryType) -> &'static str {
+    match memory_type {
+        MemoryType::ShortTerm => "对话",
+        MemoryType::LongTerm => "长期记忆",
+        MemoryType::Emotional => "情感",
+        MemoryType::Preference => "偏好",
+        MemoryType::Relationship => "关系",
+    }
+}
+
+fn weekday_label(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "星期一",
+        Weekday::Tue => "星期二",
+        Weekday::Wed => "星期三",
+        Weekday::Thu => "星期四",
+        Weekday::Fri => "星期五",
+        Weekday::Sat => "星期六",
+        Weekday::Sun => "星期日",
+    }
+}
+
+fn emotion_summary(emotion: &EmotionalState) -> String {
+    format!(
+        "开心 {:.2} · 亲密 {:.2} · 信任 {:.2} · 依赖 {:.2}",
+        emotion.happiness, emotion.affection, emotion.trust, emotion.dependency,
+    )
+}
+
+/// 当天依次出现的心情，相邻重复的只保留一个
+fn day_moods<'a>(entries: &[&'a MemoryEntry]) -> Vec<&'a str> {
+    let mut moods: Vec<&str> = Vec::new();
+    for emotion in entries.iter().filter_map(|entry| entry.emotional_context.as_ref()) {
+        if moods.last() != Some(&emotion.mood.as_str()) {
+            moods.push(&emotion.mood);
+        }
+    }
+    moods
+}
+
+/// 按`tz`的日期分组渲染日记，`entries`需按创建时间排序
+///
+/// 开头的情感变化表逐天列出心情和当天最后记录的情感数值；记忆系统不单独保存情感日志，
+/// 情感历史取自每条记忆记录的情感。每天开头列出当天依次出现的心情，每条记忆带时间、类型和心情标注。
+pub fn render_journal<Tz: TimeZone>(entries: &[MemoryEntry], tz: &Tz) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let mut journal = String::from("# MIRA日记\n");
+    let local = |time: &DateTime<Utc>| time.with_timezone(tz);
+
+    match (entries.first(), entries.last()) {
+        (Some(first), Some(last)) => {
+            let _ = writeln!(
+                journal,
+                "\n{} 至 {}，共 {} 条记忆",
+                local(&first.created_at).format("%Y-%m-%d"),
+                local(&last.created_at).format("%Y-%m-%d"),
+                entries.len(),
+            );
+        }
+        _ => {
+            journal.push_str("\n这段时间没有记忆。\n");
+            return journal;
+        }
+    }
+
+    let mut days: Vec<(NaiveDate, Vec<&MemoryEntry>)> = Vec::new();
+    for entry in entries {
+        let date = local(&entry.created_at).date_naive();
+        match days.last_mut() {
+            Some((day, day_entries)) if *day == date => day_entries.push(entry),
+            _ => days.push((date, vec![entry])),
+        }
+    }
+
+    let history: Vec<(NaiveDate, Vec<&str>, &EmotionalState)> = days.iter()
+        .filter_map(|(date, day_entries)| {
+            let last = day_entries.iter().rev().find_map(|entry| entry.emotional_context.as_ref())?;
+            Some((*date, day_moods(day_entries), last))
+        })
+        .collect();
+    if !history.is_empty() {
+        journal.push_str("\n## 情感变化\n\n| 日期 | 心情 | 开心 | 亲密 | 信任 | 依赖 |\n| --- | --- | --- | --- | --- | --- |\n");
+        for (date, moods, emotion) in &history {
+            let _ = writeln!(
+                journal,
+                "| {} | {} | {:.2} | {:.2} | {:.2} | {:.2} |",
+                date.format("%Y-%m-%d"), moods.join(" → "),
+                emotion.happiness, emotion.affection, emotion.trust, emotion.dependency,
+            );
+        }
+    }
+
+    for (date, day_entries) in days {
+        let _ = writeln!(journal, "\n## {}（{}）\n", date.format("%Y-%m-%d"), weekday_label(date.weekday()));
+
+        let moods = day_moods(&day_entries);
+        if let Some(emotion) = day_entries.iter().rev().find_map(|entry| entry.emotional_context.as_ref()) {
+            let _ = writeln!(journal, "> 心情：{}  \n> {}\n", moods.join(" → "), emotion_summary(emotion));
+        }
+
+        for entry in day_entries {
+            let _ = write!(journal, "### {} · {}", local(&entry.created_at).format("%H:%M"), type_label(&entry.memory_type));
+            if let Some(ref emotion) = entry.emotional_context {
+                let _ = write!(journal, " · {}", emotion.mood);
+            }
+            journal.push_str("\n\n");
+            // 多行内容保留换行
+            for line in entry.content.lines() {
+                let _ = writeln!(journal, "{}  ", line);
+            }
+            if !entry.keywords.is_empty() {
+                let _ = writeln!(journal, "\n*关键词：{}*", entry.keywords.join("、"));
+            }
+            journal.push('\n');
+        }
+    }
+    journal
+}
+
+impl MemorySystem {
+    /// 把创建时间在`range`内的记忆导出为Markdown日记，按本地时区分天，返回导出的记忆条数
+    #[tracing::instrument(skip_all, fields(user_id = %self.user_id()))]
+    pub async fn export_journal(&self, range: impl RangeBounds<DateTime<Utc>>, path: impl AsRef<Path>) -> Result<usize> {
+        let entries: Vec<MemoryEntry> = self.list_memories(None).await?
+            .into_iter()
+            .filter(|entry| range.contains(&entry.created_at))
+            .map(|entry| MemoryEntry::clone(&entry))
+            .collect();
+
+        tokio::fs::write(path, render_journal(&entries, &Local)).await?;
+        Ok(entries.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::MockVectorStore;
+    use std::sync::Arc;
+
+    fn entry(time: &str, content: &str, mood: Option<&str>) -> MemoryEntry {
+        let mut entry = MemoryEntry::new(MemoryType::LongTerm, content.to_string(), vec![], 0.5);
+        entry.created_at = time.parse().unwrap();
+        entry.emotional_context = mood.map(|mood| EmotionalState { mood: mood.to_string(), ..EmotionalState::default() });
+        entry
+    }
+
+    #[test]
+    fn test_render_groups_by_day_with_moods() {
+        let entries = vec![
+            entry("2024-02-14T09:00:00Z", "一起吃早餐", Some("开心")),
+            entry("2024-02-14T20:00:00Z", "小明: 情人节快乐\nMira: 你也是", Some("幸福")),
+            entry("2024-02-15T08:30:00Z", "小明说今天要加班", None),
+        ];
+
+        let journal = render_journal(&entries, &Utc);
+        assert!(journal.contains("2024-02-14 至 2024-02-15，共 3 条记忆"));
+        assert!(journal.contains("## 2024-02-14（星期三）"));
+        assert!(journal.contains("> 心情：开心 → 幸福"));
+        assert!(journal.contains("## 情感变化"));
+        assert!(journal.contains("| 2024-02-14 | 开心 → 幸福 | 0.50 |"));
+        assert!(!journal.contains("| 2024-02-15 |"));
+        assert!(journal.find("## 情感变化").unwrap() < journal.find("## 2024-02-14").unwrap());
+        assert!(journal.contains("### 20:00 · 长期记忆 · 幸福\n\n小明: 情人节快乐  \nMira: 你也是  \n"));
+        assert!(journal.find("## 2024-02-15").unwrap() > journal.find("一起吃早餐").unwrap());
+        assert!(render_journal(&[], &Utc).contains("没有记忆"));
+    }
+
+    #[tokio::test]
+    async fn test_export_journal_filters_range() {
+        let system = MemorySystem::new("alice".to_string(), Arc::new(MockVectorStore::new()), None).await.unwrap();
+        system.import_memories(vec![
+            entry("2024-02-14T12:00:00Z", "情人节约会", Some("幸福")),
+            entry("2024-03-01T12:00:00Z", "三月的记忆", None),
+        ]).await.unwrap();
+
+        let path = std::env::temp_dir().join(format!("mira_journal_{}.md", uuid::Uuid::new_v4()));
+        let from: DateTime<Utc> = "2024-02-01T00:00:00Z".parse().unwrap();
+        let to: DateTime<Utc> = "2024-03-01T00:00:00Z".parse().unwrap();
+        assert_eq!(system.export_journal(from..to, &path).await.unwrap(), 1);
+
+        let journal = std::fs::read_to_string(&path).unwrap();
+        assert!(journal.contains("情人节约会"));
+        assert!(!journal.contains("三月的记忆"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
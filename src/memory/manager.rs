@@ -0,0 +1,303 @@
+//! 多用户记忆管理 - 所有用户共享同一个向量存储，按用户惰性创建隔离的记忆系统
+//!
+//! 新建的记忆系统立即启动短期记忆清理和整理任务，设置情感引擎时同时启动情感衰减。
+
+use crate::bridge::InferenceClient;
+use crate::emotion::{EmotionalEngine, EmotionalTrigger};
+use crate::memory::embedder::{Embedder, HashEmbedder};
+use crate::memory::ingest::{IngestConfig, IngestPipeline, IngestRequest};
+use crate::plugins::PluginRegistry;
+use crate::vector_store::{CodecKind, VectorStore, WriteBehindConfig};
+use crate::{EmotionalState, MemoryConfig, MemoryEntry, MemorySystem, Result};
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+/// 情感变化通知的缓冲条数，订阅方落后超过此数量时丢弃最旧的通知
+pub const EMOTION_CHANNEL_CAPACITY: usize = 256;
+
+/// 用户情感状态变化
+#[derive(Debug, Clone)]
+pub struct EmotionChange {
+    pub user_id: String,
+    /// 引起变化的触发器，直接设置状态时为None
+    pub trigger: Option<EmotionalTrigger>,
+    pub state: EmotionalState,
+}
+
+/// 按用户ID管理记忆系统
+#[derive(Debug)]
+pub struct MemoryManager {
+    vector_store: Arc<dyn VectorStore<Error = anyhow::Error>>,
+    config: RwLock<Arc<MemoryConfig>>,
+    systems: DashMap<String, Arc<MemorySystem>>,
+    emotion_changes: broadcast::Sender<EmotionChange>,
+    plugins: Option<Arc<PluginRegistry>>,
+    embedder: Arc<dyn Embedder>,
+    payload_codec: CodecKind,
+    write_behind: Option<WriteBehindConfig>,
+    ingest_config: IngestConfig,
+    /// 情感衰减使用的引擎，未设置时不启动衰减
+    engine: Option<Arc<EmotionalEngine>>,
+    /// 重排阶段的交叉编码器，未设置时`cross_encoder_weight`不起作用
+    rerank_inference: Option<Arc<dyn InferenceClient>>,
+    /// 第一次写入时启动
+    ingest: OnceLock<Arc<IngestPipeline>>,
+}
+
+impl MemoryManager {
+    /// 创建管理器，所有用户使用相同的配置
+    pub fn new(vector_store: Arc<dyn VectorStore<Error = anyhow::Error>>, config: Option<MemoryConfig>) -> Self {
+        Self {
+            vector_store,
+            config: RwLock::new(Arc::new(config.unwrap_or_default())),
+            systems: DashMap::new(),
+            emotion_changes: broadcast::channel(EMOTION_CHANNEL_CAPACITY).0,
+            plugins: None,
+            embedder: Arc::new(HashEmbedder::default()),
+            payload_codec: CodecKind::default(),
+            write_behind: None,
+            ingest_config: IngestConfig::default(),
+            engine: None,
+            rerank_inference: None,
+            ingest: OnceLock::new(),
+        }
+    }
+
+    /// 所有记忆系统共用的嵌入生成器
+    pub fn with_embedder(mut self, embedder: Arc<dyn Embedder>) -> Self {
+        self.embedder = embedder;
+        self
+    }
+
+    /// 之后创建的记忆系统使用的插件
+    pub fn with_plugins(mut self, plugins: Arc<PluginRegistry>) -> Self {
+        self.plugins = Some(plugins);
+        self
+    }
+
+    /// 记忆系统和对话会话使用的插件
+    pub fn plugins(&self) -> Option<Arc<PluginRegistry>> {
+        self.plugins.clone()
+    }
+
+    /// 之后创建的记忆系统写入payload时使用的编码
+    pub fn with_payload_codec(mut self, codec: CodecKind) -> Self {
+        self.payload_codec = codec;
+        self
+    }
+
+    /// 之后创建的记忆系统启用写后缓冲，向量由后台任务批量写入
+    pub fn with_write_behind(mut self, config: WriteBehindConfig) -> Self {
+        self.write_behind = Some(config);
+        self
+    }
+
+    /// 之后创建的记忆系统按该引擎的衰减配置定期衰减情感状态
+    pub fn with_emotional_engine(mut self, engine: Arc<EmotionalEngine>) -> Self {
+        self.engine = Some(engine);
+        self
+    }
+
+    /// 记忆系统衰减情感状态使用的引擎，其他入口共用它以便配置热加载同时生效
+    pub fn emotional_engine(&self) -> Option<Arc<EmotionalEngine>> {
+        self.engine.clone()
+    }
+
+    /// 之后创建的记忆系统在重排阶段使用该客户端的交叉编码器
+    pub fn with_rerank_inference(mut self, inference: Arc<dyn InferenceClient>) -> Self {
+        self.rerank_inference = Some(inference);
+        self
+    }
+
+    /// 写入管道的队列容量和工作任务数
+    pub fn with_ingest(mut self, config: IngestConfig) -> Self {
+        self.ingest_config = config;
+        self
+    }
+
+    /// 所有用户共享的写入管道，第一次调用时启动工作任务 - 需要在tokio运行时中调用
+    pub fn ingest(&self) -> Arc<IngestPipeline> {
+        self.ingest.get_or_init(|| Arc::new(IngestPipeline::new(self.ingest_config.clone()))).clone()
+    }
+
+    /// 经写入管道为用户添加记忆，队列满时等待空位
+    pub async fn add_memory(&self, user_id: &str, request: IngestRequest) -> Result<Uuid> {
+        let system = self.get_or_create(user_id).await?;
+        self.ingest().add_memory(system, request).await
+    }
+
+    /// 经写入管道为用户添加记忆，队列满时立即返回`MemoryError::IngestQueueFull`
+    pub async fn try_add_memory(&self, user_id: &str, request: IngestRequest) -> Result<Uuid> {
+        let system = self.get_or_create(user_id).await?;
+        self.ingest().try_add_memory(system, request).await
+    }
+
+    /// 经写入管道为用户导入记忆条目，见`MemorySystem::import_memories`
+    pub async fn import_memories(&self, user_id: &str, entries: Vec<MemoryEntry>) -> Result<usize> {
+        let system = self.get_or_create(user_id).await?;
+        self.ingest().import_memories(system, entries).await
+    }
+
+    /// 所有用户共享的向量存储
+    pub fn vector_store(&self) -> Arc<dyn VectorStore<Error = anyhow::Error>> {
+        self.vector_store.clone()
+    }
+
+    /// 新建记忆系统使用的配置
+    pub fn config(&self) -> Arc<MemoryConfig> {
+        self.config.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// 替换配置，已创建的记忆系统同时更新
+    pub fn update_config(&self, config: MemoryConfig) {
+        *self.config.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(config.clone());
+        for system in self.systems.iter() {
+            system.update_config(config.clone());
+        }
+    }
+
+    /// 更新用户情感状态并通知订阅方
+    pub async fn update_emotion(
+        &self,
+        user_id: &str,
+        trigger: Option<EmotionalTrigger>,
+        state: EmotionalState,
+    ) -> Result<()> {
+        self.get_or_create(user_id).await?.update_emotional_state(state.clone()).await;
+        // 没有订阅方时发送失败，忽略即可
+        let _ = self.emotion_changes.send(EmotionChange { user_id: user_id.to_string(), trigger, state });
+        Ok(())
+    }
+
+    /// 订阅所有用户的情感变化
+    pub fn subscribe_emotions(&self) -> broadcast::Receiver<EmotionChange> {
+        self.emotion_changes.subscribe()
+    }
+
+    /// 获取用户的记忆系统，不存在时创建并启动后台任务
+    pub async fn get_or_create(&self, user_id: &str) -> Result<Arc<MemorySystem>> {
+        if let Some(system) = self.get(user_id) {
+            return Ok(system);
+        }
+
+        // 创建期间不持有分片锁，并发创建时以先插入的为准
+        let mut system = MemorySystem::new_with_embedder(
+            user_id.to_string(),
+            self.vector_store.clone(),
+            Some(self.config().as_ref().clone()),
+            self.embedder.clone(),
+        ).await?.with_payload_codec(self.payload_codec);
+        if let Some(ref plugins) = self.plugins {
+            system = system.with_plugins(plugins.clone());
+        }
+        if let Some(ref write_behind) = self.write_behind {
+            system = system.with_write_behind(write_behind.clone());
+        }
+        if let Some(ref inference) = self.rerank_inference {
+            system = system.with_rerank_inference(inference.clone());
+        }
+        let system = match self.systems.entry(user_id.to_string()) {
+            Entry::Occupied(existing) => return Ok(existing.get().clone()),
+            Entry::Vacant(vacant) => vacant.insert(Arc::new(system)).clone(),
+        };
+        system.start_background_tasks();
+        if let Some(ref engine) = self.engine {
+            system.start_emotion_decay(engine.clone());
+        }
+        Ok(system)
+    }
+
+    /// 获取已创建的记忆系统
+    pub fn get(&self, user_id: &str) -> Option<Arc<MemorySystem>> {
+        self.systems.get(user_id).map(|system| system.clone())
+    }
+
+    /// 移除用户的记忆系统，向量存储中的记忆不受影响
+    pub fn remove(&self, user_id: &str) -> Option<Arc<MemorySystem>> {
+        self.systems.remove(user_id).map(|(_, system)| system)
+    }
+
+    /// 写入所有记忆系统写后缓冲中的剩余记录，返回写入的总条数
+    pub async fn shutdown(&self) -> Result<usize> {
+        // 先处理完已排队的写入
+        if let Some(ingest) = self.ingest.get() {
+            ingest.shutdown().await;
+        }
+        let systems: Vec<Arc<MemorySystem>> = self.systems.iter().map(|entry| entry.value().clone()).collect();
+        let mut flushed = 0;
+        for system in systems {
+            flushed += system.shutdown().await?;
+        }
+        Ok(flushed)
+    }
+
+    /// 已创建记忆系统的用户ID
+    pub fn user_ids(&self) -> Vec<String> {
+        self.systems.iter().map(|entry| entry.key().clone()).collect()
+    }
+
+    /// 已创建的记忆系统数量
+    pub fn len(&self) -> usize {
+        self.systems.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.systems.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::MockVectorStore;
+    use crate::MemoryType;
+
+    #[tokio::test]
+    async fn test_users_share_store_but_not_memories() {
+        let manager = MemoryManager::new(Arc::new(MockVectorStore::new()), None);
+
+        let alice = manager.get_or_create("alice").await.unwrap();
+        assert!(Arc::ptr_eq(&alice, &manager.get_or_create("alice").await.unwrap()));
+        assert!(alice.tasks().health()[&crate::memory::TaskKind::Consolidation].periodic);
+
+        alice.add_memory(MemoryType::Preference, "喜欢猫咪".to_string(), vec![], 0.8, None).await.unwrap();
+        let bob = manager.get_or_create("bob").await.unwrap();
+        assert!(bob.retrieve_memories("喜欢猫咪", None, None).await.unwrap().is_empty());
+        assert_eq!(alice.retrieve_memories("喜欢猫咪", None, None).await.unwrap().len(), 1);
+
+        assert_eq!(manager.len(), 2);
+        assert!(manager.remove("bob").is_some());
+        assert_eq!(manager.user_ids(), vec!["alice".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_writes_go_through_shared_pipeline() {
+        let manager = MemoryManager::new(Arc::new(MockVectorStore::new()), None)
+            .with_ingest(IngestConfig { queue_capacity: 4, workers: 1 });
+
+        let request = IngestRequest::new(MemoryType::Preference, "喜欢猫咪".to_string(), vec![], 0.8);
+        let id = manager.try_add_memory("alice", request).await.unwrap();
+        assert_eq!(manager.ingest().capacity(), 4);
+        assert_eq!(manager.get("alice").unwrap().list_memories(None).await.unwrap()[0].id, id);
+
+        manager.shutdown().await.unwrap();
+        let request = IngestRequest::new(MemoryType::Preference, "关闭后".to_string(), vec![], 0.5);
+        assert!(matches!(manager.add_memory("alice", request).await, Err(crate::MemoryError::IngestClosed)));
+    }
+
+    #[tokio::test]
+    async fn test_emotion_updates_are_broadcast() {
+        let manager = MemoryManager::new(Arc::new(MockVectorStore::new()), None);
+        let mut changes = manager.subscribe_emotions();
+
+        let state = EmotionalState { happiness: 0.9, ..EmotionalState::default() };
+        manager.update_emotion("alice", Some(EmotionalTrigger::BeingPraised), state).await.unwrap();
+
+        let change = changes.recv().await.unwrap();
+        assert_eq!((change.user_id.as_str(), change.trigger), ("alice", Some(EmotionalTrigger::BeingPraised)));
+        assert_eq!(manager.get("alice").unwrap().get_emotional_state().await.happiness, 0.9);
+    }
+}
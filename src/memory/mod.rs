@@ -1,3 +1,18 @@
 //! 记忆系统模块
 
+pub mod cache;
 pub mod core;
+pub mod embedder;
+pub mod hash;
+pub mod importance;
+pub mod index;
+pub mod ingest;
+pub mod journal;
+pub mod manager;
+pub mod rerank;
+pub mod supervisor;
+pub mod tools;
+
+pub use ingest::{IngestConfig, IngestPipeline, IngestRequest};
+pub use manager::{EmotionChange, MemoryManager};
+pub use supervisor::{TaskHealth, TaskKind, TaskSupervisor};
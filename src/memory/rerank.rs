@@ -0,0 +1,85 @@
+//! 两阶段检索的重排打分 - 权重和半衰期见`RerankConfig`
+
+use crate::vector_store::DistanceMetric;
+use crate::{MemoryEntry, RerankConfig};
+use chrono::{DateTime, Utc};
+
+/// 把度量分数映射到0-1，越大越相近
+pub fn unit_similarity(metric: DistanceMetric, score: f32) -> f32 {
+    if score.is_nan() {
+        return 0.0;
+    }
+    match metric {
+        DistanceMetric::Cosine | DistanceMetric::Dot => score.clamp(0.0, 1.0),
+        DistanceMetric::Euclidean => 1.0 / (1.0 + score.max(0.0)),
+    }
+}
+
+/// 新近程度 - 刚创建时为1，每经过一个半衰期减半
+pub fn recency(created_at: DateTime<Utc>, now: DateTime<Utc>, half_life_hours: f32) -> f32 {
+    let age_hours = (now - created_at).num_seconds().max(0) as f32 / 3600.0;
+    0.5f32.powf(age_hours / half_life_hours.max(f32::EPSILON))
+}
+
+/// 候选的重排分数
+///
+/// `similarity`为0-1的相似度，`cross_encoder`为交叉编码器的相关性，没有时不参与；
+/// 结果按参与项的权重之和归一化，权重全为0时为0。
+pub fn score(
+    config: &RerankConfig,
+    entry: &MemoryEntry,
+    similarity: f32,
+    cross_encoder: Option<f32>,
+    now: DateTime<Utc>,
+) -> f32 {
+    let mut terms = vec![
+        (config.similarity_weight, similarity),
+        (config.recency_weight, recency(entry.created_at, now, config.recency_half_life_hours)),
+        (config.importance_weight, entry.importance.clamp(0.0, 1.0)),
+    ];
+    if let Some(relevance) = cross_encoder {
+        terms.push((config.cross_encoder_weight, relevance.clamp(0.0, 1.0)));
+    }
+
+    let total: f32 = terms.iter().map(|(weight, _)| weight).sum();
+    if total <= 0.0 {
+        return 0.0;
+    }
+    terms.iter().map(|(weight, value)| weight * value).sum::<f32>() / total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryType;
+    use chrono::Duration;
+
+    #[test]
+    fn test_score_blends_recency_and_importance() {
+        let config = RerankConfig { enabled: true, ..RerankConfig::default() };
+        let now = Utc::now();
+
+        let mut fresh = MemoryEntry::new(MemoryType::LongTerm, "新的".to_string(), vec![], 0.5);
+        fresh.created_at = now;
+        let mut stale = fresh.clone();
+        stale.created_at = now - Duration::hours(72);
+
+        assert!((recency(stale.created_at, now, 72.0) - 0.5).abs() < 1e-3);
+        assert!(score(&config, &fresh, 0.8, None, now) > score(&config, &stale, 0.8, None, now));
+
+        // 重要性足够高时可以超过相似度略高的候选
+        let mut important = stale.clone();
+        important.importance = 1.0;
+        let mut trivial = stale.clone();
+        trivial.importance = 0.0;
+        assert!(score(&config, &important, 0.7, None, now) > score(&config, &trivial, 0.8, None, now));
+
+        // 交叉编码器只在给出分数时参与归一化
+        let cross = RerankConfig { cross_encoder_weight: 1.0, ..config.clone() };
+        assert_eq!(score(&cross, &fresh, 0.8, None, now), score(&config, &fresh, 0.8, None, now));
+        assert!(score(&cross, &fresh, 0.8, Some(0.0), now) < score(&cross, &fresh, 0.8, Some(1.0), now));
+
+        assert_eq!(unit_similarity(DistanceMetric::Cosine, -0.3), 0.0);
+        assert_eq!(unit_similarity(DistanceMetric::Euclidean, 0.0), 1.0);
+    }
+}
@@ -0,0 +1,303 @@
+//! 后台任务监督 - 记忆系统的清理、情感衰减、整理和写后缓冲刷新都由这里启动
+//!
+//! 同一种任务已排队尚未开始时不再安排，开始后的新请求会再排一次；所有任务共享并发上限。
+//! 周期任务每次运行结束后才等待下一个周期，不会与自身重叠，监督器释放时一并停止。
+//! 每种任务记录运行次数、失败次数和最近的错误，用于健康检查。
+
+use crate::Result;
+use chrono::{DateTime, Utc};
+use futures::FutureExt;
+use std::collections::HashMap;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
+
+/// 默认同时运行的后台任务数
+pub const DEFAULT_MAX_CONCURRENT: usize = 2;
+
+/// 后台任务种类
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
+pub enum TaskKind {
+    /// 移除超出上限的短期记忆
+    Cleanup,
+    /// 情感状态随时间衰减
+    Decay,
+    /// 短期记忆转为长期记忆
+    Consolidation,
+    /// 写后缓冲刷新到向量存储
+    Flush,
+}
+
+/// 一种任务的运行状况
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct TaskHealth {
+    /// 已安排尚未开始
+    pub queued: bool,
+    pub running: bool,
+    /// 是否有周期调度
+    pub periodic: bool,
+    pub runs: u64,
+    pub failures: u64,
+    /// 最近一次运行的错误，成功后清除
+    pub last_error: Option<String>,
+    pub last_finished: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default)]
+struct Slot {
+    health: TaskHealth,
+    periodic: Option<JoinHandle<()>>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    permits: Semaphore,
+    max_concurrent: usize,
+    slots: Mutex<HashMap<TaskKind, Slot>>,
+}
+
+impl Inner {
+    fn slots(&self) -> MutexGuard<'_, HashMap<TaskKind, Slot>> {
+        self.slots.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 标记为已排队，已在排队时返回false
+    fn enqueue(&self, kind: TaskKind) -> bool {
+        let mut slots = self.slots();
+        let health = &mut slots.entry(kind).or_default().health;
+        !std::mem::replace(&mut health.queued, true)
+    }
+
+    /// 等待并发名额后运行，记录结果；任务panic时记为失败
+    async fn run(&self, kind: TaskKind, job: impl Future<Output = Result<()>>) {
+        let Ok(_permit) = self.permits.acquire().await else {
+            return;
+        };
+        {
+            let mut slots = self.slots();
+            let health = &mut slots.entry(kind).or_default().health;
+            health.queued = false;
+            health.running = true;
+        }
+
+        let result = match AssertUnwindSafe(job).catch_unwind().await {
+            Ok(result) => result.map_err(|e| e.to_string()),
+            Err(_) => Err("任务panic".to_string()),
+        };
+
+        let mut slots = self.slots();
+        let health = &mut slots.entry(kind).or_default().health;
+        health.running = false;
+        health.runs += 1;
+        health.last_finished = Some(Utc::now());
+        match result {
+            Ok(()) => health.last_error = None,
+            Err(e) => {
+                tracing::warn!("后台任务 {:?} 失败: {}", kind, e);
+                health.failures += 1;
+                health.last_error = Some(e);
+            }
+        }
+    }
+}
+
+/// 后台任务监督器
+#[derive(Debug)]
+pub struct TaskSupervisor {
+    inner: Arc<Inner>,
+}
+
+impl Default for TaskSupervisor {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_CONCURRENT)
+    }
+}
+
+impl TaskSupervisor {
+    /// 最多同时运行`max_concurrent`个任务，至少为1
+    pub fn new(max_concurrent: usize) -> Self {
+        let max_concurrent = max_concurrent.max(1);
+        Self {
+            inner: Arc::new(Inner {
+                permits: Semaphore::new(max_concurrent),
+                max_concurrent,
+                slots: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// 安排一次任务 - 同种任务已排队时不再安排，返回false
+    pub fn submit<F>(&self, kind: TaskKind, job: F) -> bool
+    where
+        F: Future<Output = Result<()>> + Send + 'static,
+    {
+        if !self.inner.enqueue(kind) {
+            return false;
+        }
+        let inner = self.inner.clone();
+        tokio::spawn(async move { inner.run(kind, job).await });
+        true
+    }
+
+    /// 每隔`period`运行一次，第一次在一个周期后运行；替换同种任务原有的周期调度
+    pub fn schedule<F, Fut>(&self, kind: TaskKind, period: Duration, job: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let inner = self.inner.clone();
+        let handle = tokio::spawn(async move {
+            let period = period.max(Duration::from_millis(1));
+            let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                if inner.enqueue(kind) {
+                    // 在独立任务中运行，取消周期调度时不中断正在运行的一次
+                    let (inner, job) = (inner.clone(), job());
+                    let _ = tokio::spawn(async move { inner.run(kind, job).await }).await;
+                }
+            }
+        });
+
+        let mut slots = self.inner.slots();
+        let slot = slots.entry(kind).or_default();
+        if let Some(previous) = slot.periodic.replace(handle) {
+            previous.abort();
+        }
+        slot.health.periodic = true;
+    }
+
+    /// 停止同种任务的周期调度，正在运行的一次不受影响
+    pub fn cancel(&self, kind: TaskKind) -> bool {
+        let mut slots = self.inner.slots();
+        let Some(slot) = slots.get_mut(&kind) else {
+            return false;
+        };
+        slot.health.periodic = false;
+        match slot.periodic.take() {
+            Some(handle) => {
+                handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 各种任务的运行状况，没有运行过的任务不在其中
+    pub fn health(&self) -> HashMap<TaskKind, TaskHealth> {
+        self.inner.slots().iter().map(|(kind, slot)| (*kind, slot.health.clone())).collect()
+    }
+
+    /// 所有任务最近一次运行都成功
+    pub fn is_healthy(&self) -> bool {
+        self.inner.slots().values().all(|slot| slot.health.last_error.is_none())
+    }
+
+    /// 停止全部周期调度并等待已安排的任务完成
+    pub async fn shutdown(&self) {
+        let handles: Vec<JoinHandle<()>> = self.inner.slots().values_mut()
+            .filter_map(|slot| {
+                slot.health.periodic = false;
+                slot.periodic.take()
+            })
+            .collect();
+        for handle in handles {
+            handle.abort();
+        }
+        // 持有全部名额时没有任务在运行，仍有排队的任务时让出名额
+        loop {
+            let permits = self.inner.permits.acquire_many(self.inner.max_concurrent as u32).await;
+            let idle = self.inner.slots().values().all(|slot| !slot.health.queued && !slot.health.running);
+            drop(permits);
+            if idle {
+                break;
+            }
+            tokio::task::yield_now().await;
+        }
+    }
+}
+
+impl Drop for TaskSupervisor {
+    fn drop(&mut self) {
+        for slot in self.inner.slots().values_mut() {
+            if let Some(handle) = slot.periodic.take() {
+                handle.abort();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryError;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_dedup_limits_and_health() {
+        let supervisor = TaskSupervisor::new(1);
+        let (release, released) = tokio::sync::oneshot::channel::<()>();
+
+        // 唯一的名额被占用，之后的任务都在排队
+        assert!(supervisor.submit(TaskKind::Flush, async move {
+            let _ = released.await;
+            Ok(())
+        }));
+        let runs = Arc::new(AtomicUsize::new(0));
+        for _ in 0..3 {
+            let runs = runs.clone();
+            supervisor.submit(TaskKind::Cleanup, async move {
+                runs.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            });
+        }
+        assert!(supervisor.submit(TaskKind::Consolidation, async {
+            Err(MemoryError::InvalidInput("整理失败".to_string()))
+        }));
+        tokio::task::yield_now().await;
+        let health = supervisor.health();
+        assert!(health[&TaskKind::Flush].running);
+        assert!(health[&TaskKind::Cleanup].queued);
+
+        release.send(()).unwrap();
+        supervisor.shutdown().await;
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        let health = supervisor.health();
+        assert_eq!(health[&TaskKind::Cleanup].runs, 1);
+        assert_eq!(health[&TaskKind::Consolidation].failures, 1);
+        assert!(health[&TaskKind::Consolidation].last_error.as_deref().unwrap().contains("整理失败"));
+        assert!(!supervisor.is_healthy());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_periodic_jobs_replace_and_cancel() {
+        let supervisor = TaskSupervisor::default();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let job = {
+            let runs = runs.clone();
+            move || {
+                let runs = runs.clone();
+                async move {
+                    runs.fetch_add(1, Ordering::SeqCst);
+                    Ok(())
+                }
+            }
+        };
+        supervisor.schedule(TaskKind::Decay, Duration::from_secs(10), job.clone());
+        // 重新调度替换原有的周期任务
+        supervisor.schedule(TaskKind::Decay, Duration::from_secs(10), job);
+        tokio::time::sleep(Duration::from_secs(25)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        assert!(supervisor.health()[&TaskKind::Decay].periodic);
+
+        assert!(supervisor.cancel(TaskKind::Decay));
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        assert!(supervisor.is_healthy());
+    }
+}
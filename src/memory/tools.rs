@@ -0,0 +1,284 @@
+//! OpenAI函数调用工具 - 让模型自己决定何时记住、回忆和调整情感
+//!
+//! `tool_definitions`生成请求中`tools`字段的JSON Schema定义，
+//! `MemoryTools`执行模型返回的`tool_calls`并生成`role: "tool"`的回复消息。
+//! 工具经`MemoryManager`操作用户的记忆系统，情感调整与其他入口一样广播给订阅方。
+
+use super::ingest::IngestRequest;
+use super::manager::MemoryManager;
+use crate::emotion::{EmotionalEngine, EmotionalTrigger};
+use crate::{MemoryError, MemoryType, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+/// 记住一条信息
+pub const REMEMBER_TOOL: &str = "remember";
+/// 回忆相关记忆
+pub const RECALL_TOOL: &str = "recall";
+/// 按触发器调整情感状态
+pub const UPDATE_EMOTION_TOOL: &str = "update_emotion";
+
+/// 一次回忆最多返回的条数，模型给出更大的值时按此截断
+pub const MAX_RECALL_LIMIT: usize = 50;
+
+/// 模型返回的工具调用，与OpenAI `tool_calls`中的元素格式一致
+#[derive(Debug, Clone, Deserialize)]
+pub struct ToolCall {
+    pub id: String,
+    pub function: FunctionCall,
+}
+
+/// 调用的函数名和JSON文本形式的参数
+#[derive(Debug, Clone, Deserialize)]
+pub struct FunctionCall {
+    pub name: String,
+    pub arguments: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RememberArgs {
+    content: String,
+    #[serde(default = "default_memory_type")]
+    memory_type: MemoryType,
+    #[serde(default)]
+    keywords: Vec<String>,
+    #[serde(default = "default_importance")]
+    importance: f32,
+}
+
+fn default_memory_type() -> MemoryType {
+    MemoryType::LongTerm
+}
+
+fn default_importance() -> f32 {
+    0.5
+}
+
+#[derive(Debug, Deserialize)]
+struct RecallArgs {
+    query: String,
+    #[serde(default)]
+    memory_types: Option<Vec<MemoryType>>,
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UpdateEmotionArgs {
+    trigger: EmotionalTrigger,
+    #[serde(default = "default_intensity")]
+    intensity: f32,
+}
+
+fn default_intensity() -> f32 {
+    1.0
+}
+
+/// 枚举值的序列化名称，用作Schema中的`enum`
+fn variant_names<T: serde::Serialize>(values: &[T]) -> Vec<Value> {
+    values.iter().filter_map(|value| serde_json::to_value(value).ok()).collect()
+}
+
+/// 请求中`tools`字段的工具定义
+pub fn tool_definitions() -> Vec<Value> {
+    let memory_types = variant_names(&MemoryType::ALL);
+    let triggers = variant_names(&EmotionalTrigger::ALL);
+
+    vec![
+        json!({
+            "type": "function",
+            "function": {
+                "name": REMEMBER_TOOL,
+                "description": "记住关于用户的一条信息，例如喜好、重要事件或约定",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "content": { "type": "string", "description": "要记住的内容" },
+                        "memory_type": { "type": "string", "enum": memory_types, "description": "记忆类型，默认LongTerm" },
+                        "keywords": { "type": "array", "items": { "type": "string" }, "description": "便于回忆的关键词" },
+                        "importance": { "type": "number", "minimum": 0.0, "maximum": 1.0, "description": "重要性，默认0.5" }
+                    },
+                    "required": ["content"]
+                }
+            }
+        }),
+        json!({
+            "type": "function",
+            "function": {
+                "name": RECALL_TOOL,
+                "description": "回忆与查询相关的记忆",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "query": { "type": "string", "description": "要回忆的内容" },
+                        "memory_types": { "type": "array", "items": { "type": "string", "enum": memory_types }, "description": "只回忆这些类型" },
+                        "limit": { "type": "integer", "minimum": 1, "maximum": MAX_RECALL_LIMIT, "description": "最多返回的条数，默认10" }
+                    },
+                    "required": ["query"]
+                }
+            }
+        }),
+        json!({
+            "type": "function",
+            "function": {
+                "name": UPDATE_EMOTION_TOOL,
+                "description": "根据对话中发生的事情调整自己的情感状态",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "trigger": { "type": "string", "enum": triggers, "description": "发生的事情" },
+                        "intensity": { "type": "number", "minimum": 0.0, "maximum": 1.0, "description": "强度，默认1.0" }
+                    },
+                    "required": ["trigger"]
+                }
+            }
+        }),
+    ]
+}
+
+/// 绑定到单个用户的工具执行器
+#[derive(Debug, Clone)]
+pub struct MemoryTools {
+    manager: Arc<MemoryManager>,
+    user_id: String,
+    engine: Arc<EmotionalEngine>,
+}
+
+impl MemoryTools {
+    pub fn new(manager: Arc<MemoryManager>, user_id: impl Into<String>) -> Self {
+        Self {
+            manager,
+            user_id: user_id.into(),
+            engine: Arc::new(EmotionalEngine::new()),
+        }
+    }
+
+    /// 使用共享的情感引擎
+    pub fn with_engine(mut self, engine: Arc<EmotionalEngine>) -> Self {
+        self.engine = engine;
+        self
+    }
+
+    /// 执行工具，`arguments`为JSON文本
+    pub async fn execute(&self, name: &str, arguments: &str) -> Result<Value> {
+        match name {
+            REMEMBER_TOOL => {
+                let args: RememberArgs = parse_arguments(name, arguments)?;
+                let emotion = self.manager.get_or_create(&self.user_id).await?.get_emotional_state().await;
+                // 经管理器的写入管道保存，模型反复调用时同样受队列容量限制
+                let request = IngestRequest::new(args.memory_type, args.content, args.keywords, args.importance.clamp(0.0, 1.0))
+                    .with_emotional_context(Some(emotion));
+                let id = self.manager.add_memory(&self.user_id, request).await?;
+                Ok(json!({ "id": id }))
+            }
+            RECALL_TOOL => {
+                let args: RecallArgs = parse_arguments(name, arguments)?;
+                let system = self.manager.get_or_create(&self.user_id).await?;
+                let entries = system.retrieve_memories(&args.query, args.memory_types, args.limit.map(|limit| limit.min(MAX_RECALL_LIMIT))).await?;
+                // 只返回模型需要的字段
+                Ok(entries.into_iter()
+                    .map(|entry| json!({
+                        "id": entry.id,
+                        "memory_type": entry.memory_type,
+                        "content": entry.content,
+                        "importance": entry.importance,
+                        "created_at": entry.created_at,
+                    }))
+                    .collect())
+            }
+            UPDATE_EMOTION_TOOL => {
+                let args: UpdateEmotionArgs = parse_arguments(name, arguments)?;
+                let current = self.manager.get_or_create(&self.user_id).await?.get_emotional_state().await;
+                let emotion = self.engine.process_trigger(&current, args.trigger.clone(), args.intensity);
+                self.manager.update_emotion(&self.user_id, Some(args.trigger), emotion.clone()).await?;
+                Ok(serde_json::to_value(emotion)?)
+            }
+            other => Err(MemoryError::InvalidInput(format!("未知的工具: {}", other))),
+        }
+    }
+
+    /// 执行一次工具调用，返回可直接追加到对话中的`tool`消息
+    ///
+    /// 执行失败时以`{"error": "..."}`作为内容返回给模型，而不是中断对话
+    pub async fn dispatch(&self, call: &ToolCall) -> Value {
+        let content = match self.execute(&call.function.name, &call.function.arguments).await {
+            Ok(result) => result,
+            Err(e) => json!({ "error": e.to_string() }),
+        };
+        json!({
+            "role": "tool",
+            "tool_call_id": call.id,
+            "content": content.to_string(),
+        })
+    }
+
+    /// 按顺序执行模型一次返回的全部工具调用
+    pub async fn dispatch_all(&self, calls: &[ToolCall]) -> Vec<Value> {
+        let mut messages = Vec::with_capacity(calls.len());
+        for call in calls {
+            messages.push(self.dispatch(call).await);
+        }
+        messages
+    }
+}
+
+fn parse_arguments<T: for<'de> Deserialize<'de>>(name: &str, arguments: &str) -> Result<T> {
+    // 没有参数时部分模型返回空字符串
+    let arguments = if arguments.trim().is_empty() { "{}" } else { arguments };
+    serde_json::from_str(arguments)
+        .map_err(|e| MemoryError::InvalidInput(format!("工具 {} 的参数无效: {}", name, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::MockVectorStore;
+
+    fn call(id: &str, name: &str, arguments: Value) -> ToolCall {
+        ToolCall {
+            id: id.to_string(),
+            function: FunctionCall { name: name.to_string(), arguments: arguments.to_string() },
+        }
+    }
+
+    #[test]
+    fn test_tool_definitions_list_enums() {
+        let definitions = tool_definitions();
+        let names: Vec<_> = definitions.iter().map(|tool| tool["function"]["name"].as_str().unwrap()).collect();
+        assert_eq!(names, vec![REMEMBER_TOOL, RECALL_TOOL, UPDATE_EMOTION_TOOL]);
+        assert!(definitions[2]["function"]["parameters"]["properties"]["trigger"]["enum"]
+            .as_array().unwrap()
+            .contains(&json!("BeingPraised")));
+        assert_eq!(definitions[1]["function"]["parameters"]["properties"]["limit"]["maximum"], MAX_RECALL_LIMIT);
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_drives_memory_system() {
+        let manager = Arc::new(MemoryManager::new(Arc::new(MockVectorStore::new()), None));
+        let mut emotions = manager.subscribe_emotions();
+        let tools = MemoryTools::new(manager.clone(), "alice");
+
+        let messages = tools.dispatch_all(&[
+            call("call_1", REMEMBER_TOOL, json!({ "content": "用户喜欢猫咪", "memory_type": "Preference" })),
+            call("call_2", RECALL_TOOL, json!({ "query": "用户喜欢猫咪" })),
+            call("call_3", UPDATE_EMOTION_TOOL, json!({ "trigger": "BeingPraised" })),
+            call("call_4", "forget", json!({})),
+            call("call_5", RECALL_TOOL, json!({ "query": "猫咪", "limit": usize::MAX })),
+        ]).await;
+
+        assert_eq!(messages[0]["tool_call_id"], "call_1");
+        let recalled: Value = serde_json::from_str(messages[1]["content"].as_str().unwrap()).unwrap();
+        assert_eq!(recalled[0]["content"], "用户喜欢猫咪");
+        let system = manager.get("alice").unwrap();
+        assert!(system.get_emotional_state().await.happiness > crate::EmotionalState::default().happiness);
+        // 情感调整经管理器广播
+        let change = emotions.try_recv().unwrap();
+        assert_eq!((change.user_id.as_str(), change.trigger), ("alice", Some(EmotionalTrigger::BeingPraised)));
+        let error: Value = serde_json::from_str(messages[3]["content"].as_str().unwrap()).unwrap();
+        assert!(error["error"].as_str().unwrap().contains("forget"));
+        // 模型给出的条数按上限截断
+        let recalled: Value = serde_json::from_str(messages[4]["content"].as_str().unwrap()).unwrap();
+        assert!(recalled.as_array().is_some_and(|entries| entries.len() <= MAX_RECALL_LIMIT));
+    }
+}
@@ -0,0 +1,305 @@
+//! 插件 - 第三方扩展互动分析、回复修饰和记忆后处理，无需修改本crate
+//!
+//! 插件在编译期通过`PluginRegistry::register_*`注册，或启用`dynamic-plugins`特性后从动态库加载。
+//! 动态库需用`declare_plugin!`导出注册函数，通过`#[repr(C)]`的`PluginRegistrar`回调注册插件，
+//! 主程序的注册表不跨越动态库边界。插件对象是trait对象，动态库仍须与主程序使用同一版本的rustc
+//! 和mira编译，加载时只校验`PLUGIN_API_VERSION`。
+
+use crate::emotion::EmotionalTrigger;
+use crate::{EmotionalState, MemoryEntry};
+use std::ffi::c_void;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// 插件接口版本，接口有不兼容修改时递增
+///
+/// 2: 注册函数改为接收`PluginRegistrar`
+pub const PLUGIN_API_VERSION: u32 = 2;
+
+/// 互动分析器 - 在内置词表分析之外产生情感触发
+pub trait InteractionAnalyzer: Send + Sync + Debug {
+    fn name(&self) -> &str;
+
+    /// 分析用户输入和相关记忆，返回触发器及强度
+    fn analyze(&self, user_input: &str, memories: &[Arc<MemoryEntry>]) -> Vec<(EmotionalTrigger, f32)>;
+}
+
+/// 回复修饰器 - 回复生成后、发送前按注册顺序依次调用
+pub trait ResponseDecorator: Send + Sync + Debug {
+    fn name(&self) -> &str;
+
+    fn decorate(&self, response: String, state: &EmotionalState) -> String;
+}
+
+/// 记忆后处理器 - 记忆写入存储前调用，可修改关键词、重要性和元数据
+///
+/// 此时嵌入已经生成，修改内容不会重新生成嵌入。
+pub trait MemoryPostProcessor: Send + Sync + Debug {
+    fn name(&self) -> &str;
+
+    fn process(&self, entry: &mut MemoryEntry);
+}
+
+/// 插件注册表
+#[derive(Debug, Clone, Default)]
+pub struct PluginRegistry {
+    analyzers: Vec<Arc<dyn InteractionAnalyzer>>,
+    decorators: Vec<Arc<dyn ResponseDecorator>>,
+    post_processors: Vec<Arc<dyn MemoryPostProcessor>>,
+    /// 已加载的动态库，放在最后以便在插件对象之后释放
+    #[cfg(feature = "dynamic-plugins")]
+    libraries: Vec<Arc<libloading::Library>>,
+}
+
+impl PluginRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register_analyzer(&mut self, analyzer: Arc<dyn InteractionAnalyzer>) -> &mut Self {
+        self.analyzers.push(analyzer);
+        self
+    }
+
+    pub fn register_decorator(&mut self, decorator: Arc<dyn ResponseDecorator>) -> &mut Self {
+        self.decorators.push(decorator);
+        self
+    }
+
+    pub fn register_post_processor(&mut self, processor: Arc<dyn MemoryPostProcessor>) -> &mut Self {
+        self.post_processors.push(processor);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.analyzers.is_empty() && self.decorators.is_empty() && self.post_processors.is_empty()
+    }
+
+    /// 已注册插件的名称
+    pub fn names(&self) -> Vec<&str> {
+        self.analyzers.iter().map(|plugin| plugin.name())
+            .chain(self.decorators.iter().map(|plugin| plugin.name()))
+            .chain(self.post_processors.iter().map(|plugin| plugin.name()))
+            .collect()
+    }
+
+    /// 全部分析器的结果，同一触发器的强度不合并
+    pub fn analyze(&self, user_input: &str, memories: &[Arc<MemoryEntry>]) -> Vec<(EmotionalTrigger, f32)> {
+        self.analyzers.iter()
+            .flat_map(|analyzer| analyzer.analyze(user_input, memories))
+            .collect()
+    }
+
+    pub fn decorate(&self, response: String, state: &EmotionalState) -> String {
+        self.decorators.iter().fold(response, |response, decorator| decorator.decorate(response, state))
+    }
+
+    pub fn post_process(&self, entry: &mut MemoryEntry) {
+        for processor in &self.post_processors {
+            processor.process(entry);
+        }
+    }
+
+    /// 从`paths`依次加载动态库，见`load_library`
+    ///
+    /// # Safety
+    ///
+    /// 同`load_library`
+    #[cfg(feature = "dynamic-plugins")]
+    pub unsafe fn load_libraries<P: AsRef<std::ffi::OsStr>>(&mut self, paths: &[P]) -> crate::Result<()> {
+        for path in paths {
+            // SAFETY: 由调用方保证
+            unsafe { self.load_library(path)? };
+        }
+        Ok(())
+    }
+
+    /// 从动态库加载插件，库需由`declare_plugin!`导出注册函数
+    ///
+    /// # Safety
+    ///
+    /// 动态库的初始化代码和注册函数会被执行，调用方需确保库可信，
+    /// 且与主程序使用同一版本的rustc和mira编译。
+    #[cfg(feature = "dynamic-plugins")]
+    pub unsafe fn load_library(&mut self, path: impl AsRef<std::ffi::OsStr>) -> crate::Result<()> {
+        use crate::MemoryError;
+
+        let path = path.as_ref();
+        let error = |e: libloading::Error| MemoryError::ConfigError(format!("加载插件 {:?} 失败: {}", path, e));
+        // SAFETY: 由调用方保证动态库可信且ABI一致
+        unsafe {
+            let library = libloading::Library::new(path).map_err(error)?;
+            let version: libloading::Symbol<extern "C" fn() -> u32> = library.get(b"mira_plugin_api_version").map_err(error)?;
+            if version() != PLUGIN_API_VERSION {
+                return Err(MemoryError::ConfigError(format!(
+                    "插件 {:?} 的接口版本为 {}，需要 {}", path, version(), PLUGIN_API_VERSION,
+                )));
+            }
+            let register: libloading::Symbol<unsafe extern "C" fn(*mut PluginRegistrar)> =
+                library.get(b"mira_register_plugins").map_err(error)?;
+            let mut registrar = PluginRegistrar::new(self);
+            register(&mut registrar);
+            self.libraries.push(Arc::new(library));
+        }
+        tracing::info!("已加载插件库 {:?}", path);
+        Ok(())
+    }
+}
+
+/// 动态库注册插件的回调 - 只含C ABI函数指针和主程序注册表的不透明指针
+///
+/// 注册函数由主程序编译，插件侧只按`#[repr(C)]`布局调用，不依赖`PluginRegistry`的内存布局。
+#[repr(C)]
+#[derive(Debug)]
+pub struct PluginRegistrar {
+    registry: *mut c_void,
+    analyzer: unsafe extern "C" fn(*mut c_void, *const Arc<dyn InteractionAnalyzer>),
+    decorator: unsafe extern "C" fn(*mut c_void, *const Arc<dyn ResponseDecorator>),
+    post_processor: unsafe extern "C" fn(*mut c_void, *const Arc<dyn MemoryPostProcessor>),
+}
+
+impl PluginRegistrar {
+    /// 注册到`registry`，只在`registry`的借用期间使用
+    #[cfg_attr(not(any(test, feature = "dynamic-plugins")), allow(dead_code))]
+    fn new(registry: &mut PluginRegistry) -> Self {
+        // SAFETY: 以下回调只由本结构体调用，registry在注册期间有效，插件指针在调用期间有效
+        unsafe extern "C" fn analyzer(registry: *mut c_void, plugin: *const Arc<dyn InteractionAnalyzer>) {
+            unsafe { (*registry.cast::<PluginRegistry>()).analyzers.push((*plugin).clone()) };
+        }
+        unsafe extern "C" fn decorator(registry: *mut c_void, plugin: *const Arc<dyn ResponseDecorator>) {
+            unsafe { (*registry.cast::<PluginRegistry>()).decorators.push((*plugin).clone()) };
+        }
+        unsafe extern "C" fn post_processor(registry: *mut c_void, plugin: *const Arc<dyn MemoryPostProcessor>) {
+            unsafe { (*registry.cast::<PluginRegistry>()).post_processors.push((*plugin).clone()) };
+        }
+
+        Self {
+            registry: (registry as *mut PluginRegistry).cast(),
+            analyzer,
+            decorator,
+            post_processor,
+        }
+    }
+
+    pub fn register_analyzer(&mut self, analyzer: Arc<dyn InteractionAnalyzer>) -> &mut Self {
+        // SAFETY: 回调和registry由主程序在注册期间提供
+        unsafe { (self.analyzer)(self.registry, &analyzer) };
+        self
+    }
+
+    pub fn register_decorator(&mut self, decorator: Arc<dyn ResponseDecorator>) -> &mut Self {
+        // SAFETY: 同上
+        unsafe { (self.decorator)(self.registry, &decorator) };
+        self
+    }
+
+    pub fn register_post_processor(&mut self, processor: Arc<dyn MemoryPostProcessor>) -> &mut Self {
+        // SAFETY: 同上
+        unsafe { (self.post_processor)(self.registry, &processor) };
+        self
+    }
+}
+
+/// 在插件动态库中导出注册函数
+///
+/// ```ignore
+/// fn register(registrar: &mut mira::plugins::PluginRegistrar) {
+///     registrar.register_decorator(std::sync::Arc::new(MyDecorator));
+/// }
+/// mira::declare_plugin!(register);
+/// ```
+#[macro_export]
+macro_rules! declare_plugin {
+    ($register:path) => {
+        #[unsafe(no_mangle)]
+        pub extern "C" fn mira_plugin_api_version() -> u32 {
+            $crate::plugins::PLUGIN_API_VERSION
+        }
+
+        /// # Safety
+        ///
+        /// 只由主程序在加载插件时调用
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn mira_register_plugins(registrar: *mut $crate::plugins::PluginRegistrar) {
+            // SAFETY: 主程序传入注册期间有效的回调
+            if let Some(registrar) = unsafe { registrar.as_mut() } {
+                $register(registrar)
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryType;
+
+    #[derive(Debug)]
+    struct Sparkle;
+
+    impl ResponseDecorator for Sparkle {
+        fn name(&self) -> &str {
+            "sparkle"
+        }
+
+        fn decorate(&self, response: String, state: &EmotionalState) -> String {
+            if state.happiness > 0.5 { format!("{} ✨", response) } else { response }
+        }
+    }
+
+    #[derive(Debug)]
+    struct CatLover;
+
+    impl InteractionAnalyzer for CatLover {
+        fn name(&self) -> &str {
+            "cat_lover"
+        }
+
+        fn analyze(&self, user_input: &str, _memories: &[Arc<MemoryEntry>]) -> Vec<(EmotionalTrigger, f32)> {
+            if user_input.contains("猫") { vec![(EmotionalTrigger::UserHappiness, 0.5)] } else { Vec::new() }
+        }
+    }
+
+    #[derive(Debug)]
+    struct Tagger;
+
+    impl MemoryPostProcessor for Tagger {
+        fn name(&self) -> &str {
+            "tagger"
+        }
+
+        fn process(&self, entry: &mut MemoryEntry) {
+            entry.metadata.insert("tagged".to_string(), "true".to_string());
+        }
+    }
+
+    #[test]
+    fn test_registry_runs_plugins_in_order() {
+        let mut registry = PluginRegistry::new();
+        assert!(registry.is_empty());
+        registry.register_analyzer(Arc::new(CatLover))
+            .register_decorator(Arc::new(Sparkle))
+            .register_decorator(Arc::new(Sparkle))
+            .register_post_processor(Arc::new(Tagger));
+        assert_eq!(registry.names(), vec!["cat_lover", "sparkle", "sparkle", "tagger"]);
+
+        assert_eq!(registry.analyze("我的猫", &[]), vec![(EmotionalTrigger::UserHappiness, 0.5)]);
+        assert!(registry.analyze("你好", &[]).is_empty());
+
+        let happy = EmotionalState { happiness: 0.8, ..EmotionalState::default() };
+        assert_eq!(registry.decorate("早安".to_string(), &happy), "早安 ✨ ✨");
+
+        let mut entry = MemoryEntry::new(MemoryType::ShortTerm, "内容".to_string(), vec![], 0.5);
+        registry.post_process(&mut entry);
+        assert_eq!(entry.metadata["tagged"], "true");
+    }
+
+    #[test]
+    fn test_registrar_registers_through_callbacks() {
+        let mut registry = PluginRegistry::new();
+        PluginRegistrar::new(&mut registry)
+            .register_analyzer(Arc::new(CatLover))
+            .register_post_processor(Arc::new(Tagger));
+        assert_eq!(registry.names(), vec!["cat_lover", "tagger"]);
+    }
+}
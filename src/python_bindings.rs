@@ -0,0 +1,350 @@
+//! Python绑定 - 通过pyo3-async-runtimes把记忆系统的异步接口暴露为Python awaitable
+//!
+//! ```python
+//! memory = await PyMemorySystem.create("user_1")
+//! memory_id = await memory.add_memory("preference", "用户喜欢咖啡", ["咖啡"], 0.8)
+//! entries = await memory.retrieve_memories("喝什么", limit=5)
+//! ```
+//!
+//! 嵌入向量以float32 numpy数组传递：传入时直接读取数组缓冲区，返回时整块移交给numpy
+
+use crate::config::MiraConfig;
+use crate::vector_store::open_store;
+use crate::{EmotionalState, MemoryEntry, MemoryError, MemorySystem, MemoryType};
+use numpy::{PyArray1, PyReadonlyArray1};
+use pyo3::exceptions::{PyKeyError, PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3_async_runtimes::tokio::future_into_py;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+impl From<MemoryError> for PyErr {
+    fn from(error: MemoryError) -> Self {
+        match error {
+            MemoryError::NotFound { .. } => PyKeyError::new_err(error.to_string()),
+            MemoryError::InvalidInput(_)
+            | MemoryError::DimensionMismatch { .. }
+            | MemoryError::FfiInvariant { .. } => PyValueError::new_err(error.to_string()),
+            other => PyRuntimeError::new_err(other.to_string()),
+        }
+    }
+}
+
+fn parse_memory_type(name: &str) -> PyResult<MemoryType> {
+    MemoryType::parse(name).ok_or_else(|| {
+        let known: Vec<_> = MemoryType::ALL.iter().map(MemoryType::as_str).collect();
+        PyValueError::new_err(format!("未知的记忆类型 {:?}，可选: {}", name, known.join(", ")))
+    })
+}
+
+/// 读取numpy嵌入 - 连续数组整块拷贝，非连续数组（如切片视图）按步长读取
+fn embedding_from_numpy(embedding: &PyReadonlyArray1<'_, f32>) -> Vec<f32> {
+    embedding.as_slice()
+        .map(<[f32]>::to_vec)
+        .unwrap_or_else(|_| embedding.as_array().to_vec())
+}
+
+#[pyclass]
+#[derive(Clone)]
+pub struct PyMemoryEntry {
+    #[pyo3(get)]
+    pub id: String,
+    #[pyo3(get)]
+    pub memory_type: String,
+    #[pyo3(get, set)]
+    pub content: String,
+    #[pyo3(get, set)]
+    pub importance: f32,
+    #[pyo3(get, set)]
+    pub keywords: Vec<String>,
+    /// 创建时间（RFC 3339）
+    #[pyo3(get)]
+    pub created_at: String,
+    #[pyo3(get)]
+    pub access_count: u32,
+    pub embedding: Option<Vec<f32>>,
+}
+
+#[pymethods]
+impl PyMemoryEntry {
+    #[new]
+    pub fn new(content: String, importance: f32, keywords: Vec<String>) -> Self {
+        MemoryEntry::new(MemoryType::ShortTerm, content, keywords, importance).into()
+    }
+
+    /// 记忆的嵌入向量（float32 numpy数组），未生成嵌入时为None；检索结果不带嵌入，用`get_embedding`读取
+    #[getter]
+    fn embedding<'py>(&self, py: Python<'py>) -> Option<Bound<'py, PyArray1<f32>>> {
+        self.embedding.clone().map(|embedding| PyArray1::from_vec(py, embedding))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("PyMemoryEntry(id={:?}, memory_type={:?}, content={:?})", self.id, self.memory_type, self.content)
+    }
+}
+
+impl From<MemoryEntry> for PyMemoryEntry {
+    fn from(entry: MemoryEntry) -> Self {
+        Self {
+            id: entry.id.to_string(),
+            memory_type: entry.memory_type.as_str().to_string(),
+            content: entry.content,
+            importance: entry.importance,
+            keywords: entry.keywords,
+            created_at: entry.created_at.to_rfc3339(),
+            access_count: entry.access_count,
+            embedding: entry.embedding,
+        }
+    }
+}
+
+#[pyclass]
+#[derive(Clone)]
+pub struct PyEmotionalState {
+    #[pyo3(get, set)]
+    pub happiness: f32,
+    #[pyo3(get, set)]
+    pub affection: f32,
+    #[pyo3(get, set)]
+    pub trust: f32,
+    #[pyo3(get, set)]
+    pub dependency: f32,
+    #[pyo3(get, set)]
+    pub mood: String,
+}
+
+#[pymethods]
+impl PyEmotionalState {
+    #[new]
+    #[pyo3(signature = (happiness=None, affection=None, trust=None, dependency=None, mood=None))]
+    pub fn new(
+        happiness: Option<f32>,
+        affection: Option<f32>,
+        trust: Option<f32>,
+        dependency: Option<f32>,
+        mood: Option<String>,
+    ) -> Self {
+        let default = Self::from(EmotionalState::default());
+        Self {
+            happiness: happiness.unwrap_or(default.happiness),
+            affection: affection.unwrap_or(default.affection),
+            trust: trust.unwrap_or(default.trust),
+            dependency: dependency.unwrap_or(default.dependency),
+            mood: mood.unwrap_or(default.mood),
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "PyEmotionalState(happiness={}, affection={}, trust={}, dependency={}, mood={:?})",
+            self.happiness, self.affection, self.trust, self.dependency, self.mood
+        )
+    }
+}
+
+impl From<EmotionalState> for PyEmotionalState {
+    fn from(state: EmotionalState) -> Self {
+        Self {
+            happiness: state.happiness,
+            affection: state.affection,
+            trust: state.trust,
+            dependency: state.dependency,
+            mood: state.mood,
+        }
+    }
+}
+
+impl From<PyEmotionalState> for EmotionalState {
+    fn from(state: PyEmotionalState) -> Self {
+        Self {
+            happiness: state.happiness.clamp(0.0, 1.0),
+            affection: state.affection.clamp(0.0, 1.0),
+            trust: state.trust.clamp(0.0, 1.0),
+            dependency: state.dependency.clamp(0.0, 1.0),
+            mood: state.mood,
+            timestamp: chrono::Utc::now(),
+        }
+    }
+}
+
+/// 记忆系统 - 所有方法返回可在asyncio中await的对象，运行在共享的tokio运行时上
+#[pyclass]
+pub struct PyMemorySystem {
+    inner: Arc<MemorySystem>,
+}
+
+#[pymethods]
+impl PyMemorySystem {
+    /// 创建记忆系统，未指定`qdrant_url`时使用进程内向量存储
+    ///
+    /// `config_path`中的`[memory]`、`[embedder]`和`[inference]`决定记忆配置和嵌入生成器，
+    /// 未指定时使用默认记忆配置和本地哈希嵌入
+    #[staticmethod]
+    #[pyo3(signature = (user_id, qdrant_url=None, collection=None, config_path=None))]
+    fn create(
+        py: Python<'_>,
+        user_id: String,
+        qdrant_url: Option<String>,
+        collection: Option<String>,
+        config_path: Option<std::path::PathBuf>,
+    ) -> PyResult<Bound<'_, PyAny>> {
+        future_into_py(py, async move {
+            let config = match config_path {
+                Some(ref path) => MiraConfig::load(Some(path))?,
+                None => MiraConfig::default(),
+            };
+            let embedder = config.embedder()?;
+            let vector_store = open_store(qdrant_url.as_deref(), collection, embedder.dimension()).await
+                .map_err(|e| MemoryError::VectorStoreError { message: e.to_string() })?;
+            let system = MemorySystem::new_with_embedder(user_id, vector_store, Some(config.memory), embedder).await?;
+            Ok(Self { inner: Arc::new(system) })
+        })
+    }
+
+    /// 添加记忆，返回记忆ID；传入`embedding`（float32 numpy数组）时不再生成嵌入
+    #[pyo3(signature = (memory_type, content, keywords=None, importance=0.5, emotional_context=None, embedding=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn add_memory<'py>(
+        &self,
+        py: Python<'py>,
+        memory_type: &str,
+        content: String,
+        keywords: Option<Vec<String>>,
+        importance: f32,
+        emotional_context: Option<PyEmotionalState>,
+        embedding: Option<PyReadonlyArray1<'py, f32>>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let memory_type = parse_memory_type(memory_type)?;
+        // 数组只能在持有GIL时读取，进入异步任务前转换
+        let embedding = embedding.as_ref().map(embedding_from_numpy);
+        let system = self.inner.clone();
+        future_into_py(py, async move {
+            let keywords = keywords.unwrap_or_default();
+            let emotional_context = emotional_context.map(EmotionalState::from);
+            let id = match embedding {
+                Some(embedding) => system.add_memory_with_embedding(
+                    memory_type, content, keywords, importance, emotional_context, embedding,
+                ).await?,
+                None => system.add_memory(memory_type, content, keywords, importance, emotional_context).await?,
+            };
+            Ok(id.to_string())
+        })
+    }
+
+    /// 检索相关记忆
+    #[pyo3(signature = (query, memory_types=None, limit=None))]
+    fn retrieve_memories<'py>(
+        &self,
+        py: Python<'py>,
+        query: String,
+        memory_types: Option<Vec<String>>,
+        limit: Option<usize>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let memory_types = memory_types
+            .map(|types| types.iter().map(|name| parse_memory_type(name)).collect::<PyResult<Vec<_>>>())
+            .transpose()?;
+        let system = self.inner.clone();
+        future_into_py(py, async move {
+            let entries = system.retrieve_memories(&query, memory_types, limit).await?;
+            Ok(entries.into_iter().map(|entry| PyMemoryEntry::from(Arc::unwrap_or_clone(entry))).collect::<Vec<_>>())
+        })
+    }
+
+    /// 使用调用方计算好的查询向量（float32 numpy数组）检索相关记忆
+    #[pyo3(signature = (embedding, memory_types=None, limit=None))]
+    fn retrieve_by_embedding<'py>(
+        &self,
+        py: Python<'py>,
+        embedding: PyReadonlyArray1<'py, f32>,
+        memory_types: Option<Vec<String>>,
+        limit: Option<usize>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let memory_types = memory_types
+            .map(|types| types.iter().map(|name| parse_memory_type(name)).collect::<PyResult<Vec<_>>>())
+            .transpose()?;
+        let embedding = embedding_from_numpy(&embedding);
+        let system = self.inner.clone();
+        future_into_py(py, async move {
+            let entries = system.retrieve_by_embedding(embedding, memory_types, limit).await?;
+            Ok(entries.into_iter().map(|entry| PyMemoryEntry::from(Arc::unwrap_or_clone(entry))).collect::<Vec<_>>())
+        })
+    }
+
+    /// 记忆的嵌入向量（float32 numpy数组），检索结果不带嵌入，需要时按ID读取；没有嵌入时为None
+    fn get_embedding<'py>(&self, py: Python<'py>, memory_id: String) -> PyResult<Bound<'py, PyAny>> {
+        let id = memory_id.parse().map_err(|e| PyValueError::new_err(format!("无效的记忆ID {:?}: {}", memory_id, e)))?;
+        let system = self.inner.clone();
+        future_into_py(py, async move {
+            let embedding = system.memory_embedding(id).await?;
+            Ok(embedding.map(|embedding| {
+                Python::with_gil(|py| PyArray1::from_vec(py, Arc::unwrap_or_clone(embedding)).unbind())
+            }))
+        })
+    }
+
+    /// 按记忆类型统计缓存中的条数，"total"为总数
+    fn get_stats<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let system = self.inner.clone();
+        future_into_py(py, async move {
+            let stats: HashMap<String, u64> = system.get_memory_stats().await;
+            Ok(stats)
+        })
+    }
+
+    /// 当前情感状态
+    fn get_emotional_state<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let system = self.inner.clone();
+        future_into_py(py, async move {
+            Ok(PyEmotionalState::from(system.get_emotional_state().await))
+        })
+    }
+
+    /// 更新情感状态，各维度截断到0-1
+    fn set_emotional_state<'py>(&self, py: Python<'py>, state: PyEmotionalState) -> PyResult<Bound<'py, PyAny>> {
+        let system = self.inner.clone();
+        future_into_py(py, async move {
+            system.update_emotional_state(state.into()).await;
+            Ok(())
+        })
+    }
+
+    /// 当前用户ID
+    #[getter]
+    fn user_id(&self) -> String {
+        self.inner.user_id().to_string()
+    }
+}
+
+#[pyfunction]
+pub fn create_memory_entry(content: String, importance: f32) -> PyResult<PyMemoryEntry> {
+    Ok(PyMemoryEntry::new(content, importance, vec![]))
+}
+
+#[pymodule]
+fn ai_girlfriend_memory(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyMemoryEntry>()?;
+    m.add_class::<PyEmotionalState>()?;
+    m.add_class::<PyMemorySystem>()?;
+    m.add_function(wrap_pyfunction!(create_memory_entry, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions_round_trip() {
+        for memory_type in MemoryType::ALL {
+            assert_eq!(parse_memory_type(memory_type.as_str()).unwrap(), memory_type);
+        }
+        assert!(parse_memory_type("LongTerm").is_err());
+
+        let state = PyEmotionalState::new(Some(1.5), None, None, None, Some("开心".to_string()));
+        let state = EmotionalState::from(state);
+        assert_eq!((state.happiness, state.mood.as_str()), (1.0, "开心"));
+
+        let entry = PyMemoryEntry::from(MemoryEntry::new(MemoryType::Preference, "咖啡".to_string(), vec![], 0.7));
+        assert_eq!((entry.memory_type.as_str(), entry.importance), ("preference", 0.7));
+    }
+}
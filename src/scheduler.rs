@@ -0,0 +1,441 @@
+//! 定时任务 - 早安问候、提醒、记忆复习和过期清理等按cron表达式或固定间隔执行的任务
+//!
+//! 任务保存在与记忆分开的存储中（见`MiraConfig::scheduler_store`），重启后重新载入；
+//! 停机期间错过的任务在启动后补执行一次，再从当前时间计算下次执行时间。
+//! 单个任务执行或保存失败只记录日志，不影响同一轮的其他任务；Webhook在后台投递。
+//! cron表达式为6段（秒 分 时 日 月 周），按`utc_offset_minutes`指定的时区解释。
+
+use crate::memory::MemoryManager;
+use crate::vector_store::VectorStore;
+use crate::webhook::{WebhookDispatcher, WebhookEvent};
+use crate::{MemoryError, MemoryType, Result};
+use chrono::{DateTime, Duration, FixedOffset, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+/// 任务存储的向量维度，任务不参与相似度搜索
+pub const JOB_VECTOR_SIZE: usize = 1;
+
+/// Qdrant中任务集合名称的后缀，接在记忆集合名称之后
+pub const SCHEDULER_COLLECTION_SUFFIX: &str = "_scheduler";
+
+/// 任务事件的缓冲条数
+pub const SCHEDULER_CHANNEL_CAPACITY: usize = 64;
+
+/// 载入任务时每页读取的点数
+const SCROLL_PAGE_SIZE: usize = 256;
+
+/// 复习时每条记忆提升的重要性
+pub const REHEARSAL_BOOST: f32 = 0.05;
+
+/// 执行时间规则
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JobSchedule {
+    /// cron表达式
+    Cron {
+        expression: String,
+        /// 表达式所用时区相对UTC的偏移（分钟）
+        #[serde(default)]
+        utc_offset_minutes: i32,
+    },
+    /// 固定间隔（秒）
+    Interval { seconds: u64 },
+    /// 只执行一次
+    Once { at: DateTime<Utc> },
+}
+
+impl JobSchedule {
+    /// 检查cron表达式、间隔和时区偏移
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Self::Cron { expression, utc_offset_minutes } => {
+                cron::Schedule::from_str(expression)
+                    .map_err(|e| MemoryError::ConfigError(format!("无效的cron表达式 {:?}: {}", expression, e)))?;
+                Self::offset(*utc_offset_minutes)?;
+            }
+            Self::Interval { seconds: 0 } => {
+                return Err(MemoryError::ConfigError("任务间隔必须大于0秒".to_string()));
+            }
+            Self::Interval { .. } | Self::Once { .. } => {}
+        }
+        Ok(())
+    }
+
+    fn offset(minutes: i32) -> Result<FixedOffset> {
+        FixedOffset::east_opt(minutes * 60)
+            .ok_or_else(|| MemoryError::ConfigError(format!("无效的时区偏移: {}分钟", minutes)))
+    }
+
+    /// `after`之后的下一次执行时间，一次性任务已过期时为None
+    pub fn next_after(&self, after: DateTime<Utc>) -> Result<Option<DateTime<Utc>>> {
+        match self {
+            Self::Cron { expression, utc_offset_minutes } => {
+                let schedule = cron::Schedule::from_str(expression)
+                    .map_err(|e| MemoryError::ConfigError(format!("无效的cron表达式 {:?}: {}", expression, e)))?;
+                let local = after.with_timezone(&Self::offset(*utc_offset_minutes)?);
+                Ok(schedule.after(&local).next().map(|next| next.with_timezone(&Utc)))
+            }
+            Self::Interval { seconds } => Ok(Some(after + Duration::seconds(*seconds as i64))),
+            Self::Once { at } => Ok((*at > after).then_some(*at)),
+        }
+    }
+}
+
+/// 任务内容
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JobAction {
+    /// 主动向用户发送消息，例如早安问候
+    Message { user_id: String, text: String },
+    /// 提醒到期，同时投递`reminder_due` Webhook
+    Reminder { user_id: String, content: String },
+    /// 复习最久未被想起的长期记忆，提升重要性以抵消遗忘
+    Rehearsal { user_id: String, limit: usize },
+    /// 删除已过期的记忆，未指定用户时处理所有已载入的用户
+    PurgeExpired { user_id: Option<String> },
+}
+
+/// 持久化的定时任务
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledJob {
+    pub id: Uuid,
+    /// 任务名，`ensure_job`按名称去重
+    pub name: String,
+    pub schedule: JobSchedule,
+    pub action: JobAction,
+    /// 下次执行时间，None表示不再执行
+    pub next_run: Option<DateTime<Utc>>,
+    pub last_run: Option<DateTime<Utc>>,
+    pub enabled: bool,
+}
+
+impl ScheduledJob {
+    /// 创建任务，从当前时间计算首次执行时间
+    pub fn new(name: impl Into<String>, schedule: JobSchedule, action: JobAction) -> Result<Self> {
+        schedule.validate()?;
+        let next_run = match &schedule {
+            // 一次性任务即使时间已过也执行一次
+            JobSchedule::Once { at } => Some(*at),
+            _ => schedule.next_after(Utc::now())?,
+        };
+        Ok(Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            schedule,
+            action,
+            next_run,
+            last_run: None,
+            enabled: true,
+        })
+    }
+
+    /// 在`now`时是否应执行
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.enabled && self.next_run.is_some_and(|next| next <= now)
+    }
+}
+
+/// 任务执行产生的事件，由对话或机器人层订阅后发送给用户
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchedulerEvent {
+    /// 需要发送给用户的主动消息
+    Message { job_id: Uuid, user_id: String, text: String },
+    /// 提醒到期
+    Reminder { job_id: Uuid, user_id: String, content: String, due_at: DateTime<Utc> },
+}
+
+/// 定时任务配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SchedulerConfig {
+    /// 默认关闭
+    pub enabled: bool,
+    /// 检查到期任务的间隔（秒）
+    pub tick_seconds: u64,
+    /// 清理过期记忆的执行时间，None表示不自动维护
+    pub maintenance: Option<JobSchedule>,
+    /// 未使用Qdrant时保存任务的文件，None时任务只保存在内存中
+    pub jobs_file: Option<PathBuf>,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            tick_seconds: 30,
+            maintenance: Some(JobSchedule::Cron {
+                expression: "0 0 4 * * *".to_string(),
+                utc_offset_minutes: 0,
+            }),
+            jobs_file: None,
+        }
+    }
+}
+
+/// 定时任务调度器
+#[derive(Debug)]
+pub struct Scheduler {
+    manager: Arc<MemoryManager>,
+    store: Arc<dyn VectorStore<Error = anyhow::Error>>,
+    jobs: DashMap<Uuid, ScheduledJob>,
+    webhooks: Option<WebhookDispatcher>,
+    events: broadcast::Sender<SchedulerEvent>,
+}
+
+impl Scheduler {
+    /// 创建调度器并从`store`载入已保存的任务，`store`只用于保存任务，不应与记忆共用
+    pub async fn new(manager: Arc<MemoryManager>, store: Arc<dyn VectorStore<Error = anyhow::Error>>) -> Result<Self> {
+        let jobs = DashMap::new();
+
+        let mut offset = None;
+        loop {
+            let page = store.scroll(offset, SCROLL_PAGE_SIZE).await.map_err(Self::store_error)?;
+            for point in page.points {
+                match serde_json::from_value::<ScheduledJob>(point.payload) {
+                    Ok(job) => {
+                        jobs.insert(job.id, job);
+                    }
+                    Err(e) => tracing::warn!("跳过无法解析的定时任务 {}: {}", point.id, e),
+                }
+            }
+            match page.next_offset {
+                Some(next) => offset = Some(next),
+                None => break,
+            }
+        }
+        tracing::info!("载入 {} 个定时任务", jobs.len());
+
+        Ok(Self {
+            manager,
+            store,
+            jobs,
+            webhooks: None,
+            events: broadcast::channel(SCHEDULER_CHANNEL_CAPACITY).0,
+        })
+    }
+
+    /// 提醒到期时投递Webhook
+    pub fn with_webhooks(mut self, webhooks: WebhookDispatcher) -> Self {
+        self.webhooks = Some(webhooks);
+        self
+    }
+
+    /// 订阅任务事件
+    pub fn subscribe(&self) -> broadcast::Receiver<SchedulerEvent> {
+        self.events.subscribe()
+    }
+
+    fn store_error(error: anyhow::Error) -> MemoryError {
+        MemoryError::VectorStoreError { message: error.to_string() }
+    }
+
+    async fn persist(&self, job: &ScheduledJob) -> Result<()> {
+        // 任务不参与相似度搜索，只需要满足存储的维度
+        let mut embedding = vec![0.0; self.store.vector_size().unwrap_or(JOB_VECTOR_SIZE)];
+        if let Some(first) = embedding.first_mut() {
+            *first = 1.0;
+        }
+        self.store.store_vector(job.id, embedding, serde_json::to_string(job)?).await
+            .map_err(Self::store_error)
+    }
+
+    /// 保存任务，ID已存在时替换
+    pub async fn add_job(&self, job: ScheduledJob) -> Result<Uuid> {
+        job.schedule.validate()?;
+        self.persist(&job).await?;
+        let id = job.id;
+        self.jobs.insert(id, job);
+        Ok(id)
+    }
+
+    /// 没有同名任务时保存，返回是否新增
+    pub async fn ensure_job(&self, job: ScheduledJob) -> Result<bool> {
+        if self.jobs.iter().any(|existing| existing.name == job.name) {
+            return Ok(false);
+        }
+        self.add_job(job).await?;
+        Ok(true)
+    }
+
+    /// 删除任务
+    pub async fn remove_job(&self, id: Uuid) -> Result<()> {
+        if self.jobs.remove(&id).is_none() {
+            return Err(MemoryError::NotFound { id });
+        }
+        self.store.delete_vector(id).await.map_err(Self::store_error)
+    }
+
+    /// 所有任务，按下次执行时间排序
+    pub fn jobs(&self) -> Vec<ScheduledJob> {
+        let mut jobs: Vec<ScheduledJob> = self.jobs.iter().map(|job| job.clone()).collect();
+        jobs.sort_by_key(|job| (job.next_run.is_none(), job.next_run));
+        jobs
+    }
+
+    /// 执行在`now`之前到期的任务，返回执行的任务数
+    ///
+    /// 执行或保存失败只记录日志，任务照常计算下次执行时间；一次性任务执行后删除。
+    pub async fn run_due(&self, now: DateTime<Utc>) -> usize {
+        let due: Vec<ScheduledJob> = self.jobs.iter()
+            .filter(|job| job.is_due(now))
+            .map(|job| job.clone())
+            .collect();
+
+        for mut job in due.iter().cloned() {
+            let scheduled_at = job.next_run.unwrap_or(now);
+            if let Err(e) = self.execute(&job, scheduled_at).await {
+                tracing::warn!("定时任务 {} ({}) 执行失败: {}", job.name, job.id, e);
+            }
+
+            job.last_run = Some(now);
+            job.next_run = job.schedule.next_after(now).unwrap_or_else(|e| {
+                tracing::warn!("定时任务 {} ({}) 无法计算下次执行时间，停止执行: {}", job.name, job.id, e);
+                None
+            });
+            // 先更新内存中的任务，保存失败时也不会在下一轮重复执行
+            let saved = if job.next_run.is_none() {
+                self.remove_job(job.id).await
+            } else {
+                self.jobs.insert(job.id, job.clone());
+                self.persist(&job).await
+            };
+            if let Err(e) = saved {
+                tracing::warn!("保存定时任务 {} ({}) 失败: {}", job.name, job.id, e);
+            }
+        }
+        due.len()
+    }
+
+    #[tracing::instrument(skip_all, fields(job = %job.name))]
+    async fn execute(&self, job: &ScheduledJob, scheduled_at: DateTime<Utc>) -> Result<()> {
+        match &job.action {
+            JobAction::Message { user_id, text } => {
+                // 没有订阅方时发送失败，忽略即可
+                let _ = self.events.send(SchedulerEvent::Message {
+                    job_id: job.id,
+                    user_id: user_id.clone(),
+                    text: text.clone(),
+                });
+            }
+            JobAction::Reminder { user_id, content } => {
+                let _ = self.events.send(SchedulerEvent::Reminder {
+                    job_id: job.id,
+                    user_id: user_id.clone(),
+                    content: content.clone(),
+                    due_at: scheduled_at,
+                });
+                if let Some(webhooks) = self.webhooks.clone() {
+                    let event = WebhookEvent::ReminderDue {
+                        user_id: user_id.clone(),
+                        reminder_id: job.id,
+                        content: content.clone(),
+                        due_at: scheduled_at,
+                    };
+                    // 投递带重试，可能持续较久，不阻塞其他任务
+                    tokio::spawn(async move {
+                        if let Err(e) = webhooks.dispatch(&event).await {
+                            tracing::warn!("投递提醒Webhook失败: {}", e);
+                        }
+                    });
+                }
+            }
+            JobAction::Rehearsal { user_id, limit } => {
+                let system = self.manager.get_or_create(user_id).await?;
+                let mut memories = system.list_memories(Some(vec![
+                    MemoryType::LongTerm,
+                    MemoryType::Preference,
+                    MemoryType::Relationship,
+                ])).await?;
+                memories.sort_by_key(|entry| entry.last_accessed);
+                for entry in memories.iter().take(*limit) {
+                    system.adjust_importance(entry.id, REHEARSAL_BOOST).await?;
+                }
+            }
+            JobAction::PurgeExpired { user_id } => {
+                for user_id in self.target_users(user_id) {
+                    self.manager.get_or_create(&user_id).await?.purge_expired_memories().await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn target_users(&self, user_id: &Option<String>) -> Vec<String> {
+        match user_id {
+            Some(user_id) => vec![user_id.clone()],
+            None => self.manager.user_ids(),
+        }
+    }
+
+    /// 每隔`tick`检查一次到期任务，直到任务被取消
+    pub fn start(self: Arc<Self>, tick: std::time::Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tick);
+            loop {
+                interval.tick().await;
+                self.run_due(Utc::now()).await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::MockVectorStore;
+
+    #[test]
+    fn test_schedule_next_after() {
+        let now: DateTime<Utc> = "2024-02-14T12:00:00Z".parse().unwrap();
+        let morning = JobSchedule::Cron { expression: "0 30 7 * * *".to_string(), utc_offset_minutes: 8 * 60 };
+        // 北京时间7:30即UTC 23:30
+        assert_eq!(morning.next_after(now).unwrap(), Some("2024-02-14T23:30:00Z".parse().unwrap()));
+        assert_eq!(JobSchedule::Interval { seconds: 90 }.next_after(now).unwrap(), Some(now + Duration::seconds(90)));
+        assert_eq!(JobSchedule::Once { at: now }.next_after(now).unwrap(), None);
+
+        assert!(JobSchedule::Cron { expression: "每天早上".to_string(), utc_offset_minutes: 0 }.validate().is_err());
+        assert!(JobSchedule::Interval { seconds: 0 }.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_jobs_survive_restart_and_run_when_due() {
+        let manager = Arc::new(MemoryManager::new(Arc::new(MockVectorStore::new()), None));
+        let jobs: Arc<dyn VectorStore<Error = anyhow::Error>> = Arc::new(MockVectorStore::new().with_vector_size(JOB_VECTOR_SIZE));
+        let scheduler = Scheduler::new(manager.clone(), jobs.clone()).await.unwrap();
+
+        let now = Utc::now();
+        let reminder = ScheduledJob::new("提醒", JobSchedule::Once { at: now }, JobAction::Reminder {
+            user_id: "alice".to_string(),
+            content: "记得给妈妈打电话".to_string(),
+        }).unwrap();
+        let greeting = ScheduledJob::new("早安", JobSchedule::Interval { seconds: 3600 }, JobAction::Message {
+            user_id: "alice".to_string(),
+            text: "早上好".to_string(),
+        }).unwrap();
+        let reminder_id = scheduler.add_job(reminder).await.unwrap();
+        scheduler.add_job(greeting.clone()).await.unwrap();
+        assert!(!scheduler.ensure_job(greeting).await.unwrap());
+
+        // 重启后从存储载入
+        let scheduler = Scheduler::new(manager.clone(), jobs).await.unwrap();
+        assert_eq!(scheduler.jobs().len(), 2);
+        // 任务不写入记忆存储
+        assert_eq!(manager.vector_store().count(None).await.unwrap(), 0);
+        let mut events = scheduler.subscribe();
+
+        assert_eq!(scheduler.run_due(now).await, 1);
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            SchedulerEvent::Reminder { job_id, .. } if job_id == reminder_id
+        ));
+        assert_eq!(scheduler.jobs().len(), 1);
+        assert_eq!(scheduler.run_due(now + Duration::hours(2)).await, 1);
+        assert!(scheduler.jobs()[0].next_run.unwrap() > now + Duration::hours(2));
+    }
+}
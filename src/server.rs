@@ -0,0 +1,667 @@
+//! REST API服务 - 通过HTTP向非Rust客户端提供多用户记忆、情感和个性化接口
+//!
+//! | 方法 | 路径 | 说明 |
+//! |------|------|------|
+//! | GET | `/health` | 健康检查 |
+//! | POST | `/users/{user_id}/memories` | 添加记忆 |
+//! | GET | `/users/{user_id}/memories?query=&limit=&types=` | 检索记忆，`types`为逗号分隔的记忆类型 |
+//! | DELETE | `/users/{user_id}/memories/{id}` | 删除记忆 |
+//! | GET/PUT | `/users/{user_id}/emotion` | 读取或设置情感状态 |
+//! | POST | `/users/{user_id}/emotion/events` | 应用情感触发器 |
+//! | POST | `/users/{user_id}/respond` | 按情感和个性修饰回复 |
+//! | GET | `/users/{user_id}/stats` | 记忆统计 |
+//! | POST | `/users/{user_id}/transcripts` | 导入语音转写分段，按会话合并为对话记忆 |
+//! | GET/PUT | `/personality` | 读取或替换个性档案 |
+//! | GET | `/users/{user_id}/chat` | WebSocket对话，见`chat_socket` |
+//!
+//! `forward_proactive`把在家状态、定时任务等产生的主动消息推送给该用户已连接的WebSocket对话。
+//!
+//! 配置访问令牌后，除`/health`外的接口要求`Authorization: Bearer <token>`；
+//! 替换个性档案影响所有用户，只接受管理令牌，未配置管理令牌时拒绝。
+//! 检索、删除、统计等不写入的接口只访问已创建的用户，用户不存在时返回404。
+
+use crate::bridge::InferenceClient;
+use crate::chat::{ChatEvent, ChatSession, InferenceHealth};
+use crate::emotion::{EmotionalEngine, EmotionalTrigger, PersonalityGenerator, PersonalityProfile};
+use crate::memory::{IngestRequest, MemoryManager};
+use crate::MemorySystem;
+use crate::transcript::{TranscriptIngestor, TranscriptReport, TranscriptSegment};
+use crate::{EmotionalState, MemoryEntry, MemoryError, MemoryType};
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use std::collections::HashMap;
+use futures::{SinkExt, StreamExt};
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+
+/// 待推送主动消息的缓冲条数
+const PROACTIVE_CHANNEL_CAPACITY: usize = 64;
+
+/// 服务共享状态
+#[derive(Clone)]
+pub struct ApiState {
+    manager: Arc<MemoryManager>,
+    /// 推送给WebSocket对话的主动消息，(用户ID, 文本)
+    proactive: broadcast::Sender<(String, String)>,
+    engine: Arc<EmotionalEngine>,
+    personality: Arc<RwLock<Arc<PersonalityGenerator>>>,
+    /// 未配置推理服务时对话只使用本地个性回复
+    inference: Option<Arc<dyn InferenceClient>>,
+    /// 所有WebSocket对话共享的推理服务健康状况
+    inference_health: Arc<InferenceHealth>,
+    api_token: Option<Arc<str>>,
+    admin_token: Option<Arc<str>>,
+}
+
+impl ApiState {
+    /// 使用默认个性档案
+    pub fn new(manager: Arc<MemoryManager>) -> Self {
+        Self {
+            manager,
+            proactive: broadcast::channel(PROACTIVE_CHANNEL_CAPACITY).0,
+            engine: Arc::new(EmotionalEngine::new()),
+            personality: Arc::new(RwLock::new(Arc::new(PersonalityGenerator::new(PersonalityProfile::default())))),
+            inference: None,
+            inference_health: Arc::default(),
+            api_token: None,
+            admin_token: None,
+        }
+    }
+
+    /// 设置后接口要求此令牌或管理令牌，空字符串视为未设置
+    pub fn with_api_token(mut self, token: Option<String>) -> Self {
+        self.api_token = token.filter(|token| !token.is_empty()).map(Arc::from);
+        self
+    }
+
+    /// 管理令牌，替换个性档案时需要
+    pub fn with_admin_token(mut self, token: Option<String>) -> Self {
+        self.admin_token = token.filter(|token| !token.is_empty()).map(Arc::from);
+        self
+    }
+
+    /// 使用共享的情感引擎，例如由配置热加载更新的引擎
+    pub fn with_engine(mut self, engine: Arc<EmotionalEngine>) -> Self {
+        self.engine = engine;
+        self
+    }
+
+    /// 指定对话使用的推理客户端
+    pub fn with_inference(mut self, inference: Arc<dyn InferenceClient>) -> Self {
+        self.inference = Some(inference);
+        self.inference_health = Arc::default();
+        self
+    }
+
+    /// 指定个性档案
+    pub fn with_personality(self, profile: PersonalityProfile) -> Self {
+        self.set_personality(profile);
+        self
+    }
+
+    /// 用户记忆管理器
+    pub fn manager(&self) -> &Arc<MemoryManager> {
+        &self.manager
+    }
+
+    /// 把事件中的主动消息推送给对应用户已连接的WebSocket对话，直到事件发送方被释放
+    ///
+    /// `message`从事件中取出(用户ID, 文本)，返回None的事件被忽略；用户没有连接时消息被丢弃。
+    pub fn forward_proactive<T, F>(&self, mut events: broadcast::Receiver<T>, message: F) -> tokio::task::JoinHandle<()>
+    where
+        T: Clone + Send + 'static,
+        F: Fn(T) -> Option<(String, String)> + Send + 'static,
+    {
+        let proactive = self.proactive.clone();
+        tokio::spawn(async move {
+            loop {
+                let event = match events.recv().await {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("主动消息推送落后，跳过 {} 条", skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                if let Some((user_id, text)) = message(event) {
+                    if proactive.send((user_id.clone(), text)).is_err() {
+                        tracing::debug!("用户 {} 没有连接的对话，丢弃主动消息", user_id);
+                    }
+                }
+            }
+        })
+    }
+
+    fn personality(&self) -> Arc<PersonalityGenerator> {
+        self.personality.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn set_personality(&self, profile: PersonalityProfile) {
+        *self.personality.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(PersonalityGenerator::new(profile));
+    }
+
+    /// 已创建的用户记忆系统，不存在时不创建
+    fn existing(&self, user_id: &str) -> Result<Arc<MemorySystem>, ApiError> {
+        self.manager.get(user_id).ok_or_else(|| ApiError::UnknownUser(user_id.to_string()))
+    }
+
+    fn is_admin(&self, token: Option<&str>) -> bool {
+        matches!((self.admin_token.as_deref(), token), (Some(expected), Some(token)) if tokens_match(expected, token))
+    }
+}
+
+/// 比较令牌，耗时与第一个不同字节的位置无关
+fn tokens_match(expected: &str, actual: &str) -> bool {
+    expected.len() == actual.len()
+        && expected.bytes().zip(actual.bytes()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// 请求头中的Bearer令牌
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers.get(header::AUTHORIZATION)?.to_str().ok()?.strip_prefix("Bearer ")
+}
+
+/// 接口错误，以`{"error": "..."}`返回
+#[derive(Debug)]
+pub enum ApiError {
+    Memory(MemoryError),
+    /// 用户没有已创建的记忆系统
+    UnknownUser(String),
+    /// 缺少访问令牌或令牌错误
+    Unauthorized,
+    /// 需要管理令牌
+    Forbidden,
+}
+
+impl From<MemoryError> for ApiError {
+    fn from(error: MemoryError) -> Self {
+        Self::Memory(error)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            Self::Memory(error) => {
+                let status = match error {
+                    MemoryError::NotFound { .. } => StatusCode::NOT_FOUND,
+                    MemoryError::InvalidInput(_) | MemoryError::DimensionMismatch { .. } => StatusCode::BAD_REQUEST,
+                    MemoryError::InferenceUnavailable(_)
+                    | MemoryError::ModelOverloaded(_)
+                    | MemoryError::IngestQueueFull { .. } => StatusCode::SERVICE_UNAVAILABLE,
+                    _ => StatusCode::INTERNAL_SERVER_ERROR,
+                };
+                (status, error.to_string())
+            }
+            Self::UnknownUser(user_id) => (StatusCode::NOT_FOUND, format!("用户不存在: {}", user_id)),
+            Self::Unauthorized => (StatusCode::UNAUTHORIZED, "缺少或错误的访问令牌".to_string()),
+            Self::Forbidden => (StatusCode::FORBIDDEN, "需要管理令牌".to_string()),
+        };
+        (status, Json(serde_json::json!({ "error": message }))).into_response()
+    }
+}
+
+type ApiResult<T> = std::result::Result<T, ApiError>;
+
+#[derive(Debug, Deserialize)]
+struct AddMemoryRequest {
+    memory_type: MemoryType,
+    content: String,
+    #[serde(default)]
+    keywords: Vec<String>,
+    #[serde(default = "default_importance")]
+    importance: f32,
+    #[serde(default)]
+    emotional_context: Option<EmotionalState>,
+}
+
+fn default_importance() -> f32 {
+    0.5
+}
+
+#[derive(Debug, Deserialize)]
+struct RetrieveQuery {
+    query: String,
+    limit: Option<usize>,
+    /// 逗号分隔的记忆类型，如`Preference,LongTerm`
+    types: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmotionEvent {
+    trigger: EmotionalTrigger,
+    #[serde(default = "default_intensity")]
+    intensity: f32,
+}
+
+fn default_intensity() -> f32 {
+    1.0
+}
+
+#[derive(Debug, Deserialize)]
+struct RespondRequest {
+    response: String,
+    #[serde(default)]
+    context: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TranscriptRequest {
+    segments: Vec<TranscriptSegment>,
+}
+
+/// 解析逗号分隔的记忆类型，名称与`MemoryEntry`序列化一致
+pub fn parse_memory_types(types: &str) -> Result<Vec<MemoryType>, MemoryError> {
+    types.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            serde_json::from_value(serde_json::Value::String(name.to_string()))
+                .map_err(|_| MemoryError::InvalidInput(format!("未知的记忆类型: {}", name)))
+        })
+        .collect()
+}
+
+/// 构建路由
+pub fn router(state: ApiState) -> Router {
+    Router::new()
+        .route("/users/{user_id}/memories", post(add_memory).get(retrieve_memories))
+        .route("/users/{user_id}/memories/{id}", delete(delete_memory))
+        .route("/users/{user_id}/emotion", get(get_emotion).put(set_emotion))
+        .route("/users/{user_id}/emotion/events", post(emotion_event))
+        .route("/users/{user_id}/respond", post(respond))
+        .route("/users/{user_id}/stats", get(stats))
+        .route("/users/{user_id}/transcripts", post(ingest_transcript))
+        .route("/users/{user_id}/chat", get(chat))
+        .route("/personality", get(get_personality).put(set_personality))
+        .route_layer(middleware::from_fn_with_state(state.clone(), authorize))
+        .route("/health", get(health))
+        .with_state(state)
+}
+
+/// 配置了访问令牌时校验请求，管理令牌同样有效
+async fn authorize(State(state): State<ApiState>, request: Request, next: Next) -> Response {
+    if let Some(expected) = state.api_token.as_deref() {
+        let token = bearer_token(request.headers());
+        if !token.is_some_and(|token| tokens_match(expected, token)) && !state.is_admin(token) {
+            return ApiError::Unauthorized.into_response();
+        }
+    }
+    next.run(request).await
+}
+
+/// 在指定地址启动服务，收到Ctrl-C后停止接收请求，并写入写后缓冲中的剩余记录
+pub async fn serve(addr: SocketAddr, state: ApiState) -> std::io::Result<()> {
+    let manager = state.manager.clone();
+    serve_with_shutdown(addr, state, async {
+        let _ = tokio::signal::ctrl_c().await;
+    }).await?;
+
+    match manager.shutdown().await {
+        Ok(flushed) => tracing::info!("MIRA REST API 已停止，写入 {} 条待写记忆", flushed),
+        Err(e) => tracing::warn!("MIRA REST API 停止时写入待写记忆失败: {}", e),
+    }
+    Ok(())
+}
+
+/// 在指定地址启动服务，`signal`完成后停止接收请求 - 不写入待写记忆，由调用方调用`MemoryManager::shutdown`
+pub async fn serve_with_shutdown(
+    addr: SocketAddr,
+    state: ApiState,
+    signal: impl std::future::Future<Output = ()> + Send + 'static,
+) -> std::io::Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    tracing::info!("MIRA REST API 监听 {}", listener.local_addr()?);
+    axum::serve(listener, router(state))
+        .with_graceful_shutdown(signal)
+        .await
+}
+
+async fn health(State(state): State<ApiState>) -> Json<serde_json::Value> {
+    // 后台任务最近一次运行失败的用户数
+    let degraded = state.manager.user_ids().iter()
+        .filter_map(|user_id| state.manager.get(user_id))
+        .filter(|system| !system.tasks().is_healthy())
+        .count();
+    Json(serde_json::json!({ "status": "ok", "users": state.manager.len(), "degraded_users": degraded }))
+}
+
+async fn add_memory(
+    State(state): State<ApiState>,
+    Path(user_id): Path<String>,
+    Json(request): Json<AddMemoryRequest>,
+) -> ApiResult<(StatusCode, Json<serde_json::Value>)> {
+    // 写入管道排满时返回503，由客户端稍后重试
+    let id = state.manager.try_add_memory(
+        &user_id,
+        IngestRequest::new(request.memory_type, request.content, request.keywords, request.importance)
+            .with_emotional_context(request.emotional_context),
+    ).await?;
+    Ok((StatusCode::CREATED, Json(serde_json::json!({ "id": id }))))
+}
+
+async fn retrieve_memories(
+    State(state): State<ApiState>,
+    Path(user_id): Path<String>,
+    Query(query): Query<RetrieveQuery>,
+) -> ApiResult<Json<Vec<Arc<MemoryEntry>>>> {
+    let memory_types = query.types.as_deref().map(parse_memory_types).transpose()?;
+    let system = state.existing(&user_id)?;
+    // 检索结果不带嵌入向量
+    Ok(Json(system.retrieve_memories(&query.query, memory_types, query.limit).await?))
+}
+
+async fn delete_memory(
+    State(state): State<ApiState>,
+    Path((user_id, id)): Path<(String, Uuid)>,
+) -> ApiResult<StatusCode> {
+    let system = state.existing(&user_id)?;
+    system.delete_memory(id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn get_emotion(
+    State(state): State<ApiState>,
+    Path(user_id): Path<String>,
+) -> ApiResult<Json<EmotionalState>> {
+    let system = state.existing(&user_id)?;
+    Ok(Json(system.get_emotional_state().await))
+}
+
+async fn set_emotion(
+    State(state): State<ApiState>,
+    Path(user_id): Path<String>,
+    Json(emotion): Json<EmotionalState>,
+) -> ApiResult<Json<EmotionalState>> {
+    let emotion = EmotionalState {
+        happiness: emotion.happiness.clamp(0.0, 1.0),
+        affection: emotion.affection.clamp(0.0, 1.0),
+        trust: emotion.trust.clamp(0.0, 1.0),
+        dependency: emotion.dependency.clamp(0.0, 1.0),
+        ..emotion
+    };
+    state.manager.update_emotion(&user_id, None, emotion.clone()).await?;
+    Ok(Json(emotion))
+}
+
+async fn emotion_event(
+    State(state): State<ApiState>,
+    Path(user_id): Path<String>,
+    Json(event): Json<EmotionEvent>,
+) -> ApiResult<Json<EmotionalState>> {
+    let current = state.manager.get_or_create(&user_id).await?.get_emotional_state().await;
+    let emotion = state.engine.process_trigger(&current, event.trigger.clone(), event.intensity);
+    state.manager.update_emotion(&user_id, Some(event.trigger), emotion.clone()).await?;
+    Ok(Json(emotion))
+}
+
+async fn respond(
+    State(state): State<ApiState>,
+    Path(user_id): Path<String>,
+    Json(request): Json<RespondRequest>,
+) -> ApiResult<Json<serde_json::Value>> {
+    let system = state.existing(&user_id)?;
+    let emotion = system.get_emotional_state().await;
+    let response = state.engine.generate_emotional_expression(&emotion, &request.response);
+    let response = state.personality().generate_personalized_response(&response, &request.context);
+    Ok(Json(serde_json::json!({ "response": response, "mood": emotion.mood })))
+}
+
+async fn ingest_transcript(
+    State(state): State<ApiState>,
+    Path(user_id): Path<String>,
+    Json(request): Json<TranscriptRequest>,
+) -> ApiResult<(StatusCode, Json<TranscriptReport>)> {
+    let system = state.manager.get_or_create(&user_id).await?;
+    let ingestor = TranscriptIngestor::new(system).with_pipeline(state.manager.ingest());
+    let report = ingestor.ingest(request.segments).await?;
+    Ok((StatusCode::CREATED, Json(report)))
+}
+
+async fn stats(
+    State(state): State<ApiState>,
+    Path(user_id): Path<String>,
+) -> ApiResult<Json<HashMap<String, u64>>> {
+    let system = state.existing(&user_id)?;
+    Ok(Json(system.get_memory_stats().await))
+}
+
+async fn get_personality(State(state): State<ApiState>) -> Json<PersonalityProfile> {
+    Json(state.personality().profile().clone())
+}
+
+async fn set_personality(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    Json(profile): Json<PersonalityProfile>,
+) -> ApiResult<Json<PersonalityProfile>> {
+    // 个性档案由所有用户共享，普通访问令牌不能修改
+    if !state.is_admin(bearer_token(&headers)) {
+        return Err(ApiError::Forbidden);
+    }
+    state.set_personality(profile.clone());
+    Ok(Json(profile))
+}
+
+async fn chat(
+    State(state): State<ApiState>,
+    Path(user_id): Path<String>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    upgrade.on_upgrade(move |socket| chat_socket(socket, state, user_id))
+}
+
+/// WebSocket对话 - 每条文本消息是一轮用户输入，依次返回JSON格式的`ChatEvent`
+///
+/// 出错时返回`{"type": "error", "message": "..."}`，连接保持打开；
+/// 主动消息随时以`{"type": "proactive", "text": "..."}`推送。
+async fn chat_socket(socket: WebSocket, state: ApiState, user_id: String) {
+    let (mut sender, mut receiver) = socket.split();
+    let (events, mut outgoing) = tokio::sync::mpsc::unbounded_channel::<serde_json::Value>();
+
+    // 单独的写任务，回复片段在生成过程中即可发出
+    let writer = tokio::spawn(async move {
+        while let Some(event) = outgoing.recv().await {
+            if sender.send(Message::Text(event.to_string().into())).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let pusher = {
+        let (events, user_id) = (events.clone(), user_id.clone());
+        let mut proactive = state.proactive.subscribe();
+        tokio::spawn(async move {
+            loop {
+                match proactive.recv().await {
+                    Ok((target, text)) if target == user_id => {
+                        if events.send(serde_json::json!({ "type": "proactive", "text": text })).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(_) | Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        })
+    };
+
+    let mut session = match state.inference.clone() {
+        Some(inference) => ChatSession::new(
+            user_id,
+            state.manager.clone(),
+            inference,
+            state.engine.clone(),
+            state.personality(),
+        ),
+        None => ChatSession::local(user_id, state.manager.clone(), state.engine.clone(), state.personality()),
+    }
+    .with_health(state.inference_health.clone());
+    while let Some(Ok(message)) = receiver.next().await {
+        let input = match message {
+            Message::Text(text) => text.trim().to_string(),
+            Message::Close(_) => break,
+            _ => continue,
+        };
+        if input.is_empty() {
+            continue;
+        }
+
+        let result = session.turn(&input, |event: ChatEvent| {
+            let _ = events.send(serde_json::to_value(event).unwrap_or_default());
+        }).await;
+        if let Err(e) = result {
+            let _ = events.send(serde_json::json!({ "type": "error", "message": e.to_string() }));
+        }
+    }
+
+    pusher.abort();
+    drop(events);
+    let _ = writer.await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::MockInferenceClient;
+    use crate::vector_store::MockVectorStore;
+
+    fn test_state() -> ApiState {
+        let manager = Arc::new(MemoryManager::new(Arc::new(MockVectorStore::new()), None));
+        ApiState::new(manager).with_inference(Arc::new(MockInferenceClient::new()))
+    }
+
+    async fn spawn_server() -> String {
+        spawn(test_state()).await
+    }
+
+    async fn spawn(state: ApiState) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(axum::serve(listener, router(state)).into_future());
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_memory_lifecycle_over_http() {
+        let base = spawn_server().await;
+        let client = reqwest::Client::new();
+
+        let response = client.post(format!("{}/users/alice/memories", base))
+            .json(&serde_json::json!({ "memory_type": "Preference", "content": "用户喜欢猫咪" }))
+            .send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let id = response.json::<serde_json::Value>().await.unwrap()["id"].as_str().unwrap().to_string();
+
+        let entries: Vec<serde_json::Value> = client.get(format!("{}/users/alice/memories", base))
+            .query(&[("query", "用户喜欢猫咪"), ("types", "Preference")])
+            .send().await.unwrap().json().await.unwrap();
+        assert_eq!(entries[0]["id"], id.as_str());
+
+        // 读取不会创建用户
+        let status = client.get(format!("{}/users/bob/memories", base))
+            .query(&[("query", "用户喜欢猫咪")])
+            .send().await.unwrap().status();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let status = client.get(format!("{}/users/bob/stats", base)).send().await.unwrap().status();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let status = client.delete(format!("{}/users/alice/memories/{}", base, id)).send().await.unwrap().status();
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let status = client.delete(format!("{}/users/alice/memories/{}", base, id)).send().await.unwrap().status();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let status = client.get(format!("{}/users/alice/memories", base))
+            .query(&[("query", "猫"), ("types", "Unknown")])
+            .send().await.unwrap().status();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_emotion_events_update_state() {
+        let base = spawn_server().await;
+        let client = reqwest::Client::new();
+
+        let status = client.get(format!("{}/users/alice/emotion", base)).send().await.unwrap().status();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let after: EmotionalState = client.post(format!("{}/users/alice/emotion/events", base))
+            .json(&serde_json::json!({ "trigger": "BeingPraised" }))
+            .send().await.unwrap().json().await.unwrap();
+        assert!(after.happiness > EmotionalState::default().happiness);
+        let current: EmotionalState = client.get(format!("{}/users/alice/emotion", base))
+            .send().await.unwrap().json().await.unwrap();
+        assert_eq!(current.happiness, after.happiness);
+
+        let reply: serde_json::Value = client.post(format!("{}/users/alice/respond", base))
+            .json(&serde_json::json!({ "response": "好的" }))
+            .send().await.unwrap().json().await.unwrap();
+        assert!(reply["response"].as_str().unwrap().contains("好的"));
+    }
+
+    #[tokio::test]
+    async fn test_tokens_guard_routes_and_personality() {
+        let state = test_state()
+            .with_api_token(Some("user-token".to_string()))
+            .with_admin_token(Some("admin-token".to_string()));
+        let base = spawn(state).await;
+        let client = reqwest::Client::new();
+
+        let status = client.get(format!("{}/health", base)).send().await.unwrap().status();
+        assert_eq!(status, StatusCode::OK);
+        let status = client.get(format!("{}/personality", base)).send().await.unwrap().status();
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let status = client.get(format!("{}/personality", base)).bearer_auth("wrong-token").send().await.unwrap().status();
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let profile: serde_json::Value = client.get(format!("{}/personality", base))
+            .bearer_auth("user-token")
+            .send().await.unwrap().json().await.unwrap();
+        let status = client.put(format!("{}/personality", base))
+            .bearer_auth("user-token")
+            .json(&profile)
+            .send().await.unwrap().status();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let status = client.put(format!("{}/personality", base))
+            .bearer_auth("admin-token")
+            .json(&profile)
+            .send().await.unwrap().status();
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_chat_over_websocket() {
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let base = spawn_server().await;
+        let url = format!("{}/users/alice/chat", base.replacen("http", "ws", 1));
+        let (mut socket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        socket.send(WsMessage::Text("你真棒".into())).await.unwrap();
+
+        let mut types = Vec::new();
+        while let Some(message) = socket.next().await {
+            let WsMessage::Text(text) = message.unwrap() else { continue };
+            let event: serde_json::Value = serde_json::from_str(&text).unwrap();
+            types.push(event["type"].as_str().unwrap().to_string());
+            if event["type"] == "done" {
+                assert!(event["response"].as_str().unwrap().contains("你真棒"));
+                break;
+            }
+        }
+        assert_eq!(types.first().map(String::as_str), Some("emotion"));
+        assert!(types.iter().any(|t| t == "token"));
+
+        let client = reqwest::Client::new();
+        let stats: HashMap<String, u64> = client.get(format!("{}/users/alice/stats", base))
+            .send().await.unwrap().json().await.unwrap();
+        assert_eq!(stats.get("ShortTerm"), Some(&1));
+    }
+}
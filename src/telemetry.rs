@@ -0,0 +1,49 @@
+//! OpenTelemetry导出 - 将tracing span通过OTLP/HTTP发送到Jaeger、Tempo等后端
+//!
+//! 导出地址读取标准环境变量`OTEL_EXPORTER_OTLP_ENDPOINT`（默认`http://localhost:4318`），
+//! 日志过滤读取`RUST_LOG`（默认`info`）。一次对话的`chat_turn` span下依次包含记忆检索、
+//! 向量存储调用、推理请求和记忆写入，span上带有`user_id`和`memory_id`。
+
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_sdk::trace::{Config, TracerProvider};
+use opentelemetry_sdk::{runtime, Resource};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+/// 持有span导出器，释放时发送尚未导出的span
+#[must_use = "释放后停止导出span"]
+#[derive(Debug)]
+pub struct TelemetryGuard {
+    provider: TracerProvider,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if let Err(e) = self.provider.shutdown() {
+            tracing::warn!("关闭OpenTelemetry导出器失败: {}", e);
+        }
+    }
+}
+
+/// 安装全局tracing订阅器：控制台日志 + OTLP导出，需要在tokio运行时中调用
+pub fn init_tracing(service_name: &str) -> anyhow::Result<TelemetryGuard> {
+    let provider = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(opentelemetry_otlp::new_exporter().http())
+        .with_trace_config(Config::default().with_resource(Resource::new([
+            KeyValue::new("service.name", service_name.to_string()),
+        ])))
+        .install_batch(runtime::Tokio)?;
+    opentelemetry::global::set_tracer_provider(provider.clone());
+
+    let tracer = provider.tracer(service_name.to_string());
+    tracing_subscriber::registry()
+        .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
+        .with(tracing_subscriber::fmt::layer())
+        .with(tracing_opentelemetry::layer().with_tracer(tracer))
+        .try_init()?;
+
+    Ok(TelemetryGuard { provider })
+}
@@ -0,0 +1,283 @@
+//! 语音转写导入 - 把语音前端转写好的分段按会话合并为对话记忆
+//!
+//! 带`session_id`的分段按会话分组，没有时相邻分段间隔超过`session_gap`即视为新会话；
+//! 会话内再按长度切成多条记忆。置信度低于阈值的分段在内容中以`[?]`标记，并记录在元数据中，
+//! 供前端提示用户确认或在检索时降低权重。
+
+use crate::bridge::local_keywords;
+use crate::import::{ENDED_AT_KEY, PARTICIPANTS_KEY};
+use crate::memory::IngestPipeline;
+use crate::{MemoryEntry, MemorySystem, MemoryType, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// 记忆元数据：所属会话
+pub const SESSION_KEY: &str = "session_id";
+/// 记忆元数据：低置信度分段数
+pub const LOW_CONFIDENCE_KEY: &str = "low_confidence_segments";
+/// 记忆元数据：分段中最低的置信度
+pub const MIN_CONFIDENCE_KEY: &str = "min_confidence";
+/// 低置信度分段在内容中的标记
+pub const LOW_CONFIDENCE_MARK: &str = "[?]";
+
+/// 一段转写结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptSegment {
+    pub speaker: String,
+    pub text: String,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    /// 识别置信度 0.0-1.0，未提供时视为可信
+    #[serde(default)]
+    pub confidence: Option<f32>,
+    /// 前端给出的会话ID
+    #[serde(default)]
+    pub session_id: Option<String>,
+}
+
+impl TranscriptSegment {
+    fn is_low_confidence(&self, threshold: f32) -> bool {
+        self.confidence.is_some_and(|confidence| confidence < threshold)
+    }
+}
+
+/// 分组和写入选项
+#[derive(Debug, Clone)]
+pub struct TranscriptOptions {
+    pub memory_type: MemoryType,
+    pub importance: f32,
+    /// 没有会话ID时，相邻分段间隔超过此值开始新会话
+    pub session_gap: Duration,
+    /// 单条记忆的最大字符数
+    pub max_chars: usize,
+    /// 置信度低于此值的分段被标记
+    pub low_confidence_threshold: f32,
+}
+
+impl Default for TranscriptOptions {
+    fn default() -> Self {
+        Self {
+            memory_type: MemoryType::ShortTerm,
+            importance: 0.5,
+            session_gap: Duration::minutes(5),
+            max_chars: 1000,
+            low_confidence_threshold: 0.6,
+        }
+    }
+}
+
+/// 导入结果
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TranscriptReport {
+    /// 写入的记忆ID，按时间排序
+    pub memory_ids: Vec<Uuid>,
+    pub sessions: usize,
+    pub low_confidence_segments: usize,
+}
+
+/// 把分段按会话分组，`segments`需按开始时间排序
+fn group_sessions(segments: &[TranscriptSegment], session_gap: Duration) -> Vec<&[TranscriptSegment]> {
+    let mut sessions = Vec::new();
+    let mut start = 0;
+    for (index, pair) in segments.windows(2).enumerate() {
+        let (previous, segment) = (&pair[0], &pair[1]);
+        let new_session = match (&previous.session_id, &segment.session_id) {
+            (Some(a), Some(b)) => a != b,
+            _ => segment.started_at - previous.ended_at > session_gap,
+        };
+        if new_session {
+            sessions.push(&segments[start..=index]);
+            start = index + 1;
+        }
+    }
+    if start < segments.len() {
+        sessions.push(&segments[start..]);
+    }
+    sessions
+}
+
+/// 把分段合并为记忆，关键词由本地规则提取，不含嵌入；返回记忆和会话数
+pub fn merge_segments(segments: &[TranscriptSegment], options: &TranscriptOptions) -> (Vec<MemoryEntry>, usize) {
+    let sessions = group_sessions(segments, options.session_gap);
+    let session_count = sessions.len();
+
+    let mut entries = Vec::new();
+    for session in sessions {
+        let session_id = session[0].session_id.clone()
+            .unwrap_or_else(|| format!("voice-{}", session[0].started_at.timestamp()));
+
+        // 会话内按长度切分，单个分段超出时单独成段
+        let mut chunks: Vec<&[TranscriptSegment]> = Vec::new();
+        let (mut start, mut chars) = (0, 0);
+        for (index, segment) in session.iter().enumerate() {
+            let line_chars = segment.speaker.chars().count() + segment.text.chars().count() + 2;
+            if index > start && chars + line_chars > options.max_chars {
+                chunks.push(&session[start..index]);
+                start = index;
+                chars = 0;
+            }
+            chars += line_chars;
+        }
+        chunks.push(&session[start..]);
+
+        for chunk in chunks {
+            let content = chunk.iter()
+                .map(|segment| {
+                    if segment.is_low_confidence(options.low_confidence_threshold) {
+                        format!("{}: {} {}", segment.speaker, segment.text, LOW_CONFIDENCE_MARK)
+                    } else {
+                        format!("{}: {}", segment.speaker, segment.text)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            let text = chunk.iter().map(|segment| segment.text.as_str()).collect::<Vec<_>>().join(" ");
+            let mut entry = MemoryEntry::new(options.memory_type.clone(), content, local_keywords(&text), options.importance);
+
+            let mut participants: Vec<&str> = chunk.iter().map(|segment| segment.speaker.as_str()).collect();
+            participants.sort_unstable();
+            participants.dedup();
+            entry.metadata.insert(PARTICIPANTS_KEY.to_string(), participants.join(", "));
+            entry.metadata.insert(SESSION_KEY.to_string(), session_id.clone());
+            entry.metadata.insert(ENDED_AT_KEY.to_string(), chunk[chunk.len() - 1].ended_at.to_rfc3339());
+
+            let low_confidence = chunk.iter()
+                .filter(|segment| segment.is_low_confidence(options.low_confidence_threshold))
+                .count();
+            if low_confidence > 0 {
+                entry.metadata.insert(LOW_CONFIDENCE_KEY.to_string(), low_confidence.to_string());
+            }
+            if let Some(min) = chunk.iter().filter_map(|segment| segment.confidence).reduce(f32::min) {
+                entry.metadata.insert(MIN_CONFIDENCE_KEY.to_string(), format!("{:.2}", min));
+            }
+
+            entry.created_at = chunk[0].started_at;
+            entry.last_accessed = entry.created_at;
+            entries.push(entry);
+        }
+    }
+    (entries, session_count)
+}
+
+/// 把转写分段写入一个用户的记忆系统
+#[derive(Debug, Clone)]
+pub struct TranscriptIngestor {
+    system: Arc<MemorySystem>,
+    pipeline: Option<Arc<IngestPipeline>>,
+    options: TranscriptOptions,
+}
+
+impl TranscriptIngestor {
+    pub fn new(system: Arc<MemorySystem>) -> Self {
+        Self {
+            system,
+            pipeline: None,
+            options: TranscriptOptions::default(),
+        }
+    }
+
+    /// 经写入管道写入，与其他写入共享并发上限；未设置时直接写入记忆系统
+    pub fn with_pipeline(mut self, pipeline: Arc<IngestPipeline>) -> Self {
+        self.pipeline = Some(pipeline);
+        self
+    }
+
+    pub fn with_options(mut self, options: TranscriptOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// 合并分段并写入，空文本的分段被忽略
+    #[tracing::instrument(skip_all, fields(user_id = %self.system.user_id(), segments = segments.len()))]
+    pub async fn ingest(&self, mut segments: Vec<TranscriptSegment>) -> Result<TranscriptReport> {
+        segments.retain(|segment| !segment.text.trim().is_empty());
+        segments.sort_by_key(|segment| segment.started_at);
+
+        let (mut entries, sessions) = merge_segments(&segments, &self.options);
+        // 使用记忆系统的嵌入生成器批量生成，与检索时的查询向量一致
+        let contents: Vec<String> = entries.iter().map(|entry| entry.content.clone()).collect();
+        let embeddings = self.system.embedder().embed_batch(&contents).await?;
+        for (entry, embedding) in entries.iter_mut().zip(embeddings) {
+            entry.embedding = Some(embedding);
+        }
+
+        let report = TranscriptReport {
+            memory_ids: entries.iter().map(|entry| entry.id).collect(),
+            sessions,
+            low_confidence_segments: segments.iter()
+                .filter(|segment| segment.is_low_confidence(self.options.low_confidence_threshold))
+                .count(),
+        };
+        match self.pipeline {
+            Some(ref pipeline) => pipeline.import_memories(self.system.clone(), entries).await?,
+            None => self.system.import_memories(entries).await?,
+        };
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::MockVectorStore;
+
+    fn segment(speaker: &str, text: &str, start_secs: i64, confidence: Option<f32>, session: Option<&str>) -> TranscriptSegment {
+        let base: DateTime<Utc> = "2024-02-14T20:00:00Z".parse().unwrap();
+        TranscriptSegment {
+            speaker: speaker.to_string(),
+            text: text.to_string(),
+            started_at: base + Duration::seconds(start_secs),
+            ended_at: base + Duration::seconds(start_secs + 5),
+            confidence,
+            session_id: session.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_merge_groups_sessions_and_flags_low_confidence() {
+        let segments = vec![
+            segment("小明", "今天好累", 0, Some(0.95), None),
+            segment("Mira", "辛苦啦", 10, None, None),
+            segment("小明", "明天去看电影吧", 20, Some(0.4), None),
+            // 间隔超过5分钟，开始新会话
+            segment("小明", "晚安", 3600, Some(0.9), None),
+        ];
+
+        let (entries, sessions) = merge_segments(&segments, &TranscriptOptions::default());
+        assert_eq!((entries.len(), sessions), (2, 2));
+        assert_eq!(entries[0].content, "小明: 今天好累\nMira: 辛苦啦\n小明: 明天去看电影吧 [?]");
+        assert_eq!(entries[0].metadata[LOW_CONFIDENCE_KEY], "1");
+        assert_eq!(entries[0].metadata[MIN_CONFIDENCE_KEY], "0.40");
+        assert_eq!(entries[0].metadata[ENDED_AT_KEY], "2024-02-14T20:00:25+00:00");
+        assert!(!entries[1].metadata.contains_key(LOW_CONFIDENCE_KEY));
+        assert_ne!(entries[0].metadata[SESSION_KEY], entries[1].metadata[SESSION_KEY]);
+
+        // 显式会话ID优先于时间间隔
+        let segments = vec![
+            segment("小明", "在吗", 0, None, Some("a")),
+            segment("小明", "在的", 3600, None, Some("a")),
+            segment("小明", "你好", 3601, None, Some("b")),
+        ];
+        let (entries, sessions) = merge_segments(&segments, &TranscriptOptions::default());
+        assert_eq!((entries.len(), sessions), (2, 2));
+        assert_eq!(entries[0].metadata[SESSION_KEY], "a");
+    }
+
+    #[tokio::test]
+    async fn test_ingest_writes_memories() {
+        let system = Arc::new(MemorySystem::new("alice".to_string(), Arc::new(MockVectorStore::new()), None).await.unwrap());
+        let report = TranscriptIngestor::new(system.clone()).ingest(vec![
+            segment("Mira", "早上好", 10, Some(0.3), None),
+            segment("小明", "早", 0, Some(0.9), None),
+            segment("小明", "  ", 20, None, None),
+        ]).await.unwrap();
+
+        assert_eq!(report.memory_ids.len(), 1);
+        assert_eq!((report.sessions, report.low_confidence_segments), (1, 1));
+        let entries = system.list_memories(None).await.unwrap();
+        assert_eq!(entries[0].content, "小明: 早\nMira: 早上好 [?]");
+        assert!(system.memory_embedding(entries[0].id).await.unwrap().is_some());
+    }
+}
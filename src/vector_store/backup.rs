@@ -0,0 +1,115 @@
+//! 向量存储的通用备份与恢复 - 基于scroll导出为JSONL，适用于任意后端
+
+use super::{store_point, StoredVector, VectorStore};
+use std::path::Path;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter};
+
+/// 每页/每批处理的点数
+const BATCH_SIZE: usize = 256;
+
+/// 将存储中的全部向量导出为JSONL文件，每行一个点，返回导出的点数
+pub async fn export_jsonl<S>(store: &S, path: impl AsRef<Path>) -> Result<usize, S::Error>
+where
+    S: VectorStore + ?Sized,
+{
+    let file = tokio::fs::File::create(path).await.map_err(anyhow::Error::from)?;
+    let mut writer = BufWriter::new(file);
+    let mut exported = 0;
+    let mut offset = None;
+
+    loop {
+        let page = store.scroll(offset, BATCH_SIZE).await?;
+
+        for point in &page.points {
+            let mut line = serde_json::to_vec(point).map_err(anyhow::Error::from)?;
+            line.push(b'\n');
+            writer.write_all(&line).await.map_err(anyhow::Error::from)?;
+        }
+        exported += page.points.len();
+
+        match page.next_offset {
+            Some(next) => offset = Some(next),
+            None => break,
+        }
+    }
+
+    writer.flush().await.map_err(anyhow::Error::from)?;
+    Ok(exported)
+}
+
+/// 从JSONL文件恢复向量，已存在的ID会被覆盖，返回导入的点数
+pub async fn import_jsonl<S>(store: &S, path: impl AsRef<Path>) -> Result<usize, S::Error>
+where
+    S: VectorStore + ?Sized,
+{
+    let file = tokio::fs::File::open(path).await.map_err(anyhow::Error::from)?;
+    let mut lines = BufReader::new(file).lines();
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    let mut emotion_batch = Vec::new();
+    let mut imported = 0;
+
+    while let Some(line) = lines.next_line().await.map_err(anyhow::Error::from)? {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let point: StoredVector = serde_json::from_str(&line).map_err(anyhow::Error::from)?;
+        // 带图片向量的点逐条写入，带情感向量的点和其余的点分别按批写入
+        if point.image.is_some() {
+            store_point(store, point).await?;
+            imported += 1;
+            continue;
+        }
+        match point.emotion {
+            Some(emotion) => emotion_batch.push((point.id, point.embedding, emotion, point.payload.to_string())),
+            None => batch.push((point.id, point.embedding, point.payload.to_string())),
+        }
+
+        if batch.len() >= BATCH_SIZE {
+            imported += batch.len();
+            store.store_vectors(std::mem::take(&mut batch)).await?;
+        }
+        if emotion_batch.len() >= BATCH_SIZE {
+            imported += emotion_batch.len();
+            store.store_multi_vectors(std::mem::take(&mut emotion_batch)).await?;
+        }
+    }
+
+    if !batch.is_empty() {
+        imported += batch.len();
+        store.store_vectors(batch).await?;
+    }
+    if !emotion_batch.is_empty() {
+        imported += emotion_batch.len();
+        store.store_multi_vectors(emotion_batch).await?;
+    }
+
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::MockVectorStore;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_export_import_roundtrip() {
+        let source = MockVectorStore::new();
+        for i in 0..3 {
+            let metadata = serde_json::json!({ "content": format!("记忆{}", i) }).to_string();
+            source.store_vector(Uuid::new_v4(), vec![i as f32, 1.0], metadata).await.unwrap();
+        }
+
+        let path = std::env::temp_dir().join(format!("mira_backup_{}.jsonl", Uuid::new_v4()));
+        assert_eq!(export_jsonl(&source, &path).await.unwrap(), 3);
+
+        let target = MockVectorStore::new();
+        assert_eq!(import_jsonl(&target, &path).await.unwrap(), 3);
+        tokio::fs::remove_file(&path).await.unwrap();
+
+        let exported = source.scroll(None, 10).await.unwrap();
+        let imported = target.scroll(None, 10).await.unwrap();
+        assert_eq!(exported.points, imported.points);
+    }
+}
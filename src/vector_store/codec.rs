@@ -0,0 +1,193 @@
+//! 序列化编码 - 本地存储文件和记忆payload可使用紧凑的二进制编码
+//!
+//! JSON始终可用，备份、导出和跨语言接口继续使用JSON；启用`binary-codec`特性后可选bincode。
+//! 二进制文件以`FILE_MAGIC`和编码名称开头，读取时自动识别，旧的JSON文件无需迁移。
+//! bincode不记录字段名，数据开头写入结构版本，版本不一致时报错而不是按错位的字段解码。
+
+use anyhow::{anyhow, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// 二进制持久化文件的文件头，其后是编码名称和换行
+pub const FILE_MAGIC: &[u8] = b"MIRA";
+
+/// payload中编码后的记忆条目（Base64）
+pub const PAYLOAD_ENCODED_ENTRY: &str = "encoded_entry";
+/// payload中记忆条目的编码名称
+pub const PAYLOAD_CODEC: &str = "codec";
+
+/// 序列化编码
+pub trait Codec {
+    /// 写入文件头和payload的编码名称
+    fn name(&self) -> &'static str;
+
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>>;
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T>;
+}
+
+/// JSON编码
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+/// bincode数据的结构版本，`MemoryEntry`或持久化结构的字段变化时递增
+#[cfg(feature = "binary-codec")]
+pub const BINCODE_SCHEMA_VERSION: u8 = 1;
+
+/// bincode编码 - 向量按4字节浮点存放，体积和解析开销都远小于JSON
+#[cfg(feature = "binary-codec")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BincodeCodec;
+
+#[cfg(feature = "binary-codec")]
+impl Codec for BincodeCodec {
+    fn name(&self) -> &'static str {
+        "bincode"
+    }
+
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>> {
+        let mut bytes = vec![BINCODE_SCHEMA_VERSION];
+        bincode::serialize_into(&mut bytes, value)?;
+        Ok(bytes)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T> {
+        match bytes.split_first() {
+            Some((&BINCODE_SCHEMA_VERSION, body)) => Ok(bincode::deserialize(body)?),
+            Some((&version, _)) => Err(anyhow!(
+                "bincode数据的结构版本为{}，当前版本为{}，需要先迁移", version, BINCODE_SCHEMA_VERSION
+            )),
+            None => Err(anyhow!("bincode数据为空")),
+        }
+    }
+}
+
+/// 可配置的编码选择
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CodecKind {
+    #[default]
+    Json,
+    #[cfg(feature = "binary-codec")]
+    Bincode,
+}
+
+impl CodecKind {
+    /// 按编码名称查找，未知或未启用的编码为None
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "json" => Some(Self::Json),
+            #[cfg(feature = "binary-codec")]
+            "bincode" => Some(Self::Bincode),
+            _ => None,
+        }
+    }
+
+    /// 编码为持久化文件内容，JSON不加文件头以保持可读
+    pub fn encode_file<T: Serialize>(&self, value: &T) -> Result<Vec<u8>> {
+        let body = self.encode(value)?;
+        if *self == Self::Json {
+            return Ok(body);
+        }
+        let mut bytes = Vec::with_capacity(FILE_MAGIC.len() + self.name().len() + 1 + body.len());
+        bytes.extend_from_slice(FILE_MAGIC);
+        bytes.extend_from_slice(self.name().as_bytes());
+        bytes.push(b'\n');
+        bytes.extend_from_slice(&body);
+        Ok(bytes)
+    }
+
+    /// 解码持久化文件，按文件头识别编码，没有文件头时按JSON读取
+    pub fn decode_file<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+        let Some(rest) = bytes.strip_prefix(FILE_MAGIC) else {
+            return JsonCodec.decode(bytes);
+        };
+        let split = rest.iter().position(|&b| b == b'\n')
+            .ok_or_else(|| anyhow!("持久化文件头不完整"))?;
+        let name = std::str::from_utf8(&rest[..split])?;
+        let codec = Self::from_name(name)
+            .ok_or_else(|| anyhow!("持久化文件使用了未启用的编码: {}", name))?;
+        codec.decode(&rest[split + 1..])
+    }
+}
+
+impl Codec for CodecKind {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Json => JsonCodec.name(),
+            #[cfg(feature = "binary-codec")]
+            Self::Bincode => BincodeCodec.name(),
+        }
+    }
+
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>> {
+        match self {
+            Self::Json => JsonCodec.encode(value),
+            #[cfg(feature = "binary-codec")]
+            Self::Bincode => BincodeCodec.encode(value),
+        }
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T> {
+        match self {
+            Self::Json => JsonCodec.decode(bytes),
+            #[cfg(feature = "binary-codec")]
+            Self::Bincode => BincodeCodec.decode(bytes),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MemoryEntry, MemoryType};
+
+    #[test]
+    fn test_json_file_has_no_header() {
+        let entry = MemoryEntry::new(MemoryType::LongTerm, "用户喜欢猫咪".to_string(), vec!["猫咪".to_string()], 0.7);
+        let bytes = CodecKind::Json.encode_file(&entry).unwrap();
+        assert_eq!(bytes.first(), Some(&b'{'));
+
+        let decoded: MemoryEntry = CodecKind::decode_file(&bytes).unwrap();
+        assert_eq!(decoded.id, entry.id);
+        assert_eq!(decoded.content, entry.content);
+        assert!(CodecKind::decode_file::<MemoryEntry>(b"MIRAzstd\n...").is_err());
+        assert_eq!(serde_json::from_str::<CodecKind>("\"json\"").unwrap(), CodecKind::Json);
+    }
+
+    #[cfg(feature = "binary-codec")]
+    #[test]
+    fn test_bincode_roundtrip_is_smaller() {
+        let mut entry = MemoryEntry::new(MemoryType::Emotional, "第一次约会".to_string(), vec!["约会".to_string()], 0.9);
+        entry.embedding = Some((0..768).map(|i| i as f32 / 768.0).collect());
+        entry.metadata.insert("source".to_string(), "chat".to_string());
+
+        let binary = CodecKind::Bincode.encode_file(&entry).unwrap();
+        assert!(binary.starts_with(b"MIRAbincode\n"));
+        assert!(binary.len() < CodecKind::Json.encode_file(&entry).unwrap().len() / 2);
+
+        let decoded: MemoryEntry = CodecKind::decode_file(&binary).unwrap();
+        assert_eq!(decoded.embedding, entry.embedding);
+        assert_eq!(decoded.created_at, entry.created_at);
+        assert_eq!(decoded.metadata, entry.metadata);
+
+        // 结构版本不一致时报错
+        let mut stale = CodecKind::Bincode.encode(&entry).unwrap();
+        stale[0] = BINCODE_SCHEMA_VERSION + 1;
+        assert!(CodecKind::Bincode.decode::<MemoryEntry>(&stale).unwrap_err().to_string().contains("结构版本"));
+    }
+}
@@ -0,0 +1,103 @@
+//! 向量距离度量
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+/// 距离度量方式
+///
+/// 分数语义与Qdrant保持一致：Cosine和Dot返回相似度（越大越相近），
+/// Euclidean返回距离（越小越相近），此时搜索阈值表示最大距离。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DistanceMetric {
+    /// 余弦相似度
+    #[default]
+    Cosine,
+    /// 点积
+    Dot,
+    /// 欧氏距离
+    Euclidean,
+}
+
+impl DistanceMetric {
+    /// 计算两个向量的分数，维度不一致时返回最差分数
+    pub fn score(&self, a: &[f32], b: &[f32]) -> f32 {
+        if a.len() != b.len() {
+            return self.worst_score();
+        }
+
+        match self {
+            DistanceMetric::Cosine => super::simd::cosine(a, b),
+            DistanceMetric::Dot => dot(a, b),
+            DistanceMetric::Euclidean => squared_l2(a, b).sqrt(),
+        }
+    }
+
+    /// 分数越大是否越相近
+    pub fn higher_is_better(&self) -> bool {
+        !matches!(self, DistanceMetric::Euclidean)
+    }
+
+    /// 排序比较 - 更相近的分数排在前面
+    pub fn compare(&self, a: f32, b: f32) -> Ordering {
+        let ordering = a.partial_cmp(&b).unwrap_or(Ordering::Equal);
+        if self.higher_is_better() { ordering.reverse() } else { ordering }
+    }
+
+    /// 分数是否满足搜索阈值
+    pub fn passes_threshold(&self, score: f32, threshold: f32) -> bool {
+        if self.higher_is_better() { score >= threshold } else { score <= threshold }
+    }
+
+    fn worst_score(&self) -> f32 {
+        match self {
+            DistanceMetric::Cosine => 0.0,
+            DistanceMetric::Dot => f32::NEG_INFINITY,
+            DistanceMetric::Euclidean => f32::INFINITY,
+        }
+    }
+}
+
+/// 点积
+///
+/// 使用多个独立累加器、按固定顺序求和，结果与平台、所选SIMD实现和线程数无关。
+pub fn dot(a: &[f32], b: &[f32]) -> f32 {
+    super::simd::dot(a, b)
+}
+
+/// 欧氏距离的平方
+pub fn squared_l2(a: &[f32], b: &[f32]) -> f32 {
+    super::simd::squared_l2(a, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scores_and_ordering() {
+        let a = [1.0, 0.0];
+        let b = [3.0, 4.0];
+
+        assert!((DistanceMetric::Cosine.score(&a, &b) - 0.6).abs() < 1e-6);
+        assert_eq!(DistanceMetric::Dot.score(&a, &b), 3.0);
+        assert!((DistanceMetric::Euclidean.score(&a, &b) - 20.0f32.sqrt()).abs() < 1e-6);
+
+        assert_eq!(DistanceMetric::Dot.compare(2.0, 1.0), Ordering::Less);
+        assert_eq!(DistanceMetric::Euclidean.compare(2.0, 1.0), Ordering::Greater);
+        assert!(DistanceMetric::Euclidean.passes_threshold(0.5, 1.0));
+        assert!(!DistanceMetric::Cosine.passes_threshold(0.5, 1.0));
+    }
+
+    #[test]
+    fn test_kernels_match_naive_loop() {
+        // 长度不是LANES的整数倍，覆盖尾部处理
+        let a: Vec<f32> = (0..771).map(|i| (i as f32 * 0.37).sin()).collect();
+        let b: Vec<f32> = (0..771).map(|i| (i as f32 * 0.11).cos()).collect();
+
+        let naive_dot: f32 = a.iter().zip(&b).map(|(x, y)| x * y).sum();
+        let naive_l2: f32 = a.iter().zip(&b).map(|(x, y)| (x - y) * (x - y)).sum();
+
+        assert!((dot(&a, &b) - naive_dot).abs() < 1e-3);
+        assert!((squared_l2(&a, &b) - naive_l2).abs() < 1e-3);
+    }
+}
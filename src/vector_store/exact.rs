@@ -0,0 +1,305 @@
+//! 精确暴力搜索 - 不使用近似索引，适合小数据集和黄金测试
+
+use super::DistanceMetric;
+use crate::bridge::{ScratchArena, ZigPerformanceUtils};
+use rayon::prelude::*;
+use std::cmp::Ordering;
+use uuid::Uuid;
+
+/// 候选数量达到该值时跨向量并行计算分数
+const PARALLEL_THRESHOLD: usize = 1024;
+
+/// 排序比较：先按分数（由度量决定方向），分数相同时按ID升序，保证结果确定
+pub fn compare_ranked(metric: DistanceMetric, a: (Uuid, f32), b: (Uuid, f32)) -> Ordering {
+    metric.compare(a.1, b.1).then_with(|| a.0.cmp(&b.0))
+}
+
+/// 对候选逐个精确计算分数，过滤阈值后按相近程度排序
+///
+/// 每个分数按固定顺序累加，同一输入在任何机器上都得到相同的分数和顺序。
+/// `key`返回候选的ID和向量。
+pub fn exact_search<'a, T, I, K>(
+    candidates: I,
+    query: &[f32],
+    metric: DistanceMetric,
+    threshold: f32,
+    key: K,
+) -> Vec<(&'a T, f32)>
+where
+    T: Sync,
+    I: IntoIterator<Item = &'a T>,
+    K: Fn(&T) -> (Uuid, &[f32]) + Sync,
+{
+    let mut ranked = score_and_filter(candidates.into_iter().collect(), query, metric, threshold, &key);
+    ranked.sort_by(|a, b| compare_ranked(metric, (key(a.0).0, a.1), (key(b.0).0, b.1)));
+    ranked
+}
+
+/// 只保留前`limit`个结果的精确搜索，结果与`exact_search`截断到`limit`一致
+///
+/// 余弦度量在Zig中单趟打分并维护大小为`limit`的堆；其他度量计算全部分数后用`top_k_by`选择，
+/// 不对全部候选排序。
+pub fn exact_top_k<'a, T, I, K>(
+    candidates: I,
+    query: &[f32],
+    metric: DistanceMetric,
+    threshold: f32,
+    limit: usize,
+    key: K,
+) -> Vec<(&'a T, f32)>
+where
+    T: Sync,
+    I: IntoIterator<Item = &'a T>,
+    K: Fn(&T) -> (Uuid, &[f32]) + Sync,
+{
+    let candidates: Vec<&'a T> = candidates.into_iter().collect();
+    if limit == 0 || candidates.is_empty() {
+        return Vec::new();
+    }
+
+    let compare = |a: &(&'a T, f32), b: &(&'a T, f32)| compare_ranked(metric, (key(a.0).0, a.1), (key(b.0).0, b.1));
+    if metric == DistanceMetric::Cosine && !query.is_empty() {
+        if let Some(mut ranked) = zig_top_k_cosine(&candidates, query, threshold, limit, &key) {
+            ranked.sort_by(compare);
+            ranked.truncate(limit);
+            return ranked;
+        }
+    }
+
+    top_k_by(score_and_filter(candidates, query, metric, threshold, &key), limit, compare)
+}
+
+/// 有界堆top-k：只保留按`compare`排在最前的`k`个元素，结果从前到后排列
+///
+/// 堆顶是已保留元素中排在最后的一个，新元素只与堆顶比较，复杂度O(n log k)。
+/// `compare`为全序时结果与完整排序后截断一致。
+pub fn top_k_by<T, I, F>(items: I, k: usize, compare: F) -> Vec<T>
+where
+    I: IntoIterator<Item = T>,
+    F: Fn(&T, &T) -> Ordering,
+{
+    if k == 0 {
+        return Vec::new();
+    }
+
+    let mut heap: Vec<T> = Vec::new();
+    for item in items {
+        if heap.len() < k {
+            heap.push(item);
+            let mut child = heap.len() - 1;
+            while child > 0 {
+                let parent = (child - 1) / 2;
+                if compare(&heap[child], &heap[parent]) != Ordering::Greater {
+                    break;
+                }
+                heap.swap(child, parent);
+                child = parent;
+            }
+        } else if compare(&item, &heap[0]) == Ordering::Less {
+            heap[0] = item;
+            let mut parent = 0;
+            loop {
+                let mut last = parent;
+                for child in [2 * parent + 1, 2 * parent + 2] {
+                    if child < heap.len() && compare(&heap[child], &heap[last]) == Ordering::Greater {
+                        last = child;
+                    }
+                }
+                if last == parent {
+                    break;
+                }
+                heap.swap(parent, last);
+                parent = last;
+            }
+        }
+    }
+
+    heap.sort_by(compare);
+    heap
+}
+
+/// 计算全部候选的分数，去掉NaN和未达到阈值的结果
+fn score_and_filter<'a, T, K>(
+    candidates: Vec<&'a T>,
+    query: &[f32],
+    metric: DistanceMetric,
+    threshold: f32,
+    key: &K,
+) -> Vec<(&'a T, f32)>
+where
+    T: Sync,
+    K: Fn(&T) -> (Uuid, &[f32]) + Sync,
+{
+    let score = |candidate: &&'a T| {
+        let (_, embedding) = key(candidate);
+        metric.score(query, embedding)
+    };
+
+    // 余弦分数一次FFI调用批量计算；其余度量的单个向量计算已经向量化，只在候选足够多时跨向量并行
+    let scores: Vec<f32> = if metric == DistanceMetric::Cosine && !query.is_empty() {
+        batch_cosine_scores(&candidates, query, key)
+    } else if candidates.len() >= PARALLEL_THRESHOLD {
+        candidates.par_iter().map(score).collect()
+    } else {
+        candidates.iter().map(score).collect()
+    };
+
+    candidates.into_iter()
+        .zip(scores)
+        .filter(|(_, score)| !score.is_nan() && metric.passes_threshold(*score, threshold))
+        .collect()
+}
+
+/// 同维候选交给Zig选出前`limit`个，排序键为候选ID；维度不一致的候选按最差分数单独参与。
+/// 返回的结果未排序，Zig调用失败时返回None
+fn zig_top_k_cosine<'a, T, K>(
+    candidates: &[&'a T],
+    query: &[f32],
+    threshold: f32,
+    limit: usize,
+    key: &K,
+) -> Option<Vec<(&'a T, f32)>>
+where
+    K: Fn(&T) -> (Uuid, &[f32]),
+{
+    let mut ranked = Vec::new();
+    let mut rows = Vec::with_capacity(candidates.len());
+    for (i, candidate) in candidates.iter().enumerate() {
+        let (_, embedding) = key(candidate);
+        if embedding.len() == query.len() {
+            rows.push(i);
+        } else {
+            let score = DistanceMetric::Cosine.score(query, embedding);
+            if DistanceMetric::Cosine.passes_threshold(score, threshold) {
+                ranked.push((*candidate, score));
+            }
+        }
+    }
+
+    let top = ScratchArena::with(|scratch| {
+        let mut matrix = scratch.alloc_slice(rows.len() * query.len(), 0.0f32);
+        let mut keys = scratch.alloc_slice(rows.len(), [0u64; 2]);
+        for ((row, sort_key), &i) in matrix.chunks_exact_mut(query.len()).zip(keys.iter_mut()).zip(&rows) {
+            let (id, embedding) = key(candidates[i]);
+            row.copy_from_slice(embedding);
+            let (high, low) = id.as_u64_pair();
+            *sort_key = [high, low];
+        }
+        ZigPerformanceUtils::top_k_cosine_similarity(query, &matrix, &keys, limit, threshold)
+    })
+    .ok()?;
+
+    ranked.extend(top.into_iter().map(|(row, score)| (candidates[rows[row]], score)));
+    Some(ranked)
+}
+
+/// 同维候选在线程暂存区中拼成连续矩阵交给Zig打分，累加顺序与`distance::dot`一致；
+/// 维度不一致的候选按`DistanceMetric::score`给出最差分数
+fn batch_cosine_scores<T, K>(candidates: &[&T], query: &[f32], key: &K) -> Vec<f32>
+where
+    K: Fn(&T) -> (Uuid, &[f32]),
+{
+    let mut scores = vec![0.0f32; candidates.len()];
+    let mut rows = Vec::with_capacity(candidates.len());
+    for (i, candidate) in candidates.iter().enumerate() {
+        let (_, embedding) = key(candidate);
+        if embedding.len() == query.len() {
+            rows.push(i);
+        } else {
+            scores[i] = DistanceMetric::Cosine.score(query, embedding);
+        }
+    }
+
+    let batch = ScratchArena::with(|scratch| {
+        let mut matrix = scratch.alloc_slice(rows.len() * query.len(), 0.0f32);
+        for (row, &i) in matrix.chunks_exact_mut(query.len()).zip(&rows) {
+            row.copy_from_slice(key(candidates[i]).1);
+        }
+        ZigPerformanceUtils::batch_cosine_similarity(query, &matrix)
+    });
+
+    match batch {
+        Ok(batch) => {
+            for (i, score) in rows.into_iter().zip(batch) {
+                scores[i] = score;
+            }
+        }
+        Err(_) => {
+            for i in rows {
+                scores[i] = DistanceMetric::Cosine.score(query, key(candidates[i]).1);
+            }
+        }
+    }
+    scores
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_cosine_matches_rust_kernel() {
+        // 维度不是8的整数倍，并混入维度不一致的点
+        let mut points: Vec<(Uuid, Vec<f32>)> = (0..300)
+            .map(|p| (Uuid::new_v4(), (0..771).map(|i| ((p * 31 + i) as f32 * 0.13).sin()).collect()))
+            .collect();
+        points.push((Uuid::new_v4(), vec![1.0; 3]));
+        let query: Vec<f32> = (0..771).map(|i| (i as f32 * 0.07).cos()).collect();
+
+        let ranked = exact_search(&points, &query, DistanceMetric::Cosine, -1.0, |p| (p.0, p.1.as_slice()));
+        assert_eq!(ranked.len(), points.len());
+        for (point, score) in ranked {
+            assert_eq!(score, DistanceMetric::Cosine.score(&query, &point.1));
+        }
+    }
+
+    #[test]
+    fn test_top_k_matches_truncated_full_ranking() {
+        // 大量同分的点让截断边界落在同分区间内
+        let points: Vec<(Uuid, Vec<f32>)> = (0..500)
+            .map(|p| (Uuid::new_v4(), vec![(p % 7) as f32, 1.0, ((p % 3) as f32).sqrt()]))
+            .collect();
+        let query = [0.8, 0.3, 0.5];
+
+        for metric in [DistanceMetric::Cosine, DistanceMetric::Dot, DistanceMetric::Euclidean] {
+            let threshold = if metric.higher_is_better() { -10.0 } else { 100.0 };
+            let key = |p: &(Uuid, Vec<f32>)| (p.0, p.1.as_slice());
+            let top: Vec<Uuid> = exact_top_k(&points, &query, metric, threshold, 25, key)
+                .iter().map(|(p, _)| p.0).collect();
+            let full: Vec<Uuid> = exact_search(&points, &query, metric, threshold, key)
+                .iter().take(25).map(|(p, _)| p.0).collect();
+            assert_eq!(top, full, "{:?}", metric);
+        }
+    }
+
+    #[test]
+    fn test_top_k_by_matches_sort_and_truncate() {
+        let values: Vec<u32> = (0..1000u32).map(|i| i.wrapping_mul(2_654_435_761) % 97).collect();
+        for k in [0, 1, 5, 96, 1000, 2000] {
+            let mut expected = values.clone();
+            expected.sort_by(|a, b| b.cmp(a));
+            expected.truncate(k);
+            assert_eq!(top_k_by(values.iter().copied(), k, |a, b| b.cmp(a)), expected, "k = {}", k);
+        }
+    }
+
+    #[test]
+    fn test_ties_are_broken_by_id() {
+        let mut points: Vec<(Uuid, Vec<f32>)> = (0..8)
+            .map(|_| (Uuid::new_v4(), vec![1.0, 0.0]))
+            .collect();
+        points.push((Uuid::new_v4(), vec![0.0, 1.0]));
+
+        let ranked = exact_search(&points, &[1.0, 0.0], DistanceMetric::Cosine, 0.5, |p| (p.0, p.1.as_slice()));
+        let ids: Vec<Uuid> = ranked.iter().map(|(p, _)| p.0).collect();
+
+        let mut expected: Vec<Uuid> = points[..8].iter().map(|p| p.0).collect();
+        expected.sort();
+        assert_eq!(ids, expected);
+
+        // 输入顺序不影响结果
+        let reversed: Vec<_> = points.iter().rev().cloned().collect();
+        let ranked_again = exact_search(&reversed, &[1.0, 0.0], DistanceMetric::Cosine, 0.5, |p| (p.0, p.1.as_slice()));
+        assert_eq!(ranked_again.iter().map(|(p, _)| p.0).collect::<Vec<_>>(), ids);
+    }
+}
@@ -0,0 +1,147 @@
+//! 向量搜索的元数据过滤条件
+
+use crate::MemoryType;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// 搜索过滤条件 - 由向量存储下推执行
+#[derive(Debug, Clone, Default)]
+pub struct SearchFilter {
+    /// 只返回属于该用户的向量
+    pub user_id: Option<String>,
+    /// 只返回这些记忆类型
+    pub memory_types: Option<Vec<MemoryType>>,
+    /// 创建时间下限（包含）
+    pub created_after: Option<DateTime<Utc>>,
+    /// 创建时间上限（包含）
+    pub created_before: Option<DateTime<Utc>>,
+    /// 自定义payload字段精确匹配
+    pub fields: HashMap<String, String>,
+}
+
+impl SearchFilter {
+    /// 按用户过滤
+    pub fn for_user(user_id: impl Into<String>) -> Self {
+        Self {
+            user_id: Some(user_id.into()),
+            ..Default::default()
+        }
+    }
+
+    /// 设置记忆类型过滤
+    pub fn with_memory_types(mut self, memory_types: Vec<MemoryType>) -> Self {
+        self.memory_types = Some(memory_types);
+        self
+    }
+
+    /// 设置创建时间范围
+    pub fn with_time_range(
+        mut self,
+        after: Option<DateTime<Utc>>,
+        before: Option<DateTime<Utc>>,
+    ) -> Self {
+        self.created_after = after;
+        self.created_before = before;
+        self
+    }
+
+    /// 添加自定义字段匹配
+    pub fn with_field(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.fields.insert(key.into(), value.into());
+        self
+    }
+
+    /// 检查payload是否满足过滤条件
+    pub fn matches(&self, payload: &Value) -> bool {
+        if let Some(ref user_id) = self.user_id {
+            if payload.get(PAYLOAD_USER_ID).and_then(Value::as_str) != Some(user_id.as_str()) {
+                return false;
+            }
+        }
+
+        if let Some(ref memory_types) = self.memory_types {
+            let matched = payload.get(PAYLOAD_MEMORY_TYPE)
+                .and_then(|v| serde_json::from_value::<MemoryType>(v.clone()).ok())
+                .is_some_and(|memory_type| memory_types.contains(&memory_type));
+            if !matched {
+                return false;
+            }
+        }
+
+        if self.created_after.is_some() || self.created_before.is_some() {
+            let Some(created_at) = payload.get(PAYLOAD_CREATED_AT_TS).and_then(Value::as_i64) else {
+                return false;
+            };
+            if self.created_after.is_some_and(|after| created_at < after.timestamp()) {
+                return false;
+            }
+            if self.created_before.is_some_and(|before| created_at > before.timestamp()) {
+                return false;
+            }
+        }
+
+        self.fields.iter().all(|(key, expected)| {
+            match payload.get(key) {
+                Some(Value::String(s)) => s == expected,
+                Some(other) => other.to_string() == *expected,
+                None => false,
+            }
+        })
+    }
+}
+
+/// payload中的用户ID字段
+pub const PAYLOAD_USER_ID: &str = "user_id";
+/// payload中的记忆类型字段
+pub const PAYLOAD_MEMORY_TYPE: &str = "memory_type";
+/// payload中的创建时间戳字段（Unix秒）
+pub const PAYLOAD_CREATED_AT_TS: &str = "created_at_ts";
+/// payload中的过期时间戳字段（Unix秒），缺失表示永不过期
+pub const PAYLOAD_EXPIRES_AT_TS: &str = "expires_at_ts";
+
+/// payload是否在`now`时已过期
+pub fn is_expired(payload: &Value, now: DateTime<Utc>) -> bool {
+    payload.get(PAYLOAD_EXPIRES_AT_TS)
+        .and_then(Value::as_i64)
+        .is_some_and(|expires_at| expires_at <= now.timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_filter_matches_user_and_type() {
+        let payload = json!({
+            "user_id": "alice",
+            "memory_type": "LongTerm",
+            "created_at_ts": 1_700_000_000i64,
+        });
+
+        assert!(SearchFilter::for_user("alice").matches(&payload));
+        assert!(!SearchFilter::for_user("bob").matches(&payload));
+        assert!(SearchFilter::for_user("alice")
+            .with_memory_types(vec![MemoryType::LongTerm])
+            .matches(&payload));
+        assert!(!SearchFilter::default()
+            .with_memory_types(vec![MemoryType::ShortTerm])
+            .matches(&payload));
+    }
+
+    #[test]
+    fn test_filter_matches_time_range_and_fields() {
+        let payload = json!({
+            "created_at_ts": 1_700_000_000i64,
+            "source": "chat",
+        });
+        let before = DateTime::from_timestamp(1_600_000_000, 0);
+        let after = DateTime::from_timestamp(1_800_000_000, 0);
+
+        assert!(SearchFilter::default().with_time_range(before, after).matches(&payload));
+        assert!(!SearchFilter::default().with_time_range(after, None).matches(&payload));
+        assert!(SearchFilter::default().with_field("source", "chat").matches(&payload));
+        assert!(!SearchFilter::default().with_field("source", "import").matches(&payload));
+    }
+}
@@ -0,0 +1,425 @@
+//! 向量存储操作指标
+//!
+//! `InstrumentedVectorStore`包装任意实现，按操作记录调用次数、错误次数和延迟分布，
+//! 通过`VectorStore::metrics`读取快照。启用`performance`特性时同时写入全局metrics注册表。
+//! 每次调用都在`vector_store` span中执行，针对单个点的操作附带`memory_id`。
+
+use super::{
+    DistanceMetric, HealthStatus, HitStream, ScrollPage, SearchFilter, SearchHit, SparseVector,
+    StoredVector, VectorSpace, VectorStore,
+};
+use async_trait::async_trait;
+use dashmap::DashMap;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tracing::Instrument;
+use uuid::Uuid;
+
+/// 延迟直方图各桶的上界(毫秒)，超过最大上界的调用计入额外的溢出桶
+pub const LATENCY_BUCKETS_MS: [u64; 10] = [1, 2, 5, 10, 25, 50, 100, 250, 1_000, 5_000];
+
+/// 超过该耗时的操作会记录警告日志
+const SLOW_OPERATION: Duration = Duration::from_millis(500);
+
+/// 单个操作的指标快照
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct OperationMetrics {
+    /// 调用次数
+    pub calls: u64,
+    /// 返回错误的次数
+    pub errors: u64,
+    /// 累计耗时
+    pub total_latency: Duration,
+    /// 最大耗时
+    pub max_latency: Duration,
+    /// 各桶调用次数，与`LATENCY_BUCKETS_MS`一一对应，最后一个为溢出桶
+    pub latency_buckets: Vec<u64>,
+}
+
+impl OperationMetrics {
+    /// 平均耗时
+    pub fn mean_latency(&self) -> Duration {
+        if self.calls == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(self.total_latency.as_secs_f64() / self.calls as f64)
+    }
+
+    /// 延迟分位数的上界估计，例如`latency_quantile(0.99)`；没有调用时返回None
+    pub fn latency_quantile(&self, quantile: f64) -> Option<Duration> {
+        if self.calls == 0 {
+            return None;
+        }
+
+        let rank = ((self.calls as f64 * quantile.clamp(0.0, 1.0)).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, count) in self.latency_buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Some(match LATENCY_BUCKETS_MS.get(index) {
+                    Some(&upper_ms) => Duration::from_millis(upper_ms).min(self.max_latency),
+                    None => self.max_latency,
+                });
+            }
+        }
+        Some(self.max_latency)
+    }
+}
+
+/// 单个操作的原子计数器
+#[derive(Debug, Default)]
+struct OperationRecorder {
+    calls: AtomicU64,
+    errors: AtomicU64,
+    total_micros: AtomicU64,
+    max_micros: AtomicU64,
+    buckets: [AtomicU64; LATENCY_BUCKETS_MS.len() + 1],
+}
+
+impl OperationRecorder {
+    fn record(&self, elapsed: Duration, ok: bool) {
+        let micros = elapsed.as_micros() as u64;
+        let bucket = LATENCY_BUCKETS_MS.iter()
+            .position(|&upper_ms| micros <= upper_ms * 1_000)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        if !ok {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        self.total_micros.fetch_add(micros, Ordering::Relaxed);
+        self.max_micros.fetch_max(micros, Ordering::Relaxed);
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> OperationMetrics {
+        OperationMetrics {
+            calls: self.calls.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            total_latency: Duration::from_micros(self.total_micros.load(Ordering::Relaxed)),
+            max_latency: Duration::from_micros(self.max_micros.load(Ordering::Relaxed)),
+            latency_buckets: self.buckets.iter().map(|b| b.load(Ordering::Relaxed)).collect(),
+        }
+    }
+}
+
+/// 按操作名汇总的指标
+#[derive(Debug, Default)]
+pub struct StoreMetrics {
+    operations: DashMap<&'static str, OperationRecorder>,
+}
+
+impl StoreMetrics {
+    /// 记录一次操作
+    pub fn record(&self, operation: &'static str, elapsed: Duration, ok: bool) {
+        self.operations.entry(operation).or_default().record(elapsed, ok);
+
+        if elapsed >= SLOW_OPERATION {
+            tracing::warn!("向量存储操作 {} 耗时 {:?}", operation, elapsed);
+        }
+
+        #[cfg(feature = "performance")]
+        {
+            ::metrics::histogram!("mira_vector_store_operation_seconds", "operation" => operation)
+                .record(elapsed.as_secs_f64());
+            if !ok {
+                ::metrics::counter!("mira_vector_store_operation_errors_total", "operation" => operation)
+                    .increment(1);
+            }
+        }
+    }
+
+    /// 当前所有操作的指标快照
+    pub fn snapshot(&self) -> HashMap<String, OperationMetrics> {
+        self.operations.iter()
+            .map(|entry| (entry.key().to_string(), entry.value().snapshot()))
+            .collect()
+    }
+
+    /// 清空已记录的指标
+    pub fn reset(&self) {
+        self.operations.clear();
+    }
+}
+
+/// 记录操作指标的向量存储包装
+#[derive(Debug)]
+pub struct InstrumentedVectorStore<S: VectorStore + ?Sized> {
+    inner: Arc<S>,
+    metrics: Arc<StoreMetrics>,
+}
+
+impl<S: VectorStore + ?Sized> InstrumentedVectorStore<S> {
+    /// 包装存储，指标从零开始
+    pub fn new(inner: Arc<S>) -> Self {
+        Self {
+            inner,
+            metrics: Arc::new(StoreMetrics::default()),
+        }
+    }
+
+    /// 共享的指标记录器，可以在包装被移入其他结构后继续读取
+    pub fn recorder(&self) -> Arc<StoreMetrics> {
+        self.metrics.clone()
+    }
+
+    /// 执行操作并记录耗时和结果
+    async fn observe<T>(
+        &self,
+        operation: &'static str,
+        future: impl Future<Output = Result<T, S::Error>>,
+    ) -> Result<T, S::Error> {
+        self.observe_in(tracing::info_span!("vector_store", operation), operation, future).await
+    }
+
+    /// 针对单个点的操作，span中同时记录记忆ID
+    async fn observe_point<T>(
+        &self,
+        operation: &'static str,
+        id: Uuid,
+        future: impl Future<Output = Result<T, S::Error>>,
+    ) -> Result<T, S::Error> {
+        self.observe_in(tracing::info_span!("vector_store", operation, memory_id = %id), operation, future).await
+    }
+
+    async fn observe_in<T>(
+        &self,
+        span: tracing::Span,
+        operation: &'static str,
+        future: impl Future<Output = Result<T, S::Error>>,
+    ) -> Result<T, S::Error> {
+        let started = Instant::now();
+        let result = future.instrument(span).await;
+        self.metrics.record(operation, started.elapsed(), result.is_ok());
+        result
+    }
+}
+
+#[async_trait]
+impl<S: VectorStore + ?Sized> VectorStore for InstrumentedVectorStore<S> {
+    type Error = S::Error;
+
+    fn vector_size(&self) -> Option<usize> {
+        self.inner.vector_size()
+    }
+
+    fn emotion_vector_size(&self) -> Option<usize> {
+        self.inner.emotion_vector_size()
+    }
+
+    fn image_vector_size(&self) -> Option<usize> {
+        self.inner.image_vector_size()
+    }
+
+    fn distance_metric(&self) -> DistanceMetric {
+        self.inner.distance_metric()
+    }
+
+    fn metrics(&self) -> HashMap<String, OperationMetrics> {
+        self.metrics.snapshot()
+    }
+
+    async fn store_vector(
+        &self,
+        id: Uuid,
+        embedding: Vec<f32>,
+        metadata: String,
+    ) -> Result<(), Self::Error> {
+        self.observe_point("store_vector", id, self.inner.store_vector(id, embedding, metadata)).await
+    }
+
+    async fn store_vectors(
+        &self,
+        points: Vec<(Uuid, Vec<f32>, String)>,
+    ) -> Result<(), Self::Error> {
+        self.observe("store_vectors", self.inner.store_vectors(points)).await
+    }
+
+    async fn search_similar(
+        &self,
+        query_embedding: Vec<f32>,
+        limit: usize,
+        threshold: f32,
+        filter: Option<SearchFilter>,
+    ) -> Result<Vec<SearchHit>, Self::Error> {
+        self.observe(
+            "search_similar",
+            self.inner.search_similar(query_embedding, limit, threshold, filter),
+        ).await
+    }
+
+    /// 流式搜索的耗时取决于消费方，不计入指标
+    fn search_stream(
+        &self,
+        query_embedding: Vec<f32>,
+        limit: usize,
+        threshold: f32,
+        filter: Option<SearchFilter>,
+    ) -> HitStream<'_, Self::Error> {
+        self.inner.search_stream(query_embedding, limit, threshold, filter)
+    }
+
+    async fn search_similar_batch(
+        &self,
+        query_embeddings: Vec<Vec<f32>>,
+        limit: usize,
+        threshold: f32,
+        filter: Option<SearchFilter>,
+    ) -> Result<Vec<Vec<SearchHit>>, Self::Error> {
+        self.observe(
+            "search_similar_batch",
+            self.inner.search_similar_batch(query_embeddings, limit, threshold, filter),
+        ).await
+    }
+
+    async fn store_hybrid(
+        &self,
+        id: Uuid,
+        embedding: Vec<f32>,
+        sparse: SparseVector,
+        metadata: String,
+    ) -> Result<(), Self::Error> {
+        self.observe_point("store_hybrid", id, self.inner.store_hybrid(id, embedding, sparse, metadata)).await
+    }
+
+    async fn search_hybrid(
+        &self,
+        query_embedding: Vec<f32>,
+        query_sparse: SparseVector,
+        limit: usize,
+        threshold: f32,
+        filter: Option<SearchFilter>,
+    ) -> Result<Vec<SearchHit>, Self::Error> {
+        self.observe(
+            "search_hybrid",
+            self.inner.search_hybrid(query_embedding, query_sparse, limit, threshold, filter),
+        ).await
+    }
+
+    async fn store_multi_vector(
+        &self,
+        id: Uuid,
+        embedding: Vec<f32>,
+        emotion_embedding: Vec<f32>,
+        metadata: String,
+    ) -> Result<(), Self::Error> {
+        self.observe_point(
+            "store_multi_vector",
+            id,
+            self.inner.store_multi_vector(id, embedding, emotion_embedding, metadata),
+        ).await
+    }
+
+    async fn store_multi_vectors(
+        &self,
+        points: Vec<(Uuid, Vec<f32>, Vec<f32>, String)>,
+    ) -> Result<(), Self::Error> {
+        self.observe("store_multi_vectors", self.inner.store_multi_vectors(points)).await
+    }
+
+    async fn search_space(
+        &self,
+        space: VectorSpace,
+        query_embedding: Vec<f32>,
+        limit: usize,
+        threshold: f32,
+        filter: Option<SearchFilter>,
+    ) -> Result<Vec<SearchHit>, Self::Error> {
+        self.observe(
+            "search_space",
+            self.inner.search_space(space, query_embedding, limit, threshold, filter),
+        ).await
+    }
+
+    async fn update_vector(&self, id: Uuid, embedding: Vec<f32>) -> Result<(), Self::Error> {
+        self.observe_point("update_vector", id, self.inner.update_vector(id, embedding)).await
+    }
+
+    async fn attach_image_vector(&self, id: Uuid, image_embedding: Vec<f32>) -> Result<(), Self::Error> {
+        self.observe_point("attach_image_vector", id, self.inner.attach_image_vector(id, image_embedding)).await
+    }
+
+    async fn update_payload(&self, id: Uuid, patch: Value) -> Result<(), Self::Error> {
+        self.observe_point("update_payload", id, self.inner.update_payload(id, patch)).await
+    }
+
+    async fn delete_vector(&self, id: Uuid) -> Result<(), Self::Error> {
+        self.observe_point("delete_vector", id, self.inner.delete_vector(id)).await
+    }
+
+    async fn purge_expired(&self, filter: Option<SearchFilter>) -> Result<Vec<Uuid>, Self::Error> {
+        self.observe("purge_expired", self.inner.purge_expired(filter)).await
+    }
+
+    async fn count(&self, filter: Option<SearchFilter>) -> Result<u64, Self::Error> {
+        self.observe("count", self.inner.count(filter)).await
+    }
+
+    async fn estimated_count(&self, filter: Option<SearchFilter>) -> Result<u64, Self::Error> {
+        self.observe("estimated_count", self.inner.estimated_count(filter)).await
+    }
+
+    async fn get_vector(&self, id: Uuid) -> Result<Option<StoredVector>, Self::Error> {
+        self.observe_point("get_vector", id, self.inner.get_vector(id)).await
+    }
+
+    async fn get_payloads(&self, ids: &[Uuid]) -> Result<HashMap<Uuid, serde_json::Value>, Self::Error> {
+        self.observe("get_payloads", self.inner.get_payloads(ids)).await
+    }
+
+    async fn scroll(&self, offset: Option<String>, limit: usize) -> Result<ScrollPage, Self::Error> {
+        self.observe("scroll", self.inner.scroll(offset, limit)).await
+    }
+
+    async fn health_check(&self) -> Result<HealthStatus, Self::Error> {
+        self.observe("health_check", self.inner.health_check()).await
+    }
+
+    async fn get_stats(&self) -> Result<HashMap<String, u64>, Self::Error> {
+        self.observe("get_stats", self.inner.get_stats()).await
+    }
+
+    async fn create_collection(&self, name: &str, vector_size: usize) -> Result<(), Self::Error> {
+        self.observe("create_collection", self.inner.create_collection(name, vector_size)).await
+    }
+
+    async fn drop_collection(&self, name: &str) -> Result<(), Self::Error> {
+        self.observe("drop_collection", self.inner.drop_collection(name)).await
+    }
+
+    async fn list_collections(&self) -> Result<Vec<String>, Self::Error> {
+        self.observe("list_collections", self.inner.list_collections()).await
+    }
+
+    async fn collection_exists(&self, name: &str) -> Result<bool, Self::Error> {
+        self.observe("collection_exists", self.inner.collection_exists(name)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::MockVectorStore;
+
+    #[tokio::test]
+    async fn test_records_calls_errors_and_latency() {
+        let store = InstrumentedVectorStore::new(Arc::new(MockVectorStore::new().with_vector_size(2)));
+
+        store.store_vector(Uuid::new_v4(), vec![1.0, 0.0], "{}".to_string()).await.unwrap();
+        store.store_vector(Uuid::new_v4(), vec![1.0], "{}".to_string()).await.unwrap_err();
+        store.search_similar(vec![1.0, 0.0], 5, 0.0, None).await.unwrap();
+
+        let metrics = store.metrics();
+        let upserts = &metrics["store_vector"];
+        assert_eq!((upserts.calls, upserts.errors), (2, 1));
+        assert_eq!(upserts.latency_buckets.iter().sum::<u64>(), 2);
+        assert!(upserts.latency_quantile(1.0).unwrap() <= upserts.max_latency);
+        assert_eq!(metrics["search_similar"].calls, 1);
+        assert!(!metrics.contains_key("delete_vector"));
+    }
+}
@@ -0,0 +1,118 @@
+//! 嵌入模型更换后的向量迁移
+
+use super::VectorStore;
+use std::future::Future;
+
+/// payload中用于重新生成嵌入的文本字段
+const CONTENT_FIELD: &str = "content";
+
+/// 迁移进度
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReembedProgress {
+    /// 已写入目标存储的点数
+    pub migrated: usize,
+    /// 因payload缺少content而跳过的点数
+    pub skipped: usize,
+}
+
+/// 遍历`source`中的全部向量，用`embedder`根据payload中的content重新生成嵌入，
+/// 连同原payload写入`target`（通常是使用新集合名和新维度的存储）。
+///
+/// `embedder`按批接收文本并返回等长的嵌入列表；每写完一批调用一次`on_progress`。
+/// 源存储不会被修改，确认迁移结果后再切换和删除旧集合。
+pub async fn reembed_all<E, Fut, P>(
+    source: &dyn VectorStore<Error = anyhow::Error>,
+    target: &dyn VectorStore<Error = anyhow::Error>,
+    mut embedder: E,
+    batch_size: usize,
+    mut on_progress: P,
+) -> Result<ReembedProgress, anyhow::Error>
+where
+    E: FnMut(Vec<String>) -> Fut,
+    Fut: Future<Output = Result<Vec<Vec<f32>>, anyhow::Error>>,
+    P: FnMut(ReembedProgress),
+{
+    let batch_size = batch_size.max(1);
+    let mut progress = ReembedProgress::default();
+    let mut offset = None;
+
+    loop {
+        let page = source.scroll(offset, batch_size).await?;
+
+        let mut texts = Vec::with_capacity(page.points.len());
+        let mut pending = Vec::with_capacity(page.points.len());
+        for point in page.points {
+            match point.payload.get(CONTENT_FIELD).and_then(|v| v.as_str()) {
+                Some(content) => {
+                    texts.push(content.to_string());
+                    pending.push((point.id, point.payload.to_string()));
+                }
+                None => {
+                    tracing::warn!("跳过缺少content的向量: {}", point.id);
+                    progress.skipped += 1;
+                }
+            }
+        }
+
+        if !texts.is_empty() {
+            let embeddings = embedder(texts).await?;
+            if embeddings.len() != pending.len() {
+                return Err(anyhow::anyhow!(
+                    "Embedder returned {} embeddings for {} inputs",
+                    embeddings.len(),
+                    pending.len()
+                ));
+            }
+
+            let points: Vec<_> = pending.into_iter()
+                .zip(embeddings)
+                .map(|((id, metadata), embedding)| (id, embedding, metadata))
+                .collect();
+            progress.migrated += points.len();
+            target.store_vectors(points).await?;
+        }
+
+        on_progress(progress);
+
+        match page.next_offset {
+            Some(next) => offset = Some(next),
+            None => break,
+        }
+    }
+
+    tracing::info!("向量迁移完成: 迁移 {} 条, 跳过 {} 条", progress.migrated, progress.skipped);
+    Ok(progress)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::MockVectorStore;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_reembed_all_changes_dimension() {
+        let source = MockVectorStore::new().with_vector_size(2);
+        for i in 0..5 {
+            let metadata = serde_json::json!({ "content": format!("记忆{}", i) }).to_string();
+            source.store_vector(Uuid::new_v4(), vec![1.0, 0.0], metadata).await.unwrap();
+        }
+        source.store_vector(Uuid::new_v4(), vec![0.0, 1.0], "{}".to_string()).await.unwrap();
+
+        let target = MockVectorStore::new().with_vector_size(3);
+        let mut reports = 0;
+        let progress = reembed_all(
+            &source,
+            &target,
+            |texts: Vec<String>| async move {
+                Ok(texts.iter().map(|t| vec![t.len() as f32, 0.0, 1.0]).collect())
+            },
+            2,
+            |_| reports += 1,
+        ).await.unwrap();
+
+        assert_eq!(progress, ReembedProgress { migrated: 5, skipped: 1 });
+        assert_eq!(reports, 3);
+        assert_eq!(target.scroll(None, 10).await.unwrap().points.len(), 5);
+    }
+}
@@ -1,17 +1,30 @@
 //! Mock向量存储实现（用于测试）
 
-use super::VectorStore;
+use super::{
+    check_dimension, DistanceMetric, HealthStatus, ScrollPage, SearchFilter, SearchHit, SparseVector,
+    StoredVector, VectorSpace, VectorStore,
+};
+use super::codec::CodecKind;
+use super::exact::{compare_ranked, exact_search, exact_top_k, top_k_by};
+use super::filter::is_expired;
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
 /// 存储的向量数据
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct VectorData {
     id: Uuid,
     embedding: Vec<f32>,
+    sparse: Option<SparseVector>,
+    #[serde(default)]
+    emotion: Option<Vec<f32>>,
+    #[serde(default)]
+    image: Option<Vec<f32>>,
     metadata: String,
 }
 
@@ -19,6 +32,27 @@ struct VectorData {
 #[derive(Debug)]
 pub struct MockVectorStore {
     data: Arc<RwLock<HashMap<Uuid, VectorData>>>,
+    /// 已创建的集合名称及向量维度（仅做登记，不隔离数据）
+    collections: Arc<RwLock<HashMap<String, usize>>>,
+    /// 限定的向量维度，None时接受任意维度
+    vector_size: Option<usize>,
+    /// 距离度量
+    distance: DistanceMetric,
+    /// 情感向量维度，None时不支持情感向量
+    emotion_vector_size: Option<usize>,
+    /// 图片向量维度，None时不支持图片向量
+    image_vector_size: Option<usize>,
+    /// 持久化文件 - 构造时加载，drop时写回
+    persist_path: Option<PathBuf>,
+    /// 写入持久化文件使用的编码，读取时按文件头识别
+    codec: CodecKind,
+}
+
+/// 持久化文件内容
+#[derive(Serialize, Deserialize, Default)]
+struct PersistedState {
+    vectors: Vec<VectorData>,
+    collections: HashMap<String, usize>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -34,66 +68,212 @@ impl MockVectorStore {
     pub fn new() -> Self {
         Self {
             data: Arc::new(RwLock::new(HashMap::new())),
+            collections: Arc::new(RwLock::new(HashMap::new())),
+            vector_size: None,
+            distance: DistanceMetric::Cosine,
+            emotion_vector_size: None,
+            image_vector_size: None,
+            persist_path: None,
+            codec: CodecKind::default(),
         }
     }
 
-    /// 计算余弦相似度 - 优化版本，增加CPU密集型计算
-    fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
-        if a.len() != b.len() {
-            return 0.0;
-        }
+    /// 创建持久化的Mock存储 - 文件存在时加载其中的数据，drop时写回
+    pub fn persistent(path: impl Into<PathBuf>) -> Result<Self, anyhow::Error> {
+        let path = path.into();
+        let state = match std::fs::read(&path) {
+            Ok(bytes) => CodecKind::decode_file::<PersistedState>(&bytes)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => PersistedState::default(),
+            Err(e) => return Err(e.into()),
+        };
 
-        // 使用rayon进行并行计算
-        use rayon::prelude::*;
-        
-        // 并行计算点积
-        let dot_product: f32 = a.par_iter()
-            .zip(b.par_iter())
-            .map(|(x, y)| x * y)
-            .sum();
-        
-        // 并行计算向量范数
-        let norm_a: f32 = a.par_iter()
-            .map(|x| x * x)
-            .sum::<f32>()
-            .sqrt();
-        
-        let norm_b: f32 = b.par_iter()
-            .map(|x| x * x)
-            .sum::<f32>()
-            .sqrt();
+        let data = state.vectors.into_iter()
+            .map(|vector_data| (vector_data.id, vector_data))
+            .collect();
 
-        if norm_a == 0.0 || norm_b == 0.0 {
-            0.0
-        } else {
-            dot_product / (norm_a * norm_b)
-        }
+        Ok(Self {
+            data: Arc::new(RwLock::new(data)),
+            collections: Arc::new(RwLock::new(state.collections)),
+            vector_size: None,
+            distance: DistanceMetric::Cosine,
+            emotion_vector_size: None,
+            image_vector_size: None,
+            persist_path: Some(path),
+            codec: CodecKind::default(),
+        })
     }
-    
-    /// 高级向量运算 - 增加CPU密集型计算
-    fn advanced_vector_operations(vectors: &[Vec<f32>]) -> Vec<f32> {
-        use rayon::prelude::*;
-        
-        vectors.par_iter()
-            .map(|vec| {
-                // 进行复杂的向量运算
-                let mut result = 0.0f32;
-                
-                // 计算多个统计量
-                let mean = vec.iter().sum::<f32>() / vec.len() as f32;
-                let variance = vec.iter()
-                    .map(|x| (x - mean).powi(2))
-                    .sum::<f32>() / vec.len() as f32;
-                
-                // 进行复杂的数学运算
-                for i in 0..vec.len() {
-                    let x = vec[i];
-                    result += (x - mean).abs() * variance.sqrt() * (i as f32).sin();
-                }
-                
-                result
+
+    /// 立即写入持久化文件，非持久化存储为无操作
+    pub async fn persist(&self) -> Result<(), anyhow::Error> {
+        let Some(ref path) = self.persist_path else {
+            return Ok(());
+        };
+
+        let data = self.data.read().await;
+        let collections = self.collections.read().await;
+        Self::write_state(path, self.codec, &data, &collections)
+    }
+
+    /// 是否在drop时写回持久化文件
+    pub fn is_persistent(&self) -> bool {
+        self.persist_path.is_some()
+    }
+
+    /// 清空所有向量
+    pub async fn clear(&self) {
+        self.data.write().await.clear();
+    }
+
+    /// 先写临时文件再重命名，避免中途退出留下损坏的文件
+    fn write_state(
+        path: &Path,
+        codec: CodecKind,
+        data: &HashMap<Uuid, VectorData>,
+        collections: &HashMap<String, usize>,
+    ) -> Result<(), anyhow::Error> {
+        let state = PersistedState {
+            vectors: data.values().cloned().collect(),
+            collections: collections.clone(),
+        };
+
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, codec.encode_file(&state)?)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// 设置持久化文件的编码，下次写回时生效
+    pub fn with_codec(mut self, codec: CodecKind) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    /// 限定向量维度
+    pub fn with_vector_size(mut self, vector_size: usize) -> Self {
+        self.vector_size = Some(vector_size);
+        self
+    }
+
+    /// 设置距离度量
+    pub fn with_distance(mut self, distance: DistanceMetric) -> Self {
+        self.distance = distance;
+        self
+    }
+
+    /// 启用情感向量
+    pub fn with_emotion_vector_size(mut self, emotion_vector_size: usize) -> Self {
+        self.emotion_vector_size = Some(emotion_vector_size);
+        self
+    }
+
+    /// 启用图片向量
+    pub fn with_image_vector_size(mut self, image_vector_size: usize) -> Self {
+        self.image_vector_size = Some(image_vector_size);
+        self
+    }
+
+    /// 精确计算与查询向量的分数，过滤阈值并按相近程度排列（同分按ID排序）
+    fn rank_similar<'a>(
+        &self,
+        data: &'a HashMap<Uuid, VectorData>,
+        query_embedding: &[f32],
+        threshold: f32,
+        filter: Option<&SearchFilter>,
+    ) -> Vec<(&'a VectorData, f32)> {
+        self.rank_in_space(data, VectorSpace::Content, query_embedding, threshold, filter)
+    }
+
+    /// 在指定向量空间中排序，没有该空间向量的点不参与
+    fn rank_in_space<'a>(
+        &self,
+        data: &'a HashMap<Uuid, VectorData>,
+        space: VectorSpace,
+        query_embedding: &[f32],
+        threshold: f32,
+        filter: Option<&SearchFilter>,
+    ) -> Vec<(&'a VectorData, f32)> {
+        exact_search(
+            Self::candidates_in_space(data, space, filter),
+            query_embedding,
+            self.distance,
+            threshold,
+            |vector_data| Self::space_embedding(vector_data, space),
+        )
+    }
+
+    /// 只取指定向量空间中最相近的`limit`个点，不对全部候选排序
+    fn top_in_space<'a>(
+        &self,
+        data: &'a HashMap<Uuid, VectorData>,
+        space: VectorSpace,
+        query_embedding: &[f32],
+        threshold: f32,
+        limit: usize,
+        filter: Option<&SearchFilter>,
+    ) -> Vec<(&'a VectorData, f32)> {
+        exact_top_k(
+            Self::candidates_in_space(data, space, filter),
+            query_embedding,
+            self.distance,
+            threshold,
+            limit,
+            |vector_data| Self::space_embedding(vector_data, space),
+        )
+    }
+
+    fn candidates_in_space<'a>(
+        data: &'a HashMap<Uuid, VectorData>,
+        space: VectorSpace,
+        filter: Option<&SearchFilter>,
+    ) -> impl Iterator<Item = &'a VectorData> {
+        data.values()
+            .filter(move |vector_data| match space {
+                VectorSpace::Content => true,
+                VectorSpace::Emotion => vector_data.emotion.is_some(),
+                VectorSpace::Image => vector_data.image.is_some(),
             })
-            .collect()
+            .filter(move |vector_data| filter.is_none_or(|f| Self::payload_matches(&vector_data.metadata, f)))
+    }
+
+    fn space_embedding(vector_data: &VectorData, space: VectorSpace) -> (Uuid, &[f32]) {
+        let embedding = match space {
+            VectorSpace::Content => vector_data.embedding.as_slice(),
+            VectorSpace::Emotion => vector_data.emotion.as_deref().unwrap_or_default(),
+            VectorSpace::Image => vector_data.image.as_deref().unwrap_or_default(),
+        };
+        (vector_data.id, embedding)
+    }
+
+    /// RRF融合常数
+    const RRF_K: f32 = 60.0;
+
+    /// 构建搜索命中结果
+    fn to_hit(vector_data: &VectorData, score: f32) -> SearchHit {
+        SearchHit {
+            id: vector_data.id,
+            score,
+            payload: serde_json::from_str(&vector_data.metadata)
+                .unwrap_or(serde_json::Value::Null),
+        }
+    }
+
+    /// 构建遍历得到的点，包含全部向量
+    fn to_stored(vector_data: &VectorData) -> StoredVector {
+        StoredVector {
+            id: vector_data.id,
+            embedding: vector_data.embedding.clone(),
+            payload: serde_json::from_str(&vector_data.metadata)
+                .unwrap_or(serde_json::Value::Null),
+            emotion: vector_data.emotion.clone(),
+            image: vector_data.image.clone(),
+        }
+    }
+
+    /// 检查存储的metadata是否满足过滤条件
+    fn payload_matches(metadata: &str, filter: &SearchFilter) -> bool {
+        serde_json::from_str::<serde_json::Value>(metadata)
+            .map(|payload| filter.matches(&payload))
+            .unwrap_or(false)
     }
 }
 
@@ -101,15 +281,36 @@ impl MockVectorStore {
 impl VectorStore for MockVectorStore {
     type Error = anyhow::Error;
 
+    fn vector_size(&self) -> Option<usize> {
+        self.vector_size
+    }
+
+    fn distance_metric(&self) -> DistanceMetric {
+        self.distance
+    }
+
+    fn emotion_vector_size(&self) -> Option<usize> {
+        self.emotion_vector_size
+    }
+
+    fn image_vector_size(&self) -> Option<usize> {
+        self.image_vector_size
+    }
+
     async fn store_vector(
         &self,
         id: Uuid,
         embedding: Vec<f32>,
         metadata: String,
     ) -> Result<(), Self::Error> {
+        check_dimension(self.vector_size, &embedding)?;
+
         let vector_data = VectorData {
             id,
             embedding,
+            sparse: None,
+            emotion: None,
+            image: None,
             metadata,
         };
 
@@ -117,47 +318,327 @@ impl VectorStore for MockVectorStore {
         Ok(())
     }
 
+    async fn store_vectors(
+        &self,
+        points: Vec<(Uuid, Vec<f32>, String)>,
+    ) -> Result<(), Self::Error> {
+        for (_, embedding, _) in &points {
+            check_dimension(self.vector_size, embedding)?;
+        }
+
+        // 只获取一次写锁
+        let mut data = self.data.write().await;
+        for (id, embedding, metadata) in points {
+            data.insert(id, VectorData { id, embedding, sparse: None, emotion: None, image: None, metadata });
+        }
+        Ok(())
+    }
+
     async fn search_similar(
         &self,
         query_embedding: Vec<f32>,
         limit: usize,
         threshold: f32,
-    ) -> Result<Vec<Uuid>, Self::Error> {
+        filter: Option<SearchFilter>,
+    ) -> Result<Vec<SearchHit>, Self::Error> {
+        check_dimension(self.vector_size, &query_embedding)?;
+
         let data = self.data.read().await;
         
-        // 使用rayon进行并行相似度计算
-        use rayon::prelude::*;
-        
-        let mut similarities: Vec<(Uuid, f32)> = data.values()
-            .collect::<Vec<_>>()
-            .par_iter()
-            .map(|vector_data| {
-                let similarity = Self::cosine_similarity(&query_embedding, &vector_data.embedding);
-                (vector_data.id, similarity)
+        let similarities = self.top_in_space(
+            &data, VectorSpace::Content, &query_embedding, threshold, limit, filter.as_ref(),
+        );
+
+        let result = similarities.into_iter()
+            .map(|(vector_data, score)| Self::to_hit(vector_data, score))
+            .collect();
+
+        Ok(result)
+    }
+
+    async fn search_similar_batch(
+        &self,
+        query_embeddings: Vec<Vec<f32>>,
+        limit: usize,
+        threshold: f32,
+        filter: Option<SearchFilter>,
+    ) -> Result<Vec<Vec<SearchHit>>, Self::Error> {
+        for query_embedding in &query_embeddings {
+            check_dimension(self.vector_size, query_embedding)?;
+        }
+
+        // 所有查询共享同一把读锁
+        let data = self.data.read().await;
+
+        let results = query_embeddings.iter()
+            .map(|query_embedding| {
+                self.top_in_space(&data, VectorSpace::Content, query_embedding, threshold, limit, filter.as_ref())
+                    .into_iter()
+                    .map(|(vector_data, score)| Self::to_hit(vector_data, score))
+                    .collect()
             })
-            .filter(|(_, similarity)| *similarity >= threshold)
             .collect();
 
-        // 并行排序
-        similarities.par_sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        Ok(results)
+    }
 
-        // 进行额外的CPU密集型计算
-        if !similarities.is_empty() {
-            let vectors: Vec<Vec<f32>> = data.values()
-                .map(|v| v.embedding.clone())
-                .collect();
-            
-            // 执行高级向量运算
-            let _advanced_results = Self::advanced_vector_operations(&vectors);
+    async fn store_hybrid(
+        &self,
+        id: Uuid,
+        embedding: Vec<f32>,
+        sparse: SparseVector,
+        metadata: String,
+    ) -> Result<(), Self::Error> {
+        check_dimension(self.vector_size, &embedding)?;
+
+        let vector_data = VectorData {
+            id,
+            embedding,
+            sparse: Some(sparse),
+            emotion: None,
+            image: None,
+            metadata,
+        };
+
+        self.data.write().await.insert(id, vector_data);
+        Ok(())
+    }
+
+    async fn store_multi_vector(
+        &self,
+        id: Uuid,
+        embedding: Vec<f32>,
+        emotion_embedding: Vec<f32>,
+        metadata: String,
+    ) -> Result<(), Self::Error> {
+        if self.emotion_vector_size.is_none() {
+            return Err(anyhow::anyhow!("Emotion vectors are not enabled for this store"));
         }
+        check_dimension(self.vector_size, &embedding)?;
+        check_dimension(self.emotion_vector_size, &emotion_embedding)?;
 
-        // 取前limit个结果
-        let result = similarities.into_iter()
+        let vector_data = VectorData {
+            id,
+            embedding,
+            sparse: None,
+            emotion: Some(emotion_embedding),
+            image: None,
+            metadata,
+        };
+
+        self.data.write().await.insert(id, vector_data);
+        Ok(())
+    }
+
+    async fn store_multi_vectors(
+        &self,
+        points: Vec<(Uuid, Vec<f32>, Vec<f32>, String)>,
+    ) -> Result<(), Self::Error> {
+        if self.emotion_vector_size.is_none() {
+            return Err(anyhow::anyhow!("Emotion vectors are not enabled for this store"));
+        }
+        for (_, embedding, emotion_embedding, _) in &points {
+            check_dimension(self.vector_size, embedding)?;
+            check_dimension(self.emotion_vector_size, emotion_embedding)?;
+        }
+
+        // 只获取一次写锁
+        let mut data = self.data.write().await;
+        for (id, embedding, emotion_embedding, metadata) in points {
+            data.insert(id, VectorData { id, embedding, sparse: None, emotion: Some(emotion_embedding), image: None, metadata });
+        }
+        Ok(())
+    }
+
+    async fn attach_image_vector(&self, id: Uuid, image_embedding: Vec<f32>) -> Result<(), Self::Error> {
+        if self.image_vector_size.is_none() {
+            return Err(anyhow::anyhow!("Image vectors are not enabled for this store"));
+        }
+        check_dimension(self.image_vector_size, &image_embedding)?;
+
+        let mut data = self.data.write().await;
+        let vector_data = data.get_mut(&id).ok_or(MockError::NotFound { id })?;
+        vector_data.image = Some(image_embedding);
+        Ok(())
+    }
+
+    async fn search_space(
+        &self,
+        space: VectorSpace,
+        query_embedding: Vec<f32>,
+        limit: usize,
+        threshold: f32,
+        filter: Option<SearchFilter>,
+    ) -> Result<Vec<SearchHit>, Self::Error> {
+        match space {
+            VectorSpace::Content => check_dimension(self.vector_size, &query_embedding)?,
+            VectorSpace::Emotion if self.emotion_vector_size.is_some() => {
+                check_dimension(self.emotion_vector_size, &query_embedding)?
+            }
+            VectorSpace::Emotion => {
+                return Err(anyhow::anyhow!("Emotion vectors are not enabled for this store"));
+            }
+            VectorSpace::Image if self.image_vector_size.is_some() => {
+                check_dimension(self.image_vector_size, &query_embedding)?
+            }
+            VectorSpace::Image => {
+                return Err(anyhow::anyhow!("Image vectors are not enabled for this store"));
+            }
+        }
+
+        let data = self.data.read().await;
+
+        Ok(self.top_in_space(&data, space, &query_embedding, threshold, limit, filter.as_ref())
+            .into_iter()
+            .map(|(vector_data, score)| Self::to_hit(vector_data, score))
+            .collect())
+    }
+
+    async fn search_hybrid(
+        &self,
+        query_embedding: Vec<f32>,
+        query_sparse: SparseVector,
+        limit: usize,
+        threshold: f32,
+        filter: Option<SearchFilter>,
+    ) -> Result<Vec<SearchHit>, Self::Error> {
+        check_dimension(self.vector_size, &query_embedding)?;
+
+        let data = self.data.read().await;
+
+        let dense = self.rank_similar(&data, &query_embedding, threshold, filter.as_ref());
+
+        let mut sparse: Vec<(&VectorData, f32)> = data.values()
+            .filter(|vector_data| filter.as_ref().is_none_or(|f| Self::payload_matches(&vector_data.metadata, f)))
+            .filter_map(|vector_data| {
+                let score = vector_data.sparse.as_ref()?.dot(&query_sparse);
+                (score > 0.0).then_some((vector_data, score))
+            })
+            .collect();
+        sparse.sort_by(|a, b| compare_ranked(DistanceMetric::Dot, (a.0.id, a.1), (b.0.id, b.1)));
+
+        // 倒数排名融合
+        let mut fused: HashMap<Uuid, (&VectorData, f32)> = HashMap::new();
+        for ranking in [dense, sparse] {
+            for (rank, (vector_data, _)) in ranking.into_iter().enumerate() {
+                fused.entry(vector_data.id).or_insert((vector_data, 0.0)).1 +=
+                    1.0 / (Self::RRF_K + rank as f32 + 1.0);
+            }
+        }
+
+        // RRF融合分数越大越好，只为选中的点解析payload
+        let top = top_k_by(fused.into_values(), limit, |a, b| {
+            compare_ranked(DistanceMetric::Dot, (a.0.id, a.1), (b.0.id, b.1))
+        });
+
+        Ok(top.into_iter()
+            .map(|(vector_data, score)| Self::to_hit(vector_data, score))
+            .collect())
+    }
+
+    async fn count(&self, filter: Option<SearchFilter>) -> Result<u64, Self::Error> {
+        let data = self.data.read().await;
+        let count = match filter {
+            Some(filter) => data.values()
+                .filter(|vector_data| Self::payload_matches(&vector_data.metadata, &filter))
+                .count(),
+            None => data.len(),
+        };
+        Ok(count as u64)
+    }
+
+    async fn purge_expired(&self, filter: Option<SearchFilter>) -> Result<Vec<Uuid>, Self::Error> {
+        let now = chrono::Utc::now();
+        let mut data = self.data.write().await;
+
+        let expired: Vec<Uuid> = data.values()
+            .filter(|vector_data| {
+                serde_json::from_str::<serde_json::Value>(&vector_data.metadata)
+                    .is_ok_and(|payload| {
+                        is_expired(&payload, now) && filter.as_ref().is_none_or(|f| f.matches(&payload))
+                    })
+            })
+            .map(|vector_data| vector_data.id)
+            .collect();
+
+        for id in &expired {
+            data.remove(id);
+        }
+        Ok(expired)
+    }
+
+    async fn get_vector(&self, id: Uuid) -> Result<Option<StoredVector>, Self::Error> {
+        let data = self.data.read().await;
+
+        Ok(data.get(&id).map(Self::to_stored))
+    }
+
+    async fn get_payloads(&self, ids: &[Uuid]) -> Result<HashMap<Uuid, serde_json::Value>, Self::Error> {
+        let data = self.data.read().await;
+
+        Ok(ids.iter()
+            .filter_map(|id| data.get(id))
+            .map(|vector_data| {
+                let payload = serde_json::from_str(&vector_data.metadata).unwrap_or(serde_json::Value::Null);
+                (vector_data.id, payload)
+            })
+            .collect())
+    }
+
+    async fn scroll(&self, offset: Option<String>, limit: usize) -> Result<ScrollPage, Self::Error> {
+        let start = offset.as_deref()
+            .map(Uuid::parse_str)
+            .transpose()
+            .map_err(|_| anyhow::anyhow!("Invalid scroll offset: {:?}", offset))?;
+
+        let data = self.data.read().await;
+
+        // 按ID排序保证游标稳定，游标为下一页第一个点的ID
+        let mut ids: Vec<&Uuid> = data.keys()
+            .filter(|id| start.is_none_or(|start| **id >= start))
+            .collect();
+        ids.sort();
+
+        let points = ids.iter()
             .take(limit)
-            .map(|(id, _)| id)
+            .map(|id| Self::to_stored(&data[*id]))
             .collect();
 
-        Ok(result)
+        Ok(ScrollPage {
+            points,
+            next_offset: ids.get(limit).map(|id| id.to_string()),
+        })
+    }
+
+    async fn update_vector(&self, id: Uuid, embedding: Vec<f32>) -> Result<(), Self::Error> {
+        check_dimension(self.vector_size, &embedding)?;
+
+        let mut data = self.data.write().await;
+
+        let vector_data = data.get_mut(&id)
+            .ok_or_else(|| anyhow::anyhow!("Vector not found: {}", id))?;
+        vector_data.embedding = embedding;
+        Ok(())
+    }
+
+    async fn update_payload(&self, id: Uuid, patch: serde_json::Value) -> Result<(), Self::Error> {
+        let serde_json::Value::Object(patch) = patch else {
+            return Err(anyhow::anyhow!("Payload patch must be a JSON object"));
+        };
+
+        let mut data = self.data.write().await;
+
+        let vector_data = data.get_mut(&id)
+            .ok_or_else(|| anyhow::anyhow!("Vector not found: {}", id))?;
+
+        let mut payload: serde_json::Value = serde_json::from_str(&vector_data.metadata)?;
+        if let serde_json::Value::Object(ref mut map) = payload {
+            map.extend(patch);
+        } else {
+            payload = serde_json::Value::Object(patch);
+        }
+        vector_data.metadata = serde_json::to_string(&payload)?;
+        Ok(())
     }
 
     async fn delete_vector(&self, id: Uuid) -> Result<(), Self::Error> {
@@ -170,6 +651,39 @@ impl VectorStore for MockVectorStore {
         }
     }
 
+    async fn create_collection(&self, name: &str, vector_size: usize) -> Result<(), Self::Error> {
+        let mut collections = self.collections.write().await;
+
+        if collections.contains_key(name) {
+            return Err(anyhow::anyhow!("Collection already exists: {}", name));
+        }
+        collections.insert(name.to_string(), vector_size);
+        Ok(())
+    }
+
+    async fn drop_collection(&self, name: &str) -> Result<(), Self::Error> {
+        if self.collections.write().await.remove(name).is_some() {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("Collection not found: {}", name))
+        }
+    }
+
+    async fn list_collections(&self) -> Result<Vec<String>, Self::Error> {
+        let mut names: Vec<String> = self.collections.read().await.keys().cloned().collect();
+        names.sort();
+        Ok(names)
+    }
+
+    async fn collection_exists(&self, name: &str) -> Result<bool, Self::Error> {
+        Ok(self.collections.read().await.contains_key(name))
+    }
+
+    async fn health_check(&self) -> Result<HealthStatus, Self::Error> {
+        // 内存存储总是可用
+        Ok(HealthStatus::Healthy)
+    }
+
     async fn get_stats(&self) -> Result<HashMap<String, u64>, Self::Error> {
         let data = self.data.read().await;
         let mut stats = HashMap::new();
@@ -187,3 +701,175 @@ impl Default for MockVectorStore {
         Self::new()
     }
 }
+
+impl Drop for MockVectorStore {
+    fn drop(&mut self) {
+        let Some(ref path) = self.persist_path else {
+            return;
+        };
+
+        // 数据只由存储自身持有，drop时独占访问，不需要加锁，也不会因锁被占用而跳过写回
+        let (Some(data), Some(collections)) = (Arc::get_mut(&mut self.data), Arc::get_mut(&mut self.collections)) else {
+            tracing::warn!("Mock向量存储仍被共享，跳过持久化: {}", path.display());
+            return;
+        };
+
+        if let Err(e) = Self::write_state(path, self.codec, data.get_mut(), collections.get_mut()) {
+            tracing::warn!("Mock向量存储持久化失败: {}: {}", path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::DimensionMismatch;
+
+    #[tokio::test]
+    async fn test_collection_management() {
+        let store = MockVectorStore::new();
+
+        assert!(!store.collection_exists("memories").await.unwrap());
+        store.create_collection("memories", 768).await.unwrap();
+        store.create_collection("archive", 768).await.unwrap();
+        assert!(store.create_collection("memories", 768).await.is_err());
+
+        assert!(store.collection_exists("memories").await.unwrap());
+        assert_eq!(store.list_collections().await.unwrap(), vec!["archive", "memories"]);
+
+        store.drop_collection("memories").await.unwrap();
+        assert!(!store.collection_exists("memories").await.unwrap());
+        assert!(store.drop_collection("memories").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_search_boosts_keyword_matches() {
+        let store = MockVectorStore::new();
+        let cat = Uuid::new_v4();
+        let coffee = Uuid::new_v4();
+
+        // 两个向量的稠密相似度相同，只有关键词不同
+        store.store_hybrid(cat, vec![1.0, 0.0], SparseVector::from_keywords(&["猫咪"]), "{}".to_string()).await.unwrap();
+        store.store_hybrid(coffee, vec![1.0, 0.0], SparseVector::from_keywords(&["咖啡"]), "{}".to_string()).await.unwrap();
+
+        let hits = store.search_hybrid(
+            vec![1.0, 0.0],
+            SparseVector::from_keywords(&["猫咪"]),
+            2,
+            0.0,
+            None,
+        ).await.unwrap();
+
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].id, cat);
+    }
+
+    #[tokio::test]
+    async fn test_scroll_visits_every_vector_once() {
+        let store = MockVectorStore::new();
+        let mut ids: Vec<Uuid> = (0..5).map(|_| Uuid::new_v4()).collect();
+        for id in &ids {
+            store.store_vector(*id, vec![1.0, 0.0], "{}".to_string()).await.unwrap();
+        }
+
+        let mut seen = Vec::new();
+        let mut offset = None;
+        loop {
+            let page = store.scroll(offset, 2).await.unwrap();
+            assert!(page.points.len() <= 2);
+            seen.extend(page.points.into_iter().map(|point| point.id));
+            match page.next_offset {
+                Some(next) => offset = Some(next),
+                None => break,
+            }
+        }
+
+        ids.sort();
+        assert_eq!(seen, ids);
+    }
+
+    #[tokio::test]
+    async fn test_dimension_mismatch_is_rejected() {
+        let store = MockVectorStore::new().with_vector_size(3);
+        assert_eq!(store.vector_size(), Some(3));
+
+        let err = store.store_vector(Uuid::new_v4(), vec![1.0, 0.0], "{}".to_string()).await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<DimensionMismatch>(),
+            Some(&DimensionMismatch { expected: 3, actual: 2 })
+        );
+        assert!(store.search_similar(vec![1.0], 5, 0.0, None).await.is_err());
+        assert!(store.store_vector(Uuid::new_v4(), vec![1.0, 0.0, 0.0], "{}".to_string()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_euclidean_ranks_nearest_first() {
+        let store = MockVectorStore::new().with_distance(DistanceMetric::Euclidean);
+        let near = Uuid::new_v4();
+        let far = Uuid::new_v4();
+        store.store_vector(far, vec![10.0, 0.0], "{}".to_string()).await.unwrap();
+        store.store_vector(near, vec![2.0, 0.0], "{}".to_string()).await.unwrap();
+
+        // 欧氏距离下阈值为最大距离
+        let hits = store.search_similar(vec![1.0, 0.0], 5, 5.0, None).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].id, near);
+        assert_eq!(hits[0].score, 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_persistent_store_survives_drop() {
+        let path = std::env::temp_dir().join(format!("mira_mock_{}.json", Uuid::new_v4()));
+        let id = Uuid::new_v4();
+
+        {
+            let store = MockVectorStore::persistent(&path).unwrap();
+            store.store_vector(id, vec![1.0, 0.0], r#"{"content":"记住我"}"#.to_string()).await.unwrap();
+        }
+
+        let store = MockVectorStore::persistent(&path).unwrap();
+        let hits = store.search_similar(vec![1.0, 0.0], 1, 0.5, None).await.unwrap();
+        assert_eq!(hits[0].id, id);
+        assert_eq!(hits[0].payload["content"], "记住我");
+
+        store.clear().await;
+        drop(store);
+        assert!(MockVectorStore::persistent(&path).unwrap().scroll(None, 10).await.unwrap().points.is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_search_emotion_space() {
+        let store = MockVectorStore::new().with_emotion_vector_size(2);
+        let (joyful, sad) = (Uuid::new_v4(), Uuid::new_v4());
+        store.store_multi_vector(joyful, vec![0.0, 1.0], vec![1.0, 0.0], "{}".to_string()).await.unwrap();
+        store.store_multi_vector(sad, vec![1.0, 0.0], vec![0.0, 1.0], "{}".to_string()).await.unwrap();
+        store.store_vector(Uuid::new_v4(), vec![1.0, 0.0], "{}".to_string()).await.unwrap();
+
+        let hits = store.search_space(VectorSpace::Emotion, vec![1.0, 0.0], 5, 0.5, None).await.unwrap();
+        assert_eq!(hits.iter().map(|hit| hit.id).collect::<Vec<_>>(), vec![joyful]);
+
+        let hits = store.search_space(VectorSpace::Content, vec![1.0, 0.0], 5, 0.5, None).await.unwrap();
+        assert_eq!(hits.len(), 2);
+
+        assert!(store.store_multi_vector(Uuid::new_v4(), vec![1.0], vec![1.0, 0.0, 0.0], "{}".to_string()).await.is_err());
+        assert!(MockVectorStore::new().search_space(VectorSpace::Emotion, vec![1.0], 1, 0.0, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_attach_and_search_image_vector() {
+        let store = MockVectorStore::new().with_emotion_vector_size(2).with_image_vector_size(3);
+        let (photo, text) = (Uuid::new_v4(), Uuid::new_v4());
+        store.store_multi_vector(photo, vec![1.0, 0.0], vec![1.0, 0.0], "{}".to_string()).await.unwrap();
+        store.store_vector(text, vec![1.0, 0.0], "{}".to_string()).await.unwrap();
+        store.attach_image_vector(photo, vec![0.0, 0.0, 1.0]).await.unwrap();
+
+        let hits = store.search_space(VectorSpace::Image, vec![0.0, 0.1, 1.0], 5, 0.5, None).await.unwrap();
+        assert_eq!(hits.iter().map(|hit| hit.id).collect::<Vec<_>>(), vec![photo]);
+        // 附加图片向量不影响情感向量
+        assert_eq!(store.search_space(VectorSpace::Emotion, vec![1.0, 0.0], 5, 0.5, None).await.unwrap().len(), 1);
+
+        assert!(store.attach_image_vector(Uuid::new_v4(), vec![0.0, 0.0, 1.0]).await.is_err());
+        assert!(store.attach_image_vector(text, vec![1.0]).await.is_err());
+    }
+}
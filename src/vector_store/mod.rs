@@ -1,13 +1,155 @@
 //! 向量存储抽象层和实现
 
 use async_trait::async_trait;
+use futures::stream::{self, BoxStream, StreamExt};
 use uuid::Uuid;
 use std::collections::HashMap;
+use std::future::Future;
+
+/// 相似度搜索命中结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    /// 向量ID
+    pub id: Uuid,
+    /// 相似度分数
+    pub score: f32,
+    /// 存储时的metadata
+    pub payload: serde_json::Value,
+}
+
+/// 流式搜索结果，按相近程度依次产出
+pub type HitStream<'a, E> = BoxStream<'a, Result<SearchHit, E>>;
+
+/// 将一次性返回全部命中的搜索转为流
+pub fn stream_search_results<'a, E, F>(search: F) -> HitStream<'a, E>
+where
+    E: Send + 'a,
+    F: Future<Output = Result<Vec<SearchHit>, E>> + Send + 'a,
+{
+    stream::once(search)
+        .flat_map(|result| {
+            let items: Vec<Result<SearchHit, E>> = match result {
+                Ok(hits) => hits.into_iter().map(Ok).collect(),
+                Err(e) => vec![Err(e)],
+            };
+            stream::iter(items)
+        })
+        .boxed()
+}
+
+/// 遍历得到的存储点
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct StoredVector {
+    /// 向量ID
+    pub id: Uuid,
+    /// 稠密向量
+    pub embedding: Vec<f32>,
+    /// 存储时的metadata
+    pub payload: serde_json::Value,
+    /// 情感向量
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emotion: Option<Vec<f32>>,
+    /// 图片向量
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<Vec<f32>>,
+}
+
+/// 写入一个遍历得到的点，带情感或图片向量时一并写入
+pub async fn store_point<S>(store: &S, point: StoredVector) -> Result<(), S::Error>
+where
+    S: VectorStore + ?Sized,
+{
+    let metadata = point.payload.to_string();
+    match point.emotion {
+        Some(emotion) => store.store_multi_vector(point.id, point.embedding, emotion, metadata).await?,
+        None => store.store_vector(point.id, point.embedding, metadata).await?,
+    }
+    match point.image {
+        Some(image) => store.attach_image_vector(point.id, image).await,
+        None => Ok(()),
+    }
+}
+
+/// 一页遍历结果
+#[derive(Debug, Clone, Default)]
+pub struct ScrollPage {
+    pub points: Vec<StoredVector>,
+    /// 下一页的游标，None表示已遍历完
+    pub next_offset: Option<String>,
+}
+
+/// 向量维度与存储配置不一致
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("向量维度不匹配: 期望 {expected}, 实际 {actual}")]
+pub struct DimensionMismatch {
+    pub expected: usize,
+    pub actual: usize,
+}
+
+/// 检查向量维度，`expected`为None时不做限制
+pub fn check_dimension(expected: Option<usize>, embedding: &[f32]) -> Result<(), DimensionMismatch> {
+    match expected {
+        Some(expected) if expected != embedding.len() => Err(DimensionMismatch {
+            expected,
+            actual: embedding.len(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// 同一个点上的向量空间
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum VectorSpace {
+    /// 内容语义嵌入
+    Content,
+    /// 情感状态嵌入
+    Emotion,
+    /// 图片嵌入（如CLIP）
+    Image,
+}
+
+/// 向量存储健康状态
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HealthStatus {
+    /// 服务可用
+    Healthy,
+    /// 服务可用但存在问题，例如断线期间还有未补写的缓冲
+    Degraded { reason: String },
+    /// 服务不可用
+    Unhealthy { reason: String },
+}
+
+impl HealthStatus {
+    /// 是否完全健康
+    pub fn is_healthy(&self) -> bool {
+        matches!(self, HealthStatus::Healthy)
+    }
+}
 
 /// 向量存储特征
 #[async_trait]
 pub trait VectorStore: std::fmt::Debug + Send + Sync {
-    type Error: Send + Sync + 'static;
+    type Error: From<anyhow::Error> + Send + Sync + 'static;
+
+    /// 存储接受的向量维度 - None表示不限制
+    fn vector_size(&self) -> Option<usize> {
+        None
+    }
+
+    /// 情感向量维度 - None表示不支持情感向量
+    fn emotion_vector_size(&self) -> Option<usize> {
+        None
+    }
+
+    /// 图片向量维度 - None表示不支持图片向量
+    fn image_vector_size(&self) -> Option<usize> {
+        None
+    }
+
+    /// 搜索使用的距离度量，决定分数排序方向
+    fn distance_metric(&self) -> DistanceMetric {
+        DistanceMetric::Cosine
+    }
 
     /// 存储向量
     async fn store_vector(
@@ -17,26 +159,288 @@ pub trait VectorStore: std::fmt::Debug + Send + Sync {
         metadata: String,
     ) -> Result<(), Self::Error>;
 
+    /// 批量存储向量 - 默认逐条写入，实现可覆盖为单次批量请求
+    async fn store_vectors(
+        &self,
+        points: Vec<(Uuid, Vec<f32>, String)>,
+    ) -> Result<(), Self::Error> {
+        for (id, embedding, metadata) in points {
+            self.store_vector(id, embedding, metadata).await?;
+        }
+        Ok(())
+    }
+
     /// 搜索相似向量
     async fn search_similar(
         &self,
         query_embedding: Vec<f32>,
         limit: usize,
         threshold: f32,
-    ) -> Result<Vec<Uuid>, Self::Error>;
+        filter: Option<SearchFilter>,
+    ) -> Result<Vec<SearchHit>, Self::Error>;
+
+    /// 流式相似度搜索 - 命中按相近程度依次产出，调用方可以随时停止消费
+    ///
+    /// 默认实现等待完整的top-k结果后再逐条产出。
+    fn search_stream(
+        &self,
+        query_embedding: Vec<f32>,
+        limit: usize,
+        threshold: f32,
+        filter: Option<SearchFilter>,
+    ) -> HitStream<'_, Self::Error> {
+        stream_search_results(self.search_similar(query_embedding, limit, threshold, filter))
+    }
+
+    /// 批量搜索相似向量 - 结果与查询一一对应
+    async fn search_similar_batch(
+        &self,
+        query_embeddings: Vec<Vec<f32>>,
+        limit: usize,
+        threshold: f32,
+        filter: Option<SearchFilter>,
+    ) -> Result<Vec<Vec<SearchHit>>, Self::Error> {
+        let mut results = Vec::with_capacity(query_embeddings.len());
+        for query_embedding in query_embeddings {
+            results.push(self.search_similar(query_embedding, limit, threshold, filter.clone()).await?);
+        }
+        Ok(results)
+    }
+
+    /// 存储稠密向量及稀疏关键词向量 - 不支持混合检索的实现只存储稠密向量
+    async fn store_hybrid(
+        &self,
+        id: Uuid,
+        embedding: Vec<f32>,
+        sparse: SparseVector,
+        metadata: String,
+    ) -> Result<(), Self::Error> {
+        let _ = sparse;
+        self.store_vector(id, embedding, metadata).await
+    }
+
+    /// 稠密+稀疏混合搜索，结果按RRF融合分数排序 - 默认退化为纯稠密搜索
+    async fn search_hybrid(
+        &self,
+        query_embedding: Vec<f32>,
+        query_sparse: SparseVector,
+        limit: usize,
+        threshold: f32,
+        filter: Option<SearchFilter>,
+    ) -> Result<Vec<SearchHit>, Self::Error> {
+        let _ = query_sparse;
+        self.search_similar(query_embedding, limit, threshold, filter).await
+    }
+
+    /// 存储内容向量和情感向量到同一个点
+    async fn store_multi_vector(
+        &self,
+        id: Uuid,
+        embedding: Vec<f32>,
+        emotion_embedding: Vec<f32>,
+        metadata: String,
+    ) -> Result<(), Self::Error> {
+        let _ = (id, embedding, emotion_embedding, metadata);
+        Err(anyhow::anyhow!("Emotion vectors are not supported by this store").into())
+    }
+
+    /// 批量存储带情感向量的点 - 默认逐条写入，实现可覆盖为单次批量请求
+    async fn store_multi_vectors(
+        &self,
+        points: Vec<(Uuid, Vec<f32>, Vec<f32>, String)>,
+    ) -> Result<(), Self::Error> {
+        for (id, embedding, emotion_embedding, metadata) in points {
+            self.store_multi_vector(id, embedding, emotion_embedding, metadata).await?;
+        }
+        Ok(())
+    }
+
+    /// 为已存储的点附加图片向量，内容向量和payload保持不变
+    async fn attach_image_vector(&self, id: Uuid, image_embedding: Vec<f32>) -> Result<(), Self::Error> {
+        let _ = (id, image_embedding);
+        Err(anyhow::anyhow!("Image vectors are not supported by this store").into())
+    }
+
+    /// 在指定向量空间中搜索 - 默认只支持内容空间
+    async fn search_space(
+        &self,
+        space: VectorSpace,
+        query_embedding: Vec<f32>,
+        limit: usize,
+        threshold: f32,
+        filter: Option<SearchFilter>,
+    ) -> Result<Vec<SearchHit>, Self::Error> {
+        match space {
+            VectorSpace::Content => self.search_similar(query_embedding, limit, threshold, filter).await,
+            VectorSpace::Emotion => {
+                Err(anyhow::anyhow!("Emotion vectors are not supported by this store").into())
+            }
+            VectorSpace::Image => {
+                Err(anyhow::anyhow!("Image vectors are not supported by this store").into())
+            }
+        }
+    }
+
+    /// 更新向量嵌入，保留原有payload
+    async fn update_vector(&self, id: Uuid, embedding: Vec<f32>) -> Result<(), Self::Error>;
+
+    /// 合并更新payload字段，未出现在patch中的字段保持不变
+    async fn update_payload(&self, id: Uuid, patch: serde_json::Value) -> Result<(), Self::Error>;
 
     /// 删除向量
     async fn delete_vector(&self, id: Uuid) -> Result<(), Self::Error>;
 
+    /// 删除payload中`expires_at_ts`已到期且满足过滤条件的向量，返回被删除的ID
+    async fn purge_expired(&self, filter: Option<SearchFilter>) -> Result<Vec<Uuid>, Self::Error> {
+        let _ = filter;
+        Err(anyhow::anyhow!("Expiry is not supported by this store").into())
+    }
+
+    /// 精确统计满足过滤条件的向量数量，直接查询存储而不依赖进程内缓存
+    async fn count(&self, filter: Option<SearchFilter>) -> Result<u64, Self::Error> {
+        let _ = filter;
+        Err(anyhow::anyhow!("Count is not supported by this store").into())
+    }
+
+    /// 估算满足过滤条件的向量数量，开销比`count`小，可能不精确
+    async fn estimated_count(&self, filter: Option<SearchFilter>) -> Result<u64, Self::Error> {
+        self.count(filter).await
+    }
+
+    /// 按ID读取向量及payload，不存在时返回None
+    async fn get_vector(&self, id: Uuid) -> Result<Option<StoredVector>, Self::Error> {
+        let _ = id;
+        Err(anyhow::anyhow!("Point lookup is not supported by this store").into())
+    }
+
+    /// 按ID批量读取payload，不存在的ID不在结果中 - 默认逐个调用`get_vector`
+    async fn get_payloads(&self, ids: &[Uuid]) -> Result<HashMap<Uuid, serde_json::Value>, Self::Error> {
+        let mut payloads = HashMap::with_capacity(ids.len());
+        for &id in ids {
+            if let Some(point) = self.get_vector(id).await? {
+                payloads.insert(id, point.payload);
+            }
+        }
+        Ok(payloads)
+    }
+
+    /// 分页遍历所有存储的向量 - 游标由实现定义，首页传None
+    async fn scroll(&self, offset: Option<String>, limit: usize) -> Result<ScrollPage, Self::Error> {
+        let _ = (offset, limit);
+        Err(anyhow::anyhow!("Scrolling is not supported by this store").into())
+    }
+
+    /// 检查存储服务状态 - 服务不可用时返回Unhealthy而不是错误
+    async fn health_check(&self) -> Result<HealthStatus, Self::Error>;
+
+    /// 按操作名统计的调用指标 - 未启用采集时为空
+    fn metrics(&self) -> HashMap<String, OperationMetrics> {
+        HashMap::new()
+    }
+
     /// 获取向量统计信息
     async fn get_stats(&self) -> Result<HashMap<String, u64>, Self::Error>;
+
+    /// 创建集合 - 不支持集合管理的实现返回错误
+    async fn create_collection(&self, name: &str, vector_size: usize) -> Result<(), Self::Error> {
+        let _ = (name, vector_size);
+        Err(anyhow::anyhow!("Collection management is not supported by this store").into())
+    }
+
+    /// 删除集合
+    async fn drop_collection(&self, name: &str) -> Result<(), Self::Error> {
+        let _ = name;
+        Err(anyhow::anyhow!("Collection management is not supported by this store").into())
+    }
+
+    /// 列出所有集合
+    async fn list_collections(&self) -> Result<Vec<String>, Self::Error> {
+        Err(anyhow::anyhow!("Collection management is not supported by this store").into())
+    }
+
+    /// 检查集合是否存在
+    async fn collection_exists(&self, name: &str) -> Result<bool, Self::Error> {
+        let _ = name;
+        Err(anyhow::anyhow!("Collection management is not supported by this store").into())
+    }
 }
 
+/// 通用备份与恢复
+pub mod backup;
+
+/// 持久化和payload编码
+pub mod codec;
+
+/// 距离度量
+pub mod distance;
+
+/// SIMD距离内核
+pub mod simd;
+
+/// 精确暴力搜索
+pub mod exact;
+
+/// 搜索过滤条件
+pub mod filter;
+
+/// 按用户隔离的存储包装
+pub mod tenant;
+
+/// 操作指标
+pub mod metrics;
+
+/// 写后缓冲
+pub mod write_behind;
+
+/// 重新嵌入迁移
+pub mod migration;
+
+/// 重试策略
+pub mod retry;
+
+/// 稀疏关键词向量
+pub mod sparse;
+
+/// 内存映射的只读段文件
+#[cfg(feature = "mmap")]
+pub mod segment;
+
 /// Qdrant实现
 pub mod qdrant_impl;
 
 /// Mock实现（用于测试）
 pub mod mock_impl;
 
-pub use qdrant_impl::QdrantStore;
+pub use codec::{Codec, CodecKind};
+pub use distance::DistanceMetric;
+pub use filter::SearchFilter;
+pub use tenant::TenantVectorStore;
+pub use metrics::{InstrumentedVectorStore, OperationMetrics, StoreMetrics};
+pub use write_behind::{WriteBehindConfig, WriteBehindVectorStore};
+pub use migration::{reembed_all, ReembedProgress};
+pub use retry::RetryPolicy;
+pub use sparse::SparseVector;
+pub use qdrant_impl::{
+    HnswParams, PartitionStrategy, ProductCompression, Quantization, QdrantConfig, QdrantStore,
+};
 pub use mock_impl::MockVectorStore;
+#[cfg(feature = "mmap")]
+pub use segment::{write_segment, Segment, SegmentVectorStore};
+
+/// 未指定集合名称时使用的Qdrant集合
+pub const DEFAULT_COLLECTION: &str = "mira_memories";
+
+/// 打开向量存储 - 指定Qdrant地址时连接Qdrant，否则使用进程内存储
+pub async fn open_store(
+    qdrant_url: Option<&str>,
+    collection: Option<String>,
+    vector_size: usize,
+) -> anyhow::Result<std::sync::Arc<dyn VectorStore<Error = anyhow::Error>>> {
+    Ok(match qdrant_url {
+        Some(url) => {
+            let collection = collection.unwrap_or_else(|| DEFAULT_COLLECTION.to_string());
+            std::sync::Arc::new(QdrantStore::new(url, collection, vector_size).await?)
+        }
+        None => std::sync::Arc::new(MockVectorStore::new()),
+    })
+}
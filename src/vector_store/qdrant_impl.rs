@@ -1,31 +1,278 @@
 //! Qdrant向量数据库实现
 //! 使用最新的Qdrant Rust客户端
 
-use super::VectorStore;
+use super::{
+    check_dimension, filter, stream_search_results, DistanceMetric, HealthStatus, HitStream,
+    RetryPolicy, ScrollPage, SearchFilter, SearchHit, SparseVector, StoredVector, VectorSpace,
+    VectorStore,
+};
+use crate::MemoryType;
 use async_trait::async_trait;
+use futures::stream::{self, StreamExt, TryStreamExt};
 use uuid::Uuid;
 use std::collections::HashMap;
 use qdrant_client::{
-    Qdrant,
+    Qdrant, QdrantError,
     qdrant::{
-        CreateCollectionBuilder, Distance, PointStruct, SearchPointsBuilder,
-        VectorParamsBuilder, ScoredPoint,
+        Condition, CreateCollectionBuilder, Distance, Filter, PointId, PointStruct, Range,
+        SearchBatchPointsBuilder, SearchPointsBuilder, VectorParamsBuilder, ScoredPoint,
+        point_id::PointIdOptions, vectors_output::VectorsOptions,
     },
 };
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::{Mutex, RwLock};
+
+/// 集合分区策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PartitionStrategy {
+    /// 所有记忆共享一个集合
+    #[default]
+    Single,
+    /// 共享一个集合，并为memory_type建立payload索引
+    PayloadIndex,
+    /// 每种记忆类型使用独立集合（`{collection_name}_{memory_type}`）
+    PerMemoryType,
+}
+
+/// HNSW索引参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HnswParams {
+    /// 每个节点的边数，越大召回越高、内存越多
+    pub m: u64,
+    /// 构建索引时的候选集大小
+    pub ef_construct: u64,
+    /// 搜索时的候选集大小，None时使用服务端默认值
+    pub search_ef: Option<u64>,
+}
+
+/// 乘积量化压缩比
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProductCompression {
+    X4,
+    X8,
+    X16,
+    X32,
+    X64,
+}
+
+/// 向量量化方式 - 以精度换内存
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Quantization {
+    /// 标量量化（f32 -> int8），内存约为原来的1/4
+    Scalar {
+        /// 截断分位数，例如0.99
+        quantile: Option<f32>,
+        /// 量化向量常驻内存
+        always_ram: bool,
+    },
+    /// 乘积量化，压缩比更高，精度损失更大
+    Product {
+        compression: ProductCompression,
+        always_ram: bool,
+    },
+}
+
+/// Qdrant连接配置
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QdrantConfig {
+    /// 服务地址，例如 `http://localhost:6334`
+    pub url: String,
+    /// 集合名称
+    pub collection_name: String,
+    /// 向量维度
+    pub vector_size: usize,
+    /// 距离度量
+    #[serde(default)]
+    pub distance: DistanceMetric,
+    /// API密钥（Qdrant Cloud或开启鉴权的自托管实例）
+    pub api_key: Option<String>,
+    /// 强制使用TLS - 开启时`http://`地址会被升级为`https://`
+    pub use_tls: bool,
+    /// 请求超时(秒)
+    pub timeout_secs: u64,
+    /// 连接超时(秒)
+    pub connect_timeout_secs: u64,
+    /// REST接口地址，用于快照恢复 - None时由gRPC地址推导（6334端口换为6333）
+    #[serde(default)]
+    pub rest_url: Option<String>,
+    /// 集合分区策略
+    pub partition: PartitionStrategy,
+    /// 瞬时故障重试策略
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    /// 连接断开时是否缓冲写入，重连后自动补写
+    #[serde(default)]
+    pub buffer_writes_when_disconnected: bool,
+    /// 断线缓冲的最大点数，超出后写入直接返回错误
+    #[serde(default = "default_max_buffered_writes")]
+    pub max_buffered_writes: usize,
+    /// HNSW索引参数，None时使用服务端默认值
+    #[serde(default)]
+    pub hnsw: Option<HnswParams>,
+    /// 向量量化，None时不量化
+    #[serde(default)]
+    pub quantization: Option<Quantization>,
+    /// 原始向量存放在磁盘上
+    #[serde(default)]
+    pub on_disk_vectors: bool,
+    /// 情感向量维度 - 设置后集合使用命名向量`content`和`emotion`
+    #[serde(default)]
+    pub emotion_vector_size: Option<usize>,
+    /// 图片向量维度 - 设置后集合使用命名向量`content`和`image`
+    #[serde(default)]
+    pub image_vector_size: Option<usize>,
+    /// 创建集合时附带稀疏关键词向量，启用稠密+稀疏混合检索
+    #[serde(default)]
+    pub hybrid: bool,
+}
+
+/// 稀疏关键词向量在集合中的名称
+const SPARSE_VECTOR_NAME: &str = "sparse";
+/// 启用情感向量时内容向量的名称
+const CONTENT_VECTOR_NAME: &str = "content";
+/// 情感向量的名称
+const EMOTION_VECTOR_NAME: &str = "emotion";
+/// 图片向量的名称
+const IMAGE_VECTOR_NAME: &str = "image";
+
+/// 流式搜索每页请求的命中数
+const STREAM_PAGE_SIZE: usize = 32;
+
+fn default_max_buffered_writes() -> usize {
+    10_000
+}
+
+impl QdrantConfig {
+    /// 使用默认选项创建配置
+    pub fn new(url: impl Into<String>, collection_name: impl Into<String>, vector_size: usize) -> Self {
+        Self {
+            url: url.into(),
+            collection_name: collection_name.into(),
+            vector_size,
+            ..Default::default()
+        }
+    }
+
+    /// 设置距离度量
+    pub fn with_distance(mut self, distance: DistanceMetric) -> Self {
+        self.distance = distance;
+        self
+    }
+
+    /// 设置HNSW索引参数
+    pub fn with_hnsw(mut self, m: u64, ef_construct: u64, search_ef: Option<u64>) -> Self {
+        self.hnsw = Some(HnswParams { m, ef_construct, search_ef });
+        self
+    }
+
+    /// 设置向量量化方式
+    pub fn with_quantization(mut self, quantization: Quantization) -> Self {
+        self.quantization = Some(quantization);
+        self
+    }
+
+    /// 实际连接使用的地址
+    fn effective_url(&self) -> String {
+        match self.url.strip_prefix("http://") {
+            Some(rest) if self.use_tls => format!("https://{}", rest),
+            _ => self.url.clone(),
+        }
+    }
+
+    /// REST接口地址
+    fn effective_rest_url(&self) -> String {
+        match self.rest_url {
+            Some(ref rest_url) => rest_url.trim_end_matches('/').to_string(),
+            None => self.effective_url().trim_end_matches('/').replace(":6334", ":6333"),
+        }
+    }
+
+    /// 集合快照恢复接口地址，集合名按路径段转义
+    fn snapshot_recover_url(&self, collection: &str) -> Result<reqwest::Url, anyhow::Error> {
+        let mut url = reqwest::Url::parse(&self.effective_rest_url())?;
+        url.path_segments_mut()
+            .map_err(|_| anyhow::anyhow!("Qdrant REST地址无效: {}", self.effective_rest_url()))?
+            .pop_if_empty()
+            .extend(["collections", collection, "snapshots", "recover"]);
+        url.query_pairs_mut().append_pair("wait", "true");
+        Ok(url)
+    }
+}
+
+impl Default for QdrantConfig {
+    fn default() -> Self {
+        Self {
+            url: "http://localhost:6334".to_string(),
+            collection_name: super::DEFAULT_COLLECTION.to_string(),
+            vector_size: 768,
+            distance: DistanceMetric::Cosine,
+            api_key: None,
+            use_tls: false,
+            timeout_secs: 10,
+            connect_timeout_secs: 5,
+            rest_url: None,
+            partition: PartitionStrategy::Single,
+            retry: RetryPolicy::default(),
+            buffer_writes_when_disconnected: false,
+            max_buffered_writes: default_max_buffered_writes(),
+            hnsw: None,
+            quantization: None,
+            on_disk_vectors: false,
+            emotion_vector_size: None,
+            image_vector_size: None,
+            hybrid: false,
+        }
+    }
+}
+
+impl std::fmt::Debug for QdrantConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QdrantConfig")
+            .field("url", &self.url)
+            .field("collection_name", &self.collection_name)
+            .field("vector_size", &self.vector_size)
+            .field("distance", &self.distance)
+            .field("api_key", &self.api_key.as_ref().map(|_| "***"))
+            .field("use_tls", &self.use_tls)
+            .field("timeout_secs", &self.timeout_secs)
+            .field("connect_timeout_secs", &self.connect_timeout_secs)
+            .field("rest_url", &self.rest_url)
+            .field("partition", &self.partition)
+            .field("retry", &self.retry)
+            .field("buffer_writes_when_disconnected", &self.buffer_writes_when_disconnected)
+            .field("max_buffered_writes", &self.max_buffered_writes)
+            .field("hnsw", &self.hnsw)
+            .field("quantization", &self.quantization)
+            .field("on_disk_vectors", &self.on_disk_vectors)
+            .field("emotion_vector_size", &self.emotion_vector_size)
+            .field("image_vector_size", &self.image_vector_size)
+            .field("hybrid", &self.hybrid)
+            .finish()
+    }
+}
 
 /// Qdrant存储实现
 pub struct QdrantStore {
-    client: Qdrant,
-    collection_name: String,
-    vector_size: usize,
+    /// 当前客户端，重连时整体替换
+    client: RwLock<Arc<Qdrant>>,
+    config: QdrantConfig,
+    /// 连接是否健康 - 检测到连接错误后置为false，下次操作时惰性重连
+    healthy: AtomicBool,
+    /// 断线期间缓冲的写入（目标集合, 点）
+    write_buffer: Mutex<Vec<(String, PointStruct)>>,
 }
 
 impl std::fmt::Debug for QdrantStore {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("QdrantStore")
-            .field("collection_name", &self.collection_name)
-            .field("vector_size", &self.vector_size)
+            .field("collection_name", &self.config.collection_name)
+            .field("vector_size", &self.config.vector_size)
+            .field("partition", &self.config.partition)
+            .field("healthy", &self.is_healthy())
             .finish()
     }
 }
@@ -49,14 +296,18 @@ impl QdrantStore {
         collection_name: String,
         vector_size: usize,
     ) -> Result<Self, anyhow::Error> {
-        let client = Qdrant::from_url(url)
-            .build()
-            .map_err(|e| anyhow::anyhow!("Qdrant client error: {}", e))?;
+        Self::from_config(QdrantConfig::new(url, collection_name, vector_size)).await
+    }
+
+    /// 根据配置创建Qdrant存储实例
+    pub async fn from_config(config: QdrantConfig) -> Result<Self, anyhow::Error> {
+        let client = Self::build_client(&config)?;
 
         let store = Self {
-            client,
-            collection_name,
-            vector_size,
+            client: RwLock::new(Arc::new(client)),
+            config,
+            healthy: AtomicBool::new(true),
+            write_buffer: Mutex::new(Vec::new()),
         };
 
         // 确保集合存在
@@ -65,180 +316,1574 @@ impl QdrantStore {
         Ok(store)
     }
 
-    /// 确保集合存在
+    /// 根据配置构建客户端
+    fn build_client(config: &QdrantConfig) -> Result<Qdrant, anyhow::Error> {
+        let mut builder = Qdrant::from_url(&config.effective_url())
+            .timeout(std::time::Duration::from_secs(config.timeout_secs))
+            .connect_timeout(std::time::Duration::from_secs(config.connect_timeout_secs));
+
+        if let Some(ref api_key) = config.api_key {
+            builder = builder.api_key(api_key.clone());
+        }
+
+        builder
+            .build()
+            .map_err(|e| anyhow::anyhow!("Qdrant client error: {}", e))
+    }
+
+    /// 连接是否健康
+    pub fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Acquire)
+    }
+
+    /// 主动探测连接状态并更新健康标记
+    pub async fn check_health(&self) -> bool {
+        let client = self.client.read().await.clone();
+        let healthy = client.health_check().await.is_ok();
+        self.healthy.store(healthy, Ordering::Release);
+        healthy
+    }
+
+    /// 获取可用的客户端，连接被标记为断开时先惰性重连
+    async fn client(&self) -> Result<Arc<Qdrant>, anyhow::Error> {
+        if !self.is_healthy() {
+            self.reconnect().await?;
+        }
+        Ok(self.client.read().await.clone())
+    }
+
+    /// 重建客户端并补写断线期间缓冲的数据
+    ///
+    /// 先用新客户端补写缓冲区再标记为健康，之后的直接写入不会被较早的缓冲写入覆盖；
+    /// 切换期间新进入缓冲区的点随后再补写一次。
+    async fn reconnect(&self) -> Result<(), anyhow::Error> {
+        let client = Arc::new(Self::build_client(&self.config)?);
+        client.health_check().await
+            .map_err(|e| anyhow::anyhow!("Qdrant reconnect failed: {}", e))?;
+
+        self.replay_buffer(&client).await?;
+        *self.client.write().await = client.clone();
+        self.healthy.store(true, Ordering::Release);
+        tracing::info!("Qdrant连接已恢复");
+
+        self.replay_buffer(&client).await
+    }
+
+    /// 补写缓冲的点，失败时未写入的点按原顺序放回缓冲区
+    async fn replay_buffer(&self, client: &Qdrant) -> Result<(), anyhow::Error> {
+        let buffered = std::mem::take(&mut *self.write_buffer.lock().await);
+        if buffered.is_empty() {
+            return Ok(());
+        }
+        tracing::info!("补写断线期间缓冲的{}个点", buffered.len());
+
+        let mut grouped: Vec<(String, Vec<PointStruct>)> = Vec::new();
+        for (collection, point) in buffered {
+            match grouped.iter_mut().find(|(name, _)| *name == collection) {
+                Some((_, points)) => points.push(point),
+                None => grouped.push((collection, vec![point])),
+            }
+        }
+
+        let mut remaining = grouped.into_iter();
+        while let Some((collection, points)) = remaining.next() {
+            let batch = HashMap::from([(collection.clone(), points.clone())]);
+            if let Err(e) = self.upsert_grouped(client, batch).await {
+                let unsent: Vec<(String, PointStruct)> = std::iter::once((collection, points))
+                    .chain(remaining)
+                    .flat_map(|(collection, points)| {
+                        points.into_iter().map(move |point| (collection.clone(), point))
+                    })
+                    .collect();
+                tracing::warn!("补写失败，{}个点放回缓冲区", unsent.len());
+                let mut buffer = self.write_buffer.lock().await;
+                let newer = std::mem::replace(&mut *buffer, unsent);
+                buffer.extend(newer);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// 按重试策略执行Qdrant调用，并根据错误更新连接状态
+    async fn run<T, F, Fut>(&self, idempotent: bool, operation: F) -> Result<T, QdrantError>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T, QdrantError>>,
+    {
+        self.config.retry.run(idempotent, operation).await
+            .inspect_err(|e| {
+                if Self::is_connection_error(e) {
+                    tracing::warn!("Qdrant连接断开，将在下次操作时重连: {}", e);
+                    self.healthy.store(false, Ordering::Release);
+                }
+            })
+    }
+
+    /// 判断错误是否由连接中断引起
+    fn is_connection_error(error: &QdrantError) -> bool {
+        let message = error.to_string();
+        ["Unavailable", "transport error", "Connection refused", "connection closed", "broken pipe"]
+            .iter()
+            .any(|pattern| message.contains(pattern))
+    }
+
+    /// 按集合分组写入，每个集合一次UpsertPoints调用
+    async fn upsert_grouped(
+        &self,
+        client: &Qdrant,
+        grouped: HashMap<String, Vec<PointStruct>>,
+    ) -> Result<(), anyhow::Error> {
+        use qdrant_client::qdrant::UpsertPointsBuilder;
+
+        for (collection, points) in grouped {
+            let upsert_request = UpsertPointsBuilder::new(&collection, points);
+
+            self.run(true, || client.upsert_points(upsert_request.clone())).await
+                .map_err(|e| anyhow::anyhow!("Qdrant client error: {}", e))?;
+        }
+
+        Ok(())
+    }
+
+    /// 写入点，断线且开启缓冲时暂存到缓冲区
+    async fn write_points(&self, grouped: HashMap<String, Vec<PointStruct>>) -> Result<(), anyhow::Error> {
+        let result = match self.client().await {
+            Ok(client) => self.upsert_grouped(&client, grouped.clone()).await,
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Err(e) if self.config.buffer_writes_when_disconnected && !self.is_healthy() => {
+                let mut buffer = self.write_buffer.lock().await;
+                let incoming: usize = grouped.values().map(Vec::len).sum();
+                if buffer.len() + incoming > self.config.max_buffered_writes {
+                    return Err(e.context("Qdrant write buffer is full"));
+                }
+                tracing::warn!("Qdrant不可用，缓冲{}个点等待重连", incoming);
+                buffer.extend(grouped.into_iter()
+                    .flat_map(|(collection, points)| {
+                        points.into_iter().map(move |point| (collection.clone(), point))
+                    }));
+                Ok(())
+            }
+            other => other,
+        }
+    }
+
+    /// 断线期间缓冲的点数
+    pub async fn buffered_writes(&self) -> usize {
+        self.write_buffer.lock().await.len()
+    }
+
+    /// 确保所有分区集合存在
     async fn ensure_collection_exists(&self) -> Result<(), anyhow::Error> {
-        // 检查集合是否存在
-        let collections = self.client.list_collections().await
-            .map_err(|e| anyhow::anyhow!("Qdrant client error: {}", e))?;
+        for collection in self.all_collections() {
+            if !self.collection_exists(&collection).await? {
+                self.create_collection(&collection, self.config.vector_size).await?;
+            }
+        }
 
-        let collection_exists = collections.collections.iter()
-            .any(|c| c.name == self.collection_name);
+        if self.config.partition == PartitionStrategy::PayloadIndex {
+            use qdrant_client::qdrant::{CreateFieldIndexCollectionBuilder, FieldType};
 
-        if !collection_exists {
-            // 创建集合
-            let collection_config = CreateCollectionBuilder::new(&self.collection_name)
-                .vectors_config(VectorParamsBuilder::new(
-                    self.vector_size as u64,
-                    Distance::Cosine
-                ));
+            let client = self.client().await?;
 
-            self.client.create_collection(collection_config).await
+            // 重复创建索引是幂等的
+            let index_request = CreateFieldIndexCollectionBuilder::new(
+                &self.config.collection_name,
+                filter::PAYLOAD_MEMORY_TYPE,
+                FieldType::Keyword,
+            );
+            self.run(true, || client.create_field_index(index_request.clone())).await
                 .map_err(|e| anyhow::anyhow!("Qdrant collection error: {}", e))?;
         }
 
         Ok(())
     }
 
-    /// 将UUID转换为Qdrant点ID
-    fn uuid_to_point_id(&self, uuid: Uuid) -> u64 {
-        // 简单的UUID到u64的转换
-        // 在生产环境中可能需要更好的映射策略
-        let bytes = uuid.as_bytes();
-        u64::from_be_bytes([
-            bytes[0], bytes[1], bytes[2], bytes[3],
-            bytes[4], bytes[5], bytes[6], bytes[7],
-        ])
-    }
-
-    /// 将Qdrant点ID转换为UUID
-    fn point_id_to_uuid(&self, point_id: u64) -> Uuid {
-        let bytes = point_id.to_be_bytes();
-        // 使用固定的后8字节创建UUID
-        let uuid_bytes = [
-            bytes[0], bytes[1], bytes[2], bytes[3],
-            bytes[4], bytes[5], bytes[6], bytes[7],
-            0, 0, 0, 0, 0, 0, 0, 0,
-        ];
-        Uuid::from_bytes(uuid_bytes)
+    /// 某记忆类型对应的分区集合名
+    fn partition_collection(&self, memory_type: &MemoryType) -> String {
+        format!("{}_{}", self.config.collection_name, memory_type.as_str())
     }
-}
 
-#[async_trait]
-impl VectorStore for QdrantStore {
-    type Error = anyhow::Error;
+    /// 当前策略下的全部集合 - 按类型分区时基础集合存放无类型的点
+    fn all_collections(&self) -> Vec<String> {
+        let mut collections = vec![self.config.collection_name.clone()];
+        if self.config.partition == PartitionStrategy::PerMemoryType {
+            collections.extend(MemoryType::ALL.iter().map(|t| self.partition_collection(t)));
+        }
+        collections
+    }
 
-    async fn store_vector(
+    /// 搜索需要访问的集合 - 类型过滤时只访问对应分区
+    fn collections_for_filter(&self, search_filter: Option<&SearchFilter>) -> Vec<String> {
+        match (self.config.partition, search_filter.and_then(|f| f.memory_types.as_ref())) {
+            (PartitionStrategy::PerMemoryType, Some(memory_types)) => memory_types.iter()
+                .map(|t| self.partition_collection(t))
+                .collect(),
+            _ => self.all_collections(),
+        }
+    }
+
+    /// 点写入的目标集合
+    fn collection_for_payload(&self, payload: &HashMap<String, qdrant_client::qdrant::Value>) -> String {
+        if self.config.partition != PartitionStrategy::PerMemoryType {
+            return self.config.collection_name.clone();
+        }
+
+        let memory_type = match payload.get(filter::PAYLOAD_MEMORY_TYPE).and_then(|v| v.kind.as_ref()) {
+            Some(qdrant_client::qdrant::value::Kind::StringValue(s)) => {
+                serde_json::from_value::<MemoryType>(Value::String(s.clone())).ok()
+            }
+            _ => None,
+        };
+
+        match memory_type {
+            Some(memory_type) => self.partition_collection(&memory_type),
+            None => self.config.collection_name.clone(),
+        }
+    }
+
+    /// 定位点所在的集合
+    async fn locate_collection(&self, id: Uuid) -> Result<String, anyhow::Error> {
+        if self.config.partition != PartitionStrategy::PerMemoryType {
+            return Ok(self.config.collection_name.clone());
+        }
+
+        use qdrant_client::qdrant::GetPointsBuilder;
+
+        let client = self.client().await?;
+        for collection in self.all_collections() {
+            let get_request = GetPointsBuilder::new(&collection, vec![Self::uuid_to_point_id(id)])
+                .with_payload(false)
+                .with_vectors(false);
+            let response = self.run(true, || client.get_points(get_request.clone())).await
+                .map_err(|e| anyhow::anyhow!("Qdrant client error: {}", e))?;
+            if !response.result.is_empty() {
+                return Ok(collection);
+            }
+        }
+
+        Err(anyhow::anyhow!("Vector not found: {}", id))
+    }
+
+    /// 把点连同全部向量移到另一个集合，`patch`合并进原payload
+    ///
+    /// 先写入新集合再从原集合删除，中途失败时点不会丢失
+    async fn move_point(
         &self,
+        client: &Qdrant,
         id: Uuid,
-        embedding: Vec<f32>,
-        metadata: String,
-    ) -> Result<(), Self::Error> {
-        let point_id = self.uuid_to_point_id(id);
-        
-        // 解析metadata为JSON
-        let metadata_json: Value = serde_json::from_str(&metadata)?;
-        
-        // 转换为HashMap
-        let payload = if let Value::Object(map) = metadata_json {
-            map.into_iter().map(|(k, v)| {
-                let qdrant_value = match v {
-                    Value::String(s) => qdrant_client::qdrant::Value {
-                        kind: Some(qdrant_client::qdrant::value::Kind::StringValue(s)),
-                    },
-                    Value::Number(n) if n.is_f64() => qdrant_client::qdrant::Value {
-                        kind: Some(qdrant_client::qdrant::value::Kind::DoubleValue(n.as_f64().unwrap())),
-                    },
-                    Value::Number(n) if n.is_i64() => qdrant_client::qdrant::Value {
-                        kind: Some(qdrant_client::qdrant::value::Kind::IntegerValue(n.as_i64().unwrap())),
-                    },
-                    Value::Bool(b) => qdrant_client::qdrant::Value {
-                        kind: Some(qdrant_client::qdrant::value::Kind::BoolValue(b)),
-                    },
-                    _ => qdrant_client::qdrant::Value {
-                        kind: Some(qdrant_client::qdrant::value::Kind::StringValue(v.to_string())),
-                    },
-                };
-                (k, qdrant_value)
-            }).collect()
-        } else {
-            std::collections::HashMap::new()
-        };
-        
-        let point = PointStruct::new(
-            point_id,
-            embedding,
-            payload,
-        );
+        from: &str,
+        to: String,
+        patch: HashMap<String, qdrant_client::qdrant::Value>,
+    ) -> Result<(), anyhow::Error> {
+        use qdrant_client::qdrant::{DeletePointsBuilder, GetPointsBuilder};
 
-        use qdrant_client::qdrant::UpsertPointsBuilder;
-        
-        let upsert_request = UpsertPointsBuilder::new(&self.collection_name, vec![point]);
-        
-        self.client.upsert_points(upsert_request).await
-            .map_err(|e| anyhow::anyhow!("Qdrant client error: {}", e))?;
+        let get_request = GetPointsBuilder::new(from, vec![Self::uuid_to_point_id(id)])
+            .with_payload(true)
+            .with_vectors(true);
+        let point = self.run(true, || client.get_points(get_request.clone())).await
+            .map_err(|e| anyhow::anyhow!("Qdrant client error: {}", e))?
+            .result.into_iter().next()
+            .ok_or_else(|| anyhow::anyhow!("Vector not found: {}", id))?;
+        let vectors = point.vectors.and_then(Self::stored_vectors)
+            .ok_or_else(|| anyhow::anyhow!("Vector not found: {}", id))?;
+
+        let mut payload = point.payload;
+        payload.extend(patch);
+        let moved = PointStruct::new(Self::uuid_to_point_id(id), vectors, payload);
+        self.upsert_grouped(client, HashMap::from([(to, vec![moved])])).await?;
 
+        let delete_request = DeletePointsBuilder::new(from).points(vec![Self::uuid_to_point_id(id)]);
+        self.run(true, || client.delete_points(delete_request.clone())).await
+            .map_err(|e| anyhow::anyhow!("Qdrant client error: {}", e))?;
         Ok(())
     }
 
-    async fn search_similar(
-        &self,
-        query_embedding: Vec<f32>,
-        limit: usize,
-        threshold: f32,
-    ) -> Result<Vec<Uuid>, Self::Error> {
-        let search_request = SearchPointsBuilder::new(
-            &self.collection_name,
-            query_embedding,
-            limit as u64,
-        ).score_threshold(threshold);
-
-        let search_result = self.client.search_points(search_request).await
-            .map_err(|e| anyhow::anyhow!("Qdrant search error: {}", e))?;
-
-        let ids = search_result.result.into_iter()
-            .map(|scored_point: ScoredPoint| {
-                if let Some(point_id) = scored_point.id {
-                    if let Some(num) = point_id.point_id_options {
-                        match num {
-                            qdrant_client::qdrant::point_id::PointIdOptions::Num(n) => {
-                                self.point_id_to_uuid(n)
-                            }
-                            _ => Uuid::nil(), // 处理字符串ID的情况
-                        }
-                    } else {
-                        Uuid::nil()
+    /// 搜索参数 - HNSW ef和量化重打分
+    fn search_params(&self) -> Option<qdrant_client::qdrant::SearchParams> {
+        use qdrant_client::qdrant::{QuantizationSearchParamsBuilder, SearchParamsBuilder};
+
+        let search_ef = self.config.hnsw.as_ref().and_then(|h| h.search_ef);
+        if search_ef.is_none() && self.config.quantization.is_none() {
+            return None;
+        }
+
+        let mut params = SearchParamsBuilder::default();
+        if let Some(ef) = search_ef {
+            params = params.hnsw_ef(ef);
+        }
+        if self.config.quantization.is_some() {
+            // 先用量化向量召回，再用原始向量重打分
+            params = params.quantization(QuantizationSearchParamsBuilder::default().rescore(true));
+        }
+        Some(params.build())
+    }
+
+    /// 按相近程度合并多个集合的搜索结果
+    fn merge_hits(mut hits: Vec<SearchHit>, limit: usize, metric: DistanceMetric) -> Vec<SearchHit> {
+        hits.sort_by(|a, b| metric.compare(a.score, b.score));
+        hits.truncate(limit);
+        hits
+    }
+
+    /// 将UUID转换为Qdrant点ID - 使用Qdrant原生UUID点ID
+    fn uuid_to_point_id(uuid: Uuid) -> PointId {
+        PointId::from(uuid.to_string())
+    }
+
+    /// 将Qdrant点ID转换为UUID，数字ID（旧版本写入）返回None
+    fn point_id_to_uuid(point_id: PointId) -> Option<Uuid> {
+        match point_id.point_id_options? {
+            PointIdOptions::Uuid(s) => Uuid::parse_str(&s).ok(),
+            PointIdOptions::Num(_) => None,
+        }
+    }
+
+    /// 迁移旧版本写入的数字点ID
+    ///
+    /// 旧版本把UUID截断为u64作为点ID，这里根据payload中的`id`字段
+    /// 以UUID点ID重新写入，并删除原来的数字点。返回迁移的点数。
+    pub async fn migrate_numeric_point_ids(&self) -> Result<usize, anyhow::Error> {
+        use qdrant_client::qdrant::{DeletePointsBuilder, ScrollPointsBuilder, UpsertPointsBuilder};
+
+        let client = self.client().await?;
+        let mut migrated = 0;
+
+        for collection in self.all_collections() {
+            let mut offset: Option<PointId> = None;
+
+            loop {
+                let mut scroll_request = ScrollPointsBuilder::new(&collection)
+                    .limit(256)
+                    .with_payload(true)
+                    .with_vectors(true);
+                if let Some(offset) = offset.take() {
+                    scroll_request = scroll_request.offset(offset);
+                }
+
+                let scroll_result = self.run(true, || client.scroll(scroll_request.clone())).await
+                    .map_err(|e| anyhow::anyhow!("Qdrant client error: {}", e))?;
+
+                let mut new_points = Vec::new();
+                let mut old_ids = Vec::new();
+
+                for point in scroll_result.result {
+                    let Some(old_id) = point.id else { continue };
+                    if !matches!(old_id.point_id_options, Some(PointIdOptions::Num(_))) {
+                        continue;
                     }
-                } else {
-                    Uuid::nil()
+
+                    let Some(new_point) = Self::migrated_point(point) else {
+                        tracing::warn!("跳过无法迁移的点: {:?}", old_id);
+                        continue;
+                    };
+
+                    new_points.push(new_point);
+                    old_ids.push(old_id);
                 }
-            })
-            .filter(|uuid| !uuid.is_nil())
-            .collect();
 
-        Ok(ids)
+                if !new_points.is_empty() {
+                    migrated += new_points.len();
+
+                    let upsert_request = UpsertPointsBuilder::new(&collection, new_points);
+                    self.run(true, || client.upsert_points(upsert_request.clone())).await
+                        .map_err(|e| anyhow::anyhow!("Qdrant client error: {}", e))?;
+
+                    let delete_request = DeletePointsBuilder::new(&collection).points(old_ids);
+                    self.run(true, || client.delete_points(delete_request.clone())).await
+                        .map_err(|e| anyhow::anyhow!("Qdrant client error: {}", e))?;
+                }
+
+                match scroll_result.next_page_offset {
+                    Some(next) => offset = Some(next),
+                    None => break,
+                }
+            }
+        }
+
+        Ok(migrated)
     }
 
-    async fn delete_vector(&self, id: Uuid) -> Result<(), Self::Error> {
-        let point_id = self.uuid_to_point_id(id);
-        
-        // 简化删除操作，直接使用点ID
-        
-        use qdrant_client::qdrant::DeletePointsBuilder;
-        
-        let delete_request = DeletePointsBuilder::new(&self.collection_name)
-            .points(vec![qdrant_client::qdrant::PointId {
-                point_id_options: Some(
-                    qdrant_client::qdrant::point_id::PointIdOptions::Num(point_id)
-                )
-            }]);
-        
-        self.client.delete_points(delete_request).await
-            .map_err(|e| anyhow::anyhow!("Qdrant client error: {}", e))?;
+    /// 为当前策略下的全部集合创建服务端快照，返回（集合, 快照名）列表
+    pub async fn create_snapshot(&self) -> Result<Vec<(String, String)>, anyhow::Error> {
+        use qdrant_client::qdrant::CreateSnapshotRequestBuilder;
+
+        let client = self.client().await?;
+        let mut snapshots = Vec::new();
+
+        for collection in self.all_collections() {
+            let snapshot_request = CreateSnapshotRequestBuilder::new(&collection);
+
+            // 重复执行会产生多余快照，不重试
+            let response = self.run(false, || client.create_snapshot(snapshot_request.clone())).await
+                .map_err(|e| anyhow::anyhow!("Qdrant snapshot error: {}", e))?;
+            let description = response.snapshot_description
+                .ok_or_else(|| anyhow::anyhow!("Qdrant snapshot error: empty response for {}", collection))?;
 
+            tracing::info!("已创建集合快照: {} -> {}", collection, description.name);
+            snapshots.push((collection, description.name));
+        }
+
+        Ok(snapshots)
+    }
+
+    /// 从快照恢复集合
+    ///
+    /// `location`为Qdrant服务端可访问的地址，例如`file:///qdrant/snapshots/...`
+    /// 或`http(s)://`链接。gRPC接口不提供恢复功能，这里调用REST接口。
+    pub async fn restore_snapshot(&self, collection: &str, location: &str) -> Result<(), anyhow::Error> {
+        let url = self.config.snapshot_recover_url(collection)?;
+
+        let http = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(self.config.timeout_secs.max(60)))
+            .build()?;
+        let mut request = http.put(url).json(&serde_json::json!({ "location": location }));
+        if let Some(ref api_key) = self.config.api_key {
+            request = request.header("api-key", api_key);
+        }
+
+        let response = request.send().await
+            .map_err(|e| anyhow::anyhow!("Qdrant snapshot error: {}", e))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("Qdrant snapshot error: {} {}", status, body));
+        }
+
+        tracing::info!("已从快照恢复集合: {} <- {}", collection, location);
         Ok(())
     }
 
-    async fn get_stats(&self) -> Result<HashMap<String, u64>, Self::Error> {
-        let collection_info = self.client.collection_info(&self.collection_name).await
-            .map_err(|e| anyhow::anyhow!("Qdrant client error: {}", e))?;
+    /// 统计各相关集合中满足过滤条件的点数之和
+    async fn count_points(&self, filter: Option<SearchFilter>, exact: bool) -> Result<u64, anyhow::Error> {
+        use qdrant_client::qdrant::CountPointsBuilder;
 
-        let mut stats = HashMap::new();
-        
-        if let Some(result) = collection_info.result {
-            stats.insert("points_count".to_string(), result.points_count.unwrap_or(0));
-            stats.insert("vectors_count".to_string(), result.vectors_count.unwrap_or(0));
+        let client = self.client().await?;
+        let qdrant_filter = filter.as_ref().map(Self::to_qdrant_filter);
+
+        let mut total = 0;
+        for collection in self.collections_for_filter(filter.as_ref()) {
+            let mut count_request = CountPointsBuilder::new(&collection).exact(exact);
+            if let Some(ref qdrant_filter) = qdrant_filter {
+                count_request = count_request.filter(qdrant_filter.clone());
+            }
+
+            let count_result = self.run(true, || client.count(count_request.clone())).await
+                .map_err(|e| anyhow::anyhow!("Qdrant client error: {}", e))?;
+            total += count_result.result.map(|result| result.count).unwrap_or(0);
         }
 
-        Ok(stats)
+        Ok(total)
+    }
+
+    /// 集合是否使用命名的内容/情感/图片向量
+    fn uses_named_vectors(&self) -> bool {
+        self.config.emotion_vector_size.is_some() || self.config.image_vector_size.is_some()
+    }
+
+    /// 内容向量的名称，未启用命名向量时为默认名称""
+    fn content_vector_name(&self) -> &'static str {
+        if self.uses_named_vectors() { CONTENT_VECTOR_NAME } else { "" }
+    }
+
+    /// 构建只含内容向量的Qdrant向量
+    fn content_vectors(&self, embedding: Vec<f32>) -> qdrant_client::qdrant::Vectors {
+        use qdrant_client::qdrant::NamedVectors;
+
+        if self.uses_named_vectors() {
+            NamedVectors::default().add_vector(CONTENT_VECTOR_NAME, embedding).into()
+        } else {
+            embedding.into()
+        }
+    }
+
+    /// 从Qdrant返回的向量中分别提取内容、情感和图片向量
+    #[allow(deprecated)]
+    fn split_vectors(
+        &self,
+        vectors: qdrant_client::qdrant::VectorsOutput,
+    ) -> (Option<Vec<f32>>, Option<Vec<f32>>, Option<Vec<f32>>) {
+        match vectors.vectors_options {
+            Some(VectorsOptions::Vector(vector)) => (Some(vector.data), None, None),
+            // 混合集合中稠密向量以默认名称""存储
+            Some(VectorsOptions::Vectors(mut named)) => {
+                let mut take = |name: &str| named.vectors.remove(name).map(|vector| vector.data);
+                (take(self.content_vector_name()), take(EMOTION_VECTOR_NAME), take(IMAGE_VECTOR_NAME))
+            }
+            None => (None, None, None),
+        }
+    }
+
+    /// 把Qdrant返回的点转为遍历结果，保留情感和图片向量
+    fn stored_point(
+        &self,
+        id: Uuid,
+        payload: HashMap<String, qdrant_client::qdrant::Value>,
+        vectors: Option<qdrant_client::qdrant::VectorsOutput>,
+    ) -> StoredVector {
+        let payload = payload.into_iter()
+            .map(|(k, v)| (k, Self::qdrant_value_to_json(v)))
+            .collect::<serde_json::Map<_, _>>();
+        let (embedding, emotion, image) = vectors.map(|vectors| self.split_vectors(vectors)).unwrap_or_default();
+
+        StoredVector {
+            id,
+            embedding: embedding.unwrap_or_default(),
+            payload: Value::Object(payload),
+            emotion,
+            image,
+        }
+    }
+
+    /// 把Qdrant返回的向量原样转为写入用的向量，保留全部命名向量
+    #[allow(deprecated)]
+    fn stored_vectors(vectors: qdrant_client::qdrant::VectorsOutput) -> Option<qdrant_client::qdrant::Vectors> {
+        use qdrant_client::qdrant::NamedVectors;
+
+        match vectors.vectors_options? {
+            VectorsOptions::Vector(vector) => Some(vector.data.into()),
+            VectorsOptions::Vectors(named) => Some(named.vectors.into_iter()
+                .fold(NamedVectors::default(), |vectors, (name, vector)| vectors.add_vector(name, vector.data))
+                .into()),
+        }
+    }
+
+    /// 以payload中的UUID为ID重建点，情感和图片等命名向量一并保留；缺少UUID或向量时返回None
+    fn migrated_point(point: qdrant_client::qdrant::RetrievedPoint) -> Option<PointStruct> {
+        let uuid = match point.payload.get("id").and_then(|v| v.kind.as_ref()) {
+            Some(qdrant_client::qdrant::value::Kind::StringValue(s)) => Uuid::parse_str(s).ok()?,
+            _ => return None,
+        };
+        let vectors = point.vectors.and_then(Self::stored_vectors)?;
+        Some(PointStruct::new(Self::uuid_to_point_id(uuid), vectors, point.payload))
+    }
+
+    /// 编码遍历游标：`{集合序号}:{点ID}`，点ID为空表示从该集合开头开始
+    fn encode_scroll_offset(collection_index: usize, point_id: PointId) -> Option<String> {
+        match point_id.point_id_options? {
+            PointIdOptions::Uuid(s) => Some(format!("{}:{}", collection_index, s)),
+            PointIdOptions::Num(n) => Some(format!("{}:{}", collection_index, n)),
+        }
+    }
+
+    /// 解码遍历游标
+    fn decode_scroll_offset(offset: &str) -> Result<(usize, Option<PointId>), anyhow::Error> {
+        let (index, point_id) = offset.split_once(':')
+            .ok_or_else(|| anyhow::anyhow!("Invalid scroll offset: {}", offset))?;
+        let index = index.parse::<usize>()
+            .map_err(|_| anyhow::anyhow!("Invalid scroll offset: {}", offset))?;
+        let point_id = match point_id {
+            "" => None,
+            id => Some(match id.parse::<u64>() {
+                Ok(n) => PointId::from(n),
+                Err(_) => PointId::from(id.to_string()),
+            }),
+        };
+        Ok((index, point_id))
+    }
+
+    /// 将搜索过滤条件转换为Qdrant payload过滤器
+    fn to_qdrant_filter(search_filter: &SearchFilter) -> Filter {
+        let mut conditions = Vec::new();
+
+        if let Some(ref user_id) = search_filter.user_id {
+            conditions.push(Condition::matches(filter::PAYLOAD_USER_ID, user_id.clone()));
+        }
+
+        if let Some(ref memory_types) = search_filter.memory_types {
+            let type_names: Vec<String> = memory_types.iter()
+                .map(|memory_type| format!("{:?}", memory_type))
+                .collect();
+            conditions.push(Condition::matches(filter::PAYLOAD_MEMORY_TYPE, type_names));
+        }
+
+        if search_filter.created_after.is_some() || search_filter.created_before.is_some() {
+            conditions.push(Condition::range(filter::PAYLOAD_CREATED_AT_TS, Range {
+                gte: search_filter.created_after.map(|t| t.timestamp() as f64),
+                lte: search_filter.created_before.map(|t| t.timestamp() as f64),
+                ..Default::default()
+            }));
+        }
+
+        for (key, value) in &search_filter.fields {
+            conditions.push(Condition::matches(key.as_str(), value.clone()));
+        }
+
+        Filter::must(conditions)
+    }
+
+    /// 将搜索结果转换为命中记录，忽略无法识别的点ID
+    fn scored_point_to_hit(&self, scored_point: ScoredPoint) -> Option<SearchHit> {
+        let id = Self::point_id_to_uuid(scored_point.id?)?;
+
+        let payload = scored_point.payload.into_iter()
+            .map(|(k, v)| (k, Self::qdrant_value_to_json(v)))
+            .collect::<serde_json::Map<_, _>>();
+
+        Some(SearchHit {
+            id,
+            score: scored_point.score,
+            payload: Value::Object(payload),
+        })
+    }
+
+    /// 将JSON值递归转换为Qdrant值
+    fn json_to_qdrant_value(value: Value) -> qdrant_client::qdrant::Value {
+        use qdrant_client::qdrant::{value::Kind, ListValue, Struct};
+
+        let kind = match value {
+            Value::Null => Kind::NullValue(0),
+            Value::Bool(b) => Kind::BoolValue(b),
+            Value::Number(n) if n.is_i64() => Kind::IntegerValue(n.as_i64().unwrap()),
+            Value::Number(n) => Kind::DoubleValue(n.as_f64().unwrap_or_default()),
+            Value::String(s) => Kind::StringValue(s),
+            Value::Array(values) => Kind::ListValue(ListValue {
+                values: values.into_iter().map(Self::json_to_qdrant_value).collect(),
+            }),
+            Value::Object(map) => Kind::StructValue(Struct {
+                fields: map.into_iter()
+                    .map(|(k, v)| (k, Self::json_to_qdrant_value(v)))
+                    .collect(),
+            }),
+        };
+
+        qdrant_client::qdrant::Value { kind: Some(kind) }
+    }
+
+    /// 将Qdrant值递归转换为JSON值
+    fn qdrant_value_to_json(value: qdrant_client::qdrant::Value) -> Value {
+        use qdrant_client::qdrant::value::Kind;
+
+        match value.kind {
+            None | Some(Kind::NullValue(_)) => Value::Null,
+            Some(Kind::BoolValue(b)) => Value::Bool(b),
+            Some(Kind::IntegerValue(i)) => Value::from(i),
+            Some(Kind::DoubleValue(d)) => Value::from(d),
+            Some(Kind::StringValue(s)) => Value::String(s),
+            Some(Kind::ListValue(list)) => Value::Array(
+                list.values.into_iter().map(Self::qdrant_value_to_json).collect(),
+            ),
+            Some(Kind::StructValue(st)) => Value::Object(
+                st.fields.into_iter()
+                    .map(|(k, v)| (k, Self::qdrant_value_to_json(v)))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// 将metadata JSON转换为Qdrant payload
+    fn metadata_to_payload(
+        metadata: &str,
+    ) -> Result<HashMap<String, qdrant_client::qdrant::Value>, anyhow::Error> {
+        let metadata_json: Value = serde_json::from_str(metadata)?;
+
+        let payload = if let Value::Object(map) = metadata_json {
+            map.into_iter()
+                .map(|(k, v)| (k, Self::json_to_qdrant_value(v)))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+
+        Ok(payload)
+    }
+
+    /// 构建Qdrant点，同时返回写入的目标集合
+    fn build_point(
+        &self,
+        id: Uuid,
+        embedding: Vec<f32>,
+        metadata: &str,
+    ) -> Result<(String, PointStruct), anyhow::Error> {
+        check_dimension(self.vector_size(), &embedding)?;
+
+        let payload = Self::metadata_to_payload(metadata)?;
+        let collection = self.collection_for_payload(&payload);
+        Ok((collection, PointStruct::new(Self::uuid_to_point_id(id), self.content_vectors(embedding), payload)))
+    }
+
+    /// 构建同时带稠密向量和稀疏向量的Qdrant点
+    fn build_hybrid_point(
+        &self,
+        id: Uuid,
+        embedding: Vec<f32>,
+        sparse: SparseVector,
+        metadata: &str,
+    ) -> Result<(String, PointStruct), anyhow::Error> {
+        use qdrant_client::qdrant::{NamedVectors, Vector};
+
+        check_dimension(self.vector_size(), &embedding)?;
+
+        let payload = Self::metadata_to_payload(metadata)?;
+        let collection = self.collection_for_payload(&payload);
+        let vectors = NamedVectors::default()
+            .add_vector(self.content_vector_name(), embedding)
+            .add_vector(SPARSE_VECTOR_NAME, Vector::new_sparse(sparse.indices, sparse.values));
+
+        Ok((collection, PointStruct::new(Self::uuid_to_point_id(id), vectors, payload)))
+    }
+
+    /// 构建同时带内容向量和情感向量的Qdrant点
+    fn build_multi_vector_point(
+        &self,
+        id: Uuid,
+        embedding: Vec<f32>,
+        emotion_embedding: Vec<f32>,
+        metadata: &str,
+    ) -> Result<(String, PointStruct), anyhow::Error> {
+        use qdrant_client::qdrant::NamedVectors;
+
+        if self.config.emotion_vector_size.is_none() {
+            return Err(anyhow::anyhow!("Emotion vectors are not enabled for this collection"));
+        }
+        check_dimension(self.vector_size(), &embedding)?;
+        check_dimension(self.config.emotion_vector_size, &emotion_embedding)?;
+
+        let payload = Self::metadata_to_payload(metadata)?;
+        let collection = self.collection_for_payload(&payload);
+        let vectors = NamedVectors::default()
+            .add_vector(CONTENT_VECTOR_NAME, embedding)
+            .add_vector(EMOTION_VECTOR_NAME, emotion_embedding);
+
+        Ok((collection, PointStruct::new(Self::uuid_to_point_id(id), vectors, payload)))
+    }
+
+    /// 某向量空间在集合中的名称，None表示默认向量
+    fn space_vector_name(&self, space: VectorSpace) -> Option<&'static str> {
+        match space {
+            VectorSpace::Content if self.uses_named_vectors() => Some(CONTENT_VECTOR_NAME),
+            VectorSpace::Content => None,
+            VectorSpace::Emotion => Some(EMOTION_VECTOR_NAME),
+            VectorSpace::Image => Some(IMAGE_VECTOR_NAME),
+        }
+    }
+
+    /// 在指定向量空间中搜索
+    async fn search_in_space(
+        &self,
+        space: VectorSpace,
+        query_embedding: Vec<f32>,
+        limit: usize,
+        threshold: f32,
+        filter: Option<SearchFilter>,
+    ) -> Result<Vec<SearchHit>, anyhow::Error> {
+        let client = self.client().await?;
+        let qdrant_filter = filter.as_ref().map(Self::to_qdrant_filter);
+        let search_params = self.search_params();
+        let vector_name = self.space_vector_name(space);
+        let mut hits = Vec::new();
+
+        for collection in self.collections_for_filter(filter.as_ref()) {
+            let mut search_request = SearchPointsBuilder::new(
+                &collection,
+                query_embedding.clone(),
+                limit as u64,
+            ).score_threshold(threshold).with_payload(true);
+
+            if let Some(vector_name) = vector_name {
+                search_request = search_request.vector_name(vector_name);
+            }
+            if let Some(ref qdrant_filter) = qdrant_filter {
+                search_request = search_request.filter(qdrant_filter.clone());
+            }
+            if let Some(ref params) = search_params {
+                search_request = search_request.params(params.clone());
+            }
+
+            let search_result = self.run(true, || client.search_points(search_request.clone())).await
+                .map_err(|e| anyhow::anyhow!("Qdrant search error: {}", e))?;
+
+            hits.extend(search_result.result.into_iter()
+                .filter_map(|scored_point| self.scored_point_to_hit(scored_point)));
+        }
+
+        Ok(Self::merge_hits(hits, limit, self.config.distance))
+    }
+}
+
+#[async_trait]
+impl VectorStore for QdrantStore {
+    type Error = anyhow::Error;
+
+    fn vector_size(&self) -> Option<usize> {
+        Some(self.config.vector_size)
+    }
+
+    fn distance_metric(&self) -> DistanceMetric {
+        self.config.distance
+    }
+
+    async fn store_vector(
+        &self,
+        id: Uuid,
+        embedding: Vec<f32>,
+        metadata: String,
+    ) -> Result<(), Self::Error> {
+        let (collection, point) = self.build_point(id, embedding, &metadata)?;
+
+        self.write_points(HashMap::from([(collection, vec![point])])).await
+    }
+
+    async fn store_vectors(
+        &self,
+        points: Vec<(Uuid, Vec<f32>, String)>,
+    ) -> Result<(), Self::Error> {
+        // 按目标集合分组
+        let mut grouped: HashMap<String, Vec<PointStruct>> = HashMap::new();
+        for (id, embedding, metadata) in points {
+            let (collection, point) = self.build_point(id, embedding, &metadata)?;
+            grouped.entry(collection).or_default().push(point);
+        }
+
+        self.write_points(grouped).await
+    }
+
+    async fn search_similar(
+        &self,
+        query_embedding: Vec<f32>,
+        limit: usize,
+        threshold: f32,
+        filter: Option<SearchFilter>,
+    ) -> Result<Vec<SearchHit>, Self::Error> {
+        check_dimension(self.vector_size(), &query_embedding)?;
+
+        self.search_in_space(VectorSpace::Content, query_embedding, limit, threshold, filter).await
+    }
+
+    /// 单集合时按页请求，消费方停止后不再请求后续页；
+    /// 跨多个分区集合时需要合并排序，退化为一次性搜索
+    fn search_stream(
+        &self,
+        query_embedding: Vec<f32>,
+        limit: usize,
+        threshold: f32,
+        filter: Option<SearchFilter>,
+    ) -> HitStream<'_, Self::Error> {
+        if let Err(e) = check_dimension(self.vector_size(), &query_embedding) {
+            return stream::once(std::future::ready(Err(e.into()))).boxed();
+        }
+
+        let mut collections = self.collections_for_filter(filter.as_ref());
+        if collections.len() != 1 {
+            return stream_search_results(self.search_similar(query_embedding, limit, threshold, filter));
+        }
+        let collection = collections.remove(0);
+        let qdrant_filter = filter.as_ref().map(Self::to_qdrant_filter);
+        let search_params = self.search_params();
+        let vector_name = self.space_vector_name(VectorSpace::Content);
+
+        stream::try_unfold(0usize, move |offset| {
+            let collection = collection.clone();
+            let query_embedding = query_embedding.clone();
+            let qdrant_filter = qdrant_filter.clone();
+            let search_params = search_params.clone();
+
+            async move {
+                if offset >= limit {
+                    return Ok(None);
+                }
+                let page_size = STREAM_PAGE_SIZE.min(limit - offset);
+
+                let client = self.client().await?;
+                let mut search_request = SearchPointsBuilder::new(
+                    &collection,
+                    query_embedding,
+                    page_size as u64,
+                ).offset(offset as u64).score_threshold(threshold).with_payload(true);
+
+                if let Some(vector_name) = vector_name {
+                    search_request = search_request.vector_name(vector_name);
+                }
+                if let Some(qdrant_filter) = qdrant_filter {
+                    search_request = search_request.filter(qdrant_filter);
+                }
+                if let Some(params) = search_params {
+                    search_request = search_request.params(params);
+                }
+
+                let search_result = self.run(true, || client.search_points(search_request.clone())).await
+                    .map_err(|e| anyhow::anyhow!("Qdrant search error: {}", e))?;
+
+                // 不足一页说明后面已没有满足阈值的结果
+                let next_offset = if search_result.result.len() < page_size { limit } else { offset + page_size };
+                let hits: Vec<SearchHit> = search_result.result.into_iter()
+                    .filter_map(|scored_point| self.scored_point_to_hit(scored_point))
+                    .collect();
+
+                Ok(Some((hits, next_offset)))
+            }
+        })
+        .map_ok(|hits| stream::iter(hits.into_iter().map(Ok)))
+        .try_flatten()
+        .boxed()
+    }
+
+    async fn search_similar_batch(
+        &self,
+        query_embeddings: Vec<Vec<f32>>,
+        limit: usize,
+        threshold: f32,
+        filter: Option<SearchFilter>,
+    ) -> Result<Vec<Vec<SearchHit>>, Self::Error> {
+        if query_embeddings.is_empty() {
+            return Ok(Vec::new());
+        }
+        for query_embedding in &query_embeddings {
+            check_dimension(self.vector_size(), query_embedding)?;
+        }
+
+        let client = self.client().await?;
+        let qdrant_filter = filter.as_ref().map(Self::to_qdrant_filter);
+        let search_params = self.search_params();
+        let mut results: Vec<Vec<SearchHit>> = vec![Vec::new(); query_embeddings.len()];
+
+        for collection in self.collections_for_filter(filter.as_ref()) {
+            let searches = query_embeddings.iter()
+                .map(|query_embedding| {
+                    let mut search = SearchPointsBuilder::new(
+                        &collection,
+                        query_embedding.clone(),
+                        limit as u64,
+                    ).score_threshold(threshold).with_payload(true);
+                    if let Some(vector_name) = self.space_vector_name(VectorSpace::Content) {
+                        search = search.vector_name(vector_name);
+                    }
+                    if let Some(ref qdrant_filter) = qdrant_filter {
+                        search = search.filter(qdrant_filter.clone());
+                    }
+                    if let Some(ref params) = search_params {
+                        search = search.params(params.clone());
+                    }
+                    search.build()
+                })
+                .collect();
+
+            let batch_request = SearchBatchPointsBuilder::new(&collection, searches);
+
+            let batch_result = self.run(true, || client.search_batch_points(batch_request.clone())).await
+                .map_err(|e| anyhow::anyhow!("Qdrant search error: {}", e))?;
+
+            for (hits, batch) in results.iter_mut().zip(batch_result.result) {
+                hits.extend(batch.result.into_iter()
+                    .filter_map(|scored_point| self.scored_point_to_hit(scored_point)));
+            }
+        }
+
+        Ok(results.into_iter().map(|hits| Self::merge_hits(hits, limit, self.config.distance)).collect())
+    }
+
+    async fn store_hybrid(
+        &self,
+        id: Uuid,
+        embedding: Vec<f32>,
+        sparse: SparseVector,
+        metadata: String,
+    ) -> Result<(), Self::Error> {
+        // 集合未配置稀疏向量时写入带名向量会被拒绝，只存稠密部分
+        if !self.config.hybrid {
+            return self.store_vector(id, embedding, metadata).await;
+        }
+
+        let (collection, point) = self.build_hybrid_point(id, embedding, sparse, &metadata)?;
+
+        self.write_points(HashMap::from([(collection, vec![point])])).await
+    }
+
+    async fn search_hybrid(
+        &self,
+        query_embedding: Vec<f32>,
+        query_sparse: SparseVector,
+        limit: usize,
+        threshold: f32,
+        filter: Option<SearchFilter>,
+    ) -> Result<Vec<SearchHit>, Self::Error> {
+        use qdrant_client::qdrant::{
+            Fusion, PrefetchQueryBuilder, Query, QueryPointsBuilder, VectorInput,
+        };
+
+        if !self.config.hybrid || query_sparse.is_empty() {
+            return self.search_similar(query_embedding, limit, threshold, filter).await;
+        }
+        check_dimension(self.vector_size(), &query_embedding)?;
+
+        let client = self.client().await?;
+        let qdrant_filter = filter.as_ref().map(Self::to_qdrant_filter);
+        let search_params = self.search_params();
+        // 每路召回多取一些候选，交给RRF融合
+        let prefetch_limit = limit.saturating_mul(4) as u64;
+        let mut hits = Vec::new();
+
+        for collection in self.collections_for_filter(filter.as_ref()) {
+            let mut dense_prefetch = PrefetchQueryBuilder::default()
+                .query(Query::new_nearest(query_embedding.clone()))
+                .score_threshold(threshold)
+                .limit(prefetch_limit);
+            let mut sparse_prefetch = PrefetchQueryBuilder::default()
+                .query(Query::new_nearest(VectorInput::new_sparse(
+                    query_sparse.indices.clone(),
+                    query_sparse.values.clone(),
+                )))
+                .using(SPARSE_VECTOR_NAME)
+                .limit(prefetch_limit);
+
+            if let Some(ref qdrant_filter) = qdrant_filter {
+                dense_prefetch = dense_prefetch.filter(qdrant_filter.clone());
+                sparse_prefetch = sparse_prefetch.filter(qdrant_filter.clone());
+            }
+            if let Some(ref params) = search_params {
+                dense_prefetch = dense_prefetch.params(params.clone());
+            }
+            if let Some(vector_name) = self.space_vector_name(VectorSpace::Content) {
+                dense_prefetch = dense_prefetch.using(vector_name);
+            }
+
+            let query_request = QueryPointsBuilder::new(&collection)
+                .add_prefetch(dense_prefetch)
+                .add_prefetch(sparse_prefetch)
+                .query(Query::new_fusion(Fusion::Rrf))
+                .limit(limit as u64)
+                .with_payload(true);
+
+            let query_result = self.run(true, || client.query(query_request.clone())).await
+                .map_err(|e| anyhow::anyhow!("Qdrant search error: {}", e))?;
+
+            hits.extend(query_result.result.into_iter()
+                .filter_map(|scored_point| self.scored_point_to_hit(scored_point)));
+        }
+
+        // RRF融合分数总是越大越好
+        Ok(Self::merge_hits(hits, limit, DistanceMetric::Dot))
+    }
+
+    async fn count(&self, filter: Option<SearchFilter>) -> Result<u64, Self::Error> {
+        self.count_points(filter, true).await
+    }
+
+    async fn estimated_count(&self, filter: Option<SearchFilter>) -> Result<u64, Self::Error> {
+        self.count_points(filter, false).await
+    }
+
+    async fn purge_expired(&self, filter: Option<SearchFilter>) -> Result<Vec<Uuid>, Self::Error> {
+        use qdrant_client::qdrant::{DeletePointsBuilder, ScrollPointsBuilder};
+
+        let client = self.client().await?;
+        let mut qdrant_filter = filter.as_ref().map(Self::to_qdrant_filter).unwrap_or_default();
+        qdrant_filter.must.push(Condition::range(filter::PAYLOAD_EXPIRES_AT_TS, Range {
+            lte: Some(chrono::Utc::now().timestamp() as f64),
+            ..Default::default()
+        }));
+
+        let mut purged = Vec::new();
+        for collection in self.collections_for_filter(filter.as_ref()) {
+            let mut offset: Option<PointId> = None;
+
+            // 先按过滤条件找出到期的点，再按ID删除，以便返回被删除的ID
+            loop {
+                let mut scroll_request = ScrollPointsBuilder::new(&collection)
+                    .filter(qdrant_filter.clone())
+                    .limit(256)
+                    .with_payload(false)
+                    .with_vectors(false);
+                if let Some(offset) = offset.take() {
+                    scroll_request = scroll_request.offset(offset);
+                }
+
+                let scroll_result = self.run(true, || client.scroll(scroll_request.clone())).await
+                    .map_err(|e| anyhow::anyhow!("Qdrant client error: {}", e))?;
+
+                let point_ids: Vec<PointId> = scroll_result.result.into_iter()
+                    .filter_map(|point| point.id)
+                    .collect();
+
+                if !point_ids.is_empty() {
+                    purged.extend(point_ids.iter().cloned().filter_map(Self::point_id_to_uuid));
+
+                    let delete_request = DeletePointsBuilder::new(&collection).points(point_ids);
+                    self.run(true, || client.delete_points(delete_request.clone())).await
+                        .map_err(|e| anyhow::anyhow!("Qdrant client error: {}", e))?;
+                }
+
+                match scroll_result.next_page_offset {
+                    Some(next) => offset = Some(next),
+                    None => break,
+                }
+            }
+        }
+
+        Ok(purged)
+    }
+
+    async fn get_vector(&self, id: Uuid) -> Result<Option<StoredVector>, Self::Error> {
+        use qdrant_client::qdrant::GetPointsBuilder;
+
+        let client = self.client().await?;
+        for collection in self.all_collections() {
+            let get_request = GetPointsBuilder::new(&collection, vec![Self::uuid_to_point_id(id)])
+                .with_payload(true)
+                .with_vectors(true);
+            let response = self.run(true, || client.get_points(get_request.clone())).await
+                .map_err(|e| anyhow::anyhow!("Qdrant client error: {}", e))?;
+
+            if let Some(point) = response.result.into_iter().next() {
+                return Ok(Some(self.stored_point(id, point.payload, point.vectors)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// 每个集合一次请求，不读取向量
+    async fn get_payloads(&self, ids: &[Uuid]) -> Result<HashMap<Uuid, Value>, Self::Error> {
+        use qdrant_client::qdrant::GetPointsBuilder;
+
+        let mut payloads = HashMap::with_capacity(ids.len());
+        if ids.is_empty() {
+            return Ok(payloads);
+        }
+        let client = self.client().await?;
+        let point_ids: Vec<PointId> = ids.iter().map(|id| Self::uuid_to_point_id(*id)).collect();
+        for collection in self.all_collections() {
+            let get_request = GetPointsBuilder::new(&collection, point_ids.clone())
+                .with_payload(true)
+                .with_vectors(false);
+            let response = self.run(true, || client.get_points(get_request.clone())).await
+                .map_err(|e| anyhow::anyhow!("Qdrant client error: {}", e))?;
+
+            for point in response.result {
+                let Some(id) = point.id.and_then(Self::point_id_to_uuid) else {
+                    continue;
+                };
+                payloads.entry(id).or_insert_with(|| self.stored_point(id, point.payload, None).payload);
+            }
+        }
+        Ok(payloads)
+    }
+
+    async fn scroll(&self, offset: Option<String>, limit: usize) -> Result<ScrollPage, Self::Error> {
+        use qdrant_client::qdrant::ScrollPointsBuilder;
+
+        let (mut collection_index, mut point_offset) = match offset {
+            Some(ref offset) => Self::decode_scroll_offset(offset)?,
+            None => (0, None),
+        };
+
+        let client = self.client().await?;
+        let collections = self.all_collections();
+        let mut page = ScrollPage::default();
+
+        // 按集合顺序遍历，一页可以跨越多个分区
+        while let Some(collection) = collections.get(collection_index) {
+            let remaining = limit.saturating_sub(page.points.len());
+            if remaining == 0 {
+                break;
+            }
+
+            let mut scroll_request = ScrollPointsBuilder::new(collection)
+                .limit(remaining as u32)
+                .with_payload(true)
+                .with_vectors(true);
+            if let Some(point_offset) = point_offset.take() {
+                scroll_request = scroll_request.offset(point_offset);
+            }
+
+            let scroll_result = self.run(true, || client.scroll(scroll_request.clone())).await
+                .map_err(|e| anyhow::anyhow!("Qdrant client error: {}", e))?;
+
+            for point in scroll_result.result {
+                // 数字ID的旧点需先执行migrate_numeric_point_ids
+                let Some(id) = point.id.and_then(Self::point_id_to_uuid) else { continue };
+                page.points.push(self.stored_point(id, point.payload, point.vectors));
+            }
+
+            match scroll_result.next_page_offset {
+                Some(next) => point_offset = Some(next),
+                None => collection_index += 1,
+            }
+        }
+
+        page.next_offset = match point_offset {
+            Some(point_id) => Self::encode_scroll_offset(collection_index, point_id),
+            None if collection_index < collections.len() => Some(format!("{}:", collection_index)),
+            None => None,
+        };
+
+        Ok(page)
+    }
+
+    fn emotion_vector_size(&self) -> Option<usize> {
+        self.config.emotion_vector_size
+    }
+
+    async fn store_multi_vector(
+        &self,
+        id: Uuid,
+        embedding: Vec<f32>,
+        emotion_embedding: Vec<f32>,
+        metadata: String,
+    ) -> Result<(), Self::Error> {
+        let (collection, point) = self.build_multi_vector_point(id, embedding, emotion_embedding, &metadata)?;
+
+        self.write_points(HashMap::from([(collection, vec![point])])).await
+    }
+
+    async fn store_multi_vectors(
+        &self,
+        points: Vec<(Uuid, Vec<f32>, Vec<f32>, String)>,
+    ) -> Result<(), Self::Error> {
+        let mut grouped: HashMap<String, Vec<PointStruct>> = HashMap::new();
+        for (id, embedding, emotion_embedding, metadata) in points {
+            let (collection, point) = self.build_multi_vector_point(id, embedding, emotion_embedding, &metadata)?;
+            grouped.entry(collection).or_default().push(point);
+        }
+
+        self.write_points(grouped).await
+    }
+
+    fn image_vector_size(&self) -> Option<usize> {
+        self.config.image_vector_size
+    }
+
+    async fn attach_image_vector(&self, id: Uuid, image_embedding: Vec<f32>) -> Result<(), Self::Error> {
+        use qdrant_client::qdrant::{NamedVectors, PointVectors, UpdatePointVectorsBuilder};
+
+        if self.config.image_vector_size.is_none() {
+            return Err(anyhow::anyhow!("Image vectors are not enabled for this collection"));
+        }
+        check_dimension(self.config.image_vector_size, &image_embedding)?;
+
+        let collection = self.locate_collection(id).await?;
+        let client = self.client().await?;
+        // 只更新图片向量，内容和情感向量保持不变
+        let point = PointVectors {
+            id: Some(Self::uuid_to_point_id(id)),
+            vectors: Some(NamedVectors::default().add_vector(IMAGE_VECTOR_NAME, image_embedding).into()),
+        };
+
+        let update_request = UpdatePointVectorsBuilder::new(&collection, vec![point]);
+
+        self.run(true, || client.update_vectors(update_request.clone())).await
+            .map_err(|e| anyhow::anyhow!("Qdrant client error: {}", e))?;
+
+        Ok(())
+    }
+
+    async fn search_space(
+        &self,
+        space: VectorSpace,
+        query_embedding: Vec<f32>,
+        limit: usize,
+        threshold: f32,
+        filter: Option<SearchFilter>,
+    ) -> Result<Vec<SearchHit>, Self::Error> {
+        match space {
+            VectorSpace::Content => check_dimension(self.vector_size(), &query_embedding)?,
+            VectorSpace::Emotion if self.config.emotion_vector_size.is_some() => {
+                check_dimension(self.config.emotion_vector_size, &query_embedding)?
+            }
+            VectorSpace::Emotion => {
+                return Err(anyhow::anyhow!("Emotion vectors are not enabled for this collection"));
+            }
+            VectorSpace::Image if self.config.image_vector_size.is_some() => {
+                check_dimension(self.config.image_vector_size, &query_embedding)?
+            }
+            VectorSpace::Image => {
+                return Err(anyhow::anyhow!("Image vectors are not enabled for this collection"));
+            }
+        }
+
+        self.search_in_space(space, query_embedding, limit, threshold, filter).await
+    }
+
+    async fn update_vector(&self, id: Uuid, embedding: Vec<f32>) -> Result<(), Self::Error> {
+        use qdrant_client::qdrant::{PointVectors, UpdatePointVectorsBuilder};
+
+        check_dimension(self.vector_size(), &embedding)?;
+
+        let collection = self.locate_collection(id).await?;
+        let client = self.client().await?;
+        let point = PointVectors {
+            id: Some(Self::uuid_to_point_id(id)),
+            vectors: Some(self.content_vectors(embedding)),
+        };
+
+        let update_request = UpdatePointVectorsBuilder::new(&collection, vec![point]);
+
+        self.run(true, || client.update_vectors(update_request.clone())).await
+            .map_err(|e| anyhow::anyhow!("Qdrant client error: {}", e))?;
+
+        Ok(())
+    }
+
+    async fn update_payload(&self, id: Uuid, patch: Value) -> Result<(), Self::Error> {
+        use qdrant_client::qdrant::{PointsIdsList, SetPayloadPointsBuilder};
+
+        let Value::Object(patch) = patch else {
+            return Err(anyhow::anyhow!("Payload patch must be a JSON object"));
+        };
+
+        let collection = self.locate_collection(id).await?;
+        let client = self.client().await?;
+        let payload: HashMap<String, qdrant_client::qdrant::Value> = patch.into_iter()
+            .map(|(k, v)| (k, Self::json_to_qdrant_value(v)))
+            .collect();
+
+        // 按类型分区时修改记忆类型需要把点移到新分区
+        if self.config.partition == PartitionStrategy::PerMemoryType
+            && payload.contains_key(filter::PAYLOAD_MEMORY_TYPE)
+        {
+            let target = self.collection_for_payload(&payload);
+            if target != collection {
+                return self.move_point(&client, id, &collection, target, payload).await;
+            }
+        }
+
+        // set_payload只覆盖给定字段
+        let set_request = SetPayloadPointsBuilder::new(&collection, payload)
+            .points_selector(PointsIdsList {
+                ids: vec![Self::uuid_to_point_id(id)],
+            });
+
+        self.run(true, || client.set_payload(set_request.clone())).await
+            .map_err(|e| anyhow::anyhow!("Qdrant client error: {}", e))?;
+
+        Ok(())
+    }
+
+    async fn delete_vector(&self, id: Uuid) -> Result<(), Self::Error> {
+        use qdrant_client::qdrant::DeletePointsBuilder;
+        
+        let client = self.client().await?;
+
+        // 删除不存在的点是无操作，直接对所有分区执行
+        for collection in self.all_collections() {
+            let delete_request = DeletePointsBuilder::new(&collection)
+                .points(vec![Self::uuid_to_point_id(id)]);
+            
+            self.run(true, || client.delete_points(delete_request.clone())).await
+                .map_err(|e| anyhow::anyhow!("Qdrant client error: {}", e))?;
+        }
+
+        Ok(())
+    }
+
+    async fn create_collection(&self, name: &str, vector_size: usize) -> Result<(), Self::Error> {
+        use qdrant_client::qdrant::{
+            CompressionRatio, HnswConfigDiffBuilder, Modifier, ProductQuantizationBuilder,
+            ScalarQuantizationBuilder, SparseVectorParamsBuilder, SparseVectorsConfigBuilder,
+            VectorsConfigBuilder,
+        };
+
+        let distance = match self.config.distance {
+            DistanceMetric::Cosine => Distance::Cosine,
+            DistanceMetric::Dot => Distance::Dot,
+            DistanceMetric::Euclidean => Distance::Euclid,
+        };
+        let content_params = VectorParamsBuilder::new(vector_size as u64, distance)
+            .on_disk(self.config.on_disk_vectors);
+
+        let client = self.client().await?;
+        let mut collection_config = CreateCollectionBuilder::new(name);
+
+        collection_config = if self.uses_named_vectors() {
+            let mut vectors_config = VectorsConfigBuilder::default();
+            vectors_config.add_named_vector_params(CONTENT_VECTOR_NAME, content_params);
+            if let Some(emotion_vector_size) = self.config.emotion_vector_size {
+                vectors_config.add_named_vector_params(
+                    EMOTION_VECTOR_NAME,
+                    VectorParamsBuilder::new(emotion_vector_size as u64, distance),
+                );
+            }
+            if let Some(image_vector_size) = self.config.image_vector_size {
+                vectors_config.add_named_vector_params(
+                    IMAGE_VECTOR_NAME,
+                    VectorParamsBuilder::new(image_vector_size as u64, distance),
+                );
+            }
+            collection_config.vectors_config(vectors_config)
+        } else {
+            collection_config.vectors_config(content_params)
+        };
+
+        if let Some(ref hnsw) = self.config.hnsw {
+            collection_config = collection_config.hnsw_config(
+                HnswConfigDiffBuilder::default()
+                    .m(hnsw.m)
+                    .ef_construct(hnsw.ef_construct)
+            );
+        }
+
+        if self.config.hybrid {
+            // IDF修正让罕见关键词获得更高权重
+            let mut sparse_config = SparseVectorsConfigBuilder::default();
+            sparse_config.add_named_vector_params(
+                SPARSE_VECTOR_NAME,
+                SparseVectorParamsBuilder::default().modifier(Modifier::Idf),
+            );
+            collection_config = collection_config.sparse_vectors_config(sparse_config);
+        }
+
+        match self.config.quantization {
+            Some(Quantization::Scalar { quantile, always_ram }) => {
+                let mut scalar = ScalarQuantizationBuilder::default().always_ram(always_ram);
+                if let Some(quantile) = quantile {
+                    scalar = scalar.quantile(quantile);
+                }
+                collection_config = collection_config.quantization_config(scalar);
+            }
+            Some(Quantization::Product { compression, always_ram }) => {
+                let ratio = match compression {
+                    ProductCompression::X4 => CompressionRatio::X4,
+                    ProductCompression::X8 => CompressionRatio::X8,
+                    ProductCompression::X16 => CompressionRatio::X16,
+                    ProductCompression::X32 => CompressionRatio::X32,
+                    ProductCompression::X64 => CompressionRatio::X64,
+                };
+                collection_config = collection_config.quantization_config(
+                    ProductQuantizationBuilder::new(ratio.into()).always_ram(always_ram)
+                );
+            }
+            None => {}
+        }
+
+        // 创建集合不是幂等操作，默认不重试
+        self.run(false, || client.create_collection(collection_config.clone())).await
+            .map_err(|e| anyhow::anyhow!("Qdrant collection error: {}", e))?;
+
+        Ok(())
+    }
+
+    async fn drop_collection(&self, name: &str) -> Result<(), Self::Error> {
+        let client = self.client().await?;
+        self.run(true, || client.delete_collection(name)).await
+            .map_err(|e| anyhow::anyhow!("Qdrant collection error: {}", e))?;
+
+        Ok(())
+    }
+
+    async fn list_collections(&self) -> Result<Vec<String>, Self::Error> {
+        let client = self.client().await?;
+        let collections = self.run(true, || client.list_collections()).await
+            .map_err(|e| anyhow::anyhow!("Qdrant client error: {}", e))?;
+
+        Ok(collections.collections.into_iter().map(|c| c.name).collect())
+    }
+
+    async fn collection_exists(&self, name: &str) -> Result<bool, Self::Error> {
+        let client = self.client().await?;
+        self.run(true, || client.collection_exists(name)).await
+            .map_err(|e| anyhow::anyhow!("Qdrant client error: {}", e))
+    }
+
+    async fn health_check(&self) -> Result<HealthStatus, Self::Error> {
+        if !self.check_health().await {
+            return Ok(HealthStatus::Unhealthy {
+                reason: format!("无法连接Qdrant: {}", self.config.effective_url()),
+            });
+        }
+
+        let client = self.client().await?;
+        for collection in self.all_collections() {
+            match client.collection_exists(&collection).await {
+                Ok(true) => {}
+                Ok(false) => return Ok(HealthStatus::Unhealthy {
+                    reason: format!("集合不存在: {}", collection),
+                }),
+                Err(e) => return Ok(HealthStatus::Unhealthy {
+                    reason: format!("查询集合失败: {}", e),
+                }),
+            }
+        }
+
+        let buffered = self.buffered_writes().await;
+        if buffered > 0 {
+            return Ok(HealthStatus::Degraded {
+                reason: format!("{} 条写入等待补写", buffered),
+            });
+        }
+
+        Ok(HealthStatus::Healthy)
+    }
+
+    async fn get_stats(&self) -> Result<HashMap<String, u64>, Self::Error> {
+        let client = self.client().await?;
+        let mut stats = HashMap::new();
+        
+        for collection in self.all_collections() {
+            let collection_info = self.run(true, || client.collection_info(&collection)).await
+                .map_err(|e| anyhow::anyhow!("Qdrant client error: {}", e))?;
+
+            if let Some(result) = collection_info.result {
+                *stats.entry("points_count".to_string()).or_insert(0) += result.points_count.unwrap_or(0);
+                *stats.entry("vectors_count".to_string()).or_insert(0) += result.vectors_count.unwrap_or(0);
+            }
+        }
+
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_tls_upgrade_and_redaction() {
+        let mut config = QdrantConfig::new("http://qdrant.example.com:6334", "memories", 768);
+        assert_eq!(config.effective_url(), "http://qdrant.example.com:6334");
+
+        config.use_tls = true;
+        config.api_key = Some("secret-key".to_string());
+        assert_eq!(config.effective_url(), "https://qdrant.example.com:6334");
+        assert!(!format!("{:?}", config).contains("secret-key"));
+    }
+
+    #[test]
+    fn test_rest_url_derived_from_grpc_url() {
+        let mut config = QdrantConfig::new("http://localhost:6334/", "memories", 768);
+        assert_eq!(config.effective_rest_url(), "http://localhost:6333");
+
+        config.rest_url = Some("https://qdrant.internal/".to_string());
+        assert_eq!(config.effective_rest_url(), "https://qdrant.internal");
+
+        assert_eq!(
+            config.snapshot_recover_url("memories/../a b?").unwrap().as_str(),
+            "https://qdrant.internal/collections/memories%2F..%2Fa%20b%3F/snapshots/recover?wait=true"
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_migrated_point_keeps_named_vectors() {
+        use qdrant_client::qdrant::{vectors, NamedVectorsOutput, RetrievedPoint, VectorOutput, VectorsOutput};
+
+        let uuid = Uuid::new_v4();
+        let vector = |data: Vec<f32>| VectorOutput { data, ..Default::default() };
+        let point = RetrievedPoint {
+            id: Some(PointId::from(7u64)),
+            payload: HashMap::from([("id".to_string(), uuid.to_string().into())]),
+            vectors: Some(VectorsOutput {
+                vectors_options: Some(VectorsOptions::Vectors(NamedVectorsOutput {
+                    vectors: HashMap::from([
+                        (CONTENT_VECTOR_NAME.to_string(), vector(vec![1.0, 0.0])),
+                        (EMOTION_VECTOR_NAME.to_string(), vector(vec![0.5; 5])),
+                    ]),
+                })),
+            }),
+            ..Default::default()
+        };
+
+        let migrated = QdrantStore::migrated_point(point).unwrap();
+        assert_eq!(migrated.id, Some(QdrantStore::uuid_to_point_id(uuid)));
+        let Some(vectors::VectorsOptions::Vectors(named)) = migrated.vectors.and_then(|v| v.vectors_options) else {
+            panic!("迁移后应保留命名向量");
+        };
+        assert_eq!(named.vectors[EMOTION_VECTOR_NAME].data, vec![0.5; 5]);
+        assert!(named.vectors.contains_key(CONTENT_VECTOR_NAME));
+
+        // 缺少UUID的点不迁移
+        assert!(QdrantStore::migrated_point(RetrievedPoint::default()).is_none());
     }
 }
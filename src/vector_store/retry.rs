@@ -0,0 +1,171 @@
+//! 向量存储操作的重试策略 - 指数退避 + 抖动
+
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::time::Duration;
+
+/// 重试策略
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// 最大尝试次数（包含首次调用）
+    pub max_attempts: u32,
+    /// 首次重试前的等待时间(毫秒)
+    pub initial_backoff_ms: u64,
+    /// 单次等待上限(毫秒)
+    pub max_backoff_ms: u64,
+    /// 退避倍数
+    pub multiplier: f64,
+    /// 是否在等待时间上加入随机抖动
+    pub jitter: bool,
+    /// 是否重试非幂等操作（例如创建集合）
+    pub retry_non_idempotent: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 5_000,
+            multiplier: 2.0,
+            jitter: true,
+            retry_non_idempotent: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 不重试
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// 第`attempt`次失败后的等待时间（attempt从1开始）
+    pub fn backoff_delay(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1) as i32;
+        let base = (self.initial_backoff_ms as f64 * self.multiplier.powi(exponent))
+            .min(self.max_backoff_ms as f64);
+
+        // 等比抖动：在[base/2, base]之间随机取值，避免多个客户端同时重试
+        let millis = if self.jitter && base > 0.0 {
+            rand::random_range(base / 2.0..=base)
+        } else {
+            base
+        };
+
+        Duration::from_millis(millis as u64)
+    }
+
+    /// 按策略执行操作，非幂等操作默认只执行一次
+    pub async fn run<T, E, F, Fut>(&self, idempotent: bool, operation: F) -> Result<T, E>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: std::fmt::Display,
+    {
+        self.run_if(idempotent, |_| true, operation).await
+    }
+
+    /// 按策略执行操作，只重试`retryable`返回true的错误，其余错误直接返回
+    pub async fn run_if<T, E, F, Fut, R>(&self, idempotent: bool, retryable: R, mut operation: F) -> Result<T, E>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: std::fmt::Display,
+        R: Fn(&E) -> bool,
+    {
+        let max_attempts = if idempotent || self.retry_non_idempotent {
+            self.max_attempts.max(1)
+        } else {
+            1
+        };
+
+        let mut attempt = 1;
+        loop {
+            match operation().await {
+                Ok(value) => return Ok(value),
+                Err(e) if attempt < max_attempts && retryable(&e) => {
+                    let delay = self.backoff_delay(attempt);
+                    tracing::warn!(
+                        "操作失败，{}ms后重试 ({}/{}): {}",
+                        delay.as_millis(), attempt, max_attempts, e
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn fast_policy() -> RetryPolicy {
+        RetryPolicy {
+            initial_backoff_ms: 1,
+            max_backoff_ms: 2,
+            jitter: false,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_backoff_is_exponential_and_capped() {
+        let policy = RetryPolicy {
+            initial_backoff_ms: 100,
+            max_backoff_ms: 300,
+            jitter: false,
+            ..Default::default()
+        };
+
+        assert_eq!(policy.backoff_delay(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff_delay(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff_delay(3), Duration::from_millis(300));
+    }
+
+    #[tokio::test]
+    async fn test_retries_idempotent_until_success() {
+        let calls = AtomicU32::new(0);
+
+        let result: Result<u32, String> = fast_policy().run(true, || async {
+            let n = calls.fetch_add(1, Ordering::SeqCst) + 1;
+            if n < 3 { Err(format!("attempt {} failed", n)) } else { Ok(n) }
+        }).await;
+
+        assert_eq!(result, Ok(3));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_non_idempotent_runs_once() {
+        let calls = AtomicU32::new(0);
+
+        let result: Result<(), String> = fast_policy().run(false, || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err("failed".to_string())
+        }).await;
+
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_permanent_errors_are_not_retried() {
+        let calls = AtomicU32::new(0);
+
+        let result: Result<(), String> = fast_policy().run_if(true, |e: &String| e.starts_with("transient"), || async {
+            let n = calls.fetch_add(1, Ordering::SeqCst) + 1;
+            Err(if n == 1 { "transient".to_string() } else { "bad request".to_string() })
+        }).await;
+
+        assert_eq!(result, Err("bad request".to_string()));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
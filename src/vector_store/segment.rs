@@ -0,0 +1,1032 @@
+//! 内存映射的只读段文件 - 大量记忆的嵌入向量留在页缓存中，不常驻进程堆
+//!
+//! 文件布局（小端）：
+//!
+//! ```text
+//! 头部 48字节: SEGMENT_MAGIC | 版本 u32 | 维度 u32 | 情感维度 u32 | 图片维度 u32 | 点数 u64 | payload总长 u64 | 字符串表长 u64
+//! 向量:        点数 × (维度 + 情感维度 + 图片维度) × f32，缺少的情感/图片向量以0填充
+//! ID:          点数 × 16字节
+//! 元数据:      点数 × 32字节: 用户 u32 | 记忆类型 u32 | 创建时间 i64 | 过期时间 i64 | 标志 u32 | 保留 u32
+//! payload偏移: (点数 + 1) × u64
+//! payload:     UTF-8 JSON依次拼接
+//! 字符串表:    JSON字符串数组，元数据中的用户和记忆类型是其中的下标
+//! ```
+//!
+//! 按用户、记忆类型、时间过滤和清理过期点只读定长的元数据列，只有自定义字段过滤才解析payload。
+//!
+//! `SegmentVectorStore`把段文件作为只读底层，新写入、修改和删除记录在上层的`MockVectorStore`中；
+//! 用`write_segment`把两层合并为新的段文件即可压实。
+
+use super::exact::{compare_ranked, exact_top_k};
+use super::filter::{PAYLOAD_CREATED_AT_TS, PAYLOAD_EXPIRES_AT_TS, PAYLOAD_MEMORY_TYPE, PAYLOAD_USER_ID};
+use super::{
+    store_point, DistanceMetric, HealthStatus, MockVectorStore, ScrollPage, SearchFilter, SearchHit, SparseVector,
+    StoredVector, VectorSpace, VectorStore,
+};
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use memmap2::Mmap;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// 段文件头部标识
+pub const SEGMENT_MAGIC: &[u8; 8] = b"MIRASEG\0";
+
+/// 当前段文件版本
+pub const SEGMENT_VERSION: u32 = 1;
+
+const HEADER_LEN: usize = 48;
+
+/// 每行元数据的字节数
+const META_LEN: usize = 32;
+
+/// 元数据中缺少的字符串
+const NO_STRING: u32 = u32::MAX;
+
+/// 元数据中缺少的时间戳
+const NO_TIMESTAMP: i64 = i64::MIN;
+
+const HAS_EMOTION: u32 = 1;
+const HAS_IMAGE: u32 = 1 << 1;
+
+/// 写入段文件时每页遍历的点数
+const BATCH_SIZE: usize = 256;
+
+/// 一行的定长元数据
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RowMeta {
+    user: u32,
+    memory_type: u32,
+    created_at: Option<i64>,
+    expires_at: Option<i64>,
+    flags: u32,
+}
+
+impl RowMeta {
+    fn encode(&self) -> [u8; META_LEN] {
+        let mut bytes = [0u8; META_LEN];
+        bytes[..4].copy_from_slice(&self.user.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.memory_type.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.created_at.unwrap_or(NO_TIMESTAMP).to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.expires_at.unwrap_or(NO_TIMESTAMP).to_le_bytes());
+        bytes[24..28].copy_from_slice(&self.flags.to_le_bytes());
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> Self {
+        let u32_at = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        let timestamp_at = |at: usize| {
+            Some(i64::from_le_bytes(bytes[at..at + 8].try_into().unwrap())).filter(|ts| *ts != NO_TIMESTAMP)
+        };
+        Self {
+            user: u32_at(0),
+            memory_type: u32_at(4),
+            created_at: timestamp_at(8),
+            expires_at: timestamp_at(16),
+            flags: u32_at(24),
+        }
+    }
+}
+
+/// 按段的字符串表解析好的过滤条件
+struct RowFilter<'a> {
+    user: Option<u32>,
+    memory_types: Option<Vec<u32>>,
+    created_after: Option<i64>,
+    created_before: Option<i64>,
+    /// 有自定义字段时仍需解析payload
+    fields: Option<&'a SearchFilter>,
+}
+
+impl RowFilter<'_> {
+    fn matches(&self, segment: &Segment, row: usize) -> bool {
+        let meta = segment.meta(row);
+        if self.user.is_some_and(|user| user != meta.user) {
+            return false;
+        }
+        if self.memory_types.as_ref().is_some_and(|types| !types.contains(&meta.memory_type)) {
+            return false;
+        }
+        if self.created_after.is_some() || self.created_before.is_some() {
+            let Some(created_at) = meta.created_at else {
+                return false;
+            };
+            if self.created_after.is_some_and(|after| created_at < after)
+                || self.created_before.is_some_and(|before| created_at > before)
+            {
+                return false;
+            }
+        }
+        self.fields.is_none_or(|filter| {
+            serde_json::from_str::<Value>(segment.payload(row)).is_ok_and(|payload| filter.matches(&payload))
+        })
+    }
+}
+
+/// `at`之后`rows`行、每行`width`字节的区段的结束位置，溢出时为None
+fn section_end(at: usize, rows: usize, width: usize) -> Option<usize> {
+    rows.checked_mul(width)?.checked_add(at)
+}
+
+/// 只读段文件
+#[derive(Debug)]
+pub struct Segment {
+    mmap: Mmap,
+    path: PathBuf,
+    dimension: usize,
+    emotion_dimension: usize,
+    image_dimension: usize,
+    /// 按ID排序的(ID, 行号)，只有这部分和字符串表常驻内存
+    index: Vec<(Uuid, u32)>,
+    /// 字符串表的反查
+    strings: HashMap<String, u32>,
+    ids_offset: usize,
+    meta_offset: usize,
+    offsets_offset: usize,
+    payload_offset: usize,
+}
+
+impl Segment {
+    /// 映射段文件并校验布局
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        if cfg!(target_endian = "big") {
+            bail!("段文件按小端存储，当前平台不支持");
+        }
+        let path = path.into();
+        let file = File::open(&path)?;
+        // 段文件只读，写入新段时先写临时文件再重命名，已映射的旧文件不受影响
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        if mmap.len() < HEADER_LEN || &mmap[..8] != SEGMENT_MAGIC {
+            bail!("不是段文件: {}", path.display());
+        }
+        let read_u32 = |at: usize| u32::from_le_bytes(mmap[at..at + 4].try_into().unwrap()) as usize;
+        let read_u64 = |at: usize| usize::try_from(u64::from_le_bytes(mmap[at..at + 8].try_into().unwrap())).ok();
+        let version = read_u32(8);
+        if version != SEGMENT_VERSION as usize {
+            bail!("不支持的段文件版本: {}", version);
+        }
+        let (dimension, emotion_dimension, image_dimension) = (read_u32(12), read_u32(16), read_u32(20));
+
+        // 头部中的长度来自文件，计算布局时不能溢出
+        let invalid = || anyhow!("段文件头部无效: {}", path.display());
+        let count = read_u64(24).filter(|count| *count <= u32::MAX as usize).ok_or_else(invalid)?;
+        let payload_len = read_u64(32).ok_or_else(invalid)?;
+        let strings_len = read_u64(40).ok_or_else(invalid)?;
+        let stride = dimension.checked_add(emotion_dimension)
+            .and_then(|stride| stride.checked_add(image_dimension))
+            .ok_or_else(invalid)?;
+        let ids_offset = stride.checked_mul(4)
+            .and_then(|width| section_end(HEADER_LEN, count, width))
+            .ok_or_else(invalid)?;
+        let meta_offset = section_end(ids_offset, count, 16).ok_or_else(invalid)?;
+        let offsets_offset = section_end(meta_offset, count, META_LEN).ok_or_else(invalid)?;
+        let payload_offset = section_end(offsets_offset, count + 1, 8).ok_or_else(invalid)?;
+        let strings_offset = payload_offset.checked_add(payload_len).ok_or_else(invalid)?;
+        let end = strings_offset.checked_add(strings_len).ok_or_else(invalid)?;
+        if mmap.len() != end {
+            bail!("段文件长度不一致: {}", path.display());
+        }
+
+        let strings: Vec<String> = serde_json::from_slice(&mmap[strings_offset..end])?;
+        let strings = strings.into_iter().enumerate().map(|(i, value)| (value, i as u32)).collect();
+
+        let mut index: Vec<(Uuid, u32)> = (0..count)
+            .map(|row| {
+                let at = ids_offset + row * 16;
+                (Uuid::from_slice(&mmap[at..at + 16]).unwrap(), row as u32)
+            })
+            .collect();
+        index.sort_unstable();
+
+        let segment = Self {
+            mmap,
+            path,
+            dimension,
+            emotion_dimension,
+            image_dimension,
+            index,
+            strings,
+            ids_offset,
+            meta_offset,
+            offsets_offset,
+            payload_offset,
+        };
+        let (prefix, _, _) = unsafe { segment.vector_bytes().align_to::<f32>() };
+        if !prefix.is_empty() {
+            bail!("段文件映射未按4字节对齐");
+        }
+        for row in 0..count {
+            let (start, end) = segment.payload_range(row);
+            if start > end || end > payload_len {
+                bail!("段文件payload偏移无效: 行 {}", row);
+            }
+        }
+        Ok(segment)
+    }
+
+    /// 段文件路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 向量维度
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    /// 情感向量维度，段中没有情感向量时为None
+    pub fn emotion_dimension(&self) -> Option<usize> {
+        (self.emotion_dimension > 0).then_some(self.emotion_dimension)
+    }
+
+    /// 图片向量维度，段中没有图片向量时为None
+    pub fn image_dimension(&self) -> Option<usize> {
+        (self.image_dimension > 0).then_some(self.image_dimension)
+    }
+
+    /// 点数
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// 查找ID所在的行
+    pub fn row(&self, id: Uuid) -> Option<usize> {
+        self.index.binary_search_by(|(other, _)| other.cmp(&id))
+            .ok()
+            .map(|position| self.index[position].1 as usize)
+    }
+
+    /// 第`row`行的嵌入向量，直接引用映射的内存
+    pub fn embedding(&self, row: usize) -> &[f32] {
+        let start = row * self.stride();
+        &self.vectors()[start..start + self.dimension]
+    }
+
+    /// 第`row`行的情感向量
+    pub fn emotion(&self, row: usize) -> Option<&[f32]> {
+        if self.meta(row).flags & HAS_EMOTION == 0 {
+            return None;
+        }
+        let start = row * self.stride() + self.dimension;
+        Some(&self.vectors()[start..start + self.emotion_dimension])
+    }
+
+    /// 第`row`行的图片向量
+    pub fn image(&self, row: usize) -> Option<&[f32]> {
+        if self.meta(row).flags & HAS_IMAGE == 0 {
+            return None;
+        }
+        let start = row * self.stride() + self.dimension + self.emotion_dimension;
+        Some(&self.vectors()[start..start + self.image_dimension])
+    }
+
+    /// 第`row`行在`space`中的向量
+    pub fn vector(&self, row: usize, space: VectorSpace) -> Option<&[f32]> {
+        match space {
+            VectorSpace::Content => Some(self.embedding(row)),
+            VectorSpace::Emotion => self.emotion(row),
+            VectorSpace::Image => self.image(row),
+        }
+    }
+
+    /// 第`row`行的payload
+    pub fn payload(&self, row: usize) -> &str {
+        let (start, end) = self.payload_range(row);
+        // 偏移在写入时与JSON文本一一对应，打开时已检查范围
+        std::str::from_utf8(&self.mmap[self.payload_offset + start..self.payload_offset + end]).unwrap_or("null")
+    }
+
+    /// 第`row`行的ID
+    pub fn id(&self, row: usize) -> Uuid {
+        let at = self.ids_offset + row * 16;
+        Uuid::from_slice(&self.mmap[at..at + 16]).unwrap()
+    }
+
+    /// 第`row`行在`now`时是否已过期
+    pub fn is_expired(&self, row: usize, now: chrono::DateTime<chrono::Utc>) -> bool {
+        self.meta(row).expires_at.is_some_and(|expires_at| expires_at <= now.timestamp())
+    }
+
+    /// 按ID排序遍历(ID, 行号)
+    pub fn entries(&self) -> &[(Uuid, u32)] {
+        &self.index
+    }
+
+    fn stride(&self) -> usize {
+        self.dimension + self.emotion_dimension + self.image_dimension
+    }
+
+    fn vector_bytes(&self) -> &[u8] {
+        &self.mmap[HEADER_LEN..self.ids_offset]
+    }
+
+    fn vectors(&self) -> &[f32] {
+        let (_, floats, _) = unsafe { self.vector_bytes().align_to::<f32>() };
+        floats
+    }
+
+    fn meta(&self, row: usize) -> RowMeta {
+        let at = self.meta_offset + row * META_LEN;
+        RowMeta::decode(&self.mmap[at..at + META_LEN])
+    }
+
+    fn payload_range(&self, row: usize) -> (usize, usize) {
+        let read = |i: usize| {
+            let at = self.offsets_offset + i * 8;
+            u64::from_le_bytes(self.mmap[at..at + 8].try_into().unwrap()) as usize
+        };
+        (read(row), read(row + 1))
+    }
+
+    /// 把过滤条件解析为元数据列上的比较，段中不可能有点满足时返回None
+    fn row_filter<'a>(&self, filter: &'a SearchFilter) -> Option<RowFilter<'a>> {
+        let user = match filter.user_id {
+            Some(ref user_id) => Some(*self.strings.get(user_id)?),
+            None => None,
+        };
+        let memory_types = match filter.memory_types {
+            Some(ref memory_types) => {
+                let indices: Vec<u32> = memory_types.iter()
+                    .filter_map(|memory_type| {
+                        let name = serde_json::to_value(memory_type).ok()?;
+                        self.strings.get(name.as_str()?).copied()
+                    })
+                    .collect();
+                if indices.is_empty() {
+                    return None;
+                }
+                Some(indices)
+            }
+            None => None,
+        };
+        Some(RowFilter {
+            user,
+            memory_types,
+            created_after: filter.created_after.map(|after| after.timestamp()),
+            created_before: filter.created_before.map(|before| before.timestamp()),
+            fields: (!filter.fields.is_empty()).then_some(filter),
+        })
+    }
+
+    fn stored_vector(&self, row: usize) -> StoredVector {
+        StoredVector {
+            id: self.id(row),
+            embedding: self.embedding(row).to_vec(),
+            payload: serde_json::from_str(self.payload(row)).unwrap_or(Value::Null),
+            emotion: self.emotion(row).map(<[f32]>::to_vec),
+            image: self.image(row).map(<[f32]>::to_vec),
+        }
+    }
+}
+
+/// 顺序写入段文件 - 向量直接写入文件，ID、元数据和payload在内存中缓冲到`finish`
+#[derive(Debug)]
+pub struct SegmentWriter {
+    path: PathBuf,
+    tmp_path: PathBuf,
+    writer: BufWriter<File>,
+    dimension: usize,
+    emotion_dimension: usize,
+    image_dimension: usize,
+    ids: Vec<Uuid>,
+    metas: Vec<u8>,
+    payloads: Vec<u8>,
+    offsets: Vec<u64>,
+    strings: HashMap<String, u32>,
+}
+
+impl SegmentWriter {
+    /// 创建段文件，写完前内容保存在临时文件中；情感或图片维度为None时段中不含该向量
+    pub fn create(
+        path: impl Into<PathBuf>,
+        dimension: usize,
+        emotion_dimension: Option<usize>,
+        image_dimension: Option<usize>,
+    ) -> Result<Self> {
+        let path = path.into();
+        let tmp_path = path.with_extension("tmp");
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        // 点数和各区段长度在finish时回填
+        writer.write_all(&[0; HEADER_LEN])?;
+        Ok(Self {
+            path,
+            tmp_path,
+            writer,
+            dimension,
+            emotion_dimension: emotion_dimension.unwrap_or(0),
+            image_dimension: image_dimension.unwrap_or(0),
+            ids: Vec::new(),
+            metas: Vec::new(),
+            payloads: Vec::new(),
+            offsets: vec![0],
+            strings: HashMap::new(),
+        })
+    }
+
+    /// 追加一个点，连同它的情感和图片向量
+    pub fn push(&mut self, point: &StoredVector) -> Result<()> {
+        super::check_dimension(Some(self.dimension), &point.embedding)?;
+        let mut flags = 0;
+        let extras = [
+            (&point.emotion, self.emotion_dimension, HAS_EMOTION, "情感"),
+            (&point.image, self.image_dimension, HAS_IMAGE, "图片"),
+        ];
+        for (vector, dimension, flag, name) in extras {
+            if let Some(vector) = vector {
+                if dimension == 0 {
+                    bail!("段文件没有{}向量维度: {}", name, point.id);
+                }
+                super::check_dimension(Some(dimension), vector)?;
+                flags |= flag;
+            }
+        }
+
+        // 每行依次为内容、情感、图片向量，缺少的向量以0填充
+        let padding = |vector: &Option<Vec<f32>>, dimension: usize| if vector.is_some() { 0 } else { dimension };
+        let values = point.embedding.iter()
+            .chain(point.emotion.iter().flatten())
+            .chain(std::iter::repeat_n(&0.0, padding(&point.emotion, self.emotion_dimension)))
+            .chain(point.image.iter().flatten())
+            .chain(std::iter::repeat_n(&0.0, padding(&point.image, self.image_dimension)));
+        for value in values {
+            self.writer.write_all(&value.to_le_bytes())?;
+        }
+
+        let payload = &point.payload;
+        let meta = RowMeta {
+            user: self.intern(payload.get(PAYLOAD_USER_ID).and_then(Value::as_str)),
+            memory_type: self.intern(payload.get(PAYLOAD_MEMORY_TYPE).and_then(Value::as_str)),
+            created_at: payload.get(PAYLOAD_CREATED_AT_TS).and_then(Value::as_i64),
+            expires_at: payload.get(PAYLOAD_EXPIRES_AT_TS).and_then(Value::as_i64),
+            flags,
+        };
+        self.metas.extend_from_slice(&meta.encode());
+        self.ids.push(point.id);
+        serde_json::to_writer(&mut self.payloads, payload)?;
+        self.offsets.push(self.payloads.len() as u64);
+        Ok(())
+    }
+
+    /// 字符串在字符串表中的下标
+    fn intern(&mut self, value: Option<&str>) -> u32 {
+        let Some(value) = value else {
+            return NO_STRING;
+        };
+        let next = self.strings.len() as u32;
+        *self.strings.entry(value.to_string()).or_insert(next)
+    }
+
+    /// 写入索引、元数据、payload和字符串表，重命名为目标文件，返回点数
+    pub fn finish(mut self) -> Result<usize> {
+        use std::io::{Seek, SeekFrom};
+
+        if self.ids.len() > u32::MAX as usize {
+            bail!("段文件点数超过上限: {}", self.ids.len());
+        }
+        for id in &self.ids {
+            self.writer.write_all(id.as_bytes())?;
+        }
+        self.writer.write_all(&self.metas)?;
+        for offset in &self.offsets {
+            self.writer.write_all(&offset.to_le_bytes())?;
+        }
+        self.writer.write_all(&self.payloads)?;
+
+        let mut table = vec![String::new(); self.strings.len()];
+        for (value, i) in self.strings.drain() {
+            table[i as usize] = value;
+        }
+        let strings = serde_json::to_vec(&table)?;
+        self.writer.write_all(&strings)?;
+
+        let mut header = [0u8; HEADER_LEN];
+        header[..8].copy_from_slice(SEGMENT_MAGIC);
+        header[8..12].copy_from_slice(&SEGMENT_VERSION.to_le_bytes());
+        header[12..16].copy_from_slice(&(self.dimension as u32).to_le_bytes());
+        header[16..20].copy_from_slice(&(self.emotion_dimension as u32).to_le_bytes());
+        header[20..24].copy_from_slice(&(self.image_dimension as u32).to_le_bytes());
+        header[24..32].copy_from_slice(&(self.ids.len() as u64).to_le_bytes());
+        header[32..40].copy_from_slice(&(self.payloads.len() as u64).to_le_bytes());
+        header[40..48].copy_from_slice(&(strings.len() as u64).to_le_bytes());
+        self.writer.seek(SeekFrom::Start(0))?;
+        self.writer.write_all(&header)?;
+
+        let file = self.writer.into_inner().map_err(|e| e.into_error())?;
+        file.sync_all()?;
+        std::fs::rename(&self.tmp_path, &self.path)?;
+        Ok(self.ids.len())
+    }
+}
+
+/// 把存储中的全部向量写成段文件，返回写入的点数
+///
+/// 情感和图片向量按存储声明的维度一并写入。
+/// 对`SegmentVectorStore`调用即为压实：段和上层的修改合并为新段，之后用新段和空的上层重新打开。
+pub async fn write_segment<S>(store: &S, path: impl AsRef<Path>, dimension: usize) -> Result<usize, S::Error>
+where
+    S: VectorStore + ?Sized,
+{
+    let mut writer = SegmentWriter::create(
+        path.as_ref(),
+        dimension,
+        store.emotion_vector_size(),
+        store.image_vector_size(),
+    )?;
+    let mut offset = None;
+
+    loop {
+        let page = store.scroll(offset, BATCH_SIZE).await?;
+        for point in &page.points {
+            writer.push(point)?;
+        }
+        match page.next_offset {
+            Some(next) => offset = Some(next),
+            None => break,
+        }
+    }
+
+    Ok(writer.finish()?)
+}
+
+/// 以只读段文件为底层的向量存储
+///
+/// 段中的点被覆盖、修改或删除后记入`shadowed`，之后只从上层读取；修改段中的点时先把它复制到上层。
+/// 上层可持久化时，被删除的段内ID保存在段文件旁的`.tombstones`文件中。
+#[derive(Debug)]
+pub struct SegmentVectorStore {
+    segment: Segment,
+    delta: MockVectorStore,
+    /// 不再以段文件为准的段内ID
+    shadowed: RwLock<HashSet<Uuid>>,
+    tombstone_path: Option<PathBuf>,
+}
+
+impl SegmentVectorStore {
+    /// 映射段文件，新写入保存在`delta`中
+    pub fn open(path: impl Into<PathBuf>, delta: MockVectorStore) -> Result<Self> {
+        let segment = Segment::open(path)?;
+        let tombstone_path = delta.is_persistent().then(|| segment.path().with_extension("tombstones"));
+        let shadowed = match tombstone_path.as_ref().map(std::fs::read) {
+            Some(Ok(bytes)) => serde_json::from_slice(&bytes)?,
+            Some(Err(e)) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => HashSet::new(),
+        };
+        let mut delta = delta.with_vector_size(segment.dimension());
+        if let Some(dimension) = segment.emotion_dimension() {
+            delta = delta.with_emotion_vector_size(dimension);
+        }
+        if let Some(dimension) = segment.image_dimension() {
+            delta = delta.with_image_vector_size(dimension);
+        }
+        Ok(Self { segment, delta, shadowed: RwLock::new(shadowed), tombstone_path })
+    }
+
+    /// 底层段文件
+    pub fn segment(&self) -> &Segment {
+        &self.segment
+    }
+
+    /// 段中仍有效的点数
+    pub async fn segment_live_len(&self) -> usize {
+        let shadowed = self.shadowed.read().await;
+        self.live_entries(&shadowed, None).count()
+    }
+
+    /// 写入上层和墓碑文件，上层不持久化时为无操作
+    pub async fn persist(&self) -> Result<()> {
+        self.delta.persist().await?;
+        match self.tombstone_path {
+            Some(ref path) => Self::write_tombstones(path, &*self.shadowed.read().await),
+            None => Ok(()),
+        }
+    }
+
+    fn write_tombstones(path: &Path, shadowed: &HashSet<Uuid>) -> Result<()> {
+        let tmp_path = path.with_extension("tombstones.tmp");
+        std::fs::write(&tmp_path, serde_json::to_vec(shadowed)?)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// 段中有效的行
+    fn live_row(&self, shadowed: &HashSet<Uuid>, id: Uuid) -> Option<usize> {
+        if shadowed.contains(&id) {
+            return None;
+        }
+        self.segment.row(id)
+    }
+
+    /// 段中的点被上层覆盖
+    async fn shadow(&self, id: Uuid) {
+        if self.segment.row(id).is_some() {
+            self.shadowed.write().await.insert(id);
+        }
+    }
+
+    /// 把段中的点复制到上层以便修改，不存在时返回false
+    async fn materialize(&self, id: Uuid) -> Result<bool> {
+        let mut shadowed = self.shadowed.write().await;
+        let Some(row) = self.live_row(&shadowed, id) else {
+            return Ok(self.delta.get_vector(id).await?.is_some());
+        };
+        store_point(&self.delta, self.segment.stored_vector(row)).await?;
+        shadowed.insert(id);
+        Ok(true)
+    }
+
+    /// 段中未被上层覆盖且满足过滤条件的(ID, 行号)，过滤只读元数据列
+    fn live_entries<'a>(
+        &'a self,
+        shadowed: &'a HashSet<Uuid>,
+        filter: Option<&'a SearchFilter>,
+    ) -> impl Iterator<Item = &'a (Uuid, u32)> + 'a {
+        let row_filter = filter.map(|filter| self.segment.row_filter(filter));
+        let entries = match row_filter {
+            Some(None) => &[][..],
+            _ => self.segment.entries(),
+        };
+        let row_filter = row_filter.flatten();
+        entries.iter()
+            .filter(move |(id, _)| !shadowed.contains(id))
+            .filter(move |(_, row)| row_filter.as_ref().is_none_or(|f| f.matches(&self.segment, *row as usize)))
+    }
+
+    /// 段内`space`中的top-k，按与上层相同的规则排序
+    fn segment_top_k(
+        &self,
+        shadowed: &HashSet<Uuid>,
+        space: VectorSpace,
+        query_embedding: &[f32],
+        limit: usize,
+        threshold: f32,
+        filter: Option<&SearchFilter>,
+    ) -> Vec<SearchHit> {
+        let segment = &self.segment;
+        let candidates: Vec<(Uuid, usize, &[f32])> = self.live_entries(shadowed, filter)
+            .filter_map(|(id, row)| {
+                let row = *row as usize;
+                Some((*id, row, segment.vector(row, space)?))
+            })
+            .collect();
+
+        exact_top_k(
+            &candidates,
+            query_embedding,
+            self.delta.distance_metric(),
+            threshold,
+            limit,
+            |(id, _, vector)| (*id, *vector),
+        )
+        .into_iter()
+        .map(|((id, row, _), score)| SearchHit {
+            id: *id,
+            score,
+            payload: serde_json::from_str(segment.payload(*row)).unwrap_or(Value::Null),
+        })
+        .collect()
+    }
+}
+
+#[async_trait]
+impl VectorStore for SegmentVectorStore {
+    type Error = anyhow::Error;
+
+    fn vector_size(&self) -> Option<usize> {
+        Some(self.segment.dimension())
+    }
+
+    fn emotion_vector_size(&self) -> Option<usize> {
+        self.delta.emotion_vector_size()
+    }
+
+    fn image_vector_size(&self) -> Option<usize> {
+        self.delta.image_vector_size()
+    }
+
+    fn distance_metric(&self) -> DistanceMetric {
+        self.delta.distance_metric()
+    }
+
+    async fn store_vector(&self, id: Uuid, embedding: Vec<f32>, metadata: String) -> Result<(), Self::Error> {
+        self.delta.store_vector(id, embedding, metadata).await?;
+        self.shadow(id).await;
+        Ok(())
+    }
+
+    async fn store_vectors(&self, points: Vec<(Uuid, Vec<f32>, String)>) -> Result<(), Self::Error> {
+        let ids: Vec<Uuid> = points.iter().map(|(id, _, _)| *id).collect();
+        self.delta.store_vectors(points).await?;
+        for id in ids {
+            self.shadow(id).await;
+        }
+        Ok(())
+    }
+
+    async fn store_hybrid(
+        &self,
+        id: Uuid,
+        embedding: Vec<f32>,
+        sparse: SparseVector,
+        metadata: String,
+    ) -> Result<(), Self::Error> {
+        self.delta.store_hybrid(id, embedding, sparse, metadata).await?;
+        self.shadow(id).await;
+        Ok(())
+    }
+
+    async fn store_multi_vector(
+        &self,
+        id: Uuid,
+        embedding: Vec<f32>,
+        emotion_embedding: Vec<f32>,
+        metadata: String,
+    ) -> Result<(), Self::Error> {
+        self.delta.store_multi_vector(id, embedding, emotion_embedding, metadata).await?;
+        self.shadow(id).await;
+        Ok(())
+    }
+
+    async fn store_multi_vectors(&self, points: Vec<(Uuid, Vec<f32>, Vec<f32>, String)>) -> Result<(), Self::Error> {
+        let ids: Vec<Uuid> = points.iter().map(|(id, _, _, _)| *id).collect();
+        self.delta.store_multi_vectors(points).await?;
+        for id in ids {
+            self.shadow(id).await;
+        }
+        Ok(())
+    }
+
+    async fn attach_image_vector(&self, id: Uuid, image_embedding: Vec<f32>) -> Result<(), Self::Error> {
+        self.materialize(id).await?;
+        self.delta.attach_image_vector(id, image_embedding).await
+    }
+
+    async fn search_similar(
+        &self,
+        query_embedding: Vec<f32>,
+        limit: usize,
+        threshold: f32,
+        filter: Option<SearchFilter>,
+    ) -> Result<Vec<SearchHit>, Self::Error> {
+        self.search_space(VectorSpace::Content, query_embedding, limit, threshold, filter).await
+    }
+
+    async fn search_space(
+        &self,
+        space: VectorSpace,
+        query_embedding: Vec<f32>,
+        limit: usize,
+        threshold: f32,
+        filter: Option<SearchFilter>,
+    ) -> Result<Vec<SearchHit>, Self::Error> {
+        // 上层与段的各向量维度一致，由上层校验查询向量
+        let mut hits = self.delta.search_space(space, query_embedding.clone(), limit, threshold, filter.clone()).await?;
+        {
+            let shadowed = self.shadowed.read().await;
+            hits.extend(self.segment_top_k(&shadowed, space, &query_embedding, limit, threshold, filter.as_ref()));
+        }
+
+        let metric = self.distance_metric();
+        hits.sort_by(|a, b| compare_ranked(metric, (a.id, a.score), (b.id, b.score)));
+        hits.truncate(limit);
+        Ok(hits)
+    }
+
+    async fn update_vector(&self, id: Uuid, embedding: Vec<f32>) -> Result<(), Self::Error> {
+        self.materialize(id).await?;
+        self.delta.update_vector(id, embedding).await
+    }
+
+    async fn update_payload(&self, id: Uuid, patch: serde_json::Value) -> Result<(), Self::Error> {
+        self.materialize(id).await?;
+        self.delta.update_payload(id, patch).await
+    }
+
+    async fn delete_vector(&self, id: Uuid) -> Result<(), Self::Error> {
+        if self.delta.get_vector(id).await?.is_some() {
+            return self.delta.delete_vector(id).await;
+        }
+        let mut shadowed = self.shadowed.write().await;
+        if self.live_row(&shadowed, id).is_none() {
+            return Err(anyhow!("Vector not found: {}", id));
+        }
+        shadowed.insert(id);
+        Ok(())
+    }
+
+    async fn purge_expired(&self, filter: Option<SearchFilter>) -> Result<Vec<Uuid>, Self::Error> {
+        let now = chrono::Utc::now();
+        let mut expired: Vec<Uuid> = {
+            let mut shadowed = self.shadowed.write().await;
+            let expired: Vec<Uuid> = self.live_entries(&shadowed, filter.as_ref())
+                .filter(|(_, row)| self.segment.is_expired(*row as usize, now))
+                .map(|(id, _)| *id)
+                .collect();
+            shadowed.extend(expired.iter().copied());
+            expired
+        };
+        expired.extend(self.delta.purge_expired(filter).await?);
+        Ok(expired)
+    }
+
+    async fn count(&self, filter: Option<SearchFilter>) -> Result<u64, Self::Error> {
+        let segment = {
+            let shadowed = self.shadowed.read().await;
+            self.live_entries(&shadowed, filter.as_ref()).count() as u64
+        };
+        Ok(segment + self.delta.count(filter).await?)
+    }
+
+    async fn get_vector(&self, id: Uuid) -> Result<Option<StoredVector>, Self::Error> {
+        if let Some(point) = self.delta.get_vector(id).await? {
+            return Ok(Some(point));
+        }
+        let shadowed = self.shadowed.read().await;
+        Ok(self.live_row(&shadowed, id).map(|row| self.segment.stored_vector(row)))
+    }
+
+    async fn get_payloads(&self, ids: &[Uuid]) -> Result<HashMap<Uuid, serde_json::Value>, Self::Error> {
+        let mut payloads = self.delta.get_payloads(ids).await?;
+        let shadowed = self.shadowed.read().await;
+        for &id in ids {
+            if payloads.contains_key(&id) {
+                continue;
+            }
+            if let Some(row) = self.live_row(&shadowed, id) {
+                payloads.insert(id, self.segment.stored_vector(row).payload);
+            }
+        }
+        Ok(payloads)
+    }
+
+    async fn scroll(&self, offset: Option<String>, limit: usize) -> Result<ScrollPage, Self::Error> {
+        let start = offset.as_deref()
+            .map(Uuid::parse_str)
+            .transpose()
+            .map_err(|_| anyhow!("Invalid scroll offset: {:?}", offset))?;
+
+        // 两层的ID互不重叠，各取limit+1个按ID归并，游标为下一页第一个点的ID
+        let mut points: Vec<StoredVector> = {
+            let shadowed = self.shadowed.read().await;
+            let entries = self.segment.entries();
+            let from = start.map_or(0, |start| entries.partition_point(|(id, _)| *id < start));
+            entries[from..].iter()
+                .filter(|(id, _)| !shadowed.contains(id))
+                .take(limit + 1)
+                .map(|(_, row)| self.segment.stored_vector(*row as usize))
+                .collect()
+        };
+        points.extend(self.delta.scroll(offset, limit + 1).await?.points);
+        points.sort_by_key(|point| point.id);
+
+        let next_offset = points.get(limit).map(|point| point.id.to_string());
+        points.truncate(limit);
+        Ok(ScrollPage { points, next_offset })
+    }
+
+    async fn health_check(&self) -> Result<HealthStatus, Self::Error> {
+        Ok(HealthStatus::Healthy)
+    }
+
+    async fn get_stats(&self) -> Result<HashMap<String, u64>, Self::Error> {
+        let segment_vectors = self.segment_live_len().await as u64;
+        let mut stats = self.delta.get_stats().await?;
+        *stats.entry("total_vectors".to_string()).or_default() += segment_vectors;
+        stats.insert("segment_vectors".to_string(), segment_vectors);
+        stats.insert("total_dimensions".to_string(), self.segment.dimension() as u64);
+        Ok(stats)
+    }
+}
+
+impl Drop for SegmentVectorStore {
+    fn drop(&mut self) {
+        let Some(ref path) = self.tombstone_path else {
+            return;
+        };
+        if let Err(e) = Self::write_tombstones(path, self.shadowed.get_mut()) {
+            tracing::warn!("段文件墓碑持久化失败: {}: {}", path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("mira_{}_{}.seg", name, Uuid::new_v4()))
+    }
+
+    #[tokio::test]
+    async fn test_segment_layered_under_delta() {
+        let source = MockVectorStore::new();
+        let (near, far, gone) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        source.store_vector(near, vec![1.0, 0.1], r#"{"content":"猫咪"}"#.to_string()).await.unwrap();
+        source.store_vector(far, vec![0.0, 1.0], r#"{"content":"咖啡"}"#.to_string()).await.unwrap();
+        source.store_vector(gone, vec![1.0, 0.0], r#"{"content":"旧的"}"#.to_string()).await.unwrap();
+
+        let path = temp_path("segment");
+        assert_eq!(write_segment(&source, &path, 2).await.unwrap(), 3);
+
+        let store = SegmentVectorStore::open(&path, MockVectorStore::new()).unwrap();
+        assert_eq!(store.segment().len(), 3);
+        assert_eq!(store.get_vector(far).await.unwrap(), source.get_vector(far).await.unwrap());
+
+        let hits = store.search_similar(vec![1.0, 0.0], 2, 0.5, None).await.unwrap();
+        assert_eq!(hits.iter().map(|hit| hit.id).collect::<Vec<_>>(), vec![gone, near]);
+
+        // 删除段中的点，修改另一个点的payload，并写入新点
+        store.delete_vector(gone).await.unwrap();
+        assert!(store.delete_vector(gone).await.is_err());
+        store.update_payload(near, serde_json::json!({ "content": "小猫" })).await.unwrap();
+        let added = Uuid::new_v4();
+        store.store_vector(added, vec![1.0, 0.05], "{}".to_string()).await.unwrap();
+
+        let hits = store.search_similar(vec![1.0, 0.0], 5, 0.5, None).await.unwrap();
+        assert_eq!(hits.iter().map(|hit| hit.id).collect::<Vec<_>>(), vec![added, near]);
+        assert_eq!(hits[1].payload["content"], "小猫");
+        assert_eq!(store.count(None).await.unwrap(), 3);
+
+        let mut seen = Vec::new();
+        let mut offset = None;
+        loop {
+            let page = store.scroll(offset, 2).await.unwrap();
+            seen.extend(page.points.into_iter().map(|point| point.id));
+            match page.next_offset {
+                Some(next) => offset = Some(next),
+                None => break,
+            }
+        }
+        let mut expected = vec![near, far, added];
+        expected.sort();
+        assert_eq!(seen, expected);
+
+        // 压实后新段包含两层合并的结果
+        let compacted = temp_path("compacted");
+        assert_eq!(write_segment(&store, &compacted, 2).await.unwrap(), 3);
+        let reopened = SegmentVectorStore::open(&compacted, MockVectorStore::new()).unwrap();
+        assert_eq!(reopened.get_vector(near).await.unwrap().unwrap().payload["content"], "小猫");
+        assert!(reopened.get_vector(gone).await.unwrap().is_none());
+
+        assert!(SegmentVectorStore::open(&path, MockVectorStore::new()).unwrap()
+            .store_vector(Uuid::new_v4(), vec![1.0], "{}".to_string()).await.is_err());
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&compacted).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_segment_keeps_extra_vectors_and_filters_by_columns() {
+        let source = MockVectorStore::new().with_emotion_vector_size(2).with_image_vector_size(2);
+        let (alice, bob, expired) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let payload = |user: &str, memory_type: &str, expires_at: i64| {
+            serde_json::json!({
+                "user_id": user,
+                "memory_type": memory_type,
+                "created_at_ts": 1_700_000_000i64,
+                "expires_at_ts": expires_at,
+            })
+            .to_string()
+        };
+        let never = i64::MAX;
+        source.store_multi_vector(alice, vec![1.0, 0.0], vec![0.0, 1.0], payload("alice", "LongTerm", never)).await.unwrap();
+        source.attach_image_vector(alice, vec![1.0, 1.0]).await.unwrap();
+        source.store_vector(bob, vec![1.0, 0.0], payload("bob", "ShortTerm", never)).await.unwrap();
+        source.store_vector(expired, vec![0.0, 1.0], payload("alice", "ShortTerm", 1)).await.unwrap();
+
+        let path = temp_path("columns");
+        assert_eq!(write_segment(&source, &path, 2).await.unwrap(), 3);
+        let store = SegmentVectorStore::open(&path, MockVectorStore::new()).unwrap();
+
+        // 情感和图片向量随段文件保存
+        assert_eq!(store.get_vector(alice).await.unwrap(), source.get_vector(alice).await.unwrap());
+        let hits = store.search_space(VectorSpace::Emotion, vec![0.0, 1.0], 5, 0.5, None).await.unwrap();
+        assert_eq!(hits.iter().map(|hit| hit.id).collect::<Vec<_>>(), vec![alice]);
+
+        assert_eq!(store.count(Some(SearchFilter::for_user("alice"))).await.unwrap(), 2);
+        assert_eq!(store.count(Some(SearchFilter::for_user("carol"))).await.unwrap(), 0);
+        let short_term = SearchFilter::default().with_memory_types(vec![crate::MemoryType::ShortTerm]);
+        assert_eq!(store.count(Some(short_term)).await.unwrap(), 2);
+        let long_term = SearchFilter::for_user("alice").with_field("memory_type", "LongTerm");
+        assert_eq!(store.count(Some(long_term)).await.unwrap(), 1);
+        let later = chrono::DateTime::from_timestamp(1_800_000_000, 0);
+        assert_eq!(store.count(Some(SearchFilter::default().with_time_range(later, None))).await.unwrap(), 0);
+
+        assert_eq!(store.purge_expired(None).await.unwrap(), vec![expired]);
+        assert_eq!(store.count(None).await.unwrap(), 2);
+
+        // 修改段中的点时情感和图片向量一并复制到上层
+        store.update_payload(alice, serde_json::json!({ "content": "新的" })).await.unwrap();
+        let point = store.get_vector(alice).await.unwrap().unwrap();
+        assert_eq!((point.emotion, point.image), (Some(vec![0.0, 1.0]), Some(vec![1.0, 1.0])));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
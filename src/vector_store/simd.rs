@@ -0,0 +1,212 @@
+//! SIMD距离内核 - 运行时检测CPU特性后选择实现
+//!
+//! 所有实现都按`LANES`路独立累加，最后按通道顺序求和，与Zig内核的累加顺序一致；不使用FMA，
+//! 因此不同实现得到逐位相同的分数。启用`portable-simd`特性（需要nightly）时使用`std::simd`；
+//! 否则在x86_64上检测到AVX2时调用以`target_feature`编译的同一内核，由编译器生成256位指令。
+//! aarch64的NEON是基线特性，标量内核本身即被向量化。
+
+use std::sync::OnceLock;
+
+/// 向量化内核的通道数 - 8路f32对应一个AVX寄存器或两个NEON寄存器
+pub const LANES: usize = 8;
+
+/// 运行时选用的内核实现
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimdLevel {
+    /// 标量内核，依赖编译器按目标基线特性自动向量化
+    Scalar,
+    /// x86_64 AVX2
+    Avx2,
+    /// aarch64 NEON
+    Neon,
+    /// `std::simd`
+    Portable,
+}
+
+impl SimdLevel {
+    /// 当前CPU上使用的实现，首次调用时检测
+    pub fn detect() -> Self {
+        static LEVEL: OnceLock<SimdLevel> = OnceLock::new();
+        *LEVEL.get_or_init(|| {
+            if cfg!(feature = "portable-simd") {
+                return SimdLevel::Portable;
+            }
+            #[cfg(target_arch = "x86_64")]
+            {
+                if std::arch::is_x86_feature_detected!("avx2") {
+                    return SimdLevel::Avx2;
+                }
+            }
+            if cfg!(target_arch = "aarch64") {
+                return SimdLevel::Neon;
+            }
+            SimdLevel::Scalar
+        })
+    }
+
+    /// 实现名称，用于日志和基准测试
+    pub fn name(&self) -> &'static str {
+        match self {
+            SimdLevel::Scalar => "scalar",
+            SimdLevel::Avx2 => "avx2",
+            SimdLevel::Neon => "neon",
+            SimdLevel::Portable => "portable-simd",
+        }
+    }
+}
+
+/// 点积，按检测到的实现分派
+pub fn dot(a: &[f32], b: &[f32]) -> f32 {
+    match SimdLevel::detect() {
+        #[cfg(feature = "portable-simd")]
+        SimdLevel::Portable => portable::dot(a, b),
+        #[cfg(target_arch = "x86_64")]
+        // SAFETY: 只有检测到AVX2时才会选中该实现
+        SimdLevel::Avx2 => unsafe { avx2::dot(a, b) },
+        _ => scalar_dot(a, b),
+    }
+}
+
+/// 欧氏距离的平方，按检测到的实现分派
+pub fn squared_l2(a: &[f32], b: &[f32]) -> f32 {
+    match SimdLevel::detect() {
+        #[cfg(feature = "portable-simd")]
+        SimdLevel::Portable => portable::squared_l2(a, b),
+        #[cfg(target_arch = "x86_64")]
+        // SAFETY: 只有检测到AVX2时才会选中该实现
+        SimdLevel::Avx2 => unsafe { avx2::squared_l2(a, b) },
+        _ => scalar_squared_l2(a, b),
+    }
+}
+
+/// 余弦相似度，任一向量为零向量时为0
+pub fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let norm_a = dot(a, a).sqrt();
+    let norm_b = dot(b, b).sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot(a, b) / (norm_a * norm_b)
+    }
+}
+
+/// 标量点积 - 使用LANES个独立累加器消除循环依赖，编译器可以将其自动向量化
+#[inline(always)]
+pub fn scalar_dot(a: &[f32], b: &[f32]) -> f32 {
+    let len = a.len().min(b.len());
+    let (a, b) = (&a[..len], &b[..len]);
+
+    let mut acc = [0.0f32; LANES];
+    let chunks_a = a.chunks_exact(LANES);
+    let chunks_b = b.chunks_exact(LANES);
+    let (rem_a, rem_b) = (chunks_a.remainder(), chunks_b.remainder());
+
+    for (ca, cb) in chunks_a.zip(chunks_b) {
+        for ((acc, x), y) in acc.iter_mut().zip(ca).zip(cb) {
+            *acc += x * y;
+        }
+    }
+
+    let tail: f32 = rem_a.iter().zip(rem_b).map(|(x, y)| x * y).sum();
+    acc.iter().sum::<f32>() + tail
+}
+
+/// 标量欧氏距离平方
+#[inline(always)]
+pub fn scalar_squared_l2(a: &[f32], b: &[f32]) -> f32 {
+    let len = a.len().min(b.len());
+    let (a, b) = (&a[..len], &b[..len]);
+
+    let mut acc = [0.0f32; LANES];
+    let chunks_a = a.chunks_exact(LANES);
+    let chunks_b = b.chunks_exact(LANES);
+    let (rem_a, rem_b) = (chunks_a.remainder(), chunks_b.remainder());
+
+    for (ca, cb) in chunks_a.zip(chunks_b) {
+        for ((acc, x), y) in acc.iter_mut().zip(ca).zip(cb) {
+            let d = x - y;
+            *acc += d * d;
+        }
+    }
+
+    let tail: f32 = rem_a.iter().zip(rem_b).map(|(x, y)| (x - y) * (x - y)).sum();
+    acc.iter().sum::<f32>() + tail
+}
+
+/// 以AVX2编译的标量内核
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    #[target_feature(enable = "avx2")]
+    pub fn dot(a: &[f32], b: &[f32]) -> f32 {
+        super::scalar_dot(a, b)
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub fn squared_l2(a: &[f32], b: &[f32]) -> f32 {
+        super::scalar_squared_l2(a, b)
+    }
+}
+
+/// `std::simd`内核
+#[cfg(feature = "portable-simd")]
+mod portable {
+    use super::LANES;
+    use std::simd::Simd;
+
+    type F32s = Simd<f32, LANES>;
+
+    pub fn dot(a: &[f32], b: &[f32]) -> f32 {
+        let len = a.len().min(b.len());
+        let (a, b) = (&a[..len], &b[..len]);
+
+        let mut acc = F32s::splat(0.0);
+        let chunks_a = a.chunks_exact(LANES);
+        let chunks_b = b.chunks_exact(LANES);
+        let (rem_a, rem_b) = (chunks_a.remainder(), chunks_b.remainder());
+
+        for (ca, cb) in chunks_a.zip(chunks_b) {
+            acc += F32s::from_slice(ca) * F32s::from_slice(cb);
+        }
+
+        // 按通道顺序求和，与标量内核一致
+        let tail: f32 = rem_a.iter().zip(rem_b).map(|(x, y)| x * y).sum();
+        acc.to_array().iter().sum::<f32>() + tail
+    }
+
+    pub fn squared_l2(a: &[f32], b: &[f32]) -> f32 {
+        let len = a.len().min(b.len());
+        let (a, b) = (&a[..len], &b[..len]);
+
+        let mut acc = F32s::splat(0.0);
+        let chunks_a = a.chunks_exact(LANES);
+        let chunks_b = b.chunks_exact(LANES);
+        let (rem_a, rem_b) = (chunks_a.remainder(), chunks_b.remainder());
+
+        for (ca, cb) in chunks_a.zip(chunks_b) {
+            let d = F32s::from_slice(ca) - F32s::from_slice(cb);
+            acc += d * d;
+        }
+
+        let tail: f32 = rem_a.iter().zip(rem_b).map(|(x, y)| (x - y) * (x - y)).sum();
+        acc.to_array().iter().sum::<f32>() + tail
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::ZigPerformanceUtils;
+
+    #[test]
+    fn test_dispatched_kernels_match_scalar_bitwise() {
+        // 长度不是LANES的整数倍，覆盖尾部处理
+        let a: Vec<f32> = (0..771).map(|i| (i as f32 * 0.37).sin()).collect();
+        let b: Vec<f32> = (0..771).map(|i| (i as f32 * 0.11).cos()).collect();
+
+        assert_eq!(dot(&a, &b).to_bits(), scalar_dot(&a, &b).to_bits(), "{}", SimdLevel::detect().name());
+        assert_eq!(squared_l2(&a, &b).to_bits(), scalar_squared_l2(&a, &b).to_bits());
+        let zig = ZigPerformanceUtils::vector_cosine_similarity(&a, &b).unwrap();
+        assert!((cosine(&a, &b) - zig).abs() < 1e-5);
+        assert_eq!(cosine(&a, &[0.0; 771]), 0.0);
+    }
+}
@@ -0,0 +1,83 @@
+//! 稀疏关键词向量 - 用于稠密+稀疏混合检索
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// 稀疏向量，indices与values一一对应且indices升序
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SparseVector {
+    pub indices: Vec<u32>,
+    pub values: Vec<f32>,
+}
+
+impl SparseVector {
+    /// 由关键词构建稀疏向量 - 关键词哈希为维度，词频为权重
+    pub fn from_keywords<S: AsRef<str>>(keywords: &[S]) -> Self {
+        let mut weights: BTreeMap<u32, f32> = BTreeMap::new();
+        for keyword in keywords {
+            let keyword = keyword.as_ref().trim().to_lowercase();
+            if keyword.is_empty() {
+                continue;
+            }
+            *weights.entry(Self::keyword_index(&keyword)).or_insert(0.0) += 1.0;
+        }
+
+        let (indices, values) = weights.into_iter().unzip();
+        Self { indices, values }
+    }
+
+    /// 关键词到维度的稳定映射（32位FNV-1a），跨进程和版本保持一致
+    pub fn keyword_index(keyword: &str) -> u32 {
+        keyword.bytes().fold(0x811c9dc5u32, |hash, byte| {
+            (hash ^ byte as u32).wrapping_mul(0x01000193)
+        })
+    }
+
+    /// 稀疏点积
+    pub fn dot(&self, other: &SparseVector) -> f32 {
+        let (mut i, mut j) = (0, 0);
+        let mut sum = 0.0;
+        while i < self.indices.len() && j < other.indices.len() {
+            match self.indices[i].cmp(&other.indices[j]) {
+                std::cmp::Ordering::Less => i += 1,
+                std::cmp::Ordering::Greater => j += 1,
+                std::cmp::Ordering::Equal => {
+                    sum += self.values[i] * other.values[j];
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+        sum
+    }
+
+    /// 是否为空向量
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_keywords_is_sorted_and_counts_duplicates() {
+        let sparse = SparseVector::from_keywords(&["猫咪", "喜欢", "猫咪", " "]);
+
+        assert_eq!(sparse.indices.len(), 2);
+        assert!(sparse.indices.windows(2).all(|w| w[0] < w[1]));
+        let cat = SparseVector::keyword_index("猫咪");
+        let pos = sparse.indices.iter().position(|&i| i == cat).unwrap();
+        assert_eq!(sparse.values[pos], 2.0);
+    }
+
+    #[test]
+    fn test_sparse_dot() {
+        let a = SparseVector::from_keywords(&["猫咪", "喜欢"]);
+        let b = SparseVector::from_keywords(&["猫咪", "咖啡"]);
+
+        assert_eq!(a.dot(&b), 1.0);
+        assert_eq!(a.dot(&SparseVector::default()), 0.0);
+    }
+}
@@ -0,0 +1,371 @@
+//! 按用户隔离的向量存储包装
+//!
+//! 多个用户共享同一集合时，每次写入都注入user_id，每次读取都强制按user_id过滤，
+//! 对不属于当前用户的点的修改和删除按不存在处理，从结构上杜绝跨用户的记忆泄露。
+
+use super::filter::PAYLOAD_USER_ID;
+use super::{
+    DistanceMetric, HealthStatus, HitStream, OperationMetrics, ScrollPage, SearchFilter, SearchHit,
+    SparseVector, StoredVector, VectorSpace, VectorStore,
+};
+use async_trait::async_trait;
+use futures::{StreamExt, TryStreamExt};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// 绑定到单个用户的向量存储
+#[derive(Debug)]
+pub struct TenantVectorStore<S: VectorStore + ?Sized> {
+    inner: Arc<S>,
+    user_id: String,
+}
+
+impl<S: VectorStore + ?Sized> TenantVectorStore<S> {
+    /// 包装共享存储，所有操作限定在`user_id`范围内
+    pub fn new(inner: Arc<S>, user_id: impl Into<String>) -> Self {
+        Self {
+            inner,
+            user_id: user_id.into(),
+        }
+    }
+
+    /// 绑定的用户ID
+    pub fn user_id(&self) -> &str {
+        &self.user_id
+    }
+
+    /// 在metadata中写入当前用户ID，覆盖调用方提供的值
+    fn inject_user(&self, metadata: &str) -> Result<String, anyhow::Error> {
+        let mut payload: Value = serde_json::from_str(metadata)?;
+        let Value::Object(ref mut map) = payload else {
+            return Err(anyhow::anyhow!("Tenant metadata must be a JSON object"));
+        };
+        map.insert(PAYLOAD_USER_ID.to_string(), Value::String(self.user_id.clone()));
+        Ok(payload.to_string())
+    }
+
+    /// 强制按当前用户过滤，覆盖调用方提供的user_id
+    fn scoped_filter(&self, filter: Option<SearchFilter>) -> SearchFilter {
+        let mut filter = filter.unwrap_or_default();
+        filter.user_id = Some(self.user_id.clone());
+        filter
+    }
+
+    /// payload是否属于当前用户
+    fn owns(&self, payload: &Value) -> bool {
+        payload.get(PAYLOAD_USER_ID).and_then(Value::as_str) == Some(self.user_id.as_str())
+    }
+
+    /// 丢弃不属于当前用户的命中 - 防御后端过滤实现有误
+    fn retain_owned(&self, mut hits: Vec<SearchHit>) -> Vec<SearchHit> {
+        hits.retain(|hit| self.owns(&hit.payload));
+        hits
+    }
+
+    /// 确认点属于当前用户，否则按不存在处理
+    async fn ensure_owned(&self, id: Uuid) -> Result<(), S::Error> {
+        match self.inner.get_payloads(&[id]).await?.get(&id) {
+            Some(payload) if self.owns(payload) => Ok(()),
+            _ => Err(anyhow::anyhow!("Vector not found: {}", id).into()),
+        }
+    }
+
+    /// 写入前检查：已存在的ID必须属于当前用户，避免覆盖他人的记忆
+    ///
+    /// 整批只查询一次，只读取payload。检查与写入之间不加锁，新记忆的ID随机生成，
+    /// 只有调用方指定他人已使用的ID并与其并发写入时才可能越过检查。
+    async fn ensure_writable(&self, ids: &[Uuid]) -> Result<(), S::Error> {
+        let existing = self.inner.get_payloads(ids).await?;
+        match existing.iter().find(|(_, payload)| !self.owns(payload)) {
+            Some((id, _)) => Err(anyhow::anyhow!("Vector {} belongs to another user", id).into()),
+            None => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl<S: VectorStore + ?Sized> VectorStore for TenantVectorStore<S> {
+    type Error = S::Error;
+
+    fn vector_size(&self) -> Option<usize> {
+        self.inner.vector_size()
+    }
+
+    fn distance_metric(&self) -> DistanceMetric {
+        self.inner.distance_metric()
+    }
+
+    fn metrics(&self) -> HashMap<String, OperationMetrics> {
+        self.inner.metrics()
+    }
+
+    fn emotion_vector_size(&self) -> Option<usize> {
+        self.inner.emotion_vector_size()
+    }
+
+    fn image_vector_size(&self) -> Option<usize> {
+        self.inner.image_vector_size()
+    }
+
+    async fn store_vector(
+        &self,
+        id: Uuid,
+        embedding: Vec<f32>,
+        metadata: String,
+    ) -> Result<(), Self::Error> {
+        self.ensure_writable(&[id]).await?;
+        let metadata = self.inject_user(&metadata)?;
+        self.inner.store_vector(id, embedding, metadata).await
+    }
+
+    async fn store_vectors(
+        &self,
+        points: Vec<(Uuid, Vec<f32>, String)>,
+    ) -> Result<(), Self::Error> {
+        let ids: Vec<Uuid> = points.iter().map(|(id, _, _)| *id).collect();
+        self.ensure_writable(&ids).await?;
+        let scoped = points.into_iter()
+            .map(|(id, embedding, metadata)| Ok((id, embedding, self.inject_user(&metadata)?)))
+            .collect::<Result<Vec<_>, anyhow::Error>>()?;
+        self.inner.store_vectors(scoped).await
+    }
+
+    async fn search_similar(
+        &self,
+        query_embedding: Vec<f32>,
+        limit: usize,
+        threshold: f32,
+        filter: Option<SearchFilter>,
+    ) -> Result<Vec<SearchHit>, Self::Error> {
+        let hits = self.inner.search_similar(
+            query_embedding,
+            limit,
+            threshold,
+            Some(self.scoped_filter(filter)),
+        ).await?;
+        Ok(self.retain_owned(hits))
+    }
+
+    fn search_stream(
+        &self,
+        query_embedding: Vec<f32>,
+        limit: usize,
+        threshold: f32,
+        filter: Option<SearchFilter>,
+    ) -> HitStream<'_, Self::Error> {
+        self.inner.search_stream(query_embedding, limit, threshold, Some(self.scoped_filter(filter)))
+            .try_filter(|hit| std::future::ready(self.owns(&hit.payload)))
+            .boxed()
+    }
+
+    async fn search_similar_batch(
+        &self,
+        query_embeddings: Vec<Vec<f32>>,
+        limit: usize,
+        threshold: f32,
+        filter: Option<SearchFilter>,
+    ) -> Result<Vec<Vec<SearchHit>>, Self::Error> {
+        let results = self.inner.search_similar_batch(
+            query_embeddings,
+            limit,
+            threshold,
+            Some(self.scoped_filter(filter)),
+        ).await?;
+        Ok(results.into_iter().map(|hits| self.retain_owned(hits)).collect())
+    }
+
+    async fn store_hybrid(
+        &self,
+        id: Uuid,
+        embedding: Vec<f32>,
+        sparse: SparseVector,
+        metadata: String,
+    ) -> Result<(), Self::Error> {
+        self.ensure_writable(&[id]).await?;
+        let metadata = self.inject_user(&metadata)?;
+        self.inner.store_hybrid(id, embedding, sparse, metadata).await
+    }
+
+    async fn search_hybrid(
+        &self,
+        query_embedding: Vec<f32>,
+        query_sparse: SparseVector,
+        limit: usize,
+        threshold: f32,
+        filter: Option<SearchFilter>,
+    ) -> Result<Vec<SearchHit>, Self::Error> {
+        let hits = self.inner.search_hybrid(
+            query_embedding,
+            query_sparse,
+            limit,
+            threshold,
+            Some(self.scoped_filter(filter)),
+        ).await?;
+        Ok(self.retain_owned(hits))
+    }
+
+    async fn store_multi_vector(
+        &self,
+        id: Uuid,
+        embedding: Vec<f32>,
+        emotion_embedding: Vec<f32>,
+        metadata: String,
+    ) -> Result<(), Self::Error> {
+        self.ensure_writable(&[id]).await?;
+        let metadata = self.inject_user(&metadata)?;
+        self.inner.store_multi_vector(id, embedding, emotion_embedding, metadata).await
+    }
+
+    async fn store_multi_vectors(
+        &self,
+        points: Vec<(Uuid, Vec<f32>, Vec<f32>, String)>,
+    ) -> Result<(), Self::Error> {
+        let ids: Vec<Uuid> = points.iter().map(|(id, _, _, _)| *id).collect();
+        self.ensure_writable(&ids).await?;
+        let scoped = points.into_iter()
+            .map(|(id, embedding, emotion_embedding, metadata)| {
+                Ok((id, embedding, emotion_embedding, self.inject_user(&metadata)?))
+            })
+            .collect::<Result<Vec<_>, anyhow::Error>>()?;
+        self.inner.store_multi_vectors(scoped).await
+    }
+
+    async fn search_space(
+        &self,
+        space: VectorSpace,
+        query_embedding: Vec<f32>,
+        limit: usize,
+        threshold: f32,
+        filter: Option<SearchFilter>,
+    ) -> Result<Vec<SearchHit>, Self::Error> {
+        let hits = self.inner.search_space(
+            space,
+            query_embedding,
+            limit,
+            threshold,
+            Some(self.scoped_filter(filter)),
+        ).await?;
+        Ok(self.retain_owned(hits))
+    }
+
+    async fn update_vector(&self, id: Uuid, embedding: Vec<f32>) -> Result<(), Self::Error> {
+        self.ensure_owned(id).await?;
+        self.inner.update_vector(id, embedding).await
+    }
+
+    async fn attach_image_vector(&self, id: Uuid, image_embedding: Vec<f32>) -> Result<(), Self::Error> {
+        self.ensure_owned(id).await?;
+        self.inner.attach_image_vector(id, image_embedding).await
+    }
+
+    async fn update_payload(&self, id: Uuid, mut patch: Value) -> Result<(), Self::Error> {
+        self.ensure_owned(id).await?;
+        // 不允许通过patch改写归属
+        if let Value::Object(ref mut map) = patch {
+            map.insert(PAYLOAD_USER_ID.to_string(), Value::String(self.user_id.clone()));
+        }
+        self.inner.update_payload(id, patch).await
+    }
+
+    async fn delete_vector(&self, id: Uuid) -> Result<(), Self::Error> {
+        self.ensure_owned(id).await?;
+        self.inner.delete_vector(id).await
+    }
+
+    async fn count(&self, filter: Option<SearchFilter>) -> Result<u64, Self::Error> {
+        self.inner.count(Some(self.scoped_filter(filter))).await
+    }
+
+    async fn estimated_count(&self, filter: Option<SearchFilter>) -> Result<u64, Self::Error> {
+        self.inner.estimated_count(Some(self.scoped_filter(filter))).await
+    }
+
+    async fn purge_expired(&self, filter: Option<SearchFilter>) -> Result<Vec<Uuid>, Self::Error> {
+        self.inner.purge_expired(Some(self.scoped_filter(filter))).await
+    }
+
+    async fn get_vector(&self, id: Uuid) -> Result<Option<StoredVector>, Self::Error> {
+        Ok(self.inner.get_vector(id).await?.filter(|point| self.owns(&point.payload)))
+    }
+
+    async fn get_payloads(&self, ids: &[Uuid]) -> Result<HashMap<Uuid, Value>, Self::Error> {
+        let mut payloads = self.inner.get_payloads(ids).await?;
+        payloads.retain(|_, payload| self.owns(payload));
+        Ok(payloads)
+    }
+
+    /// 页内只保留当前用户的点，因此单页可能少于`limit`条
+    async fn scroll(&self, offset: Option<String>, limit: usize) -> Result<ScrollPage, Self::Error> {
+        let mut page = self.inner.scroll(offset, limit).await?;
+        page.points.retain(|point| self.owns(&point.payload));
+        Ok(page)
+    }
+
+    async fn health_check(&self) -> Result<HealthStatus, Self::Error> {
+        self.inner.health_check().await
+    }
+
+    /// 统计的是共享存储的整体数量，不包含任何记忆内容
+    async fn get_stats(&self) -> Result<HashMap<String, u64>, Self::Error> {
+        self.inner.get_stats().await
+    }
+
+    async fn create_collection(&self, name: &str, vector_size: usize) -> Result<(), Self::Error> {
+        self.inner.create_collection(name, vector_size).await
+    }
+
+    async fn drop_collection(&self, name: &str) -> Result<(), Self::Error> {
+        // 删除集合会影响其他用户
+        Err(anyhow::anyhow!("Dropping collection {} is not allowed for a tenant store", name).into())
+    }
+
+    async fn list_collections(&self) -> Result<Vec<String>, Self::Error> {
+        self.inner.list_collections().await
+    }
+
+    async fn collection_exists(&self, name: &str) -> Result<bool, Self::Error> {
+        self.inner.collection_exists(name).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::MockVectorStore;
+
+    #[tokio::test]
+    async fn test_tenants_cannot_see_or_modify_each_other() {
+        let shared = Arc::new(MockVectorStore::new());
+        let alice = TenantVectorStore::new(shared.clone(), "alice");
+        let bob = TenantVectorStore::new(shared.clone(), "bob");
+
+        let id = Uuid::new_v4();
+        // 伪造的user_id会被覆盖
+        alice.store_vector(id, vec![1.0, 0.0], r#"{"user_id":"bob"}"#.to_string()).await.unwrap();
+
+        assert_eq!(alice.search_similar(vec![1.0, 0.0], 5, 0.0, None).await.unwrap().len(), 1);
+        let bob_hits = bob.search_similar(
+            vec![1.0, 0.0],
+            5,
+            0.0,
+            Some(SearchFilter::for_user("alice")),
+        ).await.unwrap();
+        assert!(bob_hits.is_empty());
+
+        assert!(bob.get_vector(id).await.unwrap().is_none());
+        assert!(bob.delete_vector(id).await.is_err());
+        assert!(bob.update_payload(id, serde_json::json!({ "content": "x" })).await.is_err());
+        assert!(bob.store_vector(id, vec![0.0, 1.0], "{}".to_string()).await.is_err());
+        assert!(alice.get_vector(id).await.unwrap().is_some());
+
+        // 批量写入中有一个ID属于他人时整批拒绝
+        let fresh = Uuid::new_v4();
+        let batch = vec![(fresh, vec![0.0, 1.0], "{}".to_string()), (id, vec![0.0, 1.0], "{}".to_string())];
+        assert!(bob.store_vectors(batch).await.is_err());
+        assert!(shared.get_vector(fresh).await.unwrap().is_none());
+        assert_eq!(bob.get_payloads(&[id, fresh]).await.unwrap().len(), 0);
+        assert_eq!(alice.get_payloads(&[id, fresh]).await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,502 @@
+//! 写后缓冲的向量存储包装
+//!
+//! `store_vector`只写入内存缓冲区并立即返回，缓冲区达到批量上限或定时器到期时
+//! 通过`store_vectors`批量写入后端。其他操作执行前会先刷新缓冲区，保证读到自己的写入。
+//!
+//! 缓冲区条数有上限，写满且刷新后仍放不下时返回错误，返回错误的写入不在缓冲区中。
+//! 批次整体移交给后端，不保留副本；瞬时故障由后端自身的重试和断线缓冲处理，
+//! 仍然失败的批次计入`lost_writes`并反映在健康检查中。
+
+use super::{
+    check_dimension, DistanceMetric, HealthStatus, HitStream, OperationMetrics, ScrollPage,
+    SearchFilter, SearchHit, SparseVector, StoredVector, VectorSpace, VectorStore,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+/// 缓冲的写入
+type PendingPoint = (Uuid, Vec<f32>, String);
+
+/// 写后缓冲配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WriteBehindConfig {
+    /// 缓冲条数达到该值时立即刷新
+    pub max_batch: usize,
+    /// 定时刷新间隔(毫秒)
+    pub flush_interval_ms: u64,
+    /// 缓冲区最多容纳的条数
+    #[serde(default = "default_max_pending")]
+    pub max_pending: usize,
+}
+
+fn default_max_pending() -> usize {
+    8192
+}
+
+impl Default for WriteBehindConfig {
+    fn default() -> Self {
+        Self {
+            max_batch: 256,
+            flush_interval_ms: 200,
+            max_pending: default_max_pending(),
+        }
+    }
+}
+
+/// 缓冲区和后端，由包装和后台刷新任务共享
+#[derive(Debug)]
+struct Buffer<S: VectorStore + ?Sized> {
+    inner: Arc<S>,
+    pending: Mutex<Vec<PendingPoint>>,
+    /// 串行化刷新，保证批次按写入顺序到达后端
+    flush_lock: Mutex<()>,
+    /// 最近一次刷新是否失败
+    flush_failing: AtomicBool,
+    /// 刷新失败而丢弃的条数
+    lost_writes: AtomicU64,
+}
+
+impl<S: VectorStore + ?Sized> Buffer<S> {
+    /// 将缓冲区写入后端，返回写入条数；失败的批次不再重试，计入丢失条数
+    async fn flush(&self) -> Result<usize, S::Error> {
+        let _guard = self.flush_lock.lock().await;
+
+        let points = std::mem::take(&mut *self.pending.lock().await);
+        if points.is_empty() {
+            return Ok(0);
+        }
+
+        let count = points.len();
+        match self.inner.store_vectors(points).await {
+            Ok(()) => {
+                self.flush_failing.store(false, Ordering::Relaxed);
+                Ok(count)
+            }
+            Err(e) => {
+                self.flush_failing.store(true, Ordering::Relaxed);
+                self.lost_writes.fetch_add(count as u64, Ordering::Relaxed);
+                tracing::error!("写后缓冲刷新失败，{} 条写入丢失", count);
+                Err(e)
+            }
+        }
+    }
+}
+
+/// 写后缓冲的向量存储包装
+#[derive(Debug)]
+pub struct WriteBehindVectorStore<S: VectorStore + ?Sized + 'static> {
+    buffer: Arc<Buffer<S>>,
+    config: WriteBehindConfig,
+    flusher: std::sync::Mutex<Option<JoinHandle<()>>>,
+}
+
+impl<S: VectorStore + ?Sized + 'static> WriteBehindVectorStore<S> {
+    /// 包装存储并启动定时刷新任务 - 需要在tokio运行时中调用
+    pub fn new(inner: Arc<S>, config: WriteBehindConfig) -> Self {
+        let store = Self::manual(inner, config);
+        let flusher = tokio::spawn({
+            let buffer = store.buffer.clone();
+            let interval = store.flush_interval();
+            async move {
+                let mut ticker = tokio::time::interval(interval);
+                ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                loop {
+                    ticker.tick().await;
+                    // 失败已在flush中记录
+                    let _ = buffer.flush().await;
+                }
+            }
+        });
+        *store.flusher.lock().unwrap_or_else(|e| e.into_inner()) = Some(flusher);
+        store
+    }
+
+    /// 包装存储但不启动定时刷新，由调用方按`flush_interval`调用`flush`
+    pub fn manual(inner: Arc<S>, config: WriteBehindConfig) -> Self {
+        Self {
+            buffer: Arc::new(Buffer {
+                inner,
+                pending: Mutex::new(Vec::new()),
+                flush_lock: Mutex::new(()),
+                flush_failing: AtomicBool::new(false),
+                lost_writes: AtomicU64::new(0),
+            }),
+            config,
+            flusher: std::sync::Mutex::new(None),
+        }
+    }
+
+    /// 定时刷新的间隔
+    pub fn flush_interval(&self) -> Duration {
+        Duration::from_millis(self.config.flush_interval_ms.max(1))
+    }
+
+    /// 等待写入后端的条数
+    pub async fn pending_writes(&self) -> usize {
+        self.buffer.pending.lock().await.len()
+    }
+
+    /// 刷新失败而丢弃的条数
+    pub fn lost_writes(&self) -> u64 {
+        self.buffer.lost_writes.load(Ordering::Relaxed)
+    }
+
+    /// 立即将缓冲区写入后端，返回写入条数
+    pub async fn flush(&self) -> Result<usize, S::Error> {
+        self.buffer.flush().await
+    }
+
+    /// 停止定时刷新并写入剩余数据
+    pub async fn shutdown(&self) -> Result<usize, S::Error> {
+        if let Some(flusher) = self.flusher.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            flusher.abort();
+        }
+        self.buffer.flush().await
+    }
+
+    /// 追加到缓冲区，达到批量上限时立即刷新 - 返回错误时这些写入没有进入缓冲区或已随批次丢弃
+    async fn enqueue(&self, points: Vec<PendingPoint>) -> Result<(), S::Error> {
+        for (_, embedding, _) in &points {
+            check_dimension(self.buffer.inner.vector_size(), embedding).map_err(anyhow::Error::from)?;
+        }
+
+        let capacity = self.config.max_pending.max(self.config.max_batch).max(1);
+        if points.len() > capacity {
+            return Err(anyhow::anyhow!("一次写入 {} 条，超过写后缓冲上限 {}", points.len(), capacity).into());
+        }
+
+        let mut points = Some(points);
+        let mut flushed = false;
+        let should_flush = loop {
+            {
+                let mut pending = self.buffer.pending.lock().await;
+                let incoming = points.as_ref().map_or(0, Vec::len);
+                if pending.len() + incoming <= capacity {
+                    pending.extend(points.take().unwrap_or_default());
+                    break pending.len() >= self.config.max_batch;
+                }
+            }
+            // 缓冲区已满，先刷新腾出空间；刷新后仍被并发写入占满时放弃
+            if flushed {
+                return Err(anyhow::anyhow!("写后缓冲已满({} 条)", capacity).into());
+            }
+            self.buffer.flush().await?;
+            flushed = true;
+        };
+
+        if should_flush {
+            self.buffer.flush().await?;
+        }
+        Ok(())
+    }
+}
+
+impl<S: VectorStore + ?Sized + 'static> Drop for WriteBehindVectorStore<S> {
+    /// 尽力写入剩余数据 - 需要可靠落盘时应显式调用`shutdown`
+    fn drop(&mut self) {
+        if let Some(flusher) = self.flusher.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            flusher.abort();
+        }
+
+        let buffer = self.buffer.clone();
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                // 失败已在flush中记录
+                handle.spawn(async move {
+                    let _ = buffer.flush().await;
+                });
+            }
+            Err(_) => {
+                let pending = buffer.pending.try_lock().map(|p| p.len()).unwrap_or(0);
+                if pending > 0 {
+                    tracing::warn!("写后缓冲在运行时之外被释放，{} 条写入丢失", pending);
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<S: VectorStore + ?Sized + 'static> VectorStore for WriteBehindVectorStore<S> {
+    type Error = S::Error;
+
+    fn vector_size(&self) -> Option<usize> {
+        self.buffer.inner.vector_size()
+    }
+
+    fn emotion_vector_size(&self) -> Option<usize> {
+        self.buffer.inner.emotion_vector_size()
+    }
+
+    fn image_vector_size(&self) -> Option<usize> {
+        self.buffer.inner.image_vector_size()
+    }
+
+    fn distance_metric(&self) -> DistanceMetric {
+        self.buffer.inner.distance_metric()
+    }
+
+    fn metrics(&self) -> HashMap<String, OperationMetrics> {
+        self.buffer.inner.metrics()
+    }
+
+    async fn store_vector(
+        &self,
+        id: Uuid,
+        embedding: Vec<f32>,
+        metadata: String,
+    ) -> Result<(), Self::Error> {
+        self.enqueue(vec![(id, embedding, metadata)]).await
+    }
+
+    async fn store_vectors(
+        &self,
+        points: Vec<(Uuid, Vec<f32>, String)>,
+    ) -> Result<(), Self::Error> {
+        self.enqueue(points).await
+    }
+
+    async fn search_similar(
+        &self,
+        query_embedding: Vec<f32>,
+        limit: usize,
+        threshold: f32,
+        filter: Option<SearchFilter>,
+    ) -> Result<Vec<SearchHit>, Self::Error> {
+        self.flush().await?;
+        self.buffer.inner.search_similar(query_embedding, limit, threshold, filter).await
+    }
+
+    /// 流式搜索不等待刷新，可能看不到尚在缓冲区中的写入
+    fn search_stream(
+        &self,
+        query_embedding: Vec<f32>,
+        limit: usize,
+        threshold: f32,
+        filter: Option<SearchFilter>,
+    ) -> HitStream<'_, Self::Error> {
+        self.buffer.inner.search_stream(query_embedding, limit, threshold, filter)
+    }
+
+    async fn search_similar_batch(
+        &self,
+        query_embeddings: Vec<Vec<f32>>,
+        limit: usize,
+        threshold: f32,
+        filter: Option<SearchFilter>,
+    ) -> Result<Vec<Vec<SearchHit>>, Self::Error> {
+        self.flush().await?;
+        self.buffer.inner.search_similar_batch(query_embeddings, limit, threshold, filter).await
+    }
+
+    async fn store_hybrid(
+        &self,
+        id: Uuid,
+        embedding: Vec<f32>,
+        sparse: SparseVector,
+        metadata: String,
+    ) -> Result<(), Self::Error> {
+        self.flush().await?;
+        self.buffer.inner.store_hybrid(id, embedding, sparse, metadata).await
+    }
+
+    async fn search_hybrid(
+        &self,
+        query_embedding: Vec<f32>,
+        query_sparse: SparseVector,
+        limit: usize,
+        threshold: f32,
+        filter: Option<SearchFilter>,
+    ) -> Result<Vec<SearchHit>, Self::Error> {
+        self.flush().await?;
+        self.buffer.inner.search_hybrid(query_embedding, query_sparse, limit, threshold, filter).await
+    }
+
+    async fn store_multi_vector(
+        &self,
+        id: Uuid,
+        embedding: Vec<f32>,
+        emotion_embedding: Vec<f32>,
+        metadata: String,
+    ) -> Result<(), Self::Error> {
+        self.flush().await?;
+        self.buffer.inner.store_multi_vector(id, embedding, emotion_embedding, metadata).await
+    }
+
+    async fn store_multi_vectors(
+        &self,
+        points: Vec<(Uuid, Vec<f32>, Vec<f32>, String)>,
+    ) -> Result<(), Self::Error> {
+        self.flush().await?;
+        self.buffer.inner.store_multi_vectors(points).await
+    }
+
+    async fn search_space(
+        &self,
+        space: VectorSpace,
+        query_embedding: Vec<f32>,
+        limit: usize,
+        threshold: f32,
+        filter: Option<SearchFilter>,
+    ) -> Result<Vec<SearchHit>, Self::Error> {
+        self.flush().await?;
+        self.buffer.inner.search_space(space, query_embedding, limit, threshold, filter).await
+    }
+
+    async fn update_vector(&self, id: Uuid, embedding: Vec<f32>) -> Result<(), Self::Error> {
+        self.flush().await?;
+        self.buffer.inner.update_vector(id, embedding).await
+    }
+
+    async fn attach_image_vector(&self, id: Uuid, image_embedding: Vec<f32>) -> Result<(), Self::Error> {
+        self.flush().await?;
+        self.buffer.inner.attach_image_vector(id, image_embedding).await
+    }
+
+    async fn update_payload(&self, id: Uuid, patch: Value) -> Result<(), Self::Error> {
+        self.flush().await?;
+        self.buffer.inner.update_payload(id, patch).await
+    }
+
+    async fn delete_vector(&self, id: Uuid) -> Result<(), Self::Error> {
+        self.flush().await?;
+        self.buffer.inner.delete_vector(id).await
+    }
+
+    async fn purge_expired(&self, filter: Option<SearchFilter>) -> Result<Vec<Uuid>, Self::Error> {
+        self.flush().await?;
+        self.buffer.inner.purge_expired(filter).await
+    }
+
+    async fn count(&self, filter: Option<SearchFilter>) -> Result<u64, Self::Error> {
+        self.flush().await?;
+        self.buffer.inner.count(filter).await
+    }
+
+    async fn estimated_count(&self, filter: Option<SearchFilter>) -> Result<u64, Self::Error> {
+        self.flush().await?;
+        self.buffer.inner.estimated_count(filter).await
+    }
+
+    async fn get_vector(&self, id: Uuid) -> Result<Option<StoredVector>, Self::Error> {
+        self.flush().await?;
+        self.buffer.inner.get_vector(id).await
+    }
+
+    async fn get_payloads(&self, ids: &[Uuid]) -> Result<HashMap<Uuid, serde_json::Value>, Self::Error> {
+        self.flush().await?;
+        self.buffer.inner.get_payloads(ids).await
+    }
+
+    async fn scroll(&self, offset: Option<String>, limit: usize) -> Result<ScrollPage, Self::Error> {
+        self.flush().await?;
+        self.buffer.inner.scroll(offset, limit).await
+    }
+
+    async fn health_check(&self) -> Result<HealthStatus, Self::Error> {
+        let status = self.buffer.inner.health_check().await?;
+        if status.is_healthy() && self.buffer.flush_failing.load(Ordering::Relaxed) {
+            return Ok(HealthStatus::Degraded {
+                reason: format!("写后缓冲刷新失败，累计丢失 {} 条写入", self.lost_writes()),
+            });
+        }
+        Ok(status)
+    }
+
+    async fn get_stats(&self) -> Result<HashMap<String, u64>, Self::Error> {
+        let mut stats = self.buffer.inner.get_stats().await?;
+        stats.insert("pending_writes".to_string(), self.pending_writes().await as u64);
+        stats.insert("lost_writes".to_string(), self.lost_writes());
+        Ok(stats)
+    }
+
+    async fn create_collection(&self, name: &str, vector_size: usize) -> Result<(), Self::Error> {
+        self.buffer.inner.create_collection(name, vector_size).await
+    }
+
+    async fn drop_collection(&self, name: &str) -> Result<(), Self::Error> {
+        self.flush().await?;
+        self.buffer.inner.drop_collection(name).await
+    }
+
+    async fn list_collections(&self) -> Result<Vec<String>, Self::Error> {
+        self.buffer.inner.list_collections().await
+    }
+
+    async fn collection_exists(&self, name: &str) -> Result<bool, Self::Error> {
+        self.buffer.inner.collection_exists(name).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::MockVectorStore;
+
+    #[tokio::test]
+    async fn test_batches_by_size_time_and_shutdown() {
+        let inner = Arc::new(MockVectorStore::new().with_vector_size(2));
+        let store = WriteBehindVectorStore::new(
+            inner.clone(),
+            WriteBehindConfig { max_batch: 3, flush_interval_ms: 60_000, ..Default::default() },
+        );
+
+        store.store_vector(Uuid::new_v4(), vec![1.0, 0.0], "{}".to_string()).await.unwrap();
+        store.store_vector(Uuid::new_v4(), vec![1.0, 0.0], "{}".to_string()).await.unwrap();
+        assert_eq!(inner.count(None).await.unwrap(), 0);
+        assert!(store.store_vector(Uuid::new_v4(), vec![1.0], "{}".to_string()).await.is_err());
+
+        // 达到批量上限
+        store.store_vector(Uuid::new_v4(), vec![0.0, 1.0], "{}".to_string()).await.unwrap();
+        assert_eq!(inner.count(None).await.unwrap(), 3);
+
+        // 读取前刷新
+        let id = Uuid::new_v4();
+        store.store_vector(id, vec![1.0, 0.0], "{}".to_string()).await.unwrap();
+        assert!(store.get_vector(id).await.unwrap().is_some());
+
+        store.store_vector(Uuid::new_v4(), vec![1.0, 0.0], "{}".to_string()).await.unwrap();
+        assert_eq!(store.shutdown().await.unwrap(), 1);
+        assert_eq!(inner.count(None).await.unwrap(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_writes_beyond_capacity() {
+        let inner = Arc::new(MockVectorStore::new());
+        let store = WriteBehindVectorStore::new(
+            inner.clone(),
+            WriteBehindConfig { max_batch: 2, flush_interval_ms: 60_000, max_pending: 2 },
+        );
+
+        let points: Vec<_> = (0..3).map(|_| (Uuid::new_v4(), vec![1.0, 0.0], "{}".to_string())).collect();
+        assert!(store.store_vectors(points).await.is_err());
+        assert_eq!(store.pending_writes().await, 0);
+
+        store.store_vector(Uuid::new_v4(), vec![1.0, 0.0], "{}".to_string()).await.unwrap();
+        assert_eq!(store.pending_writes().await, 1);
+        assert_eq!(store.shutdown().await.unwrap(), 1);
+        assert_eq!(store.lost_writes(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_flushes_on_interval() {
+        let inner = Arc::new(MockVectorStore::new());
+        let store = WriteBehindVectorStore::new(
+            inner.clone(),
+            WriteBehindConfig { max_batch: 100, flush_interval_ms: 10, ..Default::default() },
+        );
+
+        store.store_vector(Uuid::new_v4(), vec![1.0, 0.0], "{}".to_string()).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(store.pending_writes().await, 0);
+        assert_eq!(inner.count(None).await.unwrap(), 1);
+    }
+}
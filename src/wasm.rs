@@ -0,0 +1,136 @@
+//! WebAssembly绑定 - 浏览器端直接运行情感计算和个性化修饰，规则与服务端一致
+//!
+//! ```js
+//! const mood = new MoodEngine();
+//! mood.processInput("你真聪明！");
+//! const persona = new Personality("lively");
+//! persona.decorate(mood.express("好的"), "");
+//! ```
+
+use crate::emotion::{EmotionalEngine, EmotionalTrigger, PersonalityGenerator, PersonalityProfile};
+use crate::EmotionalState;
+use wasm_bindgen::prelude::*;
+
+/// 按名称解析情感触发器，名称与serde序列化一致（如`PositiveInteraction`）
+fn parse_trigger(name: &str) -> Result<EmotionalTrigger, JsError> {
+    serde_json::from_value(serde_json::Value::String(name.to_string()))
+        .map_err(|_| JsError::new(&format!("未知的情感触发器: {}", name)))
+}
+
+/// 预设个性档案
+fn preset_profile(name: &str) -> Option<PersonalityProfile> {
+    match name {
+        "obedient" => Some(PersonalityProfile::create_obedient_girlfriend()),
+        "lively" => Some(PersonalityProfile::create_lively_girlfriend()),
+        _ => None,
+    }
+}
+
+fn to_js<T: serde::Serialize>(value: &T) -> Result<JsValue, JsError> {
+    serde_wasm_bindgen::to_value(value).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// 情感引擎及其持有的当前情感状态
+#[wasm_bindgen]
+pub struct MoodEngine {
+    engine: EmotionalEngine,
+    state: EmotionalState,
+}
+
+#[wasm_bindgen]
+impl MoodEngine {
+    /// 从服务端下发的情感状态恢复，未传入时使用默认状态
+    #[wasm_bindgen(constructor)]
+    pub fn new(state: JsValue) -> Result<MoodEngine, JsError> {
+        let state = if state.is_undefined() || state.is_null() {
+            EmotionalState::default()
+        } else {
+            serde_wasm_bindgen::from_value(state).map_err(|e| JsError::new(&e.to_string()))?
+        };
+        Ok(Self { engine: EmotionalEngine::new(), state })
+    }
+
+    /// 当前情感状态
+    pub fn state(&self) -> Result<JsValue, JsError> {
+        to_js(&self.state)
+    }
+
+    /// 当前心情描述
+    #[wasm_bindgen(getter)]
+    pub fn mood(&self) -> String {
+        self.state.mood.clone()
+    }
+
+    /// 应用单个情感触发器
+    #[wasm_bindgen(js_name = processTrigger)]
+    pub fn process_trigger(&mut self, trigger: &str, intensity: f32) -> Result<(), JsError> {
+        let trigger = parse_trigger(trigger)?;
+        self.state = self.engine.process_trigger(&self.state, trigger, intensity);
+        Ok(())
+    }
+
+    /// 分析用户输入并应用识别出的触发器，返回`[触发器, 强度]`列表
+    #[wasm_bindgen(js_name = processInput)]
+    pub fn process_input(&mut self, user_input: &str) -> Result<JsValue, JsError> {
+        let triggers = self.engine.analyze_interaction(user_input, &[]);
+        for (trigger, intensity) in &triggers {
+            self.state = self.engine.process_trigger(&self.state, trigger.clone(), *intensity);
+        }
+        to_js(&triggers)
+    }
+
+    /// 按距上次更新的时间衰减情感
+    #[wasm_bindgen(js_name = applyTimeDecay)]
+    pub fn apply_time_decay(&mut self) {
+        self.state = self.engine.apply_time_decay(&self.state);
+    }
+
+    /// 按当前心情为回复添加情感化表达
+    pub fn express(&self, base_response: &str) -> String {
+        self.engine.generate_emotional_expression(&self.state, base_response)
+    }
+}
+
+/// 个性化回复生成器
+#[wasm_bindgen]
+pub struct Personality {
+    generator: PersonalityGenerator,
+}
+
+#[wasm_bindgen]
+impl Personality {
+    /// 使用预设名称（`obedient`、`lively`）或完整的个性档案对象创建
+    #[wasm_bindgen(constructor)]
+    pub fn new(profile: JsValue) -> Result<Personality, JsError> {
+        let profile = match profile.as_string() {
+            Some(name) => preset_profile(&name)
+                .ok_or_else(|| JsError::new(&format!("未知的个性预设: {}", name)))?,
+            None if profile.is_undefined() => PersonalityProfile::default(),
+            None => serde_wasm_bindgen::from_value(profile).map_err(|e| JsError::new(&e.to_string()))?,
+        };
+        Ok(Self { generator: PersonalityGenerator::new(profile) })
+    }
+
+    /// 按个性特征修饰回复
+    pub fn decorate(&self, base_response: &str, context: &str) -> String {
+        self.generator.generate_personalized_response(base_response, context)
+    }
+
+    /// 按主动程度随机生成主动话题，未触发时返回undefined
+    #[wasm_bindgen(js_name = initiativeMessage)]
+    pub fn initiative_message(&self, user_context: &str) -> Option<String> {
+        self.generator.generate_initiative_message(user_context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trigger_names_and_presets() {
+        assert_eq!(parse_trigger("BeingPraised").ok(), Some(EmotionalTrigger::BeingPraised));
+        assert!(preset_profile("lively").is_some());
+        assert!(preset_profile("tsundere").is_none());
+    }
+}
@@ -0,0 +1,369 @@
+//! Webhook通知 - 重要事件发生时向配置的地址POST JSON，供外部应用响应
+//!
+//! 请求头`X-Mira-Event`为事件名，`X-Mira-Delivery`为投递ID（重试时不变，可用于去重），
+//! `X-Mira-Timestamp`为发送时间（Unix秒）。配置密钥时附带`X-Mira-Signature: sha256=<hex>`，
+//! 签名内容为`{timestamp}.{body}`的HMAC-SHA256。网络错误、429和5xx按重试策略重试，其余4xx不重试。
+
+use crate::memory::MemoryManager;
+use crate::vector_store::RetryPolicy;
+use crate::{EmotionalState, MemoryError, Result};
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+
+/// 事件名请求头
+pub const EVENT_HEADER: &str = "X-Mira-Event";
+/// 投递ID请求头
+pub const DELIVERY_HEADER: &str = "X-Mira-Delivery";
+/// 发送时间请求头
+pub const TIMESTAMP_HEADER: &str = "X-Mira-Timestamp";
+/// 签名请求头
+pub const SIGNATURE_HEADER: &str = "X-Mira-Signature";
+
+/// 关系阶段，按亲密和信任中较低的一项划分
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelationshipMilestone {
+    /// 熟悉 - 0.4
+    Familiar,
+    /// 亲近 - 0.6
+    Close,
+    /// 亲密 - 0.8
+    Intimate,
+    /// 形影不离 - 0.95
+    Devoted,
+}
+
+impl RelationshipMilestone {
+    const LEVELS: [(RelationshipMilestone, f32); 4] = [
+        (RelationshipMilestone::Devoted, 0.95),
+        (RelationshipMilestone::Intimate, 0.8),
+        (RelationshipMilestone::Close, 0.6),
+        (RelationshipMilestone::Familiar, 0.4),
+    ];
+
+    /// 情感状态所处的阶段，尚未熟悉时为None
+    pub fn for_state(state: &EmotionalState) -> Option<Self> {
+        let level = state.affection.min(state.trust);
+        Self::LEVELS.iter()
+            .find(|(_, threshold)| level >= *threshold)
+            .map(|(milestone, _)| *milestone)
+    }
+}
+
+/// 通知事件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum WebhookEvent {
+    /// 关系进入新的阶段
+    RelationshipMilestone {
+        user_id: String,
+        milestone: RelationshipMilestone,
+        state: EmotionalState,
+    },
+    /// 开心程度持续低于阈值
+    SustainedSadness {
+        user_id: String,
+        since: DateTime<Utc>,
+        state: EmotionalState,
+    },
+    /// 提醒到期
+    ReminderDue {
+        user_id: String,
+        reminder_id: Uuid,
+        content: String,
+        due_at: DateTime<Utc>,
+    },
+}
+
+impl WebhookEvent {
+    /// 事件名，与JSON中的`event`字段一致
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::RelationshipMilestone { .. } => "relationship_milestone",
+            Self::SustainedSadness { .. } => "sustained_sadness",
+            Self::ReminderDue { .. } => "reminder_due",
+        }
+    }
+}
+
+/// Webhook配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    /// 接收通知的地址，为空时不发送
+    pub urls: Vec<String>,
+    /// 签名密钥
+    pub secret: Option<String>,
+    /// 单次请求超时(秒)
+    pub timeout_seconds: u64,
+    pub retry: RetryPolicy,
+    /// 开心程度低于此值视为难过
+    pub sadness_threshold: f32,
+    /// 难过持续多久(分钟)后通知
+    pub sadness_minutes: i64,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            urls: Vec::new(),
+            secret: None,
+            timeout_seconds: 10,
+            retry: RetryPolicy::default(),
+            sadness_threshold: 0.3,
+            sadness_minutes: 60,
+        }
+    }
+}
+
+/// HMAC-SHA256签名，返回`sha256=<hex>`
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, secret.as_bytes());
+    let mut context = ring::hmac::Context::with_key(&key);
+    context.update(timestamp.to_string().as_bytes());
+    context.update(b".");
+    context.update(body);
+    let hex: String = context.sign().as_ref().iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("sha256={}", hex)
+}
+
+#[derive(Debug, Default)]
+struct UserTracking {
+    milestone: Option<RelationshipMilestone>,
+    sad_since: Option<DateTime<Utc>>,
+    sadness_notified: bool,
+}
+
+/// 从情感变化中识别关系阶段和持续难过
+///
+/// 每个用户的第一次变化只作为基准，不产生事件；之后进入更高阶段时通知一次，
+/// 开心程度恢复到阈值以上之前，持续难过只通知一次。
+#[derive(Debug)]
+pub struct EventDetector {
+    sadness_threshold: f32,
+    sadness_duration: Duration,
+    users: DashMap<String, UserTracking>,
+}
+
+impl EventDetector {
+    pub fn new(sadness_threshold: f32, sadness_duration: Duration) -> Self {
+        Self {
+            sadness_threshold,
+            sadness_duration,
+            users: DashMap::new(),
+        }
+    }
+
+    /// 记录用户的新情感状态，返回需要通知的事件
+    pub fn observe(&self, user_id: &str, state: &EmotionalState) -> Vec<WebhookEvent> {
+        let milestone = RelationshipMilestone::for_state(state);
+        let sad = state.happiness < self.sadness_threshold;
+
+        let mut events = Vec::new();
+        let mut tracking = match self.users.get_mut(user_id) {
+            Some(tracking) => tracking,
+            None => {
+                self.users.insert(user_id.to_string(), UserTracking {
+                    milestone,
+                    sad_since: sad.then_some(state.timestamp),
+                    sadness_notified: false,
+                });
+                return events;
+            }
+        };
+
+        if milestone > tracking.milestone {
+            tracking.milestone = milestone;
+            if let Some(milestone) = milestone {
+                events.push(WebhookEvent::RelationshipMilestone {
+                    user_id: user_id.to_string(),
+                    milestone,
+                    state: state.clone(),
+                });
+            }
+        }
+
+        if !sad {
+            tracking.sad_since = None;
+            tracking.sadness_notified = false;
+            return events;
+        }
+        let since = *tracking.sad_since.get_or_insert(state.timestamp);
+        if !tracking.sadness_notified && state.timestamp - since >= self.sadness_duration {
+            tracking.sadness_notified = true;
+            events.push(WebhookEvent::SustainedSadness {
+                user_id: user_id.to_string(),
+                since,
+                state: state.clone(),
+            });
+        }
+        events
+    }
+}
+
+/// 向配置的地址投递事件
+#[derive(Debug, Clone)]
+pub struct WebhookDispatcher {
+    client: reqwest::Client,
+    config: Arc<WebhookConfig>,
+}
+
+impl WebhookDispatcher {
+    pub fn new(config: WebhookConfig) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(config.timeout_seconds))
+            .build()
+            .map_err(|e| MemoryError::ConfigError(format!("创建Webhook客户端失败: {}", e)))?;
+        Ok(Self { client, config: Arc::new(config) })
+    }
+
+    /// 投递到全部地址，任一地址最终失败时返回错误
+    #[tracing::instrument(skip_all, fields(event = event.name()))]
+    pub async fn dispatch(&self, event: &WebhookEvent) -> Result<()> {
+        let body = serde_json::to_vec(event)?;
+        let delivery_id = Uuid::new_v4();
+        let results = futures::future::join_all(
+            self.config.urls.iter().map(|url| self.deliver(url, event.name(), delivery_id, &body)),
+        ).await;
+        results.into_iter().collect()
+    }
+
+    async fn deliver(&self, url: &str, event: &str, delivery_id: Uuid, body: &[u8]) -> Result<()> {
+        let max_attempts = self.config.retry.max_attempts.max(1);
+        let mut attempt = 1;
+        loop {
+            let timestamp = Utc::now().timestamp();
+            let mut request = self.client.post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(EVENT_HEADER, event)
+                .header(DELIVERY_HEADER, delivery_id.to_string())
+                .header(TIMESTAMP_HEADER, timestamp.to_string())
+                .body(body.to_vec());
+            if let Some(ref secret) = self.config.secret {
+                request = request.header(SIGNATURE_HEADER, sign(secret, timestamp, body));
+            }
+
+            let (error, retryable) = match request.send().await {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => {
+                    let status = response.status();
+                    (format!("{} 返回 {}", url, status), status.is_server_error() || status.as_u16() == 429)
+                }
+                Err(e) => (format!("{} 请求失败: {}", url, e), true),
+            };
+            if !retryable || attempt >= max_attempts {
+                return Err(MemoryError::WebhookError(error));
+            }
+
+            let delay = self.config.retry.backoff_delay(attempt);
+            tracing::warn!("Webhook投递失败，{}ms后重试 ({}/{}): {}", delay.as_millis(), attempt, max_attempts, error);
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// 订阅情感变化，识别到关系阶段或持续难过时投递，直到管理器被释放
+    pub fn watch(self, manager: &MemoryManager) -> tokio::task::JoinHandle<()> {
+        let mut changes = manager.subscribe_emotions();
+        let detector = EventDetector::new(
+            self.config.sadness_threshold,
+            Duration::minutes(self.config.sadness_minutes),
+        );
+        tokio::spawn(async move {
+            loop {
+                let change = match changes.recv().await {
+                    Ok(change) => change,
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Webhook落后，跳过 {} 条情感变化", skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                for event in detector.observe(&change.user_id, &change.state) {
+                    // 投递和重试不阻塞后续变化的处理
+                    let dispatcher = self.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = dispatcher.dispatch(&event).await {
+                            tracing::warn!("Webhook事件 {} 投递失败: {}", event.name(), e);
+                        }
+                    });
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, header_exists, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn state(happiness: f32, level: f32, minutes: i64) -> EmotionalState {
+        EmotionalState {
+            happiness,
+            affection: level,
+            trust: level,
+            timestamp: "2024-02-14T12:00:00Z".parse::<DateTime<Utc>>().unwrap() + Duration::minutes(minutes),
+            ..EmotionalState::default()
+        }
+    }
+
+    #[test]
+    fn test_detector_fires_once_per_milestone_and_sad_period() {
+        let detector = EventDetector::new(0.3, Duration::minutes(60));
+        assert!(detector.observe("alice", &state(0.2, 0.3, 0)).is_empty());
+
+        let events = detector.observe("alice", &state(0.2, 0.65, 30));
+        assert!(matches!(events.as_slice(), [WebhookEvent::RelationshipMilestone { milestone: RelationshipMilestone::Close, .. }]));
+        assert!(detector.observe("alice", &state(0.2, 0.62, 45)).is_empty());
+
+        let events = detector.observe("alice", &state(0.1, 0.62, 61));
+        assert!(matches!(events.as_slice(), [WebhookEvent::SustainedSadness { .. }]));
+        assert!(detector.observe("alice", &state(0.1, 0.62, 120)).is_empty());
+
+        detector.observe("alice", &state(0.6, 0.62, 130));
+        assert!(detector.observe("alice", &state(0.1, 0.62, 140)).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_signs_and_retries() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(header(EVENT_HEADER, "reminder_due"))
+            .and(header_exists(SIGNATURE_HEADER))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let dispatcher = WebhookDispatcher::new(WebhookConfig {
+            urls: vec![format!("{}/hooks/mira", server.uri())],
+            secret: Some("s3cret".to_string()),
+            retry: RetryPolicy { initial_backoff_ms: 1, max_backoff_ms: 2, jitter: false, ..RetryPolicy::default() },
+            ..WebhookConfig::default()
+        }).unwrap();
+        let event = WebhookEvent::ReminderDue {
+            user_id: "alice".to_string(),
+            reminder_id: Uuid::new_v4(),
+            content: "记得给妈妈打电话".to_string(),
+            due_at: Utc::now(),
+        };
+        dispatcher.dispatch(&event).await.unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        let last = requests.last().unwrap();
+        let timestamp: i64 = last.headers[TIMESTAMP_HEADER].to_str().unwrap().parse().unwrap();
+        assert_eq!(last.headers[SIGNATURE_HEADER].to_str().unwrap(), sign("s3cret", timestamp, &last.body));
+        assert_eq!(serde_json::from_slice::<serde_json::Value>(&last.body).unwrap()["event"], "reminder_due");
+    }
+}
@@ -38,9 +38,12 @@ pub const MemoryPool = struct {
     /// 底层内存分配器，用于分配内存池缓冲区
     allocator: std.mem.Allocator,
     
-    /// 内存池的主缓冲区，所有分配都来自这个区域
+    /// 内存池的主缓冲区
     buffer: []u8,
     
+    /// 扩容时追加的缓冲区，与主缓冲区互不相邻地管理
+    segments: std.ArrayList([]u8),
+    
     /// 自由内存块的链表，维护可用的内存区域
     free_list: std.ArrayList(*FreeBlock),
     
@@ -53,6 +56,12 @@ pub const MemoryPool = struct {
     /// 延迟合并计数器 - 新增
     coalesce_counter: usize,
     
+    /// 当前未释放的分配数
+    allocation_count: usize,
+    
+    /// 已使用内存的历史峰值（字节）
+    high_water: usize,
+    
     /// 合并阈值 - 新增
     const COALESCE_THRESHOLD: usize = 10;
     
@@ -92,16 +101,23 @@ pub const MemoryPool = struct {
         return Self{
             .allocator = allocator,
             .buffer = buffer,
+            .segments = .empty,
             .free_list = free_list,
             .total_size = aligned_size,
             .used_size = 0,
             .coalesce_counter = 0,
+            .allocation_count = 0,
+            .high_water = 0,
         };
     }
     
     /// 清理内存池
     pub fn deinit(self: *Self) void {
         self.allocator.free(self.buffer);
+        for (self.segments.items) |segment| {
+            self.allocator.free(segment);
+        }
+        self.segments.deinit(self.allocator);
         self.free_list.deinit(self.allocator);
     }
     
@@ -125,9 +141,14 @@ pub const MemoryPool = struct {
                         .next = null,
                     };
                     try self.free_list.append(self.allocator, new_block);
+                    // 分割后当前块只保留请求的大小，释放时不会覆盖剩余块
+                    block.size = aligned_size;
                 }
                 
-                self.used_size += aligned_size;
+                // 未分割时整个块都交给调用者，按块大小计入使用量
+                self.used_size += block.size;
+                self.allocation_count += 1;
+                self.high_water = @max(self.high_water, self.used_size);
                 const result_ptr = @as([*]u8, @ptrCast(block)) + @sizeOf(FreeBlock);
                 const aligned_result_ptr = @as(*anyopaque, @ptrCast(@alignCast(result_ptr)));
                 return aligned_result_ptr;
@@ -137,17 +158,110 @@ pub const MemoryPool = struct {
         return error.OutOfMemory;
     }
     
+    /// 按指定对齐分配，用于SIMD向量缓冲区 - 对齐不超过8字节时等同于alloc
+    /// 
+    /// 对齐前的空隙留作独立的空闲块，返回的指针与普通分配一样通过free释放。
+    pub fn alloc_aligned(self: *Self, size: usize, alignment: usize) !*anyopaque {
+        if (!std.math.isPowerOfTwo(alignment)) return error.InvalidAlignment;
+        if (alignment <= @alignOf(u64)) return self.alloc(size);
+        
+        const aligned_size = std.mem.alignForward(usize, size, @alignOf(u64));
+        const header = @sizeOf(FreeBlock);
+        
+        for (self.free_list.items, 0..) |block, i| {
+            const start = @intFromPtr(block);
+            const end = start + header + block.size;
+            
+            // 空隙要么为0，要么足够放下一个空闲块头
+            var payload = std.mem.alignForward(usize, start + header, alignment);
+            var gap = payload - header - start;
+            if (gap != 0 and gap < header + @alignOf(FreeBlock)) {
+                payload += alignment;
+                gap += alignment;
+            }
+            if (payload + aligned_size > end) continue;
+            
+            if (gap == 0) {
+                _ = self.free_list.swapRemove(i);
+            } else {
+                // 原块缩小为对齐前的空隙，留在空闲列表中
+                block.size = gap - header;
+            }
+            
+            const result: *FreeBlock = @ptrFromInt(payload - header);
+            result.* = .{ .size = aligned_size, .next = null };
+            
+            const tail = end - payload - aligned_size;
+            if (tail > header + @alignOf(FreeBlock)) {
+                const rest: *FreeBlock = @ptrFromInt(payload + aligned_size);
+                rest.* = .{ .size = tail - header, .next = null };
+                try self.free_list.append(self.allocator, rest);
+            } else {
+                result.size += tail;
+            }
+            
+            self.used_size += result.size;
+            self.allocation_count += 1;
+            self.high_water = @max(self.high_water, self.used_size);
+            return @ptrFromInt(payload);
+        }
+        
+        return error.OutOfMemory;
+    }
+    
+    /// 扩容 - 追加一段独立的缓冲区，已分配的指针保持有效
+    pub fn grow(self: *Self, additional: usize) !void {
+        const aligned_size = std.mem.alignForward(usize, additional, @alignOf(FreeBlock));
+        if (aligned_size <= @sizeOf(FreeBlock)) return error.InvalidSize;
+        
+        const segment = try self.allocator.alloc(u8, aligned_size);
+        errdefer self.allocator.free(segment);
+        
+        try self.segments.append(self.allocator, segment);
+        errdefer _ = self.segments.pop();
+        
+        const block: *FreeBlock = @ptrCast(@alignCast(segment.ptr));
+        block.* = .{
+            .size = aligned_size - @sizeOf(FreeBlock),
+            .next = null,
+        };
+        try self.free_list.append(self.allocator, block);
+        
+        self.total_size += aligned_size;
+    }
+    
+    /// 指针是否由本池分配 - 块头需落在池的某个段内
+    pub fn owns(self: *const Self, ptr: *const anyopaque) bool {
+        return self.segment_of(@intFromPtr(ptr) -% @sizeOf(FreeBlock)) != null;
+    }
+
+    /// 地址所在的缓冲区：0为主缓冲区，其后为扩容段，不属于本池时返回null
+    fn segment_of(self: *const Self, addr: usize) ?usize {
+        if (addr >= @intFromPtr(self.buffer.ptr) and addr < @intFromPtr(self.buffer.ptr) + self.buffer.len) {
+            return 0;
+        }
+        for (self.segments.items, 1..) |segment, index| {
+            if (addr >= @intFromPtr(segment.ptr) and addr < @intFromPtr(segment.ptr) + segment.len) {
+                return index;
+            }
+        }
+        return null;
+    }
+    
     /// 释放内存 - 优化版本
     pub fn free(self: *Self, ptr: *anyopaque) void {
         const block_ptr = @as([*]u8, @ptrCast(ptr)) - @sizeOf(FreeBlock);
         const block: *FreeBlock = @ptrCast(@alignCast(block_ptr));
         
         // 验证指针有效性
-        if (@intFromPtr(block) < @intFromPtr(self.buffer.ptr) or 
-            @intFromPtr(block) >= @intFromPtr(self.buffer.ptr) + self.buffer.len) {
+        if (self.segment_of(@intFromPtr(block)) == null) {
             return; // 无效指针，忽略
         }
         
+        // 重复释放时不让统计下溢
+        self.used_size -|= block.size;
+        self.allocation_count -|= 1;
+        
         // 添加回自由列表
         self.free_list.append(self.allocator, block) catch {
             // 如果无法添加到列表，至少标记为可用
@@ -188,7 +302,9 @@ pub const MemoryPool = struct {
             const prev_end = @intFromPtr(prev_block) + @sizeOf(FreeBlock) + prev_block.size;
             const current_start = @intFromPtr(current_block);
             
-            if (prev_end == current_start) {
+            // 不同缓冲区在地址上可能恰好相邻，不能跨缓冲区合并
+            if (prev_end == current_start and
+                self.segment_of(@intFromPtr(prev_block)) == self.segment_of(current_start)) {
                 // 合并块
                 prev_block.size += @sizeOf(FreeBlock) + current_block.size;
             } else {
@@ -208,6 +324,8 @@ pub const MemoryPool = struct {
             .used = self.used_size,
             .free = self.total_size - self.used_size,
             .free_blocks = self.free_list.items.len,
+            .allocations = self.allocation_count,
+            .high_water = self.high_water,
             .fragmentation = self.calculateFragmentation(),
         };
     }
@@ -271,24 +389,38 @@ pub const MemoryPool = struct {
         // 清空自由列表
         self.free_list.clearRetainingCapacity();
         
-        // 重新初始化为单个大块
+        // 每个缓冲区重新初始化为单个大块
         const initial_block: *FreeBlock = @ptrCast(@alignCast(self.buffer.ptr));
         initial_block.* = .{
-            .size = self.total_size - @sizeOf(FreeBlock),
+            .size = self.buffer.len - @sizeOf(FreeBlock),
             .next = null,
         };
         
         self.free_list.append(self.allocator, initial_block) catch unreachable;
+        for (self.segments.items) |segment| {
+            const block: *FreeBlock = @ptrCast(@alignCast(segment.ptr));
+            block.* = .{
+                .size = segment.len - @sizeOf(FreeBlock),
+                .next = null,
+            };
+            self.free_list.append(self.allocator, block) catch unreachable;
+        }
         self.used_size = 0;
+        self.allocation_count = 0;
     }
 };
 
-/// 内存统计信息
-pub const MemoryStats = struct {
+/// 内存统计信息 - 通过FFI传给Rust，使用C布局
+pub const MemoryStats = extern struct {
     total: usize,
     used: usize,
     free: usize,
     free_blocks: usize,
+    /// 当前未释放的分配数
+    allocations: usize,
+    /// 已使用内存的历史峰值
+    high_water: usize,
+    /// 碎片率：1 - 最大空闲块 / 空闲总量
     fragmentation: f32,
 };
 
@@ -339,6 +471,44 @@ pub const EnhancedArena = struct {
     }
 };
 
+/// 线性（bump）分配器 - 单块连续缓冲区，分配只移动偏移，reset一次性回收
+///
+/// 空间不足时返回null而不是扩容，由调用方回退到堆分配，保证热路径上没有系统调用。
+pub const BumpAllocator = struct {
+    allocator: std.mem.Allocator,
+    buffer: []align(64) u8,
+    offset: usize = 0,
+    /// 偏移量的历史峰值，用于调整容量
+    high_water: usize = 0,
+
+    const Self = @This();
+
+    pub fn init(allocator: std.mem.Allocator, capacity: usize) !Self {
+        const buffer = try allocator.alignedAlloc(u8, .@"64", capacity);
+        return .{ .allocator = allocator, .buffer = buffer };
+    }
+
+    pub fn deinit(self: *Self) void {
+        self.allocator.free(self.buffer);
+    }
+
+    /// 分配`size`字节，`alignment`必须是2的幂且不超过64
+    pub fn alloc(self: *Self, size: usize, alignment: usize) ?[*]u8 {
+        if (size == 0 or !std.math.isPowerOfTwo(alignment) or alignment > 64) return null;
+        const start = std.mem.alignForward(usize, self.offset, alignment);
+        if (start > self.buffer.len or self.buffer.len - start < size) return null;
+
+        self.offset = start + size;
+        self.high_water = @max(self.high_water, self.offset);
+        return self.buffer.ptr + start;
+    }
+
+    /// 回收全部分配，保留缓冲区
+    pub fn reset(self: *Self) void {
+        self.offset = 0;
+    }
+};
+
 // 测试
 test "memory pool basic operations" {
     var pool = try MemoryPool.init(testing.allocator, 4096);
@@ -384,6 +554,84 @@ test "memory pool coalescing" {
     pool.free(ptr3);
 }
 
+test "memory pool split does not overlap remainder" {
+    var pool = try MemoryPool.init(testing.allocator, 1024);
+    defer pool.deinit();
+    
+    const first = try pool.alloc(64);
+    const second = try pool.alloc(64);
+    pool.free(first);
+    
+    // 释放的块只覆盖原请求大小，不能把仍在使用的second当作空闲空间
+    try testing.expectError(error.OutOfMemory, pool.alloc(900));
+    
+    pool.free(second);
+}
+
+test "memory pool tracks allocations and high water mark" {
+    var pool = try MemoryPool.init(testing.allocator, 4096);
+    defer pool.deinit();
+    
+    const ptr1 = try pool.alloc(100);
+    const ptr2 = try pool.alloc(200);
+    var stats = pool.get_stats();
+    try testing.expectEqual(@as(usize, 2), stats.allocations);
+    try testing.expectEqual(stats.used, stats.high_water);
+    
+    pool.free(ptr1);
+    pool.free(ptr2);
+    stats = pool.get_stats();
+    try testing.expectEqual(@as(usize, 0), stats.allocations);
+    try testing.expectEqual(@as(usize, 0), stats.used);
+    try testing.expect(stats.high_water >= 304);
+    try testing.expectEqual(stats.total, stats.free);
+}
+
+test "memory pool grows with independent segments" {
+    var pool = try MemoryPool.init(testing.allocator, 1024);
+    defer pool.deinit();
+    
+    const first = try pool.alloc(800);
+    try testing.expectError(error.OutOfMemory, pool.alloc(800));
+    
+    try pool.grow(1024);
+    const second = try pool.alloc(800);
+    try testing.expectEqual(@as(usize, 2048), pool.get_stats().total);
+    
+    pool.free(first);
+    pool.free(second);
+    pool.force_coalesce();
+    
+    // 两段缓冲区各自合并，不会拼成一个跨段的块
+    try testing.expectError(error.OutOfMemory, pool.alloc(1500));
+    try testing.expectEqual(@as(usize, 0), pool.get_stats().used);
+}
+
+test "memory pool aligned allocation" {
+    var pool = try MemoryPool.init(testing.allocator, 4096);
+    defer pool.deinit();
+    
+    const small = try pool.alloc(24);
+    const simd = try pool.alloc_aligned(256, 64);
+    const wide = try pool.alloc_aligned(100, 32);
+    try testing.expect(@intFromPtr(simd) % 64 == 0);
+    try testing.expect(@intFromPtr(wide) % 32 == 0);
+    try testing.expectError(error.InvalidAlignment, pool.alloc_aligned(16, 48));
+    try testing.expectEqual(@as(usize, 3), pool.get_stats().allocations);
+    
+    // 对齐缓冲区可以完整写入，且不覆盖相邻分配
+    const bytes: [*]u8 = @ptrCast(simd);
+    @memset(bytes[0..256], 0xAB);
+    const small_bytes: [*]u8 = @ptrCast(small);
+    small_bytes[0] = 1;
+    try testing.expectEqual(@as(u8, 0xAB), bytes[255]);
+    
+    pool.free(simd);
+    pool.free(wide);
+    pool.free(small);
+    try testing.expectEqual(@as(usize, 0), pool.get_stats().used);
+}
+
 test "enhanced arena allocator" {
     var buffer: [1024]u8 = undefined;
     var arena = EnhancedArena.init(testing.allocator, &buffer);
@@ -425,3 +673,21 @@ test "memory pool edge cases" {
 
 // 压力测试暂时禁用，因为内存池实现中有整数溢出问题
 // test "memory pool stress test" { ... }
+
+test "bump allocator aligns and resets" {
+    var bump = try BumpAllocator.init(testing.allocator, 256);
+    defer bump.deinit();
+
+    const first = bump.alloc(3, 1) orelse return error.OutOfMemory;
+    const second = bump.alloc(16, 16) orelse return error.OutOfMemory;
+    try testing.expect(@intFromPtr(second) % 16 == 0);
+    try testing.expect(@intFromPtr(second) >= @intFromPtr(first) + 3);
+    try testing.expect(bump.alloc(256, 8) == null);
+    try testing.expect(bump.alloc(8, 3) == null);
+
+    const used = bump.offset;
+    bump.reset();
+    try testing.expectEqual(@as(usize, 0), bump.offset);
+    try testing.expectEqual(used, bump.high_water);
+    try testing.expect(bump.alloc(256, 64) != null);
+}
@@ -71,7 +71,7 @@ pub const Profiler = monitor.Profiler;
 
 /// MIRA系统层库版本号，遵循语义化版本规范
 pub const version = std.SemanticVersion{
-    .major = 1,
+    .major = 2,
     .minor = 0,
     .patch = 0,
 };
@@ -91,6 +91,59 @@ pub const Config = struct {
 /// 默认配置实例
 pub const default_config = Config{};
 
+// ============================================================================
+// FFI错误码
+// ============================================================================
+//
+// 返回状态的函数直接返回错误码；返回指针的函数失败时返回null，
+// 错误码通过mira_take_last_error取得。与Rust侧`FfiErrorCode`保持一致。
+
+/// FFI错误码
+pub const ErrorCode = enum(c_int) {
+    ok = 0,
+    /// 必需的指针参数为null
+    null_pointer = 1,
+    /// 大小、长度等参数无效
+    invalid_argument = 2,
+    /// 内存不足
+    out_of_memory = 3,
+    /// 对齐不是2的幂或超出支持范围
+    invalid_alignment = 4,
+    /// 释放的指针不属于该内存池
+    invalid_pointer = 5,
+};
+
+/// 当前线程最近一次失败的错误码
+threadlocal var last_error: ErrorCode = .ok;
+
+/// 记录错误码并原样返回，便于`return fail(...)`
+fn fail(code: ErrorCode) c_int {
+    last_error = code;
+    return @intFromEnum(code);
+}
+
+/// 记录错误码并返回null，用于返回指针的函数
+fn fail_null(code: ErrorCode) ?*anyopaque {
+    last_error = code;
+    return null;
+}
+
+/// Zig错误到错误码的映射
+fn code_of(err: anyerror) ErrorCode {
+    return switch (err) {
+        error.OutOfMemory => .out_of_memory,
+        error.InvalidAlignment => .invalid_alignment,
+        else => .invalid_argument,
+    };
+}
+
+/// 取出并清除当前线程最近一次失败的错误码
+export fn mira_take_last_error() c_int {
+    const code = last_error;
+    last_error = .ok;
+    return @intFromEnum(code);
+}
+
 // ============================================================================
 // C ABI导出接口 - 为Rust主程序提供FFI集成
 // ============================================================================
@@ -114,8 +167,15 @@ pub const default_config = Config{};
 /// 注意：调用者负责通过pool_destroy释放资源
 export fn pool_init(pool_size: usize) ?*anyopaque {
     const allocator = std.heap.page_allocator;
-    const pool = memory.MemoryPool.init(allocator, pool_size) catch return null;
-    const pool_ptr = allocator.create(memory.MemoryPool) catch return null;
+    var pool = memory.MemoryPool.init(allocator, pool_size) catch |err| {
+        _ = fail(code_of(err));
+        return null;
+    };
+    const pool_ptr = allocator.create(memory.MemoryPool) catch {
+        pool.deinit();
+        _ = fail(.out_of_memory);
+        return null;
+    };
     pool_ptr.* = pool;
     return @ptrCast(pool_ptr);
 }
@@ -130,10 +190,27 @@ export fn pool_init(pool_size: usize) ?*anyopaque {
 /// - 成功时返回分配的内存指针
 /// - 失败时返回null（内存不足或参数无效）
 export fn pool_alloc(pool_ptr: ?*anyopaque, size: usize) ?*anyopaque {
-    if (pool_ptr == null or size == 0) return null;
+    if (pool_ptr == null) return fail_null(.null_pointer);
+    if (size == 0) return fail_null(.invalid_argument);
     const pool: *memory.MemoryPool = @ptrCast(@alignCast(pool_ptr));
-    const result = pool.alloc(size) catch return null;
-    return result;
+    return pool.alloc(size) catch |err| fail_null(code_of(err));
+}
+
+/// 按指定对齐从内存池分配内存块，适用于SIMD向量缓冲区
+/// 
+/// 参数：
+/// - pool_ptr: 内存池指针
+/// - size: 要分配的内存大小（字节）
+/// - alignment: 对齐字节数，必须是2的幂（如32、64）
+/// 
+/// 返回：
+/// - 成功时返回对齐的内存指针，通过pool_free释放
+/// - 失败时返回null（内存不足或参数无效）
+export fn pool_alloc_aligned(pool_ptr: ?*anyopaque, size: usize, alignment: usize) ?*anyopaque {
+    if (pool_ptr == null) return fail_null(.null_pointer);
+    if (size == 0) return fail_null(.invalid_argument);
+    const pool: *memory.MemoryPool = @ptrCast(@alignCast(pool_ptr));
+    return pool.alloc_aligned(size, alignment) catch |err| fail_null(code_of(err));
 }
 
 /// 释放内存池中的内存块
@@ -142,11 +219,33 @@ export fn pool_alloc(pool_ptr: ?*anyopaque, size: usize) ?*anyopaque {
 /// - pool_ptr: 内存池指针
 /// - ptr: 要释放的内存指针
 /// 
+/// 返回：
+/// - ok: 释放成功
+/// - invalid_pointer: 指针不属于该内存池，未做任何修改
+/// 
 /// 注意：ptr必须是由同一内存池分配的有效指针
-export fn pool_free(pool_ptr: ?*anyopaque, ptr: ?*anyopaque) void {
-    if (pool_ptr == null or ptr == null) return;
+export fn pool_free(pool_ptr: ?*anyopaque, ptr: ?*anyopaque) c_int {
+    if (pool_ptr == null or ptr == null) return fail(.null_pointer);
     const pool: *memory.MemoryPool = @ptrCast(@alignCast(pool_ptr));
+    if (!pool.owns(ptr.?)) return fail(.invalid_pointer);
     pool.free(ptr.?);
+    return @intFromEnum(ErrorCode.ok);
+}
+
+/// 为内存池追加一段缓冲区
+/// 
+/// 参数：
+/// - pool_ptr: 内存池指针
+/// - additional: 追加的大小（字节）
+/// 
+/// 返回：
+/// - ok: 扩容成功，已分配的指针保持有效
+/// - 其他错误码: 参数无效或系统内存不足
+export fn pool_grow(pool_ptr: ?*anyopaque, additional: usize) c_int {
+    if (pool_ptr == null) return fail(.null_pointer);
+    const pool: *memory.MemoryPool = @ptrCast(@alignCast(pool_ptr));
+    pool.grow(additional) catch |err| return fail(code_of(err));
+    return @intFromEnum(ErrorCode.ok);
 }
 
 /// 销毁内存池并释放所有相关资源
@@ -169,13 +268,140 @@ export fn pool_destroy(pool_ptr: ?*anyopaque) void {
 /// - stats_out: 输出统计信息的缓冲区指针
 /// 
 /// 返回：
-/// - true: 成功获取统计信息
-/// - false: 参数无效或操作失败
-export fn pool_stats(pool_ptr: ?*anyopaque, stats_out: ?*memory.MemoryStats) bool {
-    if (pool_ptr == null or stats_out == null) return false;
+/// - ok: 成功获取统计信息
+/// - null_pointer: 参数为null
+export fn pool_stats(pool_ptr: ?*anyopaque, stats_out: ?*memory.MemoryStats) c_int {
+    if (pool_ptr == null or stats_out == null) return fail(.null_pointer);
     const pool: *memory.MemoryPool = @ptrCast(@alignCast(pool_ptr));
     stats_out.?.* = pool.get_stats();
-    return true;
+    return @intFromEnum(ErrorCode.ok);
+}
+
+// ----------------------------------------------------------------------------
+// 竞技场分配C接口
+// ----------------------------------------------------------------------------
+//
+// 竞技场中的分配不单独释放，调用arena_reset一次性回收，适合检索过程中的临时缓冲区。
+
+/// 创建新的竞技场
+/// 
+/// 返回：
+/// - 成功时返回竞技场的不透明指针
+/// - 失败时返回null
+/// 
+/// 注意：调用者负责通过arena_destroy释放资源
+export fn arena_create() ?*anyopaque {
+    const allocator = std.heap.page_allocator;
+    const arena = allocator.create(std.heap.ArenaAllocator) catch return fail_null(.out_of_memory);
+    arena.* = std.heap.ArenaAllocator.init(allocator);
+    return @ptrCast(arena);
+}
+
+/// 从竞技场分配内存
+/// 
+/// 参数：
+/// - arena_ptr: 竞技场指针
+/// - size: 要分配的内存大小（字节）
+/// - alignment: 对齐字节数，必须是2的幂
+/// 
+/// 返回：
+/// - 成功时返回分配的内存指针
+/// - 失败时返回null（内存不足或参数无效）
+export fn arena_alloc(arena_ptr: ?*anyopaque, size: usize, alignment: usize) ?*anyopaque {
+    if (arena_ptr == null) return fail_null(.null_pointer);
+    if (size == 0) return fail_null(.invalid_argument);
+    if (!std.math.isPowerOfTwo(alignment)) return fail_null(.invalid_alignment);
+    const arena: *std.heap.ArenaAllocator = @ptrCast(@alignCast(arena_ptr));
+    const result = arena.allocator().rawAlloc(size, std.mem.Alignment.fromByteUnits(alignment), @returnAddress());
+    return @ptrCast(result orelse return fail_null(.out_of_memory));
+}
+
+/// 回收竞技场中的全部分配，保留已申请的内存供下次使用
+/// 
+/// 注意：之前分配的指针全部失效
+export fn arena_reset(arena_ptr: ?*anyopaque) void {
+    if (arena_ptr == null) return;
+    const arena: *std.heap.ArenaAllocator = @ptrCast(@alignCast(arena_ptr));
+    _ = arena.reset(.retain_capacity);
+}
+
+/// 竞技场当前持有的内存（字节）
+export fn arena_capacity(arena_ptr: ?*anyopaque) usize {
+    if (arena_ptr == null) return 0;
+    const arena: *std.heap.ArenaAllocator = @ptrCast(@alignCast(arena_ptr));
+    return arena.queryCapacity();
+}
+
+/// 销毁竞技场并释放全部内存
+export fn arena_destroy(arena_ptr: ?*anyopaque) void {
+    if (arena_ptr == null) return;
+    const arena: *std.heap.ArenaAllocator = @ptrCast(@alignCast(arena_ptr));
+    arena.deinit();
+    std.heap.page_allocator.destroy(arena);
+}
+
+// ----------------------------------------------------------------------------
+// 线性分配器C接口
+// ----------------------------------------------------------------------------
+//
+// 单块缓冲区上只移动偏移的分配器，空间不足时返回null由调用方回退到堆分配。
+// 不加锁，每个实例只能由一个线程使用。
+
+/// 创建容量为capacity字节的线性分配器
+/// 
+/// 注意：调用者负责通过bump_destroy释放资源
+export fn bump_create(capacity: usize) ?*anyopaque {
+    if (capacity == 0) return fail_null(.invalid_argument);
+    const allocator = std.heap.page_allocator;
+    const bump = allocator.create(memory.BumpAllocator) catch return fail_null(.out_of_memory);
+    bump.* = memory.BumpAllocator.init(allocator, capacity) catch {
+        allocator.destroy(bump);
+        return fail_null(.out_of_memory);
+    };
+    return @ptrCast(bump);
+}
+
+/// 分配size字节，对齐必须是2的幂且不超过64；空间不足时返回null
+export fn bump_alloc(bump_ptr: ?*anyopaque, size: usize, alignment: usize) ?*anyopaque {
+    if (bump_ptr == null) return fail_null(.null_pointer);
+    const bump: *memory.BumpAllocator = @ptrCast(@alignCast(bump_ptr));
+    return @ptrCast(bump.alloc(size, alignment) orelse return fail_null(.out_of_memory));
+}
+
+/// 当前偏移量，可用于bump_rewind
+export fn bump_used(bump_ptr: ?*anyopaque) usize {
+    if (bump_ptr == null) return 0;
+    const bump: *memory.BumpAllocator = @ptrCast(@alignCast(bump_ptr));
+    return bump.offset;
+}
+
+/// 回退到之前记录的偏移量，之后的分配全部失效
+export fn bump_rewind(bump_ptr: ?*anyopaque, offset: usize) void {
+    if (bump_ptr == null) return;
+    const bump: *memory.BumpAllocator = @ptrCast(@alignCast(bump_ptr));
+    bump.offset = @min(offset, bump.offset);
+}
+
+/// 偏移量历史峰值
+export fn bump_high_water(bump_ptr: ?*anyopaque) usize {
+    if (bump_ptr == null) return 0;
+    const bump: *memory.BumpAllocator = @ptrCast(@alignCast(bump_ptr));
+    return bump.high_water;
+}
+
+/// 缓冲区容量
+export fn bump_capacity(bump_ptr: ?*anyopaque) usize {
+    if (bump_ptr == null) return 0;
+    const bump: *memory.BumpAllocator = @ptrCast(@alignCast(bump_ptr));
+    return bump.buffer.len;
+}
+
+/// 销毁线性分配器并释放缓冲区
+export fn bump_destroy(bump_ptr: ?*anyopaque) void {
+    if (bump_ptr == null) return;
+    const bump: *memory.BumpAllocator = @ptrCast(@alignCast(bump_ptr));
+    bump.deinit();
+    std.heap.page_allocator.destroy(bump);
 }
 
 // ----------------------------------------------------------------------------
@@ -218,6 +444,79 @@ export fn cosine_similarity(a: [*c]const f32, b: [*c]const f32, len: usize) f32
     return vector.VectorOps.cosine_similarity(slice_a, slice_b);
 }
 
+/// 批量计算一个查询向量与多个向量的余弦相似度
+/// 一次调用完成全部打分，避免逐对跨越FFI边界
+/// 
+/// 参数：
+/// - query: 查询向量的数组指针
+/// - matrix: 按行连续存放的n个向量
+/// - n: 向量个数
+/// - dim: 向量维度
+/// - out_scores: 输出n个分数的缓冲区
+/// 
+/// 返回：
+/// - ok: 计算成功
+/// - 其他错误码: 参数无效
+export fn batch_cosine_similarity(
+    query: [*c]const f32,
+    matrix: [*c]const f32,
+    n: usize,
+    dim: usize,
+    out_scores: [*c]f32,
+) c_int {
+    if (query == null) return fail(.null_pointer);
+    if (dim == 0) return fail(.invalid_argument);
+    if (n == 0) return @intFromEnum(ErrorCode.ok);
+    if (matrix == null or out_scores == null) return fail(.null_pointer);
+    vector.VectorOps.batch_cosine_similarity(query[0..dim], matrix[0 .. n * dim], out_scores[0..n]);
+    return @intFromEnum(ErrorCode.ok);
+}
+
+/// 单趟计算余弦相似度并选出前k个结果，代替对全部候选排序
+/// 
+/// 参数：
+/// - query: 查询向量的数组指针
+/// - matrix: 按行连续存放的n个向量
+/// - keys: 每行两个u64组成的排序键（高位在前），分数相同时键小的优先
+/// - n: 向量个数
+/// - dim: 向量维度
+/// - k: 最多返回的结果数
+/// - threshold: 最低分数
+/// - out_indices: 输出k个行号的缓冲区
+/// - out_scores: 输出k个分数的缓冲区
+/// - out_count: 输出实际写入的结果数，按相近程度排列
+/// 
+/// 返回：
+/// - ok: 计算成功
+/// - 其他错误码: 参数无效
+export fn topk_cosine_similarity(
+    query: [*c]const f32,
+    matrix: [*c]const f32,
+    keys: [*c]const [2]u64,
+    n: usize,
+    dim: usize,
+    k: usize,
+    threshold: f32,
+    out_indices: [*c]usize,
+    out_scores: [*c]f32,
+    out_count: ?*usize,
+) c_int {
+    if (query == null or out_count == null) return fail(.null_pointer);
+    if (dim == 0) return fail(.invalid_argument);
+    out_count.?.* = 0;
+    if (n == 0 or k == 0) return @intFromEnum(ErrorCode.ok);
+    if (matrix == null or keys == null or out_indices == null or out_scores == null) return fail(.null_pointer);
+    out_count.?.* = vector.VectorOps.top_k_cosine(
+        query[0..dim],
+        matrix[0 .. n * dim],
+        keys[0..n],
+        threshold,
+        out_indices[0..k],
+        out_scores[0..k],
+    );
+    return @intFromEnum(ErrorCode.ok);
+}
+
 /// 就地标准化向量（使其模长为1）
 /// 对向量进行单位化处理，常用于归一化嵌入向量
 /// 
@@ -226,13 +525,14 @@ export fn cosine_similarity(a: [*c]const f32, b: [*c]const f32, len: usize) f32
 /// - len: 向量长度（元素个数）
 /// 
 /// 返回：
-/// - true: 标准化成功
-/// - false: 参数无效或向量模长为0
-export fn normalize(vec: [*c]f32, len: usize) bool {
-    if (vec == null or len == 0) return false;
+/// - ok: 标准化成功
+/// - 其他错误码: 参数无效
+export fn normalize(vec: [*c]f32, len: usize) c_int {
+    if (vec == null) return fail(.null_pointer);
+    if (len == 0) return fail(.invalid_argument);
     const slice = @as([*]f32, @ptrCast(vec))[0..len];
     vector.VectorOps.normalize(slice);
-    return true;
+    return @intFromEnum(ErrorCode.ok);
 }
 
 // ----------------------------------------------------------------------------
@@ -309,6 +609,55 @@ test {
     std.testing.refAllDeclsRecursive(@This());
 }
 
+test "arena reset reclaims allocations" {
+    const arena = arena_create() orelse return error.OutOfMemory;
+    defer arena_destroy(arena);
+    
+    const first: [*]u8 = @ptrCast(arena_alloc(arena, 4096, 8) orelse return error.OutOfMemory);
+    first[4095] = 1;
+    try std.testing.expect(arena_alloc(arena, 16, 3) == null);
+    
+    const capacity = arena_capacity(arena);
+    arena_reset(arena);
+    _ = arena_alloc(arena, 4096, 8) orelse return error.OutOfMemory;
+    try std.testing.expectEqual(capacity, arena_capacity(arena));
+}
+
+test "bump rewind keeps earlier allocations" {
+    const bump = bump_create(128) orelse return error.OutOfMemory;
+    defer bump_destroy(bump);
+
+    _ = bump_alloc(bump, 32, 8) orelse return error.OutOfMemory;
+    const mark = bump_used(bump);
+    _ = bump_alloc(bump, 64, 8) orelse return error.OutOfMemory;
+    try std.testing.expect(bump_alloc(bump, 64, 8) == null);
+
+    bump_rewind(bump, mark);
+    try std.testing.expectEqual(mark, bump_used(bump));
+    try std.testing.expectEqual(@as(usize, 96), bump_high_water(bump));
+    _ = bump_alloc(bump, 64, 8) orelse return error.OutOfMemory;
+}
+
+test "ffi calls report error codes" {
+    try std.testing.expectEqual(@as(c_int, 0), mira_take_last_error());
+
+    const pool = pool_init(1024) orelse return error.OutOfMemory;
+    defer pool_destroy(pool);
+
+    try std.testing.expect(pool_alloc(pool, 0) == null);
+    try std.testing.expectEqual(@intFromEnum(ErrorCode.invalid_argument), mira_take_last_error());
+    try std.testing.expect(pool_alloc_aligned(pool, 16, 48) == null);
+    try std.testing.expectEqual(@intFromEnum(ErrorCode.invalid_alignment), mira_take_last_error());
+    try std.testing.expect(pool_alloc(pool, 4096) == null);
+    try std.testing.expectEqual(@intFromEnum(ErrorCode.out_of_memory), mira_take_last_error());
+    try std.testing.expectEqual(@as(c_int, 0), mira_take_last_error());
+
+    var outside: u64 = 0;
+    try std.testing.expectEqual(@intFromEnum(ErrorCode.invalid_pointer), pool_free(pool, &outside));
+    const ptr = pool_alloc(pool, 64) orelse return error.OutOfMemory;
+    try std.testing.expectEqual(@intFromEnum(ErrorCode.ok), pool_free(pool, ptr));
+}
+
 // 测试内存管理模块导入
 test "memory module" {
     _ = memory;
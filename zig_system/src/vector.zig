@@ -148,6 +148,153 @@ pub const VectorOps = struct {
         }
     }
     
+    /// 批量计算查询向量与矩阵各行的余弦相似度
+    /// 
+    /// `matrix`按行存放`out.len`个与`query`同维的向量。查询向量的范数只计算一次，
+    /// 零向量的分数为0.0。
+    pub fn batch_cosine_similarity(query: []const f32, matrix: []const f32, out: []f32) void {
+        const dim = query.len;
+        if (dim == 0) return;
+        
+        const norm_q = @sqrt(dot_product_fixed(query, query));
+        for (out, 0..) |*score, row| {
+            const vec = matrix[row * dim .. (row + 1) * dim];
+            const norm_v = @sqrt(dot_product_fixed(vec, vec));
+            score.* = if (norm_q == 0.0 or norm_v == 0.0)
+                0.0
+            else
+                dot_product_fixed(query, vec) / (norm_q * norm_v);
+        }
+    }
+    
+    /// 单趟计算余弦相似度并保留分数最高的k个结果
+    /// 
+    /// `keys`为每行的128位排序键（高64位在前），分数相同时键小的排前面。
+    /// k取`out_indices.len`，结果按相近程度排列，返回实际写入的个数。
+    /// 分数低于`threshold`或为NaN的行不参与。
+    pub fn top_k_cosine(
+        query: []const f32,
+        matrix: []const f32,
+        keys: []const [2]u64,
+        threshold: f32,
+        out_indices: []usize,
+        out_scores: []f32,
+    ) usize {
+        const dim = query.len;
+        const k = @min(out_indices.len, out_scores.len);
+        if (dim == 0 or k == 0) return 0;
+        
+        // out数组本身作为小顶堆，堆顶是当前保留结果中最差的一个
+        var heap = TopKHeap{ .indices = out_indices[0..k], .scores = out_scores[0..k], .keys = keys, .len = 0 };
+        const norm_q = @sqrt(dot_product_fixed(query, query));
+        for (keys, 0..) |_, row| {
+            const vec = matrix[row * dim .. (row + 1) * dim];
+            const norm_v = @sqrt(dot_product_fixed(vec, vec));
+            const score = if (norm_q == 0.0 or norm_v == 0.0)
+                0.0
+            else
+                dot_product_fixed(query, vec) / (norm_q * norm_v);
+            if (std.math.isNan(score) or score < threshold) continue;
+            heap.offer(row, score);
+        }
+        
+        std.mem.sortContext(0, heap.len, heap);
+        return heap.len;
+    }
+    
+    /// 固定容量的小顶堆 - 同时充当排序上下文，按从好到差排列
+    const TopKHeap = struct {
+        indices: []usize,
+        scores: []f32,
+        keys: []const [2]u64,
+        len: usize,
+        
+        /// 第a个结果是否比第b个更相近：分数更高，或分数相同且键更小
+        fn better(self: TopKHeap, row_a: usize, score_a: f32, row_b: usize, score_b: f32) bool {
+            if (score_a != score_b) return score_a > score_b;
+            const key_a = self.keys[row_a];
+            const key_b = self.keys[row_b];
+            if (key_a[0] != key_b[0]) return key_a[0] < key_b[0];
+            return key_a[1] < key_b[1];
+        }
+        
+        /// 堆中位置a是否比位置b更差，用于维护堆顶为最差结果
+        fn worse_at(self: TopKHeap, a: usize, b: usize) bool {
+            return self.better(self.indices[b], self.scores[b], self.indices[a], self.scores[a]);
+        }
+        
+        pub fn lessThan(self: TopKHeap, a: usize, b: usize) bool {
+            return self.better(self.indices[a], self.scores[a], self.indices[b], self.scores[b]);
+        }
+        
+        pub fn swap(self: TopKHeap, a: usize, b: usize) void {
+            std.mem.swap(usize, &self.indices[a], &self.indices[b]);
+            std.mem.swap(f32, &self.scores[a], &self.scores[b]);
+        }
+        
+        fn offer(self: *TopKHeap, row: usize, score: f32) void {
+            if (self.len < self.indices.len) {
+                self.indices[self.len] = row;
+                self.scores[self.len] = score;
+                self.len += 1;
+                var child = self.len - 1;
+                while (child > 0) {
+                    const parent = (child - 1) / 2;
+                    if (!self.worse_at(child, parent)) break;
+                    self.swap(child, parent);
+                    child = parent;
+                }
+                return;
+            }
+            
+            if (!self.better(row, score, self.indices[0], self.scores[0])) return;
+            self.indices[0] = row;
+            self.scores[0] = score;
+            var parent: usize = 0;
+            while (true) {
+                var worst = parent;
+                const left = 2 * parent + 1;
+                const right = left + 1;
+                if (left < self.len and self.worse_at(left, worst)) worst = left;
+                if (right < self.len and self.worse_at(right, worst)) worst = right;
+                if (worst == parent) break;
+                self.swap(parent, worst);
+                parent = worst;
+            }
+        }
+    };
+    
+    /// 固定通道数的点积
+    /// 
+    /// 与Rust侧`distance::dot`使用相同的累加顺序：8个独立累加器按顺序求和，
+    /// 再加上按顺序累加的尾部，结果与机器的SIMD宽度无关。
+    pub fn dot_product_fixed(a: []const f32, b: []const f32) f32 {
+        const lanes = 8;
+        const VectorType = @Vector(lanes, f32);
+        var acc: VectorType = @splat(0.0);
+        
+        var i: usize = 0;
+        while (i + lanes <= a.len) : (i += lanes) {
+            const a_vec: VectorType = a[i..][0..lanes].*;
+            const b_vec: VectorType = b[i..][0..lanes].*;
+            acc += a_vec * b_vec;
+        }
+        
+        // 不使用@reduce，其累加顺序未定义
+        const acc_array: [lanes]f32 = acc;
+        var result: f32 = 0.0;
+        for (acc_array) |lane| {
+            result += lane;
+        }
+        
+        var tail: f32 = 0.0;
+        while (i < a.len) : (i += 1) {
+            tail += a[i] * b[i];
+        }
+        
+        return result + tail;
+    }
+    
     /// 计算加权欧几里德距离
     /// 使用Zig 0.15.1多对象for循环特性
     pub fn weighted_distance(a: []const f32, b: []const f32, weights: []const f32) f32 {
@@ -272,6 +419,37 @@ test "vector dot product" {
     try testing.expectApproxEqRel(result, expected, 0.001);
 }
 
+test "batch cosine similarity" {
+    const query = [_]f32{ 1.0, 0.0 };
+    const matrix = [_]f32{ 1.0, 0.0, 0.0, 1.0, 3.0, 4.0, 0.0, 0.0 };
+    var scores: [4]f32 = undefined;
+    
+    VectorOps.batch_cosine_similarity(&query, &matrix, &scores);
+    
+    try testing.expectApproxEqAbs(@as(f32, 1.0), scores[0], 1e-6);
+    try testing.expectApproxEqAbs(@as(f32, 0.0), scores[1], 1e-6);
+    try testing.expectApproxEqAbs(@as(f32, 0.6), scores[2], 1e-6);
+    try testing.expect(scores[3] == 0.0);
+}
+
+test "top k cosine keeps best rows and breaks ties by key" {
+    const query = [_]f32{ 1.0, 0.0 };
+    const matrix = [_]f32{ 0.0, 1.0, 1.0, 0.0, 3.0, 4.0, 2.0, 0.0, 1.0, 1.0 };
+    const keys = [_][2]u64{ .{ 0, 5 }, .{ 0, 9 }, .{ 0, 1 }, .{ 0, 2 }, .{ 0, 3 } };
+    var indices: [3]usize = undefined;
+    var scores: [3]f32 = undefined;
+    
+    const count = VectorOps.top_k_cosine(&query, &matrix, &keys, 0.1, &indices, &scores);
+    
+    // 第1和第3行分数都是1.0，键小的第3行排在前面
+    try testing.expectEqual(@as(usize, 3), count);
+    try testing.expectEqualSlices(usize, &[_]usize{ 3, 1, 4 }, &indices);
+    try testing.expectApproxEqAbs(@as(f32, 0.7071068), scores[2], 1e-6);
+    
+    const none = VectorOps.top_k_cosine(&query, &matrix, &keys, 1.5, &indices, &scores);
+    try testing.expectEqual(@as(usize, 0), none);
+}
+
 test "cosine similarity" {
     const a = [_]f32{ 1.0, 0.0, 0.0 };
     const b = [_]f32{ 0.0, 1.0, 0.0 };